@@ -29,17 +29,21 @@ typedef enum {
     SEVENZIP_ERROR_COMPRESS = 5,
     SEVENZIP_ERROR_INVALID_PARAM = 6,
     SEVENZIP_ERROR_NOT_IMPLEMENTED = 7,
+    SEVENZIP_ERROR_CANCELLED = 8,
     SEVENZIP_ERROR_UNKNOWN = 99
 } SevenZipErrorCode;
 
 /* Archive entry information */
 typedef struct {
-    char* name;              /* File name (UTF-8) */
+    char* name;              /* File name (UTF-8 decoded from UTF-16; non-UTF-8 bytes restored as-is) */
     uint64_t size;           /* Uncompressed size */
     uint64_t packed_size;    /* Compressed size */
-    uint64_t modified_time;  /* Unix timestamp */
+    uint64_t modified_time;  /* Unix timestamp (host byte order; decoded from the little-endian FILETIME) */
     uint32_t attributes;     /* File attributes */
     int is_directory;        /* 1 if directory, 0 if file */
+    uint32_t crc;            /* CRC32 of the uncompressed data */
+    int has_crc;             /* 1 if crc is defined, 0 otherwise */
+    char method[32];         /* Codec chain, e.g. "LZMA2" or "Copy" (empty if no data) */
 } SevenZipEntry;
 
 /* Archive list result */
@@ -48,6 +52,15 @@ typedef struct {
     size_t count;            /* Number of entries */
 } SevenZipList;
 
+/* Archive-level summary read from header metadata */
+typedef struct {
+    uint64_t unpacked_size;  /* Total uncompressed size of all entries */
+    uint64_t packed_size;    /* Total size of packed streams */
+    uint64_t num_entries;    /* Number of entries (files and directories) */
+    uint64_t num_folders;    /* Number of folders (solid blocks) */
+    uint64_t num_directories; /* Number of directory entries, included in num_entries */
+} SevenZipArchiveInfo;
+
 /* Progress callback function type */
 typedef void (*SevenZipProgressCallback)(uint64_t completed, uint64_t total, void* user_data);
 
@@ -61,7 +74,31 @@ typedef void (*SevenZipBytesProgressCallback)(
     void* user_data
 );
 
-/* Compression level */
+/* Called after each file entry is decompressed and CRC-checked during an
+ * archive test; passed is 1 if the entry is intact, 0 otherwise */
+typedef void (*SevenZipTestEntryCallback)(const char* entry_name, int passed, void* user_data);
+
+/* Read callback for stream input: fill up to *size bytes of buffer and set
+ * *size to the number read (0 at end of stream). Return 0 on success,
+ * non-zero to abort the operation. */
+typedef int (*SevenZipReadCallback)(uint8_t* buffer, size_t* size, void* user_data);
+
+/* Write callback for stream output: consume all size bytes of data.
+ * Return 0 on success, non-zero to abort the operation. */
+typedef int (*SevenZipWriteCallback)(const uint8_t* data, size_t size, void* user_data);
+
+/* Called once for each finished split volume with its path and size. The
+ * volume is closed and won't be touched again, so it may be moved or deleted.
+ * Return 0 to continue, non-zero to abort the operation. */
+typedef int (*SevenZipVolumeCallback)(const char* volume_path, uint64_t size, void* user_data);
+
+/* Creates a scratch file and writes its NUL-terminated path (at most
+ * path_size bytes) to path. The library reopens the file by that path and
+ * removes it when done. Return 0 on success, non-zero to abort the operation. */
+typedef int (*SevenZipTempFileCallback)(char* path, size_t path_size, void* user_data);
+
+/* Compression level; the unnamed levels 2, 4, 6 and 8 are accepted too
+ * and handed to the LZMA encoder as they are */
 typedef enum {
     SEVENZIP_LEVEL_STORE = 0,      /* No compression */
     SEVENZIP_LEVEL_FASTEST = 1,    /* Fastest compression */
@@ -71,14 +108,88 @@ typedef enum {
     SEVENZIP_LEVEL_ULTRA = 9       /* Ultra compression */
 } SevenZipCompressionLevel;
 
+/* Codec used for a group of files when creating an archive */
+typedef enum {
+    SEVENZIP_METHOD_LZMA2 = 0,     /* LZMA2 compression */
+    SEVENZIP_METHOD_COPY = 1       /* Stored without compression */
+} SevenZipMethod;
+
+/* Order in which files are fed into the solid stream */
+typedef enum {
+    SEVENZIP_SORT_NONE = 0,          /* As found: inputs in the order given, directories as read */
+    SEVENZIP_SORT_BY_EXTENSION = 1,  /* By extension (case-insensitive), then name, like 7-Zip's -mqs */
+    SEVENZIP_SORT_BY_NAME = 2,       /* By full entry name */
+    SEVENZIP_SORT_BY_SIZE = 3        /* Smallest file first */
+} SevenZipSortOrder;
+
 /* Advanced compression options */
 typedef struct {
     int num_threads;           /* Number of threads (0 = auto, default: 2) */
     uint64_t dict_size;        /* Dictionary size in bytes (0 = auto) */
     int solid;                 /* Solid archive (1 = yes, 0 = no, default: 1) */
-    const char* password;      /* Password for encryption (NULL = no encryption) */
+    const char* password;      /* Encrypt file data with 7zAES (AES-256, key derived as 7-Zip does);
+                                  names and sizes in the header stay readable (NULL = no encryption) */
+    int skip_empty_dirs;       /* Drop directories with no files beneath them (default: 0) */
+    int method;                /* Default SevenZipMethod (default: LZMA2; STORE level implies Copy) */
+    const char** method_extensions; /* Extensions (no dot, case-insensitive) with their own method (NULL = none) */
+    const int* extension_methods;   /* SevenZipMethod for each entry of method_extensions */
+    size_t method_override_count;   /* Number of entries in method_extensions */
+    int special_files;         /* SevenZipSpecialFilePolicy (default: SKIP); never read, so FIFOs can't block */
+    uint64_t time_budget_ms;   /* Abort with SEVENZIP_ERROR_CANCELLED once LZMA2 encoding is projected
+                                  to run past this many ms from the start of the call (0 = no limit) */
+    const char** codec_property_keys;   /* LZMA2 properties in 7-Zip -m syntax: mf, fb, lc, lp, pb, mc, a (NULL = none) */
+    const char** codec_property_values; /* Value for each entry of codec_property_keys, e.g. "bt4" or "273" */
+    size_t codec_property_count;        /* Number of entries in codec_property_keys */
+    const char** input_entry_names;     /* Stored name for each input path (NULL = file basename / directory contents
+                                           at the root): a file's entry name, or the prefix for a directory's contents
+                                           ("" = root) */
+    int flatten_names;         /* Store each file under its base name only, without directory entries;
+                                  two files with the same name fail with SEVENZIP_ERROR_INVALID_PARAM */
+    int store_all_times;       /* Also store creation (CTime) and access (ATime) times, not just MTime (default: 0);
+                                  on POSIX, CTime is the inode change time, as 7-Zip stores it */
+    int dedup;                 /* Store identical files back to back with a dictionary spanning them, so LZMA2
+                                  keeps one copy plus matches (default: 0); forces single-block encoding.
+                                  Files over 64 MB are not deduplicated */
+    int no_recursion;          /* Take only the files directly inside directory inputs, like 7z a -r- (default: 0) */
+    int limit_file_size;       /* Skip regular files outside [min_file_size, max_file_size] (default: 0) */
+    uint64_t min_file_size;    /* Smallest file kept in bytes, with limit_file_size */
+    uint64_t max_file_size;    /* Largest file kept in bytes, with limit_file_size */
+    int sort_order;            /* SevenZipSortOrder in which files enter the solid stream (default: NONE) */
+    int compress_header;       /* LZMA-compress the archive header, as 7-Zip does (1 = yes, 0 = no, default: 0) */
+    int detect_hardlinks;      /* POSIX: store the data of a multiply-linked file once and further names as
+                                  hard link entries (default: 0); see SEVENZIP_ATTRIB_HARDLINK. Other 7z
+                                  readers extract those as small files holding the tag and target name */
+    uint32_t kdf_iterations;   /* SHA-256 rounds deriving the 7zAES key from password: a power of two up to
+                                  2^24 (0 = 2^SEVENZIP_DEFAULT_KDF_CYCLES_POWER); needs password */
+    const uint8_t* encryption_key; /* 32-byte AES key to encrypt with as is, in place of password (NULL = none);
+                                      7-Zip only derives keys from passwords and can't open the result */
 } SevenZipCompressOptions;
 
+/* Attribute bit of a hard link entry written with detect_hardlinks. The bit
+ * is unused by Windows (it was the old volume label flag), so it only counts
+ * on entries that also carry a POSIX regular-file mode (the 0x8000 Unix
+ * extension of p7zip and 7-Zip) and whose data is SEVENZIP_HARDLINK_TAG
+ * followed by the name of the entry holding the file's content. Readers
+ * return that content for such entries. */
+#define SEVENZIP_ATTRIB_HARDLINK 0x00000008u
+
+/* Start of the data of a hard link entry, before the target's name */
+#define SEVENZIP_HARDLINK_TAG "!<hardlink>"
+
+/* File naming scheme for the volumes of a split archive */
+typedef enum {
+    SEVENZIP_VOLUME_NAMING_NUMERIC = 0,  /* archive.7z.001, archive.7z.002, ... (7-Zip default) */
+    SEVENZIP_VOLUME_NAMING_PART_N = 1,   /* archive.part1.7z, archive.part2.7z, ... */
+    SEVENZIP_VOLUME_NAMING_ZIP_STYLE = 2 /* archive.z01, archive.z02, ..., last volume archive.zip */
+} SevenZipVolumeNaming;
+
+/* What to do with FIFOs, devices and sockets found while adding files */
+typedef enum {
+    SEVENZIP_SPECIAL_SKIP = 0,           /* Leave them out, with a warning on stderr */
+    SEVENZIP_SPECIAL_ERROR = 1,          /* Fail with SEVENZIP_ERROR_INVALID_PARAM */
+    SEVENZIP_SPECIAL_METADATA_ONLY = 2   /* Store an empty entry with their name, time and mode */
+} SevenZipSpecialFilePolicy;
+
 /* Streaming compression options for large files and split archives */
 typedef struct {
     int num_threads;           /* Number of threads (0 = auto, default: 2) */
@@ -89,6 +200,14 @@ typedef struct {
     uint64_t chunk_size;       /* Chunk size for streaming (0 = auto, default: 64MB) */
     const char* temp_dir;      /* Temporary directory (NULL = system default) */
     int delete_temp_on_error;  /* Delete temp files on error (1 = yes, 0 = no, default: 1) */
+    int volume_naming;         /* SevenZipVolumeNaming for split volumes (default: NUMERIC) */
+    SevenZipVolumeCallback volume_callback; /* Called as each split volume is finished (NULL = none).
+                                  Volumes 2..n-1 are reported as soon as the next one starts; the first
+                                  volume holds the start header, so it is reported with the last one */
+    void* volume_user_data;    /* User data passed to volume_callback */
+    SevenZipTempFileCallback temp_file_callback; /* Creates scratch files in place of temp_dir (NULL = none) */
+    void* temp_file_user_data; /* User data passed to temp_file_callback */
+    int skip_empty_dirs;       /* Drop directories with no files beneath them (default: 0) */
 } SevenZipStreamOptions;
 
 /**
@@ -104,6 +223,36 @@ SEVENZIP_API SevenZipErrorCode sevenzip_init(void);
  */
 SEVENZIP_API void sevenzip_cleanup(void);
 
+/**
+ * Request that the operation running on the calling thread stops early
+ * Intended to be called from inside a progress callback; the operation
+ * returns SEVENZIP_ERROR_CANCELLED once it reaches its next check point
+ */
+SEVENZIP_API void sevenzip_request_abort(void);
+
+/**
+ * Number of threads the most recent sevenzip_create_7z call on the calling
+ * thread actually used, after LZMA2 capped the request to the number of
+ * blocks in the input. Returns 0 if no creation has run on this thread or
+ * the count is unknown.
+ */
+SEVENZIP_API uint32_t sevenzip_last_threads_used(void);
+
+/**
+ * Estimated peak encoder memory in bytes of the most recent sevenzip_create_7z
+ * call on the calling thread, computed from the effective LZMA2 settings:
+ * the match finder and window of each block coder plus the block buffers of
+ * multithreaded LZMA2. Returns 0 if nothing went through LZMA2.
+ */
+SEVENZIP_API uint64_t sevenzip_last_peak_memory(void);
+
+/**
+ * Dictionary size in bytes the LZMA2 encoder ran with in the most recent
+ * sevenzip_create_7z call on the calling thread. Returns 0 if nothing went
+ * through LZMA2.
+ */
+SEVENZIP_API uint64_t sevenzip_last_dict_size(void);
+
 /**
  * Extract a 7z archive
  * @param archive_path Path to the archive file
@@ -121,6 +270,27 @@ SEVENZIP_API SevenZipErrorCode sevenzip_extract(
     void* user_data
 );
 
+/**
+ * Extract a 7z archive whose data was encrypted with a raw AES key
+ * 
+ * Counterpart of SevenZipCompressOptions.encryption_key: 7zAES folders are
+ * decrypted with key as is, without deriving one from a password.
+ * 
+ * @param archive_path Path to the archive file
+ * @param output_dir Directory to extract to
+ * @param key AES-256 key (32 bytes)
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_extract_with_key(
+    const char* archive_path,
+    const char* output_dir,
+    const uint8_t* key,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
 /**
  * Extract specific files from a 7z archive
  * @param archive_path Path to the archive file
@@ -178,6 +348,13 @@ SEVENZIP_API SevenZipErrorCode sevenzip_create_archive(
     void* user_data
 );
 
+/**
+ * Initialize compression options with the defaults sevenzip_create_7z uses
+ * when passed NULL: 2 threads, solid LZMA2, no password, all filters off
+ * @param options Pointer to options structure to initialize
+ */
+SEVENZIP_API void sevenzip_compress_options_init(SevenZipCompressOptions* options);
+
 /**
  * Create a standard .7z archive (compatible with 7-Zip)
  * Uses LZMA2 compression and creates archives readable by official 7-Zip
@@ -199,6 +376,66 @@ SEVENZIP_API SevenZipErrorCode sevenzip_create_7z(
     void* user_data
 );
 
+/**
+ * Create a 7z archive, handing the archive bytes to a write callback
+ * The bytes are produced strictly in order (no seeking), so the callback can
+ * hash, forward or stream them anywhere.
+ * @param input_paths Array of file/directory paths to compress (NULL-terminated)
+ * @param level Compression level
+ * @param options Advanced options (NULL for defaults)
+ * @param write_callback Receives the archive bytes in order
+ * @param write_user_data User data passed to write_callback
+ * @param progress_callback Optional progress callback
+ * @param user_data User data for progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_create_7z_to_callback(
+    const char** input_paths,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* options,
+    SevenZipWriteCallback write_callback,
+    void* write_user_data,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/**
+ * LZMA2-compress a buffer in memory and report the packed size, without
+ * keeping the output (for estimating archive sizes from a sample)
+ * @param data Input bytes
+ * @param size Input size in bytes
+ * @param level Compression level (STORE reports size unchanged)
+ * @param packed_size Output: size of the LZMA2 stream
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_packed_size_of_buffer(
+    const uint8_t* data,
+    size_t size,
+    SevenZipCompressionLevel level,
+    uint64_t* packed_size
+);
+
+/**
+ * Create a single-entry .7z archive from data supplied by a read callback
+ * The total size does not need to be known in advance; data is compressed as
+ * it is read, so nothing is staged in memory or on disk.
+ * @param archive_path Path for the output .7z file
+ * @param entry_name Name of the single entry inside the archive
+ * @param level Compression level (STORE writes the data uncompressed)
+ * @param options Advanced options (NULL for defaults; only threads and dictionary size apply)
+ * @param read_callback Callback supplying the entry contents
+ * @param user_data User data passed to read_callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_create_7z_from_stream(
+    const char* archive_path,
+    const char* entry_name,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* options,
+    SevenZipReadCallback read_callback,
+    void* user_data
+);
+
 /**
  * Extract a multi-file archive created with sevenzip_create_archive()
  * @param archive_path Path to the archive file
@@ -229,12 +466,282 @@ SEVENZIP_API SevenZipErrorCode sevenzip_list(
     SevenZipList** list
 );
 
+/**
+ * Read archive-level totals from header metadata without building an entry list
+ * @param archive_path Path to the archive file
+ * @param password Optional password (NULL if not encrypted)
+ * @param info Pointer to receive the archive summary
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_get_archive_info(
+    const char* archive_path,
+    const char* password,
+    SevenZipArchiveInfo* info
+);
+
+/**
+ * Get archive-level totals of a split archive, read across all its volumes
+ * @param first_volume_path Path to the first volume (e.g. "archive.7z.001");
+ *                          the others must follow the same numeric naming
+ * @param password Optional password (NULL if not encrypted)
+ * @param info Output info structure
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_get_split_archive_info(
+    const char* first_volume_path,
+    const char* password,
+    SevenZipArchiveInfo* info
+);
+
 /**
  * Free memory allocated by sevenzip_list
  * @param list List to free
  */
 SEVENZIP_API void sevenzip_free_list(SevenZipList* list);
 
+/**
+ * Opaque handle to an open archive, for reading individual entries without
+ * re-parsing the archive headers on every call
+ */
+typedef struct SevenZipArchive SevenZipArchive;
+
+/**
+ * Open a 7z archive for entry-by-entry reading
+ * @param archive_path Path to the archive file
+ * @param password Password that decrypts 7zAES data (NULL if not encrypted); the handle keeps a copy
+ * @param archive Pointer to receive the handle (must be closed with sevenzip_archive_close)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_open(
+    const char* archive_path,
+    const char* password,
+    SevenZipArchive** archive
+);
+
+/**
+ * Decrypt 7zAES data read through the handle with a raw AES key instead of
+ * one derived from the password, see SevenZipCompressOptions.encryption_key
+ * @param archive Handle from sevenzip_archive_open
+ * @param key AES-256 key (32 bytes), copied into the handle
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_set_key(
+    SevenZipArchive* archive,
+    const uint8_t* key
+);
+
+/**
+ * List the entries of an open archive, in archive index order
+ * @param archive Open archive handle
+ * @param list Pointer to receive the list result (must be freed with sevenzip_free_list)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_list(
+    SevenZipArchive* archive,
+    SevenZipList** list
+);
+
+/**
+ * List up to count entries of an open archive, starting at index start
+ * Lets callers walk huge archives in batches instead of one large list.
+ * @param archive Open archive handle
+ * @param start Index of the first entry; past the end gives an empty list
+ * @param count Maximum number of entries to return
+ * @param list Pointer to receive the list result (must be freed with sevenzip_free_list)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_list_range(
+    SevenZipArchive* archive,
+    uint32_t start,
+    uint32_t count,
+    SevenZipList** list
+);
+
+/**
+ * Decompress a single file entry of an open archive into memory; a hard link
+ * entry (see SEVENZIP_ATTRIB_HARDLINK) reads as the content of its target
+ * @param archive Open archive handle
+ * @param index Entry index, as in the list returned by sevenzip_archive_list
+ * @param data Pointer to receive the entry contents; owned by the handle and
+ *             valid until the next read or until the archive is closed
+ * @param size Pointer to receive the content size in bytes
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_read_entry(
+    SevenZipArchive* archive,
+    uint32_t index,
+    const uint8_t** data,
+    size_t* size
+);
+
+/**
+ * Decompress one file entry of an open archive, handing its data to a write
+ * callback in chunks instead of holding it in memory
+ *
+ * Hard link entries are passed on as stored. The CRC is checked once the
+ * whole entry has been handed over, so a damaged entry reports its error
+ * after its data was written.
+ * @param archive Open archive handle
+ * @param index Entry index, as in the list returned by sevenzip_archive_list
+ * @param write_callback Receives the entry contents in order; a non-zero
+ *                       return stops decoding with SEVENZIP_ERROR_EXTRACT
+ * @param user_data User data passed to write_callback
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_NOT_IMPLEMENTED if the
+ *         entry's folder uses a coder chain other than a single Copy or
+ *         LZMA2 coder (use sevenzip_archive_read_entry), error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_stream_entry(
+    SevenZipArchive* archive,
+    uint32_t index,
+    SevenZipWriteCallback write_callback,
+    void* user_data
+);
+
+/**
+ * Decompress a whole folder (solid block) of an open archive into memory
+ *
+ * The result is the concatenated contents of the folder's file entries, in
+ * archive index order.
+ * @param archive Open archive handle
+ * @param folder_index Folder index, below sevenzip_archive_folder_count
+ * @param data Pointer to receive the decoded folder; owned by the handle and
+ *             valid until the next read or until the archive is closed
+ * @param size Pointer to receive the decoded size in bytes
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_read_folder(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    const uint8_t** data,
+    size_t* size
+);
+
+/**
+ * One folder (solid block) of an archive: a run of packed bytes that decodes
+ * to the contents of one or more consecutive file entries
+ */
+typedef struct {
+    uint64_t packed_offset;  /* Absolute offset of the packed data in the archive file */
+    uint64_t packed_size;    /* Packed size in bytes */
+    char codec[32];          /* Codec chain, e.g. "LZMA2" or "BCJ LZMA2" */
+} SevenZipFolderInfo;
+
+/** Folder index reported for entries that have no data (directories, empty files) */
+#define SEVENZIP_NO_FOLDER 0xFFFFFFFFu
+
+/**
+ * A folder exactly as its archive describes it, for copying it into another
+ * archive without decoding; the arrays are owned by the archive handle
+ */
+typedef struct {
+    const uint8_t* record;           /* Folder record from the header: coders, bonds, packed stream indices */
+    size_t record_size;              /* Size of record in bytes */
+    const uint64_t* pack_positions;  /* num_pack_streams + 1 offsets; stream i spans [i], [i + 1] */
+    uint32_t num_pack_streams;       /* Packed streams the folder reads, stored back to back */
+    const uint64_t* unpack_sizes;    /* Output size of each coder */
+    uint32_t num_unpack_sizes;       /* Number of coders */
+} SevenZipRawFolder;
+
+/**
+ * Describe one folder as stored, see SevenZipRawFolder
+ * @param archive Open archive handle
+ * @param folder_index Folder index, below sevenzip_archive_folder_count
+ * @param folder Output description, valid until the archive is closed
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_PARAM for a bad index
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_raw_folder(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipRawFolder* folder
+);
+
+/**
+ * Get the number of folders (solid blocks) in an open archive
+ * @param archive Open archive handle
+ * @return Folder count, 0 if archive is NULL
+ */
+SEVENZIP_API uint32_t sevenzip_archive_folder_count(SevenZipArchive* archive);
+
+/**
+ * Describe where one folder lives in the archive file
+ * @param archive Open archive handle
+ * @param folder_index Folder index, below sevenzip_archive_folder_count
+ * @param info Output folder description
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_folder_info(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipFolderInfo* info
+);
+
+/**
+ * Get an entry's modification time exactly as stored
+ * @param archive Open archive handle
+ * @param index Entry index, as in the list returned by sevenzip_archive_list
+ * @param filetime Output: Windows FILETIME (100 ns ticks since 1601-01-01 UTC)
+ * @return 1 if the entry stores a modification time, 0 otherwise
+ */
+SEVENZIP_API int sevenzip_archive_entry_filetime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime);
+
+/** Like sevenzip_archive_entry_filetime, for the creation time (CTime); 0 if not stored */
+SEVENZIP_API int sevenzip_archive_entry_ctime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime);
+
+/** Like sevenzip_archive_entry_filetime, for the last access time (ATime); 0 if not stored */
+SEVENZIP_API int sevenzip_archive_entry_atime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime);
+
+/** 7zAES parameters of an encrypted folder, from its coder properties */
+typedef struct {
+    int encrypted;           /* 1 if the folder has a 7zAES coder; the other fields are 0 otherwise */
+    uint32_t cycles_power;   /* The key is derived in 2^cycles_power SHA-256 rounds (0x3F = none) */
+    uint32_t salt_size;      /* Salt length in bytes */
+    uint32_t iv_size;        /* IV length in bytes */
+} SevenZipEncryptionInfo;
+
+/**
+ * Get the encryption parameters of one folder
+ * @param archive Open archive handle
+ * @param folder_index Folder index, less than sevenzip_archive_folder_count()
+ * @param info Output; info->encrypted is 0 for folders that are not encrypted
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_PARAM for a bad index
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_folder_encryption(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipEncryptionInfo* info
+);
+
+/**
+ * Get the folder that holds an entry's data
+ * @param archive Open archive handle
+ * @param index Entry index, as in the list returned by sevenzip_archive_list
+ * @return Folder index, or SEVENZIP_NO_FOLDER if the entry has no data
+ */
+SEVENZIP_API uint32_t sevenzip_archive_entry_folder(SevenZipArchive* archive, uint32_t index);
+
+/**
+ * Find the entry holding the content of a hard link entry (see
+ * SEVENZIP_ATTRIB_HARDLINK); sevenzip_archive_read_entry already returns
+ * that content for the link
+ * @param archive Open archive handle
+ * @param index Entry index, as in the list returned by sevenzip_archive_list
+ * @param target Receives the target's index, or index itself if the entry is
+ *               not a hard link
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_ARCHIVE if the link
+ *         names no file entry, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_archive_hardlink_target(
+    SevenZipArchive* archive,
+    uint32_t index,
+    uint32_t* target
+);
+
+/**
+ * Close an archive handle and release its buffers
+ * @param archive Handle to close (NULL is ignored)
+ */
+SEVENZIP_API void sevenzip_archive_close(SevenZipArchive* archive);
+
 /**
  * Test archive integrity without extracting
  * Validates CRCs, decompression, and structure without writing files to disk
@@ -251,6 +758,23 @@ SEVENZIP_API SevenZipErrorCode sevenzip_test_archive(
     void* user_data
 );
 
+/**
+ * Test archive integrity, reporting each file entry as it is checked
+ * Directories carry no data and are not reported. Every file is tested even
+ * after a failure, so the callback sees the whole archive.
+ * @param archive_path Path to the archive file (supports split volumes)
+ * @param password Optional password (NULL if not encrypted)
+ * @param entry_callback Called after each file entry with its name and result
+ * @param user_data User data passed to entry_callback
+ * @return SEVENZIP_OK if every entry passed, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_test_archive_entries(
+    const char* archive_path,
+    const char* password,
+    SevenZipTestEntryCallback entry_callback,
+    void* user_data
+);
+
 /**
  * Decompress a standalone LZMA file (.lzma)
  * @param lzma_path Path to the .lzma file
@@ -281,6 +805,128 @@ SEVENZIP_API SevenZipErrorCode sevenzip_decompress_lzma2(
     void* user_data
 );
 
+/**
+ * Compress a single file to LZMA2 format (1 properties byte + LZMA2 stream)
+ * @param input_path Path to the file to compress
+ * @param output_path Path for the compressed output file
+ * @param level Compression level
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_compress_file(
+    const char* input_path,
+    const char* output_path,
+    SevenZipCompressionLevel level,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/**
+ * Compress a single file to LZMA2 format with a custom I/O buffer size
+ * @param input_path Path to the file to compress
+ * @param output_path Path for the compressed output file
+ * @param level Compression level
+ * @param buffer_size Read/write buffer size in bytes (0 = default, 64 KB)
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_compress_file_ex(
+    const char* input_path,
+    const char* output_path,
+    SevenZipCompressionLevel level,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/**
+ * Decompress a file written by sevenzip_compress_file
+ * @param input_path Path to the compressed file
+ * @param output_path Path for the decompressed output file
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_ARCHIVE if the stream is
+ *         truncated or corrupt (the partial output is removed), error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_decompress_file(
+    const char* input_path,
+    const char* output_path,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/**
+ * Decompress a file written by sevenzip_compress_file with a custom I/O buffer size
+ * @param input_path Path to the compressed file
+ * @param output_path Path for the decompressed output file
+ * @param buffer_size Read/write buffer size in bytes (0 = default, 64 KB)
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_decompress_file_ex(
+    const char* input_path,
+    const char* output_path,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/* Sized single-file format (sevenzip_compress_file_sized): a 14-byte header,
+ * then the same LZMA2 stream as sevenzip_compress_file writes
+ *   bytes 0-3   SEVENZIP_SIZED_MAGIC
+ *   byte  4     SEVENZIP_SIZED_VERSION
+ *   bytes 5-12  uncompressed size, little-endian u64
+ *   byte  13    LZMA2 properties */
+#define SEVENZIP_SIZED_MAGIC "7zL2"
+#define SEVENZIP_SIZED_VERSION 1
+#define SEVENZIP_SIZED_HEADER_SIZE 14
+
+/**
+ * Compress a single file to LZMA2 behind a header recording its size, so
+ * readers can preallocate and detect truncation
+ * @param input_path Path to the file to compress
+ * @param output_path Path for the compressed output file
+ * @param level Compression level
+ * @param buffer_size Read/write buffer size in bytes (0 = default, 64 KB)
+ * @param progress_callback Optional progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_compress_file_sized(
+    const char* input_path,
+    const char* output_path,
+    SevenZipCompressionLevel level,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+);
+
+/**
+ * Decompress a file written by sevenzip_compress_file_sized, checking the
+ * decoded length against the header
+ * @param input_path Path to the compressed file
+ * @param output_path Path for the decompressed output file
+ * @param buffer_size Read/write buffer size in bytes (0 = default, 64 KB)
+ * @param progress_callback Optional progress callback (NULL to disable), called
+ *        with the header's size as the total
+ * @param user_data User data passed to progress callback
+ * @param decoded_size Optional output: bytes decoded, also when decoding fails
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_ARCHIVE if the header is
+ *         missing, the stream is truncated or corrupt, or the decoded length
+ *         differs from the header (the partial output is removed), error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_decompress_file_sized(
+    const char* input_path,
+    const char* output_path,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data,
+    uint64_t* decoded_size
+);
+
 /**
  * Get error message for error code
  * @param error_code Error code
@@ -294,6 +940,13 @@ SEVENZIP_API const char* sevenzip_get_error_message(SevenZipErrorCode error_code
  */
 SEVENZIP_API const char* sevenzip_get_version(void);
 
+/**
+ * Check whether this build can compress with a codec
+ * @param method SevenZipMethod value
+ * @return 1 if the codec is available, 0 if not (or the value is unknown)
+ */
+SEVENZIP_API int sevenzip_codec_supported(int method);
+
 /**
  * Initialize streaming options with defaults
  * @param options Pointer to options structure to initialize
@@ -380,6 +1033,28 @@ SEVENZIP_API SevenZipErrorCode sevenzip_extract_streaming(
     void* user_data
 );
 
+/**
+ * Extract a 7z archive split into volumes given as an explicit, ordered list
+ * The volumes are read as one logical stream regardless of their file names.
+ *
+ * @param volume_paths Volume paths in archive order
+ * @param volume_count Number of volumes (at least 1)
+ * @param output_dir Directory to extract to
+ * @param password Optional password (NULL if not encrypted)
+ * @param progress_callback Optional byte-level progress callback (NULL to disable)
+ * @param user_data User data passed to progress callback
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_OPEN_FILE if a volume can't be
+ *         opened, SEVENZIP_ERROR_INVALID_ARCHIVE if the volumes don't form an archive
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_extract_volumes(
+    const char** volume_paths,
+    size_t volume_count,
+    const char* output_dir,
+    const char* password,
+    SevenZipBytesProgressCallback progress_callback,
+    void* user_data
+);
+
 /**
  * ============================================================================
  * AES-256 Encryption Functions
@@ -410,6 +1085,55 @@ SEVENZIP_API SevenZipErrorCode sevenzip_init_encryption(
     uint32_t* aes_context
 );
 
+/**
+ * Initialize encryption context with password and a caller-chosen salt
+ * 
+ * Use this when the salt must be stored to decrypt later; the caller also
+ * picks the IV passed to sevenzip_encrypt_data().
+ * 
+ * @param password Password string (UTF-8)
+ * @param salt Salt for key derivation (should be random, typically 16 bytes)
+ * @param salt_len Length of salt in bytes
+ * @param key Output buffer for derived key (32 bytes)
+ * @param aes_context Output buffer for AES context (must be 16-byte aligned)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_init_encryption_with_salt(
+    const char* password,
+    const uint8_t* salt,
+    size_t salt_len,
+    uint8_t* key,
+    uint32_t* aes_context
+);
+
+/* Rounds exponent of the 7zAES key derivation in archives written with a
+ * password (2^19 SHA-256 rounds, as 7-Zip writes) */
+#define SEVENZIP_DEFAULT_KDF_CYCLES_POWER 19
+
+/**
+ * Derive a .7z AES-256 key exactly as 7-Zip does
+ * 
+ * Unlike the functions above, this follows the 7zAES method: the password
+ * is hashed as UTF-16LE (so non-ASCII passwords match the official tools),
+ * in 2^num_cycles_power rounds of SHA-256 over salt, password and a 64-bit
+ * round counter. A power of 0x3F means no hashing (key = salt + password,
+ * zero-padded). 7-Zip writes 19 and an empty salt by default.
+ * 
+ * @param password Password string (UTF-8)
+ * @param salt Salt from the archive's AES coder properties (may be NULL if salt_len is 0)
+ * @param salt_len Length of salt in bytes (at most 16)
+ * @param num_cycles_power Log2 of the number of SHA-256 rounds (at most 24, or 0x3F)
+ * @param key Output buffer for the derived key (32 bytes)
+ * @return SEVENZIP_OK on success, SEVENZIP_ERROR_INVALID_PARAM otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_derive_7z_key(
+    const char* password,
+    const uint8_t* salt,
+    size_t salt_len,
+    uint32_t num_cycles_power,
+    uint8_t* key
+);
+
 /**
  * Initialize decryption context with password and salt
  * 
@@ -428,6 +1152,33 @@ SEVENZIP_API SevenZipErrorCode sevenzip_init_decryption(
     uint32_t* aes_context
 );
 
+/**
+ * Initialize encryption context from a raw key, skipping key derivation
+ * 
+ * For keys obtained elsewhere (e.g. a KMS). Reference 7-Zip always derives
+ * its key from a password and can't use such a key.
+ * 
+ * @param key AES-256 key (32 bytes)
+ * @param aes_context Output buffer for AES context (must be 16-byte aligned)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_init_encryption_with_key(
+    const uint8_t* key,
+    uint32_t* aes_context
+);
+
+/**
+ * Initialize decryption context from a raw key, skipping key derivation
+ * 
+ * @param key AES-256 key (32 bytes)
+ * @param aes_context Output buffer for AES context (must be 16-byte aligned)
+ * @return SEVENZIP_OK on success, error code otherwise
+ */
+SEVENZIP_API SevenZipErrorCode sevenzip_init_decryption_with_key(
+    const uint8_t* key,
+    uint32_t* aes_context
+);
+
 /**
  * Encrypt data using AES-256-CBC with PKCS#7 padding
  * 
@@ -455,7 +1206,7 @@ SEVENZIP_API SevenZipErrorCode sevenzip_encrypt_data(
  * @param iv Initialization vector (16 bytes, from archive header)
  * @param ciphertext Encrypted data
  * @param ciphertext_len Length of ciphertext in bytes (must be multiple of 16)
- * @param plaintext Output buffer for decrypted data (must be 16-byte aligned)
+ * @param plaintext Output buffer for decrypted data (any alignment)
  * @param plaintext_len In: buffer size, Out: actual decrypted length
  * @return SEVENZIP_OK on success, SEVENZIP_ERROR_EXTRACT if wrong password
  */
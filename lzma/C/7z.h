@@ -120,6 +120,7 @@ typedef struct
   // CSzBitUi32s Parents;
   CSzBitUi64s MTime;
   CSzBitUi64s CTime;
+  CSzBitUi64s ATime;
 
   UInt32 *FolderToFile;   // NumFolders + 1
   UInt32 *FileToFolder;   // NumFiles
@@ -148,6 +148,7 @@ void SzArEx_Init(CSzArEx *p)
   // SzBitUi32s_INIT(&p->Parents)
   SzBitUi64s_INIT(&p->MTime)
   SzBitUi64s_INIT(&p->CTime)
+  SzBitUi64s_INIT(&p->ATime)
 }
 
 void SzArEx_Free(CSzArEx *p, ISzAllocPtr alloc)
@@ -166,6 +167,7 @@ void SzArEx_Free(CSzArEx *p, ISzAllocPtr alloc)
   // SzBitUi32s_Free(&p->Parents, alloc);
   SzBitUi64s_Free(&p->MTime, alloc);
   SzBitUi64s_Free(&p->CTime, alloc);
+  SzBitUi64s_Free(&p->ATime, alloc);
   
   SzAr_Free(&p->db, alloc);
   SzArEx_Init(p);
@@ -1285,6 +1287,7 @@ static SRes SzReadHeader2(
       */
       case k7zIdMTime: RINOK(ReadTime(&p->MTime, numFiles, sd, tempBufs, *numTempBufs, allocMain)) break;
       case k7zIdCTime: RINOK(ReadTime(&p->CTime, numFiles, sd, tempBufs, *numTempBufs, allocMain)) break;
+      case k7zIdATime: RINOK(ReadTime(&p->ATime, numFiles, sd, tempBufs, *numTempBufs, allocMain)) break;
       default:
       {
         SKIP_DATA(sd, size)
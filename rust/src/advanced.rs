@@ -7,6 +7,7 @@
 
 use crate::error::{Error, Result};
 use crate::ffi;
+use crate::threads::clamp_threads;
 use crate::CompressionLevel;
 use std::ffi::{CString, CStr};
 use std::path::Path;
@@ -85,6 +86,7 @@ pub fn get_error_string(code: i32) -> String {
         5 => ffi::SevenZipErrorCode::SEVENZIP_ERROR_COMPRESS,
         6 => ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_PARAM,
         7 => ffi::SevenZipErrorCode::SEVENZIP_ERROR_NOT_IMPLEMENTED,
+        8 => ffi::SevenZipErrorCode::SEVENZIP_ERROR_CANCELLED,
         _ => ffi::SevenZipErrorCode::SEVENZIP_ERROR_UNKNOWN,
     };
     
@@ -123,6 +125,7 @@ pub fn get_version() -> String {
 ///                   - 734_003_200 (700MB CD)
 ///                   - 4_700_372_992 (4.7GB DVD)
 ///                   - 4_294_967_296 (4GB FAT32 limit)
+/// * `password` - Must be `None`; volumes are never encrypted, so a password fails with [`Error::UnsupportedOptionCombination`]
 ///
 /// # Example
 ///
@@ -148,6 +151,12 @@ pub fn create_split_archive(
     volume_size: u64,
     password: Option<&str>,
 ) -> Result<()> {
+    level.validate()?;
+    if password.is_some() {
+        return Err(Error::UnsupportedOptionCombination {
+            detail: "split volumes are written without encryption; password must be None".to_string(),
+        });
+    }
     let archive_path = archive_path.as_ref().to_str()
         .ok_or_else(|| Error::Io("Invalid path encoding".to_string()))?;
     let c_archive = CString::new(archive_path)?;
@@ -171,16 +180,8 @@ pub fn create_split_archive(
     c_path_ptrs.push(std::ptr::null());
     
     // Setup compression options
-    let c_password = password
-        .map(|p| CString::new(p))
-        .transpose()?;
-    
-    let c_options = ffi::SevenZipCompressOptions {
-        num_threads: 0, // auto
-        dict_size: 0,   // auto
-        solid: 1,       // solid archive
-        password: c_password.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
-    };
+    let mut c_options = ffi::SevenZipCompressOptions::defaults();
+    c_options.num_threads = clamp_threads(0) as i32; // auto unless capped
     
     unsafe {
         let result = ffi::sevenzip_create_multivolume_7z(
@@ -280,6 +281,7 @@ pub fn compress_lzma(
     output_path: impl AsRef<Path>,
     level: CompressionLevel,
 ) -> Result<()> {
+    level.validate()?;
     let input = input_path.as_ref().to_str().ok_or(Error::Io("Invalid path encoding".to_string()))?;
     let output = output_path.as_ref().to_str().ok_or(Error::Io("Invalid path encoding".to_string()))?;
     
@@ -363,6 +365,7 @@ pub fn compress_lzma2(
     output_path: impl AsRef<Path>,
     level: CompressionLevel,
 ) -> Result<()> {
+    level.validate()?;
     let input = input_path.as_ref().to_str().ok_or(Error::Io("Invalid path encoding".to_string()))?;
     let output = output_path.as_ref().to_str().ok_or(Error::Io("Invalid path encoding".to_string()))?;
     
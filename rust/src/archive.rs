@@ -5,9 +5,22 @@
 
 use crate::error::{Error, Result};
 use crate::ffi;
+use crate::cancel::CancelToken;
+use crate::raw_copy::{self, CopiedFile, CopiedFolder, RawFolder};
+use crate::retry::RetryPolicy;
+use crate::threads::clamp_threads;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Compression level for archive operations
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -24,6 +37,60 @@ pub enum CompressionLevel {
     Maximum,
     /// Ultra compression (slowest, best ratio)
     Ultra,
+    /// A raw 7-Zip level from 0 to 9, passed to the encoder as it is
+    ///
+    /// Reaches the levels between the named ones (2, 4, 6, 8);
+    /// `Custom(5)` is the same as [`CompressionLevel::Normal`]. Values
+    /// above 9 are rejected with [`Error::InvalidParameter`].
+    Custom(u32),
+}
+
+impl CompressionLevel {
+    /// Highest level accepted by [`CompressionLevel::Custom`]
+    const MAX_CUSTOM: u32 = 9;
+
+    /// Reject a [`CompressionLevel::Custom`] level outside 0-9
+    pub(crate) fn validate(self) -> Result<Self> {
+        match self {
+            CompressionLevel::Custom(level) if level > Self::MAX_CUSTOM => Err(Error::InvalidParameter(format!(
+                "Compression level {} is out of range (0-{})",
+                level,
+                Self::MAX_CUSTOM
+            ))),
+            _ => Ok(self),
+        }
+    }
+
+    /// Dictionary size the C library picks for this level when none is set,
+    /// before it is fitted to the input
+    fn default_dict_size(self) -> u64 {
+        match self {
+            CompressionLevel::Store => 1 << 16,
+            CompressionLevel::Fastest => 1 << 18,
+            CompressionLevel::Fast => 1 << 20,
+            CompressionLevel::Normal => 1 << 23,
+            CompressionLevel::Maximum => 1 << 25,
+            CompressionLevel::Ultra => 1 << 26,
+            // The LZMA encoder's own table for raw levels
+            CompressionLevel::Custom(level @ 0..=3) => 1 << (level * 2 + 16),
+            CompressionLevel::Custom(level @ 4..=6) => 1 << (level + 19),
+            CompressionLevel::Custom(7) => 1 << 25,
+            CompressionLevel::Custom(_) => 1 << 26,
+        }
+    }
+
+    /// The next faster level, or `None` for [`CompressionLevel::Store`]
+    fn faster(self) -> Option<Self> {
+        match self {
+            CompressionLevel::Custom(level) => level.checked_sub(1).map(CompressionLevel::Custom),
+            CompressionLevel::Store => None,
+            CompressionLevel::Fastest => Some(CompressionLevel::Store),
+            CompressionLevel::Fast => Some(CompressionLevel::Fastest),
+            CompressionLevel::Normal => Some(CompressionLevel::Fast),
+            CompressionLevel::Maximum => Some(CompressionLevel::Normal),
+            CompressionLevel::Ultra => Some(CompressionLevel::Maximum),
+        }
+    }
 }
 
 impl From<CompressionLevel> for ffi::SevenZipCompressionLevel {
@@ -35,28 +102,248 @@ impl From<CompressionLevel> for ffi::SevenZipCompressionLevel {
             CompressionLevel::Normal => ffi::SevenZipCompressionLevel::SEVENZIP_LEVEL_NORMAL,
             CompressionLevel::Maximum => ffi::SevenZipCompressionLevel::SEVENZIP_LEVEL_MAXIMUM,
             CompressionLevel::Ultra => ffi::SevenZipCompressionLevel::SEVENZIP_LEVEL_ULTRA,
+            CompressionLevel::Custom(level) => ffi::SevenZipCompressionLevel(level.min(i32::MAX as u32) as i32),
+        }
+    }
+}
+
+/// Codec used to store file data
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum CompressionMethod {
+    /// LZMA2 compression
+    #[default]
+    Lzma2,
+    /// Stored without compression
+    Copy,
+}
+
+impl CompressionMethod {
+    /// Every codec the bindings know about, whether or not it is compiled in
+    pub(crate) const ALL: [CompressionMethod; 2] = [CompressionMethod::Lzma2, CompressionMethod::Copy];
+}
+
+impl From<CompressionMethod> for ffi::SevenZipMethod {
+    fn from(method: CompressionMethod) -> Self {
+        match method {
+            CompressionMethod::Lzma2 => ffi::SevenZipMethod::SEVENZIP_METHOD_LZMA2,
+            CompressionMethod::Copy => ffi::SevenZipMethod::SEVENZIP_METHOD_COPY,
+        }
+    }
+}
+
+/// File naming scheme for the volumes of a split archive
+///
+/// [`extract_streaming`](SevenZip::extract_streaming) finds the volumes of a
+/// [`Numeric`](Self::Numeric) split by itself; pass volumes named any other
+/// way to [`extract_from_volumes`](SevenZip::extract_from_volumes).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum VolumeNaming {
+    /// `archive.7z.001`, `archive.7z.002`, ... as 7-Zip names volumes (default)
+    #[default]
+    Numeric,
+    /// `archive.part1.7z`, `archive.part2.7z`, ...
+    PartN,
+    /// `archive.z01`, `archive.z02`, ..., with the last volume named `archive.zip`
+    ZipStyle,
+}
+
+impl VolumeNaming {
+    /// Path of the `index`-th volume (1-based); for [`ZipStyle`](Self::ZipStyle)
+    /// this is the name before the last volume is renamed to `.zip`
+    fn volume_path(self, archive_path: &Path, index: u32) -> PathBuf {
+        if self == VolumeNaming::Numeric {
+            let mut name = archive_path.as_os_str().to_os_string();
+            name.push(format!(".{:03}", index));
+            return PathBuf::from(name);
+        }
+
+        let mut name = archive_path.file_stem().unwrap_or_default().to_os_string();
+        if self == VolumeNaming::PartN {
+            name.push(format!(".part{}", index));
+            if let Some(extension) = archive_path.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+        } else {
+            name.push(format!(".z{:02}", index));
+        }
+        archive_path.with_file_name(name)
+    }
+
+    /// Naming scheme and archive path of a split set from the path of its
+    /// first volume (`.001`, `.part1` or `.z01`), if it is one
+    fn detect(first_volume: &Path) -> Option<(VolumeNaming, PathBuf)> {
+        let file_name = first_volume.file_name()?.to_str()?;
+        if let Some(base) = file_name.strip_suffix(".001") {
+            return Some((VolumeNaming::Numeric, first_volume.with_file_name(base)));
+        }
+        if let Some(base) = file_name.strip_suffix(".z01") {
+            return Some((VolumeNaming::ZipStyle, first_volume.with_file_name(format!("{}.zip", base))));
+        }
+        let (stem, extension) = match file_name.rsplit_once('.') {
+            Some((stem, extension)) if stem.ends_with(".part1") => (stem, format!(".{}", extension)),
+            _ => (file_name, String::new()),
+        };
+        let base = stem.strip_suffix(".part1")?;
+        Some((VolumeNaming::PartN, first_volume.with_file_name(format!("{}{}", base, extension))))
+    }
+}
+
+impl From<VolumeNaming> for ffi::SevenZipVolumeNaming {
+    fn from(naming: VolumeNaming) -> Self {
+        match naming {
+            VolumeNaming::Numeric => ffi::SevenZipVolumeNaming::SEVENZIP_VOLUME_NAMING_NUMERIC,
+            VolumeNaming::PartN => ffi::SevenZipVolumeNaming::SEVENZIP_VOLUME_NAMING_PART_N,
+            VolumeNaming::ZipStyle => ffi::SevenZipVolumeNaming::SEVENZIP_VOLUME_NAMING_ZIP_STYLE,
+        }
+    }
+}
+
+/// What to do with FIFOs, device nodes and sockets found in the input
+///
+/// Such files are never opened: reading a FIFO with no writer would block
+/// the archiver forever.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SpecialFilePolicy {
+    /// Leave them out of the archive and log a warning (default)
+    #[default]
+    Skip,
+    /// Fail with [`Error::InvalidParameter`] naming the file
+    Error,
+    /// Store an empty entry with the file's name, time and mode
+    StoreMetadataOnly,
+}
+
+impl From<SpecialFilePolicy> for ffi::SevenZipSpecialFilePolicy {
+    fn from(policy: SpecialFilePolicy) -> Self {
+        match policy {
+            SpecialFilePolicy::Skip => ffi::SevenZipSpecialFilePolicy::SEVENZIP_SPECIAL_SKIP,
+            SpecialFilePolicy::Error => ffi::SevenZipSpecialFilePolicy::SEVENZIP_SPECIAL_ERROR,
+            SpecialFilePolicy::StoreMetadataOnly => ffi::SevenZipSpecialFilePolicy::SEVENZIP_SPECIAL_METADATA_ONLY,
+        }
+    }
+}
+
+/// Order in which files are fed into the solid stream
+///
+/// Similar files next to each other compress better, since each finds
+/// matches in the one before it. 7z lists entries in the order they are
+/// stored, so [`SevenZip::list`] returns the new order; use
+/// [`SevenZip::list_sorted`] to view them another way. Directory entries
+/// keep their positions.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Input files in the order given, directory contents as read (default)
+    #[default]
+    None,
+    /// By extension (case-insensitive), then name, like 7-Zip's `-mqs`;
+    /// usually the best ratio for mixed trees
+    ByExtension,
+    /// By full entry name
+    ByName,
+    /// Smallest file first
+    BySize,
+}
+
+impl From<SortOrder> for ffi::SevenZipSortOrder {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::None => ffi::SevenZipSortOrder::SEVENZIP_SORT_NONE,
+            SortOrder::ByExtension => ffi::SevenZipSortOrder::SEVENZIP_SORT_BY_EXTENSION,
+            SortOrder::ByName => ffi::SevenZipSortOrder::SEVENZIP_SORT_BY_NAME,
+            SortOrder::BySize => ffi::SevenZipSortOrder::SEVENZIP_SORT_BY_SIZE,
         }
     }
 }
 
+/// How input paths become entry names in the archive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PathMode {
+    /// A file is stored under its file name and a directory's contents
+    /// relative to the directory itself (default)
+    #[default]
+    AsGiven,
+    /// Names are the inputs' paths relative to `base`, so `base/docs/a.txt`
+    /// is stored as `docs/a.txt` however it was passed in. Every input must
+    /// lie under `base`, otherwise [`Error::InvalidParameter`] is returned.
+    Relative {
+        /// Directory the stored names are relative to
+        base: PathBuf,
+    },
+    /// Every file is stored under its file name alone, without directories;
+    /// two files with the same name fail with [`Error::InvalidParameter`]
+    BasenameOnly,
+}
+
+/// What [`SevenZip::create_archive`] does when the archive path is taken
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ExistsPolicy {
+    /// Replace the existing file (default)
+    #[default]
+    Overwrite,
+    /// Leave it alone and return [`Error::AlreadyExists`]
+    Fail,
+    /// Write next to it with the current UTC time before the extension,
+    /// e.g. `archive-20240101-120000.7z` for `archive.7z`
+    AppendTimestamp,
+}
+
 /// Archive entry information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ArchiveEntry {
-    /// File name (UTF-8)
+    /// File name for display (UTF-8, with undecodable bytes replaced)
     pub name: String,
+    /// File name exactly as stored, for round-tripping names that are not
+    /// valid UTF-8
+    ///
+    /// 7z stores names as UTF-16. They decode to UTF-8 here, except that
+    /// bytes which were not valid UTF-8 when the archive was created (e.g.
+    /// Latin-1 file names on Linux) come back as the original bytes, and
+    /// unpaired UTF-16 surrogates from Windows names are kept as WTF-8.
+    /// Pass these bytes to [`SevenZip::extract_entry_as_raw`] to extract
+    /// entries whose `name` was mangled.
+    pub raw_name: Vec<u8>,
     /// Uncompressed size in bytes
     pub size: u64,
     /// Compressed size in bytes
     pub packed_size: u64,
     /// Unix timestamp of last modification
     pub modified_time: u64,
-    /// File attributes
+    /// File attributes: Windows `FILE_ATTRIBUTE_*` flags, plus a Unix mode
+    /// in the high 16 bits when `0x8000` is set (see [`HostOs`])
     pub attributes: u32,
     /// True if this is a directory
     pub is_directory: bool,
+    /// CRC32 of the uncompressed data, if the archive stores one
+    pub crc32: Option<u32>,
+    /// Codec chain the data is stored with, e.g. "LZMA2" or "Copy"
+    /// (empty for directories and empty files)
+    pub method: String,
 }
 
 impl ArchiveEntry {
+    /// `SEVENZIP_ATTRIB_HARDLINK`: a hard link entry written with
+    /// [`CompressOptions::detect_hardlinks`]
+    const HARDLINK: u32 = 0x08;
+    /// The Unix extension of p7zip and 7-Zip: a POSIX mode in the high 16 bits
+    const UNIX_EXTENSION: u32 = 0x8000;
+
+    /// Whether the header marks this as a hard link entry written with
+    /// [`CompressOptions::detect_hardlinks`]
+    ///
+    /// The hard link bit only counts together with a POSIX regular-file
+    /// mode, since on its own it may be a stray DOS attribute. Readers then
+    /// also check the tag that starts the entry's stored data, which names
+    /// the entry holding the file's content; reading the entry yields that
+    /// content. `size` and `crc32` still describe the stored tag.
+    pub fn is_hardlink(&self) -> bool {
+        !self.is_directory
+            && self.attributes & Self::HARDLINK != 0
+            && self.attributes & Self::UNIX_EXTENSION != 0
+            && (self.attributes >> 16) & 0o170000 == 0o100000
+    }
+
     /// Get compression ratio as a percentage (0-100)
     pub fn compression_ratio(&self) -> f64 {
         if self.size == 0 {
@@ -65,11 +352,267 @@ impl ArchiveEntry {
             (1.0 - (self.packed_size as f64 / self.size as f64)) * 100.0
         }
     }
+
+    /// Modification time as a [`SystemTime`](std::time::SystemTime)
+    /// (whole seconds; the Unix epoch if the archive stores none)
+    pub fn modified_system_time(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + Duration::from_secs(self.modified_time)
+    }
+
+    /// The entry name as a relative path with the platform's separators
+    ///
+    /// Both `/` and `\` are treated as separators. A leading `/` or drive
+    /// letter (`C:`) is dropped and `.`/`..` are resolved without climbing
+    /// above the root, so `output_dir.join(entry.path())` always stays
+    /// inside `output_dir`. A name with nothing left (e.g. `/` or `..`)
+    /// yields an empty path.
+    pub fn path(&self) -> PathBuf {
+        let mut parts: Vec<&str> = Vec::new();
+        for (i, part) in self.name.split(['/', '\\']).enumerate() {
+            let is_drive = i == 0 && part.len() == 2 && part.ends_with(':') && part.as_bytes()[0].is_ascii_alphabetic();
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                _ if is_drive => {}
+                _ => parts.push(part),
+            }
+        }
+        parts.iter().collect()
+    }
+}
+
+/// Order for [`SevenZip::list_sorted`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    /// By entry name, so each directory's contents end up together
+    Name,
+    /// Largest uncompressed size first
+    SizeDesc,
+    /// Oldest modification time first
+    ModifiedTime,
+    /// Largest packed size first
+    PackedSizeDesc,
+}
+
+/// One node of the directory hierarchy from [`SevenZip::tree`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeNode {
+    /// Last path component; empty for the root
+    pub name: String,
+    /// True for directories, including ones only implied by deeper paths
+    pub is_dir: bool,
+    /// Entries directly inside this directory, sorted by name
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    /// Add the entry at `components` below this node, creating missing
+    /// directories on the way
+    fn insert(&mut self, components: &[&str], is_dir: bool) {
+        let Some((first, rest)) = components.split_first() else {
+            return;
+        };
+        let index = match self.children.binary_search_by(|child| child.name.as_str().cmp(first)) {
+            Ok(index) => index,
+            Err(index) => {
+                let node = TreeNode { name: first.to_string(), ..Default::default() };
+                self.children.insert(index, node);
+                index
+            }
+        };
+        let child = &mut self.children[index];
+        child.is_dir |= is_dir || !rest.is_empty();
+        child.insert(rest, is_dir);
+    }
+}
+
+/// `FILE_ATTRIBUTE_*` flags of an entry, from [`ArchiveEntryFull`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WindowsAttributes {
+    /// `FILE_ATTRIBUTE_READONLY` (0x01)
+    pub readonly: bool,
+    /// `FILE_ATTRIBUTE_HIDDEN` (0x02)
+    pub hidden: bool,
+    /// `FILE_ATTRIBUTE_SYSTEM` (0x04)
+    pub system: bool,
+    /// `FILE_ATTRIBUTE_DIRECTORY` (0x10)
+    pub directory: bool,
+    /// `FILE_ATTRIBUTE_ARCHIVE` (0x20)
+    pub archive: bool,
+}
+
+impl WindowsAttributes {
+    /// Decode the low bits of a raw 7z attribute word
+    pub fn from_raw(attributes: u32) -> Self {
+        Self {
+            readonly: attributes & 0x01 != 0,
+            hidden: attributes & 0x02 != 0,
+            system: attributes & 0x04 != 0,
+            directory: attributes & 0x10 != 0,
+            archive: attributes & 0x20 != 0,
+        }
+    }
+}
+
+/// Every metadata field of an entry, raw and decoded, from [`SevenZip::list_full`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntryFull {
+    /// The entry as [`SevenZip::list`] reports it; `attributes` is the raw
+    /// attribute word and `crc32` the stored CRC
+    pub entry: ArchiveEntry,
+    /// Windows flags decoded from the low bits of the attributes
+    pub windows_attributes: WindowsAttributes,
+    /// Unix mode (type and permission bits) from the high 16 bits, when the
+    /// `0x8000` Unix-extension flag is set
+    pub unix_mode: Option<u32>,
+    /// Modification time as stored: FILETIME ticks of 100 ns since
+    /// 1601-01-01 UTC, or `None` if the entry has none
+    pub modified_ticks: Option<u64>,
+    /// `modified_ticks` as a [`SystemTime`](std::time::SystemTime), keeping
+    /// the full 100 ns precision
+    pub modified: Option<std::time::SystemTime>,
+    /// Creation time (inode change time for archives made on Unix), if the
+    /// archive stores one; see [`CompressOptions::store_all_times`]
+    pub created: Option<std::time::SystemTime>,
+    /// Last access time, if the archive stores one
+    pub accessed: Option<std::time::SystemTime>,
+    /// Folder (solid block) holding the entry's data; `None` for directories
+    /// and empty files
+    pub folder: Option<u32>,
 }
 
 /// Progress callback closure type
 pub type ProgressCallback = Box<dyn FnMut(u64, u64) + Send>;
 
+/// Callback for the directory-walk phase of archive creation
+///
+/// Called with the number of files seen so far. Shared behind a mutex so
+/// that [`CompressOptions`] stays `Clone`.
+#[derive(Clone)]
+pub struct WalkProgress(Arc<Mutex<dyn FnMut(u64) + Send>>);
+
+impl WalkProgress {
+    /// Wrap a closure receiving the running file count
+    pub fn new(callback: impl FnMut(u64) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+
+    fn report(&self, files_seen: u64) {
+        if let Ok(mut callback) = self.0.lock() {
+            callback(files_seen);
+        }
+    }
+}
+
+impl std::fmt::Debug for WalkProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WalkProgress(..)")
+    }
+}
+
+/// Hook receiving each finished split volume, see
+/// [`StreamOptions::on_volume_complete`]
+#[derive(Clone)]
+pub struct VolumeHook(Arc<Mutex<VolumeHookFn>>);
+
+type VolumeHookFn = dyn FnMut(&Path, u64) -> Result<()> + Send;
+
+impl VolumeHook {
+    /// Wrap a closure receiving each volume's path and size in bytes
+    pub fn new(hook: impl FnMut(&Path, u64) -> Result<()> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(hook)))
+    }
+
+    fn call(&self, volume: &Path, size: u64) -> Result<()> {
+        let mut hook = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        hook(volume, size)
+    }
+}
+
+impl std::fmt::Debug for VolumeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VolumeHook(..)")
+    }
+}
+
+/// Hook receiving each entry renamed during extraction, see
+/// [`ExtractOptions::on_rename`]
+#[derive(Clone)]
+pub struct RenameHook(Arc<Mutex<RenameHookFn>>);
+
+type RenameHookFn = dyn FnMut(&str, &Path) + Send;
+
+impl RenameHook {
+    /// Wrap a closure receiving each entry's stored name and the path it is
+    /// written to instead
+    pub fn new(hook: impl FnMut(&str, &Path) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(hook)))
+    }
+
+    fn call(&self, name: &str, path: &Path) {
+        let mut hook = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        hook(name, path)
+    }
+}
+
+impl std::fmt::Debug for RenameHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RenameHook(..)")
+    }
+}
+
+/// Factory for the scratch files of streaming compression, see
+/// [`StreamOptions::temp_file_factory`]
+#[derive(Clone)]
+pub struct TempFileFactory(Arc<Mutex<TempFileFactoryFn>>);
+
+type TempFileFactoryFn = dyn FnMut() -> std::io::Result<(PathBuf, std::fs::File)> + Send;
+
+impl TempFileFactory {
+    /// Wrap a closure creating a new scratch file, returning its path and
+    /// an open handle
+    pub fn new(factory: impl FnMut() -> std::io::Result<(PathBuf, std::fs::File)> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(factory)))
+    }
+
+    fn create(&self) -> std::io::Result<(PathBuf, std::fs::File)> {
+        let mut factory = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        factory()
+    }
+}
+
+impl std::fmt::Debug for TempFileFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TempFileFactory(..)")
+    }
+}
+
+/// Progress of a background operation, polled instead of pushed
+///
+/// Returned by [`SevenZip::extract_with_counter`]. The counters count
+/// archive entries, not bytes, as the extraction reports them. Clones share
+/// the same counters, and reading them never blocks the operation.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressHandle {
+    processed: Arc<AtomicU64>,
+    total: Arc<AtomicU64>,
+}
+
+impl ProgressHandle {
+    /// Entries processed so far
+    pub fn processed(&self) -> u64 {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    /// Total entries to process (0 until the operation has reported it)
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+}
+
 /// Byte-level progress callback closure type  
 /// Parameters: (bytes_processed, bytes_total, current_file_bytes, current_file_total, current_file_name)
 pub type BytesProgressCallback = Box<dyn FnMut(u64, u64, u64, u64, &str) + Send>;
@@ -178,10 +721,145 @@ pub struct CompressOptions {
     pub dict_size: u64,
     /// Create solid archive (better compression)
     pub solid: bool,
-    /// Optional password for encryption
+    /// Encrypt file data with 7zAES under this password
+    ///
+    /// Data is AES-256-CBC encrypted with the key derived as 7-Zip derives
+    /// it (see [`derive_7z_key`](crate::encryption::derive_7z_key)), so 7-Zip
+    /// opens the archive with the same password. Entry names, sizes and
+    /// times in the header stay readable. An empty password is rejected.
     pub password: Option<String>,
+    /// SHA-256 rounds that derive the key from `password` (default: 2^19,
+    /// as 7-Zip)
+    ///
+    /// The archive stores the count as a power of two, and 7-Zip refuses
+    /// more than 2^24 rounds, so anything else fails with
+    /// [`Error::InvalidParameter`], as does setting it without a password.
+    /// [`SevenZip::encryption_info`] reads it back.
+    pub kdf_iterations: Option<u32>,
+    /// Encrypt file data with this AES-256 key as is, e.g. one handed out by
+    /// a KMS, instead of deriving a key from `password`
+    ///
+    /// The folders are laid out as 7zAES, but 7-Zip always derives its key
+    /// from a password, so only this library can open the archive again,
+    /// through [`ExtractOptions::encryption_key`]. Setting both this and
+    /// `password` fails with [`Error::UnsupportedOptionCombination`].
+    pub encryption_key: Option<[u8; 32]>,
     /// Auto-detect and skip compression for incompressible data
     pub auto_detect_incompressible: bool,
+    /// Re-read every source file after writing and check it against the
+    /// stored CRC, failing with [`Error::SourceChanged`] on mismatch
+    pub verify_crc_after: bool,
+    /// Keep explicit entries for directories that contain no files
+    pub store_empty_dirs: bool,
+    /// Codec for files without an entry in `method_by_extension`
+    /// ([`CompressionLevel::Store`] always stores them with `Copy`)
+    pub method: CompressionMethod,
+    /// Per-extension codec overrides, keyed by extension without the dot
+    /// (matched case-insensitively). Each codec in use gets its own solid
+    /// block, e.g. `"jpg" => Copy` skips recompressing already-compressed media.
+    pub method_by_extension: HashMap<String, CompressionMethod>,
+    /// Walk the input tree before compressing, reporting the running file
+    /// count every 1000 files and once at the end
+    ///
+    /// The walk also gives the real total input size for thread auto-tuning,
+    /// which otherwise only counts top-level files.
+    pub walk_progress: Option<WalkProgress>,
+    /// Handling of FIFOs, devices and sockets in the input (Unix only)
+    pub special_files: SpecialFilePolicy,
+    /// Append parity data worth this percentage (0-100) of the archive size,
+    /// so [`SevenZip::recover`] can repair damaged blocks later
+    pub recovery_percent: Option<u8>,
+    /// Best-effort limit on how long compression may take
+    ///
+    /// Compression starts at the requested level. When the encoder's
+    /// throughput so far projects finishing past the budget, it is aborted
+    /// and restarted at the next faster level with whatever time is left,
+    /// down to [`CompressionLevel::Store`], which always runs to completion.
+    /// The resulting level depends on machine load, so output is not
+    /// reproducible, and the budget can still be overrun by the final
+    /// attempt. Reading the inputs counts against the budget.
+    pub time_budget: Option<Duration>,
+    /// Extra LZMA2 properties as `(key, value)` pairs in 7-Zip's `-m` syntax:
+    /// `mf` (`bt2`, `bt3`, `bt4`, `hc4`), `fb` (5-273), `lc`, `lp`, `pb`
+    /// (0-4, with `lc + lp <= 4`), `mc` (match cycles) and `a` (0 = fast,
+    /// 1 = normal). They override what the level picks; an unknown key or
+    /// bad value fails with [`Error::InvalidParameter`] naming it.
+    pub codec_properties: Vec<(String, String)>,
+    /// How input paths are turned into stored entry names
+    pub path_mode: PathMode,
+    /// Store each input directory's contents under the directory's own name
+    /// instead of at the archive root, so `/a/docs` and `/b/docs` don't mix
+    ///
+    /// Files keep their names. When inputs share a name, the first keeps it
+    /// and later ones are numbered: `docs`, `docs_2`, `docs_3`, ... (for
+    /// files before the extension: `notes.txt`, `notes_2.txt`). Only applies
+    /// with [`PathMode::AsGiven`].
+    pub preserve_root_names: bool,
+    /// Store creation and last access times alongside the modification time
+    ///
+    /// On Unix the "creation" slot holds the inode change time (`st_ctime`),
+    /// as 7-Zip itself stores it. Read back through [`SevenZip::list_full`].
+    pub store_all_times: bool,
+    /// Retry transient errors while reading input files
+    ///
+    /// Only [`SevenZip::create_tar_7z`] reads its inputs on the Rust side.
+    /// Everywhere else the C library opens and reads them, so a policy with
+    /// `attempts` above zero fails with
+    /// [`Error::UnsupportedOptionCombination`] rather than being ignored.
+    /// [`StreamOptions`] has no retry setting.
+    pub io_retry: RetryPolicy,
+    /// What to do when the archive file already exists
+    pub if_exists: ExistsPolicy,
+    /// Store identical files once (plus a few bytes per extra copy)
+    ///
+    /// Identical files are placed back to back in the solid LZMA2 stream and
+    /// the dictionary is grown to span the largest of them, turning each
+    /// repeat into long matches. Unlike the hard link entries of
+    /// [`detect_hardlinks`](Self::detect_hardlinks), this keeps the archive
+    /// readable by any 7z tool. The encoder needs about 11x that file size in
+    /// memory, so the dictionary stops at 64 MiB: larger copies are stored in
+    /// full, with a warning on stderr. Finding duplicates disables
+    /// multi-block threading. Has no effect on `Copy`-stored files.
+    ///
+    /// Like the rest of [`SevenZip::create_archive`], every input is read
+    /// into memory, so this doesn't suit multi-gigabyte images.
+    pub dedup: bool,
+    /// Descend into subdirectories of directory inputs (default: `true`)
+    ///
+    /// When `false`, like `7z a -r-`, a directory input contributes only the
+    /// files directly inside it. Its subdirectories are left out entirely,
+    /// even as empty entries, so `store_empty_dirs` has nothing to act on.
+    pub recursive: bool,
+    /// Leave out regular files smaller than this many bytes
+    pub min_file_size: Option<u64>,
+    /// Leave out regular files larger than this many bytes, e.g. to handle
+    /// huge logs separately
+    ///
+    /// Both limits are inclusive and apply to input files and to files found
+    /// in directory inputs alike. The files left out are listed in
+    /// [`CompressReport::skipped_by_size`].
+    pub max_file_size: Option<u64>,
+    /// Order of the files in the solid stream (default:
+    /// [`SortOrder::None`])
+    pub sort_order: SortOrder,
+    /// LZMA-compress the archive header (default: `false`)
+    ///
+    /// 7-Zip does this by default. Headers with many entries shrink a lot,
+    /// but every [`SevenZip::list`] or [`SevenZip::quick_check`] then has to
+    /// decompress them first. See [`ArchiveInfo::is_compressed_header`].
+    pub compress_header: bool,
+    /// On Unix, store a file with several hard links once: the first name
+    /// found gets the data, further names become hard link entries pointing
+    /// at it (default: `false`)
+    ///
+    /// The 7z format has no hard link entries, so this is an extension of
+    /// this library: it reads such entries as the target's content, and
+    /// [`ExtractOptions::restore_hardlinks`] restores the links. Other
+    /// readers, 7-Zip and p7zip included, extract every further name as a
+    /// small file holding a tag and the target's name instead of its data.
+    /// Leave this off for archives meant for other tools. No effect on
+    /// Windows.
+    pub detect_hardlinks: bool,
 }
 
 impl Default for CompressOptions {
@@ -191,7 +869,30 @@ impl Default for CompressOptions {
             dict_size: 0,   // auto
             solid: true,
             password: None,
+            kdf_iterations: None,
+            encryption_key: None,
             auto_detect_incompressible: false, // Conservative default
+            verify_crc_after: false,
+            store_empty_dirs: true,
+            method: CompressionMethod::Lzma2,
+            method_by_extension: HashMap::new(),
+            walk_progress: None,
+            special_files: SpecialFilePolicy::Skip,
+            recovery_percent: None,
+            time_budget: None,
+            codec_properties: Vec::new(),
+            path_mode: PathMode::AsGiven,
+            preserve_root_names: false,
+            store_all_times: false,
+            io_retry: RetryPolicy::default(),
+            if_exists: ExistsPolicy::Overwrite,
+            dedup: false,
+            recursive: true,
+            min_file_size: None,
+            max_file_size: None,
+            sort_order: SortOrder::None,
+            compress_header: false,
+            detect_hardlinks: false,
         }
     }
 }
@@ -207,10 +908,59 @@ impl CompressOptions {
             dict_size: 0,
             solid: true,
             password: None,
+            kdf_iterations: None,
+            encryption_key: None,
             auto_detect_incompressible: true, // Enable by default for smart mode
+            verify_crc_after: false,
+            store_empty_dirs: true,
+            method: CompressionMethod::Lzma2,
+            method_by_extension: HashMap::new(),
+            walk_progress: None,
+            special_files: SpecialFilePolicy::Skip,
+            recovery_percent: None,
+            time_budget: None,
+            codec_properties: Vec::new(),
+            path_mode: PathMode::AsGiven,
+            preserve_root_names: false,
+            store_all_times: false,
+            io_retry: RetryPolicy::default(),
+            if_exists: ExistsPolicy::Overwrite,
+            dedup: false,
+            recursive: true,
+            min_file_size: None,
+            max_file_size: None,
+            sort_order: SortOrder::None,
+            compress_header: false,
+            detect_hardlinks: false,
         })
     }
     
+    /// The dictionary size in bytes used for `total_bytes` of input at
+    /// `level`: `dict_size` if set, otherwise the level's default shrunk to
+    /// the input size (at least 4 KiB), as the encoder would
+    ///
+    /// [`SevenZip::create_archive`] passes this concrete value to the
+    /// encoder instead of `0` and reports it in
+    /// [`CompressReport::dict_size`], so output doesn't change when a later
+    /// library version picks a different default. Pin it by setting
+    /// `dict_size` to the returned value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use seven_zip::{CompressionLevel, CompressOptions};
+    ///
+    /// let opts = CompressOptions::default();
+    /// assert_eq!(opts.resolve_auto_dict(CompressionLevel::Normal, 1 << 30), 8 << 20);
+    /// assert_eq!(opts.resolve_auto_dict(CompressionLevel::Normal, 100_000), 100_000);
+    /// ```
+    pub fn resolve_auto_dict(&self, level: CompressionLevel, total_bytes: u64) -> u64 {
+        if self.dict_size > 0 {
+            return self.dict_size;
+        }
+        level.default_dict_size().min(total_bytes.max(MIN_AUTO_DICT_SIZE))
+    }
+    
     /// Enable auto-detection with method chaining
     pub fn with_auto_detect(mut self, enable: bool) -> Self {
         self.auto_detect_incompressible = enable;
@@ -228,50 +978,812 @@ impl CompressOptions {
         self.password = Some(password);
         self
     }
-}
+    
+    /// Set the key derivation rounds with method chaining
+    pub fn with_kdf_iterations(mut self, iterations: u32) -> Self {
+        self.kdf_iterations = Some(iterations);
+        self
+    }
+    
+    /// Encrypt with a raw AES-256 key with method chaining
+    pub fn with_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+    
+    /// Enable post-write CRC verification with method chaining
+    pub fn with_verify_crc_after(mut self, enable: bool) -> Self {
+        self.verify_crc_after = enable;
+        self
+    }
+    
+    /// Keep or drop empty directories with method chaining
+    pub fn with_store_empty_dirs(mut self, enable: bool) -> Self {
+        self.store_empty_dirs = enable;
+        self
+    }
+    
+    /// Set the default codec with method chaining
+    pub fn with_method(mut self, method: CompressionMethod) -> Self {
+        self.method = method;
+        self
+    }
+    
+    /// Use `method` for files with the given extension, with method chaining
+    pub fn with_method_for_extension(mut self, extension: &str, method: CompressionMethod) -> Self {
+        self.method_by_extension.insert(extension.to_string(), method);
+        self
+    }
+    
+    /// Report walk progress with method chaining
+    pub fn with_walk_progress(mut self, callback: impl FnMut(u64) + Send + 'static) -> Self {
+        self.walk_progress = Some(WalkProgress::new(callback));
+        self
+    }
+    
+    /// Set the special file policy with method chaining
+    pub fn with_special_files(mut self, policy: SpecialFilePolicy) -> Self {
+        self.special_files = policy;
+        self
+    }
+    
+    /// Add a recovery record with method chaining
+    pub fn with_recovery_percent(mut self, percent: u8) -> Self {
+        self.recovery_percent = Some(percent);
+        self
+    }
+    
+    /// Set a compression time budget with method chaining
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+    
+    /// Add an LZMA2 property such as `("fb", "273")` with method chaining
+    pub fn with_codec_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.codec_properties.push((key.into(), value.into()));
+        self
+    }
+    
+    /// Choose how entry names are derived from the input paths, with method chaining
+    pub fn with_path_mode(mut self, mode: PathMode) -> Self {
+        self.path_mode = mode;
+        self
+    }
+    
+    /// Keep each input directory under its own name, with method chaining
+    pub fn with_preserve_root_names(mut self, enable: bool) -> Self {
+        self.preserve_root_names = enable;
+        self
+    }
+    
+    /// Store creation and access times too, with method chaining
+    pub fn with_store_all_times(mut self, enable: bool) -> Self {
+        self.store_all_times = enable;
+        self
+    }
+    
+    /// Retry transient input read errors with method chaining
+    pub fn with_io_retry(mut self, policy: RetryPolicy) -> Self {
+        self.io_retry = policy;
+        self
+    }
+    
+    /// Choose what happens to an existing archive, with method chaining
+    pub fn with_if_exists(mut self, policy: ExistsPolicy) -> Self {
+        self.if_exists = policy;
+        self
+    }
+    
+    /// Store identical files once, with method chaining
+    pub fn with_dedup(mut self, enable: bool) -> Self {
+        self.dedup = enable;
+        self
+    }
 
-/// Streaming compression options for large files and split archives
-#[derive(Debug, Clone)]
-pub struct StreamOptions {
-    /// Number of threads to use (0 = auto-detect)
-    pub num_threads: usize,
-    /// Dictionary size in bytes (0 = auto)
-    pub dict_size: u64,
-    /// Create solid archive
-    pub solid: bool,
-    /// Optional password for encryption
-    pub password: Option<String>,
-    /// Split archive size in bytes (0 = no split, e.g., 4GB = 4_294_967_296)
-    pub split_size: u64,
-    /// Chunk size for streaming (0 = auto)
-    pub chunk_size: u64,
-    /// Temporary directory (None = system default)
-    pub temp_dir: Option<String>,
-    /// Delete temporary files on error
-    pub delete_temp_on_error: bool,
-}
+    /// Recurse into subdirectories or not with method chaining
+    pub fn with_recursive(mut self, enable: bool) -> Self {
+        self.recursive = enable;
+        self
+    }
 
-impl Default for StreamOptions {
-    fn default() -> Self {
-        Self {
-            num_threads: 0,
-            dict_size: 0,
-            solid: true,
-            password: None,
-            split_size: 0,
-            chunk_size: 0,
-            temp_dir: None,
-            delete_temp_on_error: true,
-        }
+    /// Skip files smaller than `bytes` with method chaining
+    pub fn with_min_file_size(mut self, bytes: u64) -> Self {
+        self.min_file_size = Some(bytes);
+        self
     }
-}
 
-/// Main 7z archive interface
-pub struct SevenZip {
-    _initialized: bool,
-}
+    /// Skip files larger than `bytes` with method chaining
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
 
-impl SevenZip {
+    /// Set the solid stream order with method chaining
+    pub fn with_sort_order(mut self, order: SortOrder) -> Self {
+        self.sort_order = order;
+        self
+    }
+
+    /// Compress the archive header with method chaining
+    pub fn with_compress_header(mut self, enable: bool) -> Self {
+        self.compress_header = enable;
+        self
+    }
+
+    /// Store hard-linked files once with method chaining
+    pub fn with_detect_hardlinks(mut self, enable: bool) -> Self {
+        self.detect_hardlinks = enable;
+        self
+    }
+
+    /// Whether a regular file of `size` bytes passes the size limits
+    fn keeps_file_size(&self, size: u64) -> bool {
+        self.min_file_size.is_none_or(|min| size >= min) && self.max_file_size.is_none_or(|max| size <= max)
+    }
+}
+
+/// Smallest dictionary [`CompressOptions::resolve_auto_dict`] picks for tiny inputs
+const MIN_AUTO_DICT_SIZE: u64 = 1 << 12;
+
+/// Summary of a completed archive creation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressReport {
+    /// Number of entries written, including directories
+    pub entries: u64,
+    /// Total uncompressed size of all entries in bytes
+    pub input_bytes: u64,
+    /// Size of the finished archive in bytes
+    pub output_bytes: u64,
+    /// Threads the LZMA2 encoder actually ran with
+    ///
+    /// The encoder caps the thread count by the input size, so small inputs
+    /// use fewer threads than requested. If the C library cannot report the
+    /// count, this is the requested `num_threads` (at least 1) instead.
+    pub threads_used: u32,
+    /// Files left out by [`CompressOptions::min_file_size`] or
+    /// [`CompressOptions::max_file_size`]
+    pub skipped_by_size: Vec<PathBuf>,
+    /// Estimated peak memory of the encoder in bytes
+    ///
+    /// Computed from the effective LZMA2 settings rather than measured: the
+    /// match finder and window of each block thread (about 11.5x the
+    /// dictionary, which shrinks to fit small inputs) plus the block buffers
+    /// of multithreaded LZMA2. The input files `create_archive` holds in
+    /// memory come on top. 0 if nothing went through LZMA2, e.g. with
+    /// [`CompressionLevel::Store`].
+    pub peak_memory_bytes: u64,
+    /// Dictionary size in bytes the LZMA2 encoder ran with, see
+    /// [`CompressOptions::resolve_auto_dict`]; 0 if nothing went through
+    /// LZMA2
+    pub dict_size: u64,
+}
+
+/// Result of [`SevenZip::compare`]; entry names are sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// Entries only the first archive has
+    pub only_in_a: Vec<String>,
+    /// Entries only the second archive has
+    pub only_in_b: Vec<String>,
+    /// Entries in both archives whose size, CRC or type differ
+    pub differing: Vec<String>,
+    /// Number of entries that match in both archives
+    pub identical: usize,
+}
+
+impl ArchiveDiff {
+    /// Both archives carry the same entries with the same content
+    pub fn is_identical(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty()
+    }
+}
+
+/// Kind of host an archive was created on, see [`SevenZip::archive_info`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HostOs {
+    /// Attributes are Windows `FILE_ATTRIBUTE_*` flags
+    Windows,
+    /// Attributes carry a Unix mode in the high 16 bits (flag `0x8000` set)
+    Unix,
+    /// Entries disagree, e.g. an archive updated from both kinds of host
+    Unknown,
+}
+
+impl HostOs {
+    /// `FILE_ATTRIBUTE_UNIX_EXTENSION`: the high 16 bits hold a Unix mode
+    const UNIX_EXTENSION: u32 = 0x8000;
+
+    /// Classify an archive by its entries' attributes; entries without
+    /// attributes (0) don't count, and `None` means no entry had any
+    pub(crate) fn from_attributes(attributes: impl IntoIterator<Item = u32>) -> Option<Self> {
+        let (mut unix, mut windows) = (false, false);
+        for attributes in attributes.into_iter().filter(|&a| a != 0) {
+            if attributes & Self::UNIX_EXTENSION != 0 {
+                unix = true;
+            } else {
+                windows = true;
+            }
+        }
+
+        match (unix, windows) {
+            (false, false) => None,
+            (true, false) => Some(HostOs::Unix),
+            (false, true) => Some(HostOs::Windows),
+            (true, true) => Some(HostOs::Unknown),
+        }
+    }
+}
+
+/// Archive-level summary from [`SevenZip::archive_info`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveInfo {
+    /// Number of entries (files and directories)
+    pub entries: u64,
+    /// Total uncompressed size of all entries in bytes
+    pub unpacked_size: u64,
+    /// Total size of the packed streams in bytes
+    pub packed_size: u64,
+    /// Number of folders (solid blocks)
+    pub folders: u64,
+    /// Host the entries were written on, or `None` if no entry stores
+    /// attributes
+    pub host_os: Option<HostOs>,
+    /// Whether the header is stored packed (`kEncodedHeader`), see
+    /// [`CompressOptions::compress_header`]
+    pub is_compressed_header: bool,
+}
+
+/// Cheap structural summary of an archive, from [`SevenZip::fingerprint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveFingerprint {
+    /// Number of entries, directories included
+    pub entry_count: u64,
+    /// Total uncompressed size of all entries in bytes
+    pub total_size: u64,
+    /// Distinct codec chains of the archive's folders, sorted, e.g. `["LZMA2"]`
+    pub codec_set: Vec<String>,
+    /// Whether any folder is encrypted
+    pub encrypted: bool,
+    /// SHA-256 of the stored header bytes
+    pub sha256_of_header: [u8; 32],
+}
+
+/// How an archive's data is encrypted, from [`SevenZip::encryption_info`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionInfo {
+    /// Number of SHA-256 rounds used to derive the key from the password,
+    /// i.e. `2^kdf_cycles_power` (1 when the key is not stretched at all)
+    pub kdf_iterations: u64,
+    /// Log2 of `kdf_iterations` as stored in the archive (7-Zip uses 19;
+    /// `0x3F` means no stretching)
+    pub kdf_cycles_power: u32,
+    /// Salt length in bytes (7-Zip writes none)
+    pub salt_size: usize,
+    /// IV length in bytes
+    pub iv_size: usize,
+}
+
+/// One folder (solid block) of an archive, from [`SevenZip::folder_layout`]
+///
+/// A folder is a run of packed bytes that decodes to the contents of its
+/// entries in order. Reading any one entry means decoding the folder from
+/// its start, so a reader seeking into a large archive only needs to fetch
+/// `packed_size` bytes at `packed_offset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderInfo {
+    /// Folder index within the archive
+    pub index: usize,
+    /// Absolute offset of the folder's packed data in the archive file
+    pub packed_offset: u64,
+    /// Size of the folder's packed data in bytes
+    pub packed_size: u64,
+    /// Entries whose data lives in this folder, in archive order
+    pub entry_names: Vec<String>,
+    /// Codec chain, e.g. `"LZMA2"` or `"BCJ LZMA2"`
+    pub codec: String,
+}
+
+/// Streaming compression options for large files and split archives
+///
+/// # Supported combinations
+///
+/// Some options only work on some paths. The create methods check this
+/// before touching any file and return
+/// [`Error::UnsupportedOptionCombination`] otherwise:
+///
+/// | Combination | Supported |
+/// |---|---|
+/// | `split_size` with `password` | No: split volumes are written unencrypted |
+/// | `password` with [`SevenZip::create_archive_true_streaming`] | No: it writes unencrypted data |
+/// | `split_size` with `solid: false` | No: split archives are one solid block |
+/// | `split_size` with [`SevenZip::create_archive_true_streaming`] | No: it writes a single file |
+/// | `split_size` with `on_volume_complete`, `volume_naming`, `fsync_on_finish` | Yes |
+///
+/// [`SevenZip::compress_to_writer`] and [`SevenZip::create_sfx`] reject
+/// [`CompressOptions::verify_crc_after`] and
+/// [`CompressOptions::recovery_percent`] the same way.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Number of threads to use (0 = auto-detect)
+    pub num_threads: usize,
+    /// Dictionary size in bytes (0 = auto)
+    pub dict_size: u64,
+    /// Create solid archive
+    pub solid: bool,
+    /// Optional password for encryption
+    pub password: Option<String>,
+    /// Split archive size in bytes (0 = no split, e.g., 4GB = 4_294_967_296)
+    pub split_size: u64,
+    /// Chunk size for streaming (0 = auto)
+    pub chunk_size: u64,
+    /// Temporary directory (None = system default)
+    pub temp_dir: Option<String>,
+    /// Delete temporary files on error
+    pub delete_temp_on_error: bool,
+    /// Shared flag that cancels the operation once set to `true`
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// How split volumes are named (default: [`VolumeNaming::Numeric`])
+    pub volume_naming: VolumeNaming,
+    /// `fsync` the archive (every volume, when split) and, on Unix, its
+    /// directory before returning, so a crash right afterwards can't lose it
+    pub fsync_on_finish: bool,
+    /// Called with each finished volume's path and size, e.g. to upload and
+    /// delete it so only about two volumes are ever on disk; an `Err` aborts
+    /// the operation and is returned. Volumes from the second to the
+    /// next-to-last are handed over as soon as the following one starts. The
+    /// first volume's start header points at the archive header written at
+    /// the very end, so it comes last, just before the final volume. An
+    /// archive that is not split is reported once, after it is complete.
+    pub on_volume_complete: Option<VolumeHook>,
+    /// Creates the scratch files of
+    /// [`SevenZip::create_archive_true_streaming`] in place of `temp_dir`,
+    /// e.g. memfd-backed or on an encrypted volume. The file is reopened by
+    /// its path while the returned handle is kept open, so a path like
+    /// `/proc/self/fd/N` works too. Each path is removed when the operation
+    /// ends.
+    pub temp_file_factory: Option<TempFileFactory>,
+    /// Keep explicit entries for directories that contain no files
+    /// (default: `true`)
+    pub store_empty_dirs: bool,
+}
+
+impl StreamOptions {
+    /// Hand each finished volume to `hook` with method chaining, see
+    /// [`on_volume_complete`](Self::on_volume_complete)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, StreamOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut opts = StreamOptions::default().with_on_volume_complete(|volume, size| {
+    ///     println!("uploading {} ({} bytes)", volume.display(), size);
+    ///     std::fs::remove_file(volume)?;
+    ///     Ok(())
+    /// });
+    /// opts.split_size = 1 << 30;
+    /// sz.create_archive_streaming("backup.7z", &["data"], CompressionLevel::Normal, Some(&opts), None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn with_on_volume_complete(
+        mut self,
+        hook: impl FnMut(&Path, u64) -> Result<()> + Send + 'static,
+    ) -> Self {
+        self.on_volume_complete = Some(VolumeHook::new(hook));
+        self
+    }
+
+    /// Cancel the operation through `token` with method chaining
+    pub fn with_cancel_token(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.flag());
+        self
+    }
+
+    /// Keep or drop empty directories with method chaining
+    pub fn with_store_empty_dirs(mut self, enable: bool) -> Self {
+        self.store_empty_dirs = enable;
+        self
+    }
+
+    /// Create scratch files through `factory` with method chaining, see
+    /// [`temp_file_factory`](Self::temp_file_factory)
+    pub fn with_temp_file_factory(
+        mut self,
+        factory: impl FnMut() -> std::io::Result<(PathBuf, std::fs::File)> + Send + 'static,
+    ) -> Self {
+        self.temp_file_factory = Some(TempFileFactory::new(factory));
+        self
+    }
+
+    /// Reject the combinations listed under "Supported combinations"
+    ///
+    /// `splits` is whether the calling writer can split at all.
+    pub(crate) fn check_combination(&self, splits: bool) -> Result<()> {
+        if self.split_size == 0 {
+            return Ok(());
+        }
+        let unsupported = |detail: &str| Err(Error::UnsupportedOptionCombination { detail: detail.to_string() });
+        if !splits {
+            return unsupported("split_size is not supported by create_archive_true_streaming; use create_archive_streaming");
+        }
+        if self.password.is_some() {
+            return unsupported("split_size with password: split volumes are written without encryption");
+        }
+        if !self.solid {
+            return unsupported("split_size with solid = false: split archives are always written as one solid block");
+        }
+        Ok(())
+    }
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            num_threads: 0,
+            dict_size: 0,
+            solid: true,
+            password: None,
+            split_size: 0,
+            chunk_size: 0,
+            temp_dir: None,
+            delete_temp_on_error: true,
+            cancel: None,
+            volume_naming: VolumeNaming::Numeric,
+            fsync_on_finish: false,
+            on_volume_complete: None,
+            temp_file_factory: None,
+            store_empty_dirs: true,
+        }
+    }
+}
+
+/// Extraction options
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Optional password for encrypted archives
+    pub password: Option<String>,
+    /// Raw AES-256 key for archives written with
+    /// [`CompressOptions::encryption_key`], used instead of `password`
+    /// (setting both fails with [`Error::UnsupportedOptionCombination`])
+    pub encryption_key: Option<[u8; 32]>,
+    /// Restore each entry's modification time from the archive
+    pub restore_mtime: bool,
+    /// Restore access times, and on Windows creation times, as well as
+    /// modification times, for archives written with
+    /// [`CompressOptions::store_all_times`]; overrides `restore_mtime`
+    pub restore_all_times: bool,
+    /// Shared flag that cancels the extraction once set to `true`
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Extract everything into this subdirectory of the output directory,
+    /// created if absent ("extract to new folder"). Must be a relative path
+    /// that stays inside the output directory.
+    pub into_named_subdir: Option<String>,
+    /// Make existing read-only files writable so they can be overwritten;
+    /// without this, a read-only file in the way fails with [`Error::Extract`]
+    pub force_overwrite_readonly: bool,
+    /// Create the output directory if it is missing (default: `true`); turn
+    /// off when the caller guarantees it exists, making a missing directory
+    /// an [`Error::OutputNotWritable`]
+    pub create_output_dir: bool,
+    /// Keep going when an entry fails to extract (e.g. bad data or CRC),
+    /// writing every other entry, then report the failed ones in
+    /// [`Error::PartialExtraction`]. Entries are extracted one by one on the
+    /// Rust side in this mode; the progress callback is called after each
+    /// entry, failed or not.
+    pub continue_on_error: bool,
+    /// Keep every file when names differ only in case (`README` and
+    /// `readme`) and the output directory is on a case-insensitive
+    /// filesystem, which is probed before extracting. The first such entry
+    /// keeps its name and the others get ` (1)`, ` (2)`, ... before the
+    /// extension; without this, the last one overwrites the others. No
+    /// effect on case-sensitive filesystems.
+    pub case_insensitive_safe: bool,
+    /// On Windows, rename entries Windows can't create instead of failing
+    /// with [`Error::ReservedName`]: device names get a `_` after the base
+    /// name (`CON` → `CON_`, `aux.txt` → `aux_.txt`) and trailing dots and
+    /// spaces become a single `_`. Renamed entries are extracted one by one
+    /// on the Rust side, still stopping at the first failure unless
+    /// [`continue_on_error`](Self::continue_on_error) is set. No effect on
+    /// other platforms, where these names are valid.
+    pub sanitize_names: bool,
+    /// Receives each entry renamed by `sanitize_names` before it is written
+    pub on_rename: Option<RenameHook>,
+    /// Recreate hard link entries (see [`ArchiveEntry::is_hardlink`]) with
+    /// [`std::fs::hard_link`]; otherwise each gets its own copy of the
+    /// target's content, as with every other extraction method
+    pub restore_hardlinks: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            password: None,
+            encryption_key: None,
+            restore_mtime: true,
+            restore_all_times: false,
+            cancel: None,
+            into_named_subdir: None,
+            force_overwrite_readonly: false,
+            create_output_dir: true,
+            continue_on_error: false,
+            case_insensitive_safe: false,
+            sanitize_names: false,
+            on_rename: None,
+            restore_hardlinks: false,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Set password with method chaining
+    pub fn with_password(mut self, password: String) -> Self {
+        self.password = Some(password);
+        self
+    }
+    
+    /// Decrypt with a raw AES-256 key with method chaining
+    pub fn with_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+    
+    /// Enable or disable modification time restoration with method chaining
+    pub fn with_restore_mtime(mut self, enable: bool) -> Self {
+        self.restore_mtime = enable;
+        self
+    }
+    
+    /// Restore every stored timestamp with method chaining
+    pub fn with_restore_all_times(mut self, enable: bool) -> Self {
+        self.restore_all_times = enable;
+        self
+    }
+    
+    /// Set a cancellation flag with method chaining
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+    
+    /// Cancel the extraction through `token` with method chaining
+    pub fn with_cancel_token(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.flag());
+        self
+    }
+    
+    /// Nest the extracted content under `name` with method chaining
+    pub fn with_named_subdir(mut self, name: impl Into<String>) -> Self {
+        self.into_named_subdir = Some(name.into());
+        self
+    }
+    
+    /// Nest the extracted content under a folder named after the archive,
+    /// e.g. `project` for `project.7z`, `backup` for `backup.tar.7z.001` or
+    /// `v1.2.3` for `v1.2.3.7z`
+    pub fn with_subdir_from_archive(self, archive_path: impl AsRef<Path>) -> Self {
+        let file_name = archive_path
+            .as_ref()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = archive_stem(&file_name);
+        self.with_named_subdir(if stem.is_empty() { "archive" } else { stem })
+    }
+    
+    /// Overwrite read-only files with method chaining
+    pub fn with_force_overwrite_readonly(mut self, enable: bool) -> Self {
+        self.force_overwrite_readonly = enable;
+        self
+    }
+    
+    /// Choose whether a missing output directory is created, with method chaining
+    pub fn with_create_output_dir(mut self, create: bool) -> Self {
+        self.create_output_dir = create;
+        self
+    }
+    
+    /// Extract past individual entry failures with method chaining
+    pub fn with_continue_on_error(mut self, enable: bool) -> Self {
+        self.continue_on_error = enable;
+        self
+    }
+    
+    /// Rename entries that collide on case-insensitive filesystems with
+    /// method chaining
+    pub fn with_case_insensitive_safe(mut self, enable: bool) -> Self {
+        self.case_insensitive_safe = enable;
+        self
+    }
+    
+    /// Rename entries Windows reserves with method chaining, see
+    /// [`sanitize_names`](Self::sanitize_names)
+    pub fn with_sanitize_names(mut self, enable: bool) -> Self {
+        self.sanitize_names = enable;
+        self
+    }
+    
+    /// Recreate hard links with method chaining
+    pub fn with_restore_hardlinks(mut self, enable: bool) -> Self {
+        self.restore_hardlinks = enable;
+        self
+    }
+    
+    /// Report renamed entries to `hook` with method chaining
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, ExtractOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let opts = ExtractOptions::default()
+    ///     .with_sanitize_names(true)
+    ///     .with_on_rename(|name, path| println!("{} extracted as {}", name, path.display()));
+    /// sz.extract_with_options("from-linux.7z", "output", &opts, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn with_on_rename(mut self, hook: impl FnMut(&str, &Path) + Send + 'static) -> Self {
+        self.on_rename = Some(RenameHook::new(hook));
+        self
+    }
+}
+
+/// What [`SevenZip::extract_manifest`] did with one entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExtractStatus {
+    /// Written to a path that did not exist before
+    Created,
+    /// Replaced an existing file
+    Overwritten,
+    /// Not written because its name would land outside the output directory,
+    /// or because its data failed to decode (then `crc_ok` is `false`)
+    Skipped,
+}
+
+/// One entry of an extraction manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedFile {
+    /// Entry name as stored in the archive
+    pub archive_name: String,
+    /// Where the entry was written (or would have been, if skipped)
+    pub disk_path: PathBuf,
+    /// Bytes written to disk; 0 for directories and skipped entries
+    pub bytes_written: u64,
+    /// The entry's data matches the CRC stored in the archive (`true` when
+    /// the archive stores no CRC for the entry); `false` entries are not
+    /// written
+    pub crc_ok: bool,
+    /// Whether the file was created, overwritten or skipped
+    pub status: ExtractStatus,
+}
+
+/// What extracting one entry would do, see [`SevenZip::extract_dry_run`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChangeAction {
+    /// Nothing exists at the target path yet
+    New,
+    /// A different file would be replaced
+    Overwrite,
+    /// The existing file already has the entry's content, or the directory
+    /// already exists
+    Identical,
+    /// Not written: the name would land outside the output directory, or a
+    /// directory is in the way of a file (or the other way round)
+    Skip,
+}
+
+/// One entry of [`SevenZip::extract_dry_run`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangePreview {
+    /// Entry name as stored in the archive
+    pub name: String,
+    /// What extraction would do with it
+    pub action: ChangeAction,
+}
+
+/// A problem [`SevenZip::verify_extractable`] found with one entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractWarning {
+    /// A path component, or the whole output path, exceeds what common
+    /// filesystems accept
+    NameTooLong {
+        /// Entry name as stored in the archive
+        name: String,
+        /// Length in bytes of the offending component or path
+        length: usize,
+        /// The limit it exceeds
+        limit: usize,
+    },
+    /// The name contains a character Windows filesystems reject
+    /// (`< > : " | ? *` or a control character)
+    InvalidChar {
+        /// Entry name as stored in the archive
+        name: String,
+        /// The first offending character
+        ch: char,
+    },
+    /// A file already exists where the entry would be written
+    WouldOverwrite {
+        /// Entry name as stored in the archive
+        name: String,
+        /// The existing file
+        path: PathBuf,
+    },
+    /// The name is absolute or uses `..` to leave the output directory
+    PathTraversal {
+        /// Entry name as stored in the archive
+        name: String,
+    },
+    /// A path component is a device name Windows reserves (`CON`,
+    /// `aux.txt`) or ends in a dot or space, see
+    /// [`ExtractOptions::sanitize_names`]
+    ReservedName {
+        /// Entry name as stored in the archive
+        name: String,
+    },
+    /// Files whose names differ only in case, so they overwrite each other
+    /// on case-insensitive filesystems (see
+    /// [`ExtractOptions::case_insensitive_safe`])
+    CaseCollision {
+        /// Entry names as stored in the archive, in archive order
+        names: Vec<String>,
+    },
+}
+
+/// Options for single-file compression and decompression
+#[derive(Debug, Clone, Default)]
+pub struct SingleFileOptions {
+    /// Read/write buffer size in bytes (0 = library default, 64 KB)
+    ///
+    /// Larger buffers mean fewer, bigger I/O calls, which is much faster on
+    /// network filesystems. Output is identical regardless of buffer size.
+    pub buffer_size: usize,
+}
+
+impl SingleFileOptions {
+    /// Set buffer size with method chaining
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+}
+
+/// Library-level settings for [`SevenZip::new_with_config`]
+#[derive(Debug, Clone)]
+pub struct LibConfig {
+    /// Call `sevenzip_init` on construction and `sevenzip_cleanup` on drop
+    ///
+    /// Set to `false` when the C library is shared with a host application
+    /// that initializes and tears it down itself; the instance then assumes
+    /// the library is already initialized and leaves global state alone.
+    pub manage_global_init: bool,
+}
+
+impl Default for LibConfig {
+    fn default() -> Self {
+        Self {
+            manage_global_init: true,
+        }
+    }
+}
+
+impl LibConfig {
+    /// Enable or disable global init/cleanup with method chaining
+    pub fn with_manage_global_init(mut self, enable: bool) -> Self {
+        self.manage_global_init = enable;
+        self
+    }
+}
+
+/// Main 7z archive interface
+pub struct SevenZip {
+    /// Whether this instance called `sevenzip_init` and must clean up
+    manages_global_init: bool,
+}
+
+impl SevenZip {
     /// Create a new SevenZip instance
     ///
     /// Initializes the underlying 7z library.
@@ -285,13 +1797,37 @@ impl SevenZip {
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
     pub fn new() -> Result<Self> {
-        unsafe {
-            let result = ffi::sevenzip_init();
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+        Self::new_with_config(&LibConfig::default())
+    }
+
+    /// Create a new SevenZip instance with explicit library settings
+    ///
+    /// With [`LibConfig::manage_global_init`] disabled, neither
+    /// `sevenzip_init` nor `sevenzip_cleanup` is called, so the crate can
+    /// share the C library with a host that manages its lifetime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{LibConfig, SevenZip};
+    ///
+    /// // The host application has already called sevenzip_init()
+    /// let config = LibConfig::default().with_manage_global_init(false);
+    /// let sz = SevenZip::new_with_config(&config)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn new_with_config(config: &LibConfig) -> Result<Self> {
+        if config.manage_global_init {
+            unsafe {
+                let result = ffi::sevenzip_init();
+                if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                    return Err(Error::from_code(result));
+                }
             }
         }
-        Ok(Self { _initialized: true })
+        Ok(Self {
+            manages_global_init: config.manage_global_init,
+        })
     }
 
     /// Extract a 7z archive
@@ -346,30 +1882,162 @@ impl SevenZip {
         password: Option<&str>,
         progress: Option<ProgressCallback>,
     ) -> Result<()> {
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        let output_dir_c = path_to_cstring(output_dir.as_ref())?;
-        let password_c = password.map(|p| CString::new(p)).transpose()?;
-
-        let (callback, user_data) = if let Some(cb) = progress {
-            // Convert Box<dyn FnMut> into raw pointer that can cross FFI boundary
-            let boxed = Box::new(cb);
-            let raw = Box::into_raw(boxed);
-            (
-                Some(progress_callback_wrapper as unsafe extern "C" fn(u64, u64, *mut std::os::raw::c_void)),
-                raw as *mut std::os::raw::c_void,
-            )
-        } else {
-            (None, ptr::null_mut())
+        let options = ExtractOptions {
+            password: password.map(str::to_owned),
+            ..Default::default()
         };
+        self.extract_with_options(archive_path, output_dir, &options, progress)
+    }
 
-        unsafe {
-            let result = ffi::sevenzip_extract(
-                archive_path_c.as_ptr(),
-                output_dir_c.as_ptr(),
-                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                callback,
-                user_data,
-            );
+    /// Extract an archive read from a non-seekable stream, e.g. a pipe
+    ///
+    /// 7z needs random access (the header sits at the end), and the C reader
+    /// only opens files by path, so the whole stream is first spooled to a
+    /// temporary file in [`std::env::temp_dir`], whatever its size. That file
+    /// is removed again when this returns, also on error. Make sure the temp
+    /// directory has room for the full archive. The counterpart of
+    /// [`compress_to_writer`](Self::compress_to_writer).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// // cat archive.7z | mytool extract
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_from_reader(std::io::stdin().lock(), "output", None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_from_reader<R: std::io::Read>(
+        &self,
+        mut reader: R,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let spool_id = SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let staging = StagingDir::new(&std::env::temp_dir().join(format!("7z-spool-{}", spool_id)))?;
+        let spooled = staging.path().join("archive.7z");
+
+        let mut file = std::fs::File::create(&spooled)?;
+        std::io::copy(&mut reader, &mut file)?;
+        drop(file);
+
+        self.extract_with_password(&spooled, output_dir, password, None)
+    }
+
+    /// Extract an archive piped into standard input, see
+    /// [`extract_from_reader`](Self::extract_from_reader)
+    pub fn extract_stdin_archive(&self, output_dir: impl AsRef<Path>, password: Option<&str>) -> Result<()> {
+        self.extract_from_reader(std::io::stdin().lock(), output_dir, password)
+    }
+
+    /// Extract a 7z archive with explicit extraction options
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Path to the archive file
+    /// * `output_dir` - Directory to extract to
+    /// * `options` - Extraction options
+    /// * `progress` - Optional progress callback
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, ExtractOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let opts = ExtractOptions::default().with_restore_mtime(false);
+    /// sz.extract_with_options("archive.7z", "output", &opts, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_with_options(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        options: &ExtractOptions,
+        progress: Option<ProgressCallback>,
+    ) -> Result<()> {
+        let password = options.password.as_deref();
+        if password.is_some() && options.encryption_key.is_some() {
+            return Err(Error::UnsupportedOptionCombination {
+                detail: "password with encryption_key: give the one the archive was encrypted with".to_string(),
+            });
+        }
+        check_archive_file(archive_path.as_ref())?;
+        check_output_dir(output_dir.as_ref(), options.create_output_dir)?;
+        let output_dir = match &options.into_named_subdir {
+            Some(name) => {
+                let subdir = Path::new(name);
+                let contained = subdir
+                    .components()
+                    .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+                if name.is_empty() || !contained {
+                    return Err(Error::InvalidParameter(format!(
+                        "Subdirectory must be a relative path inside the output directory: {}",
+                        name
+                    )));
+                }
+                let nested = output_dir.as_ref().join(subdir);
+                std::fs::create_dir_all(&nested)?;
+                nested
+            }
+            None => output_dir.as_ref().to_path_buf(),
+        };
+        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
+        let output_dir_c = path_to_cstring(&output_dir)?;
+        let password_c = password.map(|p| CString::new(p)).transpose()?;
+
+        check_cancelled(options.cancel.as_ref())?;
+        let (entries, renamed) = sanitize_reserved_names(self.list(archive_path.as_ref(), password)?, &output_dir, options)?;
+        prepare_readonly_targets(&output_dir, &entries, options.force_overwrite_readonly)?;
+        let collisions = if options.case_insensitive_safe && is_case_insensitive_dir(&output_dir)? {
+            case_collisions(&entries)
+        } else {
+            Vec::new()
+        };
+        if !options.continue_on_error {
+            if let Some(entry) = entries.iter().find(|e| contained_output_path(&output_dir, &e.name).is_none()) {
+                return Err(Error::InvalidArchive(format!("Entry path leaves the output directory: {}", entry.name)));
+            }
+        }
+        if options.continue_on_error || renamed {
+            let result = self.extract_continuing(archive_path.as_ref(), &output_dir, options, &entries, progress);
+            if !collisions.is_empty() {
+                separate_case_collisions(archive_path.as_ref(), &output_dir, options, &entries, &collisions)?;
+            }
+            return result;
+        }
+        let progress = cancellable_progress(progress, options.cancel.as_ref());
+
+        let (callback, user_data) = if let Some(cb) = progress {
+            // Convert Box<dyn FnMut> into raw pointer that can cross FFI boundary
+            let boxed = Box::new(cb);
+            let raw = Box::into_raw(boxed);
+            (
+                Some(progress_callback_wrapper as unsafe extern "C" fn(u64, u64, *mut std::os::raw::c_void)),
+                raw as *mut std::os::raw::c_void,
+            )
+        } else {
+            (None, ptr::null_mut())
+        };
+
+        unsafe {
+            let result = match &options.encryption_key {
+                Some(key) => ffi::sevenzip_extract_with_key(
+                    archive_path_c.as_ptr(),
+                    output_dir_c.as_ptr(),
+                    key.as_ptr(),
+                    callback,
+                    user_data,
+                ),
+                None => ffi::sevenzip_extract(
+                    archive_path_c.as_ptr(),
+                    output_dir_c.as_ptr(),
+                    password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                    callback,
+                    user_data,
+                ),
+            };
 
             // Clean up the callback if it was allocated
             if !user_data.is_null() {
@@ -382,9 +2050,378 @@ impl SevenZip {
             }
         }
 
+        link_hardlinks(archive_path.as_ref(), &output_dir, options, &entries)?;
+        if options.restore_all_times {
+            let full = self.list_full(archive_path.as_ref(), password)?;
+            restore_all_times(&output_dir, &full)?;
+        } else if options.restore_mtime {
+            restore_mtimes(&output_dir, &entries)?;
+        }
+        if !collisions.is_empty() {
+            separate_case_collisions(archive_path.as_ref(), &output_dir, options, &entries, &collisions)?;
+        }
+
         Ok(())
     }
 
+    /// Entry-by-entry body of [`extract_with_options`](Self::extract_with_options)
+    /// for [`ExtractOptions::continue_on_error`] and for entries renamed by
+    /// [`ExtractOptions::sanitize_names`], calling `progress` after each
+    /// entry; without `continue_on_error`, the first failure ends the
+    /// extraction
+    fn extract_continuing(
+        &self,
+        archive_path: &Path,
+        output_dir: &Path,
+        options: &ExtractOptions,
+        entries: &[ArchiveEntry],
+        mut progress: Option<ProgressCallback>,
+    ) -> Result<()> {
+        let password = options.password.as_deref();
+        let mut archive = ArchiveHandle::open_for_extract(archive_path, options)?;
+        let mut failures = Vec::new();
+        let total = entries.len() as u64;
+
+        for (index, entry) in entries.iter().enumerate() {
+            check_cancelled(options.cancel.as_ref())?;
+            let relative = normalize_entry_name(&entry.name);
+            let disk_path = output_dir.join(&relative);
+
+            let result = if !is_contained_entry_path(&relative) {
+                Err(Error::InvalidArchive(format!("Entry path leaves the output directory: {}", entry.name)))
+            } else if entry.is_directory {
+                std::fs::create_dir_all(&disk_path).map_err(Error::from)
+            } else {
+                write_entry(&mut archive, index as u32, entry, &disk_path)
+            };
+            match result {
+                Err(e) if !options.continue_on_error => return Err(e),
+                Err(e) => failures.push((entry.name.clone(), e)),
+                Ok(()) => {}
+            }
+            if let Some(cb) = progress.as_mut() {
+                cb(index as u64 + 1, total);
+            }
+        }
+
+        let failed: HashSet<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        if failures.is_empty() {
+            link_hardlinks(archive_path, output_dir, options, entries)?;
+        }
+        if options.restore_all_times {
+            let mut full = self.list_full(archive_path, password)?;
+            // Entries may have been renamed
+            for (full_entry, entry) in full.iter_mut().zip(entries) {
+                full_entry.entry.name = entry.name.clone();
+            }
+            full.retain(|f| !failed.contains(f.entry.name.as_str()));
+            restore_all_times(output_dir, &full)?;
+        } else if options.restore_mtime {
+            let written: Vec<ArchiveEntry> =
+                entries.iter().filter(|e| !failed.contains(e.name.as_str())).cloned().collect();
+            restore_mtimes(output_dir, &written)?;
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PartialExtraction { failures })
+        }
+    }
+
+    /// Extract on a background thread, exposing progress as atomic counters
+    ///
+    /// A pull-model alternative to a progress closure: poll the returned
+    /// [`ProgressHandle`] from a render loop and join the thread for the
+    /// result. The thread uses its own [`SevenZip`] with the same
+    /// global-init setting as `self`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, ExtractOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let (progress, worker) = sz.extract_with_counter("archive.7z", "output", &ExtractOptions::default());
+    /// while !worker.is_finished() {
+    ///     println!("{}/{} entries", progress.processed(), progress.total());
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    /// }
+    /// worker.join().expect("extraction thread panicked")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_with_counter(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        options: &ExtractOptions,
+    ) -> (ProgressHandle, std::thread::JoinHandle<Result<()>>) {
+        let archive_path = archive_path.as_ref().to_path_buf();
+        let output_dir = output_dir.as_ref().to_path_buf();
+        let options = options.clone();
+        let config = LibConfig { manage_global_init: self.manages_global_init };
+
+        let handle = ProgressHandle::default();
+        let counters = handle.clone();
+        let worker = std::thread::spawn(move || {
+            let sz = SevenZip::new_with_config(&config)?;
+            sz.extract_with_options(
+                &archive_path,
+                &output_dir,
+                &options,
+                Some(Box::new(move |processed, total| {
+                    counters.total.store(total, Ordering::Relaxed);
+                    counters.processed.store(processed, Ordering::Relaxed);
+                })),
+            )
+        });
+
+        (handle, worker)
+    }
+
+    /// Extract an archive and return a manifest of everything written
+    ///
+    /// Entries are extracted one by one, so each manifest line records the
+    /// final on-disk path, the byte count and a CRC check of exactly what
+    /// was written, e.g. for chain-of-custody logs. Entries whose names
+    /// would escape `output_dir` (absolute paths or `..`) are not written
+    /// and appear as [`ExtractStatus::Skipped`]. So do entries whose data is
+    /// corrupt, with `crc_ok: false`; the rest of the archive is still
+    /// extracted.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for file in sz.extract_manifest("evidence.7z", "case-42", None)? {
+    ///     println!("{:?} {} -> {} ({} bytes, crc ok: {})",
+    ///         file.status, file.archive_name, file.disk_path.display(), file.bytes_written, file.crc_ok);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_manifest(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<Vec<ExtractedFile>> {
+        let output_dir = output_dir.as_ref();
+        let mut archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let entries = archive.entries()?;
+        std::fs::create_dir_all(output_dir)?;
+
+        let mut manifest = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            let relative = normalize_entry_name(&entry.name);
+            let disk_path = output_dir.join(&relative);
+            let contained = is_contained_entry_path(&relative);
+            let existed = disk_path.exists();
+
+            let mut record = ExtractedFile {
+                archive_name: entry.name.clone(),
+                disk_path,
+                bytes_written: 0,
+                crc_ok: true,
+                status: if existed { ExtractStatus::Overwritten } else { ExtractStatus::Created },
+            };
+            if !contained {
+                record.status = ExtractStatus::Skipped;
+            } else if entry.is_directory {
+                std::fs::create_dir_all(&record.disk_path)?;
+            } else {
+                let data = match archive.read(index as u32) {
+                    Ok(data) => data,
+                    Err(Error::Extract(_)) => {
+                        record.status = ExtractStatus::Skipped;
+                        record.crc_ok = false;
+                        manifest.push(record);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                if let Some(parent) = record.disk_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&record.disk_path, data)?;
+                record.bytes_written = data.len() as u64;
+                // A hard link's stored CRC covers its tag; see `write_entry`
+                record.crc_ok = entry.is_hardlink() || entry.crc32.is_none_or(|crc| crc32fast::hash(data) == crc);
+            }
+            manifest.push(record);
+        }
+
+        let written: Vec<ArchiveEntry> = entries
+            .into_iter()
+            .zip(&manifest)
+            .filter(|(_, record)| record.status != ExtractStatus::Skipped)
+            .map(|(entry, _)| entry)
+            .collect();
+        restore_mtimes(output_dir, &written)?;
+
+        Ok(manifest)
+    }
+
+    /// Preview what extracting an archive into `output_dir` would change,
+    /// without writing anything
+    ///
+    /// Like `rsync --dry-run`: each entry is classified against what is on
+    /// disk. An existing file counts as [`ChangeAction::Identical`] if its
+    /// size and CRC32 match the entry's; without a stored CRC only empty
+    /// files can match. Only headers are read from the archive, but each
+    /// same-sized existing file is hashed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, ChangeAction};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for change in sz.extract_dry_run("update.7z", "/opt/app", None)? {
+    ///     if change.action != ChangeAction::Identical {
+    ///         println!("{:?} {}", change.action, change.name);
+    ///     }
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_dry_run(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<Vec<ChangePreview>> {
+        let output_dir = output_dir.as_ref();
+        let entries = self.list(archive_path, password)?;
+
+        let mut preview = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let relative = normalize_entry_name(&entry.name);
+            let action = if !is_contained_entry_path(&relative) {
+                ChangeAction::Skip
+            } else {
+                let disk_path = output_dir.join(&relative);
+                match std::fs::metadata(&disk_path) {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => ChangeAction::New,
+                    Err(e) => return Err(e.into()),
+                    Ok(meta) if meta.is_dir() != entry.is_directory => ChangeAction::Skip,
+                    Ok(_) if entry.is_directory => ChangeAction::Identical,
+                    Ok(meta) if meta.len() != entry.size => ChangeAction::Overwrite,
+                    Ok(_) => {
+                        let same = match entry.crc32 {
+                            Some(crc) => file_crc32(&disk_path)? == crc,
+                            None => entry.size == 0,
+                        };
+                        if same { ChangeAction::Identical } else { ChangeAction::Overwrite }
+                    }
+                }
+            };
+            preview.push(ChangePreview { name: entry.name, action });
+        }
+
+        Ok(preview)
+    }
+
+    /// Check whether extracting an archive into `target_dir` would run into
+    /// trouble, without writing anything
+    ///
+    /// A pre-flight for untrusted archives: returns a warning for every entry
+    /// whose name escapes the target directory, is too long or contains
+    /// characters Windows can't store, and for every file that would
+    /// overwrite an existing one. Character and case checks apply on every
+    /// host, since the extracted tree may later be copied to another system.
+    /// An empty list means no problems were found; free space is not checked.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let warnings = sz.verify_extractable("upload.7z", "incoming", None)?;
+    /// if warnings.is_empty() {
+    ///     sz.extract("upload.7z", "incoming")?;
+    /// } else {
+    ///     eprintln!("refusing to extract: {:?}", warnings);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn verify_extractable(
+        &self,
+        archive_path: impl AsRef<Path>,
+        target_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<Vec<ExtractWarning>> {
+        let target_dir = target_dir.as_ref();
+        let entries = self.list(archive_path.as_ref(), password)?;
+        let mut warnings: Vec<ExtractWarning> = entries
+            .iter()
+            .flat_map(|entry| extract_warnings(target_dir, entry))
+            .collect();
+        warnings.extend(case_collisions(&entries).into_iter().map(|group| ExtractWarning::CaseCollision {
+            names: group.iter().map(|&i| entries[i].name.clone()).collect(),
+        }));
+        Ok(warnings)
+    }
+
+    /// Extract only the entries modified after `since`
+    ///
+    /// For incremental restores: entries whose stored modification time is
+    /// strictly later than `since` are written, everything else is left
+    /// alone. Archive times have whole-second resolution. Entries without a
+    /// stored time, and entries whose path would leave `output_dir`, are never
+    /// extracted. Returns the number of entries written.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let last_backup = SystemTime::now() - Duration::from_secs(24 * 3600);
+    /// let restored = sz.extract_newer_than("backup.7z", "restore", last_backup, None)?;
+    /// println!("{} entries changed since the last backup", restored);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_newer_than(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        since: std::time::SystemTime,
+        password: Option<&str>,
+    ) -> Result<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        std::fs::create_dir_all(output_dir)?;
+
+        let mut written = Vec::new();
+        for (index, entry) in archive.entries()?.into_iter().enumerate() {
+            let relative = normalize_entry_name(&entry.name);
+            if entry.modified_time == 0
+                || entry.modified_system_time() <= since
+                || !is_contained_entry_path(&relative)
+            {
+                continue;
+            }
+
+            let disk_path = output_dir.join(&relative);
+            if entry.is_directory {
+                std::fs::create_dir_all(&disk_path)?;
+            } else {
+                let data = archive.read(index as u32)?;
+                if let Some(parent) = disk_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&disk_path, data)?;
+            }
+            written.push(entry);
+        }
+
+        restore_mtimes(output_dir, &written)?;
+        Ok(written.len())
+    }
+
     /// Extract specific files from an archive
     ///
     /// # Arguments
@@ -470,6 +2507,7 @@ impl SevenZip {
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
     pub fn list(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<Vec<ArchiveEntry>> {
+        check_archive_file(archive_path.as_ref())?;
         let archive_path_c = path_to_cstring(archive_path.as_ref())?;
         let password_c = password.map(|p| CString::new(p)).transpose()?;
 
@@ -483,402 +2521,470 @@ impl SevenZip {
             );
 
             if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+                return Err(open_error(archive_path.as_ref(), result));
             }
 
-            if list_ptr.is_null() {
-                return Ok(Vec::new());
-            }
-
-            let list = &*list_ptr;
-            let mut entries = Vec::with_capacity(list.count);
-
-            for i in 0..list.count {
-                let entry = &*list.entries.add(i);
-                let name = CStr::from_ptr(entry.name).to_string_lossy().into_owned();
-
-                entries.push(ArchiveEntry {
-                    name,
-                    size: entry.size,
-                    packed_size: entry.packed_size,
-                    modified_time: entry.modified_time,
-                    attributes: entry.attributes,
-                    is_directory: entry.is_directory != 0,
-                });
-            }
-
-            ffi::sevenzip_free_list(list_ptr);
-            Ok(entries)
+            Ok(take_entry_list(list_ptr))
         }
     }
 
-    /// Create a standard 7z archive
+    /// List every entry with all of its metadata, raw and decoded
     ///
-    /// **WARNING**: This function loads entire files into memory before compression.
-    /// For files larger than ~1GB, use [`create_archive_streaming`](Self::create_archive_streaming)
-    /// instead to avoid memory exhaustion.
+    /// For forensic reports that must show both what the archive stores and
+    /// what it means: the raw attribute word next to its Windows flags and
+    /// Unix mode, and the exact FILETIME next to a `SystemTime`.
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `archive_path` - Output archive path
-    /// * `input_paths` - Files/directories to compress
-    /// * `level` - Compression level
-    /// * `options` - Optional compression options
+    /// ```no_run
+    /// use seven_zip::SevenZip;
     ///
-    /// # Memory Warning
+    /// let sz = SevenZip::new()?;
+    /// for full in sz.list_full("evidence.7z", None)? {
+    ///     println!("{} attrs={:#010x} mode={:?} ticks={:?} folder={:?}",
+    ///              full.entry.name, full.entry.attributes, full.unix_mode.map(|m| format!("{:o}", m)),
+    ///              full.modified_ticks, full.folder);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn list_full(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<Vec<ArchiveEntryFull>> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let entries = archive.entries()?;
+
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let index = index as u32;
+                let attributes = entry.attributes;
+                let modified_ticks = archive.entry_filetime(index);
+                ArchiveEntryFull {
+                    windows_attributes: WindowsAttributes::from_raw(attributes),
+                    unix_mode: (attributes & HostOs::UNIX_EXTENSION != 0).then_some(attributes >> 16),
+                    modified_ticks,
+                    modified: modified_ticks.map(filetime_to_system_time),
+                    created: archive.entry_ctime(index).map(filetime_to_system_time),
+                    accessed: archive.entry_atime(index).map(filetime_to_system_time),
+                    folder: archive.entry_folder(index),
+                    entry,
+                }
+            })
+            .collect())
+    }
+
+    /// List contents of an archive in a chosen order
     ///
-    /// This function is NOT suitable for large files (82GB evidence directories, etc.).
-    /// It will attempt to load entire files into RAM and may cause system instability.
-    /// Use `create_archive_streaming()` for large files.
+    /// Same entries as [`list`](Self::list), stably sorted by `sort`, so
+    /// entries that compare equal keep their archive order.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use seven_zip::{SevenZip, CompressionLevel, CompressOptions};
+    /// use seven_zip::{SevenZip, SortKey};
     ///
     /// let sz = SevenZip::new()?;
-    /// let mut opts = CompressOptions::default();
-    /// opts.num_threads = 4;
-    /// opts.password = Some("secret".to_string());
-    ///
-    /// sz.create_archive(
-    ///     "archive.7z",
-    ///     &["file1.txt", "file2.txt", "directory"],
-    ///     CompressionLevel::Normal,
-    ///     Some(&opts)
-    /// )?;
+    /// for entry in sz.list_sorted("archive.7z", None, SortKey::SizeDesc)?.iter().take(10) {
+    ///     println!("{:>12} {}", entry.size, entry.name);
+    /// }
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn create_archive(
+    pub fn list_sorted(
         &self,
         archive_path: impl AsRef<Path>,
-        input_paths: &[impl AsRef<Path>],
-        level: CompressionLevel,
-        options: Option<&CompressOptions>,
-    ) -> Result<()> {
-        // Smart defaults: auto-tune if no options provided
-        let mut opts = options.cloned().unwrap_or_default();
-        
-        // Check total size and warn if it's large
-        let mut total_size: u64 = 0;
-        for path in input_paths {
-            if let Ok(metadata) = std::fs::metadata(path.as_ref()) {
-                if metadata.is_dir() {
-                    // Estimate directory size (walk would be expensive, just warn)
-                    eprintln!("WARNING: create_archive() loads files into memory.");
-                    eprintln!("For large directories, use create_archive_streaming() instead.");
-                } else {
-                    total_size += metadata.len();
-                }
-            }
-        }
-        
-        // Warn if total size exceeds 1GB
-        const MAX_SAFE_SIZE: u64 = 1024 * 1024 * 1024; // 1GB
-        if total_size > MAX_SAFE_SIZE {
-            eprintln!("WARNING: Total input size is {:.2} GB", total_size as f64 / 1e9);
-            eprintln!("This may exhaust system memory. Consider using create_archive_streaming().");
-        }
-        
-        // Auto-tune threads if not explicitly set (num_threads == 0)
-        if opts.num_threads == 0 && total_size > 0 {
-            opts.num_threads = calculate_optimal_threads(total_size);
+        password: Option<&str>,
+        sort: SortKey,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = self.list(archive_path, password)?;
+        match sort {
+            SortKey::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortKey::SizeDesc => entries.sort_by_key(|e| std::cmp::Reverse(e.size)),
+            SortKey::ModifiedTime => entries.sort_by_key(|e| e.modified_time),
+            SortKey::PackedSizeDesc => entries.sort_by_key(|e| std::cmp::Reverse(e.packed_size)),
         }
-        
-        // Auto-detect incompressible data if enabled and single file
-        let effective_level = if opts.auto_detect_incompressible && input_paths.len() == 1 {
-            let path = input_paths[0].as_ref();
-            if let Ok(metadata) = std::fs::metadata(path) {
-                if metadata.is_file() {
-                    match analyze_file_compressibility(path) {
-                        Ok((entropy, recommended)) if entropy > 0.95 => {
-                            eprintln!("Info: Data appears incompressible (entropy: {:.2}), using Store mode", entropy);
-                            CompressionLevel::Store
-                        },
-                        Ok((entropy, _)) if entropy > 0.85 => {
-                            eprintln!("Info: Low compression potential detected (entropy: {:.2})", entropy);
-                            level
-                        }
-                        _ => level,
-                    }
-                } else {
-                    level
-                }
-            } else {
-                level
-            }
-        } else {
-            level
-        };
-        
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        
-        // Convert input paths to C strings
-        let input_paths_c: Vec<CString> = input_paths
-            .iter()
-            .map(|p| path_to_cstring(p.as_ref()))
-            .collect::<Result<_>>()?;
-        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
-        input_ptrs.push(ptr::null()); // NULL-terminate
-
-        // Convert options to C struct
-        let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
-        let c_opts = ffi::SevenZipCompressOptions {
-            num_threads: opts.num_threads as i32,
-            dict_size: opts.dict_size,
-            solid: if opts.solid { 1 } else { 0 },
-            password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-        };
-        let opts_ptr = Box::new(c_opts);
-
-        unsafe {
-            let result = ffi::sevenzip_create_7z(
-                archive_path_c.as_ptr(),
-                input_ptrs.as_ptr(),
-                effective_level.into(),
-                Box::as_ref(&opts_ptr) as *const ffi::SevenZipCompressOptions,
-                None,
-                ptr::null_mut(),
-            );
+        Ok(entries)
+    }
 
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
-            }
+    /// Directory hierarchy of an archive, for a `tree`-style view
+    ///
+    /// Rebuilt from the entry names of [`list`](Self::list): the returned
+    /// root (with an empty name) holds the top-level entries, and
+    /// directories that are only implied by deeper paths are filled in.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, TreeNode};
+    ///
+    /// fn print(node: &TreeNode, depth: usize) {
+    ///     for child in &node.children {
+    ///         println!("{}{}{}", "  ".repeat(depth), child.name, if child.is_dir { "/" } else { "" });
+    ///         print(child, depth + 1);
+    ///     }
+    /// }
+    ///
+    /// let sz = SevenZip::new()?;
+    /// print(&sz.tree("archive.7z", None)?, 0);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn tree(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<TreeNode> {
+        let mut root = TreeNode { is_dir: true, ..Default::default() };
+        for entry in self.list(archive_path, password)? {
+            let name = normalize_entry_name(&entry.name);
+            let components: Vec<&str> = name.split('/').filter(|c| !c.is_empty()).collect();
+            root.insert(&components, entry.is_directory);
         }
-
-        Ok(())
+        Ok(root)
     }
 
-    /// Create encrypted archive with recommended settings
-    /// 
-    /// Encryption has virtually zero performance overhead (<1%)
-    /// and provides strong AES-256 security.
-    /// 
+    /// List contents of an archive as a pretty-printed JSON array
+    ///
+    /// Each element is an [`ArchiveEntry`] with its field names as keys.
+    /// Only entry metadata is written, never the password.
+    ///
     /// # Example
-    /// 
+    ///
     /// ```no_run
     /// use seven_zip::SevenZip;
-    /// 
+    ///
     /// let sz = SevenZip::new()?;
-    /// sz.create_encrypted_archive(
-    ///     "secure.7z",
-    ///     &["sensitive.txt", "private.doc"],
-    ///     "MyStrongPassword123!",
-    ///     seven_zip::CompressionLevel::Normal,
-    /// )?;
+    /// println!("{}", sz.list_json("archive.7z", None)?);
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn create_encrypted_archive(
-        &self,
-        archive_path: impl AsRef<Path>,
-        input_paths: &[impl AsRef<Path>],
-        password: &str,
-        level: CompressionLevel,
-    ) -> Result<()> {
-        let file_path_strs: Vec<String> = input_paths
-            .iter()
-            .map(|p| p.as_ref().to_string_lossy().to_string())
-            .collect();
-        let file_paths_refs: Vec<&str> = file_path_strs.iter().map(|s| s.as_str()).collect();
-        
-        let opts = CompressOptions::auto_tuned(&file_paths_refs)
-            .unwrap_or_default()
-            .with_password(password.to_string());
-        
-        self.create_archive(archive_path, input_paths, level, Some(&opts))
+    #[cfg(feature = "serde")]
+    pub fn list_json(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<String> {
+        let entries = self.list(archive_path, password)?;
+        serde_json::to_string_pretty(&entries)
+            .map_err(|e| Error::Unknown(format!("Failed to serialize entries: {}", e)))
     }
 
-    /// Create archive with smart defaults (auto-tuned threads, incompressible detection)
-    /// 
+    /// Visit every entry without building the whole list at once
+    ///
+    /// Entries are converted in batches of a few thousand, each freed before
+    /// the next is fetched, so memory stays flat beyond the parsed header
+    /// itself even for archives with millions of entries. Entries come in
+    /// archive index order. Returning `Err` from `f` stops the walk and
+    /// passes the error through.
+    ///
     /// # Example
-    /// 
+    ///
     /// ```no_run
     /// use seven_zip::SevenZip;
-    /// 
+    ///
     /// let sz = SevenZip::new()?;
-    /// sz.create_smart_archive(
-    ///     "backup.7z",
-    ///     &["file1.txt", "file2.bin"],
-    ///     seven_zip::CompressionLevel::Normal,
-    /// )?;
+    /// let mut total = 0;
+    /// sz.for_each_entry("huge.7z", None, |entry| {
+    ///     total += entry.size;
+    ///     Ok(())
+    /// })?;
+    /// println!("{} bytes", total);
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn create_smart_archive(
+    pub fn for_each_entry(
         &self,
         archive_path: impl AsRef<Path>,
-        input_paths: &[impl AsRef<Path>],
-        level: CompressionLevel,
+        password: Option<&str>,
+        mut f: impl FnMut(&ArchiveEntry) -> Result<()>,
     ) -> Result<()> {
-        let file_path_strs: Vec<String> = input_paths
-            .iter()
-            .map(|p| p.as_ref().to_string_lossy().to_string())
-            .collect();
-        let file_paths_refs: Vec<&str> = file_path_strs.iter().map(|s| s.as_str()).collect();
-        
-        let opts = CompressOptions::auto_tuned(&file_paths_refs).unwrap_or_default();
-        self.create_archive(archive_path, input_paths, level, Some(&opts))
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+
+        let mut start = 0u32;
+        loop {
+            let batch = archive.entries_range(start, ENTRY_BATCH_SIZE)?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            for entry in &batch {
+                f(entry)?;
+            }
+            start += batch.len() as u32;
+        }
     }
 
-    /// Test archive integrity
+    /// Walk the entries in archive order, each with a reader for its data
     ///
-    /// Validates CRCs and decompression without writing files.
+    /// Data is only decompressed once the entry's reader is first read, so
+    /// entries whose reader is dropped unread cost nothing in a non-solid
+    /// archive. In a solid archive, entries share one compressed stream:
+    /// reading any entry decodes its whole block, including the skipped
+    /// entries before it, so skipping saves less than it seems. Each reader
+    /// borrows the [`EntriesReader`] and must be dropped before advancing.
     ///
     /// # Example
     ///
     /// ```no_run
     /// use seven_zip::SevenZip;
+    /// use std::io::Read;
     ///
     /// let sz = SevenZip::new()?;
-    /// sz.test_archive("archive.7z", None)?;
-    /// println!("Archive is valid!");
-    /// # Ok::<(), seven_zip::Error>(())
+    /// let mut entries = sz.entries_with_readers("archive.7z", None)?;
+    /// while let Some(item) = entries.next() {
+    ///     let (entry, mut reader) = item?;
+    ///     if entry.name.ends_with(".log") {
+    ///         let mut text = String::new();
+    ///         reader.read_to_string(&mut text)?;
+    ///         println!("{}: {} lines", entry.name, text.lines().count());
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn test_archive(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<()> {
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        let password_c = password.map(|p| CString::new(p)).transpose()?;
+    pub fn entries_with_readers(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<EntriesReader> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let entries = archive.entries()?;
+        Ok(EntriesReader {
+            archive,
+            entries: entries.into_iter(),
+            next_index: 0,
+        })
+    }
 
-        unsafe {
-            let result = ffi::sevenzip_test_archive(
-                archive_path_c.as_ptr(),
-                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                None,
-                ptr::null_mut(),
-            );
+    /// Open an archive for folder-level reading
+    ///
+    /// Lower-level than [`entries_with_readers`](Self::entries_with_readers):
+    /// the [`ArchiveReader`] hands out each folder's raw decompressed stream
+    /// together with the entries it holds, for callers that route entry data
+    /// themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Path to the archive file
+    /// * `password` - Optional password for encrypted archives
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// use std::io::Read;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut reader = sz.reader("archive.7z", None)?;
+    /// for folder in 0..reader.folders().len() {
+    ///     let entries = reader.entries_in_folder(folder).to_vec();
+    ///     let mut stream = reader.substream(folder)?;
+    ///     for entry in entries {
+    ///         let mut data = vec![0; entry.size as usize];
+    ///         stream.read_exact(&mut data)?;
+    ///         println!("{}: {} bytes", entry.name, data.len());
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reader(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<ArchiveReader> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let mut folders = (0..archive.folder_count())
+            .map(|index| archive.folder_info(index))
+            .collect::<Result<Vec<_>>>()?;
+        let mut folder_entries = vec![Vec::new(); folders.len()];
 
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+        for (index, entry) in archive.entries()?.into_iter().enumerate() {
+            let Some(folder) = archive.entry_folder(index as u32) else {
+                continue;
+            };
+            if let (Some(info), Some(entries)) = (folders.get_mut(folder as usize), folder_entries.get_mut(folder as usize)) {
+                info.entry_names.push(entry.name.clone());
+                entries.push(entry);
             }
         }
 
-        Ok(())
+        Ok(ArchiveReader { archive, folders, folder_entries })
     }
 
-    /// Create a 7z archive with streaming compression (supports large files and split archives)
+    /// Get the total uncompressed size of an archive without listing it
     ///
-    /// This method is optimized for large files and supports creating split/multi-volume archives.
-    /// Files are processed in chunks to avoid loading entire files into RAM.
+    /// Sums the per-folder unpack sizes stored in the archive header instead
+    /// of building the full entry list, which is much cheaper on archives with
+    /// many entries.
     ///
     /// # Arguments
     ///
-    /// * `archive_path` - Base path for the archive (e.g., "archive.7z")
-    ///                    For split archives, creates archive.7z.001, archive.7z.002, etc.
-    /// * `input_paths` - Files/directories to compress
-    /// * `level` - Compression level
-    /// * `options` - Streaming options (split size, chunk size, etc.)
-    /// * `progress` - Optional byte-level progress callback
+    /// * `archive_path` - Path to the archive file
+    /// * `password` - Optional password for encrypted archives
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use seven_zip::{SevenZip, CompressionLevel, StreamOptions};
+    /// use seven_zip::SevenZip;
     ///
     /// let sz = SevenZip::new()?;
-    /// let mut opts = StreamOptions::default();
-    /// opts.split_size = 4_294_967_296; // 4GB segments
-    /// opts.chunk_size = 67_108_864;     // 64MB chunks
-    /// opts.num_threads = 8;
+    /// let bytes = sz.uncompressed_size("archive.7z", None)?;
+    /// println!("Needs {} bytes of free space", bytes);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn uncompressed_size(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<u64> {
+        Ok(header_summary(archive_path.as_ref(), password)?.unpacked_size)
+    }
+
+    /// Count an archive's files and directories without listing it
     ///
-    /// sz.create_archive_streaming(
-    ///     "large_archive.7z",
-    ///     &["/path/to/large/file.img"],
-    ///     CompressionLevel::Normal,
-    ///     Some(&opts),
-    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
-    ///         println!("Processing {}: {}/{} bytes", filename, file_bytes, file_total);
-    ///         println!("Total: {}/{} bytes", processed, total);
-    ///     }))
-    /// )?;
+    /// Returns `(file_count, dir_count)`. Only the header is parsed; no entry
+    /// names are decoded or copied, so this is the cheapest way to size a
+    /// progress bar.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let (files, dirs) = sz.count_entries("archive.7z", None)?;
+    /// println!("{} files in {} directories", files, dirs);
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn create_archive_streaming(
+    pub fn count_entries(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<(usize, usize)> {
+        let info = header_summary(archive_path.as_ref(), password)?;
+        let dirs = info.num_directories as usize;
+        Ok((info.num_entries as usize - dirs, dirs))
+    }
+
+    /// Read every entry's stored CRC32 without decompressing anything
+    ///
+    /// Returns `(name, crc)` pairs in archive order, taken from the header,
+    /// the same data as [`ArchiveEntry::crc32`]. The cost grows with the
+    /// header, not the packed data, which suits deduplicating a large corpus.
+    ///
+    /// The CRC is `None` for directories, for empty files, and for files
+    /// whose archive stores no CRC of their own. That last case covers a
+    /// solid folder that records only one CRC over all its files combined.
+    /// Getting those files' CRCs takes decompressing them, e.g. by hashing
+    /// each reader from [`entries_with_readers`](Self::entries_with_readers).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for (name, crc) in sz.checksum_entries("corpus.7z", None)? {
+    ///     if let Some(crc) = crc {
+    ///         println!("{:08x}  {}", crc, name);
+    ///     }
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn checksum_entries(
         &self,
         archive_path: impl AsRef<Path>,
-        input_paths: &[impl AsRef<Path>],
-        level: CompressionLevel,
-        options: Option<&StreamOptions>,
-        progress: Option<BytesProgressCallback>,
-    ) -> Result<()> {
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        
-        // Convert input paths to C strings
-        let input_paths_c: Vec<CString> = input_paths
-            .iter()
-            .map(|p| path_to_cstring(p.as_ref()))
-            .collect::<Result<_>>()?;
-        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
-        input_ptrs.push(ptr::null()); // NULL-terminate
+        password: Option<&str>,
+    ) -> Result<Vec<(String, Option<u32>)>> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        Ok(archive.entries()?.into_iter().map(|entry| (entry.name, entry.crc32)).collect())
+    }
 
-        // Convert options to C struct
-        let (opts_ptr, _password_c, _temp_dir_c) = if let Some(opts) = options {
-            let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
-            let temp_dir_c = opts.temp_dir.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
-            let c_opts = ffi::SevenZipStreamOptions {
-                num_threads: opts.num_threads as i32,
-                dict_size: opts.dict_size,
-                solid: if opts.solid { 1 } else { 0 },
-                password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                split_size: opts.split_size,
-                chunk_size: opts.chunk_size,
-                temp_dir: temp_dir_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                delete_temp_on_error: if opts.delete_temp_on_error { 1 } else { 0 },
-            };
-            (Box::new(c_opts), password_c, temp_dir_c)
-        } else {
-            // Initialize with defaults
-            let mut c_opts = std::mem::MaybeUninit::<ffi::SevenZipStreamOptions>::uninit();
-            unsafe {
-                ffi::sevenzip_stream_options_init(c_opts.as_mut_ptr());
-                (Box::new(c_opts.assume_init()), None, None)
-            }
-        };
+    /// Get the total size of an archive's packed streams without listing it
+    ///
+    /// The counterpart of [`uncompressed_size`](Self::uncompressed_size),
+    /// read from the same header metadata; the two give the compression
+    /// ratio at a glance. The signature header and the archive header itself
+    /// are not included. Pass the first volume of a split archive (e.g.
+    /// `archive.7z.001`) to total the streams across all its volumes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let packed = sz.packed_size("archive.7z", None)?;
+    /// let unpacked = sz.uncompressed_size("archive.7z", None)?;
+    /// println!("{:.1}% of the original size", packed as f64 * 100.0 / unpacked.max(1) as f64);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn packed_size(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<u64> {
+        let archive_path = archive_path.as_ref();
+        let is_numbered_volume = archive_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.len() == 3 && ext.bytes().all(|b| b.is_ascii_digit()));
+        if !is_numbered_volume {
+            return Ok(header_summary(archive_path, password)?.packed_size);
+        }
 
-        // Set up progress callback
-        let (callback, user_data) = if let Some(cb) = progress {
-            let boxed = Box::new(cb);
-            let raw = Box::into_raw(boxed);
-            (
-                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
-                raw as *mut std::os::raw::c_void,
+        let archive_path_c = path_to_cstring(archive_path)?;
+        let password_c = password.map(CString::new).transpose()?;
+        let mut info = ffi::SevenZipArchiveInfo::default();
+        let result = unsafe {
+            ffi::sevenzip_get_split_archive_info(
+                archive_path_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                &mut info,
             )
-        } else {
-            (None, ptr::null_mut())
         };
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+        Ok(info.packed_size)
+    }
 
-        unsafe {
-            let result = ffi::sevenzip_create_7z_streaming(
-                archive_path_c.as_ptr(),
-                input_ptrs.as_ptr(),
-                level.into(),
-                &*opts_ptr,
-                callback,
-                user_data,
-            );
+    /// Read archive-level totals and the host OS the archive was created on
+    ///
+    /// The totals come from the header alone. The host OS is judged from the
+    /// entry attributes: 7-Zip on Unix sets the `0x8000` flag and keeps the
+    /// Unix mode in the high 16 bits, while Windows hosts store plain
+    /// `FILE_ATTRIBUTE_*` flags. Use it to decide how to read
+    /// [`ArchiveEntry::attributes`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, HostOs};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let info = sz.archive_info("archive.7z", None)?;
+    /// if info.host_os == Some(HostOs::Unix) {
+    ///     println!("entry attributes carry Unix modes");
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn archive_info(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<ArchiveInfo> {
+        let archive_path = archive_path.as_ref();
+        let summary = header_summary(archive_path, password)?;
+        let entries = self.list(archive_path, password)?;
+        let raw_header = read_raw_header(archive_path)?;
 
-            // Clean up the callback if it was allocated
-            if !user_data.is_null() {
-                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
-                // Drops automatically
-            }
+        Ok(ArchiveInfo {
+            entries: summary.num_entries,
+            unpacked_size: summary.unpacked_size,
+            packed_size: summary.packed_size,
+            folders: summary.num_folders,
+            host_os: HostOs::from_attributes(entries.iter().map(|entry| entry.attributes)),
+            is_compressed_header: raw_header.is_some_and(|h| h.bytes.first() == Some(&ENCODED_HEADER_ID)),
+        })
+    }
 
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+    /// Report the 7zAES key-derivation parameters of an archive
+    ///
+    /// Reads the AES coder properties of the first encrypted folder, which
+    /// lets an auditor check that an archive meets a minimum work factor.
+    /// Returns `None` if no folder is encrypted. Archives whose headers are
+    /// encrypted too can't be opened without a decoder for them, so they fail
+    /// like any other unreadable archive.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// if let Some(info) = sz.encryption_info("evidence.7z", None)? {
+    ///     assert!(info.kdf_iterations >= 1 << 19, "weak key derivation: {:?}", info);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn encryption_info(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<Option<EncryptionInfo>> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        for index in 0..archive.folder_count() {
+            if let Some(info) = archive.folder_encryption(index)? {
+                return Ok(Some(info));
             }
         }
-
-        Ok(())
+        Ok(None)
     }
 
-    /// Extract a 7z archive with streaming decompression and byte-level progress
-    ///
-    /// Automatically handles split/multi-volume archives. For split archives, provide
-    /// the path to the first volume (e.g., "archive.7z.001").
-    ///
-    /// # Arguments
+    /// Compare the entries of two archives without extracting them
     ///
-    /// * `archive_path` - Path to archive (for splits, use base name like "archive.7z.001")
-    /// * `output_dir` - Directory to extract to
-    /// * `password` - Optional password for encrypted archives
-    /// * `progress` - Optional byte-level progress callback
+    /// Entries are matched by name. A pair differs if the sizes differ, if
+    /// both headers store a CRC and the CRCs differ, or if one is a
+    /// directory and the other is not. Only headers are read, so comparing
+    /// a backup against the source-of-truth archive is cheap.
     ///
     /// # Example
     ///
@@ -886,276 +2992,4022 @@ impl SevenZip {
     /// use seven_zip::SevenZip;
     ///
     /// let sz = SevenZip::new()?;
-    /// sz.extract_streaming(
-    ///     "archive.7z.001",  // First volume of split archive
-    ///     "output",
-    ///     None,
-    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
-    ///         if total > 0 {
-    ///             let percent = (processed as f64 / total as f64) * 100.0;
-    ///             println!("Extracting {}: {:.1}%", filename, percent);
-    ///         }
-    ///     }))
-    /// )?;
+    /// let diff = sz.compare("evidence.7z", "backup/evidence.7z", None, None)?;
+    /// assert!(diff.is_identical(), "backup differs: {:?}", diff);
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn extract_streaming(
+    pub fn compare(
         &self,
-        archive_path: impl AsRef<Path>,
-        output_dir: impl AsRef<Path>,
-        password: Option<&str>,
-        progress: Option<BytesProgressCallback>,
-    ) -> Result<()> {
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        let output_dir_c = path_to_cstring(output_dir.as_ref())?;
-        let password_c = password.map(|p| CString::new(p)).transpose()?;
+        a: impl AsRef<Path>,
+        b: impl AsRef<Path>,
+        password_a: Option<&str>,
+        password_b: Option<&str>,
+    ) -> Result<ArchiveDiff> {
+        let entries_a = self.list(a, password_a)?;
+        let mut by_name_b: HashMap<String, ArchiveEntry> = self
+            .list(b, password_b)?
+            .into_iter()
+            .map(|entry| (normalize_entry_name(&entry.name), entry))
+            .collect();
 
-        // Set up progress callback
-        let (callback, user_data) = if let Some(cb) = progress {
-            let boxed = Box::new(cb);
-            let raw = Box::into_raw(boxed);
-            (
-                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
-                raw as *mut std::os::raw::c_void,
-            )
-        } else {
-            (None, ptr::null_mut())
-        };
+        let mut diff = ArchiveDiff::default();
+        for entry_a in entries_a {
+            let name = normalize_entry_name(&entry_a.name);
+            match by_name_b.remove(&name) {
+                None => diff.only_in_a.push(name),
+                Some(entry_b) => {
+                    let crc_differs = matches!(
+                        (entry_a.crc32, entry_b.crc32),
+                        (Some(crc_a), Some(crc_b)) if crc_a != crc_b
+                    );
+                    if entry_a.size != entry_b.size
+                        || entry_a.is_directory != entry_b.is_directory
+                        || crc_differs
+                    {
+                        diff.differing.push(name);
+                    } else {
+                        diff.identical += 1;
+                    }
+                }
+            }
+        }
+        diff.only_in_b = by_name_b.into_keys().collect();
 
-        unsafe {
-            let result = ffi::sevenzip_extract_streaming(
-                archive_path_c.as_ptr(),
-                output_dir_c.as_ptr(),
-                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                callback,
-                user_data,
-            );
+        diff.only_in_a.sort();
+        diff.only_in_b.sort();
+        diff.differing.sort();
+        Ok(diff)
+    }
 
-            // Clean up the callback if it was allocated
-            if !user_data.is_null() {
-                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
-                // Drops automatically
-            }
+    /// Report the folders (solid blocks) of an archive and which entries each holds
+    ///
+    /// Lets a caller build a seek index for random access: to read one entry,
+    /// fetch only its folder's packed bytes instead of the whole archive.
+    /// Directories and empty files have no data and appear in no folder.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for folder in sz.folder_layout("archive.7z", None)? {
+    ///     println!("block {} at {}+{} ({}): {:?}", folder.index,
+    ///              folder.packed_offset, folder.packed_size, folder.codec, folder.entry_names);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn folder_layout(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<Vec<FolderInfo>> {
+        let archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let mut folders = (0..archive.folder_count())
+            .map(|index| archive.folder_info(index))
+            .collect::<Result<Vec<_>>>()?;
 
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+        for (index, entry) in archive.entries()?.into_iter().enumerate() {
+            if let Some(folder) = archive.entry_folder(index as u32) {
+                if let Some(info) = folders.get_mut(folder as usize) {
+                    info.entry_names.push(entry.name);
+                }
             }
         }
 
-        Ok(())
+        Ok(folders)
     }
 
-    /// Create a 7z archive using TRUE streaming compression (RECOMMENDED for large archives)
+    /// List the immediate children of a directory inside an archive
     ///
-    /// ⚠️ **IMPORTANT**: This method processes files in 64MB chunks WITHOUT loading
-    /// all data into RAM first. Use this for archives larger than 8GB to avoid
-    /// out-of-memory crashes.
+    /// Returns only entries exactly one path component below `dir`. Pass `""`
+    /// to list the archive root. Intermediate directories that the archive does
+    /// not store explicitly are synthesized as directory entries.
     ///
-    /// The standard `create_archive_streaming` method (when split_size == 0) still
-    /// loads all file data into memory before compression, which causes OOM for
-    /// large archives. This method fixes that limitation.
+    /// # Arguments
     ///
-    /// Memory usage: ~250MB peak regardless of archive size
+    /// * `archive_path` - Path to the archive file
+    /// * `dir` - Directory within the archive, using `/` as separator
+    /// * `password` - Optional password for encrypted archives
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for entry in sz.list_dir("archive.7z", "docs", None)? {
+    ///     println!("{}{}", entry.name, if entry.is_directory { "/" } else { "" });
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn list_dir(
+        &self,
+        archive_path: impl AsRef<Path>,
+        dir: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let entries = self.list(archive_path, password)?;
+        Ok(direct_children(entries, dir))
+    }
+
+    /// Extract a single entry to an arbitrary file path
+    ///
+    /// Unlike [`extract_files`](Self::extract_files), `dest_path` is the path of
+    /// the output file itself rather than a directory, so the entry can be
+    /// renamed as it is extracted. Missing parent directories are created and
+    /// an existing file at `dest_path` is overwritten.
     ///
     /// # Arguments
     ///
-    /// * `archive_path` - Output archive path
-    /// * `input_paths` - Files/directories to compress
-    /// * `level` - Compression level
-    /// * `options` - Streaming options (chunk size, threads, etc.)
-    /// * `progress` - Optional byte-level progress callback
+    /// * `archive_path` - Path to the archive file
+    /// * `entry_name` - Name of the entry inside the archive (`/` or `\` separated)
+    /// * `dest_path` - Output file path
+    /// * `password` - Optional password for encrypted archives
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `entry_name` is not in the archive
+    /// or names a directory.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use seven_zip::{SevenZip, CompressionLevel, StreamOptions};
+    /// use seven_zip::SevenZip;
     ///
     /// let sz = SevenZip::new()?;
-    /// let mut opts = StreamOptions::default();
-    /// opts.num_threads = 8;
-    /// opts.chunk_size = 64 * 1024 * 1024; // 64MB chunks
+    /// sz.extract_entry_as("archive.7z", "docs/readme.txt", "out/README", None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_entry_as(
+        &self,
+        archive_path: impl AsRef<Path>,
+        entry_name: &str,
+        dest_path: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let wanted = normalize_entry_name(entry_name);
+        self.extract_matching_entry(
+            archive_path.as_ref(),
+            |entry| normalize_entry_name(&entry.name) == wanted,
+            entry_name,
+            dest_path.as_ref(),
+            password,
+        )
+    }
+
+    /// Extract a single entry, found by its exact stored name, to a file path
     ///
-    /// // Create 88GB archive without running out of memory
-    /// sz.create_archive_true_streaming(
-    ///     "forensic_evidence.7z",
-    ///     &["/path/to/88gb/evidence/folder"],
-    ///     CompressionLevel::Normal,
-    ///     Some(&opts),
-    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
-    ///         let percent = if total > 0 {
-    ///             (processed as f64 / total as f64) * 100.0
-    ///         } else { 0.0 };
-    ///         println!("[{:.1}%] {} ({}/{} bytes)", percent, filename, file_bytes, file_total);
-    ///     }))
-    /// )?;
+    /// Same as [`extract_entry_as`](Self::extract_entry_as), but matches
+    /// [`ArchiveEntry::raw_name`] byte for byte, so entries whose names are
+    /// not valid UTF-8 can be extracted too.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for entry in sz.list("archive.7z", None)? {
+    ///     if entry.name.contains('\u{FFFD}') {
+    ///         sz.extract_entry_as_raw("archive.7z", &entry.raw_name, "recovered.bin", None)?;
+    ///     }
+    /// }
     /// # Ok::<(), seven_zip::Error>(())
     /// ```
-    pub fn create_archive_true_streaming(
+    pub fn extract_entry_as_raw(
         &self,
         archive_path: impl AsRef<Path>,
-        input_paths: &[impl AsRef<Path>],
-        level: CompressionLevel,
-        options: Option<&StreamOptions>,
-        progress: Option<BytesProgressCallback>,
+        raw_name: &[u8],
+        dest_path: impl AsRef<Path>,
+        password: Option<&str>,
     ) -> Result<()> {
-        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
-        
-        // Convert input paths to C strings
-        let input_paths_c: Vec<CString> = input_paths
-            .iter()
-            .map(|p| path_to_cstring(p.as_ref()))
-            .collect::<Result<_>>()?;
-        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
-        input_ptrs.push(ptr::null()); // NULL-terminate
+        self.extract_matching_entry(
+            archive_path.as_ref(),
+            |entry| entry.raw_name == raw_name,
+            &String::from_utf8_lossy(raw_name),
+            dest_path.as_ref(),
+            password,
+        )
+    }
 
-        // Convert options to C struct
-        let (opts_ptr, _password_c, _temp_dir_c) = if let Some(opts) = options {
-            let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
-            let temp_dir_c = opts.temp_dir.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
-            let c_opts = ffi::SevenZipStreamOptions {
-                num_threads: opts.num_threads as i32,
-                dict_size: opts.dict_size,
-                solid: if opts.solid { 1 } else { 0 },
-                password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                split_size: opts.split_size,
-                chunk_size: opts.chunk_size,
-                temp_dir: temp_dir_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
-                delete_temp_on_error: if opts.delete_temp_on_error { 1 } else { 0 },
-            };
+    /// Extract several entries, each to its own file path
+    ///
+    /// Each pair maps an entry name to its output file, as in
+    /// [`extract_entry_as`](Self::extract_entry_as), but the archive is opened
+    /// and each solid block decoded only once. All names are checked before
+    /// anything is written, so a typo leaves the destinations untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Path to the archive file
+    /// * `mapping` - `(entry_name, dest_path)` pairs
+    /// * `password` - Optional password for encrypted archives
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if an entry name is not in the
+    /// archive or names a directory.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// use std::path::PathBuf;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_mapped("deploy.7z", &[
+    ///     ("nginx.conf", PathBuf::from("/etc/nginx/nginx.conf")),
+    ///     ("app/settings.toml", PathBuf::from("/opt/app/settings.toml")),
+    /// ], None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_mapped(
+        &self,
+        archive_path: impl AsRef<Path>,
+        mapping: &[(&str, PathBuf)],
+        password: Option<&str>,
+    ) -> Result<()> {
+        let mut archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let entries = archive.entries()?;
+        let by_name: HashMap<String, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (normalize_entry_name(&entry.name), index))
+            .collect();
+
+        let mut targets = Vec::with_capacity(mapping.len());
+        for (entry_name, dest_path) in mapping {
+            let index = *by_name.get(&normalize_entry_name(entry_name)).ok_or_else(|| {
+                Error::InvalidParameter(format!("Entry not found in archive: {}", entry_name))
+            })?;
+            if entries[index].is_directory {
+                return Err(Error::InvalidParameter(format!(
+                    "Entry is a directory: {}",
+                    entry_name
+                )));
+            }
+            targets.push((index, dest_path));
+        }
+
+        // Archive order, so entries of one solid block are read back to back
+        targets.sort_by_key(|(index, _)| *index);
+        for (index, dest_path) in targets {
+            let data = archive.read(index as u32)?;
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(dest_path, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shared body of the `extract_entry_as` variants
+    fn extract_matching_entry(
+        &self,
+        archive_path: &Path,
+        matches: impl Fn(&ArchiveEntry) -> bool,
+        entry_name: &str,
+        dest_path: &Path,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let mut archive = ArchiveHandle::open(archive_path, password)?;
+        let (index, entry) = archive
+            .entries()?
+            .into_iter()
+            .enumerate()
+            .find(|(_, entry)| matches(entry))
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!("Entry not found in archive: {}", entry_name))
+            })?;
+
+        if entry.is_directory {
+            return Err(Error::InvalidParameter(format!(
+                "Entry is a directory: {}",
+                entry_name
+            )));
+        }
+
+        let data = archive.read(index as u32)?;
+        if let Some(parent) = dest_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(dest_path, data)?;
+
+        Ok(())
+    }
+
+    /// Copy entries from one archive into a new archive
+    ///
+    /// Entries whose folder (solid block) is copied as a whole move over as
+    /// packed bytes, without being decompressed and compressed again; the
+    /// codec chain, and any encryption, stays as it was. Entries that share
+    /// a folder with entries left behind are decompressed to a staging
+    /// directory next to `dst` and recompressed with LZMA2 at
+    /// [`CompressionLevel::Normal`], whatever their source codec and level.
+    /// If any of those folders was encrypted, the recompressed data is
+    /// encrypted again with `password` and the source's key-derivation
+    /// iterations, so no entry ends up in `dst` in the clear. Names,
+    /// modification times and attributes carry over either way.
+    ///
+    /// `dst` is written to a temporary file and renamed into place; an
+    /// existing file at `dst` is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `names` is empty or names an
+    /// entry that is not in `src`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.copy_entries("full.7z", "subset.7z", &["logs/app.log", "config.toml"], None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn copy_entries(
+        &self,
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        names: &[&str],
+        password: Option<&str>,
+    ) -> Result<()> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        if names.is_empty() {
+            return Err(Error::InvalidParameter("No entries to copy".to_string()));
+        }
+
+        let mut archive = ArchiveHandle::open(src, password)?;
+        let entries = archive.entries()?;
+        let by_name: HashMap<String, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (normalize_entry_name(&entry.name), index))
+            .collect();
+
+        let mut selected = HashSet::new();
+        for name in names {
+            let index = *by_name.get(&normalize_entry_name(name)).ok_or_else(|| {
+                Error::InvalidParameter(format!("Entry not found in archive: {}", name))
+            })?;
+            selected.insert(index);
+        }
+
+        let mut folder_members: HashMap<u32, Vec<usize>> = HashMap::new();
+        let mut empty = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            match archive.entry_folder(index as u32) {
+                Some(folder) => folder_members.entry(folder).or_default().push(index),
+                None if selected.contains(&index) => empty.push(copied_file(&archive, entry, index)),
+                None => {}
+            }
+        }
+
+        let mut folders = Vec::new();
+        let mut partial = Vec::new();
+        let mut partial_encryption = None;
+        for folder in 0..archive.folder_count() {
+            let Some(members) = folder_members.get(&folder) else {
+                continue;
+            };
+            let chosen: Vec<usize> = members.iter().copied().filter(|i| selected.contains(i)).collect();
+            if chosen.len() == members.len() {
+                folders.push(CopiedFolder {
+                    source: src.to_path_buf(),
+                    folder: archive.raw_folder(folder)?,
+                    files: chosen.iter().map(|&i| copied_file(&archive, &entries[i], i)).collect(),
+                });
+            } else if !chosen.is_empty() {
+                partial.extend(chosen);
+                partial_encryption = partial_encryption.or(archive.folder_encryption(folder)?);
+            }
+        }
+
+        let staging = StagingDir::new(dst)?;
+        if !partial.is_empty() {
+            // Recompress entries of shared folders into folders of their own
+            let content_dir = staging.path().join("content");
+            let mut staged = HashMap::new();
+            for index in partial {
+                let name = normalize_entry_name(&entries[index].name);
+                if !is_contained_entry_path(&name) {
+                    return Err(Error::InvalidParameter(format!(
+                        "Entry name escapes the staging directory: {}",
+                        entries[index].name
+                    )));
+                }
+                let path = content_dir.join(&name);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, archive.read(index as u32)?)?;
+                staged.insert(name, copied_file(&archive, &entries[index], index));
+            }
+
+            // Data read from an encrypted folder is written back encrypted
+            let repack_options = partial_encryption.map(|info| CompressOptions {
+                password: password.map(str::to_string),
+                kdf_iterations: u32::try_from(info.kdf_iterations).ok().filter(|n| n.is_power_of_two() && *n <= 1 << 24),
+                ..Default::default()
+            });
+            let repacked = staging.path().join("repacked.7z");
+            self.create_archive(&repacked, &[&content_dir], CompressionLevel::Normal, repack_options.as_ref())?;
+            let repacked_archive = ArchiveHandle::open(&repacked, password)?;
+            let repacked_entries = repacked_archive.entries()?;
+            for folder in 0..repacked_archive.folder_count() {
+                let mut files = Vec::new();
+                for (index, entry) in repacked_entries.iter().enumerate() {
+                    if repacked_archive.entry_folder(index as u32) != Some(folder) {
+                        continue;
+                    }
+                    let file = staged.remove(&normalize_entry_name(&entry.name)).ok_or_else(|| {
+                        Error::Unknown(format!("Unexpected entry in recompressed data: {}", entry.name))
+                    })?;
+                    files.push(file);
+                }
+                folders.push(CopiedFolder {
+                    source: repacked.clone(),
+                    folder: repacked_archive.raw_folder(folder)?,
+                    files,
+                });
+            }
+        }
+
+        let output = staging.path().join("copy.7z");
+        raw_copy::write_archive(&output, &folders, &empty)?;
+        std::fs::rename(&output, dst)?;
+        Ok(())
+    }
+
+    /// Replace the content of a single existing entry
+    ///
+    /// Archives written by this library are solid, so the whole archive is
+    /// rebuilt: entries are extracted to a staging directory next to the
+    /// archive, the entry is overwritten and everything is recompressed with
+    /// `level` and `options`. Pass the settings the archive was created with
+    /// to keep its method, solid mode and dictionary. `options.password`, or
+    /// `options.encryption_key`, opens the archive and encrypts the rebuilt
+    /// one. The result is written
+    /// to a temporary file and renamed over the original, so readers never
+    /// see a half-written archive and the original is untouched if anything
+    /// fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `entry_name` does not exist or is
+    /// a directory; this method never adds new entries.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.replace_entry("config.7z", "settings.json", br#"{"theme":"dark"}"#, CompressionLevel::Normal, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn replace_entry(
+        &self,
+        archive_path: impl AsRef<Path>,
+        entry_name: &str,
+        new_data: &[u8],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<()> {
+        let archive_path = archive_path.as_ref();
+        let wanted = normalize_entry_name(entry_name);
+        let password = options.and_then(|o| o.password.as_deref());
+
+        let entry = self
+            .list(archive_path, password)?
+            .into_iter()
+            .find(|entry| normalize_entry_name(&entry.name) == wanted)
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!("Entry not found in archive: {}", entry_name))
+            })?;
+        if entry.is_directory {
+            return Err(Error::InvalidParameter(format!(
+                "Entry is a directory: {}",
+                entry_name
+            )));
+        }
+
+        let staging = StagingDir::new(archive_path)?;
+        let content_dir = staging.path().join("content");
+        let extract_opts = ExtractOptions {
+            password: password.map(str::to_owned),
+            encryption_key: options.and_then(|o| o.encryption_key),
+            ..ExtractOptions::default()
+        };
+        self.extract_with_options(archive_path, &content_dir, &extract_opts, None)?;
+
+        std::fs::write(content_dir.join(&wanted), new_data)?;
+
+        let rebuilt = staging.path().join("rebuilt.7z");
+        self.create_archive(&rebuilt, &[&content_dir], level, options)?;
+
+        std::fs::rename(&rebuilt, archive_path)?;
+        Ok(())
+    }
+
+    /// Recompress an existing archive at a different level or method
+    ///
+    /// For archives whose source files are gone, e.g. turning a
+    /// [`CompressionLevel::Store`] archive into an [`CompressionLevel::Ultra`]
+    /// one. Entries are unpacked to a staging directory next to
+    /// `output_archive` (on disk, not in RAM), with their modification times
+    /// restored, and compressed again through
+    /// [`create_archive_streaming`](Self::create_archive_streaming) with
+    /// `level` and `options`. Names, timestamps and directory entries carry
+    /// over.
+    ///
+    /// `options.password` both opens an encrypted input and encrypts the
+    /// output, so an encrypted archive stays encrypted.
+    ///
+    /// The new archive is written to a temporary file and renamed into place,
+    /// so `output_archive` may be the same path as `input_archive`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `options.split_size` is set;
+    /// the output is always a single file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.recompress("stored.7z", "small.7z", CompressionLevel::Ultra, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn recompress(
+        &self,
+        input_archive: impl AsRef<Path>,
+        output_archive: impl AsRef<Path>,
+        level: CompressionLevel,
+        options: Option<&StreamOptions>,
+    ) -> Result<()> {
+        let output_archive = output_archive.as_ref();
+        if options.is_some_and(|o| o.split_size != 0) {
+            return Err(Error::InvalidParameter(
+                "recompress writes a single file; split_size must be 0".to_string(),
+            ));
+        }
+        let password = options.and_then(|o| o.password.clone());
+
+        let staging = StagingDir::new(output_archive)?;
+        let content_dir = staging.path().join("content");
+        let extract_opts = ExtractOptions {
+            password: password.clone(),
+            restore_mtime: true,
+            ..ExtractOptions::default()
+        };
+        self.extract_with_options(input_archive.as_ref(), &content_dir, &extract_opts, None)?;
+
+        let rebuilt = staging.path().join("rebuilt.7z");
+        self.create_archive_streaming(&rebuilt, &[&content_dir], level, options, None)?;
+
+        std::fs::rename(&rebuilt, output_archive)?;
+        Ok(())
+    }
+
+    /// Set the modification time of every entry to `mtime`
+    ///
+    /// Meant for normalizing timestamps after the fact, e.g. for
+    /// reproducible builds. Only the header is rebuilt: packed data is copied
+    /// byte for byte, never decoded or encoded again, so this is cheap even
+    /// for large archives. Compressed headers (as 7-Zip writes by default)
+    /// are read as well; the new header is written uncompressed.
+    ///
+    /// The new archive is written to a temporary file next to the original
+    /// and renamed over it, so a crash leaves the original intact. A
+    /// recovery record (see [`CompressOptions::recovery_percent`]) is
+    /// computed again for the new archive at the same strength.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `mtime` predates 1601, the
+    /// earliest time 7z can store.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// use std::time::SystemTime;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.set_all_mtimes("release.7z", SystemTime::UNIX_EPOCH, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn set_all_mtimes(
+        &self,
+        archive_path: impl AsRef<Path>,
+        mtime: std::time::SystemTime,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let archive_path = archive_path.as_ref();
+        let filetime = system_time_to_filetime(mtime)?;
+        let recovery_percent = crate::recovery::recovery_percent(archive_path)?;
+
+        let archive = ArchiveHandle::open(archive_path, password)?;
+        let mut folder_files: HashMap<u32, Vec<CopiedFile>> = HashMap::new();
+        let mut empty = Vec::new();
+        for (index, entry) in archive.entries()?.iter().enumerate() {
+            let file = CopiedFile {
+                mtime: Some(filetime),
+                ..copied_file(&archive, entry, index)
+            };
+            match archive.entry_folder(index as u32) {
+                Some(folder) => folder_files.entry(folder).or_default().push(file),
+                None => empty.push(file),
+            }
+        }
+        let mut folders = Vec::new();
+        for folder in 0..archive.folder_count() {
+            if let Some(files) = folder_files.remove(&folder) {
+                folders.push(CopiedFolder {
+                    source: archive_path.to_path_buf(),
+                    folder: archive.raw_folder(folder)?,
+                    files,
+                });
+            }
+        }
+        drop(archive);
+
+        let staging = StagingDir::new(archive_path)?;
+        let rebuilt = staging.path().join("rebuilt.7z");
+        raw_copy::write_archive(&rebuilt, &folders, &empty)?;
+        if let Some(percent) = recovery_percent {
+            crate::recovery::append_recovery_record(&rebuilt, percent)?;
+        }
+        std::fs::rename(&rebuilt, archive_path)?;
+        Ok(())
+    }
+
+    /// Create a standard 7z archive
+    ///
+    /// **WARNING**: This function loads entire files into memory before compression.
+    /// For files larger than ~1GB, use [`create_archive_streaming`](Self::create_archive_streaming)
+    /// instead to avoid memory exhaustion.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Output archive path
+    /// * `input_paths` - Files/directories to compress
+    /// * `level` - Compression level
+    /// * `options` - Optional compression options
+    ///
+    /// # Memory Warning
+    ///
+    /// This function is NOT suitable for large files (82GB evidence directories, etc.).
+    /// It will attempt to load entire files into RAM and may cause system instability.
+    /// Use `create_archive_streaming()` for large files.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, CompressOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut opts = CompressOptions::default();
+    /// opts.num_threads = 4;
+    /// opts.password = Some("secret".to_string());
+    ///
+    /// sz.create_archive(
+    ///     "archive.7z",
+    ///     &["file1.txt", "file2.txt", "directory"],
+    ///     CompressionLevel::Normal,
+    ///     Some(&opts)
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<()> {
+        self.create_archive_at(archive_path.as_ref(), input_paths, level, options)?;
+        Ok(())
+    }
+
+    /// Create a 7z archive from the paths listed in a text file
+    ///
+    /// Like 7-Zip's `-i@list.txt`: one path per line, blank lines and lines
+    /// starting with `#` are skipped. Relative paths are resolved against the
+    /// current directory, not the list file's. Every listed path is checked
+    /// up front and all missing ones are reported in a single
+    /// [`Error::InvalidParameter`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.create_archive_from_listfile("selection.7z", "selection.txt", CompressionLevel::Normal, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive_from_listfile(
+        &self,
+        archive_path: impl AsRef<Path>,
+        list_file: impl AsRef<Path>,
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<()> {
+        let list_file = list_file.as_ref();
+        let contents = std::fs::read_to_string(list_file).map_err(|e| {
+            Error::Io(format!("Cannot read list file {}: {}", list_file.display(), e))
+        })?;
+        let input_paths: Vec<PathBuf> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PathBuf::from)
+            .collect();
+
+        if input_paths.is_empty() {
+            return Err(Error::InvalidParameter(format!(
+                "List file {} names no paths",
+                list_file.display()
+            )));
+        }
+        let missing: Vec<String> = input_paths
+            .iter()
+            .filter(|path| !path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::InvalidParameter(format!(
+                "{} path(s) in {} do not exist: {}",
+                missing.len(),
+                list_file.display(),
+                missing.join(", ")
+            )));
+        }
+
+        self.create_archive(archive_path, &input_paths, level, options)
+    }
+
+    /// [`create_archive`](Self::create_archive), returning the path actually
+    /// written (which [`ExistsPolicy::AppendTimestamp`] may change)
+    fn create_archive_at(
+        &self,
+        archive_path: &Path,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<PathBuf> {
+        let policy = options.map_or(ExistsPolicy::Overwrite, |o| o.if_exists);
+        let archive_path = resolve_existing_archive(archive_path, policy)?;
+        self.write_archive(ArchiveOutput::Path(&archive_path), input_paths, level, options)?;
+
+        if let Some(opts) = options.filter(|o| o.verify_crc_after) {
+            self.verify_sources_unchanged(&archive_path, input_paths, opts)?;
+        }
+        if let Some(percent) = options.and_then(|o| o.recovery_percent) {
+            crate::recovery::append_recovery_record(&archive_path, percent)?;
+        }
+
+        Ok(archive_path)
+    }
+
+    /// Shared body of [`create_archive`](Self::create_archive) and friends:
+    /// tune the options, then compress the inputs into `output`
+    pub(crate) fn write_archive(
+        &self,
+        mut output: ArchiveOutput<'_>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<()> {
+        level.validate()?;
+
+        // Smart defaults: auto-tune if no options provided
+        let mut opts = options.cloned().unwrap_or_default();
+        // Fail fast on typos before the C side has done any work
+        check_input_paths(input_paths, &opts)?;
+        check_encryption(&opts)?;
+        if opts.io_retry.attempts > 0 {
+            return Err(Error::UnsupportedOptionCombination {
+                detail: "io_retry: the C library reads the inputs here and can't retry; use create_tar_7z".to_string(),
+            });
+        }
+        
+        // Check total size and warn if it's large
+        let mut total_size: u64 = 0;
+        if let Some(walk_progress) = &opts.walk_progress {
+            // The caller asked for a full walk, so the real total is known
+            total_size = walk_input_tree(input_paths, opts.recursive, walk_progress);
+        } else {
+            for path in input_paths {
+                if let Ok(metadata) = std::fs::metadata(path.as_ref()) {
+                    if metadata.is_dir() {
+                        // Estimate directory size (walk would be expensive, just warn)
+                        eprintln!("WARNING: create_archive() loads files into memory.");
+                        eprintln!("For large directories, use create_archive_streaming() instead.");
+                    } else {
+                        total_size += metadata.len();
+                    }
+                }
+            }
+        }
+        
+        // Warn if total size exceeds 1GB
+        const MAX_SAFE_SIZE: u64 = 1024 * 1024 * 1024; // 1GB
+        if total_size > MAX_SAFE_SIZE {
+            eprintln!("WARNING: Total input size is {:.2} GB", total_size as f64 / 1e9);
+            eprintln!("This may exhaust system memory. Consider using create_archive_streaming().");
+        }
+        
+        // Auto-tune threads if not explicitly set (num_threads == 0)
+        if opts.num_threads == 0 && total_size > 0 {
+            opts.num_threads = calculate_optimal_threads(total_size);
+        }
+        
+        // Auto-detect incompressible data if enabled and single file
+        let effective_level = if opts.auto_detect_incompressible && input_paths.len() == 1 {
+            let path = input_paths[0].as_ref();
+            if let Ok(metadata) = std::fs::metadata(path) {
+                if metadata.is_file() {
+                    match analyze_file_compressibility(path) {
+                        Ok((entropy, recommended)) if entropy > 0.95 => {
+                            eprintln!("Info: Data appears incompressible (entropy: {:.2}), using Store mode", entropy);
+                            CompressionLevel::Store
+                        },
+                        Ok((entropy, _)) if entropy > 0.85 => {
+                            eprintln!("Info: Low compression potential detected (entropy: {:.2})", entropy);
+                            level
+                        }
+                        _ => level,
+                    }
+                } else {
+                    level
+                }
+            } else {
+                level
+            }
+        } else {
+            level
+        };
+        
+        // Convert input paths to C strings
+        let input_paths_c: Vec<CString> = input_paths
+            .iter()
+            .map(|p| path_to_cstring(p.as_ref()))
+            .collect::<Result<_>>()?;
+        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
+        input_ptrs.push(ptr::null()); // NULL-terminate
+
+        // Convert options to C struct
+        let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
+        let extensions_c: Vec<CString> = opts
+            .method_by_extension
+            .keys()
+            .map(|ext| CString::new(ext.trim_start_matches('.')))
+            .collect::<std::result::Result<_, _>>()?;
+        let extension_ptrs: Vec<*const i8> = extensions_c.iter().map(|s| s.as_ptr()).collect();
+        let extension_methods: Vec<i32> = opts
+            .method_by_extension
+            .values()
+            .map(|&m| ffi::SevenZipMethod::from(m) as i32)
+            .collect();
+        let (property_keys_c, property_values_c): (Vec<CString>, Vec<CString>) = opts
+            .codec_properties
+            .iter()
+            .map(|(key, value)| Ok((CString::new(key.as_str())?, CString::new(value.as_str())?)))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let property_key_ptrs: Vec<*const i8> = property_keys_c.iter().map(|s| s.as_ptr()).collect();
+        let property_value_ptrs: Vec<*const i8> = property_values_c.iter().map(|s| s.as_ptr()).collect();
+        let entry_names_c: Option<Vec<CString>> = input_entry_names(input_paths, &opts)?
+            .map(|names| names.into_iter().map(CString::new).collect::<std::result::Result<_, _>>())
+            .transpose()?;
+        let entry_name_ptrs: Option<Vec<*const i8>> =
+            entry_names_c.as_ref().map(|names| names.iter().map(|s| s.as_ptr()).collect());
+        let c_opts = ffi::SevenZipCompressOptions {
+            num_threads: clamp_threads(opts.num_threads) as i32,
+            dict_size: opts.dict_size,
+            solid: if opts.solid { 1 } else { 0 },
+            password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            skip_empty_dirs: if opts.store_empty_dirs { 0 } else { 1 },
+            method: ffi::SevenZipMethod::from(opts.method) as i32,
+            method_extensions: extension_ptrs.as_ptr(),
+            extension_methods: extension_methods.as_ptr(),
+            method_override_count: extension_ptrs.len(),
+            special_files: ffi::SevenZipSpecialFilePolicy::from(opts.special_files) as i32,
+            time_budget_ms: 0,
+            codec_property_keys: property_key_ptrs.as_ptr(),
+            codec_property_values: property_value_ptrs.as_ptr(),
+            codec_property_count: property_key_ptrs.len(),
+            input_entry_names: entry_name_ptrs.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            flatten_names: if opts.path_mode == PathMode::BasenameOnly { 1 } else { 0 },
+            store_all_times: opts.store_all_times as i32,
+            dedup: opts.dedup as i32,
+            no_recursion: !opts.recursive as i32,
+            limit_file_size: (opts.min_file_size.is_some() || opts.max_file_size.is_some()) as i32,
+            min_file_size: opts.min_file_size.unwrap_or(0),
+            max_file_size: opts.max_file_size.unwrap_or(u64::MAX),
+            sort_order: ffi::SevenZipSortOrder::from(opts.sort_order) as i32,
+            compress_header: opts.compress_header as i32,
+            detect_hardlinks: opts.detect_hardlinks as i32,
+            kdf_iterations: opts.kdf_iterations.unwrap_or(0),
+            encryption_key: opts.encryption_key.as_ref().map_or(ptr::null(), |key| key.as_ptr()),
+        };
+        let mut opts_ptr = Box::new(c_opts);
+        let deadline = opts.time_budget.map(|budget| Instant::now() + budget);
+        let mut effective_level = effective_level;
+        // Resolve an automatic dictionary here, so the encoder never falls
+        // back on its own default; if the inputs can't be walked here, the
+        // C side reports the problem
+        let auto_dict_input = (opts.dict_size == 0)
+            .then(|| source_paths_by_entry_name(input_paths, &opts).ok())
+            .flatten()
+            .map(|files| files.values().filter_map(|path| std::fs::metadata(path).ok()).map(|m| m.len()).sum::<u64>());
+
+        let result = loop {
+            if let Some(total_bytes) = auto_dict_input {
+                opts_ptr.dict_size = opts.resolve_auto_dict(effective_level, total_bytes);
+            }
+            if let Some(deadline) = deadline {
+                // Store never runs the encoder, so it is the unbounded last resort
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    effective_level = CompressionLevel::Store;
+                }
+                opts_ptr.time_budget_ms = match effective_level {
+                    CompressionLevel::Store => 0,
+                    _ => (remaining.as_millis() as u64).max(1),
+                };
+            }
+            unsafe { ffi::sevenzip_clear_last_error() };
+
+            let result = match &mut output {
+                ArchiveOutput::Path(archive_path) => {
+                    let archive_path_c = path_to_cstring(archive_path)?;
+                    unsafe {
+                        ffi::sevenzip_create_7z(
+                            archive_path_c.as_ptr(),
+                            input_ptrs.as_ptr(),
+                            effective_level.into(),
+                            Box::as_ref(&opts_ptr) as *const ffi::SevenZipCompressOptions,
+                            None,
+                            ptr::null_mut(),
+                        )
+                    }
+                }
+                ArchiveOutput::Writer(writer) => {
+                    let mut sink = WriterSink { writer: &mut **writer, error: None };
+                    let result = unsafe {
+                        ffi::sevenzip_create_7z_to_callback(
+                            input_ptrs.as_ptr(),
+                            effective_level.into(),
+                            Box::as_ref(&opts_ptr) as *const ffi::SevenZipCompressOptions,
+                            Some(writer_sink_callback),
+                            &mut sink as *mut WriterSink as *mut std::os::raw::c_void,
+                            None,
+                            ptr::null_mut(),
+                        )
+                    };
+                    // The writer's own error says more than the C error code
+                    if let Some(err) = sink.error {
+                        return Err(err.into());
+                    }
+                    result
+                }
+            };
+
+            // Over budget: retry at the next faster level
+            match effective_level.faster() {
+                Some(faster)
+                    if deadline.is_some()
+                        && result == ffi::SevenZipErrorCode::SEVENZIP_ERROR_CANCELLED =>
+                {
+                    effective_level = faster;
+                }
+                _ => break result,
+            }
+        };
+
+        if result == ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_PARAM {
+            // The C side names the special file or codec property it refused
+            if let Ok(detail) = crate::advanced::DetailedError::get_last() {
+                if !detail.message.is_empty() {
+                    return Err(Error::InvalidParameter(detail.message));
+                }
+            }
+        }
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+
+        Ok(())
+    }
+
+    /// Predict the compressed size of some inputs without writing an archive
+    ///
+    /// Reads the first 1 MiB of each file (at most 32 MiB overall),
+    /// compresses that sample in memory and scales its ratio up to the
+    /// total input size. Archive headers are not included. For homogeneous data the estimate is
+    /// usually within about 10% of the real size; when file heads are
+    /// unrepresentative (e.g. a text header before binary data) it can be
+    /// off by much more, so leave headroom when picking a split size.
+    /// [`CompressionMethod::Copy`] and [`CompressionLevel::Store`] return
+    /// the exact input size.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, CompressionMethod};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let estimate = sz.estimate_compressed_size(&["logs"], CompressionLevel::Normal, CompressionMethod::Lzma2)?;
+    /// println!("About {} MB to upload", estimate / 1_000_000);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn estimate_compressed_size(
+        &self,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        method: CompressionMethod,
+    ) -> Result<u64> {
+        level.validate()?;
+        check_input_paths(input_paths, &CompressOptions::default())?;
+        let mut files: Vec<PathBuf> = source_paths_by_entry_name(input_paths, &CompressOptions::default())?.into_values().collect();
+        files.sort();
+
+        let mut total: u64 = 0;
+        let mut sample = Vec::new();
+        for file in &files {
+            let metadata = std::fs::metadata(file)?;
+            if !metadata.is_file() {
+                continue;
+            }
+            total += metadata.len();
+
+            let room = ESTIMATE_SAMPLE_TOTAL.saturating_sub(sample.len());
+            let take = room.min(ESTIMATE_SAMPLE_PER_FILE) as u64;
+            if take > 0 {
+                std::fs::File::open(file)?.take(take).read_to_end(&mut sample)?;
+            }
+        }
+
+        if method == CompressionMethod::Copy || level == CompressionLevel::Store || sample.is_empty() {
+            return Ok(total);
+        }
+
+        let mut packed: u64 = 0;
+        let result = unsafe {
+            ffi::sevenzip_packed_size_of_buffer(sample.as_ptr(), sample.len(), level.into(), &mut packed)
+        };
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+
+        let ratio = packed as f64 / sample.len() as f64;
+        Ok((total as f64 * ratio).ceil() as u64)
+    }
+
+    /// Create a 7z archive and report what was written
+    ///
+    /// Same as [`create_archive`](Self::create_archive), but returns a
+    /// [`CompressReport`] with entry and byte counts and the number of
+    /// threads the encoder ran with.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let report = sz.create_archive_with_report("archive.7z", &["data"], CompressionLevel::Normal, None)?;
+    /// println!("{} entries using {} threads", report.entries, report.threads_used);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive_with_report(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<CompressReport> {
+        let archive_path = self.create_archive_at(archive_path.as_ref(), input_paths, level, options)?;
+        self.compress_report(&archive_path, input_paths, options)
+    }
+
+    /// Create a self-extracting archive: an SFX stub followed by a 7z archive
+    ///
+    /// `sfx_module` is a 7-Zip SFX module such as `7z.sfx` or `7zSD.sfx`;
+    /// the result runs as a Windows `.exe` that unpacks itself, so the
+    /// recipients need no 7-Zip install. The stub must be a PE executable,
+    /// which catches passing some other file by mistake. 7z offsets are
+    /// relative to the archive's own start, so the appended archive stays
+    /// valid as it is. As with
+    /// [`compress_to_writer`](Self::compress_to_writer), the archive is
+    /// written in one go, so `verify_crc_after` and `recovery_percent` are
+    /// rejected. On error, a newly created `exe_path` is removed again.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.create_sfx("report.exe", &["report"], CompressionLevel::Normal, None, "7z.sfx")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_sfx(
+        &self,
+        exe_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+        sfx_module: impl AsRef<Path>,
+    ) -> Result<()> {
+        use std::io::Write;
+
+        let sfx_module = sfx_module.as_ref();
+        let stub = std::fs::read(sfx_module)?;
+        if !is_pe_executable(&stub) {
+            return Err(Error::InvalidParameter(format!(
+                "Not an SFX module (no PE executable header): {}",
+                sfx_module.display()
+            )));
+        }
+
+        let exe_path = exe_path.as_ref();
+        let guard = PartialOutputGuard::new(exe_path, true);
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(guard.path())?);
+        writer.write_all(&stub)?;
+        self.compress_to_writer(input_paths, level, options, &mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        guard.finish()
+    }
+
+    /// Create a 7z archive and stream it into a writer, e.g. stdout or a pipe
+    ///
+    /// The writer does not need to be seekable. 7z's start header points at
+    /// a header written after the packed data, so the archive is assembled
+    /// (in memory, as with [`create_archive`](Self::create_archive)) before
+    /// any byte is emitted, and then written strictly in order. No temporary
+    /// file is used.
+    ///
+    /// `options.verify_crc_after` and `options.recovery_percent` are not
+    /// supported here, since the output cannot be read back; they are
+    /// rejected with [`Error::UnsupportedOptionCombination`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut stdout = std::io::stdout().lock();
+    /// sz.compress_to_writer(&["data"], CompressionLevel::Normal, None, &mut stdout)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn compress_to_writer<W: std::io::Write>(
+        &self,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+        writer: &mut W,
+    ) -> Result<()> {
+        if options.is_some_and(|o| o.verify_crc_after) {
+            return Err(Error::UnsupportedOptionCombination {
+                detail: "verify_crc_after needs a seekable archive file".to_string(),
+            });
+        }
+        if options.is_some_and(|o| o.recovery_percent.is_some()) {
+            return Err(Error::UnsupportedOptionCombination {
+                detail: "recovery_percent needs a seekable archive file".to_string(),
+            });
+        }
+
+        self.write_archive(ArchiveOutput::Writer(writer), input_paths, level, options)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Create an archive from entries sent through a bounded channel
+    ///
+    /// Each `(name, data)` sent becomes a file entry; names use `/` between
+    /// directories. Once the producer is [`ENTRY_CHANNEL_CAPACITY`] entries
+    /// ahead, `send` blocks until the worker catches up. Dropping the sender
+    /// finalizes the archive, and joining the returned thread gives the
+    /// result. The thread uses its own [`SevenZip`] with the same
+    /// global-init setting as `self`.
+    ///
+    /// The solid block is encoded in one go, so the worker spools entries to
+    /// a staging directory beside the archive as they arrive and compresses
+    /// them after the sender is dropped. The channel thus bounds the memory
+    /// held between producer and disk; the encoding itself runs at the end.
+    /// An invalid or duplicate name ends the worker with
+    /// [`Error::InvalidParameter`], after which `send` fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let (sender, worker) = sz.compress_channel("logs.7z", CompressionLevel::Normal, None);
+    /// for day in 1..=31 {
+    ///     let log = format!("entries for day {}", day).into_bytes();
+    ///     if sender.send((format!("logs/day-{:02}.txt", day), log)).is_err() {
+    ///         break; // the worker failed; join it for the error
+    ///     }
+    /// }
+    /// drop(sender);
+    /// worker.join().expect("compression thread panicked")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn compress_channel(
+        &self,
+        archive_path: impl AsRef<Path>,
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> (EntrySender, std::thread::JoinHandle<Result<()>>) {
+        let archive_path = archive_path.as_ref().to_path_buf();
+        let options = options.cloned();
+        let config = LibConfig { manage_global_init: self.manages_global_init };
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<(String, Vec<u8>)>(ENTRY_CHANNEL_CAPACITY);
+
+        let worker = std::thread::spawn(move || {
+            let sz = SevenZip::new_with_config(&config)?;
+            let staging = StagingDir::new(&archive_path)?;
+            let content_dir = staging.path().join("content");
+            std::fs::create_dir_all(&content_dir)?;
+
+            let mut seen = HashSet::new();
+            for (name, data) in receiver {
+                let relative = normalize_entry_name(&name);
+                if !is_contained_entry_path(&relative) {
+                    return Err(Error::InvalidParameter(format!("Invalid entry name: {}", name)));
+                }
+                if !seen.insert(relative.clone()) {
+                    return Err(Error::InvalidParameter(format!("Duplicate entry name: {}", name)));
+                }
+                let path = content_dir.join(&relative);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, data)?;
+            }
+            if seen.is_empty() {
+                return Err(Error::InvalidParameter("No entries were sent".to_string()));
+            }
+
+            sz.create_archive(&archive_path, &[&content_dir], level, options.as_ref())
+        });
+
+        (sender, worker)
+    }
+
+    /// Build the [`CompressReport`] for an archive just written on this thread
+    pub(crate) fn compress_report(
+        &self,
+        archive_path: &Path,
+        input_paths: &[impl AsRef<Path>],
+        options: Option<&CompressOptions>,
+    ) -> Result<CompressReport> {
+        // The count is thread-local on the C side, so read it before anything
+        // else can start another compression on this thread
+        let reported = unsafe { ffi::sevenzip_last_threads_used() };
+        let peak_memory_bytes = unsafe { ffi::sevenzip_last_peak_memory() };
+        let dict_size = unsafe { ffi::sevenzip_last_dict_size() };
+        let threads_used = if reported > 0 {
+            reported
+        } else {
+            clamp_threads(options.map_or(1, |o| o.num_threads)).max(1) as u32
+        };
+
+        let password = options.and_then(|o| o.password.as_deref());
+        let info = header_summary(archive_path, password)?;
+
+        Ok(CompressReport {
+            entries: info.num_entries,
+            input_bytes: info.unpacked_size,
+            output_bytes: std::fs::metadata(archive_path)?.len(),
+            threads_used,
+            skipped_by_size: options.map_or_else(Vec::new, |opts| files_skipped_by_size(input_paths, opts)),
+            peak_memory_bytes,
+            dict_size,
+        })
+    }
+
+    /// Re-read the sources of a freshly written archive and compare them
+    /// against the stored CRCs (used by [`CompressOptions::verify_crc_after`])
+    pub(crate) fn verify_sources_unchanged(
+        &self,
+        archive_path: &Path,
+        input_paths: &[impl AsRef<Path>],
+        opts: &CompressOptions,
+    ) -> Result<()> {
+        let sources = source_paths_by_entry_name(input_paths, opts)?;
+
+        for entry in self.list(archive_path, opts.password.as_deref())? {
+            if entry.is_directory {
+                continue;
+            }
+            let (Some(stored), Some(source)) = (entry.crc32, sources.get(&entry.name)) else {
+                continue;
+            };
+            if file_crc32(source)? != stored {
+                return Err(Error::SourceChanged {
+                    path: source.display().to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create encrypted archive with recommended settings
+    /// 
+    /// Encryption has virtually zero performance overhead (<1%)
+    /// and provides strong AES-256 security.
+    /// 
+    /// # Example
+    /// 
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// 
+    /// let sz = SevenZip::new()?;
+    /// sz.create_encrypted_archive(
+    ///     "secure.7z",
+    ///     &["sensitive.txt", "private.doc"],
+    ///     "MyStrongPassword123!",
+    ///     seven_zip::CompressionLevel::Normal,
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_encrypted_archive(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        password: &str,
+        level: CompressionLevel,
+    ) -> Result<()> {
+        let file_path_strs: Vec<String> = input_paths
+            .iter()
+            .map(|p| p.as_ref().to_string_lossy().to_string())
+            .collect();
+        let file_paths_refs: Vec<&str> = file_path_strs.iter().map(|s| s.as_str()).collect();
+        
+        let opts = CompressOptions::auto_tuned(&file_paths_refs)
+            .unwrap_or_default()
+            .with_password(password.to_string());
+        
+        self.create_archive(archive_path, input_paths, level, Some(&opts))
+    }
+
+    /// Create archive with smart defaults (auto-tuned threads, incompressible detection)
+    /// 
+    /// # Example
+    /// 
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    /// 
+    /// let sz = SevenZip::new()?;
+    /// sz.create_smart_archive(
+    ///     "backup.7z",
+    ///     &["file1.txt", "file2.bin"],
+    ///     seven_zip::CompressionLevel::Normal,
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_smart_archive(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+    ) -> Result<()> {
+        let file_path_strs: Vec<String> = input_paths
+            .iter()
+            .map(|p| p.as_ref().to_string_lossy().to_string())
+            .collect();
+        let file_paths_refs: Vec<&str> = file_path_strs.iter().map(|s| s.as_str()).collect();
+        
+        let opts = CompressOptions::auto_tuned(&file_paths_refs).unwrap_or_default();
+        self.create_archive(archive_path, input_paths, level, Some(&opts))
+    }
+
+    /// Test archive integrity
+    ///
+    /// Validates CRCs and decompression without writing files.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.test_archive("archive.7z", None)?;
+    /// println!("Archive is valid!");
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn test_archive(&self, archive_path: impl AsRef<Path>, password: Option<&str>) -> Result<()> {
+        check_archive_file(archive_path.as_ref())?;
+        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
+        let password_c = password.map(|p| CString::new(p)).transpose()?;
+
+        unsafe {
+            let result = ffi::sevenzip_test_archive(
+                archive_path_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                None,
+                ptr::null_mut(),
+            );
+
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Test archive integrity, reporting each entry as it is checked
+    ///
+    /// Like [`test_archive`](Self::test_archive), but `on_entry` is called
+    /// after each file entry is decompressed and CRC-checked, with its name
+    /// and whether it passed. Testing continues past a failed entry, so
+    /// every file is reported before the error is returned. Directories
+    /// carry no data and are not reported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.test_archive_with_callback("archive.7z", None, |name, passed| {
+    ///     println!("{} {}", if passed { "OK  " } else { "FAIL" }, name);
+    /// })?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn test_archive_with_callback(
+        &self,
+        archive_path: impl AsRef<Path>,
+        password: Option<&str>,
+        mut on_entry: impl FnMut(&str, bool),
+    ) -> Result<()> {
+        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
+        let password_c = password.map(CString::new).transpose()?;
+        let mut state = TestEntryState { callback: &mut on_entry, panic: None };
+
+        unsafe {
+            let result = ffi::sevenzip_test_archive_entries(
+                archive_path_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                Some(test_entry_callback_wrapper),
+                &mut state as *mut TestEntryState as *mut std::os::raw::c_void,
+            );
+
+            if let Some(payload) = state.panic.take() {
+                std::panic::resume_unwind(payload);
+            }
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cheap structural check of a .7z file without decompressing any data
+    ///
+    /// Verifies the signature header and its CRC, that the header lies
+    /// within the file, the header's CRC, that the header parses, and that
+    /// the packed streams it describes fit before it. This catches truncated
+    /// copies and corrupted headers in a fraction of the time
+    /// [`test_archive`](Self::test_archive) takes, but not damage inside the
+    /// compressed data.
+    ///
+    /// Returns `Ok(false)` for a damaged archive and an error if the file
+    /// can't be read or does not start with the 7z signature at all.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// if !sz.quick_check("backup.7z")? {
+    ///     eprintln!("backup.7z is truncated or its header is damaged");
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn quick_check(&self, archive_path: impl AsRef<Path>) -> Result<bool> {
+        let archive_path = archive_path.as_ref();
+        let Some(header) = read_raw_header(archive_path)? else {
+            return Ok(false);
+        };
+
+        match header_summary(archive_path, None) {
+            Ok(summary) => Ok(summary.packed_size <= header.next_offset),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Compact summary of an archive's structure, for indexing many archives
+    ///
+    /// Only the headers are read. `sha256_of_header` hashes the stored header
+    /// bytes, which hold every name, size, CRC and timestamp, so it changes
+    /// whenever the archive's structure or any entry's content does, without
+    /// hashing the data itself. Archives with encrypted headers can't be
+    /// fingerprinted, since no password is taken.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let fp = sz.fingerprint("evidence.7z")?;
+    /// println!("{} entries, {} bytes, codecs {:?}", fp.entry_count, fp.total_size, fp.codec_set);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn fingerprint(&self, archive_path: impl AsRef<Path>) -> Result<ArchiveFingerprint> {
+        use sha2::{Digest, Sha256};
+
+        let archive_path = archive_path.as_ref();
+        let header = read_raw_header(archive_path)?.ok_or_else(|| {
+            Error::InvalidArchive(format!("Truncated or damaged header: {}", archive_path.display()))
+        })?;
+
+        let archive = ArchiveHandle::open(archive_path, None)?;
+        let entries = archive.entries()?;
+        let mut codec_set = Vec::new();
+        let mut encrypted = false;
+        for index in 0..archive.folder_count() {
+            codec_set.push(archive.folder_info(index)?.codec);
+            encrypted |= archive.folder_encryption(index)?.is_some();
+        }
+        codec_set.sort();
+        codec_set.dedup();
+
+        Ok(ArchiveFingerprint {
+            entry_count: entries.len() as u64,
+            total_size: entries.iter().map(|e| e.size).sum(),
+            codec_set,
+            encrypted,
+            sha256_of_header: Sha256::digest(&header.bytes).into(),
+        })
+    }
+
+    /// Create a 7z archive with streaming compression (supports large files and split archives)
+    ///
+    /// This method is optimized for large files and supports creating split/multi-volume archives.
+    /// Files are processed in chunks to avoid loading entire files into RAM.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Base path for the archive (e.g., "archive.7z")
+    ///                    For split archives, creates archive.7z.001, archive.7z.002, etc.
+    /// * `input_paths` - Files/directories to compress
+    /// * `level` - Compression level
+    /// * `options` - Streaming options (split size, chunk size, etc.)
+    /// * `progress` - Optional byte-level progress callback
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, StreamOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut opts = StreamOptions::default();
+    /// opts.split_size = 4_294_967_296; // 4GB segments
+    /// opts.chunk_size = 67_108_864;     // 64MB chunks
+    /// opts.num_threads = 8;
+    ///
+    /// sz.create_archive_streaming(
+    ///     "large_archive.7z",
+    ///     &["/path/to/large/file.img"],
+    ///     CompressionLevel::Normal,
+    ///     Some(&opts),
+    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
+    ///         println!("Processing {}: {}/{} bytes", filename, file_bytes, file_total);
+    ///         println!("Total: {}/{} bytes", processed, total);
+    ///     }))
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive_streaming(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&StreamOptions>,
+        progress: Option<BytesProgressCallback>,
+    ) -> Result<()> {
+        level.validate()?;
+        if let Some(opts) = options {
+            opts.check_combination(true)?;
+        }
+        
+        // Convert input paths to C strings
+        let input_paths_c: Vec<CString> = input_paths
+            .iter()
+            .map(|p| path_to_cstring(p.as_ref()))
+            .collect::<Result<_>>()?;
+        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
+        input_ptrs.push(ptr::null()); // NULL-terminate
+
+        let cancel = options.and_then(|o| o.cancel.as_ref());
+        check_cancelled(cancel)?;
+        let (progress, panic_slot) = panic_safe_bytes_progress(progress);
+        let progress = cancellable_bytes_progress(progress, cancel);
+        let mut guard = PartialOutputGuard::new(
+            archive_path.as_ref(),
+            options.is_none_or(|o| o.delete_temp_on_error),
+        );
+        if let Some(opts) = options.filter(|o| o.split_size > 0) {
+            guard = guard.in_place(opts.volume_naming);
+        }
+        let archive_path_c = path_to_cstring(guard.path())?;
+        // Boxed so the pointer handed to C stays put
+        let volume_hook = options
+            .and_then(|o| o.on_volume_complete.clone().map(|hook| (hook, o.fsync_on_finish)))
+            .map(|(hook, fsync)| Box::new(VolumeHookState { hook, fsync, outcome: RefCell::new(None) }));
+
+        // Convert options to C struct
+        let (opts_ptr, _password_c, _temp_dir_c) = if let Some(opts) = options {
+            let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
+            let temp_dir_c = opts.temp_dir.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
+            let c_opts = ffi::SevenZipStreamOptions {
+                num_threads: clamp_threads(opts.num_threads) as i32,
+                dict_size: opts.dict_size,
+                solid: if opts.solid { 1 } else { 0 },
+                password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                split_size: opts.split_size,
+                chunk_size: opts.chunk_size,
+                temp_dir: temp_dir_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                delete_temp_on_error: if opts.delete_temp_on_error { 1 } else { 0 },
+                volume_naming: opts.volume_naming.into(),
+                volume_callback: volume_hook.as_ref().map(|_| volume_callback_wrapper as _),
+                volume_user_data: volume_hook
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |state| &**state as *const VolumeHookState as *mut _),
+                temp_file_callback: None,
+                temp_file_user_data: ptr::null_mut(),
+                skip_empty_dirs: if opts.store_empty_dirs { 0 } else { 1 },
+            };
+            (Box::new(c_opts), password_c, temp_dir_c)
+        } else {
+            // Initialize with defaults
+            let mut c_opts = std::mem::MaybeUninit::<ffi::SevenZipStreamOptions>::uninit();
+            unsafe {
+                ffi::sevenzip_stream_options_init(c_opts.as_mut_ptr());
+                (Box::new(c_opts.assume_init()), None, None)
+            }
+        };
+
+        // Set up progress callback
+        let (callback, user_data) = if let Some(cb) = progress {
+            let boxed = Box::new(cb);
+            let raw = Box::into_raw(boxed);
+            (
+                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
+                raw as *mut std::os::raw::c_void,
+            )
+        } else {
+            (None, ptr::null_mut())
+        };
+
+        unsafe {
+            let result = ffi::sevenzip_create_7z_streaming(
+                archive_path_c.as_ptr(),
+                input_ptrs.as_ptr(),
+                level.into(),
+                &*opts_ptr,
+                callback,
+                user_data,
+            );
+
+            // Clean up the callback if it was allocated
+            if !user_data.is_null() {
+                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
+                // Drops automatically
+            }
+
+            // Re-raise a panic from the progress callback; the guard removes
+            // the partial output while unwinding
+            resume_callback_panic(&panic_slot);
+
+            if let Some(outcome) = volume_hook.as_ref().and_then(|state| state.outcome.take()) {
+                match outcome {
+                    HookOutcome::Failed(err) => return Err(err),
+                    HookOutcome::Panicked(payload) => std::panic::resume_unwind(payload),
+                }
+            }
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        guard.finish()?;
+        if let Some(opts) = options.filter(|o| o.fsync_on_finish) {
+            sync_archive_output(archive_path.as_ref(), opts.volume_naming)?;
+        }
+        // Split volumes were reported by the C side as they were finished
+        if let Some(hook) = options.filter(|o| o.split_size == 0).and_then(|o| o.on_volume_complete.as_ref()) {
+            hook.call(archive_path.as_ref(), std::fs::metadata(archive_path.as_ref())?.len())?;
+        }
+        Ok(())
+    }
+
+    /// Extract a 7z archive with streaming decompression and byte-level progress
+    ///
+    /// Automatically handles split/multi-volume archives. For split archives, provide
+    /// the path to the first volume (e.g., "archive.7z.001").
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Path to archive (for splits, use base name like "archive.7z.001")
+    /// * `output_dir` - Directory to extract to
+    /// * `password` - Optional password for encrypted archives
+    /// * `progress` - Optional byte-level progress callback
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_streaming(
+    ///     "archive.7z.001",  // First volume of split archive
+    ///     "output",
+    ///     None,
+    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
+    ///         if total > 0 {
+    ///             let percent = (processed as f64 / total as f64) * 100.0;
+    ///             println!("Extracting {}: {:.1}%", filename, percent);
+    ///         }
+    ///     }))
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_streaming(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+        progress: Option<BytesProgressCallback>,
+    ) -> Result<()> {
+        let archive_path_c = path_to_cstring(archive_path.as_ref())?;
+        let output_dir_c = path_to_cstring(output_dir.as_ref())?;
+        let password_c = password.map(|p| CString::new(p)).transpose()?;
+
+        // Set up progress callback
+        let (callback, user_data) = if let Some(cb) = progress {
+            let boxed = Box::new(cb);
+            let raw = Box::into_raw(boxed);
+            (
+                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
+                raw as *mut std::os::raw::c_void,
+            )
+        } else {
+            (None, ptr::null_mut())
+        };
+
+        unsafe {
+            let result = ffi::sevenzip_extract_streaming(
+                archive_path_c.as_ptr(),
+                output_dir_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                callback,
+                user_data,
+            );
+
+            // Clean up the callback if it was allocated
+            if !user_data.is_null() {
+                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
+                // Drops automatically
+            }
+
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Extract a split archive from an explicit, ordered list of volumes
+    ///
+    /// Unlike [`extract_streaming`](Self::extract_streaming), which finds
+    /// volumes by their `.001`, `.002`, ... suffixes, this reads `volumes`
+    /// back to back in the given order whatever they are called, e.g. parts
+    /// renamed to `part1.bin`, `part2.bin` in transit.
+    ///
+    /// Returns [`Error::InvalidArchive`] if the volumes don't form a valid
+    /// archive, for example because one is missing or they are out of order,
+    /// or if an entry's name would place it outside `output_dir`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_from_volumes(&["part1.bin", "part2.bin", "part3.bin"], "output", None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_from_volumes(
+        &self,
+        volumes: &[impl AsRef<Path>],
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        if volumes.is_empty() {
+            return Err(Error::InvalidParameter("No volumes given".to_string()));
+        }
+
+        let volume_cstrings: Vec<CString> = volumes
+            .iter()
+            .map(|p| path_to_cstring(p.as_ref()))
+            .collect::<Result<_>>()?;
+        let volume_ptrs: Vec<*const std::os::raw::c_char> = volume_cstrings.iter().map(|s| s.as_ptr()).collect();
+        let output_dir_c = path_to_cstring(output_dir.as_ref())?;
+        let password_c = password.map(CString::new).transpose()?;
+
+        let result = unsafe {
+            ffi::sevenzip_extract_volumes(
+                volume_ptrs.as_ptr(),
+                volume_ptrs.len(),
+                output_dir_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                None,
+                ptr::null_mut(),
+            )
+        };
+
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+        Ok(())
+    }
+
+    /// Byte range each volume of a split archive covers in the logical
+    /// archive, i.e. all volumes concatenated in order
+    ///
+    /// `first_volume` is the first volume of a set named in any
+    /// [`VolumeNaming`] scheme (`archive.7z.001`, `archive.part1.7z` or
+    /// `archive.z01`); the following volumes are found by name until one is
+    /// missing. Any other path is taken as a single, unsplit archive. Only
+    /// file sizes are read, so this works before the set is complete, e.g.
+    /// to plan range requests for a parallel download.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for (volume, range) in sz.volume_ranges("backup.7z.001")? {
+    ///     println!("{}: bytes {}..{}", volume.display(), range.start, range.end);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn volume_ranges(&self, first_volume: impl AsRef<Path>) -> Result<Vec<(PathBuf, std::ops::Range<u64>)>> {
+        let first_volume = first_volume.as_ref();
+        let mut volumes = vec![first_volume.to_path_buf()];
+        if let Some((naming, archive_path)) = VolumeNaming::detect(first_volume) {
+            volumes.extend((2..).map(|index| naming.volume_path(&archive_path, index)).take_while(|p| p.exists()));
+            if naming == VolumeNaming::ZipStyle && archive_path.exists() {
+                volumes.push(archive_path);
+            }
+        }
+
+        let mut offset = 0u64;
+        volumes
+            .into_iter()
+            .map(|volume| {
+                let size = std::fs::metadata(&volume)?.len();
+                let range = offset..offset + size;
+                offset += size;
+                Ok((volume, range))
+            })
+            .collect()
+    }
+
+    /// Create a 7z archive using TRUE streaming compression (RECOMMENDED for large archives)
+    ///
+    /// ⚠️ **IMPORTANT**: This method processes files in 64MB chunks WITHOUT loading
+    /// all data into RAM first. Use this for archives larger than 8GB to avoid
+    /// out-of-memory crashes.
+    ///
+    /// The standard `create_archive_streaming` method (when split_size == 0) still
+    /// loads all file data into memory before compression, which causes OOM for
+    /// large archives. This method fixes that limitation.
+    ///
+    /// Memory usage: ~250MB peak regardless of archive size
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Output archive path
+    /// * `input_paths` - Files/directories to compress
+    /// * `level` - Compression level
+    /// * `options` - Streaming options (chunk size, threads, etc.)
+    /// * `progress` - Optional byte-level progress callback
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, StreamOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let mut opts = StreamOptions::default();
+    /// opts.num_threads = 8;
+    /// opts.chunk_size = 64 * 1024 * 1024; // 64MB chunks
+    ///
+    /// // Create 88GB archive without running out of memory
+    /// sz.create_archive_true_streaming(
+    ///     "forensic_evidence.7z",
+    ///     &["/path/to/88gb/evidence/folder"],
+    ///     CompressionLevel::Normal,
+    ///     Some(&opts),
+    ///     Some(Box::new(|processed, total, file_bytes, file_total, filename| {
+    ///         let percent = if total > 0 {
+    ///             (processed as f64 / total as f64) * 100.0
+    ///         } else { 0.0 };
+    ///         println!("[{:.1}%] {} ({}/{} bytes)", percent, filename, file_bytes, file_total);
+    ///     }))
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive_true_streaming(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&StreamOptions>,
+        progress: Option<BytesProgressCallback>,
+    ) -> Result<()> {
+        level.validate()?;
+        if let Some(opts) = options {
+            opts.check_combination(false)?;
+            if opts.password.is_some() {
+                return Err(Error::UnsupportedOptionCombination {
+                    detail: "password is not supported by create_archive_true_streaming; use create_archive_streaming"
+                        .to_string(),
+                });
+            }
+        }
+        
+        // Convert input paths to C strings
+        let input_paths_c: Vec<CString> = input_paths
+            .iter()
+            .map(|p| path_to_cstring(p.as_ref()))
+            .collect::<Result<_>>()?;
+        let mut input_ptrs: Vec<*const i8> = input_paths_c.iter().map(|s| s.as_ptr()).collect();
+        input_ptrs.push(ptr::null()); // NULL-terminate
+
+        let cancel = options.and_then(|o| o.cancel.as_ref());
+        check_cancelled(cancel)?;
+        let (progress, panic_slot) = panic_safe_bytes_progress(progress);
+        let progress = cancellable_bytes_progress(progress, cancel);
+        let guard = PartialOutputGuard::new(
+            archive_path.as_ref(),
+            options.is_none_or(|o| o.delete_temp_on_error),
+        );
+        let archive_path_c = path_to_cstring(guard.path())?;
+        // Boxed so the pointer handed to C stays put
+        let temp_files = options
+            .and_then(|o| o.temp_file_factory.clone())
+            .map(|factory| Box::new(TempFileState { factory, created: RefCell::new(Vec::new()), outcome: RefCell::new(None) }));
+
+        // Convert options to C struct
+        let (opts_ptr, _password_c, _temp_dir_c) = if let Some(opts) = options {
+            let password_c = opts.password.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
+            let temp_dir_c = opts.temp_dir.as_ref().map(|p| CString::new(p.as_str())).transpose()?;
+            let c_opts = ffi::SevenZipStreamOptions {
+                num_threads: clamp_threads(opts.num_threads) as i32,
+                dict_size: opts.dict_size,
+                solid: if opts.solid { 1 } else { 0 },
+                password: password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                split_size: opts.split_size,
+                chunk_size: opts.chunk_size,
+                temp_dir: temp_dir_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                delete_temp_on_error: if opts.delete_temp_on_error { 1 } else { 0 },
+                volume_naming: opts.volume_naming.into(),
+                volume_callback: None,
+                volume_user_data: ptr::null_mut(),
+                temp_file_callback: temp_files.as_ref().map(|_| temp_file_callback_wrapper as _),
+                temp_file_user_data: temp_files
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |state| &**state as *const TempFileState as *mut _),
+                skip_empty_dirs: if opts.store_empty_dirs { 0 } else { 1 },
+            };
             (Box::new(c_opts), password_c, temp_dir_c)
         } else {
-            // Initialize with defaults
-            let mut c_opts = std::mem::MaybeUninit::<ffi::SevenZipStreamOptions>::uninit();
+            // Initialize with defaults
+            let mut c_opts = std::mem::MaybeUninit::<ffi::SevenZipStreamOptions>::uninit();
+            unsafe {
+                ffi::sevenzip_stream_options_init(c_opts.as_mut_ptr());
+                (Box::new(c_opts.assume_init()), None, None)
+            }
+        };
+
+        // Set up progress callback
+        let (callback, user_data) = if let Some(cb) = progress {
+            let boxed = Box::new(cb);
+            let raw = Box::into_raw(boxed);
+            (
+                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
+                raw as *mut std::os::raw::c_void,
+            )
+        } else {
+            (None, ptr::null_mut())
+        };
+
+        unsafe {
+            let result = ffi::sevenzip_create_7z_true_streaming(
+                archive_path_c.as_ptr(),
+                input_ptrs.as_ptr(),
+                level.into(),
+                &*opts_ptr,
+                callback,
+                user_data,
+            );
+
+            // Clean up the callback if it was allocated
+            if !user_data.is_null() {
+                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
+                // Drops automatically
+            }
+
+            // The C side removes its scratch file, except when it fails
+            // before opening it
+            if let Some(state) = temp_files.as_ref() {
+                for (path, file) in state.created.take() {
+                    drop(file);
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+
+            // Re-raise a panic from the progress callback; the guard removes
+            // the partial output while unwinding
+            resume_callback_panic(&panic_slot);
+
+            if let Some(outcome) = temp_files.as_ref().and_then(|state| state.outcome.take()) {
+                match outcome {
+                    HookOutcome::Failed(err) => return Err(err),
+                    HookOutcome::Panicked(payload) => std::panic::resume_unwind(payload),
+                }
+            }
+
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        guard.finish()?;
+        if let Some(opts) = options.filter(|o| o.fsync_on_finish) {
+            sync_archive_output(archive_path.as_ref(), opts.volume_naming)?;
+        }
+        // This writer never splits, so the archive is the only volume
+        if let Some(hook) = options.and_then(|o| o.on_volume_complete.as_ref()) {
+            hook.call(archive_path.as_ref(), std::fs::metadata(archive_path.as_ref())?.len())?;
+        }
+        Ok(())
+    }
+
+    /// Compress a single file to LZMA2 format
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.compress_file("input.txt", "output.lzma2", CompressionLevel::Normal)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn compress_file(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        level: CompressionLevel,
+    ) -> Result<()> {
+        self.compress_file_with_options(input_path, output_path, level, &SingleFileOptions::default())
+    }
+
+    /// Compress a single file to LZMA2 format with custom I/O options
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, SingleFileOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let opts = SingleFileOptions::default().with_buffer_size(8 * 1024 * 1024);
+    /// sz.compress_file_with_options("input.bin", "output.lzma2", CompressionLevel::Normal, &opts)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn compress_file_with_options(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        level: CompressionLevel,
+        options: &SingleFileOptions,
+    ) -> Result<()> {
+        level.validate()?;
+        let input_path_c = path_to_cstring(input_path.as_ref())?;
+        let output_path_c = path_to_cstring(output_path.as_ref())?;
+
+        unsafe {
+            let result = ffi::sevenzip_compress_file_ex(
+                input_path_c.as_ptr(),
+                output_path_c.as_ptr(),
+                level.into(),
+                options.buffer_size,
+                None,
+                ptr::null_mut(),
+            );
+
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decompress a single LZMA2 file
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.decompress_file("input.lzma2", "output.txt")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn decompress_file(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        self.decompress_file_with_options(input_path, output_path, &SingleFileOptions::default(), None)
+    }
+
+    /// Decompress a single LZMA2 file with custom I/O options and progress
+    ///
+    /// The stream stores no uncompressed size, so `progress` is called with
+    /// the bytes written so far as both arguments. A stream that ends before
+    /// its end marker (an incomplete download) or has corrupt data fails with
+    /// [`Error::InvalidArchive`], and the partial output is removed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, SingleFileOptions};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let opts = SingleFileOptions::default().with_buffer_size(8 * 1024 * 1024);
+    /// sz.decompress_file_with_options("input.lzma2", "output.bin", &opts, Some(Box::new(|written, _| {
+    ///     println!("{} bytes", written);
+    /// })))?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn decompress_file_with_options(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        options: &SingleFileOptions,
+        progress: Option<ProgressCallback>,
+    ) -> Result<()> {
+        let input_path_c = path_to_cstring(input_path.as_ref())?;
+        let output_path_c = path_to_cstring(output_path.as_ref())?;
+
+        let (callback, user_data) = if let Some(cb) = progress {
+            let raw = Box::into_raw(Box::new(cb));
+            (
+                Some(progress_callback_wrapper as unsafe extern "C" fn(u64, u64, *mut std::os::raw::c_void)),
+                raw as *mut std::os::raw::c_void,
+            )
+        } else {
+            (None, ptr::null_mut())
+        };
+
+        unsafe {
+            let result = ffi::sevenzip_decompress_file_ex(
+                input_path_c.as_ptr(),
+                output_path_c.as_ptr(),
+                options.buffer_size,
+                callback,
+                user_data,
+            );
+
+            if !user_data.is_null() {
+                drop(Box::from_raw(user_data as *mut ProgressCallback));
+            }
+
+            if result == ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_ARCHIVE {
+                return Err(Error::InvalidArchive(format!(
+                    "Truncated or corrupt LZMA2 stream: {}",
+                    input_path.as_ref().display()
+                )));
+            }
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compress a single file to LZMA2 behind a header recording its size
+    ///
+    /// The 14-byte header holds a magic (`7zL2`), a format version, the
+    /// uncompressed size as a little-endian `u64` and the LZMA2 property
+    /// byte; the stream after it is the same as from
+    /// [`compress_file`](Self::compress_file). Readers can preallocate the
+    /// output, and [`decompress_file_sized`](Self::decompress_file_sized)
+    /// checks that decoding yields exactly that many bytes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.compress_file_sized("disk.img", "disk.img.lzma2", CompressionLevel::Fast)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn compress_file_sized(
+        &self,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        level: CompressionLevel,
+    ) -> Result<()> {
+        level.validate()?;
+        let input_path_c = path_to_cstring(input_path.as_ref())?;
+        let output_path_c = path_to_cstring(output_path.as_ref())?;
+
+        let result = unsafe {
+            ffi::sevenzip_compress_file_sized(
+                input_path_c.as_ptr(),
+                output_path_c.as_ptr(),
+                level.into(),
+                0,
+                None,
+                ptr::null_mut(),
+            )
+        };
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+        Ok(())
+    }
+
+    /// Decompress a file written by [`compress_file_sized`](Self::compress_file_sized)
+    ///
+    /// Fails with [`Error::InvalidArchive`] if the file has no size header,
+    /// or if the stream is corrupt or decodes to a length other than the
+    /// header's, e.g. after a truncated copy. The partial output is removed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.decompress_file_sized("disk.img.lzma2", "disk.img")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn decompress_file_sized(&self, input_path: impl AsRef<Path>, output_path: impl AsRef<Path>) -> Result<()> {
+        let input_path = input_path.as_ref();
+        let mut header = [0u8; SIZED_HEADER_SIZE];
+        let has_header = std::fs::File::open(input_path)?.read_exact(&mut header).is_ok()
+            && header.starts_with(SIZED_MAGIC)
+            && header[4] == SIZED_VERSION;
+        if !has_header {
+            return Err(Error::InvalidArchive(format!(
+                "No sized LZMA2 header: {}",
+                input_path.display()
+            )));
+        }
+        let expected = u64::from_le_bytes(header[5..13].try_into().expect("8 bytes"));
+
+        let input_path_c = path_to_cstring(input_path)?;
+        let output_path_c = path_to_cstring(output_path.as_ref())?;
+        let mut decoded = 0u64;
+        let result = unsafe {
+            ffi::sevenzip_decompress_file_sized(
+                input_path_c.as_ptr(),
+                output_path_c.as_ptr(),
+                0,
+                None,
+                ptr::null_mut(),
+                &mut decoded,
+            )
+        };
+
+        match result {
+            ffi::SevenZipErrorCode::SEVENZIP_OK => Ok(()),
+            ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_ARCHIVE if decoded < expected => {
+                Err(Error::InvalidArchive(format!(
+                    "Stream ends after {} of {} bytes: {}",
+                    decoded,
+                    expected,
+                    input_path.display()
+                )))
+            }
+            ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_ARCHIVE if decoded > expected => {
+                Err(Error::InvalidArchive(format!(
+                    "Stream decodes to more than the {} bytes its header records: {}",
+                    expected,
+                    input_path.display()
+                )))
+            }
+            ffi::SevenZipErrorCode::SEVENZIP_ERROR_INVALID_ARCHIVE => Err(Error::InvalidArchive(format!(
+                "Corrupt LZMA2 stream: {}",
+                input_path.display()
+            ))),
+            code => Err(Error::from_code(code)),
+        }
+    }
+}
+
+impl Drop for SevenZip {
+    fn drop(&mut self) {
+        if self.manages_global_init {
             unsafe {
-                ffi::sevenzip_stream_options_init(c_opts.as_mut_ptr());
-                (Box::new(c_opts.assume_init()), None, None)
+                ffi::sevenzip_cleanup();
+            }
+        }
+    }
+}
+
+// Helper functions
+
+/// FILETIME ticks between 1601-01-01 and the Unix epoch
+const UNIX_EPOCH_TICKS: u64 = 116_444_736_000_000_000;
+
+/// Convert a Windows FILETIME (100ns ticks since 1601-01-01 UTC) to a time
+fn filetime_to_system_time(ticks: u64) -> std::time::SystemTime {
+    let tick = |n: u64| Duration::from_nanos(n.saturating_mul(100));
+    if ticks >= UNIX_EPOCH_TICKS {
+        std::time::UNIX_EPOCH + tick(ticks - UNIX_EPOCH_TICKS)
+    } else {
+        std::time::UNIX_EPOCH - tick(UNIX_EPOCH_TICKS - ticks)
+    }
+}
+
+/// Convert a time to a Windows FILETIME (100ns ticks since 1601-01-01 UTC)
+fn system_time_to_filetime(time: std::time::SystemTime) -> Result<u64> {
+    let out_of_range = || Error::InvalidParameter("Time is outside the 7z timestamp range".to_string());
+    match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(after) => u64::try_from(after.as_nanos() / 100)
+            .ok()
+            .and_then(|ticks| ticks.checked_add(UNIX_EPOCH_TICKS))
+            .ok_or_else(out_of_range),
+        Err(before) => u64::try_from(before.duration().as_nanos().div_ceil(100))
+            .ok()
+            .and_then(|ticks| UNIX_EPOCH_TICKS.checked_sub(ticks))
+            .ok_or_else(out_of_range),
+    }
+}
+
+/// Pre-flight for archive creation: every input must exist, and no two
+/// inputs may end up as the same entry
+///
+/// File inputs are stored under the names [`input_entry_names`] gives them,
+/// or by default under their file name, so two files with the same name from
+/// different directories only collide in the default case.
+/// Reject conflicting secrets and a key derivation round count the archive
+/// can't store
+fn check_encryption(opts: &CompressOptions) -> Result<()> {
+    if opts.password.is_some() && opts.encryption_key.is_some() {
+        return Err(Error::UnsupportedOptionCombination {
+            detail: "password with encryption_key: the key is used as is, so there is nothing to derive".to_string(),
+        });
+    }
+    let Some(iterations) = opts.kdf_iterations else {
+        return Ok(());
+    };
+    if opts.password.is_none() {
+        return Err(Error::InvalidParameter("kdf_iterations is set but there is no password to derive a key from".to_string()));
+    }
+    if !iterations.is_power_of_two() || iterations > 1 << 24 {
+        return Err(Error::InvalidParameter(format!(
+            "kdf_iterations must be a power of two up to 2^24, got {}",
+            iterations
+        )));
+    }
+    Ok(())
+}
+
+fn check_input_paths(input_paths: &[impl AsRef<Path>], opts: &CompressOptions) -> Result<()> {
+    let mut seen_paths = HashSet::new();
+
+    for path in input_paths {
+        let path = path.as_ref();
+        std::fs::metadata(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Error::InvalidParameter(format!("Input path does not exist: {}", path.display()))
+            } else {
+                Error::InvalidParameter(format!("Cannot access input path {}: {}", path.display(), e))
+            }
+        })?;
+
+        if !seen_paths.insert(std::fs::canonicalize(path)?) {
+            return Err(Error::InvalidParameter(format!(
+                "Duplicate input path: {}",
+                path.display()
+            )));
+        }
+    }
+
+    let names = input_entry_names(input_paths, opts)?;
+    let mut stored: HashMap<String, &Path> = HashMap::new();
+    for (i, path) in input_paths.iter().enumerate() {
+        let path = path.as_ref();
+        if !path.is_file() {
+            continue;
+        }
+        let name = match &names {
+            Some(names) => names[i].clone(),
+            None => path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        if let Some(other) = stored.insert(name.clone(), path) {
+            return Err(Error::InvalidParameter(format!(
+                "Input paths {} and {} would both be stored as {}",
+                other.display(),
+                path.display(),
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Magic bytes at the start of every .7z file
+const SEVEN_ZIP_SIGNATURE: [u8; 6] = [0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C];
+
+/// Size of the signature header that precedes the packed streams
+const SIGNATURE_HEADER_SIZE: u64 = 32;
+
+/// First byte of a packed header (`kEncodedHeader`); a plain one starts
+/// with `kHeader` (0x01)
+const ENCODED_HEADER_ID: u8 = 0x17;
+
+/// Newest 7z format version (major, minor) the bundled LZMA SDK reads, as
+/// written by current 7-Zip; the SDK rejects any other major version
+const SUPPORTED_FORMAT_VERSION: (u8, u8) = (0, 4);
+
+/// Entries fetched per batch by [`SevenZip::for_each_entry`]
+const ENTRY_BATCH_SIZE: u32 = 4096;
+
+/// Bytes read from the start of each file by [`SevenZip::estimate_compressed_size`]
+const ESTIMATE_SAMPLE_PER_FILE: usize = 1024 * 1024;
+
+/// Cap on the total sample [`SevenZip::estimate_compressed_size`] compresses
+const ESTIMATE_SAMPLE_TOTAL: usize = 32 * 1024 * 1024;
+
+/// `SEVENZIP_SIZED_MAGIC`, `SEVENZIP_SIZED_VERSION` and
+/// `SEVENZIP_SIZED_HEADER_SIZE`, see [`SevenZip::compress_file_sized`]
+const SIZED_MAGIC: &[u8; 4] = b"7zL2";
+const SIZED_VERSION: u8 = 1;
+const SIZED_HEADER_SIZE: usize = 14;
+
+/// Entries [`SevenZip::compress_channel`] buffers before `send` blocks
+pub const ENTRY_CHANNEL_CAPACITY: usize = 4;
+
+/// Sending half of [`SevenZip::compress_channel`]: `(entry name, data)`
+pub type EntrySender = SyncSender<(String, Vec<u8>)>;
+
+/// Distinguishes concurrent [`SevenZip::extract_from_reader`] spool files
+static SPOOL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Files between two [`WalkProgress`] reports
+const WALK_PROGRESS_INTERVAL: u64 = 1000;
+
+/// Count the files under `input_paths`, reporting progress, and return their
+/// total size. Unreadable entries are skipped; the C walker reports them.
+fn walk_input_tree(input_paths: &[impl AsRef<Path>], recursive: bool, progress: &WalkProgress) -> u64 {
+    let mut files_seen: u64 = 0;
+    let mut total_bytes: u64 = 0;
+    // Each path with whether it was given as an input
+    let mut pending: Vec<(PathBuf, bool)> = input_paths.iter().map(|p| (p.as_ref().to_path_buf(), true)).collect();
+
+    while let Some((path, is_input)) = pending.pop() {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            let dir = std::fs::read_dir(&path).ok().filter(|_| recursive || is_input);
+            pending.extend(dir.into_iter().flatten().filter_map(|e| e.ok()).map(|e| (e.path(), false)));
+            continue;
+        }
+
+        files_seen += 1;
+        total_bytes += metadata.len();
+        if files_seen.is_multiple_of(WALK_PROGRESS_INTERVAL) {
+            progress.report(files_seen);
+        }
+    }
+
+    progress.report(files_seen);
+    total_bytes
+}
+
+/// The regular files among the inputs that the size limits leave out, found
+/// by the same walk as the C writer's
+fn files_skipped_by_size(input_paths: &[impl AsRef<Path>], opts: &CompressOptions) -> Vec<PathBuf> {
+    if opts.min_file_size.is_none() && opts.max_file_size.is_none() {
+        return Vec::new();
+    }
+    let mut skipped = Vec::new();
+    let mut pending: Vec<(PathBuf, bool)> = input_paths.iter().map(|p| (p.as_ref().to_path_buf(), true)).collect();
+    while let Some((path, is_input)) = pending.pop() {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            let dir = std::fs::read_dir(&path).ok().filter(|_| opts.recursive || is_input);
+            pending.extend(dir.into_iter().flatten().filter_map(|e| e.ok()).map(|e| (e.path(), false)));
+        } else if metadata.is_file() && !opts.keeps_file_size(metadata.len()) {
+            skipped.push(path);
+        }
+    }
+    skipped.sort();
+    skipped
+}
+
+/// Map archive entry names to the source files they were read from, using the
+/// same naming rules as the C writer (by default files by basename,
+/// directories by their contents relative to the directory itself)
+fn source_paths_by_entry_name(
+    input_paths: &[impl AsRef<Path>],
+    opts: &CompressOptions,
+) -> Result<HashMap<String, PathBuf>> {
+    fn walk(dir: &Path, base: &Path, prefix: &str, opts: &CompressOptions, out: &mut HashMap<String, PathBuf>) -> Result<()> {
+        let flatten = opts.path_mode == PathMode::BasenameOnly;
+        for dirent in std::fs::read_dir(dir)? {
+            let path = dirent?.path();
+            if path.is_dir() {
+                if opts.recursive {
+                    walk(&path, base, prefix, opts, out)?;
+                }
+            } else if flatten {
+                if let Some(name) = path.file_name() {
+                    out.insert(name.to_string_lossy().into_owned(), path.clone());
+                }
+            } else if let Ok(rel) = path.strip_prefix(base) {
+                out.insert(format!("{}{}", prefix, rel.to_string_lossy()), path.clone());
+            }
+        }
+        Ok(())
+    }
+
+    let names = input_entry_names(input_paths, opts)?;
+    let mut out = HashMap::new();
+    for (i, input) in input_paths.iter().enumerate() {
+        let input = input.as_ref();
+        let given = names.as_ref().map(|n| n[i].as_str());
+        if input.is_dir() {
+            let prefix = match given {
+                Some(name) if !name.is_empty() => format!("{}/", name),
+                _ => String::new(),
+            };
+            walk(input, input, &prefix, opts, &mut out)?;
+        } else if let Some(name) = given {
+            out.insert(name.to_string(), input.to_path_buf());
+        } else if let Some(name) = input.file_name() {
+            out.insert(name.to_string_lossy().into_owned(), input.to_path_buf());
+        }
+    }
+    Ok(out)
+}
+
+/// Stored name for each input under [`PathMode::Relative`] or
+/// [`CompressOptions::preserve_root_names`]: a file's entry name, or the
+/// prefix for a directory's contents (empty for the base itself). `None`
+/// otherwise, when the C writer's default naming applies.
+fn input_entry_names(input_paths: &[impl AsRef<Path>], opts: &CompressOptions) -> Result<Option<Vec<String>>> {
+    let base = match &opts.path_mode {
+        PathMode::Relative { base } => base,
+        PathMode::AsGiven if opts.preserve_root_names => return unique_root_names(input_paths).map(Some),
+        _ => return Ok(None),
+    };
+    let base = std::path::absolute(base)?;
+    input_paths
+        .iter()
+        .map(|input| {
+            let input = input.as_ref();
+            let full = std::path::absolute(input)?;
+            let rel = full
+                .strip_prefix(&base)
+                .ok()
+                .filter(|rel| !rel.as_os_str().is_empty() || input.is_dir())
+                .ok_or_else(|| {
+                    Error::InvalidParameter(format!(
+                        "{} is not under the base directory {}",
+                        input.display(),
+                        base.display()
+                    ))
+                })?;
+            let parts: Vec<_> = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            Ok(parts.join("/"))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Each input's own name, numbered from `_2` on where names repeat (see
+/// [`CompressOptions::preserve_root_names`])
+fn unique_root_names(input_paths: &[impl AsRef<Path>]) -> Result<Vec<String>> {
+    let mut used = HashSet::new();
+    let mut names = Vec::with_capacity(input_paths.len());
+    for input in input_paths {
+        let input = input.as_ref();
+        let name = std::path::absolute(input)?
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if dot > 0 && !input.is_dir() => name.split_at(dot),
+            _ => (name.as_str(), ""),
+        };
+
+        let mut unique = name.clone();
+        let mut n = 2;
+        while !unique.is_empty() && !used.insert(unique.clone()) {
+            unique = format!("{}_{}{}", stem, n, extension);
+            n += 1;
+        }
+        names.push(unique);
+    }
+    Ok(names)
+}
+
+/// Select the entries directly under `dir`, synthesizing missing directories
+fn direct_children(entries: Vec<ArchiveEntry>, dir: &str) -> Vec<ArchiveEntry> {
+    let dir = dir.replace('\\', "/");
+    let dir = dir.trim_matches('/');
+    let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+
+    let mut children: Vec<ArchiveEntry> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for mut entry in entries {
+        let name = entry.name.replace('\\', "/");
+        let Some(rest) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let rest = rest.trim_end_matches('/');
+        if rest.is_empty() {
+            continue;
+        }
+
+        match rest.split_once('/') {
+            // Direct child: keep it, replacing any placeholder synthesized earlier
+            None => {
+                entry.name = format!("{}{}", prefix, rest);
+                match seen.get(&entry.name) {
+                    Some(&idx) => children[idx] = entry,
+                    None => {
+                        seen.insert(entry.name.clone(), children.len());
+                        children.push(entry);
+                    }
+                }
+            }
+            // Deeper descendant: make sure its top-level directory is listed
+            Some((child, _)) => {
+                let child_name = format!("{}{}", prefix, child);
+                if !seen.contains_key(&child_name) {
+                    seen.insert(child_name.clone(), children.len());
+                    children.push(ArchiveEntry {
+                        raw_name: child_name.clone().into_bytes(),
+                        name: child_name,
+                        size: 0,
+                        packed_size: 0,
+                        modified_time: 0,
+                        attributes: 0,
+                        is_directory: true,
+                        crc32: None,
+                        method: String::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    children
+}
+
+/// Set extracted files' modification times from their archive entries
+///
+/// Directories are handled last, deepest first, since writing their contents
+/// would otherwise bump the restored time again.
+fn restore_mtimes(output_dir: &Path, entries: &[ArchiveEntry]) -> Result<()> {
+    let (mut dirs, files): (Vec<&ArchiveEntry>, Vec<&ArchiveEntry>) =
+        entries.iter().partition(|e| e.is_directory);
+    dirs.sort_by_key(|e| std::cmp::Reverse(e.name.matches('/').count()));
+
+    for entry in files.into_iter().chain(dirs) {
+        if entry.modified_time == 0 {
+            continue;
+        }
+        let Some(path) = contained_output_path(output_dir, &entry.name) else {
+            continue;
+        };
+        if path.exists() {
+            let mtime = filetime::FileTime::from_unix_time(entry.modified_time as i64, 0);
+            filetime::set_file_mtime(&path, mtime)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decompress one file entry to `disk_path`, checking its stored CRC
+///
+/// A hard link entry's CRC covers its tag, not the target's content it reads
+/// as, so it is left to the check the decoder already made.
+fn write_entry(archive: &mut ArchiveHandle, index: u32, entry: &ArchiveEntry, disk_path: &Path) -> Result<()> {
+    let data = archive.read(index)?;
+    if !entry.is_hardlink() && entry.crc32.is_some_and(|crc| crc32fast::hash(data) != crc) {
+        return Err(Error::Extract(format!("CRC mismatch: {}", entry.name)));
+    }
+    if let Some(parent) = disk_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(disk_path, data)?;
+    Ok(())
+}
+
+/// Set extracted files' access, modification and (on Windows) creation
+/// times from their archive entries, in the same order as [`restore_mtimes`]
+fn restore_all_times(output_dir: &Path, entries: &[ArchiveEntryFull]) -> Result<()> {
+    let (mut dirs, files): (Vec<&ArchiveEntryFull>, Vec<&ArchiveEntryFull>) =
+        entries.iter().partition(|e| e.entry.is_directory);
+    dirs.sort_by_key(|e| std::cmp::Reverse(e.entry.name.matches('/').count()));
+
+    for full in files.into_iter().chain(dirs) {
+        let Some(path) = contained_output_path(output_dir, &full.entry.name) else {
+            continue;
+        };
+        if !path.exists() {
+            continue;
+        }
+        let atime = full.accessed.map(filetime::FileTime::from_system_time);
+        let mtime = full.modified.map(filetime::FileTime::from_system_time);
+        match (atime, mtime) {
+            (Some(atime), Some(mtime)) => filetime::set_file_times(&path, atime, mtime)?,
+            (Some(atime), None) => filetime::set_file_atime(&path, atime)?,
+            (None, Some(mtime)) => filetime::set_file_mtime(&path, mtime)?,
+            (None, None) => {}
+        }
+        #[cfg(windows)]
+        if let Some(created) = full.created {
+            set_creation_time(&path, created)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set a file's or directory's creation time (Unix has no settable equivalent)
+#[cfg(windows)]
+fn set_creation_time(path: &Path, created: std::time::SystemTime) -> Result<()> {
+    use std::os::windows::fs::{FileTimesExt, OpenOptionsExt};
+    /// Lets `CreateFileW` open directories as well as files
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    file.set_times(std::fs::FileTimes::new().set_created(created))?;
+    Ok(())
+}
+
+/// Where to write an archive under `policy`, given that `archive_path` may exist
+fn resolve_existing_archive(archive_path: &Path, policy: ExistsPolicy) -> Result<PathBuf> {
+    if !archive_path.exists() {
+        return Ok(archive_path.to_path_buf());
+    }
+    match policy {
+        ExistsPolicy::Overwrite => Ok(archive_path.to_path_buf()),
+        ExistsPolicy::Fail => Err(Error::AlreadyExists {
+            path: archive_path.display().to_string(),
+        }),
+        ExistsPolicy::AppendTimestamp => {
+            let file_name = archive_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            // Insert before the first extension, so backup.tar.7z keeps .tar.7z
+            let split = file_name.get(1..).and_then(|rest| rest.find('.')).map_or(file_name.len(), |i| i + 1);
+            let (stem, extension) = file_name.split_at(split);
+            let stamp = utc_timestamp(std::time::SystemTime::now());
+
+            let mut candidate = archive_path.with_file_name(format!("{}-{}{}", stem, stamp, extension));
+            let mut n = 2;
+            while candidate.exists() {
+                candidate = archive_path.with_file_name(format!("{}-{}-{}{}", stem, stamp, n, extension));
+                n += 1;
+            }
+            Ok(candidate)
+        }
+    }
+}
+
+/// Format a time as `YYYYMMDD-HHMMSS` in UTC
+fn utc_timestamp(time: std::time::SystemTime) -> String {
+    let secs = time.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, day_secs) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days, Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        day_secs / 3_600,
+        day_secs / 60 % 60,
+        day_secs % 60
+    )
+}
+
+/// Fail fast with [`Error::OutputNotWritable`] unless `output_dir` exists
+/// (after creating it, if `create`) and accepts new files
+///
+/// On Unix, a directory without write bits counts as not writable even where
+/// the process could write anyway (e.g. as root), matching how read-only
+/// files are treated. Windows ignores the read-only attribute on directories,
+/// so there only the probe file, created and removed again, decides.
+fn check_output_dir(output_dir: &Path, create: bool) -> Result<()> {
+    let not_writable = || Error::OutputNotWritable { path: output_dir.display().to_string() };
+
+    if create && std::fs::create_dir_all(output_dir).is_err() {
+        return Err(not_writable());
+    }
+    let metadata = std::fs::metadata(output_dir).map_err(|_| not_writable())?;
+    if !metadata.is_dir() || (cfg!(unix) && metadata.permissions().readonly()) {
+        return Err(not_writable());
+    }
+
+    let probe = output_dir.join(format!(".7z-write-probe-{}", std::process::id()));
+    std::fs::File::create(&probe).map_err(|_| not_writable())?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// `file_name` without its archive extensions: a volume number, then `.7z`,
+/// then `.tar`, each only if present
+fn archive_stem(file_name: &str) -> &str {
+    let mut stem = file_name;
+    if let Some((rest, ext)) = stem.rsplit_once('.') {
+        if !ext.is_empty() && ext.bytes().all(|b| b.is_ascii_digit()) {
+            stem = rest;
+        }
+    }
+    for archive_ext in ["7z", "tar"] {
+        if let Some((rest, ext)) = stem.rsplit_once('.') {
+            if ext.eq_ignore_ascii_case(archive_ext) {
+                stem = rest;
+            }
+        }
+    }
+    stem
+}
+
+/// Where `name` is extracted under `output_dir`, or `None` if the name would
+/// leave it
+fn contained_output_path(output_dir: &Path, name: &str) -> Option<PathBuf> {
+    let relative = normalize_entry_name(name);
+    is_contained_entry_path(&relative).then(|| output_dir.join(relative))
+}
+
+/// Whether a normalized entry name is a non-empty relative path that stays
+/// inside the output directory (no `..`, no root)
+fn is_contained_entry_path(relative: &str) -> bool {
+    !relative.is_empty()
+        && Path::new(relative)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+/// Longest file name component most filesystems accept, in bytes
+const MAX_NAME_COMPONENT_LEN: usize = 255;
+
+/// Longest output path the host accepts without special handling, in bytes
+const MAX_OUTPUT_PATH_LEN: usize = if cfg!(windows) { 260 } else { 4096 };
+
+/// Characters Windows filesystems reject in file names
+const WINDOWS_INVALID_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// The [`ExtractWarning`]s for extracting `entry` into `target_dir`
+fn extract_warnings(target_dir: &Path, entry: &ArchiveEntry) -> Vec<ExtractWarning> {
+    let name = || entry.name.clone();
+    let relative = entry.name.replace('\\', "/");
+    if relative.starts_with('/') || !is_contained_entry_path(relative.trim_end_matches('/')) {
+        return vec![ExtractWarning::PathTraversal { name: name() }];
+    }
+
+    let mut warnings = Vec::new();
+    if let Some(ch) = relative
+        .chars()
+        .find(|c| WINDOWS_INVALID_CHARS.contains(c) || c.is_control())
+    {
+        warnings.push(ExtractWarning::InvalidChar { name: name(), ch });
+    }
+    if relative.split('/').any(is_windows_reserved_component) {
+        warnings.push(ExtractWarning::ReservedName { name: name() });
+    }
+
+    let path = target_dir.join(relative.trim_end_matches('/'));
+    let longest = relative.split('/').map(str::len).max().unwrap_or(0);
+    let path_len = path.as_os_str().len();
+    if longest > MAX_NAME_COMPONENT_LEN {
+        warnings.push(ExtractWarning::NameTooLong { name: name(), length: longest, limit: MAX_NAME_COMPONENT_LEN });
+    } else if path_len > MAX_OUTPUT_PATH_LEN {
+        warnings.push(ExtractWarning::NameTooLong { name: name(), length: path_len, limit: MAX_OUTPUT_PATH_LEN });
+    }
+
+    if !entry.is_directory && std::fs::symlink_metadata(&path).is_ok_and(|m| !m.is_dir()) {
+        warnings.push(ExtractWarning::WouldOverwrite { name: name(), path });
+    }
+    warnings
+}
+
+/// Device names Windows reserves in every directory, with any extension
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether Windows can't create a file or directory named `component`: a
+/// reserved device name, alone or with an extension, or a trailing dot or space
+fn is_windows_reserved_component(component: &str) -> bool {
+    if component == "." || component == ".." {
+        return false;
+    }
+    let stem = component.split('.').next().unwrap_or_default().trim_end_matches(' ');
+    WINDOWS_RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) || component.ends_with(['.', ' '])
+}
+
+/// Safe equivalent of a component [`is_windows_reserved_component`] rejects
+fn sanitize_windows_component(component: &str) -> String {
+    if !is_windows_reserved_component(component) {
+        return component.to_string();
+    }
+    let trimmed = component.trim_end_matches(['.', ' ']);
+    let (stem, extension) = trimmed.split_at(trimmed.find('.').unwrap_or(trimmed.len()));
+    if WINDOWS_RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem.trim_end_matches(' '))) {
+        format!("{}_{}", stem, extension)
+    } else {
+        format!("{}_", trimmed)
+    }
+}
+
+/// On Windows, check entry names for ones Windows can't create and, with
+/// [`ExtractOptions::sanitize_names`], rename them, reporting each rename;
+/// returns the entries and whether any was renamed. Elsewhere all names pass.
+fn sanitize_reserved_names(
+    mut entries: Vec<ArchiveEntry>,
+    output_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<(Vec<ArchiveEntry>, bool)> {
+    if !cfg!(windows) {
+        return Ok((entries, false));
+    }
+
+    let mut renamed = false;
+    for entry in &mut entries {
+        let name = normalize_entry_name(&entry.name);
+        if !name.split('/').any(is_windows_reserved_component) {
+            continue;
+        }
+        if !options.sanitize_names {
+            return Err(Error::ReservedName { name: entry.name.clone() });
+        }
+        let safe = name.split('/').map(sanitize_windows_component).collect::<Vec<_>>().join("/");
+        if let Some(hook) = &options.on_rename {
+            hook.call(&entry.name, &output_dir.join(&safe));
+        }
+        entry.name = safe;
+        renamed = true;
+    }
+    Ok((entries, renamed))
+}
+
+/// Groups of file entries whose names differ only in case, each listing
+/// entry indexes in archive order; names leaving the output directory are
+/// left out, since they are never extracted
+fn case_collisions(entries: &[ArchiveEntry]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<String, usize> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate().filter(|(_, e)| !e.is_directory) {
+        let name = normalize_entry_name(&entry.name);
+        if !is_contained_entry_path(&name) {
+            continue;
+        }
+        match group_of.entry(name.to_lowercase()) {
+            std::collections::hash_map::Entry::Occupied(slot) => groups[*slot.get()].push(index),
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(groups.len());
+                groups.push(vec![index]);
+            }
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// `name` with ` (n)` inserted before the extension of its last component
+fn numbered_name(name: &str, n: usize) -> String {
+    let (dir, file) = name.rsplit_once('/').map_or(("", name), |(dir, file)| (dir, file));
+    let sep = if dir.is_empty() { "" } else { "/" };
+    match file.rfind('.').filter(|&dot| dot > 0) {
+        Some(dot) => format!("{}{}{} ({}){}", dir, sep, &file[..dot], n, &file[dot..]),
+        None => format!("{}{}{} ({})", dir, sep, file, n),
+    }
+}
+
+/// Whether `dir` is on a case-insensitive filesystem, found by creating a
+/// lowercase probe file there and looking it up in upper case
+fn is_case_insensitive_dir(dir: &Path) -> Result<bool> {
+    let probe_name = format!(".7z-case-probe-{}-{}", std::process::id(), SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed));
+    let probe = dir.join(&probe_name);
+    std::fs::File::create(&probe)?;
+    let insensitive = dir.join(probe_name.to_uppercase()).exists();
+    std::fs::remove_file(&probe)?;
+    Ok(insensitive)
+}
+
+/// With [`ExtractOptions::restore_hardlinks`], replace the copies extracted
+/// for hard link entries by links to their targets
+fn link_hardlinks(
+    archive_path: &Path,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    entries: &[ArchiveEntry],
+) -> Result<()> {
+    if !options.restore_hardlinks || !entries.iter().any(ArchiveEntry::is_hardlink) {
+        return Ok(());
+    }
+    let mut archive = ArchiveHandle::open_for_extract(archive_path, options)?;
+
+    for (index, entry) in entries.iter().enumerate().filter(|(_, e)| e.is_hardlink()) {
+        let Some(target) = archive.hardlink_target(index as u32)? else {
+            continue;
+        };
+        let (Some(target), Some(link)) = (
+            contained_output_path(output_dir, &entries[target as usize].name),
+            contained_output_path(output_dir, &entry.name),
+        ) else {
+            return Err(Error::InvalidArchive(format!("Hard link leaves the output directory: {}", entry.name)));
+        };
+        match std::fs::remove_file(&link) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        std::fs::hard_link(&target, &link)?;
+    }
+    Ok(())
+}
+
+/// Undo the overwrites among case-colliding entries after an extraction
+/// onto a case-insensitive filesystem, where each group landed in one file
+///
+/// The later entries of each group are written again under
+/// [`numbered_name`]s that no other entry uses, then the first entry is
+/// rewritten over the shared file.
+///
+/// `entries` may have been renamed; `collisions` refer to the renamed
+/// entries by index.
+fn separate_case_collisions(
+    archive_path: &Path,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    entries: &[ArchiveEntry],
+    collisions: &[Vec<usize>],
+) -> Result<()> {
+    let mut archive = ArchiveHandle::open_for_extract(archive_path, options)?;
+    let mut taken: HashSet<String> = entries.iter().map(|e| normalize_entry_name(&e.name).to_lowercase()).collect();
+
+    for group in collisions {
+        let mut written = Vec::with_capacity(group.len());
+        let mut n = 0;
+        for &index in &group[1..] {
+            let name = normalize_entry_name(&entries[index].name);
+            let renamed = loop {
+                n += 1;
+                let candidate = numbered_name(&name, n);
+                if taken.insert(candidate.to_lowercase()) {
+                    break candidate;
+                }
+            };
+            written.push((index, renamed));
+        }
+        written.push((group[0], normalize_entry_name(&entries[group[0]].name)));
+
+        for (index, name) in written {
+            let entry = &entries[index];
+            let path = output_dir.join(&name);
+            write_entry(&mut archive, index as u32, entry, &path)?;
+            if (options.restore_mtime || options.restore_all_times) && entry.modified_time != 0 {
+                filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(entry.modified_time as i64, 0))?;
             }
+        }
+    }
+    Ok(())
+}
+
+/// Check the files extraction would overwrite for read-only ones
+///
+/// With `force`, read-only files are made writable (on Unix, by adding the
+/// owner write bit); otherwise the first one found is an error naming it,
+/// rather than an OS permission error halfway through the extraction.
+fn prepare_readonly_targets(output_dir: &Path, entries: &[ArchiveEntry], force: bool) -> Result<()> {
+    for entry in entries.iter().filter(|e| !e.is_directory) {
+        // Extraction never writes outside `output_dir`, so nothing there is a target
+        let Some(path) = contained_output_path(output_dir, &entry.name) else {
+            continue;
         };
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+        let mut permissions = metadata.permissions();
+        if !permissions.readonly() {
+            continue;
+        }
+        if !force {
+            return Err(Error::Extract(format!(
+                "Cannot overwrite read-only file: {}",
+                path.display()
+            )));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(permissions.mode() | 0o200);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&path, permissions)?;
+    }
+    Ok(())
+}
 
-        // Set up progress callback
-        let (callback, user_data) = if let Some(cb) = progress {
-            let boxed = Box::new(cb);
-            let raw = Box::into_raw(boxed);
-            (
-                Some(bytes_progress_callback_wrapper as unsafe extern "C" fn(u64, u64, u64, u64, *const std::os::raw::c_char, *mut std::os::raw::c_void)),
-                raw as *mut std::os::raw::c_void,
+/// Compute the CRC32 of a file's current contents
+fn file_crc32(path: &Path) -> Result<u32> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Convert a C entry list into owned entries and free it
+///
+/// # Safety
+///
+/// `list_ptr` must be null or a list returned by the C library that has not
+/// been freed yet.
+unsafe fn take_entry_list(list_ptr: *mut ffi::SevenZipList) -> Vec<ArchiveEntry> {
+    unsafe {
+        if list_ptr.is_null() {
+            return Vec::new();
+        }
+
+        let list = &*list_ptr;
+        let mut entries = Vec::with_capacity(list.count);
+
+        for i in 0..list.count {
+            let entry = &*list.entries.add(i);
+            let raw_name = if entry.name.is_null() {
+                Vec::new()
+            } else {
+                CStr::from_ptr(entry.name).to_bytes().to_vec()
+            };
+
+            entries.push(ArchiveEntry {
+                name: String::from_utf8_lossy(&raw_name).into_owned(),
+                raw_name,
+                size: entry.size,
+                packed_size: entry.packed_size,
+                modified_time: entry.modified_time,
+                attributes: entry.attributes,
+                is_directory: entry.is_directory != 0,
+                crc32: if entry.has_crc != 0 { Some(entry.crc) } else { None },
+                method: CStr::from_ptr(entry.method.as_ptr()).to_string_lossy().into_owned(),
+            });
+        }
+
+        ffi::sevenzip_free_list(list_ptr);
+        entries
+    }
+}
+
+/// Archive-level totals from the header, via the C API
+fn header_summary(archive_path: &Path, password: Option<&str>) -> Result<ffi::SevenZipArchiveInfo> {
+    check_archive_file(archive_path)?;
+    let archive_path_c = path_to_cstring(archive_path)?;
+    let password_c = password.map(CString::new).transpose()?;
+    let mut info = ffi::SevenZipArchiveInfo::default();
+
+    unsafe {
+        let result = ffi::sevenzip_get_archive_info(
+            archive_path_c.as_ptr(),
+            password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            &mut info,
+        );
+
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(open_error(archive_path, result));
+        }
+    }
+
+    Ok(info)
+}
+
+/// Catch the two usual wrong archive paths before the C reader turns them
+/// into a bare [`Error::InvalidArchive`]: a directory and an empty file
+///
+/// Paths that can't be inspected are left for the reader to report.
+fn check_archive_file(archive_path: &Path) -> Result<()> {
+    let path = || archive_path.display().to_string();
+    match std::fs::metadata(archive_path) {
+        Ok(metadata) if metadata.is_dir() => Err(Error::NotAFile { path: path() }),
+        Ok(metadata) if metadata.is_file() && metadata.len() == 0 => Err(Error::EmptyArchive { path: path() }),
+        _ => Ok(()),
+    }
+}
+
+/// The error for a failed attempt to open `archive_path`: the C side only
+/// says the archive is invalid, so look for an unsupported format version
+/// behind it before falling back to `code`
+fn open_error(archive_path: &Path, code: ffi::SevenZipErrorCode) -> Error {
+    let mut start = [0u8; 8];
+    let read = std::fs::File::open(archive_path).and_then(|mut f| f.read_exact(&mut start));
+    if read.is_ok() && start.starts_with(&SEVEN_ZIP_SIGNATURE) {
+        let found = (start[6], start[7]);
+        if found > SUPPORTED_FORMAT_VERSION {
+            return Error::UnsupportedVersion { found, supported: SUPPORTED_FORMAT_VERSION };
+        }
+    }
+    Error::from_code(code)
+}
+
+/// The next header as stored, and where it sits, see [`read_raw_header`]
+struct RawHeader {
+    /// Offset of the header after the 32-byte signature header
+    next_offset: u64,
+    /// Header bytes, possibly packed (`kEncodedHeader`)
+    bytes: Vec<u8>,
+}
+
+/// Read the signature header and the next header it points to
+///
+/// `Ok(None)` means the file is truncated or a header CRC doesn't match; a
+/// file that doesn't start with the 7z signature at all is an error.
+fn read_raw_header(archive_path: &Path) -> Result<Option<RawHeader>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(archive_path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut start = Vec::with_capacity(SIGNATURE_HEADER_SIZE as usize);
+    (&mut file).take(SIGNATURE_HEADER_SIZE).read_to_end(&mut start)?;
+    if !start.starts_with(&SEVEN_ZIP_SIGNATURE) {
+        let prefix_len = start.len().min(SEVEN_ZIP_SIGNATURE.len());
+        if prefix_len == 0 || start[..prefix_len] != SEVEN_ZIP_SIGNATURE[..prefix_len] {
+            return Err(Error::InvalidArchive(format!("Not a 7z archive: {}", archive_path.display())));
+        }
+        // A cut-off signature is still a (badly) truncated archive
+        return Ok(None);
+    }
+    if start.len() < SIGNATURE_HEADER_SIZE as usize {
+        return Ok(None);
+    }
+
+    let le_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+    let le_u64 = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap());
+    if crc32fast::hash(&start[12..32]) != le_u32(&start[8..12]) {
+        return Ok(None);
+    }
+    let next_offset = le_u64(&start[12..20]);
+    let next_size = le_u64(&start[20..28]);
+    let header_end = SIGNATURE_HEADER_SIZE
+        .checked_add(next_offset)
+        .and_then(|end| end.checked_add(next_size));
+    if header_end.is_none_or(|end| end > file_len) {
+        return Ok(None);
+    }
+
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(SIGNATURE_HEADER_SIZE + next_offset))?;
+    file.take(next_size).read_to_end(&mut bytes)?;
+    if crc32fast::hash(&bytes) != le_u32(&start[28..32]) {
+        return Ok(None);
+    }
+
+    Ok(Some(RawHeader { next_offset, bytes }))
+}
+
+/// Whether `bytes` start like a PE executable: an `MZ` DOS header whose
+/// `e_lfanew` field points at a `PE\0\0` signature
+fn is_pe_executable(bytes: &[u8]) -> bool {
+    if bytes.len() < 0x40 || !bytes.starts_with(b"MZ") {
+        return false;
+    }
+    let pe_offset = u32::from_le_bytes([bytes[0x3C], bytes[0x3D], bytes[0x3E], bytes[0x3F]]) as usize;
+    bytes.get(pe_offset..pe_offset.saturating_add(4)) == Some(b"PE\0\0")
+}
+
+/// Metadata of the entry at `index` for a copied archive header
+fn copied_file(archive: &ArchiveHandle, entry: &ArchiveEntry, index: usize) -> CopiedFile {
+    CopiedFile {
+        name: entry.name.clone(),
+        size: entry.size,
+        crc32: entry.crc32,
+        mtime: archive.entry_filetime(index as u32),
+        ctime: archive.entry_ctime(index as u32),
+        atime: archive.entry_atime(index as u32),
+        attributes: (entry.attributes != 0).then_some(entry.attributes),
+        is_directory: entry.is_directory,
+    }
+}
+
+/// Normalize an entry name for comparison: `/` separators, no leading or
+/// trailing separator
+fn normalize_entry_name(name: &str) -> String {
+    name.replace('\\', "/").trim_matches('/').to_string()
+}
+
+/// An archive opened once and read entry by entry
+///
+/// Keeps the parsed headers and the last decoded solid block around, so
+/// reading several entries does not re-open or re-decode the archive.
+pub(crate) struct ArchiveHandle {
+    raw: *mut ffi::SevenZipArchive,
+}
+
+impl ArchiveHandle {
+    pub(crate) fn open(archive_path: &Path, password: Option<&str>) -> Result<Self> {
+        check_archive_file(archive_path)?;
+        let archive_path_c = path_to_cstring(archive_path)?;
+        let password_c = password.map(CString::new).transpose()?;
+        let mut raw: *mut ffi::SevenZipArchive = ptr::null_mut();
+
+        let result = unsafe {
+            ffi::sevenzip_archive_open(
+                archive_path_c.as_ptr(),
+                password_c.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                &mut raw,
             )
-        } else {
-            (None, ptr::null_mut())
+        };
+
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(open_error(archive_path, result));
+        }
+
+        Ok(Self { raw })
+    }
+
+    /// Open with the secret of `options`: its password or raw key
+    pub(crate) fn open_for_extract(archive_path: &Path, options: &ExtractOptions) -> Result<Self> {
+        let archive = Self::open(archive_path, options.password.as_deref())?;
+        if let Some(key) = &options.encryption_key {
+            let result = unsafe { ffi::sevenzip_archive_set_key(archive.raw, key.as_ptr()) };
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Entries in archive index order
+    pub(crate) fn entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut list_ptr: *mut ffi::SevenZipList = ptr::null_mut();
+
+        unsafe {
+            let result = ffi::sevenzip_archive_list(self.raw, &mut list_ptr);
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+            Ok(take_entry_list(list_ptr))
+        }
+    }
+
+    /// Up to `count` entries starting at index `start`
+    pub(crate) fn entries_range(&self, start: u32, count: u32) -> Result<Vec<ArchiveEntry>> {
+        let mut list_ptr: *mut ffi::SevenZipList = ptr::null_mut();
+
+        unsafe {
+            let result = ffi::sevenzip_archive_list_range(self.raw, start, count, &mut list_ptr);
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
+            Ok(take_entry_list(list_ptr))
+        }
+    }
+
+    /// Number of folders (solid blocks)
+    pub(crate) fn folder_count(&self) -> u32 {
+        unsafe { ffi::sevenzip_archive_folder_count(self.raw) }
+    }
+
+    /// Location and codec of one folder; `entry_names` is left empty
+    pub(crate) fn folder_info(&self, index: u32) -> Result<FolderInfo> {
+        let mut info = ffi::SevenZipFolderInfo {
+            packed_offset: 0,
+            packed_size: 0,
+            codec: [0; 32],
         };
 
         unsafe {
-            let result = ffi::sevenzip_create_7z_true_streaming(
-                archive_path_c.as_ptr(),
-                input_ptrs.as_ptr(),
-                level.into(),
-                &*opts_ptr,
-                callback,
-                user_data,
-            );
+            let result = ffi::sevenzip_archive_folder_info(self.raw, index, &mut info);
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
+            }
 
-            // Clean up the callback if it was allocated
-            if !user_data.is_null() {
-                let _boxed = Box::from_raw(user_data as *mut BytesProgressCallback);
-                // Drops automatically
+            Ok(FolderInfo {
+                index: index as usize,
+                packed_offset: info.packed_offset,
+                packed_size: info.packed_size,
+                entry_names: Vec::new(),
+                codec: CStr::from_ptr(info.codec.as_ptr()).to_string_lossy().into_owned(),
+            })
+        }
+    }
+
+    /// The folder at `index` as stored, for copying it without decoding
+    pub(crate) fn raw_folder(&self, index: u32) -> Result<RawFolder> {
+        let mut raw = ffi::SevenZipRawFolder {
+            record: ptr::null(),
+            record_size: 0,
+            pack_positions: ptr::null(),
+            num_pack_streams: 0,
+            unpack_sizes: ptr::null(),
+            num_unpack_sizes: 0,
+        };
+
+        unsafe {
+            let result = ffi::sevenzip_archive_raw_folder(self.raw, index, &mut raw);
+            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+                return Err(Error::from_code(result));
             }
 
+            let positions = std::slice::from_raw_parts(raw.pack_positions, raw.num_pack_streams as usize + 1);
+            Ok(RawFolder {
+                record: std::slice::from_raw_parts(raw.record, raw.record_size).to_vec(),
+                packed_offset: self.folder_info(index)?.packed_offset,
+                pack_sizes: positions.windows(2).map(|w| w[1] - w[0]).collect(),
+                unpack_sizes: std::slice::from_raw_parts(raw.unpack_sizes, raw.num_unpack_sizes as usize).to_vec(),
+            })
+        }
+    }
+
+    /// Stored modification time of the entry at `index` as FILETIME ticks
+    pub(crate) fn entry_filetime(&self, index: u32) -> Option<u64> {
+        self.stored_time(index, ffi::sevenzip_archive_entry_filetime)
+    }
+
+    /// Stored creation time of the entry at `index` as FILETIME ticks
+    pub(crate) fn entry_ctime(&self, index: u32) -> Option<u64> {
+        self.stored_time(index, ffi::sevenzip_archive_entry_ctime)
+    }
+
+    /// Stored access time of the entry at `index` as FILETIME ticks
+    pub(crate) fn entry_atime(&self, index: u32) -> Option<u64> {
+        self.stored_time(index, ffi::sevenzip_archive_entry_atime)
+    }
+
+    fn stored_time(
+        &self,
+        index: u32,
+        getter: unsafe extern "C" fn(*mut ffi::SevenZipArchive, u32, *mut u64) -> std::os::raw::c_int,
+    ) -> Option<u64> {
+        let mut ticks = 0u64;
+        let stored = unsafe { getter(self.raw, index, &mut ticks) };
+        (stored != 0).then_some(ticks)
+    }
+
+    /// 7zAES parameters of one folder, if it is encrypted
+    pub(crate) fn folder_encryption(&self, index: u32) -> Result<Option<EncryptionInfo>> {
+        let mut info = ffi::SevenZipEncryptionInfo::default();
+        let result = unsafe { ffi::sevenzip_archive_folder_encryption(self.raw, index, &mut info) };
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+        if info.encrypted == 0 {
+            return Ok(None);
+        }
+
+        let unstretched = info.cycles_power == 0x3F;
+        Ok(Some(EncryptionInfo {
+            kdf_iterations: if unstretched { 1 } else { 1u64 << info.cycles_power },
+            kdf_cycles_power: info.cycles_power,
+            salt_size: info.salt_size as usize,
+            iv_size: info.iv_size as usize,
+        }))
+    }
+
+    /// Folder holding the data of the entry at `index`, if it has any
+    pub(crate) fn entry_folder(&self, index: u32) -> Option<u32> {
+        let folder = unsafe { ffi::sevenzip_archive_entry_folder(self.raw, index) };
+        (folder != ffi::SEVENZIP_NO_FOLDER).then_some(folder)
+    }
+
+    /// Index of the entry holding the content of the hard link entry at
+    /// `index`, or `None` if it is not one
+    pub(crate) fn hardlink_target(&mut self, index: u32) -> Result<Option<u32>> {
+        let mut target = index;
+        let result = unsafe { ffi::sevenzip_archive_hardlink_target(self.raw, index, &mut target) };
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+        Ok((target != index).then_some(target))
+    }
+
+    /// Decompress the file entry at `index`, or for a hard link entry the
+    /// content of its target; the data borrows the handle's buffer
+    pub(crate) fn read(&mut self, index: u32) -> Result<&[u8]> {
+        let mut data: *const u8 = ptr::null();
+        let mut size: usize = 0;
+
+        unsafe {
+            let result = ffi::sevenzip_archive_read_entry(self.raw, index, &mut data, &mut size);
             if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
                 return Err(Error::from_code(result));
             }
+            if size == 0 {
+                return Ok(&[]);
+            }
+            Ok(std::slice::from_raw_parts(data, size))
         }
+    }
 
+    /// Decompress the file entry at `index` into `writer` chunk by chunk,
+    /// without holding it in memory. Hard link entries are written as stored.
+    /// Fails with [`Error::NotImplemented`] for folders other than a single
+    /// Copy or LZMA2 coder; [`read`](Self::read) handles those.
+    pub(crate) fn stream(&mut self, index: u32, writer: &mut dyn std::io::Write) -> Result<()> {
+        let mut sink = WriterSink { writer, error: None };
+        let result = unsafe {
+            ffi::sevenzip_archive_stream_entry(
+                self.raw,
+                index,
+                Some(writer_sink_callback),
+                &mut sink as *mut WriterSink as *mut std::os::raw::c_void,
+            )
+        };
+        if let Some(err) = sink.error {
+            return Err(err.into());
+        }
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
         Ok(())
     }
 
-    /// Compress a single file to LZMA2 format
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// use seven_zip::{SevenZip, CompressionLevel};
-    ///
-    /// let sz = SevenZip::new()?;
-    /// sz.compress_file("input.txt", "output.lzma2", CompressionLevel::Normal)?;
-    /// # Ok::<(), seven_zip::Error>(())
-    /// ```
-    pub fn compress_file(
-        &self,
-        input_path: impl AsRef<Path>,
-        output_path: impl AsRef<Path>,
-        level: CompressionLevel,
-    ) -> Result<()> {
-        let input_path_c = path_to_cstring(input_path.as_ref())?;
-        let output_path_c = path_to_cstring(output_path.as_ref())?;
+    /// Decompress the whole folder at `index`; the data borrows the handle's buffer
+    pub(crate) fn read_folder(&mut self, index: u32) -> Result<&[u8]> {
+        let mut data: *const u8 = ptr::null();
+        let mut size: usize = 0;
 
         unsafe {
-            let result = ffi::sevenzip_compress_file(
-                input_path_c.as_ptr(),
-                output_path_c.as_ptr(),
-                level.into(),
-                None,
-                ptr::null_mut(),
-            );
-
+            let result = ffi::sevenzip_archive_read_folder(self.raw, index, &mut data, &mut size);
             if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
                 return Err(Error::from_code(result));
             }
+            if size == 0 {
+                return Ok(&[]);
+            }
+            Ok(std::slice::from_raw_parts(data, size))
         }
+    }
+}
 
-        Ok(())
+impl Drop for ArchiveHandle {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sevenzip_archive_close(self.raw);
+        }
     }
+}
 
-    /// Decompress a single LZMA2 file
+/// Entries of an archive with on-demand readers, from
+/// [`SevenZip::entries_with_readers`]
+pub struct EntriesReader {
+    archive: ArchiveHandle,
+    entries: std::vec::IntoIter<ArchiveEntry>,
+    next_index: u32,
+}
+
+impl EntriesReader {
+    /// The next entry and a reader for its data, or `None` at the end
     ///
-    /// # Example
+    /// Not an [`Iterator`], since the reader borrows `self`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<(ArchiveEntry, EntryReader<'_>)>> {
+        let entry = self.entries.next()?;
+        let index = self.next_index;
+        self.next_index += 1;
+
+        let state = if entry.is_directory {
+            EntryReaderState::Ready(&[])
+        } else {
+            EntryReaderState::Pending(&mut self.archive, index)
+        };
+        Some(Ok((entry, EntryReader { state, pos: 0 })))
+    }
+}
+
+/// Reader over one entry's data, from [`EntriesReader::next`]
+///
+/// Decompression happens on the first read; a decoding failure (bad data,
+/// wrong password) is reported as an [`std::io::Error`] wrapping the
+/// library [`Error`].
+pub struct EntryReader<'a> {
+    state: EntryReaderState<'a>,
+    pos: usize,
+}
+
+enum EntryReaderState<'a> {
+    Pending(&'a mut ArchiveHandle, u32),
+    Ready(&'a [u8]),
+    Failed,
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if let EntryReaderState::Pending(..) = self.state {
+            let EntryReaderState::Pending(archive, index) = std::mem::replace(&mut self.state, EntryReaderState::Failed) else {
+                unreachable!()
+            };
+            self.state = EntryReaderState::Ready(archive.read(index).map_err(std::io::Error::other)?);
+        }
+        let EntryReaderState::Ready(data) = self.state else {
+            return Err(std::io::Error::other("Entry failed to decode"));
+        };
+
+        let n = buf.len().min(data.len() - self.pos);
+        buf[..n].copy_from_slice(&data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Folder-level access to an archive, from [`SevenZip::reader`]
+///
+/// Each folder (solid block) decodes to one stream holding the data of its
+/// file entries back to back, in the order of
+/// [`entries_in_folder`](Self::entries_in_folder). Splitting that stream by
+/// entry size is left to the caller, which makes this the building block for
+/// custom extraction logic.
+pub struct ArchiveReader {
+    archive: ArchiveHandle,
+    folders: Vec<FolderInfo>,
+    folder_entries: Vec<Vec<ArchiveEntry>>,
+}
+
+impl ArchiveReader {
+    /// All folders in the archive, indexed by [`FolderInfo::index`]
+    pub fn folders(&self) -> &[FolderInfo] {
+        &self.folders
+    }
+
+    /// File entries stored in a folder, in stream order; empty for an
+    /// unknown folder
+    pub fn entries_in_folder(&self, folder_index: usize) -> &[ArchiveEntry] {
+        self.folder_entries.get(folder_index).map_or(&[], Vec::as_slice)
+    }
+
+    /// The decompressed stream of a folder
     ///
-    /// ```no_run
-    /// use seven_zip::SevenZip;
+    /// The whole folder is decoded up front. The reader borrows `self`, so it
+    /// must be dropped before opening the next substream.
+    pub fn substream(&mut self, folder_index: usize) -> Result<impl Read + '_> {
+        if folder_index >= self.folders.len() {
+            return Err(Error::InvalidParameter(format!(
+                "Folder index {} out of range ({} folders)",
+                folder_index,
+                self.folders.len()
+            )));
+        }
+        self.archive.read_folder(folder_index as u32)
+    }
+}
+
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString> {
+    let path_str = path.to_str()
+        .ok_or_else(|| Error::InvalidParameter("Invalid path encoding".to_string()))?;
+    CString::new(path_str)
+        .map_err(|_| Error::InvalidParameter("Path contains null byte".to_string()))
+}
+
+/// Destination of [`SevenZip::write_archive`]
+pub(crate) enum ArchiveOutput<'a> {
+    /// Written to this file by the C library
+    Path(&'a Path),
+    /// Streamed, in order, into this writer
+    Writer(&'a mut dyn std::io::Write),
+}
+
+/// State behind [`writer_sink_callback`]
+struct WriterSink<'a> {
+    writer: &'a mut dyn std::io::Write,
+    error: Option<std::io::Error>,
+}
+
+/// C write callback forwarding archive bytes to a [`WriterSink`]
+unsafe extern "C" fn writer_sink_callback(
+    data: *const u8,
+    size: usize,
+    user_data: *mut std::os::raw::c_void,
+) -> std::os::raw::c_int {
+    // SAFETY: user_data is the WriterSink owned by the caller of the C
+    // function, which outlives the call; data holds size readable bytes
+    let sink = unsafe { &mut *(user_data as *mut WriterSink) };
+    let bytes = unsafe { std::slice::from_raw_parts(data, size) };
+
+    match sink.writer.write_all(bytes) {
+        Ok(()) => 0,
+        Err(e) => {
+            sink.error = Some(e);
+            1
+        }
+    }
+}
+
+/// Stages the output of a create operation and removes it unless finished
+///
+/// The operation writes to [`path`](Self::path), a temporary file beside the
+/// archive, which [`finish`](Self::finish) renames over the archive path. An
+/// error return or unwinding out of a panicking progress callback thus never
+/// leaves a partial file in place of an existing archive; the staged file is
+/// removed instead, or kept for inspection when `delete_on_error` is false.
+pub(crate) struct PartialOutputGuard {
+    archive_path: PathBuf,
+    staged_path: Option<PathBuf>,
+    remove_archive: bool,
+    remove_volumes: bool,
+    volume_naming: VolumeNaming,
+    armed: bool,
+}
+
+impl PartialOutputGuard {
+    pub(crate) fn new(archive_path: &Path, delete_on_error: bool) -> Self {
+        let file_name = archive_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let staged_name = format!(
+            ".{}.partial-{}-{}",
+            file_name,
+            std::process::id(),
+            SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        Self {
+            archive_path: archive_path.to_path_buf(),
+            staged_path: Some(archive_path.with_file_name(staged_name)),
+            remove_archive: false,
+            remove_volumes: false,
+            volume_naming: VolumeNaming::Numeric,
+            armed: delete_on_error,
+        }
+    }
+
+    /// Write to the archive path itself, for writers that may split
     ///
-    /// let sz = SevenZip::new()?;
-    /// sz.decompress_file("input.lzma2", "output.txt")?;
-    /// # Ok::<(), seven_zip::Error>(())
-    /// ```
-    pub fn decompress_file(
-        &self,
-        input_path: impl AsRef<Path>,
-        output_path: impl AsRef<Path>,
-    ) -> Result<()> {
-        let input_path_c = path_to_cstring(input_path.as_ref())?;
-        let output_path_c = path_to_cstring(output_path.as_ref())?;
+    /// Volumes can't be staged, since each is handed out as soon as it is
+    /// finished. Only the archive and the volumes named by `naming` that did
+    /// not exist before the operation started are removed on failure, so an
+    /// existing file is never deleted because of an early failure.
+    pub(crate) fn in_place(mut self, naming: VolumeNaming) -> Self {
+        self.staged_path = None;
+        self.remove_archive = !self.archive_path.exists();
+        self.remove_volumes = !naming.volume_path(&self.archive_path, 1).exists();
+        self.volume_naming = naming;
+        self
+    }
 
-        unsafe {
-            let result = ffi::sevenzip_decompress_file(
-                input_path_c.as_ptr(),
-                output_path_c.as_ptr(),
-                None,
-                ptr::null_mut(),
-            );
+    /// Where the operation writes its output
+    pub(crate) fn path(&self) -> &Path {
+        self.staged_path.as_deref().unwrap_or(&self.archive_path)
+    }
 
-            if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
-                return Err(Error::from_code(result));
+    /// Move the output into place; called once the operation completed successfully
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if let Some(staged) = &self.staged_path {
+            std::fs::rename(staged, &self.archive_path)?;
+        }
+        self.armed = false;
+        Ok(())
+    }
+}
+
+impl Drop for PartialOutputGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        if let Some(staged) = &self.staged_path {
+            let _ = std::fs::remove_file(staged);
+        }
+        if self.remove_archive {
+            let _ = std::fs::remove_file(&self.archive_path);
+        }
+        if self.remove_volumes {
+            let mut index = 1;
+            while std::fs::remove_file(self.volume_naming.volume_path(&self.archive_path, index)).is_ok() {
+                index += 1;
             }
         }
+    }
+}
 
-        Ok(())
+/// Flush a finished archive, or all of its split volumes, to stable storage,
+/// then the directory entries that name them (Unix only)
+fn sync_archive_output(archive_path: &Path, naming: VolumeNaming) -> Result<()> {
+    // Write access, since Windows can't flush a read-only handle
+    let sync_file = |path: &Path| -> std::io::Result<()> {
+        std::fs::OpenOptions::new().write(true).open(path)?.sync_all()
+    };
+
+    if archive_path.exists() {
+        sync_file(archive_path)?;
     }
+    let mut index = 1;
+    loop {
+        let volume = naming.volume_path(archive_path, index);
+        if !volume.exists() {
+            break;
+        }
+        sync_file(&volume)?;
+        index += 1;
+    }
+    if naming == VolumeNaming::ZipStyle {
+        let last = archive_path.with_extension("zip");
+        if last.exists() {
+            sync_file(&last)?;
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        let parent = archive_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
 }
 
-impl Drop for SevenZip {
+/// Scratch directory beside an archive, removed with its contents on drop
+///
+/// Living in the archive's own directory keeps the final rename on one
+/// filesystem, so replacing the archive is atomic.
+struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    fn new(archive_path: &Path) -> Result<Self> {
+        let file_name = archive_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parent = archive_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let path = parent.join(format!(".{}.staging-{}", file_name, std::process::id()));
+
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
     fn drop(&mut self) {
-        unsafe {
-            ffi::sevenzip_cleanup();
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Panic payload caught inside a progress callback, re-raised once the C call returns
+type PanicSlot = Arc<Mutex<Option<Box<dyn Any + Send>>>>;
+
+/// Wrap a byte progress callback so a panic inside it does not unwind through
+/// C frames. The panic is caught, the C operation is asked to abort, and the
+/// payload is parked in the returned slot for [`resume_callback_panic`].
+fn panic_safe_bytes_progress(
+    progress: Option<BytesProgressCallback>,
+) -> (Option<BytesProgressCallback>, PanicSlot) {
+    let slot: PanicSlot = Arc::new(Mutex::new(None));
+    let Some(mut inner) = progress else {
+        return (None, slot);
+    };
+    let caught = Arc::clone(&slot);
+    let wrapped: BytesProgressCallback = Box::new(move |processed, total, file_bytes, file_total, name| {
+        let mut caught = caught.lock().unwrap_or_else(|e| e.into_inner());
+        if caught.is_some() {
+            return;
+        }
+        let call = AssertUnwindSafe(|| inner(processed, total, file_bytes, file_total, name));
+        if let Err(payload) = std::panic::catch_unwind(call) {
+            *caught = Some(payload);
+            // SAFETY: only sets a flag for the operation running on this thread
+            unsafe { ffi::sevenzip_request_abort() };
         }
+    });
+    (Some(wrapped), slot)
+}
+
+/// Resume a panic caught by [`panic_safe_bytes_progress`], if any
+fn resume_callback_panic(slot: &PanicSlot) {
+    let payload = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(payload) = payload {
+        std::panic::resume_unwind(payload);
     }
 }
 
-// Helper functions
+/// Fail early if the cancellation flag is already set
+fn check_cancelled(cancel: Option<&Arc<AtomicBool>>) -> Result<()> {
+    match cancel {
+        Some(flag) if flag.load(Ordering::Relaxed) => Err(Error::Cancelled),
+        _ => Ok(()),
+    }
+}
 
-fn path_to_cstring(path: &Path) -> Result<CString> {
-    let path_str = path.to_str()
-        .ok_or_else(|| Error::InvalidParameter("Invalid path encoding".to_string()))?;
-    CString::new(path_str)
-        .map_err(|_| Error::InvalidParameter("Path contains null byte".to_string()))
+/// Wrap a progress callback so that it asks the C side to abort once `cancel`
+/// is set. A callback is installed even when the caller passed none, since the
+/// C operation only checks for aborts after reporting progress.
+fn cancellable_progress(
+    progress: Option<ProgressCallback>,
+    cancel: Option<&Arc<AtomicBool>>,
+) -> Option<ProgressCallback> {
+    let Some(cancel) = cancel.cloned() else {
+        return progress;
+    };
+    let mut inner = progress;
+    Some(Box::new(move |completed, total| {
+        if let Some(cb) = inner.as_mut() {
+            cb(completed, total);
+        }
+        if cancel.load(Ordering::Relaxed) {
+            // SAFETY: only sets a flag for the operation running on this thread
+            unsafe { ffi::sevenzip_request_abort() };
+        }
+    }))
+}
+
+/// Byte-level counterpart of [`cancellable_progress`]
+fn cancellable_bytes_progress(
+    progress: Option<BytesProgressCallback>,
+    cancel: Option<&Arc<AtomicBool>>,
+) -> Option<BytesProgressCallback> {
+    let Some(cancel) = cancel.cloned() else {
+        return progress;
+    };
+    let mut inner = progress;
+    Some(Box::new(move |processed, total, file_bytes, file_total, name| {
+        if let Some(cb) = inner.as_mut() {
+            cb(processed, total, file_bytes, file_total, name);
+        }
+        if cancel.load(Ordering::Relaxed) {
+            // SAFETY: only sets a flag for the operation running on this thread
+            unsafe { ffi::sevenzip_request_abort() };
+        }
+    }))
 }
 
 unsafe extern "C" fn progress_callback_wrapper(
@@ -1173,6 +7025,127 @@ unsafe extern "C" fn progress_callback_wrapper(
     }
 }
 
+/// What [`test_entry_callback_wrapper`] works with
+struct TestEntryState<'a> {
+    callback: &'a mut dyn FnMut(&str, bool),
+    /// A panic from `callback`, resumed once the C call returns
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn test_entry_callback_wrapper(
+    entry_name: *const std::os::raw::c_char,
+    passed: std::os::raw::c_int,
+    user_data: *mut std::os::raw::c_void,
+) {
+    if user_data.is_null() {
+        return;
+    }
+    // SAFETY: user_data points at the TestEntryState on the stack of
+    // test_archive_with_callback, which outlives the C call
+    let (state, name) = unsafe {
+        let name = if !entry_name.is_null() {
+            CStr::from_ptr(entry_name).to_str().unwrap_or("<invalid utf-8>")
+        } else {
+            ""
+        };
+        (&mut *(user_data as *mut TestEntryState), name)
+    };
+    // The C side cannot be stopped from here, so later entries go unreported
+    if state.panic.is_some() {
+        return;
+    }
+    let call = AssertUnwindSafe(|| (state.callback)(name, passed != 0));
+    if let Err(payload) = std::panic::catch_unwind(call) {
+        state.panic = Some(payload);
+    }
+}
+
+/// What the C side's volume callback points at
+struct VolumeHookState {
+    hook: VolumeHook,
+    /// `fsync` each volume before handing it over
+    fsync: bool,
+    /// Why the hook stopped the operation, if it did
+    outcome: RefCell<Option<HookOutcome>>,
+}
+
+enum HookOutcome {
+    Failed(Error),
+    Panicked(Box<dyn Any + Send>),
+}
+
+unsafe extern "C" fn volume_callback_wrapper(
+    volume_path: *const std::os::raw::c_char,
+    size: u64,
+    user_data: *mut std::os::raw::c_void,
+) -> std::os::raw::c_int {
+    if user_data.is_null() || volume_path.is_null() {
+        return 0;
+    }
+    // SAFETY: user_data is the VolumeHookState boxed for this call, and
+    // volume_path a NUL-terminated path, both valid for the callback's duration
+    let (state, volume) = unsafe {
+        (
+            &*(user_data as *const VolumeHookState),
+            PathBuf::from(CStr::from_ptr(volume_path).to_string_lossy().into_owned()),
+        )
+    };
+    let call = AssertUnwindSafe(|| {
+        if state.fsync {
+            std::fs::File::open(&volume)?.sync_all()?;
+        }
+        state.hook.call(&volume, size)
+    });
+    let outcome = match std::panic::catch_unwind(call) {
+        Ok(Ok(())) => return 0,
+        Ok(Err(err)) => HookOutcome::Failed(err),
+        Err(payload) => HookOutcome::Panicked(payload),
+    };
+    *state.outcome.borrow_mut() = Some(outcome);
+    1
+}
+
+/// What [`temp_file_callback_wrapper`] works with
+struct TempFileState {
+    factory: TempFileFactory,
+    /// Files handed to the C side, held open until the operation ends
+    created: RefCell<Vec<(PathBuf, std::fs::File)>>,
+    /// Why the factory stopped the operation, if it did
+    outcome: RefCell<Option<HookOutcome>>,
+}
+
+unsafe extern "C" fn temp_file_callback_wrapper(
+    path: *mut std::os::raw::c_char,
+    path_size: usize,
+    user_data: *mut std::os::raw::c_void,
+) -> std::os::raw::c_int {
+    if user_data.is_null() || path.is_null() {
+        return 1;
+    }
+    // SAFETY: user_data is the TempFileState boxed for this call
+    let state = unsafe { &*(user_data as *const TempFileState) };
+    let call = AssertUnwindSafe(|| -> Result<()> {
+        let (temp_path, file) = state.factory.create()?;
+        // Tracked first, so it is cleaned up even if it can't be handed over
+        state.created.borrow_mut().push((temp_path.clone(), file));
+        let temp_path_c = path_to_cstring(&temp_path)?;
+        let bytes = temp_path_c.as_bytes_with_nul();
+        if bytes.len() > path_size {
+            return Err(Error::InvalidParameter(format!("Temp file path too long: {}", temp_path.display())));
+        }
+        // SAFETY: path points to path_size writable bytes
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), path as *mut u8, bytes.len()) };
+        Ok(())
+    });
+    let outcome = match std::panic::catch_unwind(call) {
+        Ok(Ok(())) => return 0,
+        Ok(Err(err)) => HookOutcome::Failed(err),
+        Err(payload) => HookOutcome::Panicked(payload),
+    };
+    *state.outcome.borrow_mut() = Some(outcome);
+    1
+}
+
 unsafe extern "C" fn bytes_progress_callback_wrapper(
     bytes_processed: u64,
     bytes_total: u64,
@@ -1212,19 +7185,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sanitize_windows_component() {
+        assert_eq!(sanitize_windows_component("CON"), "CON_");
+        assert_eq!(sanitize_windows_component("aux.txt"), "aux_.txt");
+        assert_eq!(sanitize_windows_component("Lpt1.tar.gz"), "Lpt1_.tar.gz");
+        assert_eq!(sanitize_windows_component("notes. "), "notes_");
+        assert_eq!(sanitize_windows_component("nul."), "nul_");
+        assert_eq!(sanitize_windows_component("console.log"), "console.log");
+        assert_eq!(sanitize_windows_component("COM10"), "COM10");
+        assert!(!is_windows_reserved_component(".."));
+    }
+
+    #[test]
+    fn test_host_os_from_attributes() {
+        // 7-Zip on Linux: 0x8000 | mode << 16, plus ARCHIVE or DIRECTORY
+        let unix_file = 0x8000 | (0o100644 << 16) | 0x20;
+        let unix_dir = 0x8000 | (0o040755 << 16) | 0x10;
+        // 7-Zip on Windows: ARCHIVE, DIRECTORY, READONLY | ARCHIVE
+        let windows = [0x20, 0x10, 0x21];
+
+        assert_eq!(HostOs::from_attributes([unix_file, unix_dir]), Some(HostOs::Unix));
+        assert_eq!(HostOs::from_attributes(windows), Some(HostOs::Windows));
+        assert_eq!(HostOs::from_attributes([unix_file, 0x20]), Some(HostOs::Unknown));
+        assert_eq!(HostOs::from_attributes([0, unix_dir]), Some(HostOs::Unix));
+        assert_eq!(HostOs::from_attributes([0, 0]), None);
+    }
+
+    #[test]
+    fn test_stream_matches_read() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let noise: Vec<u8> = (0..3_000_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        std::fs::write(temp.path().join("a.txt"), "first entry").unwrap();
+        std::fs::write(temp.path().join("b.bin"), &noise).unwrap();
+        std::fs::write(temp.path().join("c.txt"), "last ".repeat(300_000)).unwrap();
+        let inputs = ["a.txt", "b.bin", "c.txt"].map(|name| temp.path().join(name));
+
+        let sz = SevenZip::new().unwrap();
+        for level in [CompressionLevel::Store, CompressionLevel::Fast] {
+            let archive_path = temp.path().join("solid.7z");
+            sz.create_archive(&archive_path, &inputs, level, None).unwrap();
+            let mut archive = ArchiveHandle::open(&archive_path, None).unwrap();
+            for index in 0..archive.entries().unwrap().len() as u32 {
+                let mut streamed = Vec::new();
+                archive.stream(index, &mut streamed).unwrap();
+                assert_eq!(streamed, archive.read(index).unwrap(), "{:?} entry {}", level, index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_sources_unchanged_reports_changed_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let inputs = ["a.txt", "b.txt"].map(|name| temp.path().join(name));
+        std::fs::write(&inputs[0], "first file contents").unwrap();
+        std::fs::write(&inputs[1], "second file contents").unwrap();
+
+        let sz = SevenZip::new().unwrap();
+        let archive_path = temp.path().join("crc.7z");
+        let opts = CompressOptions::default().with_verify_crc_after(true);
+        sz.create_archive(&archive_path, &inputs, CompressionLevel::Normal, Some(&opts)).unwrap();
+
+        // Same size, different bytes, as if written to while being archived
+        std::fs::write(&inputs[1], "second file CONTENTS").unwrap();
+        match sz.verify_sources_unchanged(&archive_path, &inputs, &opts) {
+            Err(Error::SourceChanged { path }) => assert_eq!(path, inputs[1].display().to_string()),
+            other => panic!("expected SourceChanged, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_archive_entry_compression_ratio() {
         let entry = ArchiveEntry {
             name: "test.txt".to_string(),
+            raw_name: b"test.txt".to_vec(),
             size: 1000,
             packed_size: 300,
             modified_time: 0,
             attributes: 0,
             is_directory: false,
+            crc32: None,
+            method: String::new(),
         };
         assert_eq!(entry.compression_ratio(), 70.0);
     }
 
+    #[test]
+    fn test_utc_timestamp() {
+        let at = |secs| utc_timestamp(std::time::UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "19700101-000000");
+        assert_eq!(at(1_704_110_400), "20240101-120000");
+        assert_eq!(at(1_709_164_799), "20240228-235959");
+        assert_eq!(at(1_709_164_800), "20240229-000000");
+    }
+
+    #[test]
+    fn test_archive_entry_path_is_relative() {
+        let entry = |name: &str| ArchiveEntry {
+            name: name.to_string(),
+            raw_name: name.as_bytes().to_vec(),
+            size: 0,
+            packed_size: 0,
+            modified_time: 0,
+            attributes: 0,
+            is_directory: false,
+            crc32: None,
+            method: String::new(),
+        };
+        let expected: PathBuf = ["dir", "sub", "file.txt"].iter().collect();
+
+        assert_eq!(entry("dir/sub/file.txt").path(), expected);
+        assert_eq!(entry("dir\\sub\\file.txt").path(), expected);
+        assert_eq!(entry("/dir/./sub//file.txt").path(), expected);
+        assert_eq!(entry("C:\\dir\\sub\\file.txt").path(), expected);
+        assert_eq!(entry("../../dir/x/../sub/file.txt").path(), expected);
+        assert!(entry("dir/sub/file.txt").path().is_relative());
+        assert_eq!(entry("/").path(), PathBuf::new());
+    }
+
+    #[test]
+    fn test_case_collisions_and_numbered_names() {
+        let entry = |name: &str, is_directory| ArchiveEntry {
+            name: name.to_string(),
+            raw_name: name.as_bytes().to_vec(),
+            size: 0,
+            packed_size: 0,
+            modified_time: 0,
+            attributes: 0,
+            is_directory,
+            crc32: None,
+            method: String::new(),
+        };
+        let entries = [
+            entry("A.txt", false),
+            entry("docs", true),
+            entry("DOCS", true),
+            entry("a.txt", false),
+            entry("docs/Readme", false),
+            entry("Docs/README", false),
+            entry("b.txt", false),
+        ];
+        assert_eq!(case_collisions(&entries), vec![vec![0, 3], vec![4, 5]]);
+
+        assert_eq!(numbered_name("a.txt", 1), "a (1).txt");
+        assert_eq!(numbered_name("Docs/README", 2), "Docs/README (2)");
+        assert_eq!(numbered_name("dir.d/.hidden", 1), "dir.d/.hidden (1)");
+        assert_eq!(numbered_name("x/archive.tar.gz", 1), "x/archive.tar (1).gz");
+    }
+
+    #[test]
+    fn test_direct_children_synthesizes_directories() {
+        let file = |name: &str| ArchiveEntry {
+            name: name.to_string(),
+            raw_name: name.as_bytes().to_vec(),
+            size: 1,
+            packed_size: 0,
+            modified_time: 0,
+            attributes: 0,
+            is_directory: false,
+            crc32: None,
+            method: String::new(),
+        };
+        let entries = vec![file("a/b/deep.txt"), file("a/top.txt"), file("root.txt")];
+
+        let root: Vec<_> = direct_children(entries.clone(), "").into_iter().map(|e| e.name).collect();
+        assert_eq!(root, vec!["a", "root.txt"]);
+
+        let a = direct_children(entries, "a");
+        assert_eq!(a.len(), 2);
+        assert!(a[0].is_directory && a[0].name == "a/b");
+        assert_eq!(a[1].name, "a/top.txt");
+    }
+
     #[test]
     fn test_default_options() {
         let opts = CompressOptions::default();
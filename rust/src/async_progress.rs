@@ -0,0 +1,110 @@
+//! Async progress reporting (requires the `tokio` feature)
+//!
+//! Runs blocking archive operations on tokio's blocking pool and exposes their
+//! progress as a [`Stream`] instead of a `Send` callback.
+
+use crate::archive::SevenZip;
+use crate::error::{Error, Result};
+use std::future::Future;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+/// Number of progress events buffered before new intermediate updates are dropped
+const PROGRESS_CHANNEL_CAPACITY: usize = 16;
+
+/// A single progress update from an async operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of entries processed so far
+    pub processed: u64,
+    /// Total number of entries in the archive
+    pub total: u64,
+    /// Name of the entry that was just processed
+    pub current_file: String,
+}
+
+impl SevenZip {
+    /// Extract an archive on the blocking pool, streaming progress events
+    ///
+    /// Returns a stream of [`Progress`] updates and a future that resolves once
+    /// extraction has finished. The stream ends when the operation completes.
+    /// If the consumer falls behind, new intermediate updates are dropped
+    /// while the buffer is full, so the worker never blocks on the channel;
+    /// the final update is always delivered.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> seven_zip::Result<()> {
+    /// use seven_zip::SevenZip;
+    /// use std::path::Path;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let (mut progress, done) =
+    ///     sz.extract_progress_stream(Path::new("archive.7z"), Path::new("output"), None);
+    /// let done = tokio::spawn(done);
+    /// while let Some(p) = progress.next().await {
+    ///     println!("{}/{}: {}", p.processed, p.total, p.current_file);
+    /// }
+    /// done.await.unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_progress_stream(
+        &self,
+        archive_path: &Path,
+        output_dir: &Path,
+        password: Option<&str>,
+    ) -> (impl Stream<Item = Progress>, impl Future<Output = Result<()>>) {
+        // Paths are taken as `&Path` rather than `impl AsRef<Path>` so that the
+        // returned future does not borrow from the arguments and can be spawned
+        let archive_path = archive_path.to_path_buf();
+        let output_dir = output_dir.to_path_buf();
+        let password = password.map(str::to_owned);
+        let (tx, rx) = mpsc::channel(PROGRESS_CHANNEL_CAPACITY);
+
+        let handle = tokio::task::spawn_blocking(move || -> Result<()> {
+            // The blocking task may outlive `self`, so it holds its own handle
+            let sz = SevenZip::new()?;
+            let names: Vec<String> = sz
+                .list(&archive_path, password.as_deref())?
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect();
+
+            sz.extract_with_password(
+                &archive_path,
+                &output_dir,
+                password.as_deref(),
+                Some(Box::new(move |processed, total| {
+                    let current_file = processed
+                        .checked_sub(1)
+                        .and_then(|i| names.get(i as usize))
+                        .cloned()
+                        .unwrap_or_default();
+                    let update = Progress { processed, total, current_file };
+                    // One slot stays reserved for the final update. When the
+                    // consumer lags behind, the updates already queued are kept
+                    // and new intermediate ones are dropped until it catches
+                    // up, so the worker never blocks
+                    if processed == total || tx.capacity() > 1 {
+                        let _ = tx.try_send(update);
+                    }
+                })),
+            )
+        });
+
+        let done = async move {
+            handle
+                .await
+                .map_err(|e| Error::Unknown(format!("Extraction task failed: {}", e)))?
+        };
+
+        (ReceiverStream::new(rx), done)
+    }
+}
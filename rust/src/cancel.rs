@@ -0,0 +1,64 @@
+//! Cooperative cancellation for long-running operations
+//!
+//! The library never installs signal handlers itself. To stop an archive
+//! operation on Ctrl+C, set a [`CancelToken`] from your own handler; the
+//! operation notices at its next progress tick and returns
+//! [`Error::Cancelled`](crate::Error::Cancelled).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A shareable "please stop" flag
+///
+/// Clones share the same flag, so one clone can be handed to a signal
+/// handler or another thread while the operation holds the other.
+///
+/// # Example
+///
+/// The recommended Ctrl+C pattern: move a clone into your own SIGINT
+/// handler (for instance via the `ctrlc` crate) and pass the token to the
+/// operation. Here a timer thread stands in for the handler.
+///
+/// ```no_run
+/// use seven_zip::{CancelToken, SevenZip, StreamOptions, CompressionLevel};
+///
+/// let token = CancelToken::new();
+/// let handler_token = token.clone();
+/// // ctrlc::set_handler(move || handler_token.cancel())?;
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(5));
+///     handler_token.cancel();
+/// });
+///
+/// let opts = StreamOptions::default().with_cancel_token(&token);
+/// let sz = SevenZip::new()?;
+/// match sz.create_archive_streaming("backup.7z", &["data"], CompressionLevel::Normal, Some(&opts), None) {
+///     Err(seven_zip::Error::Cancelled) => eprintln!("interrupted"),
+///     other => other?,
+/// }
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every operation watching this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// The underlying flag, as taken by the `cancel` option fields
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.0)
+    }
+}
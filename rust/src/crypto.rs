@@ -0,0 +1,342 @@
+//! Encrypt-only transforms for standalone blobs
+//!
+//! [`encrypt_blob`] runs data through the C library's AES-256-CBC with the
+//! same key derivation as [`encryption`](crate::encryption), but without
+//! any compression or archive container. The result carries its own salt
+//! and IV, so [`decrypt_blob`] needs nothing but the password, and an
+//! HMAC-SHA256 tag that tells a wrong password or a corrupted blob apart
+//! from a good one. Blobs are not 7z archives; only this module reads them
+//! back. For large ciphertexts, [`Decryptor`] decrypts piece by piece
+//! instead.
+//!
+//! Keys that come from elsewhere, e.g. a KMS, can be used directly with
+//! [`encrypt_blob_with_key`] and [`decrypt_blob_with_key`], skipping the
+//! password KDF. Reference 7-Zip always derives its key from a password, so
+//! it has no way to take such a key. Archives take one through
+//! [`CompressOptions::encryption_key`](crate::CompressOptions::encryption_key).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use seven_zip::crypto::{decrypt_blob, encrypt_blob};
+//!
+//! let blob = encrypt_blob(b"case notes", "hunter2")?;
+//! assert_eq!(decrypt_blob(&blob, "hunter2")?, b"case notes");
+//! # Ok::<(), seven_zip::Error>(())
+//! ```
+
+use crate::encryption_native::{generate_iv, generate_salt, SALT_SIZE};
+use crate::error::{Error, Result};
+use crate::ffi;
+use aes::cipher::{generic_array::GenericArray, BlockDecryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::ffi::CString;
+
+/// Length of [`EncryptedBlob::mac`]
+pub const MAC_SIZE: usize = 32;
+
+/// Label the MAC key is derived from the AES key with, so the two keys differ
+const MAC_KEY_LABEL: &[u8] = b"seven_zip blob mac";
+
+/// Data encrypted by [`encrypt_blob`], with everything needed to decrypt it
+/// except the password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedBlob {
+    /// Random salt for key derivation
+    pub salt: [u8; SALT_SIZE],
+    /// Random CBC initialization vector
+    pub iv: [u8; ffi::AES_BLOCK_SIZE],
+    /// AES-256-CBC ciphertext with PKCS#7 padding
+    pub ciphertext: Vec<u8>,
+    /// HMAC-SHA256 over salt, IV and ciphertext, keyed from the AES key
+    pub mac: [u8; MAC_SIZE],
+}
+
+/// Encrypt `data` with a key derived from `password`
+///
+/// Every call picks a fresh random salt and IV, so encrypting the same data
+/// twice gives different blobs.
+pub fn encrypt_blob(data: &[u8], password: &str) -> Result<EncryptedBlob> {
+    let salt = generate_salt();
+    let (mut key, mut aes_context) = derive(password, &salt, ffi::sevenzip_init_encryption_with_salt)?;
+    let blob = encrypt_with(data, salt, &key, &mut aes_context);
+    key.fill(0);
+    blob
+}
+
+/// Encrypt `data` with a raw 256-bit AES key, bypassing the password KDF
+///
+/// The blob's salt is unused and left all zeros; the IV is still random
+/// for every call.
+pub fn encrypt_blob_with_key(data: &[u8], key: &[u8; ffi::AES_KEY_SIZE]) -> Result<EncryptedBlob> {
+    let mut aes_context = key_schedule(key, ffi::sevenzip_init_encryption_with_key)?;
+    encrypt_with(data, [0; SALT_SIZE], key, &mut aes_context)
+}
+
+/// Decrypt a blob written by [`encrypt_blob`]
+///
+/// Fails with [`Error::WrongPassword`] if the MAC does not check out, which
+/// is what a wrong password or a corrupted blob produces.
+pub fn decrypt_blob(blob: &EncryptedBlob, password: &str) -> Result<Vec<u8>> {
+    check_ciphertext_len(&blob.ciphertext)?;
+    let (mut key, mut aes_context) = derive(password, &blob.salt, ffi::sevenzip_init_decryption)?;
+    let verified = verify_mac(blob, &key);
+    key.fill(0);
+    if let Err(e) = verified {
+        aes_context.fill(0);
+        return Err(e);
+    }
+    decrypt_with(blob, &mut aes_context)
+}
+
+/// Decrypt a blob written by [`encrypt_blob_with_key`]
+///
+/// Fails with [`Error::WrongPassword`] if the MAC does not check out, which
+/// is what a wrong key or a corrupted blob produces.
+pub fn decrypt_blob_with_key(blob: &EncryptedBlob, key: &[u8; ffi::AES_KEY_SIZE]) -> Result<Vec<u8>> {
+    check_ciphertext_len(&blob.ciphertext)?;
+    verify_mac(blob, key)?;
+    let mut aes_context = key_schedule(key, ffi::sevenzip_init_decryption_with_key)?;
+    decrypt_with(blob, &mut aes_context)
+}
+
+/// HMAC-SHA256 state keyed for the blob encrypted under `key`, already fed
+/// the salt and IV
+fn blob_mac(key: &[u8; ffi::AES_KEY_SIZE], salt: &[u8], iv: &[u8]) -> Hmac<Sha256> {
+    let mut derive = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    derive.update(MAC_KEY_LABEL);
+    let mut mac_key = derive.finalize().into_bytes();
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).expect("HMAC takes any key length");
+    mac_key.fill(0);
+    mac.update(salt);
+    mac.update(iv);
+    mac
+}
+
+/// Check the blob's MAC in constant time
+fn verify_mac(blob: &EncryptedBlob, key: &[u8; ffi::AES_KEY_SIZE]) -> Result<()> {
+    let mut mac = blob_mac(key, &blob.salt, &blob.iv);
+    mac.update(&blob.ciphertext);
+    mac.verify_slice(&blob.mac).map_err(|_| Error::WrongPassword)
+}
+
+/// AES-256-CBC encrypt `data` under a fresh IV with a ready key schedule,
+/// and MAC the result with a key derived from `key`
+fn encrypt_with(
+    data: &[u8],
+    salt: [u8; SALT_SIZE],
+    key: &[u8; ffi::AES_KEY_SIZE],
+    aes_context: &mut [u32; ffi::AES_NUM_IVMRK_WORDS],
+) -> Result<EncryptedBlob> {
+    let iv = generate_iv();
+    let mut ciphertext = vec![0u8; (data.len() / ffi::AES_BLOCK_SIZE + 1) * ffi::AES_BLOCK_SIZE];
+    let mut ciphertext_len = ciphertext.len();
+    let result = unsafe {
+        ffi::sevenzip_encrypt_data(
+            aes_context.as_mut_ptr(),
+            iv.as_ptr(),
+            data.as_ptr(),
+            data.len(),
+            ciphertext.as_mut_ptr(),
+            &mut ciphertext_len,
+        )
+    };
+    aes_context.fill(0);
+    if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+        return Err(Error::from_code(result));
+    }
+
+    ciphertext.truncate(ciphertext_len);
+    let mut mac = blob_mac(key, &salt, &iv);
+    mac.update(&ciphertext);
+    let mac = mac.finalize().into_bytes().into();
+    Ok(EncryptedBlob { salt, iv, ciphertext, mac })
+}
+
+fn check_ciphertext_len(ciphertext: &[u8]) -> Result<()> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(ffi::AES_BLOCK_SIZE) {
+        return Err(Error::InvalidParameter(
+            "Ciphertext length must be a non-zero multiple of 16 bytes".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// AES-256-CBC decrypt a blob with a ready key schedule
+fn decrypt_with(blob: &EncryptedBlob, aes_context: &mut [u32; ffi::AES_NUM_IVMRK_WORDS]) -> Result<Vec<u8>> {
+    let mut plaintext = vec![0u8; blob.ciphertext.len()];
+    let mut plaintext_len = plaintext.len();
+    let result = unsafe {
+        ffi::sevenzip_decrypt_data(
+            aes_context.as_mut_ptr(),
+            blob.iv.as_ptr(),
+            blob.ciphertext.as_ptr(),
+            blob.ciphertext.len(),
+            plaintext.as_mut_ptr(),
+            &mut plaintext_len,
+        )
+    };
+    aes_context.fill(0);
+    match result {
+        ffi::SevenZipErrorCode::SEVENZIP_OK => {
+            plaintext.truncate(plaintext_len);
+            Ok(plaintext)
+        }
+        ffi::SevenZipErrorCode::SEVENZIP_ERROR_EXTRACT => Err(Error::WrongPassword),
+        code => Err(Error::from_code(code)),
+    }
+}
+
+/// Incremental decryption of [`encrypt_blob`] output
+///
+/// Feed ciphertext in pieces of any size to [`update`](Self::update), which
+/// returns the plaintext decrypted so far, then call
+/// [`finalize`](Self::finalize) with the blob's MAC for the rest. The last
+/// block is held back across `update` calls because it carries the PKCS#7
+/// padding. Memory use stays at one block plus the piece being processed,
+/// however large the ciphertext.
+///
+/// The MAC can only be checked once all ciphertext has been seen, so the
+/// plaintext from `update` is unauthenticated until `finalize` succeeds;
+/// discard it if `finalize` fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::crypto::{encrypt_blob, Decryptor};
+///
+/// let blob = encrypt_blob(&vec![7u8; 100_000], "hunter2")?;
+/// let mut decryptor = Decryptor::new("hunter2", &blob.salt, &blob.iv)?;
+/// let mut plaintext = Vec::new();
+/// for piece in blob.ciphertext.chunks(4096) {
+///     plaintext.extend(decryptor.update(piece)?);
+/// }
+/// plaintext.extend(decryptor.finalize(&blob.mac)?);
+/// assert_eq!(plaintext.len(), 100_000);
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+pub struct Decryptor {
+    cipher: cbc::Decryptor<aes::Aes256>,
+    mac: Hmac<Sha256>,
+    pending: Vec<u8>,
+}
+
+impl Decryptor {
+    /// Start decrypting data encrypted with `password`, `salt` and `iv`
+    /// (the fields of an [`EncryptedBlob`])
+    pub fn new(password: &str, salt: &[u8], iv: &[u8; ffi::AES_BLOCK_SIZE]) -> Result<Self> {
+        let (mut key, mut aes_context) = derive(password, salt, ffi::sevenzip_init_decryption)?;
+        let cipher = cbc::Decryptor::<aes::Aes256>::new(&key.into(), iv.into());
+        let mac = blob_mac(&key, salt, iv);
+        key.fill(0);
+        aes_context.fill(0);
+        Ok(Self { cipher, mac, pending: Vec::new() })
+    }
+
+    /// Start decrypting data encrypted with a raw `key` and `iv`, as from
+    /// [`encrypt_blob_with_key`]
+    pub fn with_key(key: &[u8; ffi::AES_KEY_SIZE], iv: &[u8; ffi::AES_BLOCK_SIZE]) -> Self {
+        let cipher = cbc::Decryptor::<aes::Aes256>::new(key.into(), iv.into());
+        let mac = blob_mac(key, &[0; SALT_SIZE], iv);
+        Self { cipher, mac, pending: Vec::new() }
+    }
+
+    /// Decrypt the next piece of ciphertext
+    ///
+    /// Returns every complete block except the last one seen so far; the
+    /// output may be empty if the piece was smaller than a block.
+    pub fn update(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.mac.update(ciphertext);
+        self.pending.extend_from_slice(ciphertext);
+        // Keep the trailing partial block, or the last full block if none
+        let held = match self.pending.len() % ffi::AES_BLOCK_SIZE {
+            0 => ffi::AES_BLOCK_SIZE.min(self.pending.len()),
+            partial => partial,
+        };
+        let ready = self.pending.len() - held;
+
+        let mut plaintext: Vec<u8> = self.pending.drain(..ready).collect();
+        self.decrypt_in_place(&mut plaintext);
+        Ok(plaintext)
+    }
+
+    /// Check `mac` (the blob's [`EncryptedBlob::mac`]) against all the
+    /// ciphertext seen, then decrypt the held-back last block
+    ///
+    /// Fails with [`Error::WrongPassword`] if the MAC or the padding is
+    /// invalid, and with [`Error::InvalidParameter`] if the total ciphertext
+    /// length was not a non-zero multiple of 16 bytes.
+    pub fn finalize(mut self, mac: &[u8; MAC_SIZE]) -> Result<Vec<u8>> {
+        if self.pending.len() != ffi::AES_BLOCK_SIZE {
+            return Err(Error::InvalidParameter(
+                "Ciphertext length must be a non-zero multiple of 16 bytes".to_string(),
+            ));
+        }
+        self.mac.clone().verify_slice(mac).map_err(|_| Error::WrongPassword)?;
+
+        let mut last = std::mem::take(&mut self.pending);
+        self.decrypt_in_place(&mut last);
+        let padding = last[ffi::AES_BLOCK_SIZE - 1] as usize;
+        if padding == 0
+            || padding > ffi::AES_BLOCK_SIZE
+            || last[ffi::AES_BLOCK_SIZE - padding..].iter().any(|&b| b as usize != padding)
+        {
+            return Err(Error::WrongPassword);
+        }
+        last.truncate(ffi::AES_BLOCK_SIZE - padding);
+        Ok(last)
+    }
+
+    /// CBC-decrypt whole blocks, continuing the chain from earlier calls
+    fn decrypt_in_place(&mut self, data: &mut [u8]) {
+        for block in data.chunks_exact_mut(ffi::AES_BLOCK_SIZE) {
+            self.cipher.decrypt_block_mut(GenericArray::from_mut_slice(block));
+        }
+    }
+}
+
+/// C key setup shared by encryption and decryption
+type KeySetup = unsafe extern "C" fn(
+    *const std::os::raw::c_char,
+    *const u8,
+    usize,
+    *mut u8,
+    *mut u32,
+) -> ffi::SevenZipErrorCode;
+
+/// Derive the key and AES key schedule for `password` and `salt`
+fn derive(
+    password: &str,
+    salt: &[u8],
+    setup: KeySetup,
+) -> Result<([u8; ffi::AES_KEY_SIZE], Box<[u32; ffi::AES_NUM_IVMRK_WORDS]>)> {
+    if password.is_empty() {
+        return Err(Error::InvalidParameter("Password cannot be empty".to_string()));
+    }
+    let c_password = CString::new(password)
+        .map_err(|_| Error::InvalidParameter("Invalid password string".to_string()))?;
+
+    let mut key = [0u8; ffi::AES_KEY_SIZE];
+    let mut aes_context = Box::new([0u32; ffi::AES_NUM_IVMRK_WORDS]);
+    let result = unsafe {
+        setup(c_password.as_ptr(), salt.as_ptr(), salt.len(), key.as_mut_ptr(), aes_context.as_mut_ptr())
+    };
+    if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+        return Err(Error::from_code(result));
+    }
+    Ok((key, aes_context))
+}
+
+/// C key setup from a raw key, for encryption or decryption
+type RawKeySetup = unsafe extern "C" fn(*const u8, *mut u32) -> ffi::SevenZipErrorCode;
+
+/// Build the AES key schedule for a raw `key`
+fn key_schedule(key: &[u8; ffi::AES_KEY_SIZE], setup: RawKeySetup) -> Result<Box<[u32; ffi::AES_NUM_IVMRK_WORDS]>> {
+    let mut aes_context = Box::new([0u32; ffi::AES_NUM_IVMRK_WORDS]);
+    let result = unsafe { setup(key.as_ptr(), aes_context.as_mut_ptr()) };
+    if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+        return Err(Error::from_code(result));
+    }
+    Ok(aes_context)
+}
@@ -406,6 +406,58 @@ pub fn verify_password(
     Ok(())
 }
 
+/// Rounds exponent 7-Zip writes into new archives (2^19 SHA-256 rounds), as
+/// does [`CompressOptions::password`](crate::CompressOptions::password)
+pub const DEFAULT_7Z_CYCLES_POWER: u32 = 19;
+
+/// Derive a .7z AES-256 key the way 7-Zip does
+///
+/// The contexts above use their own PBKDF2 scheme over the UTF-8 bytes of
+/// the password, which suits data this crate encrypts and decrypts itself.
+/// Archives from the official tools instead use the 7zAES KDF: the password
+/// is encoded as UTF-16LE and hashed in `2^num_cycles_power` SHA-256 rounds
+/// together with the salt and a round counter. Use this function whenever a
+/// key must match 7-Zip's, otherwise passwords with non-ASCII characters
+/// won't agree. `salt` and `num_cycles_power` come from the AES coder
+/// properties in the archive header (7-Zip writes an empty salt and
+/// [`DEFAULT_7Z_CYCLES_POWER`]). Archives created with
+/// [`CompressOptions::password`](crate::CompressOptions::password) are
+/// encrypted under exactly this key.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::encryption::{derive_7z_key, DEFAULT_7Z_CYCLES_POWER};
+///
+/// let key = derive_7z_key("pä55wörd", &[], DEFAULT_7Z_CYCLES_POWER)?;
+/// assert_eq!(key.len(), 32);
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+pub fn derive_7z_key(password: &str, salt: &[u8], num_cycles_power: u32) -> Result<[u8; ffi::AES_KEY_SIZE]> {
+    let c_password = CString::new(password)
+        .map_err(|_| Error::InvalidParameter("Invalid password string".to_string()))?;
+    let mut key = [0u8; ffi::AES_KEY_SIZE];
+
+    let result = unsafe {
+        ffi::sevenzip_derive_7z_key(
+            c_password.as_ptr(),
+            salt.as_ptr(),
+            salt.len(),
+            num_cycles_power,
+            key.as_mut_ptr(),
+        )
+    };
+    if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+        return Err(Error::InvalidParameter(format!(
+            "Invalid 7z key derivation parameters: {} salt bytes, cycles power {}",
+            salt.len(),
+            num_cycles_power
+        )));
+    }
+
+    Ok(key)
+}
+
 // Ensure sensitive data is zeroed on drop
 impl Drop for EncryptionContext {
     fn drop(&mut self) {
@@ -442,6 +494,28 @@ mod tests {
         assert!(result.is_err());
     }
 
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_derive_7z_key_hashes_utf16_password() {
+        // Expected keys follow 7-Zip's 7zAES KDF over the UTF-16LE password
+        let key = derive_7z_key("pä55wörd", &[], DEFAULT_7Z_CYCLES_POWER).unwrap();
+        assert_eq!(hex(&key), "b874c2e103f69c508c1475b21d099ba6edc415c056b8f42997b57c2af4fa1721");
+
+        let salt: Vec<u8> = (0..16).collect();
+        let key = derive_7z_key("pä55wörd", &salt, 6).unwrap();
+        assert_eq!(hex(&key), "0fa6a1cd405a2d271c97fa52e3ac24dcacd5d8dbe8e239b6aeee6a440ee81441");
+
+        let key = derive_7z_key("ab", &[], 0x3F).unwrap();
+        assert_eq!(&key[..4], b"a\0b\0");
+        assert!(key[4..].iter().all(|&b| b == 0));
+
+        assert!(derive_7z_key("pw", &[0; 17], DEFAULT_7Z_CYCLES_POWER).is_err());
+        assert!(derive_7z_key("pw", &[], 25).is_err());
+    }
+
     #[test]
     fn test_decryption_context_validation() {
         let result = DecryptionContext::new("", &[1, 2, 3]);
@@ -34,6 +34,64 @@ pub enum Error {
     EncryptionError(String),
     /// Decryption failed (wrong password or corrupted data)
     DecryptionError(String),
+    /// Operation was cancelled by the caller
+    Cancelled,
+    /// The password does not decrypt the data
+    WrongPassword,
+    /// A source file changed while it was being archived
+    SourceChanged {
+        /// Path of the file whose contents no longer match the stored CRC
+        path: String,
+    },
+    /// The extraction output directory is missing or can't be written to
+    OutputNotWritable {
+        /// The output directory
+        path: String,
+    },
+    /// The archive to create exists and
+    /// [`ExistsPolicy::Fail`](crate::ExistsPolicy::Fail) forbids replacing it
+    AlreadyExists {
+        /// The existing archive
+        path: String,
+    },
+    /// The archive uses a newer 7z format version than this library reads;
+    /// upgrading the library may help
+    UnsupportedVersion {
+        /// Version (major, minor) stored in the archive
+        found: (u8, u8),
+        /// Newest version this build supports
+        supported: (u8, u8),
+    },
+    /// The options combine features that can't be used together; see the
+    /// supported combinations on [`StreamOptions`](crate::StreamOptions)
+    UnsupportedOptionCombination {
+        /// Which options clash and why
+        detail: String,
+    },
+    /// An entry's name can't be created on Windows: a reserved device name
+    /// such as `CON` or `aux.txt`, or a trailing dot or space (see
+    /// [`ExtractOptions::sanitize_names`](crate::ExtractOptions::sanitize_names))
+    ReservedName {
+        /// Entry name as stored in the archive
+        name: String,
+    },
+    /// The archive path names a directory, not an archive file
+    NotAFile {
+        /// The path given as the archive
+        path: String,
+    },
+    /// The archive file is empty (0 bytes), e.g. left behind by a failed
+    /// download or copy
+    EmptyArchive {
+        /// The empty archive file
+        path: String,
+    },
+    /// Some entries failed to extract; all others were written
+    /// (with [`ExtractOptions::continue_on_error`](crate::ExtractOptions::continue_on_error))
+    PartialExtraction {
+        /// Name of each failed entry and why it failed
+        failures: Vec<(String, Error)>,
+    },
 }
 
 impl Error {
@@ -64,6 +122,7 @@ impl Error {
             SevenZipErrorCode::SEVENZIP_ERROR_NOT_IMPLEMENTED => {
                 Error::NotImplemented("Feature not implemented".to_string())
             }
+            SevenZipErrorCode::SEVENZIP_ERROR_CANCELLED => Error::Cancelled,
             SevenZipErrorCode::SEVENZIP_ERROR_UNKNOWN => {
                 Error::Unknown("Unknown error".to_string())
             }
@@ -85,6 +144,17 @@ impl Error {
             Error::Io(_) => Error::Io(msg),
             Error::EncryptionError(_) => Error::EncryptionError(msg),
             Error::DecryptionError(_) => Error::DecryptionError(msg),
+            Error::Cancelled => Error::Cancelled,
+            Error::WrongPassword => Error::WrongPassword,
+            Error::SourceChanged { path } => Error::SourceChanged { path },
+            Error::OutputNotWritable { path } => Error::OutputNotWritable { path },
+            Error::AlreadyExists { path } => Error::AlreadyExists { path },
+            Error::UnsupportedVersion { found, supported } => Error::UnsupportedVersion { found, supported },
+            Error::UnsupportedOptionCombination { detail } => Error::UnsupportedOptionCombination { detail },
+            Error::ReservedName { name } => Error::ReservedName { name },
+            Error::NotAFile { path } => Error::NotAFile { path },
+            Error::EmptyArchive { path } => Error::EmptyArchive { path },
+            Error::PartialExtraction { failures } => Error::PartialExtraction { failures },
         }
     }
 }
@@ -103,6 +173,33 @@ impl fmt::Display for Error {
             Error::Io(msg) => write!(f, "IO error: {}", msg),
             Error::EncryptionError(msg) => write!(f, "Encryption failed: {}", msg),
             Error::DecryptionError(msg) => write!(f, "Decryption failed: {}", msg),
+            Error::Cancelled => write!(f, "Operation cancelled"),
+            Error::WrongPassword => write!(f, "Wrong password"),
+            Error::SourceChanged { path } => {
+                write!(f, "Source changed during archiving: {}", path)
+            }
+            Error::OutputNotWritable { path } => {
+                write!(f, "Output directory is not writable: {}", path)
+            }
+            Error::AlreadyExists { path } => write!(f, "Archive already exists: {}", path),
+            Error::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Unsupported 7z format version {}.{} (this library reads up to {}.{}); upgrade the library",
+                found.0, found.1, supported.0, supported.1
+            ),
+            Error::UnsupportedOptionCombination { detail } => {
+                write!(f, "Unsupported option combination: {}", detail)
+            }
+            Error::ReservedName { name } => write!(f, "Entry name is reserved on Windows: {}", name),
+            Error::NotAFile { path } => write!(f, "Archive path is a directory, not a file: {}", path),
+            Error::EmptyArchive { path } => write!(f, "Archive file is empty: {}", path),
+            Error::PartialExtraction { failures } => {
+                write!(f, "{} entries failed to extract", failures.len())?;
+                if let Some((name, err)) = failures.first() {
+                    write!(f, ", first {}: {}", name, err)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -22,10 +22,15 @@ pub enum SevenZipErrorCode {
     SEVENZIP_ERROR_COMPRESS = 5,
     SEVENZIP_ERROR_INVALID_PARAM = 6,
     SEVENZIP_ERROR_NOT_IMPLEMENTED = 7,
+    SEVENZIP_ERROR_CANCELLED = 8,
     SEVENZIP_ERROR_UNKNOWN = 99,
 }
 
 /// Archive entry information from C API
+///
+/// Every integer field is in host byte order: the C side decodes the 7z
+/// header's little-endian values byte by byte (and encodes them the same way
+/// when writing), so no swapping is needed here on big-endian targets.
 #[repr(C)]
 #[derive(Debug)]
 pub struct SevenZipEntry {
@@ -35,6 +40,15 @@ pub struct SevenZipEntry {
     pub modified_time: u64,
     pub attributes: u32,
     pub is_directory: c_int,
+    pub crc: u32,
+    pub has_crc: c_int,
+    pub method: [c_char; 32],
+}
+
+/// Opaque open-archive handle from C API
+#[repr(C)]
+pub struct SevenZipArchive {
+    _private: [u8; 0],
 }
 
 /// Archive list result from C API
@@ -45,10 +59,59 @@ pub struct SevenZipList {
     pub count: usize,
 }
 
+/// Archive-level summary from C API
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SevenZipArchiveInfo {
+    pub unpacked_size: u64,
+    pub packed_size: u64,
+    pub num_entries: u64,
+    pub num_folders: u64,
+    pub num_directories: u64,
+}
+
+/// A folder as stored in its archive, from C API
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SevenZipRawFolder {
+    pub record: *const u8,
+    pub record_size: usize,
+    pub pack_positions: *const u64,
+    pub num_pack_streams: u32,
+    pub unpack_sizes: *const u64,
+    pub num_unpack_sizes: u32,
+}
+
+/// One folder (solid block) of an open archive from C API
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SevenZipFolderInfo {
+    pub packed_offset: u64,
+    pub packed_size: u64,
+    pub codec: [c_char; 32],
+}
+
+/// 7zAES parameters of one folder from C API
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SevenZipEncryptionInfo {
+    pub encrypted: c_int,
+    pub cycles_power: u32,
+    pub salt_size: u32,
+    pub iv_size: u32,
+}
+
+/// Folder index the C API reports for entries without data
+pub const SEVENZIP_NO_FOLDER: u32 = u32::MAX;
+
 /// Progress callback function type
 pub type SevenZipProgressCallback =
     Option<unsafe extern "C" fn(completed: u64, total: u64, user_data: *mut c_void)>;
 
+/// Write callback receiving archive bytes in order (return non-zero to abort)
+pub type SevenZipWriteCallback =
+    Option<unsafe extern "C" fn(data: *const u8, size: usize, user_data: *mut c_void) -> c_int>;
+
 /// Byte-level progress callback for streaming operations
 pub type SevenZipBytesProgressCallback = Option<
     unsafe extern "C" fn(
@@ -61,16 +124,76 @@ pub type SevenZipBytesProgressCallback = Option<
     ),
 >;
 
+/// Per-entry result callback for archive tests (`passed` is 1 or 0)
+pub type SevenZipTestEntryCallback =
+    Option<unsafe extern "C" fn(entry_name: *const c_char, passed: c_int, user_data: *mut c_void)>;
+
+/// Finished split volume callback (return non-zero to abort)
+pub type SevenZipVolumeCallback = Option<
+    unsafe extern "C" fn(volume_path: *const c_char, size: u64, user_data: *mut c_void) -> c_int,
+>;
+
+/// Scratch file factory callback: writes the new file's path (return non-zero to abort)
+pub type SevenZipTempFileCallback = Option<
+    unsafe extern "C" fn(path: *mut c_char, path_size: usize, user_data: *mut c_void) -> c_int,
+>;
+
+/// Read callback supplying stream input (returns 0 on success, *size = 0 at end)
+pub type SevenZipReadCallback = Option<
+    unsafe extern "C" fn(buffer: *mut u8, size: *mut usize, user_data: *mut c_void) -> c_int,
+>;
+
 /// Compression levels
+///
+/// A plain integer rather than an enum: the C side also accepts the
+/// unnamed levels 2, 4, 6 and 8.
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SevenZipCompressionLevel(pub c_int);
+
+impl SevenZipCompressionLevel {
+    pub const SEVENZIP_LEVEL_STORE: Self = Self(0);
+    pub const SEVENZIP_LEVEL_FASTEST: Self = Self(1);
+    pub const SEVENZIP_LEVEL_FAST: Self = Self(3);
+    pub const SEVENZIP_LEVEL_NORMAL: Self = Self(5);
+    pub const SEVENZIP_LEVEL_MAXIMUM: Self = Self(7);
+    pub const SEVENZIP_LEVEL_ULTRA: Self = Self(9);
+}
+
+/// Codec for a group of files
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum SevenZipCompressionLevel {
-    SEVENZIP_LEVEL_STORE = 0,
-    SEVENZIP_LEVEL_FASTEST = 1,
-    SEVENZIP_LEVEL_FAST = 3,
-    SEVENZIP_LEVEL_NORMAL = 5,
-    SEVENZIP_LEVEL_MAXIMUM = 7,
-    SEVENZIP_LEVEL_ULTRA = 9,
+pub enum SevenZipMethod {
+    SEVENZIP_METHOD_LZMA2 = 0,
+    SEVENZIP_METHOD_COPY = 1,
+}
+
+/// File naming scheme for split volumes
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SevenZipVolumeNaming {
+    SEVENZIP_VOLUME_NAMING_NUMERIC = 0,
+    SEVENZIP_VOLUME_NAMING_PART_N = 1,
+    SEVENZIP_VOLUME_NAMING_ZIP_STYLE = 2,
+}
+
+/// Handling of FIFOs, devices and sockets during compression
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SevenZipSpecialFilePolicy {
+    SEVENZIP_SPECIAL_SKIP = 0,
+    SEVENZIP_SPECIAL_ERROR = 1,
+    SEVENZIP_SPECIAL_METADATA_ONLY = 2,
+}
+
+/// Order of the files in the solid stream
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SevenZipSortOrder {
+    SEVENZIP_SORT_NONE = 0,
+    SEVENZIP_SORT_BY_EXTENSION = 1,
+    SEVENZIP_SORT_BY_NAME = 2,
+    SEVENZIP_SORT_BY_SIZE = 3,
 }
 
 /// Advanced compression options
@@ -81,6 +204,40 @@ pub struct SevenZipCompressOptions {
     pub dict_size: u64,
     pub solid: c_int,
     pub password: *const c_char,
+    pub skip_empty_dirs: c_int,
+    pub method: c_int,
+    pub method_extensions: *const *const c_char,
+    pub extension_methods: *const c_int,
+    pub method_override_count: usize,
+    pub special_files: c_int,
+    pub time_budget_ms: u64,
+    pub codec_property_keys: *const *const c_char,
+    pub codec_property_values: *const *const c_char,
+    pub codec_property_count: usize,
+    pub input_entry_names: *const *const c_char,
+    pub flatten_names: c_int,
+    pub store_all_times: c_int,
+    pub dedup: c_int,
+    pub no_recursion: c_int,
+    pub limit_file_size: c_int,
+    pub min_file_size: u64,
+    pub max_file_size: u64,
+    pub sort_order: c_int,
+    pub compress_header: c_int,
+    pub detect_hardlinks: c_int,
+    pub kdf_iterations: u32,
+    pub encryption_key: *const u8,
+}
+
+impl SevenZipCompressOptions {
+    /// Options as set by `sevenzip_compress_options_init`
+    pub fn defaults() -> Self {
+        let mut options = std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            sevenzip_compress_options_init(options.as_mut_ptr());
+            options.assume_init()
+        }
+    }
 }
 
 /// Streaming compression options for large files and split archives
@@ -95,6 +252,12 @@ pub struct SevenZipStreamOptions {
     pub chunk_size: u64,
     pub temp_dir: *const c_char,
     pub delete_temp_on_error: c_int,
+    pub volume_naming: SevenZipVolumeNaming,
+    pub volume_callback: SevenZipVolumeCallback,
+    pub volume_user_data: *mut c_void,
+    pub temp_file_callback: SevenZipTempFileCallback,
+    pub temp_file_user_data: *mut c_void,
+    pub skip_empty_dirs: c_int,
 }
 
 /// AES encryption constants
@@ -114,6 +277,18 @@ extern "C" {
     /// Cleanup the 7z library
     pub fn sevenzip_cleanup();
 
+    /// Ask the operation running on the calling thread to stop (call from a progress callback)
+    pub fn sevenzip_request_abort();
+
+    /// Threads used by the last sevenzip_create_7z on the calling thread (0 = unknown)
+    pub fn sevenzip_last_threads_used() -> u32;
+
+    /// Estimated peak encoder memory of the last sevenzip_create_7z on the calling thread
+    pub fn sevenzip_last_peak_memory() -> u64;
+
+    /// LZMA2 dictionary size of the last sevenzip_create_7z on the calling thread (0 = none)
+    pub fn sevenzip_last_dict_size() -> u64;
+
     // ============================================================================
     // Archive Extraction Functions
     // ============================================================================
@@ -127,6 +302,15 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Extract a 7z archive encrypted with a raw AES-256 key
+    pub fn sevenzip_extract_with_key(
+        archive_path: *const c_char,
+        output_dir: *const c_char,
+        key: *const u8,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
     /// Extract specific files from a 7z archive
     pub fn sevenzip_extract_files(
         archive_path: *const c_char,
@@ -170,6 +354,9 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Initialize compression options with the defaults of `sevenzip_create_7z`
+    pub fn sevenzip_compress_options_init(options: *mut SevenZipCompressOptions);
+
     /// Create a standard .7z archive (compatible with 7-Zip)
     pub fn sevenzip_create_7z(
         archive_path: *const c_char,
@@ -180,6 +367,35 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Create a .7z archive, handing the bytes to a write callback in order
+    pub fn sevenzip_create_7z_to_callback(
+        input_paths: *const *const c_char,
+        level: SevenZipCompressionLevel,
+        options: *const SevenZipCompressOptions,
+        write_callback: SevenZipWriteCallback,
+        write_user_data: *mut c_void,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
+    /// LZMA2-compress a buffer in memory, reporting only the packed size
+    pub fn sevenzip_packed_size_of_buffer(
+        data: *const u8,
+        size: usize,
+        level: SevenZipCompressionLevel,
+        packed_size: *mut u64,
+    ) -> SevenZipErrorCode;
+
+    /// Create a single-entry 7z archive from a read callback
+    pub fn sevenzip_create_7z_from_stream(
+        archive_path: *const c_char,
+        entry_name: *const c_char,
+        level: SevenZipCompressionLevel,
+        options: *const SevenZipCompressOptions,
+        read_callback: SevenZipReadCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
     // ============================================================================
     // Streaming Compression (Large Files & Split Archives)
     // ============================================================================
@@ -217,6 +433,16 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Extract a 7z archive from an explicit, ordered list of volumes
+    pub fn sevenzip_extract_volumes(
+        volume_paths: *const *const c_char,
+        volume_count: usize,
+        output_dir: *const c_char,
+        password: *const c_char,
+        progress_callback: SevenZipBytesProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
     /// Compress files with streaming support and split archives
     pub fn sevenzip_compress_stream(
         archive_path: *const c_char,
@@ -249,6 +475,113 @@ extern "C" {
     /// Free memory allocated by sevenzip_list
     pub fn sevenzip_free_list(list: *mut SevenZipList);
 
+    /// Open an archive for entry-by-entry reading
+    pub fn sevenzip_archive_open(
+        archive_path: *const c_char,
+        password: *const c_char,
+        archive: *mut *mut SevenZipArchive,
+    ) -> SevenZipErrorCode;
+
+    /// Decrypt through an open archive with a raw AES-256 key
+    pub fn sevenzip_archive_set_key(archive: *mut SevenZipArchive, key: *const u8) -> SevenZipErrorCode;
+
+    /// List the entries of an open archive
+    pub fn sevenzip_archive_list(
+        archive: *mut SevenZipArchive,
+        list: *mut *mut SevenZipList,
+    ) -> SevenZipErrorCode;
+
+    /// List up to `count` entries of an open archive starting at `start`
+    pub fn sevenzip_archive_list_range(
+        archive: *mut SevenZipArchive,
+        start: u32,
+        count: u32,
+        list: *mut *mut SevenZipList,
+    ) -> SevenZipErrorCode;
+
+    /// Decompress one file entry of an open archive into a handle-owned buffer
+    pub fn sevenzip_archive_read_entry(
+        archive: *mut SevenZipArchive,
+        index: u32,
+        data: *mut *const u8,
+        size: *mut usize,
+    ) -> SevenZipErrorCode;
+
+    /// Decompress one file entry of an open archive in chunks to a write callback
+    pub fn sevenzip_archive_stream_entry(
+        archive: *mut SevenZipArchive,
+        index: u32,
+        write_callback: SevenZipWriteCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
+    pub fn sevenzip_archive_raw_folder(
+        archive: *mut SevenZipArchive,
+        folder_index: u32,
+        folder: *mut SevenZipRawFolder,
+    ) -> SevenZipErrorCode;
+
+    pub fn sevenzip_archive_read_folder(
+        archive: *mut SevenZipArchive,
+        folder_index: u32,
+        data: *mut *const u8,
+        size: *mut usize,
+    ) -> SevenZipErrorCode;
+
+    /// Number of folders (solid blocks) in an open archive
+    pub fn sevenzip_archive_folder_count(archive: *mut SevenZipArchive) -> u32;
+
+    /// Packed location and codec chain of one folder
+    pub fn sevenzip_archive_folder_info(
+        archive: *mut SevenZipArchive,
+        folder_index: u32,
+        info: *mut SevenZipFolderInfo,
+    ) -> SevenZipErrorCode;
+
+    /// Stored modification time of an entry as FILETIME ticks; returns 1 if present
+    pub fn sevenzip_archive_entry_filetime(archive: *mut SevenZipArchive, index: u32, filetime: *mut u64) -> c_int;
+
+    /// Stored creation time of an entry as FILETIME ticks; returns 1 if present
+    pub fn sevenzip_archive_entry_ctime(archive: *mut SevenZipArchive, index: u32, filetime: *mut u64) -> c_int;
+
+    /// Stored access time of an entry as FILETIME ticks; returns 1 if present
+    pub fn sevenzip_archive_entry_atime(archive: *mut SevenZipArchive, index: u32, filetime: *mut u64) -> c_int;
+
+    /// Get the 7zAES parameters of one folder
+    pub fn sevenzip_archive_folder_encryption(
+        archive: *mut SevenZipArchive,
+        folder_index: u32,
+        info: *mut SevenZipEncryptionInfo,
+    ) -> SevenZipErrorCode;
+
+    /// Folder holding an entry's data, or SEVENZIP_NO_FOLDER
+    pub fn sevenzip_archive_entry_folder(archive: *mut SevenZipArchive, index: u32) -> u32;
+
+    /// Find the entry holding a hard link entry's content (`index` itself
+    /// for other entries)
+    pub fn sevenzip_archive_hardlink_target(
+        archive: *mut SevenZipArchive,
+        index: u32,
+        target: *mut u32,
+    ) -> SevenZipErrorCode;
+
+    /// Close an archive handle
+    pub fn sevenzip_archive_close(archive: *mut SevenZipArchive);
+
+    /// Read archive-level totals from header metadata
+    pub fn sevenzip_get_archive_info(
+        archive_path: *const c_char,
+        password: *const c_char,
+        info: *mut SevenZipArchiveInfo,
+    ) -> SevenZipErrorCode;
+
+    /// Read archive-level totals of a split archive across its volumes
+    pub fn sevenzip_get_split_archive_info(
+        first_volume_path: *const c_char,
+        password: *const c_char,
+        info: *mut SevenZipArchiveInfo,
+    ) -> SevenZipErrorCode;
+
     /// Test archive integrity without extracting
     pub fn sevenzip_test_archive(
         archive_path: *const c_char,
@@ -257,6 +590,14 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Test archive integrity, reporting each file entry's result
+    pub fn sevenzip_test_archive_entries(
+        archive_path: *const c_char,
+        password: *const c_char,
+        entry_callback: SevenZipTestEntryCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
     // ============================================================================
     // Single File Compression/Decompression
     // ============================================================================
@@ -270,6 +611,16 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Compress a single file to LZMA2 format with a custom I/O buffer size
+    pub fn sevenzip_compress_file_ex(
+        input_path: *const c_char,
+        output_path: *const c_char,
+        level: SevenZipCompressionLevel,
+        buffer_size: usize,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
     /// Decompress a single LZMA2 file
     pub fn sevenzip_decompress_file(
         input_path: *const c_char,
@@ -278,6 +629,36 @@ extern "C" {
         user_data: *mut c_void,
     ) -> SevenZipErrorCode;
 
+    /// Decompress a single LZMA2 file with a custom I/O buffer size
+    pub fn sevenzip_decompress_file_ex(
+        input_path: *const c_char,
+        output_path: *const c_char,
+        buffer_size: usize,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
+    /// Compress a single file to LZMA2 behind a size header
+    pub fn sevenzip_compress_file_sized(
+        input_path: *const c_char,
+        output_path: *const c_char,
+        level: SevenZipCompressionLevel,
+        buffer_size: usize,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+    ) -> SevenZipErrorCode;
+
+    /// Decompress a file written by `sevenzip_compress_file_sized`,
+    /// checking its length against the header
+    pub fn sevenzip_decompress_file_sized(
+        input_path: *const c_char,
+        output_path: *const c_char,
+        buffer_size: usize,
+        progress_callback: SevenZipProgressCallback,
+        user_data: *mut c_void,
+        decoded_size: *mut u64,
+    ) -> SevenZipErrorCode;
+
     // ============================================================================
     // Encryption Functions (AES-256-CBC)
     // ============================================================================
@@ -290,6 +671,24 @@ extern "C" {
         aes_context: *mut u32,
     ) -> SevenZipErrorCode;
 
+    /// Initialize encryption context with password and a caller-chosen salt
+    pub fn sevenzip_init_encryption_with_salt(
+        password: *const c_char,
+        salt: *const u8,
+        salt_len: usize,
+        key: *mut u8,
+        aes_context: *mut u32,
+    ) -> SevenZipErrorCode;
+
+    /// Derive a .7z AES key with 7-Zip's KDF (UTF-16LE password)
+    pub fn sevenzip_derive_7z_key(
+        password: *const c_char,
+        salt: *const u8,
+        salt_len: usize,
+        num_cycles_power: u32,
+        key: *mut u8,
+    ) -> SevenZipErrorCode;
+
     /// Initialize decryption context with password and salt
     pub fn sevenzip_init_decryption(
         password: *const c_char,
@@ -299,6 +698,12 @@ extern "C" {
         aes_context: *mut u32,
     ) -> SevenZipErrorCode;
 
+    /// Initialize encryption context from a raw 256-bit key
+    pub fn sevenzip_init_encryption_with_key(key: *const u8, aes_context: *mut u32) -> SevenZipErrorCode;
+
+    /// Initialize decryption context from a raw 256-bit key
+    pub fn sevenzip_init_decryption_with_key(key: *const u8, aes_context: *mut u32) -> SevenZipErrorCode;
+
     /// Encrypt data using AES-256-CBC with PKCS#7 padding
     pub fn sevenzip_encrypt_data(
         aes_context: *mut u32,
@@ -406,6 +811,17 @@ extern "C" {
     
     /// Get library version string
     pub fn sevenzip_get_version() -> *const c_char;
+    
+    /// Check whether a codec (SevenZipMethod value) is compiled in
+    pub fn sevenzip_codec_supported(method: c_int) -> c_int;
+
+    // ============================================================================
+    // Header Encoding (ffi_internal.h)
+    // ============================================================================
+
+    /// Write a 7z variable-length number (up to 9 bytes) at `*buf` and
+    /// advance `*buf` past it
+    pub fn sevenzip_write_number(buf: *mut *mut u8, value: u64);
 }
 
 /// Detailed error information structure
@@ -432,9 +848,9 @@ mod tests {
 
     #[test]
     fn test_compression_level_values() {
-        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_STORE as i32, 0);
-        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_NORMAL as i32, 5);
-        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_ULTRA as i32, 9);
+        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_STORE.0, 0);
+        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_NORMAL.0, 5);
+        assert_eq!(SevenZipCompressionLevel::SEVENZIP_LEVEL_ULTRA.0, 9);
     }
 
     #[test]
@@ -0,0 +1,114 @@
+//! Archive format detection
+//!
+//! Identifies archive and compressed-stream formats from their magic bytes, so
+//! misnamed files can be dispatched correctly regardless of extension.
+
+use crate::error::Result;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of bytes needed to recognize every supported format
+///
+/// Most signatures sit in the first few bytes; tar's `ustar` marker is the
+/// exception at offset 257.
+const SNIFF_LEN: usize = 262;
+
+/// Offset of the `ustar` magic inside a tar header
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Archive or compressed-stream format identified by [`detect_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArchiveFormat {
+    /// 7z archive (`37 7A BC AF 27 1C`)
+    SevenZip,
+    /// Zip archive (`PK\x03\x04`, or `PK\x05\x06` when empty)
+    Zip,
+    /// xz stream (`FD 37 7A 58 5A 00`)
+    Xz,
+    /// gzip stream (`1F 8B`)
+    Gzip,
+    /// bzip2 stream (`BZh`)
+    Bzip2,
+    /// Zstandard frame (`28 B5 2F FD`)
+    Zstd,
+    /// RAR archive, v4 or v5 (`Rar!\x1A\x07`)
+    Rar,
+    /// POSIX tar archive (`ustar` at offset 257)
+    Tar,
+    /// Not a recognized format
+    Unknown,
+}
+
+impl ArchiveFormat {
+    /// Identify a format from the leading bytes of a file
+    ///
+    /// Pass at least the first 262 bytes to recognize tar; shorter inputs
+    /// (including empty ones) are matched against what is available.
+    pub fn from_magic(header: &[u8]) -> Self {
+        const SIGNATURES: &[(&[u8], ArchiveFormat)] = &[
+            (&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C], ArchiveFormat::SevenZip),
+            (b"PK\x03\x04", ArchiveFormat::Zip),
+            (b"PK\x05\x06", ArchiveFormat::Zip),
+            (b"PK\x07\x08", ArchiveFormat::Zip),
+            (&[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00], ArchiveFormat::Xz),
+            (&[0x1F, 0x8B], ArchiveFormat::Gzip),
+            (b"BZh", ArchiveFormat::Bzip2),
+            (&[0x28, 0xB5, 0x2F, 0xFD], ArchiveFormat::Zstd),
+            (b"Rar!\x1A\x07", ArchiveFormat::Rar),
+        ];
+
+        if let Some(&(_, format)) = SIGNATURES.iter().find(|(magic, _)| header.starts_with(magic)) {
+            return format;
+        }
+
+        if header
+            .get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5)
+            .is_some_and(|magic| magic == b"ustar")
+        {
+            return ArchiveFormat::Tar;
+        }
+
+        ArchiveFormat::Unknown
+    }
+
+    /// Conventional file extension for the format, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            ArchiveFormat::SevenZip => Some("7z"),
+            ArchiveFormat::Zip => Some("zip"),
+            ArchiveFormat::Xz => Some("xz"),
+            ArchiveFormat::Gzip => Some("gz"),
+            ArchiveFormat::Bzip2 => Some("bz2"),
+            ArchiveFormat::Zstd => Some("zst"),
+            ArchiveFormat::Rar => Some("rar"),
+            ArchiveFormat::Tar => Some("tar"),
+            ArchiveFormat::Unknown => None,
+        }
+    }
+}
+
+/// Detect the format of a file by sniffing its magic bytes
+///
+/// Only the first few hundred bytes are read; the extension is ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::{detect_format, ArchiveFormat};
+///
+/// match detect_format("download.bin")? {
+///     ArchiveFormat::SevenZip => println!("7z archive"),
+///     ArchiveFormat::Zip => println!("zip archive"),
+///     other => println!("something else: {:?}", other),
+/// }
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+pub fn detect_format(path: impl AsRef<Path>) -> Result<ArchiveFormat> {
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    File::open(path.as_ref())?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)?;
+
+    Ok(ArchiveFormat::from_magic(&header))
+}
@@ -0,0 +1,142 @@
+//! Archive digests computed while the archive is written
+//!
+//! The C writer produces the archive strictly in order, so its bytes can be
+//! hashed on their way to disk instead of reading the finished file back.
+
+use crate::archive::{ArchiveOutput, CompressOptions, CompressReport, CompressionLevel, PartialOutputGuard, SevenZip};
+use crate::error::Result;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Hash algorithm for [`SevenZip::create_archive_hashed`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HashKind {
+    /// SHA-256 (32-byte digest)
+    Sha256,
+    /// BLAKE3 (32-byte digest)
+    Blake3,
+}
+
+impl SevenZip {
+    /// Create a 7z archive and return the digest of the archive file
+    ///
+    /// The digest is computed over the output bytes as they are written, so
+    /// publishing a checksum costs no second pass over the finished archive.
+    /// It equals the digest of the file on disk, e.g. what `sha256sum`
+    /// prints for [`HashKind::Sha256`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, HashKind};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let (report, digest) = sz.create_archive_hashed(
+    ///     "release.7z",
+    ///     &["dist"],
+    ///     CompressionLevel::Normal,
+    ///     None,
+    ///     HashKind::Sha256,
+    /// )?;
+    /// let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    /// println!("{}  release.7z ({} bytes)", hex, report.output_bytes);
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_archive_hashed(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+        hasher: HashKind,
+    ) -> Result<(CompressReport, Vec<u8>)> {
+        let archive_path = archive_path.as_ref();
+        let guard = PartialOutputGuard::new(archive_path, true);
+
+        let mut writer = HashingWriter::new(BufWriter::new(File::create(guard.path())?), hasher);
+        self.write_archive(ArchiveOutput::Writer(&mut writer), input_paths, level, options)?;
+        writer.inner.flush()?;
+        drop(writer.inner);
+
+        if let Some(opts) = options.filter(|o| o.verify_crc_after) {
+            self.verify_sources_unchanged(guard.path(), input_paths, opts)?;
+        }
+        if let Some(percent) = options.and_then(|o| o.recovery_percent) {
+            // Only the appended record needs hashing, not the whole file again
+            let archive_len = std::fs::metadata(guard.path())?.len();
+            crate::recovery::append_recovery_record(guard.path(), percent)?;
+            let mut record = File::open(guard.path())?;
+            record.seek(SeekFrom::Start(archive_len))?;
+            io::copy(&mut record, &mut writer.state)?;
+        }
+        let digest = writer.state.finish();
+
+        guard.finish()?;
+        let report = self.compress_report(archive_path, input_paths, options)?;
+        Ok((report, digest))
+    }
+}
+
+/// Writer that hashes everything passing through it
+struct HashingWriter<W> {
+    inner: W,
+    state: HashState,
+}
+
+enum HashState {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W, kind: HashKind) -> Self {
+        let state = match kind {
+            HashKind::Sha256 => HashState::Sha256(Sha256::new()),
+            HashKind::Blake3 => HashState::Blake3(Box::new(blake3::Hasher::new())),
+        };
+        Self { inner, state }
+    }
+}
+
+impl HashState {
+    fn update(&mut self, buf: &[u8]) {
+        match self {
+            HashState::Sha256(hasher) => hasher.update(buf),
+            HashState::Blake3(hasher) => {
+                hasher.update(buf);
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            HashState::Sha256(hasher) => hasher.finalize().to_vec(),
+            HashState::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Write for HashState {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.state.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
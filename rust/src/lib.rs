@@ -7,6 +7,8 @@
 //! - **Extract** .7z archives (100% compatible with 7-Zip)
 //! - **Create** standard 7z archives
 //! - **List** archive contents
+//! - **Extract and list** .zip archives (ZipCrypto and AES encrypted)
+//! - **Tar passthrough** .tar.7z archives preserving Unix metadata
 //! - **Compress** single files or directories
 //! - **Encrypt** archives with AES-256-CBC
 //! - **Split** archives for multi-volume support (NEW!)
@@ -152,8 +154,11 @@
 //!
 //! - [`archive`] - High-level archive operations
 //! - [`advanced`] - Split archives, raw LZMA, enhanced error reporting (NEW!)
+//! - [`format`] - Archive format detection from magic bytes
 //! - [`encryption`] - AES-256 encryption (C library backend)
 //! - [`encryption_native`] - AES-256 encryption (pure Rust, recommended)
+//! - [`crypto`] - Self-contained encrypted blobs (encrypt-only, no archive)
+//! - `async_progress` - Async progress streams (requires the `tokio` feature)
 //! - [`error`] - Error types and result handling
 //! - [`ffi`] - Raw FFI bindings (internal use)
 
@@ -167,20 +172,82 @@ mod ffi;
 pub mod error;
 pub mod archive;
 pub mod advanced;
+pub mod format;
 pub mod encryption;
 pub mod encryption_native;
+pub mod crypto;
+#[cfg(feature = "tokio")]
+pub mod async_progress;
+mod zip_archive;
+mod tar_archive;
+mod hashing;
+mod recovery;
+mod raw_copy;
+mod progress;
+mod cancel;
+mod retry;
+mod password;
+mod threads;
+#[cfg(feature = "signing")]
+mod signing;
 
 // Re-export main types
 pub use error::{Error, Result};
 pub use archive::{
     SevenZip,
     ArchiveEntry,
+    ArchiveEntryFull,
+    ArchiveFingerprint,
+    ChangeAction,
+    ChangePreview,
+    EntriesReader,
+    ArchiveReader,
+    EntryReader,
+    ExistsPolicy,
+    RenameHook,
+    WindowsAttributes,
     CompressionLevel,
     CompressOptions,
+    CompressionMethod,
+    VolumeNaming,
+    SpecialFilePolicy,
+    CompressReport,
+    ArchiveDiff,
+    FolderInfo,
+    ArchiveInfo,
+    HostOs,
+    EncryptionInfo,
+    PathMode,
+    SortKey,
+    SortOrder,
+    TreeNode,
+    ExtractOptions,
+    ExtractStatus,
+    ExtractedFile,
+    ExtractWarning,
+    LibConfig,
+    SingleFileOptions,
     StreamOptions,
     ProgressCallback,
+    ProgressHandle,
+    WalkProgress,
+    VolumeHook,
+    TempFileFactory,
     BytesProgressCallback,
+    EntrySender,
+    ENTRY_CHANNEL_CAPACITY,
 };
+pub use format::{detect_format, ArchiveFormat};
+pub use hashing::HashKind;
+pub use progress::{DetailedProgress, ProgressTracker};
+pub use cancel::CancelToken;
+pub use retry::{RetryPolicy, RetryReader};
+pub use password::Password;
+pub use threads::{global_thread_limit, set_global_thread_limit};
+#[cfg(feature = "tokio")]
+pub use async_progress::Progress;
+#[cfg(feature = "signing")]
+pub use signing::{sign_archive, verify_signature, SIGNATURE_LENGTH};
 
 // Re-export encryption - prefer native Rust implementation
 pub use encryption_native::{
@@ -219,6 +286,25 @@ pub fn version() -> &'static str {
     VERSION
 }
 
+/// Codecs the linked C library can compress with
+///
+/// Use this to offer only working choices, e.g. in a method picker; a codec
+/// missing here fails at runtime.
+///
+/// # Example
+///
+/// ```
+/// use seven_zip::{supported_methods, CompressionMethod};
+///
+/// assert!(supported_methods().contains(&CompressionMethod::Lzma2));
+/// ```
+pub fn supported_methods() -> Vec<CompressionMethod> {
+    CompressionMethod::ALL
+        .into_iter()
+        .filter(|&method| unsafe { ffi::sevenzip_codec_supported(ffi::SevenZipMethod::from(method) as i32) } != 0)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
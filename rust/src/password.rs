@@ -0,0 +1,97 @@
+//! Loading archive passwords without putting them on the command line
+//!
+//! Arguments show up in process listings, so CI jobs and scripts should hand
+//! secrets over in a file or an environment variable instead. [`Password`]
+//! reads them from there and wipes its copy when dropped.
+
+use crate::error::{Error, Result};
+use std::io::Read;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// An archive password, zeroed in memory when dropped
+///
+/// Dereferences to `str`, so `&password` goes wherever a `&str` password
+/// does, and `Some(password.as_str())` to the methods taking
+/// `Option<&str>`. `Debug` output never shows the secret.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::{Password, SevenZip};
+///
+/// // e.g. mytool extract --password-file /run/secrets/archive
+/// let password = Password::from_file("/run/secrets/archive")?;
+/// let sz = SevenZip::new()?;
+/// sz.extract_with_password("evidence.7z", "output", Some(password.as_str()), None)?;
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct Password(Zeroizing<String>);
+
+impl Password {
+    /// Wrap a password the caller already holds
+    pub fn new(password: impl Into<String>) -> Self {
+        Self(Zeroizing::new(password.into()))
+    }
+
+    /// Read the password from a file, dropping one trailing newline
+    /// (`\n` or `\r\n`) as left by `echo` or an editor
+    ///
+    /// Returns [`Error::InvalidParameter`] if the file is not UTF-8 or holds
+    /// an empty password.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)?;
+        // Sized up front, so reading never leaves a reallocated copy behind
+        let len = file.metadata()?.len() as usize;
+        let mut bytes = Zeroizing::new(Vec::with_capacity(len + 1));
+        file.read_to_end(&mut bytes)?;
+
+        let text = std::str::from_utf8(&bytes).map_err(|_| {
+            Error::InvalidParameter(format!("Password file is not valid UTF-8: {}", path.display()))
+        })?;
+        Self::from_secret(text, || format!("Password file is empty: {}", path.display()))
+    }
+
+    /// Read the password from the environment variable `var`, dropping one
+    /// trailing newline
+    ///
+    /// Returns [`Error::InvalidParameter`] if the variable is unset, not
+    /// UTF-8 or empty.
+    pub fn from_env(var: &str) -> Result<Self> {
+        let value = Zeroizing::new(std::env::var(var).map_err(|e| {
+            Error::InvalidParameter(format!("Cannot read password from environment variable {}: {}", var, e))
+        })?);
+        Self::from_secret(&value, || format!("Environment variable {} is empty", var))
+    }
+
+    fn from_secret(text: &str, empty: impl FnOnce() -> String) -> Result<Self> {
+        let text = text
+            .strip_suffix('\n')
+            .map_or(text, |rest| rest.strip_suffix('\r').unwrap_or(rest));
+        if text.is_empty() {
+            return Err(Error::InvalidParameter(empty()));
+        }
+        Ok(Self::new(text))
+    }
+
+    /// The password, for the `password` arguments of the archive methods
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Password {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Password(..)")
+    }
+}
@@ -0,0 +1,119 @@
+//! Throughput and ETA on top of raw byte-count progress
+//!
+//! The C library only reports byte counts. [`ProgressTracker`] timestamps
+//! each tick and derives speed and time remaining from them, so progress UIs
+//! don't each redo the arithmetic.
+
+use crate::archive::BytesProgressCallback;
+use std::time::{Duration, Instant};
+
+/// Bytes per megabyte for the `*_mbps` fields
+const BYTES_PER_MB: f64 = 1_000_000.0;
+
+/// One progress update with speed and ETA, see [`ProgressTracker`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedProgress {
+    /// Bytes processed so far
+    pub processed: u64,
+    /// Total bytes to process (0 if unknown)
+    pub total: u64,
+    /// Speed since the previous update, in MB/s (10^6 bytes per second)
+    pub instantaneous_mbps: f64,
+    /// Speed since the tracker started, in MB/s
+    pub average_mbps: f64,
+    /// Time left at the average speed; `None` while the total is unknown or
+    /// nothing has been processed yet
+    pub eta: Option<Duration>,
+}
+
+impl DetailedProgress {
+    /// Wrap `callback` as a [`BytesProgressCallback`] for the streaming APIs
+    ///
+    /// The clock starts when this is called, so call it right before
+    /// starting the operation.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel, DetailedProgress};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.create_archive_streaming(
+    ///     "backup.7z",
+    ///     &["data"],
+    ///     CompressionLevel::Normal,
+    ///     None,
+    ///     Some(DetailedProgress::bytes_callback(|p| {
+    ///         println!("{:.1} MB/s, ETA {:?}", p.average_mbps, p.eta);
+    ///     })),
+    /// )?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn bytes_callback(mut callback: impl FnMut(&DetailedProgress) + Send + 'static) -> BytesProgressCallback {
+        let mut tracker = ProgressTracker::new();
+        Box::new(move |processed, total, _file_bytes, _file_total, _name| {
+            callback(&tracker.update(processed, total));
+        })
+    }
+}
+
+/// Derives [`DetailedProgress`] from successive byte counts
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    started: Instant,
+    last: Option<(Instant, u64)>,
+    instantaneous_mbps: f64,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    /// Start tracking now
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Start tracking at `started`, e.g. when the operation really began
+    pub fn starting_at(started: Instant) -> Self {
+        Self { started, last: None, instantaneous_mbps: 0.0 }
+    }
+
+    /// Record a tick at the current time
+    pub fn update(&mut self, processed: u64, total: u64) -> DetailedProgress {
+        self.update_at(processed, total, Instant::now())
+    }
+
+    /// Record a tick observed at `now`
+    pub fn update_at(&mut self, processed: u64, total: u64, now: Instant) -> DetailedProgress {
+        let (last_time, last_processed) = self.last.unwrap_or((self.started, 0));
+        let interval = now.saturating_duration_since(last_time).as_secs_f64();
+        // Ticks can arrive faster than the clock resolution; keep the last
+        // speed rather than dividing by zero
+        if interval > 0.0 {
+            let bytes = processed.saturating_sub(last_processed) as f64;
+            self.instantaneous_mbps = bytes / interval / BYTES_PER_MB;
+        }
+        self.last = Some((now, processed));
+
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        let average_bps = if elapsed > 0.0 { processed as f64 / elapsed } else { 0.0 };
+        let eta = if total == 0 || average_bps <= 0.0 {
+            None
+        } else {
+            let remaining = total.saturating_sub(processed) as f64;
+            Some(Duration::from_secs_f64(remaining / average_bps))
+        };
+
+        DetailedProgress {
+            processed,
+            total,
+            instantaneous_mbps: self.instantaneous_mbps,
+            average_mbps: average_bps / BYTES_PER_MB,
+            eta,
+        }
+    }
+}
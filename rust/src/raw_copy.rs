@@ -0,0 +1,276 @@
+//! Writing 7z archives from packed folders of other archives
+//!
+//! [`SevenZip::copy_entries`](crate::SevenZip::copy_entries) moves folders
+//! between archives byte for byte, so their entries are never decoded and
+//! encoded again. This module writes the archive around them: the packed
+//! streams back to back after the signature header, then a plain
+//! (uncompressed) header describing folders and files.
+
+use crate::error::Result;
+use crate::ffi;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const SIGNATURE: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+const FORMAT_VERSION: [u8; 2] = [0, 4];
+
+// Property IDs of the 7z header
+const ID_END: u8 = 0x00;
+const ID_HEADER: u8 = 0x01;
+const ID_MAIN_STREAMS_INFO: u8 = 0x04;
+const ID_FILES_INFO: u8 = 0x05;
+const ID_PACK_INFO: u8 = 0x06;
+const ID_UNPACK_INFO: u8 = 0x07;
+const ID_SUBSTREAMS_INFO: u8 = 0x08;
+const ID_SIZE: u8 = 0x09;
+const ID_CRC: u8 = 0x0A;
+const ID_FOLDER: u8 = 0x0B;
+const ID_CODERS_UNPACK_SIZE: u8 = 0x0C;
+const ID_NUM_UNPACK_STREAM: u8 = 0x0D;
+const ID_EMPTY_STREAM: u8 = 0x0E;
+const ID_EMPTY_FILE: u8 = 0x0F;
+const ID_NAME: u8 = 0x11;
+const ID_CTIME: u8 = 0x12;
+const ID_ATIME: u8 = 0x13;
+const ID_MTIME: u8 = 0x14;
+const ID_WIN_ATTRIBUTES: u8 = 0x15;
+
+/// A folder as stored in its source archive
+#[derive(Debug, Clone)]
+pub(crate) struct RawFolder {
+    /// Folder record from the source header: coders, bonds, packed stream indices
+    pub record: Vec<u8>,
+    /// Absolute offset of the first packed stream in the source file
+    pub packed_offset: u64,
+    /// Size of each packed stream; they are stored back to back
+    pub pack_sizes: Vec<u64>,
+    /// Output size of each coder
+    pub unpack_sizes: Vec<u64>,
+}
+
+/// File metadata carried into the new header
+#[derive(Debug, Clone)]
+pub(crate) struct CopiedFile {
+    pub name: String,
+    pub size: u64,
+    pub crc32: Option<u32>,
+    /// Modification time as FILETIME ticks
+    pub mtime: Option<u64>,
+    /// Creation time as FILETIME ticks
+    pub ctime: Option<u64>,
+    /// Access time as FILETIME ticks
+    pub atime: Option<u64>,
+    pub attributes: Option<u32>,
+    pub is_directory: bool,
+}
+
+/// A folder to copy and the files it holds, in stream order
+#[derive(Debug, Clone)]
+pub(crate) struct CopiedFolder {
+    pub source: PathBuf,
+    pub folder: RawFolder,
+    pub files: Vec<CopiedFile>,
+}
+
+/// Write a new archive at `path` from `folders` plus entries without data
+/// (directories and empty files)
+pub(crate) fn write_archive(path: &Path, folders: &[CopiedFolder], empty: &[CopiedFile]) -> Result<()> {
+    let mut out = File::create(path)?;
+    out.write_all(&[0u8; 32])?;
+
+    let mut packed_total = 0u64;
+    for copied in folders {
+        let mut source = File::open(&copied.source)?;
+        source.seek(SeekFrom::Start(copied.folder.packed_offset))?;
+        let size: u64 = copied.folder.pack_sizes.iter().sum();
+        let copied_bytes = io::copy(&mut (&mut source).take(size), &mut out)?;
+        if copied_bytes != size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "packed stream ends early").into());
+        }
+        packed_total += size;
+    }
+
+    let header = encode_header(folders, empty);
+    out.write_all(&header)?;
+
+    let mut start_header = Vec::with_capacity(20);
+    start_header.extend_from_slice(&packed_total.to_le_bytes());
+    start_header.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    start_header.extend_from_slice(&crc32fast::hash(&header).to_le_bytes());
+
+    out.seek(SeekFrom::Start(0))?;
+    out.write_all(&SIGNATURE)?;
+    out.write_all(&FORMAT_VERSION)?;
+    out.write_all(&crc32fast::hash(&start_header).to_le_bytes())?;
+    out.write_all(&start_header)?;
+    out.sync_all()?;
+    Ok(())
+}
+
+fn encode_header(folders: &[CopiedFolder], empty: &[CopiedFile]) -> Vec<u8> {
+    let mut h = vec![ID_HEADER];
+
+    if !folders.is_empty() {
+        h.push(ID_MAIN_STREAMS_INFO);
+
+        h.push(ID_PACK_INFO);
+        write_number(&mut h, 0);
+        let pack_sizes: Vec<u64> = folders.iter().flat_map(|f| f.folder.pack_sizes.iter().copied()).collect();
+        write_number(&mut h, pack_sizes.len() as u64);
+        h.push(ID_SIZE);
+        for size in &pack_sizes {
+            write_number(&mut h, *size);
+        }
+        h.push(ID_END);
+
+        h.push(ID_UNPACK_INFO);
+        h.push(ID_FOLDER);
+        write_number(&mut h, folders.len() as u64);
+        h.push(0); // not external
+        for copied in folders {
+            h.extend_from_slice(&copied.folder.record);
+        }
+        h.push(ID_CODERS_UNPACK_SIZE);
+        for copied in folders {
+            for size in &copied.folder.unpack_sizes {
+                write_number(&mut h, *size);
+            }
+        }
+        h.push(ID_END);
+
+        // No folder CRCs, so every file gets its own digest here
+        h.push(ID_SUBSTREAMS_INFO);
+        h.push(ID_NUM_UNPACK_STREAM);
+        for copied in folders {
+            write_number(&mut h, copied.files.len() as u64);
+        }
+        if folders.iter().any(|f| f.files.len() > 1) {
+            h.push(ID_SIZE);
+            for copied in folders {
+                if let Some((_, leading)) = copied.files.split_last() {
+                    for file in leading {
+                        write_number(&mut h, file.size);
+                    }
+                }
+            }
+        }
+        let files: Vec<&CopiedFile> = folders.iter().flat_map(|f| f.files.iter()).collect();
+        h.push(ID_CRC);
+        write_defined(&mut h, &files.iter().map(|f| f.crc32.is_some()).collect::<Vec<_>>());
+        for crc in files.iter().filter_map(|f| f.crc32) {
+            h.extend_from_slice(&crc.to_le_bytes());
+        }
+        h.push(ID_END);
+
+        h.push(ID_END);
+    }
+
+    // Entries without data come first; the rest take the substreams in order
+    let files: Vec<&CopiedFile> = empty.iter().chain(folders.iter().flat_map(|f| f.files.iter())).collect();
+    h.push(ID_FILES_INFO);
+    write_number(&mut h, files.len() as u64);
+
+    if !empty.is_empty() {
+        let empty_stream: Vec<bool> = (0..files.len()).map(|i| i < empty.len()).collect();
+        write_property(&mut h, ID_EMPTY_STREAM, &bit_vector(&empty_stream));
+        let empty_file: Vec<bool> = empty.iter().map(|f| !f.is_directory).collect();
+        if empty_file.contains(&true) {
+            write_property(&mut h, ID_EMPTY_FILE, &bit_vector(&empty_file));
+        }
+    }
+
+    let mut names = vec![0u8]; // not external
+    for file in &files {
+        for unit in file.name.encode_utf16().chain(std::iter::once(0)) {
+            names.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+    write_property(&mut h, ID_NAME, &names);
+
+    let times = |time: fn(&CopiedFile) -> Option<u64>| files.iter().map(|f| time(f)).collect::<Vec<_>>();
+    for (id, times) in [
+        (ID_CTIME, times(|f| f.ctime)),
+        (ID_ATIME, times(|f| f.atime)),
+        (ID_MTIME, times(|f| f.mtime)),
+    ] {
+        if times.iter().any(Option::is_some) {
+            write_property(&mut h, id, &optional_values(&times, |t| t.to_le_bytes().to_vec()));
+        }
+    }
+    let attributes: Vec<Option<u32>> = files.iter().map(|f| f.attributes).collect();
+    if attributes.iter().any(Option::is_some) {
+        write_property(&mut h, ID_WIN_ATTRIBUTES, &optional_values(&attributes, |a| a.to_le_bytes().to_vec()));
+    }
+
+    h.push(ID_END);
+    h.push(ID_END);
+    h
+}
+
+/// 7z variable-length number, encoded by the C library's header writer
+fn write_number(out: &mut Vec<u8>, value: u64) {
+    let mut buf = [0u8; 9];
+    let mut end = buf.as_mut_ptr();
+    // SAFETY: a number takes at most 9 bytes, and `end` stays within `buf`
+    let len = unsafe {
+        ffi::sevenzip_write_number(&mut end, value);
+        end.offset_from(buf.as_ptr()) as usize
+    };
+    out.extend_from_slice(&buf[..len]);
+}
+
+fn write_property(out: &mut Vec<u8>, id: u8, data: &[u8]) {
+    out.push(id);
+    write_number(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+/// Bits packed most significant first
+fn bit_vector(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, set)| **set) {
+        bytes[i / 8] |= 0x80 >> (i % 8);
+    }
+    bytes
+}
+
+/// "All defined" flag, followed by a bit vector unless it is set
+fn write_defined(out: &mut Vec<u8>, defined: &[bool]) {
+    if defined.iter().all(|d| *d) {
+        out.push(1);
+    } else {
+        out.push(0);
+        out.extend_from_slice(&bit_vector(defined));
+    }
+}
+
+/// Defined vector, external flag, then the defined values
+fn optional_values<T: Copy>(values: &[Option<T>], encode: impl Fn(T) -> Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_defined(&mut data, &values.iter().map(Option::is_some).collect::<Vec<_>>());
+    data.push(0); // not external
+    for value in values.iter().flatten() {
+        data.extend_from_slice(&encode(*value));
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_number_encoding() {
+        let encode = |value| {
+            let mut out = Vec::new();
+            write_number(&mut out, value);
+            out
+        };
+        assert_eq!(encode(0x7F), [0x7F]);
+        assert_eq!(encode(0x80), [0x80, 0x80]);
+        assert_eq!(encode(0x3FFF), [0xBF, 0xFF]);
+        assert_eq!(encode(0x4000), [0xC0, 0x00, 0x40]);
+        assert_eq!(encode(u64::MAX), [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+}
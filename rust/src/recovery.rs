@@ -0,0 +1,428 @@
+//! Recovery records: parity data appended to a 7z archive
+//!
+//! The archive is cut into at most 255 equal blocks, each with a CRC32, and
+//! Cauchy Reed-Solomon parity blocks over GF(256) are appended after the 7z
+//! data. 7z readers stop at the end header, so the archive stays readable
+//! by any tool. Any mix of damaged data and parity blocks can be rebuilt as
+//! long as no more blocks are damaged than there are parity blocks.
+//!
+//! Record layout after the archive bytes:
+//!
+//! ```text
+//! parity blocks      parity_blocks * block_size bytes
+//! block CRC32s       (data_blocks + parity_blocks) * 4 bytes, little-endian
+//! footer             magic, data_len u64, block_size u32, data_blocks u32,
+//!                    parity_blocks u32, CRC32 of CRC table and footer u32
+//! ```
+
+use crate::archive::SevenZip;
+use crate::error::{Error, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const FOOTER_MAGIC: &[u8; 8] = b"7zRecRec";
+const FOOTER_SIZE: usize = 8 + 8 + 4 + 4 + 4 + 4;
+
+/// GF(256) codewords hold at most 255 symbols (data + parity blocks)
+const MAX_BLOCKS: usize = 255;
+
+/// Bytes of every block processed at once, bounding memory to
+/// `(data_blocks + parity_blocks) * CHUNK_SIZE`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// GF(256) log and exp tables for the polynomial x^8 + x^4 + x^3 + x^2 + 1
+struct Gf {
+    log: [u8; 256],
+    exp: [u8; 512],
+}
+
+const GF: Gf = {
+    let mut log = [0u8; 256];
+    let mut exp = [0u8; 512];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11D;
+        }
+        i += 1;
+    }
+    Gf { log, exp }
+};
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    GF.exp[GF.log[a as usize] as usize + GF.log[b as usize] as usize]
+}
+
+fn gf_inv(a: u8) -> u8 {
+    GF.exp[255 - GF.log[a as usize] as usize]
+}
+
+/// `dst ^= coefficient * src`, byte by byte
+fn mul_add(dst: &mut [u8], src: &[u8], coefficient: u8) {
+    if coefficient == 0 {
+        return;
+    }
+    let log_c = GF.log[coefficient as usize] as usize;
+    for (d, &s) in dst.iter_mut().zip(src) {
+        if s != 0 {
+            *d ^= GF.exp[log_c + GF.log[s as usize] as usize];
+        }
+    }
+}
+
+/// Parity coefficient of data block `data` in parity block `parity`
+///
+/// Rows come from a Cauchy matrix, every square submatrix of which is
+/// invertible, so any `e` intact parity blocks rebuild any `e` data blocks.
+fn coefficient(data_blocks: usize, parity: usize, data: usize) -> u8 {
+    gf_inv(((data_blocks + parity) as u8) ^ (data as u8))
+}
+
+/// Invert a square GF(256) matrix by Gauss-Jordan elimination
+fn invert(mut matrix: Vec<Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<u8>> = (0..n)
+        .map(|r| (0..n).map(|c| u8::from(r == c)).collect())
+        .collect();
+
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| matrix[r][col] != 0)?;
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = gf_inv(matrix[col][col]);
+        for c in 0..n {
+            matrix[col][c] = gf_mul(matrix[col][c], scale);
+            inverse[col][c] = gf_mul(inverse[col][c], scale);
+        }
+        for r in 0..n {
+            let factor = matrix[r][col];
+            if r != col && factor != 0 {
+                for c in 0..n {
+                    matrix[r][c] ^= gf_mul(factor, matrix[col][c]);
+                    inverse[r][c] ^= gf_mul(factor, inverse[col][c]);
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Shape of a recovery record
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    data_len: u64,
+    block_size: u64,
+    data_blocks: usize,
+    parity_blocks: usize,
+}
+
+impl Layout {
+    /// Largest block split that fits GF(256) with `percent` parity
+    fn for_data(data_len: u64, percent: u8) -> Layout {
+        let parity_for = |n: usize| (n * percent as usize).div_ceil(100).max(1);
+        let max_data = (1..MAX_BLOCKS)
+            .rev()
+            .find(|&n| n + parity_for(n) <= MAX_BLOCKS)
+            .unwrap_or(1);
+        let block_size = data_len.div_ceil(max_data as u64).max(1);
+        let data_blocks = data_len.div_ceil(block_size).max(1) as usize;
+        Layout {
+            data_len,
+            block_size,
+            data_blocks,
+            parity_blocks: parity_for(data_blocks),
+        }
+    }
+
+    fn total_blocks(&self) -> usize {
+        self.data_blocks + self.parity_blocks
+    }
+
+    /// File offset of block `index` (data blocks first, then parity)
+    fn block_offset(&self, index: usize) -> u64 {
+        if index < self.data_blocks {
+            index as u64 * self.block_size
+        } else {
+            self.data_len + (index - self.data_blocks) as u64 * self.block_size
+        }
+    }
+
+    /// Stored length of block `index`; only the last data block is short
+    fn block_len(&self, index: usize) -> u64 {
+        if index < self.data_blocks {
+            (self.data_len - self.block_offset(index)).min(self.block_size)
+        } else {
+            self.block_size
+        }
+    }
+
+    fn record_len(&self) -> u64 {
+        self.parity_blocks as u64 * self.block_size + self.total_blocks() as u64 * 4 + FOOTER_SIZE as u64
+    }
+}
+
+/// Read `len` bytes of block `index` starting at `start`, zero-padded
+/// past the end of a short block
+fn read_segment(file: &mut File, layout: &Layout, index: usize, start: u64, buf: &mut [u8]) -> Result<()> {
+    buf.fill(0);
+    let available = layout.block_len(index).saturating_sub(start).min(buf.len() as u64) as usize;
+    if available > 0 {
+        file.seek(SeekFrom::Start(layout.block_offset(index) + start))?;
+        file.read_exact(&mut buf[..available])?;
+    }
+    Ok(())
+}
+
+/// CRC32 of block `index`, zero-padded to the full block size
+fn block_crc(file: &mut File, layout: &Layout, index: usize) -> Result<u32> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut start = 0;
+    while start < layout.block_size {
+        let len = (layout.block_size - start).min(CHUNK_SIZE as u64) as usize;
+        read_segment(file, layout, index, start, &mut buf[..len])?;
+        hasher.update(&buf[..len]);
+        start += len as u64;
+    }
+    Ok(hasher.finalize())
+}
+
+/// Compute every parity block from the data blocks and write them at
+/// their place after the archive data
+fn write_parity(file: &mut File, layout: &Layout) -> Result<()> {
+    let mut data = vec![0u8; CHUNK_SIZE];
+    let mut parity = vec![vec![0u8; CHUNK_SIZE]; layout.parity_blocks];
+    let mut start = 0;
+    while start < layout.block_size {
+        let len = (layout.block_size - start).min(CHUNK_SIZE as u64) as usize;
+        parity.iter_mut().for_each(|p| p[..len].fill(0));
+        for j in 0..layout.data_blocks {
+            read_segment(file, layout, j, start, &mut data[..len])?;
+            for (i, p) in parity.iter_mut().enumerate() {
+                mul_add(&mut p[..len], &data[..len], coefficient(layout.data_blocks, i, j));
+            }
+        }
+        for (i, p) in parity.iter().enumerate() {
+            file.seek(SeekFrom::Start(layout.block_offset(layout.data_blocks + i) + start))?;
+            file.write_all(&p[..len])?;
+        }
+        start += len as u64;
+    }
+    Ok(())
+}
+
+/// Write the CRC table and footer after the parity blocks
+fn write_index(file: &mut File, layout: &Layout) -> Result<()> {
+    let mut index = Vec::with_capacity(layout.total_blocks() * 4 + FOOTER_SIZE);
+    for block in 0..layout.total_blocks() {
+        let crc = block_crc(file, layout, block)?;
+        index.extend_from_slice(&crc.to_le_bytes());
+    }
+    index.extend_from_slice(FOOTER_MAGIC);
+    index.extend_from_slice(&layout.data_len.to_le_bytes());
+    index.extend_from_slice(&(layout.block_size as u32).to_le_bytes());
+    index.extend_from_slice(&(layout.data_blocks as u32).to_le_bytes());
+    index.extend_from_slice(&(layout.parity_blocks as u32).to_le_bytes());
+    let crc = crc32fast::hash(&index);
+    index.extend_from_slice(&crc.to_le_bytes());
+
+    file.seek(SeekFrom::Start(layout.data_len + layout.parity_blocks as u64 * layout.block_size))?;
+    file.write_all(&index)?;
+    file.set_len(layout.data_len + layout.record_len())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Append a recovery record worth `percent` of the archive size
+pub(crate) fn append_recovery_record(archive_path: &Path, percent: u8) -> Result<()> {
+    if percent > 100 {
+        return Err(Error::InvalidParameter(format!(
+            "recovery_percent must be between 0 and 100, got {}",
+            percent
+        )));
+    }
+    if percent == 0 {
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new().read(true).write(true).open(archive_path)?;
+    let layout = Layout::for_data(file.metadata()?.len(), percent);
+    if layout.block_size > u32::MAX as u64 {
+        return Err(Error::InvalidParameter(
+            "Archive is too large for a recovery record".to_string(),
+        ));
+    }
+    write_parity(&mut file, &layout)?;
+    write_index(&mut file, &layout)
+}
+
+/// Strength of the archive's recovery record as a percentage for
+/// [`append_recovery_record`], or `None` if it has no intact record
+pub(crate) fn recovery_percent(archive_path: &Path) -> Result<Option<u8>> {
+    let mut file = File::open(archive_path)?;
+    Ok(read_record(&mut file).ok().map(|(layout, _)| {
+        (layout.parity_blocks * 100).div_ceil(layout.data_blocks).clamp(1, 100) as u8
+    }))
+}
+
+/// Read the footer and CRC table of a recovery record
+fn read_record(file: &mut File) -> Result<(Layout, Vec<u32>)> {
+    let missing = || Error::InvalidArchive("Archive has no intact recovery record".to_string());
+
+    let file_len = file.metadata()?.len();
+    if file_len < FOOTER_SIZE as u64 {
+        return Err(missing());
+    }
+    let mut footer = [0u8; FOOTER_SIZE];
+    file.seek(SeekFrom::Start(file_len - FOOTER_SIZE as u64))?;
+    file.read_exact(&mut footer)?;
+    if &footer[..8] != FOOTER_MAGIC {
+        return Err(missing());
+    }
+
+    let u32_at = |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let layout = Layout {
+        data_len: u64::from_le_bytes(footer[8..16].try_into().unwrap()),
+        block_size: u32_at(&footer, 16) as u64,
+        data_blocks: u32_at(&footer, 20) as usize,
+        parity_blocks: u32_at(&footer, 24) as usize,
+    };
+    if layout.block_size == 0
+        || layout.data_blocks == 0
+        || layout.total_blocks() > MAX_BLOCKS
+        || layout.data_len.checked_add(layout.record_len()) != Some(file_len)
+        || layout.data_len.div_ceil(layout.block_size) != layout.data_blocks as u64
+    {
+        return Err(missing());
+    }
+
+    let table_len = layout.total_blocks() * 4;
+    let mut index = vec![0u8; table_len + FOOTER_SIZE];
+    file.seek(SeekFrom::Start(file_len - index.len() as u64))?;
+    file.read_exact(&mut index)?;
+    if crc32fast::hash(&index[..index.len() - 4]) != u32_at(&index, index.len() - 4) {
+        return Err(missing());
+    }
+    let crcs = (0..layout.total_blocks()).map(|i| u32_at(&index, i * 4)).collect();
+    Ok((layout, crcs))
+}
+
+impl SevenZip {
+    /// Repair an archive in place from its recovery record
+    ///
+    /// Blocks whose CRC no longer matches are rebuilt from the parity data
+    /// written by [`CompressOptions::recovery_percent`](crate::CompressOptions::recovery_percent).
+    /// Returns the number of blocks repaired, 0 if the archive was intact.
+    /// Fails with [`Error::InvalidArchive`] if the archive has no readable
+    /// recovery record or more blocks are damaged than the record can fix.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// let repaired = sz.recover("evidence.7z")?;
+    /// if repaired > 0 {
+    ///     println!("Repaired {} damaged blocks", repaired);
+    /// }
+    /// sz.extract("evidence.7z", "output")?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn recover(&self, archive_path: impl AsRef<Path>) -> Result<usize> {
+        let mut file = OpenOptions::new().read(true).write(true).open(archive_path.as_ref())?;
+        let (layout, crcs) = read_record(&mut file)?;
+
+        let mut damaged = Vec::new();
+        for (block, &crc) in crcs.iter().enumerate() {
+            if block_crc(&mut file, &layout, block)? != crc {
+                damaged.push(block);
+            }
+        }
+        if damaged.is_empty() {
+            return Ok(0);
+        }
+
+        let lost: Vec<usize> = damaged.iter().copied().filter(|&b| b < layout.data_blocks).collect();
+        let intact_parity: Vec<usize> = (0..layout.parity_blocks)
+            .filter(|&i| !damaged.contains(&(layout.data_blocks + i)))
+            .collect();
+        if lost.len() > intact_parity.len() {
+            return Err(Error::InvalidArchive(format!(
+                "{} damaged blocks exceed the {} usable recovery blocks",
+                lost.len(),
+                intact_parity.len()
+            )));
+        }
+
+        if !lost.is_empty() {
+            rebuild_data(&mut file, &layout, &lost, &intact_parity[..lost.len()])?;
+            for &block in &lost {
+                if block_crc(&mut file, &layout, block)? != crcs[block] {
+                    return Err(Error::InvalidArchive(
+                        "Recovery record does not match the archive".to_string(),
+                    ));
+                }
+            }
+        }
+        if damaged.len() > lost.len() {
+            write_parity(&mut file, &layout)?;
+        }
+        file.sync_all()?;
+        Ok(damaged.len())
+    }
+}
+
+/// Rebuild the `lost` data blocks from as many intact parity blocks
+fn rebuild_data(file: &mut File, layout: &Layout, lost: &[usize], parity_rows: &[usize]) -> Result<()> {
+    let matrix = parity_rows
+        .iter()
+        .map(|&row| lost.iter().map(|&col| coefficient(layout.data_blocks, row, col)).collect())
+        .collect();
+    let inverse = invert(matrix)
+        .ok_or_else(|| Error::InvalidArchive("Recovery record is inconsistent".to_string()))?;
+
+    let mut data = vec![0u8; CHUNK_SIZE];
+    let mut syndromes = vec![vec![0u8; CHUNK_SIZE]; parity_rows.len()];
+    let mut rebuilt = vec![0u8; CHUNK_SIZE];
+    let mut start = 0;
+    while start < layout.block_size {
+        let len = (layout.block_size - start).min(CHUNK_SIZE as u64) as usize;
+
+        // Parity minus the contribution of every intact data block leaves
+        // the contribution of the lost blocks alone
+        for (syndrome, &row) in syndromes.iter_mut().zip(parity_rows) {
+            read_segment(file, layout, layout.data_blocks + row, start, &mut syndrome[..len])?;
+        }
+        for j in (0..layout.data_blocks).filter(|j| !lost.contains(j)) {
+            read_segment(file, layout, j, start, &mut data[..len])?;
+            for (syndrome, &row) in syndromes.iter_mut().zip(parity_rows) {
+                mul_add(&mut syndrome[..len], &data[..len], coefficient(layout.data_blocks, row, j));
+            }
+        }
+
+        for (k, &block) in lost.iter().enumerate() {
+            rebuilt[..len].fill(0);
+            for (syndrome, &factor) in syndromes.iter().zip(&inverse[k]) {
+                mul_add(&mut rebuilt[..len], &syndrome[..len], factor);
+            }
+            let keep = layout.block_len(block).saturating_sub(start).min(len as u64) as usize;
+            if keep > 0 {
+                file.seek(SeekFrom::Start(layout.block_offset(block) + start))?;
+                file.write_all(&rebuilt[..keep])?;
+            }
+        }
+        start += len as u64;
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Retrying transient I/O errors on input files
+//!
+//! Reads from network mounts can fail once and succeed a moment later. With
+//! a [`RetryPolicy`], the Rust-side reads of input files wait and try again
+//! instead of aborting the whole operation. Errors that won't go away by
+//! themselves, such as a missing file or a denied permission, fail at once.
+
+use std::io::{self, ErrorKind, Read};
+use std::time::Duration;
+
+/// How often and how patiently to retry a failed read
+///
+/// The default makes no retries.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::{SevenZip, CompressionLevel, CompressOptions, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let sz = SevenZip::new()?;
+/// let opts = CompressOptions::default()
+///     .with_io_retry(RetryPolicy { attempts: 5, backoff: Duration::from_millis(200) });
+/// sz.create_tar_7z("share.tar.7z", &["/mnt/share/case-42"], CompressionLevel::Normal, Some(&opts))?;
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first failure of an operation
+    pub attempts: u32,
+    /// Wait before the first retry; doubled before each further one
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Whether `err` may go away on its own and is worth retrying
+    ///
+    /// Timeouts, interruptions, busy resources, stale network handles and
+    /// plain I/O errors (`EIO`) count as transient; everything else,
+    /// including `NotFound` and `PermissionDenied`, does not.
+    pub fn is_transient(err: &io::Error) -> bool {
+        match err.kind() {
+            ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+            | ErrorKind::WouldBlock
+            | ErrorKind::ResourceBusy
+            | ErrorKind::StaleNetworkFileHandle => true,
+            // EIO on Unix; ERROR_UNEXP_NET_ERR and ERROR_NETNAME_DELETED on Windows
+            _ if cfg!(unix) => err.raw_os_error() == Some(5),
+            _ => matches!(err.raw_os_error(), Some(59) | Some(64)),
+        }
+    }
+
+    /// Run `op`, retrying it while it fails with a transient error
+    pub fn run<T>(&self, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut delay = self.backoff;
+        let mut retries = 0;
+        loop {
+            match op() {
+                Err(e) if retries < self.attempts && Self::is_transient(&e) => {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A reader whose every `read` call is retried under a [`RetryPolicy`]
+#[derive(Debug)]
+pub struct RetryReader<R> {
+    inner: R,
+    policy: RetryPolicy,
+}
+
+impl<R: Read> RetryReader<R> {
+    /// Wrap `inner`, retrying its reads according to `policy`
+    pub fn new(inner: R, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// Unwrap the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for RetryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        self.policy.run(|| inner.read(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails its first `failures` reads with `kind`, then yields `data`
+    struct FlakyReader {
+        data: io::Cursor<Vec<u8>>,
+        failures: u32,
+        kind: ErrorKind,
+        calls: u32,
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::new(self.kind, "flaky"));
+            }
+            self.data.read(buf)
+        }
+    }
+
+    fn flaky(failures: u32, kind: ErrorKind) -> FlakyReader {
+        FlakyReader { data: io::Cursor::new(b"evidence".to_vec()), failures, kind, calls: 0 }
+    }
+
+    #[test]
+    fn test_retry_reader_recovers_from_transient_errors() {
+        let policy = RetryPolicy { attempts: 3, backoff: Duration::from_millis(1) };
+        let mut reader = RetryReader::new(flaky(3, ErrorKind::TimedOut), policy);
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"evidence");
+
+        // One failure too many
+        let mut reader = RetryReader::new(flaky(4, ErrorKind::TimedOut), policy);
+        assert_eq!(reader.read_to_end(&mut Vec::new()).unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_retry_reader_fails_fast_on_permanent_errors() {
+        let policy = RetryPolicy { attempts: 3, backoff: Duration::from_millis(1) };
+        for kind in [ErrorKind::NotFound, ErrorKind::PermissionDenied] {
+            let mut reader = RetryReader::new(flaky(1, kind), policy);
+            assert_eq!(reader.read(&mut [0; 8]).unwrap_err().kind(), kind);
+            assert_eq!(reader.into_inner().calls, 1);
+        }
+        assert!(RetryPolicy::is_transient(&io::Error::from_raw_os_error(if cfg!(unix) { 5 } else { 59 })));
+    }
+}
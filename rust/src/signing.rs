@@ -0,0 +1,99 @@
+//! Detached Ed25519 signatures over archive files (requires the `signing` feature)
+//!
+//! Encryption keeps an archive private but says nothing about who made it.
+//! A detached signature published next to the archive lets anyone holding
+//! the public key check that the file is exactly what the key owner signed.
+//!
+//! Signatures are Ed25519ph (RFC 8032, empty context): the archive is fed
+//! through SHA-512 in chunks and the digest is signed, so archives of any
+//! size are signed without loading them into memory.
+
+use crate::error::{Error, Result};
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha512};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Length of a signature from [`sign_archive`]
+pub const SIGNATURE_LENGTH: usize = ed25519_dalek::SIGNATURE_LENGTH;
+
+/// Sign the archive at `archive_path` with an Ed25519 private key
+///
+/// `private_key` is the 32-byte secret seed, or the 64-byte seed plus
+/// public key many tools store. Returns the [`SIGNATURE_LENGTH`]-byte
+/// detached signature.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::sign_archive;
+///
+/// let key = std::fs::read("release.key")?;
+/// let signature = sign_archive("release.7z", &key)?;
+/// std::fs::write("release.7z.sig", signature)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn sign_archive(archive_path: impl AsRef<Path>, private_key: &[u8]) -> Result<Vec<u8>> {
+    let key = match private_key.len() {
+        32 => SigningKey::from_bytes(private_key.try_into().expect("length checked")),
+        64 => SigningKey::from_keypair_bytes(private_key.try_into().expect("length checked"))
+            .map_err(|_| Error::InvalidParameter("Public half of the private key does not match".to_string()))?,
+        len => {
+            return Err(Error::InvalidParameter(format!(
+                "Ed25519 private key must be 32 or 64 bytes, got {}",
+                len
+            )))
+        }
+    };
+    let digest = archive_digest(archive_path.as_ref())?;
+    let signature = key
+        .sign_prehashed(digest, None)
+        .map_err(|e| Error::EncryptionError(format!("Signing failed: {}", e)))?;
+    Ok(signature.to_bytes().to_vec())
+}
+
+/// Check a detached signature from [`sign_archive`] against the archive at
+/// `archive_path` and a 32-byte Ed25519 public key
+///
+/// Returns `Ok(false)` if the archive or signature was altered or the
+/// signature belongs to another key. Errors are kept for unreadable
+/// archives and for keys or signatures of the wrong size.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::verify_signature;
+///
+/// let public_key = std::fs::read("release.pub")?;
+/// let signature = std::fs::read("release.7z.sig")?;
+/// if !verify_signature("release.7z", &signature, &public_key)? {
+///     eprintln!("release.7z does not match its signature");
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn verify_signature(archive_path: impl AsRef<Path>, signature: &[u8], public_key: &[u8]) -> Result<bool> {
+    let public_key: &[u8; 32] = public_key.try_into().map_err(|_| {
+        Error::InvalidParameter(format!("Ed25519 public key must be 32 bytes, got {}", public_key.len()))
+    })?;
+    let key = VerifyingKey::from_bytes(public_key)
+        .map_err(|_| Error::InvalidParameter("Not a valid Ed25519 public key".to_string()))?;
+    let signature = Signature::from_slice(signature).map_err(|_| {
+        Error::InvalidParameter(format!(
+            "Ed25519 signature must be {} bytes, got {}",
+            SIGNATURE_LENGTH,
+            signature.len()
+        ))
+    })?;
+
+    let digest = archive_digest(archive_path.as_ref())?;
+    Ok(key.verify_prehashed_strict(digest, None, &signature).is_ok())
+}
+
+/// SHA-512 state after reading the whole archive
+fn archive_digest(archive_path: &Path) -> Result<Sha512> {
+    let mut file = File::open(archive_path)?;
+    let mut hasher = Sha512::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher)
+}
@@ -0,0 +1,341 @@
+//! `.tar.7z` support
+//!
+//! The 7z format has no place for Unix ownership, permission bits or symlinks,
+//! so inputs are wrapped in a tar stream first. The tar is generated on a
+//! background thread and piped straight into the compressor; it is never
+//! written to disk.
+
+use crate::archive::{path_to_cstring, ArchiveHandle, CompressOptions, CompressionLevel, PartialOutputGuard, SevenZip};
+use crate::error::{Error, Result};
+use crate::ffi;
+use crate::retry::{RetryPolicy, RetryReader};
+use crate::threads::clamp_threads;
+use std::ffi::CString;
+use std::io::{self, Read, Write};
+use std::os::raw::{c_int, c_void};
+use std::path::{Path, PathBuf};
+
+/// Opens an input file for the tar stream
+type OpenInput = fn(&Path) -> io::Result<Box<dyn Read>>;
+
+fn open_input_file(path: &Path) -> io::Result<Box<dyn Read>> {
+    Ok(Box::new(std::fs::File::open(path)?))
+}
+
+impl SevenZip {
+    /// Create a `.tar.7z` archive
+    ///
+    /// The inputs are packed into a tar stream (keeping uid/gid, mode bits,
+    /// mtimes and symlinks as links) which is compressed into a 7z archive
+    /// with a single entry named after the archive, e.g. `backup.tar` for
+    /// `backup.tar.7z`. Unlike [`create_archive`](Self::create_archive),
+    /// directory inputs keep their own name as the top-level path in the tar.
+    ///
+    /// Only `num_threads`, `dict_size`, `io_retry` and `recursive` from
+    /// `options` apply; with `io_retry`, opening and reading input files is
+    /// retried on transient errors. The tar stream is written unencrypted, so
+    /// a `password` or `encryption_key` fails with
+    /// [`Error::UnsupportedOptionCombination`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::{SevenZip, CompressionLevel};
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.create_tar_7z("backup.tar.7z", &["project"], CompressionLevel::Normal, None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn create_tar_7z(
+        &self,
+        archive_path: impl AsRef<Path>,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+    ) -> Result<()> {
+        self.create_tar_7z_from(archive_path.as_ref(), input_paths, level, options, open_input_file)
+    }
+
+    /// [`create_tar_7z`](Self::create_tar_7z), reading input files through `open`
+    fn create_tar_7z_from(
+        &self,
+        archive_path: &Path,
+        input_paths: &[impl AsRef<Path>],
+        level: CompressionLevel,
+        options: Option<&CompressOptions>,
+        open: OpenInput,
+    ) -> Result<()> {
+        level.validate()?;
+        let entry_name_c = CString::new(tar_entry_name(archive_path))?;
+
+        let opts = options.cloned().unwrap_or_default();
+        if opts.password.is_some() || opts.encryption_key.is_some() {
+            return Err(Error::UnsupportedOptionCombination {
+                detail: "encryption is not supported by create_tar_7z; the tar stream is written unencrypted".to_string(),
+            });
+        }
+        let mut c_opts = ffi::SevenZipCompressOptions::defaults();
+        c_opts.num_threads = clamp_threads(opts.num_threads) as i32;
+        c_opts.dict_size = opts.dict_size;
+
+        let inputs: Vec<PathBuf> = input_paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+        for input in &inputs {
+            std::fs::symlink_metadata(input)
+                .map_err(|e| Error::OpenFile(format!("{}: {}", input.display(), e)))?;
+        }
+
+        let guard = PartialOutputGuard::new(archive_path, true);
+        let archive_path_c = path_to_cstring(guard.path())?;
+        let (mut reader, writer) = io::pipe()?;
+        let (retry, recursive) = (opts.io_retry, opts.recursive);
+        let tar_thread = std::thread::spawn(move || write_tar(writer, &inputs, open, retry, recursive));
+
+        let result = unsafe {
+            ffi::sevenzip_create_7z_from_stream(
+                archive_path_c.as_ptr(),
+                entry_name_c.as_ptr(),
+                level.into(),
+                &c_opts,
+                Some(pipe_read_callback),
+                &mut reader as *mut io::PipeReader as *mut c_void,
+            )
+        };
+
+        // Closing the read end unblocks the tar thread if compression stopped early
+        drop(reader);
+        let tar_result = tar_thread
+            .join()
+            .map_err(|_| Error::Unknown("Tar writer thread panicked".to_string()))?;
+
+        // A tar failure ends the stream early, which the compressor cannot tell
+        // apart from a normal end, so it takes precedence
+        tar_result.map_err(|e| Error::Io(format!("Failed to build tar stream: {}", e)))?;
+        if result != ffi::SevenZipErrorCode::SEVENZIP_OK {
+            return Err(Error::from_code(result));
+        }
+
+        guard.finish()
+    }
+
+    /// Extract a `.tar.7z` archive created by [`create_tar_7z`](Self::create_tar_7z)
+    ///
+    /// Restores permission bits, mtimes and symlinks. Ownership is recorded in
+    /// the tar but not applied, since that needs elevated privileges. The tar is
+    /// decompressed in chunks and piped straight into the unpacker, so it is
+    /// never held in memory or written to disk. Its CRC is only checked at the
+    /// end, so a damaged archive can leave partly unpacked files behind.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_tar_7z("backup.tar.7z", "restore", None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_tar_7z(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let mut archive = ArchiveHandle::open(archive_path.as_ref(), password)?;
+        let mut files = archive
+            .entries()?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_directory);
+
+        let (index, _) = match (files.next(), files.next()) {
+            (Some(only), None) => only,
+            _ => {
+                return Err(Error::InvalidArchive(
+                    "Expected a single tar entry in .tar.7z archive".to_string(),
+                ))
+            }
+        };
+
+        let (reader, mut writer) = io::pipe()?;
+        let output_dir = output_dir.as_ref().to_path_buf();
+        let tar_thread = std::thread::spawn(move || -> io::Result<()> {
+            let mut tar = tar::Archive::new(reader);
+            tar.set_preserve_permissions(true);
+            tar.set_preserve_mtime(true);
+            tar.unpack(&output_dir)?;
+            // Drain the padding after the end marker, so the whole entry is
+            // decoded and its CRC checked
+            io::copy(&mut tar.into_inner(), &mut io::sink())?;
+            Ok(())
+        });
+
+        let streamed = match archive.stream(index as u32, &mut writer) {
+            // Coder chains the streaming decoder can't handle
+            Err(Error::NotImplemented(_)) => archive
+                .read(index as u32)
+                .and_then(|data| writer.write_all(data).map_err(Into::into)),
+            other => other,
+        };
+        // Closing the write end ends the tar stream
+        drop(writer);
+        let tar_result = tar_thread
+            .join()
+            .map_err(|_| Error::Unknown("Tar reader thread panicked".to_string()))?;
+
+        // A write error only means the unpacker stopped reading, and its own
+        // error says why; a decoding error is the cause of anything it reports
+        match (streamed, tar_result) {
+            (Err(e), Ok(())) => Err(e),
+            (Err(e), Err(_)) if !matches!(e, Error::Io(_)) => Err(e),
+            (_, Err(e)) => Err(Error::Extract(format!("Failed to unpack tar stream: {}", e))),
+            (Ok(()), Ok(())) => Ok(()),
+        }
+    }
+}
+
+/// Name of the tar entry inside a `.tar.7z` archive: the file name minus `.7z`
+fn tar_entry_name(archive_path: &Path) -> String {
+    let file_name = archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = file_name.strip_suffix(".7z").unwrap_or(&file_name);
+
+    if stem.ends_with(".tar") {
+        stem.to_string()
+    } else if stem.is_empty() {
+        "archive.tar".to_string()
+    } else {
+        format!("{}.tar", stem)
+    }
+}
+
+/// Write all inputs as a tar stream into `writer`, closing it when done
+fn write_tar(
+    writer: io::PipeWriter,
+    inputs: &[PathBuf],
+    open: OpenInput,
+    retry: RetryPolicy,
+    recursive: bool,
+) -> io::Result<()> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for input in inputs {
+        let name = input
+            .file_name()
+            .map(PathBuf::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("No file name: {}", input.display())))?;
+
+        append_tree(&mut builder, input, &name, open, retry, recursive)?;
+    }
+
+    builder.into_inner()?;
+    Ok(())
+}
+
+/// Append `path` as `name`, recursing into directories in name order
+///
+/// Regular files are opened with `open` and read through `retry`; symlinks
+/// and other entries carry no data and are appended as the tar crate sees
+/// them. Without `recursive`, the subdirectories of a directory are skipped.
+fn append_tree(
+    builder: &mut tar::Builder<io::PipeWriter>,
+    path: &Path,
+    name: &Path,
+    open: OpenInput,
+    retry: RetryPolicy,
+    recursive: bool,
+) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+
+    if metadata.is_dir() {
+        builder.append_dir(name, path)?;
+        let mut children = std::fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            if !recursive && child.file_type()?.is_dir() {
+                continue;
+            }
+            append_tree(builder, &child.path(), &name.join(child.file_name()), open, retry, recursive)?;
+        }
+    } else if metadata.is_file() {
+        let file = retry.run(|| open(path))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, tar::HeaderMode::Complete);
+        builder.append_data(&mut header, name, RetryReader::new(file, retry))?;
+    } else {
+        builder.append_path_with_name(path, name)?;
+    }
+    Ok(())
+}
+
+/// C read callback pulling tar bytes from the pipe
+unsafe extern "C" fn pipe_read_callback(buffer: *mut u8, size: *mut usize, user_data: *mut c_void) -> c_int {
+    // SAFETY: user_data is the PipeReader passed by create_tar_7z, which
+    // outlives the C call; buffer holds *size writable bytes
+    let reader = unsafe { &mut *(user_data as *mut io::PipeReader) };
+    let buf = unsafe { std::slice::from_raw_parts_mut(buffer, *size) };
+
+    loop {
+        match reader.read(buf) {
+            Ok(n) => {
+                unsafe { *size = n };
+                return 0;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    /// Reads still to fail before a [`flaky_open`] reader passes data through
+    static FAILURES_LEFT: AtomicU32 = AtomicU32::new(0);
+
+    /// A file whose reads time out while [`FAILURES_LEFT`] is above zero
+    struct FlakyFile(std::fs::File);
+
+    impl Read for FlakyFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if FAILURES_LEFT.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "flaky mount"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    fn flaky_open(path: &Path) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(FlakyFile(std::fs::File::open(path)?)))
+    }
+
+    #[test]
+    fn test_create_tar_7z_retries_failing_reads() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = temp.path().join("case-42");
+        std::fs::create_dir(&input).unwrap();
+        std::fs::write(input.join("log.txt"), "read on the third try").unwrap();
+        let archive = temp.path().join("share.tar.7z");
+        let sz = SevenZip::new().unwrap();
+
+        // Without retries the first failed read ends the operation
+        FAILURES_LEFT.store(2, Ordering::SeqCst);
+        assert!(sz.create_tar_7z_from(&archive, &[&input], CompressionLevel::Fast, None, flaky_open).is_err());
+        assert!(!archive.exists());
+
+        FAILURES_LEFT.store(2, Ordering::SeqCst);
+        let policy = RetryPolicy { attempts: 3, backoff: Duration::from_millis(1) };
+        let opts = CompressOptions::default().with_io_retry(policy);
+        sz.create_tar_7z_from(&archive, &[&input], CompressionLevel::Fast, Some(&opts), flaky_open).unwrap();
+        assert_eq!(FAILURES_LEFT.load(Ordering::SeqCst), 0);
+
+        let out = temp.path().join("out");
+        sz.extract_tar_7z(&archive, &out, None).unwrap();
+        assert_eq!(std::fs::read_to_string(out.join("case-42/log.txt")).unwrap(), "read on the third try");
+    }
+}
@@ -0,0 +1,45 @@
+//! Process-wide ceiling on compression threads
+//!
+//! Per-call `num_threads` settings are chosen by each caller in isolation.
+//! A service running many archive jobs at once can cap them all here, so
+//! twenty concurrent jobs don't each start ten encoder threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Global thread cap; 0 means none
+static GLOBAL_THREAD_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Cap the threads of every subsequent operation in this process
+///
+/// Each operation uses at most `limit` threads, whatever its own
+/// `num_threads` asks for; operations left on auto use exactly `limit`.
+/// `0` removes the cap. Operations already running are not affected.
+///
+/// # Example
+///
+/// ```no_run
+/// use seven_zip::{set_global_thread_limit, SevenZip, CompressionLevel, CompressOptions};
+///
+/// set_global_thread_limit(2);
+/// let sz = SevenZip::new()?;
+/// let opts = CompressOptions::default().with_threads(8); // runs with 2
+/// sz.create_archive("backup.7z", &["data"], CompressionLevel::Normal, Some(&opts))?;
+/// # Ok::<(), seven_zip::Error>(())
+/// ```
+pub fn set_global_thread_limit(limit: usize) {
+    GLOBAL_THREAD_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// The cap set by [`set_global_thread_limit`], or 0 if there is none
+pub fn global_thread_limit() -> usize {
+    GLOBAL_THREAD_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Apply the global cap to a requested thread count (0 = auto)
+pub(crate) fn clamp_threads(requested: usize) -> usize {
+    match global_thread_limit() {
+        0 => requested,
+        limit if requested == 0 => limit,
+        limit => requested.min(limit),
+    }
+}
@@ -0,0 +1,189 @@
+//! ZIP archive support
+//!
+//! The LZMA SDK only understands 7z containers, so ZIP files are read with the
+//! pure Rust `zip` crate. Stored and Deflate entries are supported, encrypted
+//! with either legacy ZipCrypto or WinZip AES.
+
+use crate::archive::{ArchiveEntry, SevenZip};
+use crate::error::{Error, Result};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use zip::read::ZipFile;
+use zip::result::ZipError;
+use zip::{HasZipMetadata, ZipArchive};
+
+impl From<ZipError> for Error {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(e) => Error::Io(e.to_string()),
+            ZipError::InvalidArchive(msg) => Error::InvalidArchive(msg.to_string()),
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                Error::DecryptionError("Password required to decrypt zip entry".to_string())
+            }
+            ZipError::UnsupportedArchive(msg) => Error::NotImplemented(msg.to_string()),
+            ZipError::FileNotFound => Error::InvalidArchive("Zip entry not found".to_string()),
+            ZipError::InvalidPassword => {
+                Error::DecryptionError("Wrong password for zip entry".to_string())
+            }
+            other => Error::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl SevenZip {
+    /// Extract a ZIP archive
+    ///
+    /// Entries may be stored or Deflate-compressed, and encrypted with either
+    /// ZipCrypto or WinZip AES. Other compression methods return
+    /// [`Error::NotImplemented`]. Entries whose names would escape
+    /// `output_dir` are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive_path` - Path to the .zip file
+    /// * `output_dir` - Directory to extract to
+    /// * `password` - Optional password for encrypted entries
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// sz.extract_zip("download.zip", "output", None)?;
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn extract_zip(
+        &self,
+        archive_path: impl AsRef<Path>,
+        output_dir: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let output_dir = output_dir.as_ref();
+        let mut archive = ZipArchive::new(File::open(archive_path.as_ref())?)?;
+        fs::create_dir_all(output_dir)?;
+
+        for i in 0..archive.len() {
+            let mut file = match password {
+                Some(password) => archive.by_index_decrypt(i, password.as_bytes())?,
+                None => archive.by_index(i)?,
+            };
+
+            let relative = file.enclosed_name().ok_or_else(|| {
+                Error::InvalidArchive(format!("Unsafe path in zip entry: {}", file.name()))
+            })?;
+            let out_path = output_dir.join(relative);
+
+            if file.is_dir() {
+                fs::create_dir_all(&out_path)?;
+                continue;
+            }
+
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = File::create(&out_path)?;
+            io::copy(&mut file, &mut out)?;
+
+            #[cfg(unix)]
+            if let Some(mode) = file.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o7777))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// List the contents of a ZIP archive
+    ///
+    /// Listing reads only the central directory, so no password is needed
+    /// even for encrypted archives; the parameter is accepted for symmetry
+    /// with [`extract_zip`](Self::extract_zip).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use seven_zip::SevenZip;
+    ///
+    /// let sz = SevenZip::new()?;
+    /// for entry in sz.list_zip("download.zip", None)? {
+    ///     println!("{}: {} bytes", entry.name, entry.size);
+    /// }
+    /// # Ok::<(), seven_zip::Error>(())
+    /// ```
+    pub fn list_zip(
+        &self,
+        archive_path: impl AsRef<Path>,
+        _password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let mut archive = ZipArchive::new(File::open(archive_path.as_ref())?)?;
+        let mut entries = Vec::with_capacity(archive.len());
+
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i)?;
+            entries.push(ArchiveEntry {
+                name: file.name().trim_end_matches('/').to_string(),
+                raw_name: file.name_raw().strip_suffix(b"/").unwrap_or(file.name_raw()).to_vec(),
+                size: file.size(),
+                packed_size: file.compressed_size(),
+                modified_time: file.last_modified().map_or(0, dos_time_to_unix),
+                attributes: zip_attributes(&file),
+                is_directory: file.is_dir(),
+                crc32: Some(file.crc32()),
+                method: zip_method_name(file.compression()),
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Attributes of a ZIP entry in the 7z convention, see [`ArchiveEntry::attributes`]
+///
+/// Entries made on Unix keep their mode in the high 16 bits of the external
+/// attributes; those get the `0x8000` flag 7-Zip uses for a Unix mode. Others
+/// carry MS-DOS attributes in the low byte, which match Windows' flags.
+fn zip_attributes(file: &ZipFile<'_>) -> u32 {
+    /// "Version made by" host of entries with a Unix mode
+    const HOST_UNIX: u8 = 3;
+    const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+    const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+    let data = file.get_metadata();
+    let directory = if file.is_dir() { FILE_ATTRIBUTE_DIRECTORY } else { 0 };
+    if data.system as u8 == HOST_UNIX && data.external_attributes >> 16 != 0 {
+        (data.external_attributes & 0xFFFF_0000) | FILE_ATTRIBUTE_UNIX_EXTENSION | directory
+    } else {
+        (data.external_attributes & 0xFF) | directory
+    }
+}
+
+/// Display name of a ZIP compression method, in the style of 7z method names
+fn zip_method_name(method: zip::CompressionMethod) -> String {
+    match method {
+        zip::CompressionMethod::Stored => "Copy".to_string(),
+        zip::CompressionMethod::Deflated => "Deflate".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Convert a ZIP (MS-DOS, local time treated as UTC) timestamp to Unix seconds
+fn dos_time_to_unix(dt: zip::DateTime) -> u64 {
+    // Days from civil date, Howard Hinnant's algorithm
+    let (y, m, d) = (i64::from(dt.year()), i64::from(dt.month()), i64::from(dt.day()));
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400
+        + i64::from(dt.hour()) * 3_600
+        + i64::from(dt.minute()) * 60
+        + i64::from(dt.second());
+    secs.max(0) as u64
+}
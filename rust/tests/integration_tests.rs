@@ -6,7 +6,8 @@
 //! - Progress callbacks
 //! - Error handling
 
-use seven_zip::{SevenZip, CompressionLevel, CompressOptions};
+use seven_zip::{SevenZip, CompressionLevel, CompressionMethod, CompressOptions, ExtractOptions, SingleFileOptions};
+use seven_zip::{detect_format, ArchiveFormat};
 use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
@@ -18,6 +19,31 @@ fn create_test_file(dir: &std::path::Path, name: &str, content: &str) -> PathBuf
     path
 }
 
+/// Rename an entry by rewriting its UTF-16 name in the plain header and
+/// fixing both header CRCs, e.g. to get names the writer refuses to store;
+/// `to` must have as many UTF-16 units as `from`
+fn patch_entry_name(archive: &std::path::Path, from: &str, to: &str) {
+    let utf16 = |s: &str| s.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+    patch_header(archive, &utf16(from), &utf16(to));
+}
+
+/// Replace the first occurrence of `from` in the plain header of `archive`
+/// with `to`, fixing up the CRCs
+fn patch_header(archive: &std::path::Path, from: &[u8], to: &[u8]) {
+    assert_eq!(from.len(), to.len());
+    let mut bytes = fs::read(archive).unwrap();
+    let next_offset = 32 + u64::from_le_bytes(bytes[12..20].try_into().unwrap()) as usize;
+    let next_size = u64::from_le_bytes(bytes[20..28].try_into().unwrap()) as usize;
+    let header = &mut bytes[next_offset..next_offset + next_size];
+    let at = header.windows(from.len()).position(|w| w == from).expect("bytes in a plain header");
+    header[at..at + to.len()].copy_from_slice(to);
+    let header_crc = crc32fast::hash(header);
+    bytes[28..32].copy_from_slice(&header_crc.to_le_bytes());
+    let start_crc = crc32fast::hash(&bytes[12..32]);
+    bytes[8..12].copy_from_slice(&start_crc.to_le_bytes());
+    fs::write(archive, bytes).unwrap();
+}
+
 #[test]
 fn test_library_initialization() {
     let result = SevenZip::new();
@@ -104,6 +130,13 @@ fn test_encrypted_archive() {
     assert!(extracted_file.exists(), "Extracted file should exist");
     let content = fs::read_to_string(extracted_file).unwrap();
     assert_eq!(content, "Secret data!", "Content should match");
+    
+    // The data is really encrypted, so it can't be read without the password
+    let bytes = fs::read(&archive_path).unwrap();
+    assert!(!bytes.windows(12).any(|w| w == b"Secret data!"));
+    let no_password = temp.path().join("no_password");
+    assert!(sz.extract(&archive_path, &no_password).is_err());
+    sz.test_archive(&archive_path, Some(password)).unwrap();
 }
 
 #[test]
@@ -139,9 +172,7 @@ fn test_wrong_password_fails() {
         None,
     );
     
-    // Should fail (note: might succeed but produce garbage, depends on C library)
-    // At minimum, we verify the API works
-    assert!(result.is_ok() || result.is_err(), "Extraction API should work");
+    assert!(result.is_err(), "Extraction with the wrong password should fail");
 }
 
 #[test]
@@ -238,38 +269,60 @@ fn test_compression_levels() {
     }
 }
 
-// TODO: Implement single file compression/decompression in C library
-// #[test]
-// fn test_single_file_compression() {
-//     let temp = TempDir::new().unwrap();
-//     let input = create_test_file(temp.path(), "input.txt", "Test data for compression");
-//     let compressed = temp.path().join("output.lzma2");
-//     let decompressed = temp.path().join("decompressed.txt");
-//     
-//     let sz = SevenZip::new().unwrap();
-//     
-//     // Compress
-//     let result = sz.compress_file(
-//         input.to_str().unwrap(),
-//         compressed.to_str().unwrap(),
-//         CompressionLevel::Normal,
-//     );
-//     assert!(result.is_ok(), "Single file compression should succeed");
-//     assert!(compressed.exists(), "Compressed file should exist");
-//     
-//     // Decompress
-//     let result = sz.decompress_file(
-//         compressed.to_str().unwrap(),
-//         decompressed.to_str().unwrap(),
-//     );
-//     assert!(result.is_ok(), "Decompression should succeed");
-//     assert!(decompressed.exists(), "Decompressed file should exist");
-//     
-//     // Verify content
-//     let original = fs::read_to_string(input).unwrap();
-//     let restored = fs::read_to_string(decompressed).unwrap();
-//     assert_eq!(original, restored, "Content should match after roundtrip");
-// }
+#[test]
+fn test_single_file_compression() {
+    let temp = TempDir::new().unwrap();
+    let input = create_test_file(temp.path(), "input.txt", "Test data for compression");
+    let compressed = temp.path().join("output.lzma2");
+    let decompressed = temp.path().join("decompressed.txt");
+    
+    let sz = SevenZip::new().unwrap();
+    
+    // Compress
+    let result = sz.compress_file(
+        input.to_str().unwrap(),
+        compressed.to_str().unwrap(),
+        CompressionLevel::Normal,
+    );
+    assert!(result.is_ok(), "Single file compression should succeed");
+    assert!(compressed.exists(), "Compressed file should exist");
+    
+    // Decompress
+    let result = sz.decompress_file(
+        compressed.to_str().unwrap(),
+        decompressed.to_str().unwrap(),
+    );
+    assert!(result.is_ok(), "Decompression should succeed");
+    assert!(decompressed.exists(), "Decompressed file should exist");
+    
+    // Verify content
+    let original = fs::read_to_string(input).unwrap();
+    let restored = fs::read_to_string(decompressed).unwrap();
+    assert_eq!(original, restored, "Content should match after roundtrip");
+}
+
+#[test]
+fn test_single_file_buffer_size_does_not_change_output() {
+    let temp = TempDir::new().unwrap();
+    let content: String = (0..20000).map(|i| format!("line {} of buffered input\n", i % 977)).collect();
+    let input = create_test_file(temp.path(), "input.txt", &content);
+    let small = temp.path().join("small.lzma2");
+    let large = temp.path().join("large.lzma2");
+    
+    let sz = SevenZip::new().unwrap();
+    sz.compress_file_with_options(&input, &small, CompressionLevel::Normal,
+        &SingleFileOptions::default().with_buffer_size(512)).unwrap();
+    sz.compress_file_with_options(&input, &large, CompressionLevel::Normal,
+        &SingleFileOptions::default().with_buffer_size(8 * 1024 * 1024)).unwrap();
+    
+    assert_eq!(fs::read(&small).unwrap(), fs::read(&large).unwrap(),
+        "Buffer size must not affect compressed output");
+    
+    let restored = temp.path().join("restored.txt");
+    sz.decompress_file_with_options(&small, &restored,
+        &SingleFileOptions::default().with_buffer_size(512), None).unwrap();
+    assert_eq!(fs::read_to_string(&restored).unwrap(), content);
+}
 
 // TODO: Fix progress callback fat pointer handling
 // #[test]
@@ -820,3 +873,3583 @@ fn test_compressoptions_builder_pattern() {
 }
 
 
+
+#[test]
+fn test_stored_crc32_matches_source() {
+    let temp = TempDir::new().unwrap();
+    let archive_path = temp.path().join("crc.7z");
+    let file_a = create_test_file(temp.path(), "a.txt", "first file contents");
+    let file_b = create_test_file(temp.path(), "b.txt", "second file, somewhat longer contents");
+    
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_verify_crc_after(true);
+    sz.create_archive(&archive_path, &[&file_a, &file_b], CompressionLevel::Normal, Some(&opts))
+        .expect("Archive creation with CRC verification should succeed");
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    assert_eq!(entries.len(), 2);
+    for entry in entries {
+        let source = temp.path().join(&entry.name);
+        let expected = crc32fast::hash(&fs::read(source).unwrap());
+        assert_eq!(entry.crc32, Some(expected), "Stored CRC mismatch for {}", entry.name);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_extract_progress_stream() {
+    use tokio_stream::StreamExt;
+    
+    let temp = TempDir::new().unwrap();
+    let archive_path = temp.path().join("progress.7z");
+    let files: Vec<PathBuf> = (0..5)
+        .map(|i| create_test_file(temp.path(), &format!("file{}.txt", i), "progress stream data"))
+        .collect();
+    
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &files, CompressionLevel::Fast, None).unwrap();
+    
+    let extract_dir = temp.path().join("extracted");
+    fs::create_dir(&extract_dir).unwrap();
+    
+    let (progress, done) = sz.extract_progress_stream(&archive_path, &extract_dir, None);
+    let done = tokio::spawn(done);
+    let events: Vec<_> = progress.collect().await;
+    done.await.unwrap().expect("Extraction should succeed");
+    
+    let last = events.last().expect("Should receive at least one progress event");
+    assert_eq!(last.processed, 5);
+    assert_eq!(last.total, 5);
+    assert!(!last.current_file.is_empty());
+    assert!(events.windows(2).all(|w| w[0].processed < w[1].processed));
+    assert!(extract_dir.join("file4.txt").exists());
+}
+
+#[test]
+fn test_extract_restores_mtime() {
+    let temp = TempDir::new().unwrap();
+    let archive_path = temp.path().join("mtime.7z");
+    let source = create_test_file(temp.path(), "old.txt", "written a long time ago");
+    let old_mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&source, old_mtime).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let extract_dir = temp.path().join("extracted");
+    fs::create_dir(&extract_dir).unwrap();
+    sz.extract_with_options(&archive_path, &extract_dir, &ExtractOptions::default(), None).unwrap();
+    
+    let metadata = fs::metadata(extract_dir.join("old.txt")).unwrap();
+    let restored = filetime::FileTime::from_last_modification_time(&metadata);
+    assert!(
+        (restored.unix_seconds() - old_mtime.unix_seconds()).abs() <= 1,
+        "Extracted mtime {} should match archived mtime {}",
+        restored.unix_seconds(),
+        old_mtime.unix_seconds()
+    );
+}
+
+#[test]
+fn test_list_dir_returns_direct_children() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("tree");
+    fs::create_dir_all(source.join("a/b")).unwrap();
+    create_test_file(&source, "top.txt", "root level");
+    create_test_file(&source.join("a"), "x.txt", "in a");
+    create_test_file(&source.join("a"), "y.txt", "also in a");
+    create_test_file(&source.join("a/b"), "z.txt", "in a/b");
+    
+    let archive_path = temp.path().join("nested.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let mut names: Vec<String> = sz.list_dir(&archive_path, "a", None).unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["a/b", "a/x.txt", "a/y.txt"]);
+    
+    let mut root: Vec<String> = sz.list_dir(&archive_path, "", None).unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    root.sort();
+    assert_eq!(root, vec!["a", "top.txt"]);
+}
+
+#[test]
+fn test_extract_entry_as_renames_single_entry() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("src");
+    fs::create_dir_all(source.join("docs")).unwrap();
+    create_test_file(&source, "other.txt", "not this one");
+    create_test_file(&source.join("docs"), "readme.txt", "Renamed on the way out");
+    
+    let archive_path = temp.path().join("rename.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let dest = temp.path().join("out/nested/README");
+    sz.extract_entry_as(&archive_path, "docs/readme.txt", &dest, None).unwrap();
+    
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "Renamed on the way out");
+    assert!(!temp.path().join("out/nested/other.txt").exists());
+    
+    // Directories cannot be extracted as a file
+    assert!(sz.extract_entry_as(&archive_path, "docs", temp.path().join("dir_out"), None).is_err());
+}
+
+#[test]
+fn test_extract_entry_as_decrypts_with_password() {
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "prod.yaml", "db_password: hunter2");
+    let archive_path = temp.path().join("config.7z");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_password("deploy".to_string());
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let dest = temp.path().join("prod-backup.yaml");
+    sz.extract_entry_as(&archive_path, "prod.yaml", &dest, Some("deploy")).unwrap();
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "db_password: hunter2");
+    
+    for password in [None, Some("wrong")] {
+        let dest = temp.path().join("failed.yaml");
+        assert!(sz.extract_entry_as(&archive_path, "prod.yaml", &dest, password).is_err());
+        assert!(!dest.exists(), "{:?}", password);
+    }
+}
+
+#[test]
+fn test_uncompressed_size_matches_listing() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    create_test_file(&source, "one.txt", &"1".repeat(1000));
+    create_test_file(&source, "empty.txt", "");
+    create_test_file(&source.join("sub"), "two.txt", &"two ".repeat(2500));
+    
+    let archive_path = temp.path().join("sizes.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let listed: u64 = sz.list(&archive_path, None).unwrap().iter().map(|e| e.size).sum();
+    let total = sz.uncompressed_size(&archive_path, None).unwrap();
+    assert_eq!(total, listed);
+    assert_eq!(total, 11000);
+}
+
+#[test]
+fn test_extract_cancelled_from_another_thread() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    
+    let temp = TempDir::new().unwrap();
+    let files: Vec<PathBuf> = (0..50)
+        .map(|i| create_test_file(temp.path(), &format!("f{:02}.txt", i), "cancel me"))
+        .collect();
+    let archive_path = temp.path().join("cancel.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &files, CompressionLevel::Fast, None).unwrap();
+    
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    let (set_tx, set_rx) = mpsc::channel::<()>();
+    
+    // A separate thread flips the flag once extraction is under way
+    let flag = Arc::clone(&cancel);
+    let canceller = std::thread::spawn(move || {
+        started_rx.recv().unwrap();
+        flag.store(true, Ordering::SeqCst);
+        set_tx.send(()).unwrap();
+    });
+    
+    let mut signalled = false;
+    let progress: seven_zip::ProgressCallback = Box::new(move |_, _| {
+        if !signalled {
+            signalled = true;
+            started_tx.send(()).unwrap();
+            set_rx.recv().unwrap();
+        }
+    });
+    
+    let extract_dir = temp.path().join("extracted");
+    fs::create_dir(&extract_dir).unwrap();
+    let opts = ExtractOptions::default().with_cancel(Arc::clone(&cancel));
+    let result = sz.extract_with_options(&archive_path, &extract_dir, &opts, Some(progress));
+    canceller.join().unwrap();
+    
+    assert_eq!(result, Err(seven_zip::Error::Cancelled));
+    assert!(!extract_dir.join("f49.txt").exists(), "Extraction should stop early");
+}
+
+#[test]
+fn test_empty_directories_round_trip() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("project");
+    fs::create_dir_all(source.join("empty")).unwrap();
+    fs::create_dir_all(source.join("src")).unwrap();
+    create_test_file(&source.join("src"), "main.rs", "fn main() {}");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("dirs.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    assert!(entries.iter().any(|e| e.name == "empty" && e.is_directory),
+        "Empty directory should be stored as a directory entry");
+    
+    let extract_dir = temp.path().join("extracted");
+    fs::create_dir(&extract_dir).unwrap();
+    sz.extract(&archive_path, &extract_dir).unwrap();
+    assert!(extract_dir.join("empty").is_dir(), "Empty directory should be recreated");
+    assert!(extract_dir.join("src/main.rs").is_file());
+    
+    // Opting out drops the empty directory but keeps populated ones
+    let opts = CompressOptions::default().with_store_empty_dirs(false);
+    let no_dirs_path = temp.path().join("no_dirs.7z");
+    sz.create_archive(&no_dirs_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    let names: Vec<String> = sz.list(&no_dirs_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert!(!names.iter().any(|n| n == "empty"));
+    assert!(names.iter().any(|n| n == "src"));
+}
+
+#[test]
+fn test_streaming_store_empty_dirs() {
+    use seven_zip::StreamOptions;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("project");
+    fs::create_dir_all(source.join("empty")).unwrap();
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    fs::write(source.join("data.bin"), &data).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    for store in [true, false] {
+        let dir = temp.path().join(format!("store_{}", store));
+        fs::create_dir(&dir).unwrap();
+        
+        // Written in one piece and split into volumes
+        let single = StreamOptions::default().with_store_empty_dirs(store);
+        sz.create_archive_streaming(dir.join("single.7z"), &[&source], CompressionLevel::Fast, Some(&single), None)
+            .unwrap();
+        let entries = sz.list(dir.join("single.7z"), None).unwrap();
+        assert_eq!(entries.iter().any(|e| e.name == "empty" && e.is_directory), store);
+        
+        let split = StreamOptions { split_size: 100_000, ..single };
+        let split_dir = dir.join("split");
+        fs::create_dir(&split_dir).unwrap();
+        sz.create_archive_streaming(split_dir.join("split.7z"), &[&source], CompressionLevel::Store, Some(&split), None)
+            .unwrap();
+        let mut volumes: Vec<PathBuf> = fs::read_dir(&split_dir).unwrap().map(|e| e.unwrap().path()).collect();
+        volumes.sort();
+        assert!(volumes.len() > 1);
+        let output = dir.join("out");
+        sz.extract_from_volumes(&volumes, &output, None).unwrap();
+        assert_eq!(fs::read(output.join("project/data.bin")).unwrap(), data);
+        assert_eq!(output.join("project/empty").is_dir(), store);
+    }
+}
+
+#[test]
+fn test_panicking_progress_callback_leaves_no_partial_output() {
+    use seven_zip::StreamOptions;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    
+    let temp = TempDir::new().unwrap();
+    let scratch = TempDir::new().unwrap();
+    let input = create_test_file(temp.path(), "input.txt", &"panic test data ".repeat(4096));
+    let archive_path = temp.path().join("partial.7z");
+    
+    let sz = SevenZip::new().unwrap();
+    let opts = StreamOptions {
+        chunk_size: 4096,
+        temp_dir: Some(scratch.path().to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        sz.create_archive_true_streaming(
+            &archive_path,
+            &[&input],
+            CompressionLevel::Fast,
+            Some(&opts),
+            Some(Box::new(|_, _, _, _, _| panic!("progress callback failure"))),
+        )
+    }));
+    
+    assert!(result.is_err(), "Callback panic should propagate to the caller");
+    assert!(!archive_path.exists(), "Partial archive should be removed");
+    assert_eq!(fs::read_dir(scratch.path()).unwrap().count(), 0, "Temp files should be removed");
+    
+    // A failed write over an existing archive leaves it untouched
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Fast, None).unwrap();
+    let existing = fs::read(&archive_path).unwrap();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        sz.create_archive_streaming(
+            &archive_path,
+            &[&input],
+            CompressionLevel::Fast,
+            Some(&opts),
+            Some(Box::new(|_, _, _, _, _| panic!("progress callback failure"))),
+        )
+    }));
+    assert!(result.is_err(), "Callback panic should propagate to the caller");
+    assert_eq!(fs::read(&archive_path).unwrap(), existing);
+    
+    // Also when the output was already being written to
+    let missing = temp.path().join("missing.txt");
+    let result = sz.create_archive_hashed(&archive_path, &[&missing], CompressionLevel::Fast, None, seven_zip::HashKind::Sha256);
+    assert!(result.is_err());
+    assert_eq!(fs::read(&archive_path).unwrap(), existing);
+    assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2, "Only the input and the archive should remain");
+}
+
+#[test]
+fn test_detect_format_from_magic_bytes() {
+    let temp = TempDir::new().unwrap();
+    let fixture = |name: &str, bytes: &[u8]| {
+        let path = temp.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    };
+    
+    // Extensions are deliberately misleading: only the content matters
+    let zip = fixture("zip.7z", b"PK\x03\x04\x14\x00\x00\x00");
+    let xz = fixture("xz.zip", &[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00, 0x00, 0x04]);
+    let gzip = fixture("gzip.bin", &[0x1F, 0x8B, 0x08, 0x00]);
+    let mut tar_header = vec![0u8; 512];
+    tar_header[257..263].copy_from_slice(b"ustar\0");
+    let tar = fixture("tar.dat", &tar_header);
+    let unknown = fixture("notes.7z", b"just some text");
+    let empty = fixture("empty.xz", b"");
+    
+    assert_eq!(detect_format(&zip).unwrap(), ArchiveFormat::Zip);
+    assert_eq!(detect_format(&xz).unwrap(), ArchiveFormat::Xz);
+    assert_eq!(detect_format(&gzip).unwrap(), ArchiveFormat::Gzip);
+    assert_eq!(detect_format(&tar).unwrap(), ArchiveFormat::Tar);
+    assert_eq!(detect_format(&unknown).unwrap(), ArchiveFormat::Unknown);
+    assert_eq!(detect_format(&empty).unwrap(), ArchiveFormat::Unknown);
+    
+    // A real archive written by this library
+    let input = create_test_file(temp.path(), "input.txt", "format detection");
+    let archive = temp.path().join("real.zip");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive, &[&input], CompressionLevel::Fast, None).unwrap();
+    assert_eq!(detect_format(&archive).unwrap(), ArchiveFormat::SevenZip);
+    
+    assert!(detect_format(temp.path().join("missing")).is_err());
+}
+
+/// How a zip fixture's entries are encrypted
+enum ZipEncryption<'a> {
+    None,
+    Aes(&'a str),
+    ZipCrypto(&'a str),
+}
+
+/// Helper to write a zip fixture with the given (name, content) entries
+fn write_zip_fixture(path: &std::path::Path, files: &[(&str, &str)], encryption: ZipEncryption) {
+    use std::io::Write;
+    use zip::unstable::write::FileOptionsExt;
+    use zip::write::SimpleFileOptions;
+    
+    let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    let mut options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+    match encryption {
+        ZipEncryption::None => {}
+        ZipEncryption::Aes(password) => options = options.with_aes_encryption(zip::AesMode::Aes256, password),
+        ZipEncryption::ZipCrypto(password) => options = options.with_deprecated_encryption(password.as_bytes()),
+    }
+    for (name, content) in files {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn test_extract_plain_zip() {
+    let temp = TempDir::new().unwrap();
+    let zip_path = temp.path().join("plain.zip");
+    write_zip_fixture(&zip_path, &[("hello.txt", "Hello from zip"), ("dir/nested.txt", "Nested entry")], ZipEncryption::None);
+    
+    let sz = SevenZip::new().unwrap();
+    let mut names: Vec<String> = sz.list_zip(&zip_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["dir/nested.txt", "hello.txt"]);
+    
+    let output = temp.path().join("out");
+    sz.extract_zip(&zip_path, &output, None).unwrap();
+    assert_eq!(fs::read_to_string(output.join("hello.txt")).unwrap(), "Hello from zip");
+    assert_eq!(fs::read_to_string(output.join("dir/nested.txt")).unwrap(), "Nested entry");
+}
+
+#[test]
+fn test_extract_aes_encrypted_zip() {
+    let temp = TempDir::new().unwrap();
+    let zip_path = temp.path().join("aes.zip");
+    write_zip_fixture(&zip_path, &[("secret.txt", "AES protected content")], ZipEncryption::Aes("zip-password"));
+    
+    let sz = SevenZip::new().unwrap();
+    assert!(sz.extract_zip(&zip_path, temp.path().join("no_pw"), None).is_err());
+    assert!(sz.extract_zip(&zip_path, temp.path().join("bad_pw"), Some("wrong")).is_err());
+    
+    let output = temp.path().join("out");
+    sz.extract_zip(&zip_path, &output, Some("zip-password")).unwrap();
+    assert_eq!(fs::read_to_string(output.join("secret.txt")).unwrap(), "AES protected content");
+}
+
+#[test]
+fn test_extract_zipcrypto_encrypted_zip() {
+    let temp = TempDir::new().unwrap();
+    let zip_path = temp.path().join("zipcrypto.zip");
+    write_zip_fixture(&zip_path, &[("legacy.txt", "ZipCrypto protected content")], ZipEncryption::ZipCrypto("old-password"));
+    
+    let sz = SevenZip::new().unwrap();
+    assert!(matches!(
+        sz.extract_zip(&zip_path, temp.path().join("no_pw"), None),
+        Err(seven_zip::Error::DecryptionError(_))
+    ));
+    assert!(sz.extract_zip(&zip_path, temp.path().join("bad_pw"), Some("wrong")).is_err());
+    
+    let output = temp.path().join("out");
+    sz.extract_zip(&zip_path, &output, Some("old-password")).unwrap();
+    assert_eq!(fs::read_to_string(output.join("legacy.txt")).unwrap(), "ZipCrypto protected content");
+}
+
+#[test]
+fn test_list_zip_attributes_carry_unix_mode() {
+    let temp = TempDir::new().unwrap();
+    let zip_path = temp.path().join("modes.zip");
+    write_zip_fixture(&zip_path, &[("file.txt", "mode bits")], ZipEncryption::None);
+    
+    let sz = SevenZip::new().unwrap();
+    let entries = sz.list_zip(&zip_path, None).unwrap();
+    assert_eq!(entries.len(), 1);
+    // 7-Zip's convention: 0x8000 flags a Unix mode in the high 16 bits
+    let attributes = entries[0].attributes;
+    assert_ne!(attributes & 0x8000, 0, "{:#x}", attributes);
+    assert_eq!(attributes >> 16, 0o100644, "{:#x}", attributes);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tar_7z_round_trip_preserves_mode() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("project");
+    fs::create_dir_all(source.join("bin")).unwrap();
+    let script = create_test_file(&source.join("bin"), "run.sh", "#!/bin/sh\necho hi\n");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o751)).unwrap();
+    create_test_file(&source, "README", "tarred and compressed");
+    std::os::unix::fs::symlink("README", source.join("LINK")).unwrap();
+    
+    let archive_path = temp.path().join("project.tar.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_tar_7z(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "project.tar");
+    sz.test_archive(&archive_path, None).unwrap();
+    
+    let output = temp.path().join("restored");
+    sz.extract_tar_7z(&archive_path, &output, None).unwrap();
+    
+    let restored_script = output.join("project/bin/run.sh");
+    let mode = fs::metadata(&restored_script).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o751);
+    assert_eq!(fs::read_to_string(output.join("project/README")).unwrap(), "tarred and compressed");
+    assert_eq!(fs::read_link(output.join("project/LINK")).unwrap(), PathBuf::from("README"));
+    
+    // Large tars are streamed through in chunks; damage is still reported
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let noise: Vec<u8> = (0..3_000_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    fs::write(source.join("noise.bin"), &noise).unwrap();
+    for level in [CompressionLevel::Store, CompressionLevel::Normal] {
+        sz.create_tar_7z(&archive_path, &[&source], level, None).unwrap();
+        let output = temp.path().join(format!("large-{:?}", level));
+        sz.extract_tar_7z(&archive_path, &output, None).unwrap();
+        assert_eq!(fs::read(output.join("project/noise.bin")).unwrap(), noise);
+    }
+    let mut bytes = fs::read(&archive_path).unwrap();
+    bytes[2_000_000] ^= 0xFF;
+    fs::write(&archive_path, &bytes).unwrap();
+    assert!(sz.extract_tar_7z(&archive_path, temp.path().join("damaged"), None).is_err());
+}
+
+#[test]
+fn test_io_retry_only_where_rust_reads_inputs() {
+    use seven_zip::RetryPolicy;
+    
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "log.txt", "retried reads");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default()
+        .with_io_retry(RetryPolicy { attempts: 3, backoff: std::time::Duration::from_millis(1) });
+    
+    let err = sz.create_archive(temp.path().join("plain.7z"), &[&file], CompressionLevel::Fast, Some(&opts)).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::UnsupportedOptionCombination { .. }), "{:?}", err);
+    assert!(!temp.path().join("plain.7z").exists());
+    sz.create_tar_7z(temp.path().join("logs.tar.7z"), &[&file], CompressionLevel::Fast, Some(&opts)).unwrap();
+}
+
+#[test]
+fn test_replace_entry_leaves_other_entries_untouched() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("config");
+    fs::create_dir_all(source.join("profiles")).unwrap();
+    create_test_file(&source, "settings.json", r#"{"theme":"light"}"#);
+    create_test_file(&source, "notes.txt", "keep me");
+    create_test_file(&source.join("profiles"), "default.toml", "name = \"default\"");
+    
+    let archive_path = temp.path().join("config.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    let before = sz.list(&archive_path, None).unwrap();
+    
+    sz.replace_entry(&archive_path, "settings.json", br#"{"theme":"dark"}"#, CompressionLevel::Normal, None).unwrap();
+    
+    let mut before_names: Vec<String> = before.iter().map(|e| e.name.clone()).collect();
+    let mut after_names: Vec<String> = sz.list(&archive_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    before_names.sort();
+    after_names.sort();
+    assert_eq!(before_names, after_names);
+    
+    let output = temp.path().join("out");
+    sz.extract(&archive_path, &output).unwrap();
+    assert_eq!(fs::read_to_string(output.join("settings.json")).unwrap(), r#"{"theme":"dark"}"#);
+    assert_eq!(fs::read_to_string(output.join("notes.txt")).unwrap(), "keep me");
+    assert_eq!(fs::read_to_string(output.join("profiles/default.toml")).unwrap(), "name = \"default\"");
+    
+    // The rebuild uses the level it is given
+    let large = "compressible ".repeat(10_000);
+    sz.replace_entry(&archive_path, "notes.txt", large.as_bytes(), CompressionLevel::Store, None).unwrap();
+    assert!(fs::metadata(&archive_path).unwrap().len() > large.len() as u64);
+    sz.replace_entry(&archive_path, "notes.txt", large.as_bytes(), CompressionLevel::Normal, None).unwrap();
+    assert!(fs::metadata(&archive_path).unwrap().len() < large.len() as u64 / 10);
+    
+    // Missing entries are not added, and no staging files are left behind
+    assert!(sz.replace_entry(&archive_path, "missing.txt", b"x", CompressionLevel::Normal, None).is_err());
+    let leftovers: Vec<_> = fs::read_dir(temp.path()).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.contains("staging"))
+        .collect();
+    assert!(leftovers.is_empty(), "staging directory left behind: {:?}", leftovers);
+}
+
+#[test]
+fn test_compress_report_threads_used() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "a.txt", &"alpha ".repeat(2000));
+    create_test_file(&source, "b.txt", &"beta ".repeat(2000));
+    
+    let archive_path = temp.path().join("report.7z");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_threads(2);
+    let report = sz.create_archive_with_report(&archive_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    assert!(report.threads_used >= 1);
+    assert_eq!(report.entries, 2);
+    assert_eq!(report.input_bytes, 12000 + 10000);
+    assert_eq!(report.output_bytes, fs::metadata(&archive_path).unwrap().len());
+}
+
+#[test]
+fn test_method_by_extension_mixes_codecs_in_one_archive() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("media");
+    fs::create_dir_all(source.join("photos")).unwrap();
+    create_test_file(&source, "readme.txt", &"caption text ".repeat(500));
+    create_test_file(&source.join("photos"), "beach.jpg", &"not really a jpeg ".repeat(200));
+    create_test_file(&source, "notes.txt", &"more notes ".repeat(300));
+    create_test_file(&source.join("photos"), "SUNSET.JPG", "uppercase extension");
+    
+    let archive_path = temp.path().join("media.7z");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_method_for_extension("jpg", CompressionMethod::Copy);
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    let method = |name: &str| entries.iter().find(|e| e.name == name).unwrap().method.clone();
+    assert_eq!(method("readme.txt"), "LZMA2");
+    assert_eq!(method("notes.txt"), "LZMA2");
+    assert_eq!(method("photos/beach.jpg"), "Copy");
+    assert_eq!(method("photos/SUNSET.JPG"), "Copy");
+    
+    // Both folders decode back to the original files
+    sz.test_archive(&archive_path, None).unwrap();
+    let output = temp.path().join("out");
+    sz.extract(&archive_path, &output).unwrap();
+    assert_eq!(fs::read_to_string(output.join("notes.txt")).unwrap(), "more notes ".repeat(300));
+    assert_eq!(fs::read_to_string(output.join("photos/beach.jpg")).unwrap(), "not really a jpeg ".repeat(200));
+    assert_eq!(fs::read_to_string(output.join("photos/SUNSET.JPG")).unwrap(), "uppercase extension");
+}
+
+#[test]
+fn test_create_archive_hashed_matches_file_digest() {
+    use sha2::{Digest, Sha256};
+    use seven_zip::HashKind;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("release");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "app.bin", &"binary-ish payload ".repeat(1000));
+    create_test_file(&source, "CHANGELOG", "v1.0.0: first release");
+    
+    let archive_path = temp.path().join("release.7z");
+    let sz = SevenZip::new().unwrap();
+    let (report, digest) = sz
+        .create_archive_hashed(&archive_path, &[&source], CompressionLevel::Normal, None, HashKind::Sha256)
+        .unwrap();
+    
+    let on_disk = fs::read(&archive_path).unwrap();
+    assert_eq!(digest, Sha256::digest(&on_disk).to_vec());
+    assert_eq!(report.output_bytes, on_disk.len() as u64);
+    assert_eq!(report.entries, 2);
+    sz.test_archive(&archive_path, None).unwrap();
+    
+    let (_, blake) = sz
+        .create_archive_hashed(temp.path().join("release-b3.7z"), &[&source], CompressionLevel::Normal, None, HashKind::Blake3)
+        .unwrap();
+    assert_eq!(blake, blake3::hash(&fs::read(temp.path().join("release-b3.7z")).unwrap()).as_bytes().to_vec());
+}
+
+#[test]
+fn test_extract_into_named_subdir() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("src_dir");
+    fs::create_dir_all(source.join("lib")).unwrap();
+    create_test_file(&source, "Cargo.toml", "[package]");
+    create_test_file(&source.join("lib"), "mod.rs", "pub fn f() {}");
+    
+    let archive_path = temp.path().join("project.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Fast, None).unwrap();
+    
+    let output = temp.path().join("out");
+    let opts = ExtractOptions::default().with_subdir_from_archive(&archive_path);
+    sz.extract_with_options(&archive_path, &output, &opts, None).unwrap();
+    assert_eq!(fs::read_to_string(output.join("project/Cargo.toml")).unwrap(), "[package]");
+    assert_eq!(fs::read_to_string(output.join("project/lib/mod.rs")).unwrap(), "pub fn f() {}");
+    assert!(!output.join("Cargo.toml").exists());
+    
+    let opts = ExtractOptions::default().with_named_subdir("v2");
+    sz.extract_with_options(&archive_path, &output, &opts, None).unwrap();
+    assert!(output.join("v2/lib/mod.rs").exists());
+    
+    let subdir = |path: &str| ExtractOptions::default().with_subdir_from_archive(path).into_named_subdir.unwrap();
+    assert_eq!(subdir("releases/v1.2.3.7z"), "v1.2.3");
+    assert_eq!(subdir("backup.tar.7z.001"), "backup");
+    assert_eq!(subdir("notes.txt.7z"), "notes.txt");
+    assert_eq!(subdir(".7z"), "archive");
+    
+    let escaping = ExtractOptions::default().with_named_subdir("../elsewhere");
+    assert!(sz.extract_with_options(&archive_path, &output, &escaping, None).is_err());
+    assert!(!temp.path().join("elsewhere").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_non_utf8_entry_name_round_trips_via_raw_name() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("names");
+    fs::create_dir_all(&source).unwrap();
+    let latin1 = b"caf\xe9.txt";
+    fs::write(source.join(OsStr::from_bytes(latin1)), "latin-1 name").unwrap();
+    create_test_file(&source, "na\u{ef}ve \u{1F389}.txt", "utf-8 name");
+    
+    let archive_path = temp.path().join("names.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Fast, None).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    let mangled = entries.iter().find(|e| e.raw_name == latin1).expect("raw name preserved");
+    assert_eq!(mangled.name, "caf\u{FFFD}.txt");
+    let unicode = entries.iter().find(|e| e.name == "na\u{ef}ve \u{1F389}.txt").expect("utf-8 name decoded");
+    assert_eq!(unicode.raw_name, "na\u{ef}ve \u{1F389}.txt".as_bytes());
+    
+    let dest = temp.path().join("recovered.txt");
+    sz.extract_entry_as_raw(&archive_path, &mangled.raw_name, &dest, None).unwrap();
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "latin-1 name");
+    assert!(sz.extract_entry_as(&archive_path, "caf\u{e9}.txt", &dest, None).is_err());
+    
+    // Full extraction recreates the original bytes on disk
+    let output = temp.path().join("out");
+    sz.extract(&archive_path, &output).unwrap();
+    assert_eq!(fs::read_to_string(output.join(OsStr::from_bytes(latin1))).unwrap(), "latin-1 name");
+    assert_eq!(fs::read_to_string(output.join("na\u{ef}ve \u{1F389}.txt")).unwrap(), "utf-8 name");
+}
+
+#[test]
+fn test_new_with_config_without_global_init() {
+    use seven_zip::LibConfig;
+    
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "hosted.txt", "shared runtime");
+    let archive_path = temp.path().join("hosted.7z");
+    
+    // The "host" owns the global init for the whole test
+    let host = SevenZip::new().unwrap();
+    host.create_archive(&archive_path, &[&file], CompressionLevel::Fast, None).unwrap();
+    
+    let config = LibConfig::default().with_manage_global_init(false);
+    let guest = SevenZip::new_with_config(&config).unwrap();
+    let entries = guest.list(&archive_path, None).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "hosted.txt");
+    drop(guest);
+    
+    // Dropping the guest left the host's runtime alone
+    assert_eq!(host.list(&archive_path, None).unwrap().len(), 1);
+}
+
+#[test]
+fn test_walk_progress_reports_files_seen() {
+    use std::sync::{Arc, Mutex};
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("tree");
+    for dir in ["a", "a/b", "c"] {
+        fs::create_dir_all(source.join(dir)).unwrap();
+    }
+    for (i, dir) in ["a", "a/b", "c", "a", "c"].iter().enumerate() {
+        create_test_file(&source.join(dir), &format!("f{}.txt", i), "walk me");
+    }
+    
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reports);
+    let opts = CompressOptions::default().with_walk_progress(move |files| sink.lock().unwrap().push(files));
+    
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(temp.path().join("tree.7z"), &[&source], CompressionLevel::Fast, Some(&opts)).unwrap();
+    
+    let reports = reports.lock().unwrap();
+    assert!(!reports.is_empty(), "walk progress never fired");
+    assert_eq!(*reports.last().unwrap(), 5);
+}
+
+#[test]
+fn test_extract_from_renamed_volumes() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("a.txt");
+    fs::write(&source, "volume contents ".repeat(2000)).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("whole.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Store, None).unwrap();
+    
+    // Cut the archive into oddly named pieces
+    let bytes = fs::read(&archive).unwrap();
+    let cuts = [0, 100, bytes.len() / 2, bytes.len()];
+    let volumes: Vec<_> = (0..3)
+        .map(|i| {
+            let path = temp.path().join(format!("part{}.bin", i + 1));
+            fs::write(&path, &bytes[cuts[i]..cuts[i + 1]]).unwrap();
+            path
+        })
+        .collect();
+    
+    let output = temp.path().join("out");
+    sz.extract_from_volumes(&volumes, &output, None).unwrap();
+    assert_eq!(fs::read(output.join("a.txt")).unwrap(), fs::read(&source).unwrap());
+    
+    // Out of order, the volumes no longer form an archive
+    let reversed: Vec<_> = volumes.iter().rev().collect();
+    let err = sz.extract_from_volumes(&reversed, temp.path().join("bad"), None).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::InvalidArchive(_)), "unexpected error: {:?}", err);
+}
+
+#[test]
+fn test_extract_volumes_nested_entries() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    fs::create_dir_all(source.join("dir")).unwrap();
+    create_test_file(&source.join("dir"), "file.txt", &"nested ".repeat(2000));
+    create_test_file(&source, "ab.txt", "top level");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("nested.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Store, None).unwrap();
+    let split = |archive: &std::path::Path, prefix: &str| -> Vec<std::path::PathBuf> {
+        let bytes = fs::read(archive).unwrap();
+        let middle = bytes.len() / 2;
+        [&bytes[..middle], &bytes[middle..]]
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                let path = temp.path().join(format!("{}{}.bin", prefix, i + 1));
+                fs::write(&path, part).unwrap();
+                path
+            })
+            .collect()
+    };
+    
+    let output = temp.path().join("out");
+    sz.extract_from_volumes(&split(&archive, "nested"), &output, None).unwrap();
+    assert_eq!(fs::read_to_string(output.join("dir/file.txt")).unwrap(), "nested ".repeat(2000));
+    assert_eq!(fs::read_to_string(output.join("ab.txt")).unwrap(), "top level");
+    
+    // An entry that would land outside the output directory fails the call
+    patch_entry_name(&archive, "ab.txt", "../abc");
+    let err = sz.extract_from_volumes(&split(&archive, "escape"), temp.path().join("bad"), None).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::InvalidArchive(_)), "unexpected error: {:?}", err);
+    assert!(!temp.path().join("abc").exists());
+}
+
+#[test]
+fn test_split_volume_naming_schemes() {
+    use seven_zip::{StreamOptions, VolumeNaming};
+    
+    let temp = TempDir::new().unwrap();
+    // Incompressible data so the archive needs several volumes
+    let mut state = 0x2545_f491_u32;
+    let data: Vec<u8> = (0..300_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let source = temp.path().join("data.bin");
+    fs::write(&source, &data).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let cases = [
+        (VolumeNaming::PartN, ["split.part1.7z", "split.part2.7z"], "split.part4.7z"),
+        (VolumeNaming::ZipStyle, ["split.z01", "split.z02"], "split.zip"),
+    ];
+    for (naming, first_two, last) in cases {
+        let dir = temp.path().join(format!("{:?}", naming));
+        fs::create_dir(&dir).unwrap();
+        
+        let opts = StreamOptions { split_size: 100_000, volume_naming: naming, ..Default::default() };
+        sz.create_archive_streaming(dir.join("split.7z"), &[&source], CompressionLevel::Store, Some(&opts), None)
+            .unwrap();
+        
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 4, "{:?}: {:?}", naming, names);
+        for name in first_two.iter().chain([&last]) {
+            assert!(names.contains(&name.to_string()), "{:?}: missing {} in {:?}", naming, name, names);
+        }
+        
+        // The volumes read back in order; zip-style sorts its .zip last already
+        let volumes: Vec<PathBuf> = names.iter().map(|n| dir.join(n)).collect();
+        let output = dir.join("out");
+        sz.extract_from_volumes(&volumes, &output, None).unwrap();
+        assert_eq!(fs::read(output.join("data.bin")).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_recompress_store_to_normal() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("docs");
+    fs::create_dir_all(source.join("nested")).unwrap();
+    create_test_file(&source, "a.txt", &"recompress me please ".repeat(5000));
+    create_test_file(&source.join("nested"), "b.txt", &"and me as well ".repeat(3000));
+    
+    let sz = SevenZip::new().unwrap();
+    let stored = temp.path().join("stored.7z");
+    sz.create_archive(&stored, &[&source], CompressionLevel::Store, None).unwrap();
+    
+    let packed = temp.path().join("packed.7z");
+    sz.recompress(&stored, &packed, CompressionLevel::Normal, None).unwrap();
+    assert!(fs::metadata(&packed).unwrap().len() < fs::metadata(&stored).unwrap().len() / 10);
+    
+    let summary = |path: &PathBuf| {
+        let mut entries: Vec<_> = sz.list(path, None).unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.size, e.modified_time, e.crc32))
+            .collect();
+        entries.sort();
+        entries
+    };
+    assert_eq!(summary(&stored), summary(&packed));
+    
+    let output = temp.path().join("out");
+    sz.extract(&packed, &output).unwrap();
+    assert_eq!(fs::read(output.join("a.txt")).unwrap(), fs::read(source.join("a.txt")).unwrap());
+    assert_eq!(fs::read(output.join("nested/b.txt")).unwrap(), fs::read(source.join("nested/b.txt")).unwrap());
+}
+
+#[test]
+fn test_extract_with_counter_polls_until_done() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("src");
+    fs::create_dir_all(&source).unwrap();
+    for i in 0..5 {
+        create_test_file(&source, &format!("f{}.txt", i), &"counted ".repeat(10_000));
+    }
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("counted.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Fast, None).unwrap();
+    
+    let output = temp.path().join("out");
+    let (progress, worker) = sz.extract_with_counter(&archive, &output, &ExtractOptions::default());
+    let mut last = 0;
+    while !worker.is_finished() {
+        let processed = progress.processed();
+        assert!(processed >= last, "progress went backwards");
+        last = processed;
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    worker.join().unwrap().unwrap();
+    
+    assert!(progress.total() > 0);
+    assert_eq!(progress.processed(), progress.total());
+    assert_eq!(fs::read_dir(&output).unwrap().count(), 5);
+}
+
+#[test]
+fn test_extract_with_counter_continue_on_error() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    create_test_file(&input, "good1.txt", "first good file");
+    create_test_file(&input, "bad.txt", "CORRUPT-ME-PLEASE");
+    create_test_file(&input, "good2.txt", "second good file");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("damaged.7z");
+    sz.create_archive(&archive, &[&input], CompressionLevel::Store, None).unwrap();
+    let mut bytes = fs::read(&archive).unwrap();
+    let at = bytes.windows(7).position(|w| w == b"CORRUPT").unwrap();
+    bytes[at] ^= 0xFF;
+    fs::write(&archive, bytes).unwrap();
+    
+    // Entries are written one by one in this mode, failed ones counted too
+    let output = temp.path().join("out");
+    let opts = ExtractOptions::default().with_continue_on_error(true);
+    let (progress, worker) = sz.extract_with_counter(&archive, &output, &opts);
+    let mut last = 0;
+    while !worker.is_finished() {
+        let processed = progress.processed();
+        assert!(processed >= last, "progress went backwards");
+        last = processed;
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    assert!(matches!(worker.join().unwrap(), Err(seven_zip::Error::PartialExtraction { .. })));
+    
+    assert_eq!(progress.total(), 3);
+    assert_eq!(progress.processed(), 3);
+}
+
+#[test]
+fn test_create_archive_rejects_missing_and_duplicate_inputs() {
+    let temp = TempDir::new().unwrap();
+    let present = create_test_file(temp.path(), "present.txt", "here");
+    let missing = temp.path().join("typo.txt");
+    let archive = temp.path().join("out.7z");
+    
+    let sz = SevenZip::new().unwrap();
+    let err = sz.create_archive(&archive, &[&present, &missing], CompressionLevel::Fast, None).unwrap_err();
+    match err {
+        seven_zip::Error::InvalidParameter(msg) => assert!(msg.contains("typo.txt"), "message: {}", msg),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(!archive.exists());
+    
+    // The same file twice, or two files that would share an entry name
+    fs::create_dir(temp.path().join("other")).unwrap();
+    let same_name = create_test_file(&temp.path().join("other"), "present.txt", "there");
+    for inputs in [[&present, &present], [&present, &same_name]] {
+        let err = sz.create_archive(&archive, &inputs, CompressionLevel::Fast, None).unwrap_err();
+        assert!(matches!(err, seven_zip::Error::InvalidParameter(_)), "unexpected error: {:?}", err);
+    }
+    
+    // Same-named files are fine when the stored names differ
+    let relative = CompressOptions {
+        path_mode: seven_zip::PathMode::Relative { base: temp.path().to_path_buf() },
+        ..Default::default()
+    };
+    let numbered = CompressOptions { preserve_root_names: true, ..Default::default() };
+    for (opts, names) in [(relative, ["other/present.txt", "present.txt"]), (numbered, ["present.txt", "present_2.txt"])] {
+        sz.create_archive(&archive, &[&present, &same_name], CompressionLevel::Fast, Some(&opts)).unwrap();
+        let mut stored: Vec<String> = sz.list(&archive, None).unwrap().into_iter().map(|e| e.name).collect();
+        stored.sort();
+        assert_eq!(stored, names);
+    }
+}
+
+#[test]
+fn test_compress_to_non_seekable_writer() {
+    /// Write-only sink, like a pipe
+    struct PipeLike(Vec<u8>);
+    impl std::io::Write for PipeLike {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    let temp = TempDir::new().unwrap();
+    let source = create_test_file(temp.path(), "piped.txt", &"through a pipe ".repeat(1000));
+    
+    let sz = SevenZip::new().unwrap();
+    let mut pipe = PipeLike(Vec::new());
+    sz.compress_to_writer(&[&source], CompressionLevel::Normal, None, &mut pipe).unwrap();
+    assert!(pipe.0.starts_with(b"7z\xBC\xAF\x27\x1C"));
+    
+    let archive = temp.path().join("piped.7z");
+    fs::write(&archive, &pipe.0).unwrap();
+    let output = temp.path().join("out");
+    sz.extract(&archive, &output).unwrap();
+    assert_eq!(fs::read(output.join("piped.txt")).unwrap(), fs::read(&source).unwrap());
+}
+
+#[test]
+fn test_modified_time_is_little_endian_on_disk() {
+    let temp = TempDir::new().unwrap();
+    let source = create_test_file(temp.path(), "dated.txt", "fixed timestamp");
+    let mtime: u64 = 1_600_000_000;
+    filetime::set_file_mtime(&source, filetime::FileTime::from_unix_time(mtime as i64, 0)).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("dated.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    // The header stores a FILETIME (100ns ticks since 1601) little-endian,
+    // whatever the host; check the bytes directly so a big-endian host that
+    // wrote or read native order would fail here
+    let filetime = mtime * 10_000_000 + 116_444_736_000_000_000;
+    let bytes = fs::read(&archive).unwrap();
+    assert!(bytes.windows(8).any(|w| w == filetime.to_le_bytes()), "FILETIME not stored little-endian");
+    
+    let entries = sz.list(&archive, None).unwrap();
+    assert_eq!(entries[0].modified_time, mtime);
+}
+
+#[test]
+fn test_set_all_mtimes_rewrites_header_only() {
+    use std::time::{Duration, SystemTime};
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("build");
+    fs::create_dir_all(source.join("bin")).unwrap();
+    create_test_file(&source, "readme.txt", "reproducible");
+    create_test_file(&source.join("bin"), "tool", &"binary-ish ".repeat(500));
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("build.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Normal, None).unwrap();
+    let packed_before = fs::read(&archive).unwrap()[32..64].to_vec();
+    
+    for (when, expected) in [
+        (SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000), 1_000_000_000),
+        (SystemTime::UNIX_EPOCH, 0),
+    ] {
+        sz.set_all_mtimes(&archive, when, None).unwrap();
+        let entries = sz.list(&archive, None).unwrap();
+        assert_eq!(entries.len(), 3);
+        for entry in &entries {
+            assert_eq!(entry.modified_time, expected, "{}", entry.name);
+        }
+    }
+    
+    // Packed data is untouched and the archive still extracts
+    assert_eq!(fs::read(&archive).unwrap()[32..64], packed_before[..]);
+    sz.test_archive(&archive, None).unwrap();
+    let output = temp.path().join("out");
+    sz.extract(&archive, &output).unwrap();
+    assert_eq!(fs::read_to_string(output.join("readme.txt")).unwrap(), "reproducible");
+    
+    // Compressed headers are rewritten too; the other times and the
+    // recovery record are kept
+    let opts = CompressOptions::default()
+        .with_compress_header(true)
+        .with_store_all_times(true)
+        .with_recovery_percent(10);
+    let archive = temp.path().join("full.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    let before = sz.list_full(&archive, None).unwrap();
+    sz.set_all_mtimes(&archive, SystemTime::UNIX_EPOCH, None).unwrap();
+    let after = sz.list_full(&archive, None).unwrap();
+    assert_eq!(after.len(), before.len());
+    for full in &after {
+        let old = before.iter().find(|b| b.entry.name == full.entry.name).unwrap();
+        assert_eq!(full.modified, Some(SystemTime::UNIX_EPOCH));
+        assert!(full.accessed.is_some());
+        assert_eq!((full.created, full.accessed), (old.created, old.accessed));
+        assert_eq!(full.entry.attributes, old.entry.attributes);
+    }
+    assert_eq!(sz.recover(&archive).unwrap(), 0);
+    let mut bytes = fs::read(&archive).unwrap();
+    bytes[40] ^= 0xFF;
+    fs::write(&archive, &bytes).unwrap();
+    assert!(sz.recover(&archive).unwrap() > 0);
+    sz.test_archive(&archive, None).unwrap();
+    
+    let leftovers: Vec<_> = fs::read_dir(temp.path()).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.contains("staging"))
+        .collect();
+    assert!(leftovers.is_empty(), "staging directory left behind: {:?}", leftovers);
+}
+
+#[test]
+fn test_for_each_entry_visits_every_entry() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("many");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    for i in 0..40 {
+        create_test_file(&source, &format!("f{:02}.txt", i), "x");
+    }
+    create_test_file(&source.join("sub"), "nested.txt", "y");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("many.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Fast, None).unwrap();
+    let listed = sz.list(&archive, None).unwrap();
+    
+    let mut seen = Vec::new();
+    sz.for_each_entry(&archive, None, |entry| {
+        seen.push(entry.name.clone());
+        Ok(())
+    }).unwrap();
+    assert_eq!(seen.len(), listed.len());
+    assert_eq!(seen, listed.iter().map(|e| e.name.clone()).collect::<Vec<_>>());
+    
+    // An error from the closure stops the walk
+    let mut calls = 0;
+    let result = sz.for_each_entry(&archive, None, |_| {
+        calls += 1;
+        if calls == 3 { Err(seven_zip::Error::Cancelled) } else { Ok(()) }
+    });
+    assert_eq!(result, Err(seven_zip::Error::Cancelled));
+    assert_eq!(calls, 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fifo_in_input_is_skipped() {
+    use seven_zip::SpecialFilePolicy;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("tree");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "regular.txt", "kept");
+    let fifo = source.join("pipe");
+    let status = std::process::Command::new("mkfifo").arg(&fifo).status().unwrap();
+    assert!(status.success());
+    
+    // Nobody writes to the FIFO, so opening it would hang the test
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("tree.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Fast, None).unwrap();
+    sz.test_archive(&archive, None).unwrap();
+    let names: Vec<String> = sz.list(&archive, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert!(names.iter().any(|n| n.ends_with("regular.txt")));
+    assert!(!names.iter().any(|n| n.ends_with("pipe")));
+    
+    let strict = CompressOptions::default().with_special_files(SpecialFilePolicy::Error);
+    match sz.create_archive(temp.path().join("strict.7z"), &[&source], CompressionLevel::Fast, Some(&strict)) {
+        Err(seven_zip::Error::InvalidParameter(msg)) => assert!(msg.contains("pipe"), "{}", msg),
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+    
+    let metadata = CompressOptions::default().with_special_files(SpecialFilePolicy::StoreMetadataOnly);
+    let archive = temp.path().join("metadata.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Fast, Some(&metadata)).unwrap();
+    let entries = sz.list(&archive, None).unwrap();
+    let pipe = entries.iter().find(|e| e.name.ends_with("pipe")).unwrap();
+    assert_eq!(pipe.size, 0);
+    assert!(!pipe.is_directory);
+}
+
+#[test]
+fn test_supported_methods_include_builtin_codecs() {
+    let methods = seven_zip::supported_methods();
+    assert!(methods.contains(&CompressionMethod::Lzma2));
+    assert!(methods.contains(&CompressionMethod::Copy));
+}
+
+#[test]
+fn test_recovery_record_repairs_corruption() {
+    let temp = TempDir::new().unwrap();
+    // Pseudo-random bytes so the archive is about as large as the input
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let content: Vec<u8> = (0..200_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let input = temp.path().join("evidence.bin");
+    fs::write(&input, &content).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("evidence.7z");
+    let opts = CompressOptions::default().with_recovery_percent(10);
+    sz.create_archive(&archive, &[&input], CompressionLevel::Store, Some(&opts)).unwrap();
+    sz.test_archive(&archive, None).unwrap();
+    assert_eq!(sz.recover(&archive).unwrap(), 0);
+    
+    let mut bytes = fs::read(&archive).unwrap();
+    for b in &mut bytes[100_000..100_300] {
+        *b ^= 0x5A;
+    }
+    fs::write(&archive, &bytes).unwrap();
+    assert!(sz.test_archive(&archive, None).is_err());
+    
+    assert!(sz.recover(&archive).unwrap() > 0);
+    sz.test_archive(&archive, None).unwrap();
+    let output = temp.path().join("out");
+    sz.extract(&archive, &output).unwrap();
+    assert_eq!(fs::read(output.join("evidence.bin")).unwrap(), content);
+    
+    // Archives without a record say so
+    let plain = temp.path().join("plain.7z");
+    sz.create_archive(&plain, &[&input], CompressionLevel::Store, None).unwrap();
+    assert!(matches!(sz.recover(&plain), Err(seven_zip::Error::InvalidArchive(_))));
+}
+
+#[test]
+fn test_extract_manifest_lists_written_files() {
+    use seven_zip::ExtractStatus;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("case");
+    fs::create_dir_all(source.join("logs")).unwrap();
+    create_test_file(&source, "report.txt", "findings");
+    create_test_file(&source.join("logs"), "auth.log", "login ok\nlogin failed\n");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("case.7z");
+    sz.create_archive(&archive, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let output = temp.path().join("out");
+    fs::create_dir_all(&output).unwrap();
+    create_test_file(&output, "report.txt", "stale");
+    let manifest = sz.extract_manifest(&archive, &output, None).unwrap();
+    
+    let entries = sz.list(&archive, None).unwrap();
+    assert_eq!(manifest.len(), entries.len());
+    for (file, entry) in manifest.iter().zip(&entries) {
+        assert_eq!(file.archive_name, entry.name);
+        assert_eq!(file.disk_path, output.join(&entry.name));
+        assert!(file.disk_path.exists());
+        assert!(file.crc_ok);
+        if !entry.is_directory {
+            assert_eq!(file.bytes_written, entry.size);
+            assert_eq!(fs::metadata(&file.disk_path).unwrap().len(), entry.size);
+        }
+    }
+    
+    let report = manifest.iter().find(|f| f.archive_name.ends_with("report.txt")).unwrap();
+    assert_eq!(report.status, ExtractStatus::Overwritten);
+    assert_eq!(fs::read_to_string(&report.disk_path).unwrap(), "findings");
+    let log = manifest.iter().find(|f| f.archive_name.ends_with("auth.log")).unwrap();
+    assert_eq!(log.status, ExtractStatus::Created);
+    
+    // A corrupt entry is recorded as failing its CRC and not written
+    let stored = temp.path().join("stored.7z");
+    let input = create_test_file(temp.path(), "note.txt", "stored as is");
+    sz.create_archive(&stored, &[&input], CompressionLevel::Store, None).unwrap();
+    let mut bytes = fs::read(&stored).unwrap();
+    bytes[32] ^= 0xFF;
+    fs::write(&stored, &bytes).unwrap();
+    let corrupt_out = temp.path().join("corrupt");
+    let manifest = sz.extract_manifest(&stored, &corrupt_out, None).unwrap();
+    assert_eq!(manifest.len(), 1);
+    assert!(!manifest[0].crc_ok);
+    assert_eq!(manifest[0].status, ExtractStatus::Skipped);
+    assert_eq!(manifest[0].bytes_written, 0);
+    assert!(!corrupt_out.join("note.txt").exists());
+}
+
+#[test]
+fn test_extract_over_readonly_file() {
+    let temp = TempDir::new().unwrap();
+    let input = create_test_file(temp.path(), "locked.txt", "new content");
+    let sz = SevenZip::new().unwrap();
+    let archive = temp.path().join("locked.7z");
+    sz.create_archive(&archive, &[&input], CompressionLevel::Normal, None).unwrap();
+    
+    let output = temp.path().join("out");
+    fs::create_dir_all(&output).unwrap();
+    let existing = create_test_file(&output, "locked.txt", "old content");
+    let mut permissions = fs::metadata(&existing).unwrap().permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(0o444);
+    }
+    #[cfg(not(unix))]
+    permissions.set_readonly(true);
+    fs::set_permissions(&existing, permissions).unwrap();
+    
+    match sz.extract_with_options(&archive, &output, &ExtractOptions::default(), None) {
+        Err(seven_zip::Error::Extract(msg)) => assert!(msg.contains("locked.txt"), "{}", msg),
+        other => panic!("expected a read-only error, got {:?}", other),
+    }
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "old content");
+    
+    let opts = ExtractOptions::default().with_force_overwrite_readonly(true);
+    sz.extract_with_options(&archive, &output, &opts, None).unwrap();
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "new content");
+}
+
+#[test]
+fn test_encrypt_blob_round_trip() {
+    use seven_zip::crypto::{decrypt_blob, encrypt_blob};
+    
+    let data = b"chain of custody: sealed at 09:14".repeat(10);
+    let blob = encrypt_blob(&data, "correct horse").unwrap();
+    assert_eq!(blob.ciphertext.len() % 16, 0);
+    assert_ne!(&blob.ciphertext[..data.len()], &data[..]);
+    assert_eq!(decrypt_blob(&blob, "correct horse").unwrap(), data);
+    
+    // Fresh salt and IV per call
+    let again = encrypt_blob(&data, "correct horse").unwrap();
+    assert_ne!(again.salt, blob.salt);
+    assert_ne!(again.ciphertext, blob.ciphertext);
+    
+    assert_eq!(decrypt_blob(&blob, "battery staple"), Err(seven_zip::Error::WrongPassword));
+    
+    // Wrong passwords never slip through on a lucky padding byte, and any
+    // change to the blob is caught
+    for i in 0..16 {
+        assert_eq!(decrypt_blob(&blob, &format!("wrong {}", i)), Err(seven_zip::Error::WrongPassword));
+    }
+    let mut tampered = blob.clone();
+    tampered.ciphertext[0] ^= 1;
+    assert_eq!(decrypt_blob(&tampered, "correct horse"), Err(seven_zip::Error::WrongPassword));
+}
+
+#[test]
+fn test_encrypt_blob_with_raw_key() {
+    use seven_zip::crypto::{decrypt_blob, decrypt_blob_with_key, encrypt_blob_with_key, Decryptor};
+    
+    let key: [u8; 32] = std::array::from_fn(|i| i as u8 * 7);
+    let data = b"key from the KMS, not a password".repeat(5);
+    let blob = encrypt_blob_with_key(&data, &key).unwrap();
+    assert_eq!(blob.salt, [0; 16]);
+    assert_ne!(&blob.ciphertext[..data.len()], &data[..]);
+    assert_eq!(decrypt_blob_with_key(&blob, &key).unwrap(), data);
+    
+    let mut decryptor = Decryptor::with_key(&key, &blob.iv);
+    let mut streamed = decryptor.update(&blob.ciphertext).unwrap();
+    streamed.extend(decryptor.finalize(&blob.mac).unwrap());
+    assert_eq!(streamed, data);
+    
+    let mut other = key;
+    other[0] ^= 1;
+    assert_eq!(decrypt_blob_with_key(&blob, &other), Err(seven_zip::Error::WrongPassword));
+    assert_eq!(decrypt_blob(&blob, "password"), Err(seven_zip::Error::WrongPassword));
+}
+
+#[test]
+fn test_streaming_decryptor_matches_one_shot() {
+    use seven_zip::crypto::{decrypt_blob, encrypt_blob, Decryptor};
+    
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let blob = encrypt_blob(&data, "pieces").unwrap();
+    let one_shot = decrypt_blob(&blob, "pieces").unwrap();
+    
+    let mut decryptor = Decryptor::new("pieces", &blob.salt, &blob.iv).unwrap();
+    let mut streamed = Vec::new();
+    let mut rest = &blob.ciphertext[..];
+    for size in [1, 7, 16, 33, 4096, 5].iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (piece, tail) = rest.split_at((*size).min(rest.len()));
+        streamed.extend(decryptor.update(piece).unwrap());
+        rest = tail;
+    }
+    streamed.extend(decryptor.finalize(&blob.mac).unwrap());
+    assert_eq!(streamed, one_shot);
+    assert_eq!(streamed, data);
+    
+    let mut wrong = Decryptor::new("not pieces", &blob.salt, &blob.iv).unwrap();
+    wrong.update(&blob.ciphertext).unwrap();
+    assert_eq!(wrong.finalize(&blob.mac), Err(seven_zip::Error::WrongPassword));
+}
+
+#[test]
+fn test_compare_archives() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "same.txt", "unchanged");
+    create_test_file(&source, "edited.txt", "version 1");
+    create_test_file(&source, "removed.txt", "gone later");
+    
+    let sz = SevenZip::new().unwrap();
+    let original = temp.path().join("original.7z");
+    sz.create_archive(&original, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let diff = sz.compare(&original, &original, None, None).unwrap();
+    assert!(diff.is_identical());
+    assert_eq!(diff.identical, sz.list(&original, None).unwrap().len());
+    
+    // Same size, different content: only the CRC tells them apart
+    create_test_file(&source, "edited.txt", "version 2");
+    fs::remove_file(source.join("removed.txt")).unwrap();
+    create_test_file(&source, "added.txt", "new");
+    let modified = temp.path().join("modified.7z");
+    sz.create_archive(&modified, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let diff = sz.compare(&original, &modified, None, None).unwrap();
+    assert!(!diff.is_identical());
+    assert_eq!(diff.differing, vec!["edited.txt".to_string()]);
+    assert_eq!(diff.only_in_a, vec!["removed.txt".to_string()]);
+    assert_eq!(diff.only_in_b, vec!["added.txt".to_string()]);
+    assert_eq!(diff.identical, 1);
+}
+
+#[test]
+fn test_folder_layout_of_solid_archive() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "a.txt", &"alpha ".repeat(200));
+    create_test_file(&source, "b.txt", &"bravo ".repeat(200));
+    create_test_file(&source, "c.txt", &"charlie ".repeat(200));
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("solid.7z");
+    let opts = CompressOptions::default();
+    assert!(opts.solid);
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let folders = sz.folder_layout(&archive_path, None).unwrap();
+    assert_eq!(folders.len(), 1);
+    
+    let folder = &folders[0];
+    assert_eq!(folder.index, 0);
+    let mut names = folder.entry_names.clone();
+    names.sort();
+    assert_eq!(names, vec!["a.txt", "b.txt", "c.txt"]);
+    
+    // The packed data sits after the 32-byte signature header and inside the file
+    let archive_len = fs::metadata(&archive_path).unwrap().len();
+    assert!(folder.packed_offset >= 32);
+    assert!(folder.packed_size > 0);
+    assert!(folder.packed_offset + folder.packed_size <= archive_len);
+    assert!(folder.codec.contains("LZMA"), "codec: {}", folder.codec);
+}
+
+#[test]
+fn test_archive_reader_splits_substream() {
+    use std::io::Read;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "a.txt", &"alpha ".repeat(200));
+    create_test_file(&source, "b.txt", &"bravo ".repeat(300));
+    create_test_file(&source, "c.txt", &"charlie ".repeat(100));
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("solid.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let mut reader = sz.reader(&archive_path, None).unwrap();
+    assert_eq!(reader.folders().len(), 1);
+    assert_eq!(reader.folders()[0].entry_names.len(), 3);
+    
+    let entries = reader.entries_in_folder(0).to_vec();
+    assert!(reader.entries_in_folder(1).is_empty());
+    let mut stream = Vec::new();
+    reader.substream(0).unwrap().read_to_end(&mut stream).unwrap();
+    assert_eq!(stream.len() as u64, entries.iter().map(|e| e.size).sum::<u64>());
+    
+    let mut rest = stream.as_slice();
+    for entry in &entries {
+        let (data, tail) = rest.split_at(entry.size as usize);
+        assert_eq!(data, fs::read(source.join(&entry.name)).unwrap().as_slice(), "{}", entry.name);
+        rest = tail;
+    }
+    
+    assert!(matches!(reader.substream(1), Err(seven_zip::Error::InvalidParameter(_))));
+}
+
+#[test]
+fn test_extract_mapped() {
+    use std::path::PathBuf;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("conf")).unwrap();
+    create_test_file(&source, "conf/app.toml", "port = 8080");
+    create_test_file(&source, "hosts", "127.0.0.1 localhost");
+    create_test_file(&source, "other.txt", "not deployed");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("deploy.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let out = temp.path().join("root");
+    let app = out.join("opt/app/settings.toml");
+    let hosts = out.join("etc/hosts");
+    sz.extract_mapped(&archive_path, &[("conf/app.toml", app.clone()), ("hosts", hosts.clone())], None).unwrap();
+    assert_eq!(fs::read_to_string(&app).unwrap(), "port = 8080");
+    assert_eq!(fs::read_to_string(&hosts).unwrap(), "127.0.0.1 localhost");
+    
+    // An unknown name fails before anything is written
+    let stray = out.join("stray.txt");
+    let result = sz.extract_mapped(&archive_path, &[("other.txt", stray.clone()), ("missing", PathBuf::from("x"))], None);
+    assert!(matches!(result, Err(seven_zip::Error::InvalidParameter(_))));
+    assert!(!stray.exists());
+}
+
+#[test]
+fn test_unsupported_option_combination_fails_up_front() {
+    use seven_zip::StreamOptions;
+    
+    let temp = TempDir::new().unwrap();
+    let test_file = create_test_file(temp.path(), "data.txt", "volume data");
+    let archive_path = temp.path().join("split.7z");
+    let sz = SevenZip::new().unwrap();
+    
+    let encrypted = StreamOptions { split_size: 100_000, password: Some("secret".to_string()), ..Default::default() };
+    let non_solid = StreamOptions { split_size: 100_000, solid: false, ..Default::default() };
+    for opts in [&encrypted, &non_solid] {
+        let result = sz.create_archive_streaming(&archive_path, &[&test_file], CompressionLevel::Normal, Some(opts), None);
+        assert!(
+            matches!(result, Err(seven_zip::Error::UnsupportedOptionCombination { .. })),
+            "{:?}",
+            result
+        );
+    }
+    
+    let result = sz.create_archive_true_streaming(&archive_path, &[&test_file], CompressionLevel::Normal, Some(&non_solid), None);
+    assert!(matches!(result, Err(seven_zip::Error::UnsupportedOptionCombination { .. })));
+    
+    // Nothing was written
+    assert!(!archive_path.exists());
+    assert!(!temp.path().join("split.7z.001").exists());
+}
+
+#[test]
+fn test_count_entries_matches_list() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("sub/deeper")).unwrap();
+    fs::create_dir_all(source.join("empty")).unwrap();
+    create_test_file(&source, "a.txt", "a");
+    create_test_file(&source, "sub/b.txt", "b");
+    create_test_file(&source, "sub/deeper/c.txt", "c");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("counts.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    let dirs = entries.iter().filter(|e| e.is_directory).count();
+    assert!(dirs > 0);
+    assert_eq!(sz.count_entries(&archive_path, None).unwrap(), (entries.len() - dirs, dirs));
+    assert_eq!(sz.count_entries(&archive_path, None).unwrap().0, 3);
+}
+
+#[test]
+fn test_temp_file_factory() {
+    use seven_zip::StreamOptions;
+    use std::sync::{Arc, Mutex};
+    
+    let temp = TempDir::new().unwrap();
+    let scratch = TempDir::new().unwrap();
+    let input = create_test_file(temp.path(), "input.txt", &"scratch data ".repeat(4096));
+    let archive_path = temp.path().join("factory.7z");
+    
+    let created = Arc::new(Mutex::new(Vec::new()));
+    let (dir, log) = (scratch.path().to_path_buf(), created.clone());
+    let opts = StreamOptions::default().with_temp_file_factory(move || {
+        let path = dir.join(format!("scratch-{}.bin", log.lock().unwrap().len()));
+        let file = fs::File::create(&path)?;
+        log.lock().unwrap().push(path.clone());
+        Ok((path, file))
+    });
+    
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive_true_streaming(&archive_path, &[&input], CompressionLevel::Fast, Some(&opts), None).unwrap();
+    
+    assert_eq!(created.lock().unwrap().len(), 1, "Factory should create the scratch file");
+    assert_eq!(fs::read_dir(scratch.path()).unwrap().count(), 0, "Scratch files should be removed");
+    assert!(archive_path.exists());
+    
+    // A failing factory aborts the operation with its error
+    let failing = StreamOptions::default()
+        .with_temp_file_factory(|| Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "sandboxed")));
+    let result = sz.create_archive_true_streaming(temp.path().join("fail.7z"), &[&input], CompressionLevel::Fast, Some(&failing), None);
+    assert!(matches!(result, Err(seven_zip::Error::Io(_))), "{:?}", result);
+}
+
+#[test]
+fn test_extract_dry_run() {
+    use seven_zip::ChangeAction;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    create_test_file(&source, "same.txt", "unchanged");
+    create_test_file(&source, "changed.txt", "new content");
+    create_test_file(&source, "resized.txt", "longer new content");
+    create_test_file(&source, "fresh.txt", "brand new");
+    create_test_file(&source, "blocked", "a file");
+    create_test_file(&source, "sub/inner.txt", "inner");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("update.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    // Partially populated target
+    let target = temp.path().join("target");
+    fs::create_dir_all(target.join("sub")).unwrap();
+    fs::create_dir_all(target.join("blocked")).unwrap();
+    create_test_file(&target, "same.txt", "unchanged");
+    create_test_file(&target, "changed.txt", "old content");
+    create_test_file(&target, "resized.txt", "short");
+    
+    let preview = sz.extract_dry_run(&archive_path, &target, None).unwrap();
+    let action = |name: &str| preview.iter().find(|c| c.name == name).unwrap_or_else(|| panic!("{}", name)).action;
+    assert_eq!(action("same.txt"), ChangeAction::Identical);
+    assert_eq!(action("changed.txt"), ChangeAction::Overwrite);
+    assert_eq!(action("resized.txt"), ChangeAction::Overwrite);
+    assert_eq!(action("fresh.txt"), ChangeAction::New);
+    assert_eq!(action("blocked"), ChangeAction::Skip);
+    assert_eq!(action("sub"), ChangeAction::Identical);
+    assert_eq!(action("sub/inner.txt"), ChangeAction::New);
+    
+    // Nothing was written
+    assert!(!target.join("fresh.txt").exists());
+    assert_eq!(fs::read_to_string(target.join("changed.txt")).unwrap(), "old content");
+}
+
+#[test]
+fn test_custom_compression_level() {
+    let temp = TempDir::new().unwrap();
+    let input = create_test_file(temp.path(), "input.txt", &"custom level data ".repeat(2000));
+    let sz = SevenZip::new().unwrap();
+    
+    let named = temp.path().join("named.7z");
+    let custom = temp.path().join("custom.7z");
+    sz.create_archive(&named, &[&input], CompressionLevel::Normal, None).unwrap();
+    sz.create_archive(&custom, &[&input], CompressionLevel::Custom(5), None).unwrap();
+    assert_eq!(fs::read(&named).unwrap(), fs::read(&custom).unwrap());
+    
+    // A level between the named ones round-trips
+    let between = temp.path().join("between.7z");
+    sz.create_archive(&between, &[&input], CompressionLevel::Custom(4), None).unwrap();
+    let out = temp.path().join("out");
+    sz.extract(&between, &out).unwrap();
+    assert_eq!(fs::read(out.join("input.txt")).unwrap(), fs::read(&input).unwrap());
+    
+    let result = sz.create_archive(temp.path().join("bad.7z"), &[&input], CompressionLevel::Custom(10), None);
+    assert!(matches!(result, Err(seven_zip::Error::InvalidParameter(_))));
+}
+
+#[test]
+fn test_copy_entries() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("case");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "a.txt", &"first notes ".repeat(200));
+    create_test_file(&source, "b.txt", &"second notes ".repeat(200));
+    create_test_file(&source, "c.jpg", &"photo one ".repeat(300));
+    create_test_file(&source, "d.jpg", &"photo two ".repeat(300));
+    
+    // One folder per codec: the text files share one, the photos the other
+    let sz = SevenZip::new().unwrap();
+    let src = temp.path().join("case.7z");
+    let opts = CompressOptions::default().with_method_for_extension("jpg", CompressionMethod::Copy);
+    sz.create_archive(&src, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    // A whole folder moves over as packed bytes
+    let photos = temp.path().join("photos.7z");
+    sz.copy_entries(&src, &photos, &["c.jpg", "d.jpg"], None).unwrap();
+    let mut names: Vec<String> = sz.list(&photos, None).unwrap().into_iter().map(|e| e.name).collect();
+    names.sort();
+    assert_eq!(names, ["c.jpg", "d.jpg"]);
+    let packed = |path: &std::path::Path, folder: &seven_zip::FolderInfo| {
+        let bytes = fs::read(path).unwrap();
+        bytes[folder.packed_offset as usize..(folder.packed_offset + folder.packed_size) as usize].to_vec()
+    };
+    let src_folder = sz.folder_layout(&src, None).unwrap().into_iter().find(|f| f.codec == "Copy").unwrap();
+    let dst_folder = sz.folder_layout(&photos, None).unwrap().remove(0);
+    assert_eq!(packed(&photos, &dst_folder), packed(&src, &src_folder));
+    sz.test_archive(&photos, None).unwrap();
+    let out = temp.path().join("photos");
+    sz.extract(&photos, &out).unwrap();
+    assert_eq!(fs::read_to_string(out.join("c.jpg")).unwrap(), "photo one ".repeat(300));
+    assert_eq!(fs::read_to_string(out.join("d.jpg")).unwrap(), "photo two ".repeat(300));
+    
+    // Half of a shared folder is recompressed instead
+    let mixed = temp.path().join("mixed.7z");
+    sz.copy_entries(&src, &mixed, &["a.txt", "c.jpg"], None).unwrap();
+    assert_eq!(sz.list(&mixed, None).unwrap().len(), 2);
+    let out = temp.path().join("mixed");
+    sz.extract(&mixed, &out).unwrap();
+    assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "first notes ".repeat(200));
+    assert_eq!(fs::read_to_string(out.join("c.jpg")).unwrap(), "photo one ".repeat(300));
+    assert!(!out.join("b.txt").exists());
+    
+    let result = sz.copy_entries(&src, &mixed, &["missing.txt"], None);
+    assert!(matches!(result, Err(seven_zip::Error::InvalidParameter(_))));
+    
+    // Recompressed entries of an encrypted folder stay encrypted
+    let encrypted_src = temp.path().join("encrypted.7z");
+    let opts = CompressOptions::default().with_password("case-password".to_string()).with_kdf_iterations(1 << 10);
+    sz.create_archive(&encrypted_src, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    let notes = temp.path().join("notes.7z");
+    sz.copy_entries(&encrypted_src, &notes, &["a.txt"], Some("case-password")).unwrap();
+    let info = sz.encryption_info(&notes, Some("case-password")).unwrap().expect("copy should be encrypted");
+    assert_eq!(info.kdf_iterations, 1 << 10);
+    assert!(!fs::read(&notes).unwrap().windows(12).any(|w| w == b"first notes "));
+    assert!(sz.extract(&notes, temp.path().join("notes_no_pw")).is_err());
+    let out = temp.path().join("notes");
+    sz.extract_with_password(&notes, &out, Some("case-password"), None).unwrap();
+    assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "first notes ".repeat(200));
+}
+
+#[test]
+#[cfg(windows)]
+fn test_extract_sanitizes_reserved_names() {
+    use seven_zip::ExtractOptions;
+    use std::sync::{Arc, Mutex};
+    
+    // Only a verbatim path can create a file named CON
+    let temp = TempDir::new().unwrap();
+    let source = std::path::PathBuf::from(format!(r"\\?\{}", temp.path().join("src").display()));
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("CON"), "console notes").unwrap();
+    let plain = create_test_file(&source, "plain.txt", "plain");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("reserved.7z");
+    sz.create_archive(&archive_path, &[&source.join("CON"), &plain], CompressionLevel::Normal, None).unwrap();
+    
+    let out = temp.path().join("out");
+    let result = sz.extract_with_options(&archive_path, &out, &ExtractOptions::default(), None);
+    assert!(matches!(result, Err(seven_zip::Error::ReservedName { ref name }) if name == "CON"), "{:?}", result);
+    
+    let renames = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&renames);
+    let opts = ExtractOptions::default()
+        .with_sanitize_names(true)
+        .with_on_rename(move |name, path| seen.lock().unwrap().push((name.to_string(), path.to_path_buf())));
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reported = Arc::clone(&reports);
+    let progress: seven_zip::ProgressCallback = Box::new(move |done, total| reported.lock().unwrap().push((done, total)));
+    sz.extract_with_options(&archive_path, &out, &opts, Some(progress)).unwrap();
+    assert_eq!(reports.lock().unwrap().last(), Some(&(2, 2)));
+    assert_eq!(fs::read_to_string(out.join("CON_")).unwrap(), "console notes");
+    assert_eq!(fs::read_to_string(out.join("plain.txt")).unwrap(), "plain");
+    assert_eq!(*renames.lock().unwrap(), [("CON".to_string(), out.join("CON_"))]);
+}
+
+#[test]
+fn test_volume_ranges() {
+    use seven_zip::{StreamOptions, VolumeNaming};
+    
+    let temp = TempDir::new().unwrap();
+    let data: Vec<u8> = (0..250_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    let source = temp.path().join("data.bin");
+    fs::write(&source, &data).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    for (naming, first) in [(VolumeNaming::Numeric, "split.7z.001"), (VolumeNaming::ZipStyle, "split.z01")] {
+        let dir = temp.path().join(format!("{:?}", naming));
+        fs::create_dir(&dir).unwrap();
+        let opts = StreamOptions { split_size: 100_000, volume_naming: naming, ..Default::default() };
+        sz.create_archive_streaming(dir.join("split.7z"), &[&source], CompressionLevel::Store, Some(&opts), None)
+            .unwrap();
+        
+        let ranges = sz.volume_ranges(dir.join(first)).unwrap();
+        assert_eq!(ranges.len(), 3, "{:?}: {:?}", naming, ranges);
+        let mut end = 0;
+        for (volume, range) in &ranges {
+            assert_eq!(range.start, end, "{:?}: gap or overlap before {}", naming, volume.display());
+            assert_eq!(range.end - range.start, fs::metadata(volume).unwrap().len());
+            end = range.end;
+        }
+        let total: u64 = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().metadata().unwrap().len()).sum();
+        assert_eq!(end, total);
+        
+        // The ranges reassemble the archive
+        let volumes: Vec<_> = ranges.into_iter().map(|(volume, _)| volume).collect();
+        let output = dir.join("out");
+        sz.extract_from_volumes(&volumes, &output, None).unwrap();
+        assert_eq!(fs::read(output.join("data.bin")).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_resolve_auto_dict_is_deterministic() {
+    let opts = CompressOptions::default();
+    assert_eq!(opts.resolve_auto_dict(CompressionLevel::Normal, 1 << 30), 8 << 20);
+    assert_eq!(opts.resolve_auto_dict(CompressionLevel::Ultra, 1 << 30), 64 << 20);
+    assert_eq!(opts.resolve_auto_dict(CompressionLevel::Fast, 300_000), 300_000);
+    assert_eq!(opts.resolve_auto_dict(CompressionLevel::Normal, 10), 4096);
+    assert_eq!(opts.resolve_auto_dict(CompressionLevel::Custom(4), 1 << 30), 8 << 20);
+    assert_eq!(
+        opts.resolve_auto_dict(CompressionLevel::Maximum, 5_000_000),
+        opts.resolve_auto_dict(CompressionLevel::Maximum, 5_000_000)
+    );
+    let pinned = CompressOptions { dict_size: 1 << 20, ..Default::default() };
+    assert_eq!(pinned.resolve_auto_dict(CompressionLevel::Ultra, 10), 1 << 20);
+    
+    // The encoder runs with the resolved value, and the report says so
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir(&source).unwrap();
+    create_test_file(&source, "a.txt", &"dictionary ".repeat(20_000));
+    create_test_file(&source, "b.txt", &"resolution ".repeat(10_000));
+    let sz = SevenZip::new().unwrap();
+    for level in [CompressionLevel::Fast, CompressionLevel::Normal] {
+        let archive_path = temp.path().join("auto.7z");
+        let report = sz.create_archive_with_report(&archive_path, &[&source], level, None).unwrap();
+        assert_eq!(report.dict_size, opts.resolve_auto_dict(level, 330_000));
+        assert_eq!(report.dict_size, 330_000);
+    }
+    let report = sz.create_archive_with_report(temp.path().join("pinned.7z"), &[&source], CompressionLevel::Normal, Some(&pinned)).unwrap();
+    assert_eq!(report.dict_size, 1 << 20);
+}
+
+#[test]
+fn test_password_from_file() {
+    use seven_zip::crypto::{decrypt_blob, encrypt_blob};
+    use seven_zip::Password;
+    
+    let temp = TempDir::new().unwrap();
+    let password_file = temp.path().join("archive.pass");
+    fs::write(&password_file, "s3cret pass\r\n").unwrap();
+    let password = Password::from_file(&password_file).unwrap();
+    assert_eq!(password.as_str(), "s3cret pass");
+    assert_eq!(format!("{:?}", password), "Password(..)");
+    
+    // Derefs to &str wherever one is expected, e.g. an encrypting call
+    let blob = encrypt_blob(b"sealed", &password).unwrap();
+    assert_eq!(decrypt_blob(&blob, "s3cret pass").unwrap(), b"sealed");
+    assert_eq!(decrypt_blob(&blob, "s3cret pass\r\n"), Err(seven_zip::Error::WrongPassword));
+    
+    fs::write(&password_file, "\n").unwrap();
+    assert!(matches!(Password::from_file(&password_file), Err(seven_zip::Error::InvalidParameter(_))));
+}
+
+#[test]
+fn test_password_from_env() {
+    use seven_zip::Password;
+    
+    // Setting variables in the multithreaded test harness is unsound, so the
+    // checks run in a child process that starts with them set
+    const VAR: &str = "SEVEN_ZIP_TEST_PASSWORD";
+    const UNSET: &str = "SEVEN_ZIP_TEST_UNSET_PASSWORD";
+    if std::env::var_os(VAR).is_some() {
+        assert_eq!(Password::from_env(VAR).unwrap().as_str(), "from env");
+        assert!(matches!(Password::from_env(UNSET), Err(seven_zip::Error::InvalidParameter(_))));
+        return;
+    }
+    
+    let child = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_password_from_env", "--test-threads=1"])
+        .env(VAR, "from env\n")
+        .env_remove(UNSET)
+        .output()
+        .unwrap();
+    let report = String::from_utf8_lossy(&child.stdout);
+    assert!(child.status.success() && report.contains("1 passed"), "{}", report);
+}
+
+#[test]
+fn test_checksum_entries_matches_source_crcs() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("corpus");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    let contents = [("a.txt", "alpha ".repeat(1000)), ("sub/b.txt", "beta ".repeat(700)), ("c.bin", "gamma".to_string())];
+    for (name, content) in &contents {
+        fs::write(source.join(name), content).unwrap();
+    }
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("corpus.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let checksums = sz.checksum_entries(&archive_path, None).unwrap();
+    assert_eq!(checksums.len(), sz.list(&archive_path, None).unwrap().len());
+    for (name, content) in &contents {
+        let (_, crc) = checksums.iter().find(|(n, _)| n == name).unwrap();
+        assert_eq!(*crc, Some(crc32fast::hash(content.as_bytes())), "{}", name);
+    }
+    let (_, dir_crc) = checksums.iter().find(|(n, _)| n == "sub").unwrap();
+    assert_eq!(*dir_crc, None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_detect_hardlinks_stores_data_once() {
+    use std::os::unix::fs::MetadataExt;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("evidence");
+    fs::create_dir_all(&source).unwrap();
+    // Pseudo-random, so the two names can't share the data through LZMA matches
+    let mut state = 0x2545_f491_u32;
+    let data: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    fs::write(source.join("image.raw"), &data).unwrap();
+    fs::hard_link(source.join("image.raw"), source.join("image-copy.raw")).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("linked.7z");
+    let opts = CompressOptions::default().with_detect_hardlinks(true);
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let entries = sz.list(&archive_path, None).unwrap();
+    let links: Vec<_> = entries.iter().filter(|e| e.is_hardlink()).collect();
+    assert_eq!(links.len(), 1);
+    let stored: u64 = entries.iter().map(|e| e.size).sum();
+    assert!(stored < data.len() as u64 + 64, "data stored more than once: {} bytes", stored);
+    assert!(fs::metadata(&archive_path).unwrap().len() < data.len() as u64 * 3 / 2);
+    
+    let output = temp.path().join("linked");
+    let opts = ExtractOptions::default().with_restore_hardlinks(true);
+    sz.extract_with_options(&archive_path, &output, &opts, None).unwrap();
+    let first = fs::metadata(output.join("image.raw")).unwrap();
+    let second = fs::metadata(output.join("image-copy.raw")).unwrap();
+    assert_eq!(first.ino(), second.ino());
+    assert_eq!(fs::read(output.join(&links[0].name)).unwrap(), data);
+    
+    // Without restore_hardlinks, the link becomes a separate copy
+    let output = temp.path().join("copied");
+    sz.extract_with_options(&archive_path, &output, &ExtractOptions::default(), None).unwrap();
+    let first = fs::metadata(output.join("image.raw")).unwrap();
+    let second = fs::metadata(output.join("image-copy.raw")).unwrap();
+    assert_ne!(first.ino(), second.ino());
+    assert_eq!(fs::read(output.join(&links[0].name)).unwrap(), data);
+    
+    // Every read path yields the target's content for the link
+    let single = temp.path().join("single.raw");
+    sz.extract_entry_as(&archive_path, &links[0].name, &single, None).unwrap();
+    assert_eq!(fs::read(&single).unwrap(), data);
+    let streamed = temp.path().join("streamed");
+    sz.extract_streaming(&archive_path, &streamed, None, None).unwrap();
+    assert_eq!(fs::read(streamed.join(&links[0].name)).unwrap(), data);
+    let mut readers = sz.entries_with_readers(&archive_path, None).unwrap();
+    while let Some(item) = readers.next() {
+        let (entry, mut reader) = item.unwrap();
+        let mut content = Vec::new();
+        std::io::Read::read_to_end(&mut reader, &mut content).unwrap();
+        assert_eq!(content, data, "{}", entry.name);
+    }
+    
+    // The attribute bit alone, without the tag in the data, is no hard link
+    let plain = temp.path().join("plain.txt");
+    fs::write(&plain, "just a file").unwrap();
+    let plain_archive = temp.path().join("plain.7z");
+    sz.create_archive(&plain_archive, &[&plain], CompressionLevel::Normal, None).unwrap();
+    let attributes = sz.list(&plain_archive, None).unwrap()[0].attributes;
+    patch_header(&plain_archive, &attributes.to_le_bytes(), &(attributes | 0x08).to_le_bytes());
+    assert!(sz.list(&plain_archive, None).unwrap()[0].is_hardlink());
+    let output = temp.path().join("plain");
+    sz.extract_with_options(&plain_archive, &output, &ExtractOptions::default().with_restore_hardlinks(true), None)
+        .unwrap();
+    assert_eq!(fs::read_to_string(output.join("plain.txt")).unwrap(), "just a file");
+}
+
+#[test]
+fn test_directory_and_empty_file_archive_paths() {
+    let temp = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+    let output = temp.path().join("out");
+    
+    let dir = temp.path().join("backup.7z");
+    fs::create_dir(&dir).unwrap();
+    let not_a_file = seven_zip::Error::NotAFile { path: dir.display().to_string() };
+    assert_eq!(sz.list(&dir, None).unwrap_err(), not_a_file);
+    assert_eq!(sz.extract(&dir, &output).unwrap_err(), not_a_file);
+    assert_eq!(sz.test_archive(&dir, None).unwrap_err(), not_a_file);
+    
+    let empty = create_test_file(temp.path(), "download.7z", "");
+    let empty_archive = seven_zip::Error::EmptyArchive { path: empty.display().to_string() };
+    assert_eq!(sz.list(&empty, None).unwrap_err(), empty_archive);
+    assert_eq!(sz.extract(&empty, &output).unwrap_err(), empty_archive);
+    assert_eq!(sz.list_full(&empty, None).unwrap_err(), empty_archive);
+    assert!(!output.exists(), "nothing is created for a bad archive path");
+}
+
+#[test]
+fn test_compress_channel() {
+    let temp = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("piped.7z");
+    
+    let (sender, worker) = sz.compress_channel(&archive_path, CompressionLevel::Fast, None);
+    let producer = std::thread::spawn(move || {
+        for (name, data) in [
+            ("readme.txt", b"sent first".to_vec()),
+            ("logs/app.log", b"log line\n".repeat(500)),
+            ("logs/empty.log", Vec::new()),
+        ] {
+            sender.send((name.to_string(), data)).unwrap();
+        }
+        // Dropping the sender finalizes the archive
+    });
+    producer.join().unwrap();
+    worker.join().unwrap().unwrap();
+    
+    sz.test_archive(&archive_path, None).unwrap();
+    let mut names: Vec<String> =
+        sz.list(&archive_path, None).unwrap().into_iter().filter(|e| !e.is_directory).map(|e| e.name).collect();
+    names.sort();
+    assert_eq!(names, ["logs/app.log", "logs/empty.log", "readme.txt"]);
+    let output = temp.path().join("out");
+    sz.extract(&archive_path, &output).unwrap();
+    assert_eq!(fs::read(output.join("readme.txt")).unwrap(), b"sent first");
+    assert_eq!(fs::read(output.join("logs/app.log")).unwrap(), b"log line\n".repeat(500));
+    assert_eq!(fs::read(output.join("logs/empty.log")).unwrap(), b"");
+    
+    // A bad name stops the worker; the producer's next send fails
+    let (sender, worker) = sz.compress_channel(temp.path().join("bad.7z"), CompressionLevel::Fast, None);
+    sender.send(("../escape.txt".to_string(), b"x".to_vec())).unwrap();
+    let err = worker.join().unwrap().unwrap_err();
+    assert!(matches!(err, seven_zip::Error::InvalidParameter(_)), "{:?}", err);
+    assert!(sender.send(("later.txt".to_string(), Vec::new())).is_err());
+    assert!(!temp.path().join("bad.7z").exists());
+}
+
+#[cfg(feature = "signing")]
+#[test]
+fn test_sign_and_verify_archive() {
+    use seven_zip::{sign_archive, verify_signature, SIGNATURE_LENGTH};
+    
+    let temp = TempDir::new().unwrap();
+    let source = create_test_file(temp.path(), "release.txt", "signed release notes");
+    let archive_path = temp.path().join("release.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    // RFC 8032 test vector 1 key pair
+    let seed = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+        0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+    ];
+    let public_key = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+    ];
+    let signature = sign_archive(&archive_path, &seed).unwrap();
+    assert_eq!(signature.len(), SIGNATURE_LENGTH);
+    assert!(verify_signature(&archive_path, &signature, &public_key).unwrap());
+    // The 64-byte seed + public key form signs the same way
+    let keypair = [seed, public_key].concat();
+    assert_eq!(sign_archive(&archive_path, &keypair).unwrap(), signature);
+    
+    // One flipped byte in the archive or the signature fails verification
+    let mut bytes = fs::read(&archive_path).unwrap();
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0x01;
+    let tampered = temp.path().join("tampered.7z");
+    fs::write(&tampered, &bytes).unwrap();
+    assert!(!verify_signature(&tampered, &signature, &public_key).unwrap());
+    let mut forged = signature.clone();
+    forged[0] ^= 0x01;
+    assert!(!verify_signature(&archive_path, &forged, &public_key).unwrap());
+    
+    assert!(matches!(
+        verify_signature(&archive_path, &signature[..10], &public_key),
+        Err(seven_zip::Error::InvalidParameter(_))
+    ));
+    assert!(matches!(sign_archive(&archive_path, &seed[..16]), Err(seven_zip::Error::InvalidParameter(_))));
+}
+
+#[test]
+fn test_compress_file_sized_round_trip_and_truncation() {
+    let temp = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+    let data: Vec<u8> = (0..300_000u32).flat_map(|i| (i % 251).to_le_bytes()).collect();
+    let input = temp.path().join("disk.img");
+    fs::write(&input, &data).unwrap();
+    
+    let packed = temp.path().join("disk.img.lzma2");
+    sz.compress_file_sized(&input, &packed, CompressionLevel::Fast).unwrap();
+    let bytes = fs::read(&packed).unwrap();
+    assert_eq!(&bytes[..4], b"7zL2");
+    assert_eq!(u64::from_le_bytes(bytes[5..13].try_into().unwrap()), data.len() as u64);
+    
+    let restored = temp.path().join("restored.img");
+    sz.decompress_file_sized(&packed, &restored).unwrap();
+    assert_eq!(fs::read(&restored).unwrap(), data);
+    
+    // Cut off the end of the stream
+    let truncated = temp.path().join("truncated.lzma2");
+    fs::write(&truncated, &bytes[..bytes.len() * 2 / 3]).unwrap();
+    let output = temp.path().join("partial.img");
+    let err = sz.decompress_file_sized(&truncated, &output).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::InvalidArchive(_)), "{:?}", err);
+    assert!(!output.exists(), "partial output is removed");
+    
+    // A header promising more data than the stream holds
+    let mut inflated = bytes.clone();
+    inflated[5..13].copy_from_slice(&(data.len() as u64 + 1).to_le_bytes());
+    fs::write(&truncated, &inflated).unwrap();
+    let err = sz.decompress_file_sized(&truncated, &output).unwrap_err();
+    assert!(err.to_string().contains(&format!("{} of {} bytes", data.len(), data.len() + 1)), "{}", err);
+    
+    // Plain compress_file output has no size header
+    let plain = temp.path().join("plain.lzma2");
+    sz.compress_file(&input, &plain, CompressionLevel::Fast).unwrap();
+    assert!(matches!(sz.decompress_file_sized(&plain, &output), Err(seven_zip::Error::InvalidArchive(_))));
+}
+
+#[test]
+fn test_force_overwrite_readonly_stays_in_output_dir() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    fs::create_dir_all(source.join("ab")).unwrap();
+    create_test_file(&source.join("ab"), "victim.txt", "from the archive");
+    let archive_path = temp.path().join("escape.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    patch_entry_name(&archive_path, "ab/victim.txt", "../victim.txt");
+    
+    // A read-only file where the escaping entry points
+    let victim = create_test_file(temp.path(), "victim.txt", "outside");
+    let mut permissions = fs::metadata(&victim).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&victim, permissions).unwrap();
+    
+    let opts = ExtractOptions::default().with_force_overwrite_readonly(true);
+    let err = sz.extract_with_options(&archive_path, temp.path().join("out"), &opts, None).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::InvalidArchive(_)), "{:?}", err);
+    assert!(fs::metadata(&victim).unwrap().permissions().readonly());
+    assert_eq!(fs::read_to_string(&victim).unwrap(), "outside");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_restored_mtimes_stay_in_output_dir() {
+    let temp = TempDir::new().unwrap();
+    let victim = create_test_file(temp.path(), "victim.txt", "outside");
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&victim, old).unwrap();
+    
+    // An entry named with the victim's absolute path, extracted under the output directory
+    let absolute = victim.to_str().unwrap().to_string();
+    let source = temp.path().join("source");
+    fs::create_dir_all(&source).unwrap();
+    let placeholder = "x".repeat(absolute.len());
+    create_test_file(&source, &placeholder, "from the archive");
+    let archive_path = temp.path().join("absolute.7z");
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    patch_entry_name(&archive_path, &placeholder, &absolute);
+    
+    let output = temp.path().join("out");
+    let since = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1);
+    assert_eq!(sz.extract_newer_than(&archive_path, &output, since, None).unwrap(), 1);
+    assert_eq!(fs::read_to_string(output.join(absolute.trim_start_matches('/'))).unwrap(), "from the archive");
+    assert_eq!(filetime::FileTime::from_last_modification_time(&fs::metadata(&victim).unwrap()), old);
+}
+
+#[test]
+fn test_time_budget_downgrades_level() {
+    use rand::RngCore;
+    use std::time::{Duration, Instant};
+    
+    let temp = TempDir::new().unwrap();
+    // Under 1 MiB, so the C side's own incompressibility check stays out of the way
+    let mut data = vec![0u8; 900 * 1024];
+    rand::thread_rng().fill_bytes(&mut data);
+    let input = temp.path().join("noise.bin");
+    fs::write(&input, &data).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("budget.7z");
+    let budget = Duration::from_millis(20);
+    let opts = CompressOptions::default().with_time_budget(budget);
+    
+    let started = Instant::now();
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Ultra, Some(&opts)).unwrap();
+    let elapsed = started.elapsed();
+    
+    // Best effort: the final Store attempt may overrun, but only by a copy
+    assert!(elapsed < budget + Duration::from_secs(2), "took {:?}", elapsed);
+    
+    sz.test_archive(&archive_path, None).unwrap();
+    let out = temp.path().join("out");
+    sz.extract(&archive_path, &out).unwrap();
+    assert_eq!(fs::read(out.join("noise.bin")).unwrap(), data);
+}
+
+#[test]
+fn test_archive_info_reports_host_os() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    create_test_file(&source, "a.txt", "alpha");
+    create_test_file(&source.join("sub"), "b.txt", "bravo");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("host.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let info = sz.archive_info(&archive_path, None).unwrap();
+    assert_eq!(info.entries, 3);
+    assert_eq!(info.unpacked_size, 10);
+    assert_eq!(info.folders, 1);
+    
+    let expected = if cfg!(windows) { seven_zip::HostOs::Windows } else { seven_zip::HostOs::Unix };
+    assert_eq!(info.host_os, Some(expected));
+    
+    #[cfg(unix)]
+    for entry in sz.list(&archive_path, None).unwrap() {
+        // The Unix mode sits in the high 16 bits
+        let mode = entry.attributes >> 16;
+        assert_eq!(mode & 0o170000 == 0o040000, entry.is_directory, "{}", entry.name);
+    }
+}
+
+#[test]
+fn test_fsync_on_finish() {
+    use seven_zip::StreamOptions;
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("evidence.bin");
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    fs::write(&source, &data).unwrap();
+    let sz = SevenZip::new().unwrap();
+    
+    let single = temp.path().join("single.7z");
+    let opts = StreamOptions { fsync_on_finish: true, ..Default::default() };
+    sz.create_archive_streaming(&single, &[&source], CompressionLevel::Normal, Some(&opts), None).unwrap();
+    sz.test_archive(&single, None).unwrap();
+    
+    // Every volume of a split archive is synced too
+    let split_dir = temp.path().join("split");
+    fs::create_dir(&split_dir).unwrap();
+    let opts = StreamOptions { split_size: 100_000, fsync_on_finish: true, ..Default::default() };
+    sz.create_archive_streaming(split_dir.join("split.7z"), &[&source], CompressionLevel::Store, Some(&opts), None)
+        .unwrap();
+    let output = temp.path().join("out");
+    sz.extract_streaming(split_dir.join("split.7z.001"), &output, None, None).unwrap();
+    assert_eq!(fs::read(output.join("evidence.bin")).unwrap(), data);
+}
+
+#[test]
+fn test_codec_properties() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "log.txt", &"2024-01-01 INFO request served\n".repeat(2000));
+    let sz = SevenZip::new().unwrap();
+    
+    let archive_path = temp.path().join("tuned.7z");
+    let opts = CompressOptions::default()
+        .with_codec_property("fb", "273")
+        .with_codec_property("mf", "bt4");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Maximum, Some(&opts)).unwrap();
+    
+    let output = temp.path().join("out");
+    sz.extract(&archive_path, &output).unwrap();
+    assert_eq!(
+        fs::read(output.join("log.txt")).unwrap(),
+        fs::read(source.join("log.txt")).unwrap()
+    );
+    
+    let opts = CompressOptions::default().with_codec_property("turbo", "1");
+    match sz.create_archive(temp.path().join("bad.7z"), &[&source], CompressionLevel::Normal, Some(&opts)) {
+        Err(seven_zip::Error::InvalidParameter(message)) => assert!(message.contains("turbo"), "{}", message),
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+    
+    let opts = CompressOptions::default().with_codec_property("fb", "9000");
+    match sz.create_archive(temp.path().join("bad.7z"), &[&source], CompressionLevel::Normal, Some(&opts)) {
+        Err(seven_zip::Error::InvalidParameter(message)) => assert!(message.contains("fb=9000"), "{}", message),
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_list_json_round_trip() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    create_test_file(&source, "a.txt", "alpha");
+    create_test_file(&source.join("sub"), "b.txt", "bravo");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("listed.7z");
+    let opts = CompressOptions::default().with_password("hunter2".to_string());
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let json = sz.list_json(&archive_path, Some("hunter2")).unwrap();
+    assert!(!json.contains("hunter2"));
+    let parsed: Vec<seven_zip::ArchiveEntry> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, sz.list(&archive_path, Some("hunter2")).unwrap());
+}
+
+#[test]
+fn test_detailed_progress_eta_decreases() {
+    use seven_zip::ProgressTracker;
+    use std::time::{Duration, Instant};
+    
+    let start = Instant::now();
+    let mut tracker = ProgressTracker::starting_at(start);
+    let total = 100_000_000;
+    
+    // 10 MB per second, steadily
+    let mut last_eta = None;
+    for second in 1..=9u64 {
+        let p = tracker.update_at(second * 10_000_000, total, start + Duration::from_secs(second));
+        assert!((p.instantaneous_mbps - 10.0).abs() < 1e-6, "{:?}", p);
+        assert!((p.average_mbps - 10.0).abs() < 1e-6, "{:?}", p);
+        let eta = p.eta.unwrap();
+        assert!((eta.as_secs_f64() - (10 - second) as f64).abs() < 1e-6, "{:?}", p);
+        if let Some(last) = last_eta {
+            assert!(eta < last);
+        }
+        last_eta = Some(eta);
+    }
+    
+    // A burst shows up in the instantaneous speed before the average
+    let p = tracker.update_at(total, total, start + Duration::from_millis(9_500));
+    assert!((p.instantaneous_mbps - 20.0).abs() < 1e-6, "{:?}", p);
+    assert!(p.average_mbps > 10.0 && p.average_mbps < 20.0);
+    assert_eq!(p.eta, Some(Duration::ZERO));
+    
+    // Unknown total: no ETA
+    let mut tracker = ProgressTracker::starting_at(start);
+    assert_eq!(tracker.update_at(5, 0, start + Duration::from_secs(1)).eta, None);
+}
+
+#[test]
+fn test_extract_newer_than() {
+    use std::time::{Duration, UNIX_EPOCH};
+    
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    let cutoff = 1_650_000_000;
+    for (name, mtime) in [("old.txt", cutoff - 86_400), ("edge.txt", cutoff), ("new.txt", cutoff + 1), ("newer.txt", cutoff + 86_400)] {
+        let path = create_test_file(&source, name, name);
+        filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(mtime as i64, 0)).unwrap();
+    }
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("incremental.7z");
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Normal, None).unwrap();
+    
+    let output = temp.path().join("restore");
+    let since = UNIX_EPOCH + Duration::from_secs(cutoff);
+    let count = sz.extract_newer_than(&archive_path, &output, since, None).unwrap();
+    assert_eq!(count, 2);
+    
+    let mut restored: Vec<String> = fs::read_dir(&output)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    restored.sort();
+    assert_eq!(restored, vec!["new.txt", "newer.txt"]);
+    assert_eq!(fs::read_to_string(output.join("newer.txt")).unwrap(), "newer.txt");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_into_readonly_dir() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "a.txt", "alpha");
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("a.7z");
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, None).unwrap();
+    
+    let locked = temp.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+    let result = sz.extract(&archive_path, &locked);
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(
+        result,
+        Err(seven_zip::Error::OutputNotWritable { path: locked.display().to_string() })
+    );
+    assert!(!locked.join("a.txt").exists());
+    
+    // Without creation, a missing directory fails the same way
+    let missing = temp.path().join("missing");
+    let opts = ExtractOptions::default().with_create_output_dir(false);
+    assert!(matches!(
+        sz.extract_with_options(&archive_path, &missing, &opts, None),
+        Err(seven_zip::Error::OutputNotWritable { .. })
+    ));
+    assert!(!missing.exists());
+    
+    sz.extract(&archive_path, &missing).unwrap();
+    assert_eq!(fs::read_to_string(missing.join("a.txt")).unwrap(), "alpha");
+}
+
+#[test]
+fn test_estimate_compressed_size() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir(&input).unwrap();
+    let text = "The quick brown fox jumps over the lazy dog.\n".repeat(20_000);
+    create_test_file(&input, "a.txt", &text);
+    create_test_file(&input, "b.txt", &text);
+    let total = 2 * text.len() as u64;
+    
+    let sz = SevenZip::new().unwrap();
+    let estimate = sz
+        .estimate_compressed_size(&[&input], CompressionLevel::Normal, CompressionMethod::Lzma2)
+        .unwrap();
+    assert!(estimate > 0);
+    assert!(estimate < total / 10, "estimate {} for {} input bytes", estimate, total);
+    
+    let copy = sz
+        .estimate_compressed_size(&[&input], CompressionLevel::Normal, CompressionMethod::Copy)
+        .unwrap();
+    assert_eq!(copy, total);
+    let store = sz
+        .estimate_compressed_size(&[&input], CompressionLevel::Store, CompressionMethod::Lzma2)
+        .unwrap();
+    assert_eq!(store, total);
+}
+
+#[test]
+fn test_cancel_token_stops_streaming_create() {
+    use rand::RngCore;
+    use seven_zip::{CancelToken, StreamOptions};
+    use std::sync::mpsc;
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir(&input).unwrap();
+    let mut data = vec![0u8; 4 * 1024 * 1024];
+    rand::thread_rng().fill_bytes(&mut data);
+    for i in 0..4 {
+        fs::write(input.join(format!("f{}.bin", i)), &data).unwrap();
+    }
+    
+    let token = CancelToken::new();
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    let (set_tx, set_rx) = mpsc::channel::<()>();
+    
+    let remote = token.clone();
+    let canceller = std::thread::spawn(move || {
+        started_rx.recv().unwrap();
+        remote.cancel();
+        set_tx.send(()).unwrap();
+    });
+    
+    let mut signalled = false;
+    let progress: seven_zip::BytesProgressCallback = Box::new(move |_, _, _, _, _| {
+        if !signalled {
+            signalled = true;
+            started_tx.send(()).unwrap();
+            set_rx.recv().unwrap();
+        }
+    });
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("cancel.7z");
+    let opts = StreamOptions::default().with_cancel_token(&token);
+    let result = sz.create_archive_streaming(&archive_path, &[&input], CompressionLevel::Fast, Some(&opts), Some(progress));
+    canceller.join().unwrap();
+    
+    assert!(token.is_cancelled());
+    assert_eq!(result, Err(seven_zip::Error::Cancelled));
+    assert!(!archive_path.exists());
+}
+
+#[test]
+fn test_path_modes() {
+    use seven_zip::PathMode;
+    
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("project");
+    let docs = project.join("docs");
+    let src = project.join("src");
+    fs::create_dir_all(&docs).unwrap();
+    fs::create_dir_all(&src).unwrap();
+    let readme = create_test_file(&project, "README.md", "readme");
+    create_test_file(&docs, "guide.md", "guide");
+    create_test_file(&src, "main.rs", "fn main() {}");
+    
+    let sz = SevenZip::new().unwrap();
+    let names = |archive: &std::path::Path| -> Vec<String> {
+        let mut names: Vec<String> = sz
+            .list(archive, None)
+            .unwrap()
+            .into_iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        names
+    };
+    let inputs = [readme.clone(), docs.clone()];
+    
+    let as_given = temp.path().join("as_given.7z");
+    sz.create_archive(&as_given, &inputs, CompressionLevel::Fast, None).unwrap();
+    assert_eq!(names(&as_given), vec!["README.md", "guide.md"]);
+    
+    let relative = temp.path().join("relative.7z");
+    let opts = CompressOptions::default().with_path_mode(PathMode::Relative { base: project.clone() });
+    sz.create_archive(&relative, &inputs, CompressionLevel::Fast, Some(&opts)).unwrap();
+    assert_eq!(names(&relative), vec!["README.md", "docs/guide.md"]);
+    
+    let whole = temp.path().join("whole.7z");
+    let opts = CompressOptions::default().with_path_mode(PathMode::Relative { base: temp.path().to_path_buf() });
+    sz.create_archive(&whole, &[&project], CompressionLevel::Fast, Some(&opts)).unwrap();
+    assert_eq!(names(&whole), vec!["project/README.md", "project/docs/guide.md", "project/src/main.rs"]);
+    
+    let outside = CompressOptions::default().with_path_mode(PathMode::Relative { base: docs.clone() });
+    assert!(matches!(
+        sz.create_archive(temp.path().join("bad.7z"), &[&readme], CompressionLevel::Fast, Some(&outside)),
+        Err(seven_zip::Error::InvalidParameter(_))
+    ));
+    
+    let flat = temp.path().join("flat.7z");
+    let opts = CompressOptions::default().with_path_mode(PathMode::BasenameOnly);
+    sz.create_archive(&flat, &[&project], CompressionLevel::Fast, Some(&opts)).unwrap();
+    assert_eq!(names(&flat), vec!["README.md", "guide.md", "main.rs"]);
+    assert!(sz.list(&flat, None).unwrap().iter().all(|e| !e.is_directory));
+    
+    create_test_file(&src, "guide.md", "another guide");
+    let result = sz.create_archive(temp.path().join("clash.7z"), &[&project], CompressionLevel::Fast, Some(&opts));
+    match result {
+        Err(seven_zip::Error::InvalidParameter(msg)) => assert!(msg.contains("guide.md"), "{}", msg),
+        other => panic!("expected a name collision, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_list_sorted() {
+    use seven_zip::SortKey;
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(input.join("sub")).unwrap();
+    create_test_file(&input, "medium.txt", &"m".repeat(500));
+    create_test_file(&input, "big.txt", &"b".repeat(5000));
+    create_test_file(&input.join("sub"), "small.txt", "s");
+    create_test_file(&input, "tiny.txt", "");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("sorted.7z");
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Normal, None).unwrap();
+    
+    let by_size = sz.list_sorted(&archive_path, None, SortKey::SizeDesc).unwrap();
+    assert_eq!(by_size.len(), sz.list(&archive_path, None).unwrap().len());
+    assert!(by_size.windows(2).all(|w| w[0].size >= w[1].size));
+    assert_eq!(by_size[0].name, "big.txt");
+    
+    let by_name: Vec<String> = sz
+        .list_sorted(&archive_path, None, SortKey::Name)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(by_name, vec!["big.txt", "medium.txt", "sub", "sub/small.txt", "tiny.txt"]);
+}
+
+#[test]
+fn test_list_full() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(input.join("sub")).unwrap();
+    create_test_file(&input, "a.txt", "alpha");
+    create_test_file(&input.join("sub"), "b.txt", "beta");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("full.7z");
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Normal, None).unwrap();
+    
+    let full = sz.list_full(&archive_path, None).unwrap();
+    assert_eq!(full.len(), sz.list(&archive_path, None).unwrap().len());
+    for f in &full {
+        let attrs = f.entry.attributes;
+        assert_eq!(f.windows_attributes.directory, attrs & 0x10 != 0);
+        assert_eq!(f.windows_attributes.directory, f.entry.is_directory);
+        if let Some(mode) = f.unix_mode {
+            assert_eq!(mode, attrs >> 16);
+        }
+        
+        let ticks = f.modified_ticks.expect("writer stores mtimes");
+        let secs = f.modified.unwrap().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(secs, ticks / 10_000_000 - 11_644_473_600);
+        assert_eq!(secs, f.entry.modified_time);
+        
+        assert_eq!(f.folder.is_some(), !f.entry.is_directory && f.entry.size > 0);
+    }
+    #[cfg(unix)]
+    assert!(full.iter().all(|f| f.unix_mode.is_some()));
+}
+
+#[test]
+fn test_all_times_round_trip() {
+    use std::time::{Duration, UNIX_EPOCH};
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    let file = create_test_file(&input, "evidence.txt", "chain of custody");
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    let atime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    filetime::set_file_times(
+        &file,
+        filetime::FileTime::from_system_time(atime),
+        filetime::FileTime::from_system_time(mtime),
+    )
+    .unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("times.7z");
+    let opts = CompressOptions::default().with_store_all_times(true);
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let full = sz.list_full(&archive_path, None).unwrap();
+    assert_eq!(full[0].modified, Some(mtime));
+    assert_eq!(full[0].accessed, Some(atime));
+    assert!(full[0].created.is_some());
+    
+    // Without the option only the modification time is stored
+    let plain_path = temp.path().join("plain.7z");
+    sz.create_archive(&plain_path, &[&file], CompressionLevel::Normal, None).unwrap();
+    let plain = sz.list_full(&plain_path, None).unwrap();
+    assert_eq!((plain[0].accessed, plain[0].created), (None, None));
+    
+    let output = temp.path().join("output");
+    let extract_opts = ExtractOptions::default().with_restore_all_times(true);
+    sz.extract_with_options(&archive_path, &output, &extract_opts, None).unwrap();
+    let restored = fs::metadata(output.join("evidence.txt")).unwrap();
+    assert_eq!(restored.modified().unwrap(), mtime);
+    assert_eq!(restored.accessed().unwrap(), atime);
+    // Only Windows can set a creation time
+    if cfg!(windows) {
+        assert_eq!(restored.created().ok(), full[0].created);
+    }
+}
+
+#[test]
+fn test_extract_from_reader() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(input.join("sub")).unwrap();
+    create_test_file(&input, "a.txt", "piped");
+    create_test_file(&input.join("sub"), "b.txt", "through stdin");
+    
+    let sz = SevenZip::new().unwrap();
+    let mut archive = Vec::new();
+    sz.compress_to_writer(&[&input], CompressionLevel::Normal, None, &mut archive).unwrap();
+    
+    let output = temp.path().join("output");
+    sz.extract_from_reader(std::io::Cursor::new(archive), &output, None).unwrap();
+    assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "piped");
+    assert_eq!(fs::read_to_string(output.join("sub/b.txt")).unwrap(), "through stdin");
+    
+    let garbage = std::io::Cursor::new(b"not an archive".to_vec());
+    assert!(sz.extract_from_reader(garbage, temp.path().join("bad"), None).is_err());
+}
+
+#[test]
+fn test_if_exists_policy() {
+    use seven_zip::ExistsPolicy;
+    
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "data.txt", "new contents");
+    let archive_path = temp.path().join("archive.7z");
+    fs::write(&archive_path, b"precious").unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let fail = CompressOptions::default().with_if_exists(ExistsPolicy::Fail);
+    let result = sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, Some(&fail));
+    assert_eq!(
+        result,
+        Err(seven_zip::Error::AlreadyExists { path: archive_path.display().to_string() })
+    );
+    assert_eq!(fs::read(&archive_path).unwrap(), b"precious");
+    
+    let stamp = CompressOptions::default().with_if_exists(ExistsPolicy::AppendTimestamp);
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, Some(&stamp)).unwrap();
+    assert_eq!(fs::read(&archive_path).unwrap(), b"precious");
+    let created: Vec<String> = fs::read_dir(temp.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("archive-"))
+        .collect();
+    assert_eq!(created.len(), 1);
+    // archive-YYYYMMDD-HHMMSS.7z
+    assert_eq!(created[0].len(), "archive-20240101-120000.7z".len());
+    assert!(created[0].ends_with(".7z"));
+    assert_eq!(sz.list(temp.path().join(&created[0]), None).unwrap()[0].name, "data.txt");
+}
+
+#[test]
+fn test_fingerprint() {
+    let temp = TempDir::new().unwrap();
+    let mtime = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+    let make_input = |name: &str, report: &str| {
+        let dir = temp.path().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in [("index.txt", "case 42"), ("report.txt", report)] {
+            let path = create_test_file(&dir, file, content);
+            filetime::set_file_mtime(&path, mtime).unwrap();
+        }
+        dir
+    };
+    
+    let sz = SevenZip::new().unwrap();
+    let mut fingerprints = Vec::new();
+    for (name, report) in [("a", "findings: none"), ("b", "findings: none"), ("c", "findings: many")] {
+        let archive_path = temp.path().join(format!("{}.7z", name));
+        sz.create_archive(&archive_path, &[make_input(name, report)], CompressionLevel::Normal, None).unwrap();
+        fingerprints.push(sz.fingerprint(&archive_path).unwrap());
+    }
+    
+    assert_eq!(fingerprints[0], fingerprints[1]);
+    assert_eq!(fingerprints[0].entry_count, 2);
+    assert_eq!(fingerprints[0].total_size, 21);
+    assert_eq!(fingerprints[0].codec_set, vec!["LZMA2".to_string()]);
+    assert!(!fingerprints[0].encrypted);
+    
+    // Same structure, different content: only the header hash moves
+    assert_ne!(fingerprints[2].sha256_of_header, fingerprints[0].sha256_of_header);
+    assert_eq!(
+        (fingerprints[2].entry_count, fingerprints[2].total_size, &fingerprints[2].codec_set),
+        (fingerprints[0].entry_count, fingerprints[0].total_size, &fingerprints[0].codec_set)
+    );
+}
+
+#[test]
+fn test_entries_with_readers() {
+    use std::io::Read;
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(input.join("sub")).unwrap();
+    create_test_file(&input, "a.txt", "first");
+    create_test_file(&input.join("sub"), "b.txt", &"second ".repeat(1000));
+    create_test_file(&input, "empty.txt", "");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("readers.7z");
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Normal, None).unwrap();
+    
+    let mut entries = sz.entries_with_readers(&archive_path, None).unwrap();
+    let mut seen = Vec::new();
+    while let Some(item) = entries.next() {
+        let (entry, mut reader) = item.unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len() as u64, entry.size);
+        if !entry.is_directory {
+            assert_eq!(data, fs::read(input.join(&entry.name)).unwrap());
+        }
+        seen.push(entry.name);
+    }
+    seen.sort();
+    assert_eq!(seen, vec!["a.txt", "empty.txt", "sub", "sub/b.txt"]);
+    
+    // Entries can be skipped without reading them
+    let mut entries = sz.entries_with_readers(&archive_path, None).unwrap();
+    let mut last = None;
+    while let Some(item) = entries.next() {
+        let (entry, mut reader) = item.unwrap();
+        if entry.name == "sub/b.txt" {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).unwrap();
+            last = Some(text);
+        }
+    }
+    assert_eq!(last.unwrap(), "second ".repeat(1000));
+}
+
+#[test]
+fn test_continue_on_error() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    create_test_file(&input, "good1.txt", "first good file");
+    create_test_file(&input, "bad.txt", "CORRUPT-ME-PLEASE");
+    create_test_file(&input, "good2.txt", "second good file");
+    
+    // Stored, so damaging one file's bytes leaves the others intact
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("damaged.7z");
+    sz.create_archive(&archive_path, &[&input], CompressionLevel::Store, None).unwrap();
+    let mut bytes = fs::read(&archive_path).unwrap();
+    let at = bytes.windows(7).position(|w| w == b"CORRUPT").unwrap();
+    bytes[at] ^= 0xFF;
+    fs::write(&archive_path, bytes).unwrap();
+    
+    let output = temp.path().join("output");
+    let opts = ExtractOptions::default().with_continue_on_error(true);
+    match sz.extract_with_options(&archive_path, &output, &opts, None) {
+        Err(seven_zip::Error::PartialExtraction { failures }) => {
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].0, "bad.txt");
+        }
+        other => panic!("expected PartialExtraction, got {:?}", other),
+    }
+    assert_eq!(fs::read_to_string(output.join("good1.txt")).unwrap(), "first good file");
+    assert_eq!(fs::read_to_string(output.join("good2.txt")).unwrap(), "second good file");
+    
+    // Without the flag the whole extraction fails
+    let strict = temp.path().join("strict");
+    assert!(sz.extract_with_options(&archive_path, &strict, &ExtractOptions::default(), None).is_err());
+}
+
+#[test]
+fn test_dedup_identical_files() {
+    use rand::RngCore;
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("images");
+    fs::create_dir_all(input.join("copies")).unwrap();
+    let mut image = vec![0u8; 3 * 1024 * 1024];
+    rand::thread_rng().fill_bytes(&mut image);
+    fs::write(input.join("disk.img"), &image).unwrap();
+    fs::write(input.join("copies/disk-clone.img"), &image).unwrap();
+    create_test_file(&input, "notes.txt", "two identical images");
+    
+    let sz = SevenZip::new().unwrap();
+    let plain_path = temp.path().join("plain.7z");
+    sz.create_archive(&plain_path, &[&input], CompressionLevel::Normal, None).unwrap();
+    let dedup_path = temp.path().join("dedup.7z");
+    let opts = CompressOptions::default().with_dedup(true);
+    sz.create_archive(&dedup_path, &[&input], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let one_copy = image.len() as u64;
+    let plain_size = fs::metadata(&plain_path).unwrap().len();
+    let dedup_size = fs::metadata(&dedup_path).unwrap().len();
+    assert!(plain_size > one_copy * 19 / 10, "plain archive: {} bytes", plain_size);
+    assert!(dedup_size < one_copy * 11 / 10, "dedup archive: {} bytes", dedup_size);
+    
+    let output = temp.path().join("output");
+    sz.extract(&dedup_path, &output).unwrap();
+    assert_eq!(fs::read(output.join("disk.img")).unwrap(), image);
+    assert_eq!(fs::read(output.join("copies/disk-clone.img")).unwrap(), image);
+    assert_eq!(fs::read_to_string(output.join("notes.txt")).unwrap(), "two identical images");
+}
+
+#[test]
+fn test_unsupported_format_version() {
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "data.txt", "from the future");
+    let archive_path = temp.path().join("future.7z");
+    
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, None).unwrap();
+    // Bytes 6-7 hold the format version and aren't covered by any CRC
+    let mut bytes = fs::read(&archive_path).unwrap();
+    bytes[6] = 1;
+    bytes[7] = 0;
+    fs::write(&archive_path, bytes).unwrap();
+    
+    let expected = seven_zip::Error::UnsupportedVersion { found: (1, 0), supported: (0, 4) };
+    assert_eq!(sz.list(&archive_path, None), Err(expected.clone()));
+    assert_eq!(sz.extract(&archive_path, temp.path().join("out")), Err(expected.clone()));
+    assert_eq!(sz.list_full(&archive_path, None).unwrap_err(), expected);
+    assert!(expected.to_string().contains("upgrade"));
+}
+
+#[test]
+fn test_create_archive_from_listfile() {
+    let sz = SevenZip::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let first = create_test_file(temp_dir.path(), "first.txt", "first");
+    let second = create_test_file(temp_dir.path(), "second.txt", "second");
+    let missing = temp_dir.path().join("missing.txt");
+    let archive_path = temp_dir.path().join("listed.7z");
+
+    let list_file = temp_dir.path().join("list.txt");
+    fs::write(
+        &list_file,
+        format!("# generated selection\n{}\n\n{}\n{}\n", first.display(), second.display(), missing.display()),
+    )
+    .unwrap();
+
+    match sz.create_archive_from_listfile(&archive_path, &list_file, CompressionLevel::Fast, None) {
+        Err(seven_zip::Error::InvalidParameter(msg)) => {
+            assert!(msg.contains("missing.txt"), "{}", msg);
+            assert!(!msg.contains("first.txt"), "{}", msg);
+        }
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+    assert!(!archive_path.exists());
+
+    // Without the missing line the rest is archived
+    fs::write(&list_file, format!("{}\n  {}  \n", first.display(), second.display())).unwrap();
+    sz.create_archive_from_listfile(&archive_path, &list_file, CompressionLevel::Fast, None).unwrap();
+    let mut names: Vec<String> = sz.list(&archive_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    names.sort();
+    assert_eq!(names, ["first.txt", "second.txt"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_verify_extractable() {
+    use seven_zip::ExtractWarning;
+
+    let sz = SevenZip::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir(&src_dir).unwrap();
+    // Fine on Linux, but not extractable on Windows
+    let colon = create_test_file(&src_dir, "report 10:30.txt", "timed");
+    let plain = create_test_file(&src_dir, "plain.txt", "plain");
+    let device = create_test_file(&src_dir, "aux.txt", "device");
+    let archive_path = temp_dir.path().join("names.7z");
+    sz.create_archive(&archive_path, &[&colon, &plain, &device], CompressionLevel::Fast, None).unwrap();
+
+    let target = temp_dir.path().join("target");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("plain.txt"), "already here").unwrap();
+
+    let warnings = sz.verify_extractable(&archive_path, &target, None).unwrap();
+    assert_eq!(warnings.len(), 3, "{:?}", warnings);
+    assert!(warnings.contains(&ExtractWarning::InvalidChar { name: "report 10:30.txt".into(), ch: ':' }));
+    assert!(warnings.contains(&ExtractWarning::ReservedName { name: "aux.txt".into() }));
+    assert!(warnings.contains(&ExtractWarning::WouldOverwrite {
+        name: "plain.txt".into(),
+        path: target.join("plain.txt"),
+    }));
+
+    // Nothing was written
+    assert_eq!(fs::read_dir(&target).unwrap().count(), 1);
+    assert_eq!(fs::read_to_string(target.join("plain.txt")).unwrap(), "already here");
+}
+
+#[test]
+fn test_on_volume_complete() {
+    use seven_zip::StreamOptions;
+    use std::sync::{Arc, Mutex};
+
+    let temp = TempDir::new().unwrap();
+    let data: Vec<u8> = (0..350_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    let source = temp.path().join("data.bin");
+    fs::write(&source, &data).unwrap();
+    let uploaded = temp.path().join("uploaded");
+    fs::create_dir(&uploaded).unwrap();
+    let archive_path = temp.path().join("split.7z");
+
+    // "Upload" each volume by moving it away as soon as it is handed over
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&seen);
+    let target = uploaded.clone();
+    let opts = StreamOptions { split_size: 100_000, ..Default::default() }.with_on_volume_complete(move |volume, size| {
+        assert_eq!(fs::metadata(volume)?.len(), size);
+        let name = volume.file_name().unwrap().to_string_lossy().into_owned();
+        fs::rename(volume, target.join(&name))?;
+        log.lock().unwrap().push(name);
+        Ok(())
+    });
+    let sz = SevenZip::new().unwrap();
+    sz.create_archive_streaming(&archive_path, &[&source], CompressionLevel::Store, Some(&opts), None).unwrap();
+
+    // Once each; the middle volumes in order, then the first and the last
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen, ["split.7z.002", "split.7z.003", "split.7z.001", "split.7z.004"]);
+    assert!(!temp.path().join("split.7z.001").exists());
+
+    let mut volumes: Vec<PathBuf> = seen.iter().map(|name| uploaded.join(name)).collect();
+    volumes.sort();
+    let output = temp.path().join("out");
+    sz.extract_from_volumes(&volumes, &output, None).unwrap();
+    assert_eq!(fs::read(output.join("data.bin")).unwrap(), data);
+
+    // An error from the hook aborts and comes back to the caller
+    let opts = StreamOptions { split_size: 100_000, ..Default::default() }
+        .with_on_volume_complete(|_, _| Err(seven_zip::Error::Io("upload failed".into())));
+    let err = sz
+        .create_archive_streaming(temp.path().join("failed.7z"), &[&source], CompressionLevel::Store, Some(&opts), None)
+        .unwrap_err();
+    assert_eq!(err, seven_zip::Error::Io("upload failed".into()));
+    assert!(!temp.path().join("failed.7z.001").exists());
+}
+
+#[test]
+fn test_non_recursive_directory_input() {
+    let sz = SevenZip::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let source = temp_dir.path().join("source");
+    fs::create_dir_all(source.join("nested/deeper")).unwrap();
+    fs::create_dir(source.join("empty")).unwrap();
+    create_test_file(&source, "top.txt", "top level");
+    create_test_file(&source, "also_top.txt", "top level too");
+    create_test_file(&source.join("nested"), "inner.txt", "one down");
+    create_test_file(&source.join("nested/deeper"), "deep.txt", "two down");
+
+    let archive_path = temp_dir.path().join("flat.7z");
+    let opts = CompressOptions::default().with_recursive(false);
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Fast, Some(&opts)).unwrap();
+
+    let mut names: Vec<String> = sz.list(&archive_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    names.sort();
+    assert_eq!(names, ["also_top.txt", "top.txt"]);
+
+    // The default still walks the whole tree
+    sz.create_archive(&archive_path, &[&source], CompressionLevel::Fast, None).unwrap();
+    let names: Vec<String> = sz.list(&archive_path, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert!(names.contains(&"nested/deeper/deep.txt".to_string()), "{:?}", names);
+}
+
+#[test]
+fn test_packed_size() {
+    use seven_zip::StreamOptions;
+
+    let sz = SevenZip::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let text = create_test_file(temp_dir.path(), "text.txt", &"packed size check ".repeat(20_000));
+    let archive_path = temp_dir.path().join("text.7z");
+    sz.create_archive(&archive_path, &[&text], CompressionLevel::Normal, None).unwrap();
+
+    let packed = sz.packed_size(&archive_path, None).unwrap();
+    let unpacked = sz.uncompressed_size(&archive_path, None).unwrap();
+    assert!(packed > 0 && packed < unpacked / 10, "packed {} of {}", packed, unpacked);
+    // Only the signature header and the archive header come on top
+    let on_disk = fs::metadata(&archive_path).unwrap().len();
+    assert!(packed < on_disk && on_disk - packed < 1024, "packed {} on disk {}", packed, on_disk);
+
+    // Split archives are totalled across their volumes
+    let noise: Vec<u8> = (0..250_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    let noise_path = temp_dir.path().join("noise.bin");
+    fs::write(&noise_path, &noise).unwrap();
+    let split_path = temp_dir.path().join("split.7z");
+    let opts = StreamOptions { split_size: 100_000, ..Default::default() };
+    sz.create_archive_streaming(&split_path, &[&noise_path], CompressionLevel::Store, Some(&opts), None).unwrap();
+    assert_eq!(sz.packed_size(temp_dir.path().join("split.7z.001"), None).unwrap(), noise.len() as u64);
+}
+
+#[test]
+fn test_file_size_limits() {
+    let sz = SevenZip::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let logs = temp_dir.path().join("logs");
+    fs::create_dir_all(logs.join("old")).unwrap();
+    create_test_file(&logs, "empty.log", "");
+    create_test_file(&logs, "small.log", &"x".repeat(100));
+    create_test_file(&logs, "medium.log", &"x".repeat(1_000));
+    create_test_file(&logs.join("old"), "large.log", &"x".repeat(10_000));
+    let single = create_test_file(temp_dir.path(), "huge.log", &"x".repeat(20_000));
+
+    let archive_path = temp_dir.path().join("logs.7z");
+    let opts = CompressOptions::default().with_min_file_size(1).with_max_file_size(1_000);
+    let report = sz
+        .create_archive_with_report(&archive_path, &[&logs, &single], CompressionLevel::Fast, Some(&opts))
+        .unwrap();
+
+    let mut files: Vec<String> = sz
+        .list(&archive_path, None)
+        .unwrap()
+        .into_iter()
+        .filter(|e| !e.is_directory)
+        .map(|e| e.name)
+        .collect();
+    files.sort();
+    assert_eq!(files, ["medium.log", "small.log"]);
+    assert_eq!(
+        report.skipped_by_size,
+        [single, logs.join("empty.log"), logs.join("old/large.log")]
+    );
+}
+
+#[test]
+fn test_sort_order_groups_similar_files() {
+    use seven_zip::SortOrder;
+
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    // Two unrelated bodies, each repeated across files of one extension
+    let body = |seed: u64| {
+        let mut state = seed;
+        (0..40_000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                char::from(b'a' + ((state >> 33) % 26) as u8)
+            })
+            .collect::<String>()
+    };
+    let (text, binary) = (body(1), body(2));
+    let mut inputs = Vec::new();
+    for i in 0..4 {
+        inputs.push(create_test_file(temp_dir.path(), &format!("t{}.txt", i), &text));
+        inputs.push(create_test_file(temp_dir.path(), &format!("b{}.bin", i), &binary));
+    }
+
+    // A dictionary too small to reach back past the interleaved file
+    let size_with = |order: SortOrder, name: &str| {
+        let archive = temp_dir.path().join(name);
+        let opts = CompressOptions { dict_size: 64 * 1024, ..Default::default() }.with_sort_order(order);
+        sz.create_archive(&archive, &inputs, CompressionLevel::Normal, Some(&opts)).unwrap();
+        fs::metadata(&archive).unwrap().len()
+    };
+    let unsorted = size_with(SortOrder::None, "none.7z");
+    let grouped = size_with(SortOrder::ByExtension, "ext.7z");
+    assert!(grouped < unsorted, "{} >= {}", grouped, unsorted);
+
+    // The listing follows the storage order
+    let archive = temp_dir.path().join("ext.7z");
+    let names: Vec<String> = sz.list(&archive, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["b0.bin", "b1.bin", "b2.bin", "b3.bin", "t0.txt", "t1.txt", "t2.txt", "t3.txt"]);
+
+    let by_size = temp_dir.path().join("size.7z");
+    let small = create_test_file(temp_dir.path(), "small.txt", "tiny");
+    let opts = CompressOptions::default().with_sort_order(SortOrder::BySize);
+    sz.create_archive(&by_size, &[&inputs[0], &small], CompressionLevel::Normal, Some(&opts)).unwrap();
+    let names: Vec<String> = sz.list(&by_size, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["small.txt", "t0.txt"]);
+}
+
+#[test]
+fn test_archive_with_callback() {
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    let inputs = [
+        create_test_file(temp_dir.path(), "a.txt", "first"),
+        create_test_file(temp_dir.path(), "b.txt", "second"),
+        create_test_file(temp_dir.path(), "c.txt", "third"),
+    ];
+    let archive = temp_dir.path().join("test.7z");
+    sz.create_archive(&archive, &inputs, CompressionLevel::Normal, None).unwrap();
+
+    let mut events = Vec::new();
+    sz.test_archive_with_callback(&archive, None, |name, passed| events.push((name.to_string(), passed)))
+        .unwrap();
+    assert_eq!(
+        events,
+        [("a.txt".to_string(), true), ("b.txt".to_string(), true), ("c.txt".to_string(), true)]
+    );
+
+    // A panicking callback unwinds out of the call, not across the C frames
+    let mut calls = 0;
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        sz.test_archive_with_callback(&archive, None, |_, _| {
+            calls += 1;
+            panic!("callback failed");
+        })
+    }));
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback failed"));
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_tree() {
+    use seven_zip::{PathMode, TreeNode};
+
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    // Only the two files are stored; `a` and `a/b` must be synthesized
+    let root = temp_dir.path().join("src");
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    let inputs = [
+        create_test_file(&root.join("a/b"), "c.txt", "deep"),
+        create_test_file(&root.join("a"), "d.txt", "shallow"),
+    ];
+    let archive = temp_dir.path().join("tree.7z");
+    let opts = CompressOptions { path_mode: PathMode::Relative { base: root }, ..Default::default() };
+    sz.create_archive(&archive, &inputs, CompressionLevel::Normal, Some(&opts)).unwrap();
+    assert_eq!(sz.list(&archive, None).unwrap().len(), 2);
+
+    let file = |name: &str| TreeNode { name: name.to_string(), is_dir: false, children: vec![] };
+    let dir = |name: &str, children| TreeNode { name: name.to_string(), is_dir: true, children };
+    assert_eq!(
+        sz.tree(&archive, None).unwrap(),
+        dir("", vec![dir("a", vec![dir("b", vec![file("c.txt")]), file("d.txt")])])
+    );
+}
+
+#[test]
+fn test_peak_memory_grows_with_dictionary() {
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    // Larger than the small dictionary, so the big one isn't cut back to it
+    let words = ["alpha ", "bravo ", "charlie ", "delta ", "echo "];
+    let text: String = (0..100_000usize).map(|i| words[(i * 7 + i / 3) % 5]).collect();
+    let input = create_test_file(temp_dir.path(), "words.txt", &text);
+
+    let peak_with = |dict_size: u64| {
+        let archive = temp_dir.path().join(format!("dict-{}.7z", dict_size));
+        let opts = CompressOptions { dict_size, num_threads: 1, ..Default::default() };
+        let report = sz.create_archive_with_report(&archive, &[&input], CompressionLevel::Normal, Some(&opts)).unwrap();
+        report.peak_memory_bytes
+    };
+    let small = peak_with(64 * 1024);
+    let large = peak_with(4 * 1024 * 1024);
+    assert!(small > 64 * 1024, "{}", small);
+    assert!(large > small, "{} <= {}", large, small);
+
+    let archive = temp_dir.path().join("store.7z");
+    let report = sz.create_archive_with_report(&archive, &[&input], CompressionLevel::Store, None).unwrap();
+    assert_eq!(report.peak_memory_bytes, 0);
+}
+
+#[test]
+fn test_create_sfx() {
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+    let input = create_test_file(temp_dir.path(), "readme.txt", "Double-click to unpack");
+
+    // Smallest thing that passes for a PE image: DOS header pointing at "PE\0\0"
+    let mut stub = vec![0u8; 0x80];
+    stub[..2].copy_from_slice(b"MZ");
+    stub[0x3C] = 0x40;
+    stub[0x40..0x44].copy_from_slice(b"PE\0\0");
+    let sfx_module = temp_dir.path().join("7z.sfx");
+    fs::write(&sfx_module, &stub).unwrap();
+
+    let exe = temp_dir.path().join("readme.exe");
+    sz.create_sfx(&exe, &[&input], CompressionLevel::Normal, None, &sfx_module).unwrap();
+    let bytes = fs::read(&exe).unwrap();
+    assert_eq!(&bytes[..stub.len()], &stub[..]);
+
+    // The bytes after the stub are a complete archive on their own
+    let tail = temp_dir.path().join("tail.7z");
+    fs::write(&tail, &bytes[stub.len()..]).unwrap();
+    assert!(sz.quick_check(&tail).unwrap());
+    let names: Vec<String> = sz.list(&tail, None).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["readme.txt"]);
+
+    // Anything else is refused before the output is created
+    let not_a_stub = create_test_file(temp_dir.path(), "notes.txt", "MZ but nothing more");
+    let other = temp_dir.path().join("other.exe");
+    let result = sz.create_sfx(&other, &[&input], CompressionLevel::Normal, None, &not_a_stub);
+    assert!(matches!(result, Err(seven_zip::Error::InvalidParameter(_))));
+    assert!(!other.exists());
+}
+
+#[test]
+fn test_case_collisions() {
+    use seven_zip::{ExtractOptions, ExtractWarning, PathMode};
+
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    // Built on a case-sensitive filesystem, or the two can't coexist
+    let upper_dir = temp_dir.path().join("upper");
+    let lower_dir = temp_dir.path().join("lower");
+    fs::create_dir_all(&upper_dir).unwrap();
+    fs::create_dir_all(&lower_dir).unwrap();
+    let inputs = [
+        create_test_file(&upper_dir, "A.txt", "upper"),
+        create_test_file(&lower_dir, "a.txt", "lower"),
+    ];
+    let archive = temp_dir.path().join("case.7z");
+    let opts = CompressOptions { path_mode: PathMode::BasenameOnly, ..Default::default() };
+    sz.create_archive(&archive, &inputs, CompressionLevel::Normal, Some(&opts)).unwrap();
+
+    let out = temp_dir.path().join("out");
+    assert_eq!(
+        sz.verify_extractable(&archive, &out, None).unwrap(),
+        [ExtractWarning::CaseCollision { names: vec!["A.txt".to_string(), "a.txt".to_string()] }]
+    );
+
+    // Both contents survive whatever the filesystem's case sensitivity
+    let options = ExtractOptions::default().with_case_insensitive_safe(true);
+    sz.extract_with_options(&archive, &out, &options, None).unwrap();
+    let mut contents: Vec<String> = fs::read_dir(&out)
+        .unwrap()
+        .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+        .collect();
+    contents.sort();
+    assert_eq!(contents, ["lower", "upper"]);
+}
+
+#[test]
+fn test_compress_header() {
+    let temp_dir = TempDir::new().unwrap();
+    let sz = SevenZip::new().unwrap();
+
+    // Many similar names make a header worth compressing
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    for i in 0..200 {
+        create_test_file(&src, &format!("evidence_item_{:04}.txt", i), &format!("item {}", i));
+    }
+
+    let mut sizes = Vec::new();
+    for compress_header in [false, true] {
+        let archive = temp_dir.path().join(format!("header-{}.7z", compress_header));
+        let opts = CompressOptions::default().with_compress_header(compress_header);
+        sz.create_archive(&archive, &[&src], CompressionLevel::Normal, Some(&opts)).unwrap();
+
+        assert_eq!(sz.archive_info(&archive, None).unwrap().is_compressed_header, compress_header);
+        assert!(sz.quick_check(&archive).unwrap());
+        sz.test_archive(&archive, None).unwrap();
+        let entries = sz.list(&archive, None).unwrap();
+        assert_eq!(entries.len(), 200);
+        assert!(entries.iter().any(|e| e.name == "evidence_item_0199.txt"));
+
+        let out = temp_dir.path().join(format!("out-{}", compress_header));
+        sz.extract(&archive, &out).unwrap();
+        assert_eq!(fs::read_to_string(out.join("evidence_item_0042.txt")).unwrap(), "item 42");
+        sizes.push(fs::metadata(&archive).unwrap().len());
+    }
+    assert!(sizes[1] < sizes[0], "{:?}", sizes);
+}
+
+#[test]
+fn test_encryption_info() {
+    // Header-only 7z with one folder coded 7zAES (cycles power 19, 16-byte IV,
+    // no salt) -> LZMA2, as 7-Zip writes with -p. The packed bytes are filler;
+    // only the header is read.
+    const AES_ARCHIVE_HEX: &str = concat!(
+        "377abcaf271c0004266444c910000000000000004d0000000000000056fa5da4000102030405060708090a0b0c0d0e0f",
+        "0104060001091000070b0100022406f1070112530fa0a1a2a3a4a5a6a7a8a9aaabacadaeaf2121011801000c0c050000",
+        "05011117007300650063007200650074002e0074007800740000000000",
+    );
+    let bytes: Vec<u8> = (0..AES_ARCHIVE_HEX.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&AES_ARCHIVE_HEX[i..i + 2], 16).unwrap())
+        .collect();
+    
+    let temp = TempDir::new().unwrap();
+    let encrypted = temp.path().join("encrypted.7z");
+    fs::write(&encrypted, bytes).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let entries = sz.list(&encrypted, None).unwrap();
+    assert_eq!(entries[0].name, "secret.txt");
+    assert_eq!(entries[0].method, "LZMA2 7zAES");
+    
+    let info = sz.encryption_info(&encrypted, None).unwrap().expect("folder is encrypted");
+    assert_eq!(info.kdf_cycles_power, 19);
+    assert_eq!(info.kdf_iterations, 1 << 19);
+    assert_eq!(info.salt_size, 0);
+    assert_eq!(info.iv_size, 16);
+    
+    let file = create_test_file(temp.path(), "plain.txt", "not encrypted");
+    let plain = temp.path().join("plain.7z");
+    sz.create_archive(&plain, &[&file], CompressionLevel::Normal, None).unwrap();
+    assert_eq!(sz.encryption_info(&plain, None).unwrap(), None);
+}
+
+#[test]
+fn test_encrypted_archive_uses_7z_key() {
+    use aes::cipher::{BlockDecryptMut, KeyIvInit};
+    use seven_zip::encryption::{derive_7z_key, DEFAULT_7Z_CYCLES_POWER};
+    
+    let temp = TempDir::new().unwrap();
+    let content = "stored, so the AES input is the file itself";
+    let file = create_test_file(temp.path(), "notes.txt", content);
+    let archive = temp.path().join("encrypted.7z");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_password("pä55wörd".to_string());
+    sz.create_archive(&archive, &[&file], CompressionLevel::Store, Some(&opts)).unwrap();
+    
+    let info = sz.encryption_info(&archive, None).unwrap().expect("folder is encrypted");
+    assert_eq!((info.kdf_cycles_power, info.salt_size, info.iv_size), (DEFAULT_7Z_CYCLES_POWER, 0, 16));
+    assert_eq!(sz.list(&archive, None).unwrap()[0].method, "Copy 7zAES");
+    
+    // Decrypt the packed stream by hand: the key is 7-Zip's, over the UTF-16LE
+    // password, and the IV follows the AES coder ID and property flags
+    let bytes = fs::read(&archive).unwrap();
+    let props = bytes.windows(7).position(|w| w == [0x06, 0xF1, 0x07, 0x01, 18, 0x53, 0x0F]).unwrap() + 7;
+    let iv: [u8; 16] = bytes[props..props + 16].try_into().unwrap();
+    let key = derive_7z_key("pä55wörd", &[], DEFAULT_7Z_CYCLES_POWER).unwrap();
+    let mut packed = bytes[32..32 + content.len().div_ceil(16) * 16].to_vec();
+    cbc::Decryptor::<aes::Aes256>::new(&key.into(), &iv.into())
+        .decrypt_padded_mut::<aes::cipher::block_padding::NoPadding>(&mut packed)
+        .unwrap();
+    assert_eq!(&packed[..content.len()], content.as_bytes());
+    assert!(packed[content.len()..].iter().all(|&b| b == 0));
+    
+    let out = temp.path().join("out");
+    let opts = ExtractOptions { password: Some("pä55wörd".to_string()), ..Default::default() };
+    sz.extract_with_options(&archive, &out, &opts, None).unwrap();
+    assert_eq!(fs::read_to_string(out.join("notes.txt")).unwrap(), content);
+    
+    let rejected = CompressOptions::default().with_password(String::new());
+    assert!(sz.create_archive(temp.path().join("empty.7z"), &[&file], CompressionLevel::Store, Some(&rejected)).is_err());
+}
+
+#[test]
+fn test_kdf_iterations_round_trip() {
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "evidence.txt", "chain of custody");
+    let sz = SevenZip::new().unwrap();
+    
+    let archive = temp.path().join("hardened.7z");
+    let opts = CompressOptions::default().with_password("s3cret".to_string()).with_kdf_iterations(1 << 20);
+    sz.create_archive(&archive, &[&file], CompressionLevel::Fast, Some(&opts)).unwrap();
+    let info = sz.encryption_info(&archive, None).unwrap().unwrap();
+    assert_eq!((info.kdf_iterations, info.kdf_cycles_power), (1 << 20, 20));
+    
+    let out = temp.path().join("out");
+    sz.extract_with_password(&archive, &out, Some("s3cret"), None).unwrap();
+    assert_eq!(fs::read_to_string(out.join("evidence.txt")).unwrap(), "chain of custody");
+    
+    // Counts the header can't store, or 7-Zip won't decode, are refused up front
+    for iterations in [3, 1 << 25, 0] {
+        let opts = CompressOptions::default().with_password("s3cret".to_string()).with_kdf_iterations(iterations);
+        let err = sz.create_archive(temp.path().join("bad.7z"), &[&file], CompressionLevel::Fast, Some(&opts)).unwrap_err();
+        assert!(matches!(err, seven_zip::Error::InvalidParameter(_)), "{}: {:?}", iterations, err);
+    }
+    let unkeyed = CompressOptions::default().with_kdf_iterations(1 << 20);
+    assert!(sz.create_archive(temp.path().join("bad.7z"), &[&file], CompressionLevel::Fast, Some(&unkeyed)).is_err());
+    assert!(!temp.path().join("bad.7z").exists());
+}
+
+#[test]
+fn test_raw_encryption_key_round_trip() {
+    let key: [u8; 32] = std::array::from_fn(|i| (i * 7 + 3) as u8);
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("src");
+    fs::create_dir_all(&source).unwrap();
+    create_test_file(&source, "a.txt", "wrapped by the KMS");
+    create_test_file(&source, "b.txt", "second file, same folder");
+    let archive = temp.path().join("kms.7z");
+    let sz = SevenZip::new().unwrap();
+    let opts = CompressOptions::default().with_encryption_key(key);
+    sz.create_archive(&archive, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    assert!(sz.encryption_info(&archive, None).unwrap().is_some());
+    
+    // Both the C extractor and the entry-by-entry reader take the key
+    for continue_on_error in [false, true] {
+        let out = temp.path().join(format!("out_{}", continue_on_error));
+        let opts = ExtractOptions { continue_on_error, ..ExtractOptions::default().with_encryption_key(key) };
+        sz.extract_with_options(&archive, &out, &opts, None).unwrap();
+        assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "wrapped by the KMS");
+        assert_eq!(fs::read_to_string(out.join("b.txt")).unwrap(), "second file, same folder");
+    }
+    
+    let mut wrong = key;
+    wrong[0] ^= 1;
+    let wrong = ExtractOptions::default().with_encryption_key(wrong);
+    assert!(sz.extract_with_options(&archive, temp.path().join("wrong"), &wrong, None).is_err());
+    assert!(sz.extract(&archive, temp.path().join("none")).is_err());
+    
+    let both = ExtractOptions::default().with_encryption_key(key).with_password("x".to_string());
+    let err = sz.extract_with_options(&archive, temp.path().join("both"), &both, None).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::UnsupportedOptionCombination { .. }), "{:?}", err);
+    let both = CompressOptions::default().with_encryption_key(key).with_password("x".to_string());
+    let err = sz.create_archive(temp.path().join("both.7z"), &[&source], CompressionLevel::Normal, Some(&both)).unwrap_err();
+    assert!(matches!(err, seven_zip::Error::UnsupportedOptionCombination { .. }), "{:?}", err);
+}
+
+#[test]
+fn test_quick_check() {
+    let temp = TempDir::new().unwrap();
+    let file = create_test_file(temp.path(), "data.txt", &"quick check ".repeat(1000));
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("good.7z");
+    sz.create_archive(&archive_path, &[&file], CompressionLevel::Normal, None).unwrap();
+    assert!(sz.quick_check(&archive_path).unwrap());
+    
+    let bytes = fs::read(&archive_path).unwrap();
+    let truncated = temp.path().join("truncated.7z");
+    fs::write(&truncated, &bytes[..bytes.len() - 10]).unwrap();
+    assert!(!sz.quick_check(&truncated).unwrap());
+    
+    let mut damaged = bytes.clone();
+    let last = damaged.len() - 3;
+    damaged[last] ^= 0xFF;
+    let damaged_path = temp.path().join("damaged.7z");
+    fs::write(&damaged_path, &damaged).unwrap();
+    assert!(!sz.quick_check(&damaged_path).unwrap());
+    
+    assert!(matches!(sz.quick_check(&file), Err(seven_zip::Error::InvalidArchive(_))));
+}
+
+#[test]
+fn test_preserve_root_names() {
+    let temp = TempDir::new().unwrap();
+    let a_docs = temp.path().join("a").join("docs");
+    let b_docs = temp.path().join("b").join("docs");
+    fs::create_dir_all(&a_docs).unwrap();
+    fs::create_dir_all(&b_docs).unwrap();
+    create_test_file(&a_docs, "index.md", "from a");
+    create_test_file(&b_docs, "index.md", "from b");
+    let notes = create_test_file(temp.path(), "notes.txt", "notes");
+    
+    let sz = SevenZip::new().unwrap();
+    let archive_path = temp.path().join("roots.7z");
+    let opts = CompressOptions::default().with_preserve_root_names(true);
+    sz.create_archive(&archive_path, &[&a_docs, &b_docs, &notes], CompressionLevel::Normal, Some(&opts)).unwrap();
+    
+    let mut names: Vec<String> = sz
+        .list(&archive_path, None)
+        .unwrap()
+        .into_iter()
+        .filter(|e| !e.is_directory)
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["docs/index.md", "docs_2/index.md", "notes.txt"]);
+    
+    let out = temp.path().join("out");
+    sz.extract(&archive_path, &out).unwrap();
+    assert_eq!(fs::read_to_string(out.join("docs/index.md")).unwrap(), "from a");
+    assert_eq!(fs::read_to_string(out.join("docs_2/index.md")).unwrap(), "from b");
+}
+
+#[test]
+fn test_decompress_truncated_stream() {
+    use rand::RngCore;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input.bin");
+    let mut data = vec![0u8; 256 * 1024];
+    rand::thread_rng().fill_bytes(&mut data);
+    fs::write(&input, &data).unwrap();
+    
+    let sz = SevenZip::new().unwrap();
+    let compressed = temp.path().join("input.lzma2");
+    sz.compress_file(&input, &compressed, CompressionLevel::Fast).unwrap();
+    
+    let written = Arc::new(AtomicU64::new(0));
+    let seen = Arc::clone(&written);
+    let restored = temp.path().join("restored.bin");
+    sz.decompress_file_with_options(&compressed, &restored, &SingleFileOptions::default(), Some(Box::new(move |done, _| {
+        seen.store(done, Ordering::SeqCst);
+    })))
+    .unwrap();
+    assert_eq!(written.load(Ordering::SeqCst), data.len() as u64);
+    assert_eq!(fs::read(&restored).unwrap(), data);
+    
+    let bytes = fs::read(&compressed).unwrap();
+    let truncated = temp.path().join("truncated.lzma2");
+    fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+    let partial = temp.path().join("partial.bin");
+    assert!(matches!(
+        sz.decompress_file(&truncated, &partial),
+        Err(seven_zip::Error::InvalidArchive(_))
+    ));
+    assert!(!partial.exists(), "partial output must be removed");
+}
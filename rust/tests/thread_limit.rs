@@ -0,0 +1,34 @@
+//! The global thread cap is process-wide state, so it is tested in its own
+//! test binary where it can't slow down or skew the other integration tests.
+
+use seven_zip::{global_thread_limit, set_global_thread_limit, CompressOptions, CompressionLevel, SevenZip};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_global_thread_limit_caps_operations() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("data");
+    fs::create_dir_all(&source).unwrap();
+    for i in 0..8 {
+        let content: String = (0..1_000_000).map(|n| char::from(b'a' + ((n * 7 + i) % 26) as u8)).collect();
+        fs::write(source.join(format!("file{}.txt", i)), content).unwrap();
+    }
+    
+    let sz = SevenZip::new().unwrap();
+    // A small dictionary splits the input into enough LZMA2 blocks for 8 threads
+    let opts = CompressOptions { dict_size: 64 * 1024, ..CompressOptions::default().with_threads(8) };
+    
+    set_global_thread_limit(2);
+    assert_eq!(global_thread_limit(), 2);
+    let capped = temp.path().join("capped.7z");
+    let report = sz.create_archive_with_report(&capped, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    assert!(report.threads_used <= 2, "used {} threads", report.threads_used);
+    assert!(report.threads_used >= 1);
+    
+    set_global_thread_limit(0);
+    assert_eq!(global_thread_limit(), 0);
+    let uncapped = temp.path().join("uncapped.7z");
+    let report = sz.create_archive_with_report(&uncapped, &[&source], CompressionLevel::Normal, Some(&opts)).unwrap();
+    assert!(report.threads_used > 2, "used {} threads", report.threads_used);
+}
@@ -5,9 +5,11 @@
  */
 
 #include "../include/7z_ffi.h"
+#include "ffi_internal.h"
 #include "Lzma2Enc.h"
 #include "7zCrc.h"
 #include "Alloc.h"
+#include "CpuArch.h"
 
 #include <stdio.h>
 #include <stdlib.h>
@@ -32,33 +34,6 @@
 /* 7z format constants */
 static const Byte k7zSignature[k7zSignature_Size] = {'7', 'z', 0xBC, 0xAF, 0x27, 0x1C};
 
-/* Property IDs for 7z headers */
-typedef enum {
-    k7zIdEnd = 0x00,
-    k7zIdHeader = 0x01,
-    k7zIdArchiveProperties = 0x02,
-    k7zIdMainStreamsInfo = 0x04,
-    k7zIdFilesInfo = 0x05,
-    k7zIdPackInfo = 0x06,
-    k7zIdUnpackInfo = 0x07,
-    k7zIdSubStreamsInfo = 0x08,
-    k7zIdSize = 0x09,
-    k7zIdCRC = 0x0A,
-    k7zIdFolder = 0x0B,
-    k7zIdCodersUnpackSize = 0x0C,
-    k7zIdNumUnpackStream = 0x0D,
-    k7zIdEmptyStream = 0x0E,
-    k7zIdEmptyFile = 0x0F,
-    k7zIdAnti = 0x10,
-    k7zIdName = 0x11,
-    k7zIdCTime = 0x12,
-    k7zIdATime = 0x13,
-    k7zIdMTime = 0x14,
-    k7zIdWinAttrib = 0x15,
-    k7zIdStartPos = 0x17,
-    k7zIdDummy = 0x19
-} E7zIdEnum;
-
 /* Test if data is compressible by checking entropy
  * Returns 1 if data appears compressible, 0 if it looks like random/encrypted data
  */
@@ -96,22 +71,65 @@ typedef struct {
     uint64_t size;
     uint64_t pack_size;  /* Support files >4GB */
     uint64_t mtime;
+    uint64_t ctime;      /* Creation (Windows) or inode change (POSIX) time */
+    uint64_t atime;
     uint32_t attrib;
     uint32_t crc;
     Byte* data;  /* Raw data (for in-memory compression) */
     int is_dir;
+    int method;  /* SevenZipMethod; files are grouped into one folder per method */
+    int has_inode;   /* dev/ino are set: a regular file with several links */
+    uint64_t dev;
+    uint64_t ino;
+    size_t link_to;  /* 1 + index of the entry holding this hard link's data (0 = none) */
 } SevenZFile;
 
+/* Slot of the builder's inode table: the first entry seen for a (dev, ino) */
+typedef struct {
+    uint64_t dev;
+    uint64_t ino;
+    size_t entry;    /* 1 + index into files (0 = empty slot) */
+} SevenZInodeSlot;
+
 /* Archive builder */
 typedef struct {
     SevenZFile* files;
     size_t file_count;
     size_t file_capacity;
     CLzma2EncProps props;
-    Byte lzma2_prop_byte;  /* LZMA2 property byte for header */
-    int use_copy_codec;    /* 1 = use Copy codec (store), 0 = use LZMA2 */
+    int use_copy_codec;    /* 1 = Copy is the default method (store), 0 = LZMA2 */
+    int special_files;     /* SevenZipSpecialFilePolicy */
+    uint64_t start_ms;     /* Monotonic time the operation started */
+    uint64_t time_budget_ms; /* 0 = no limit */
+    int store_all_times;   /* Write CTime and ATime as well as MTime */
+    int has_duplicates;    /* Identical files were grouped; always encode, never fall back to Copy */
+    int no_recursion;      /* Skip subdirectories of directory inputs */
+    int limit_file_size;   /* Skip regular files outside [min_file_size, max_file_size] */
+    uint64_t min_file_size;
+    uint64_t max_file_size;
+    int compress_header;   /* LZMA-pack the header behind a kEncodedHeader */
+    int detect_hardlinks;  /* Store further links to a file as hard link entries */
+    SevenZInodeSlot* inodes; /* Open-addressing table of multi-link files, by (dev, ino) */
+    size_t inode_capacity; /* Power of two, or 0 before the first multi-link file */
+    size_t inode_count;
+    const char* password;  /* Encrypt folders with 7zAES (NULL = no encryption) */
+    uint32_t kdf_cycles_power; /* Log2 of the key derivation rounds */
+    const uint8_t* encryption_key; /* Raw AES key used in place of password (NULL = derive one) */
 } SevenZArchiveBuilder;
 
+/* One folder (solid block) of the archive, holding every file of one method */
+typedef struct {
+    int use_copy_codec;    /* 1 = Copy codec, 0 = LZMA2 */
+    Byte lzma2_prop_byte;  /* LZMA2 property byte for header */
+    Byte* pack_data;       /* Packed stream */
+    size_t pack_size;
+    uint64_t unpack_size;
+    size_t num_files;      /* Number of non-directory entries in this folder */
+    int encrypted;         /* pack_data is 7zAES output over the codec's */
+    uint64_t coder_size;   /* Size of the codec's output, before AES padding */
+    Byte aes_props[SEVENZIP_AES_PROPS_SIZE];
+} SevenZFolder;
+
 /* Helper: Write number in variable-length encoding (7z format) 
  * 
  * 7z number encoding (from 7zArcIn.c ReadNumber):
@@ -125,7 +143,7 @@ typedef struct {
  * - 0xFE:      8 bytes (56 bits)
  * - 0xFF:      9 bytes (64 bits)
  */
-static void WriteNumber(Byte** buf, uint64_t value) {
+void sevenzip_write_number(uint8_t** buf, uint64_t value) {
     Byte* p = *buf;
     
     if (value < 0x80) {
@@ -214,6 +232,27 @@ static size_t GetNumberSize(uint64_t value) {
     return 9;
 }
 
+/* Helper: 7z attribute word for a stat() mode. Like 7-Zip on POSIX hosts,
+ * the mode goes in the high 16 bits behind FILE_ATTRIBUTE_UNIX_EXTENSION
+ * (0x8000), with the matching Windows flags in the low bits. */
+static uint32_t attrib_from_mode(unsigned mode) {
+#ifdef _WIN32
+    uint32_t attrib = (mode & _S_IFMT) == _S_IFDIR ? 0x10 : 0x20;  /* DIRECTORY : ARCHIVE */
+    if (!(mode & _S_IWRITE)) attrib |= 0x01;                       /* READONLY */
+    return attrib;
+#else
+    uint32_t attrib = 0x8000 | ((uint32_t)(mode & 0xFFFF) << 16);
+    if (S_ISDIR(mode)) attrib |= 0x10;
+    if (!(mode & S_IWUSR)) attrib |= 0x01;
+    return attrib;
+#endif
+}
+
+/* Helper: Whether a regular file of this size is left out by the size limits */
+static int file_size_excluded(const SevenZArchiveBuilder* builder, uint64_t size) {
+    return builder->limit_file_size && (size < builder->min_file_size || size > builder->max_file_size);
+}
+
 /* Helper: Add directory and its contents recursively */
 #ifdef _WIN32
 #include <windows.h>
@@ -235,6 +274,13 @@ static SevenZipErrorCode add_directory_recursive(
         if (strcmp(find_data.cFileName, ".") == 0 || strcmp(find_data.cFileName, "..") == 0) {
             continue;
         }
+        if (builder->no_recursion && (find_data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY)) {
+            continue;
+        }
+        if (!(find_data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY) &&
+            file_size_excluded(builder, ((uint64_t)find_data.nFileSizeHigh << 32) | find_data.nFileSizeLow)) {
+            continue;
+        }
         
         char full_path[4096];
         snprintf(full_path, sizeof(full_path), "%s\\%s", dir_path, find_data.cFileName);
@@ -264,6 +310,12 @@ static SevenZipErrorCode add_directory_recursive(
         ull.LowPart = ft.dwLowDateTime;
         ull.HighPart = ft.dwHighDateTime;
         file->mtime = ull.QuadPart;
+        ull.LowPart = find_data.ftCreationTime.dwLowDateTime;
+        ull.HighPart = find_data.ftCreationTime.dwHighDateTime;
+        file->ctime = ull.QuadPart;
+        ull.LowPart = find_data.ftLastAccessTime.dwLowDateTime;
+        ull.HighPart = find_data.ftLastAccessTime.dwHighDateTime;
+        file->atime = ull.QuadPart;
         file->attrib = find_data.dwFileAttributes;
         file->is_dir = (find_data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY) != 0;
         
@@ -309,7 +361,100 @@ static SevenZipErrorCode add_directory_recursive(
     return SEVENZIP_OK;
 }
 #else
+/*
+ * Helper: Decide whether a FIFO, device or socket is added (as an empty
+ * entry, never opened) or skipped. Returns 1 to add, 0 to skip and -1 when
+ * the policy makes it an error.
+ */
+static int keep_special_file(const char* path, int policy) {
+    if (policy == SEVENZIP_SPECIAL_METADATA_ONLY) {
+        return 1;
+    }
+    if (policy == SEVENZIP_SPECIAL_ERROR) {
+        char message[512];
+        snprintf(message, sizeof(message), "Special file (FIFO, device or socket) in input: %s", path);
+        sevenzip_set_error_internal(SEVENZIP_ERROR_INVALID_PARAM, message, path, -1,
+                                    "Remove it from the input or choose another special file policy");
+        return -1;
+    }
+    fprintf(stderr, "WARNING: Skipping special file %s\n", path);
+    return 0;
+}
+
 #include <dirent.h>
+static SevenZipErrorCode add_directory_recursive(
+    SevenZArchiveBuilder* builder,
+    const char* dir_path,
+    const char* base_path
+);
+
+#ifndef _WIN32
+/* Helper: Slot of the inode table holding (dev, ino), or the empty slot where
+ * it belongs. The table must have at least one empty slot. */
+static SevenZInodeSlot* find_inode_slot(SevenZInodeSlot* slots, size_t capacity,
+                                        uint64_t dev, uint64_t ino) {
+    uint64_t h = (ino ^ (dev * 0x9E3779B97F4A7C15ULL)) * 0xBF58476D1CE4E5B9ULL;
+    size_t i = (size_t)(h ^ (h >> 31)) & (capacity - 1);
+    while (slots[i].entry && (slots[i].dev != dev || slots[i].ino != ino)) {
+        i = (i + 1) & (capacity - 1);
+    }
+    return &slots[i];
+}
+
+/* Helper: Double the inode table (or allocate it), keeping it at most half full */
+static int grow_inode_table(SevenZArchiveBuilder* builder) {
+    size_t capacity = builder->inode_capacity ? builder->inode_capacity * 2 : 64;
+    SevenZInodeSlot* slots = (SevenZInodeSlot*)calloc(capacity, sizeof(SevenZInodeSlot));
+    if (!slots) {
+        return 0;
+    }
+    for (size_t i = 0; i < builder->inode_capacity; i++) {
+        const SevenZInodeSlot* old = &builder->inodes[i];
+        if (old->entry) {
+            *find_inode_slot(slots, capacity, old->dev, old->ino) = *old;
+        }
+    }
+    free(builder->inodes);
+    builder->inodes = slots;
+    builder->inode_capacity = capacity;
+    return 1;
+}
+#endif
+
+/* Helper: With detect_hardlinks, record the inode of a file that has several
+ * links. Returns 1 if an earlier entry already holds its data, making `file`
+ * a hard link entry that needs no data of its own, and -1 when out of memory.
+ * `file` must be the last entry added to the builder. */
+static int note_hardlink(SevenZArchiveBuilder* builder, SevenZFile* file, const struct STAT* st) {
+#ifdef _WIN32
+    (void)builder;
+    (void)file;
+    (void)st;
+    return 0;
+#else
+    if (!builder->detect_hardlinks || !S_ISREG(st->st_mode) || st->st_nlink < 2) {
+        return 0;
+    }
+    file->has_inode = 1;
+    file->dev = (uint64_t)st->st_dev;
+    file->ino = (uint64_t)st->st_ino;
+    if ((builder->inode_count + 1) * 2 > builder->inode_capacity && !grow_inode_table(builder)) {
+        return -1;
+    }
+    SevenZInodeSlot* slot = find_inode_slot(builder->inodes, builder->inode_capacity,
+                                            file->dev, file->ino);
+    if (slot->entry) {
+        file->link_to = slot->entry;
+        return 1;
+    }
+    slot->dev = file->dev;
+    slot->ino = file->ino;
+    slot->entry = builder->file_count;
+    builder->inode_count++;
+    return 0;
+#endif
+}
+
 static SevenZipErrorCode add_directory_recursive(
     SevenZArchiveBuilder* builder,
     const char* dir_path,
@@ -335,6 +480,21 @@ static SevenZipErrorCode add_directory_recursive(
             closedir(dir);
             return SEVENZIP_ERROR_OPEN_FILE;
         }
+        if (builder->no_recursion && S_ISDIR(st.st_mode)) {
+            continue;
+        }
+        if (S_ISREG(st.st_mode) && file_size_excluded(builder, (uint64_t)st.st_size)) {
+            continue;
+        }
+        
+        if (!S_ISDIR(st.st_mode) && !S_ISREG(st.st_mode)) {
+            int keep = keep_special_file(full_path, builder->special_files);
+            if (keep < 0) {
+                closedir(dir);
+                return SEVENZIP_ERROR_INVALID_PARAM;
+            }
+            if (!keep) continue;
+        }
         
         /* Expand array if needed */
         if (builder->file_count >= builder->file_capacity) {
@@ -356,9 +516,17 @@ static SevenZipErrorCode add_directory_recursive(
         while (*rel_path == '/') rel_path++;
         file->name = strdup(rel_path);
         file->mtime = (uint64_t)st.st_mtime * 10000000ULL + 116444736000000000ULL;
-        file->attrib = (uint32_t)st.st_mode;
+        file->ctime = (uint64_t)st.st_ctime * 10000000ULL + 116444736000000000ULL;
+        file->atime = (uint64_t)st.st_atime * 10000000ULL + 116444736000000000ULL;
+        file->attrib = attrib_from_mode((unsigned)st.st_mode);
         file->is_dir = S_ISDIR(st.st_mode);
         
+        int linked = note_hardlink(builder, file, &st);
+        if (linked < 0) {
+            closedir(dir);
+            return SEVENZIP_ERROR_MEMORY;
+        }
+        
         if (file->is_dir) {
             /* Recursively add directory contents */
             SevenZipErrorCode err = add_directory_recursive(builder, full_path, base_path);
@@ -366,7 +534,7 @@ static SevenZipErrorCode add_directory_recursive(
                 closedir(dir);
                 return err;
             }
-        } else if (S_ISREG(st.st_mode)) {
+        } else if (S_ISREG(st.st_mode) && !linked) {
             /* Read file data */
             FILE* f = fopen(full_path, "rb");
             if (!f) {
@@ -399,33 +567,585 @@ static SevenZipErrorCode add_directory_recursive(
 }
 #endif
 
-/* Helper: Compress all files into a single LZMA2 stream */
-static SevenZipErrorCode compress_all_files(
+/* Helper: Turn the entries found by note_hardlink into hard link entries,
+ * once entry names are final: the data is SEVENZIP_HARDLINK_TAG and the name
+ * of the entry holding the file's content, and SEVENZIP_ATTRIB_HARDLINK marks
+ * the entry. Must run before anything reorders or drops file slots. */
+static SevenZipErrorCode resolve_hardlinks(SevenZArchiveBuilder* builder) {
+    const size_t tag_len = sizeof(SEVENZIP_HARDLINK_TAG) - 1;
+    for (size_t i = 0; i < builder->file_count; i++) {
+        SevenZFile* file = &builder->files[i];
+        if (!file->link_to) continue;
+        const char* target = builder->files[file->link_to - 1].name;
+        size_t len = tag_len + strlen(target);
+        file->data = (Byte*)malloc(len);
+        if (!file->data) {
+            return SEVENZIP_ERROR_MEMORY;
+        }
+        memcpy(file->data, SEVENZIP_HARDLINK_TAG, tag_len);
+        memcpy(file->data + tag_len, target, len - tag_len);
+        file->size = len;
+        file->pack_size = len;
+        file->attrib |= SEVENZIP_ATTRIB_HARDLINK;
+        file->link_to = 0;
+    }
+    return SEVENZIP_OK;
+}
+
+/* Helper: qsort comparator for an array of names */
+static int compare_names(const void* a, const void* b) {
+    return strcmp(*(const char* const*)a, *(const char* const*)b);
+}
+
+/* Helper: Whether any of the sorted names starts with the len bytes of prefix.
+ * Names sharing a prefix are adjacent, so a binary search finds the first. */
+static int has_name_with_prefix(const char** sorted, size_t count, const char* prefix, size_t len) {
+    size_t lo = 0, hi = count;
+    while (lo < hi) {
+        size_t mid = lo + (hi - lo) / 2;
+        if (strncmp(sorted[mid], prefix, len) < 0) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    return lo < count && strncmp(sorted[lo], prefix, len) == 0;
+}
+
+/* Helper: Remove directory entries that have no files anywhere beneath them */
+static SevenZipErrorCode drop_empty_directories(SevenZArchiveBuilder* builder) {
+    /* Sort the file names once, so each directory is one lookup per separator */
+    const char** file_names = (const char**)malloc((builder->file_count + 1) * sizeof(char*));
+    if (!file_names) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    size_t num_file_names = 0;
+    for (size_t i = 0; i < builder->file_count; i++) {
+        if (!builder->files[i].is_dir) file_names[num_file_names++] = builder->files[i].name;
+    }
+    qsort(file_names, num_file_names, sizeof(char*), compare_names);
+    
+    size_t kept = 0;
+    for (size_t i = 0; i < builder->file_count; i++) {
+        SevenZFile* entry = &builder->files[i];
+        int keep = !entry->is_dir;
+        
+        if (!keep) {
+            size_t len = strlen(entry->name);
+            char* prefix = (char*)malloc(len + 2);
+            if (!prefix) {
+                free(file_names);
+                return SEVENZIP_ERROR_MEMORY;
+            }
+            memcpy(prefix, entry->name, len);
+            prefix[len + 1] = 0;
+            prefix[len] = '/';
+            keep = has_name_with_prefix(file_names, num_file_names, prefix, len + 1);
+            prefix[len] = '\\';
+            keep = keep || has_name_with_prefix(file_names, num_file_names, prefix, len + 1);
+            free(prefix);
+        }
+        
+        if (keep) {
+            builder->files[kept++] = *entry;
+        } else {
+            free(entry->name);
+            free(entry->data);
+        }
+    }
+    builder->file_count = kept;
+    free(file_names);
+    return SEVENZIP_OK;
+}
+
+/* Helper: Rename the entries added for one input. A file input takes `name`
+ * as its entry name; a directory input nests its contents under `name`. */
+static SevenZipErrorCode rename_input_entries(
     SevenZArchiveBuilder* builder,
-    Byte** output,
-    size_t* output_size
+    size_t first,
+    const char* name,
+    int is_dir_input
 ) {
+    for (size_t i = first; i < builder->file_count; i++) {
+        SevenZFile* entry = &builder->files[i];
+        char* renamed;
+        if (!is_dir_input) {
+            renamed = strdup(name);
+        } else if (*name) {
+            size_t len = strlen(name) + strlen(entry->name) + 2;
+            renamed = (char*)malloc(len);
+            if (renamed) snprintf(renamed, len, "%s/%s", name, entry->name);
+        } else {
+            continue;
+        }
+        if (!renamed) return SEVENZIP_ERROR_MEMORY;
+        free(entry->name);
+        entry->name = renamed;
+    }
+    return SEVENZIP_OK;
+}
+
+/* Helper: Reduce every file to its base name and drop directory entries,
+ * failing if two files end up with the same name */
+static SevenZipErrorCode flatten_entry_names(SevenZArchiveBuilder* builder) {
+    size_t kept = 0;
+    for (size_t i = 0; i < builder->file_count; i++) {
+        SevenZFile* entry = &builder->files[i];
+        if (entry->is_dir) {
+            free(entry->name);
+            free(entry->data);
+            continue;
+        }
+        const char* base = entry->name;
+        for (const char* c = entry->name; *c; c++) {
+            if (*c == '/' || *c == '\\') base = c + 1;
+        }
+        memmove(entry->name, base, strlen(base) + 1);
+        builder->files[kept++] = *entry;
+    }
+    builder->file_count = kept;
+    
+    for (size_t i = 0; i < builder->file_count; i++) {
+        for (size_t j = i + 1; j < builder->file_count; j++) {
+            if (strcmp(builder->files[i].name, builder->files[j].name) == 0) {
+                char message[512];
+                snprintf(message, sizeof(message), "Two input files are both named %s", builder->files[i].name);
+                sevenzip_set_error_internal(SEVENZIP_ERROR_INVALID_PARAM, message, builder->files[i].name, -1,
+                                            "Rename one of them or keep directory names in the archive");
+                return SEVENZIP_ERROR_INVALID_PARAM;
+            }
+        }
+    }
+    return SEVENZIP_OK;
+}
+
+/* Helper: Extension of an entry name (without the dot), or NULL if it has none */
+static const char* entry_extension(const char* name) {
+    const char* base = name;
+    for (const char* c = name; *c; c++) {
+        if (*c == '/' || *c == '\\') base = c + 1;
+    }
+    const char* dot = strrchr(base, '.');
+    /* A leading dot marks a hidden file, not an extension */
+    return (dot && dot != base) ? dot + 1 : NULL;
+}
+
+/* Helper: ASCII case-insensitive string equality */
+static int ascii_equal_nocase(const char* a, const char* b) {
+    for (; *a && *b; a++, b++) {
+        char ca = (*a >= 'A' && *a <= 'Z') ? (char)(*a + 32) : *a;
+        char cb = (*b >= 'A' && *b <= 'Z') ? (char)(*b + 32) : *b;
+        if (ca != cb) return 0;
+    }
+    return *a == *b;
+}
+
+/* Helper: Pick each file's method from the extension overrides, then order
+ * the files so that each method's files are contiguous (LZMA2 first), since
+ * 7z assigns file streams to folders in entry order */
+static SevenZipErrorCode assign_file_methods(
+    SevenZArchiveBuilder* builder,
+    const SevenZipCompressOptions* opts
+) {
+    int default_method = builder->use_copy_codec ? SEVENZIP_METHOD_COPY : opts->method;
+    int has_copy = 0, has_lzma2 = 0;
+    
+    for (size_t i = 0; i < builder->file_count; i++) {
+        SevenZFile* file = &builder->files[i];
+        if (file->is_dir) continue;
+        
+        file->method = default_method;
+        const char* ext = entry_extension(file->name);
+        if (ext && opts->method_extensions && opts->extension_methods) {
+            for (size_t j = 0; j < opts->method_override_count; j++) {
+                if (opts->method_extensions[j] && ascii_equal_nocase(ext, opts->method_extensions[j])) {
+                    file->method = opts->extension_methods[j];
+                    break;
+                }
+            }
+        }
+        
+        if (file->method == SEVENZIP_METHOD_COPY) has_copy = 1;
+        else if (file->method == SEVENZIP_METHOD_LZMA2) has_lzma2 = 1;
+        else return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    if (!has_copy || !has_lzma2) return SEVENZIP_OK;
+    
+    /* Stable partition of the file slots; directories keep their positions */
+    SevenZFile* ordered = (SevenZFile*)malloc(builder->file_count * sizeof(SevenZFile));
+    if (!ordered) return SEVENZIP_ERROR_MEMORY;
+    size_t n = 0;
+    for (int method = SEVENZIP_METHOD_LZMA2; method <= SEVENZIP_METHOD_COPY; method++) {
+        for (size_t i = 0; i < builder->file_count; i++) {
+            if (!builder->files[i].is_dir && builder->files[i].method == method) {
+                ordered[n++] = builder->files[i];
+            }
+        }
+    }
+    n = 0;
+    for (size_t i = 0; i < builder->file_count; i++) {
+        if (!builder->files[i].is_dir) {
+            builder->files[i] = ordered[n++];
+        }
+    }
+    free(ordered);
+    return SEVENZIP_OK;
+}
+
+/* Sort key for the solid stream order */
+typedef struct {
+    const SevenZFile* file;
+    const char* ext;     /* "" when the name has no extension */
+    int order;           /* SevenZipSortOrder */
+    size_t index;        /* Original slot, the final tie-breaker */
+} SortSlot;
+
+/* Helper: ASCII case-insensitive string ordering */
+static int ascii_compare_nocase(const char* a, const char* b) {
+    for (; *a && *b; a++, b++) {
+        char ca = (*a >= 'A' && *a <= 'Z') ? (char)(*a + 32) : *a;
+        char cb = (*b >= 'A' && *b <= 'Z') ? (char)(*b + 32) : *b;
+        if (ca != cb) return (unsigned char)ca < (unsigned char)cb ? -1 : 1;
+    }
+    return (unsigned char)*a - (unsigned char)*b;
+}
+
+static int compare_sort_slots(const void* a, const void* b) {
+    const SortSlot* x = (const SortSlot*)a;
+    const SortSlot* y = (const SortSlot*)b;
+    int cmp = 0;
+    switch (x->order) {
+        case SEVENZIP_SORT_BY_EXTENSION:
+            cmp = ascii_compare_nocase(x->ext, y->ext);
+            if (cmp == 0) cmp = strcmp(x->file->name, y->file->name);
+            break;
+        case SEVENZIP_SORT_BY_NAME:
+            cmp = strcmp(x->file->name, y->file->name);
+            break;
+        case SEVENZIP_SORT_BY_SIZE:
+            if (x->file->size != y->file->size) cmp = x->file->size < y->file->size ? -1 : 1;
+            break;
+    }
+    if (cmp != 0) return cmp;
+    return x->index < y->index ? -1 : (x->index > y->index);
+}
+
+/* Helper: Reorder the file slots by sort_order so that similar files sit
+ * next to each other in the solid stream; directories keep their positions */
+static SevenZipErrorCode sort_file_slots(SevenZArchiveBuilder* builder, int order) {
+    if (order == SEVENZIP_SORT_NONE) return SEVENZIP_OK;
+    if (order < SEVENZIP_SORT_NONE || order > SEVENZIP_SORT_BY_SIZE) return SEVENZIP_ERROR_INVALID_PARAM;
+    
+    size_t count = builder->file_count;
+    SortSlot* slots = (SortSlot*)malloc((count ? count : 1) * sizeof(SortSlot));
+    SevenZFile* ordered = (SevenZFile*)malloc((count ? count : 1) * sizeof(SevenZFile));
+    if (!slots || !ordered) {
+        free(slots);
+        free(ordered);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    size_t n = 0;
+    for (size_t i = 0; i < count; i++) {
+        if (builder->files[i].is_dir) continue;
+        const char* ext = entry_extension(builder->files[i].name);
+        slots[n].file = &builder->files[i];
+        slots[n].ext = ext ? ext : "";
+        slots[n].order = order;
+        slots[n].index = i;
+        n++;
+    }
+    qsort(slots, n, sizeof(SortSlot), compare_sort_slots);
+    for (size_t k = 0; k < n; k++) {
+        ordered[k] = *slots[k].file;
+    }
+    n = 0;
+    for (size_t i = 0; i < count; i++) {
+        if (!builder->files[i].is_dir) {
+            builder->files[i] = ordered[n++];
+        }
+    }
+    free(slots);
+    free(ordered);
+    return SEVENZIP_OK;
+}
+
+/* Largest dictionary used to reach back over a duplicate (Ultra's 64 MB). The
+ * encoder needs about 11x this, so larger duplicates are left as they are. */
+#define DEDUP_MAX_DICT_SIZE (1u << 26)
+
+/* Sort key for finding identical files */
+typedef struct {
+    uint64_t size;
+    uint32_t crc;
+    size_t index;
+} DuplicateKey;
+
+static int compare_duplicate_keys(const void* a, const void* b) {
+    const DuplicateKey* x = (const DuplicateKey*)a;
+    const DuplicateKey* y = (const DuplicateKey*)b;
+    if (x->size != y->size) return x->size < y->size ? -1 : 1;
+    if (x->crc != y->crc) return x->crc < y->crc ? -1 : 1;
+    return x->index < y->index ? -1 : (x->index > y->index);
+}
+
+/* Helper: Move each file's identical copies right behind it and make the
+ * dictionary reach back over the largest one, so a solid LZMA2 stream stores
+ * every repeat as a few long matches. Standard 7z readers can't share one
+ * stream between entries (hard link entries are this library's extension),
+ * so this keeps dedup archives readable everywhere. Files larger than
+ * DEDUP_MAX_DICT_SIZE are skipped with a warning. */
+static SevenZipErrorCode group_duplicate_files(SevenZArchiveBuilder* builder) {
+    size_t count = builder->file_count;
+    DuplicateKey* keys = (DuplicateKey*)malloc((count ? count : 1) * sizeof(DuplicateKey));
+    size_t* next_copy = (size_t*)malloc((count ? count : 1) * sizeof(size_t));
+    char* is_copy = (char*)calloc(count ? count : 1, 1);
+    if (!keys || !next_copy || !is_copy) {
+        free(keys);
+        free(next_copy);
+        free(is_copy);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    size_t num_keys = 0;
+    for (size_t i = 0; i < count; i++) {
+        SevenZFile* file = &builder->files[i];
+        next_copy[i] = (size_t)-1;
+        if (file->is_dir || !file->data || file->size == 0) continue;
+        keys[num_keys].size = file->size;
+        keys[num_keys].crc = CrcCalc(file->data, (size_t)file->size);
+        keys[num_keys].index = i;
+        num_keys++;
+    }
+    qsort(keys, num_keys, sizeof(DuplicateKey), compare_duplicate_keys);
+    
+    /* Chain the real copies of each run's first file (CRCs can collide) */
+    uint64_t largest = 0;
+    size_t too_large = 0;
+    for (size_t a = 0; a < num_keys; ) {
+        size_t b = a + 1;
+        while (b < num_keys && keys[b].size == keys[a].size && keys[b].crc == keys[a].crc) b++;
+        const SevenZFile* first = &builder->files[keys[a].index];
+        if (first->size > DEDUP_MAX_DICT_SIZE) {
+            too_large += b - a - 1;
+            a = b;
+            continue;
+        }
+        size_t tail = keys[a].index;
+        for (size_t k = a + 1; k < b; k++) {
+            size_t index = keys[k].index;
+            if (memcmp(builder->files[index].data, first->data, (size_t)first->size) == 0) {
+                next_copy[tail] = index;
+                tail = index;
+                is_copy[index] = 1;
+                if (first->size > largest) largest = first->size;
+            }
+        }
+        a = b;
+    }
+    free(keys);
+    if (too_large > 0) {
+        fprintf(stderr, "WARNING: Not deduplicating %zu possible copies larger than %u MB\n",
+                too_large, DEDUP_MAX_DICT_SIZE >> 20);
+    }
+    
+    SevenZipErrorCode result = SEVENZIP_OK;
+    if (largest > 0) {
+        /* Stable reorder of the file slots; directories keep their positions */
+        SevenZFile* ordered = (SevenZFile*)malloc(count * sizeof(SevenZFile));
+        if (!ordered) {
+            result = SEVENZIP_ERROR_MEMORY;
+        } else {
+            size_t n = 0;
+            for (size_t i = 0; i < count; i++) {
+                if (builder->files[i].is_dir || is_copy[i]) continue;
+                for (size_t j = i; j != (size_t)-1; j = next_copy[j]) {
+                    ordered[n++] = builder->files[j];
+                }
+            }
+            n = 0;
+            for (size_t i = 0; i < count; i++) {
+                if (!builder->files[i].is_dir) {
+                    builder->files[i] = ordered[n++];
+                }
+            }
+            free(ordered);
+            
+            /* A copy starts right after the original, so it is in reach once
+             * the dictionary covers the file; split blocks would lose that */
+            if (builder->props.lzmaProps.dictSize < largest) {
+                builder->props.lzmaProps.dictSize = (UInt32)largest;
+            }
+            builder->props.blockSize = LZMA2_ENC_PROPS_BLOCK_SIZE_SOLID;
+            builder->has_duplicates = 1;
+        }
+    }
+    
+    free(next_copy);
+    free(is_copy);
+    return result;
+}
+
+/* Helper: Parse a whole decimal number within [min, max] */
+static int parse_prop_number(const char* value, unsigned long min, unsigned long max, unsigned long* out) {
+    char* end;
+    if (!value || !*value) return 0;
+    unsigned long n = strtoul(value, &end, 10);
+    if (*end != '\0' || n < min || n > max) return 0;
+    *out = n;
+    return 1;
+}
+
+/* Helper: Record an INVALID_PARAM error for a codec property */
+static SevenZipErrorCode codec_property_error(const char* key, const char* value, const char* detail) {
+    char message[256];
+    snprintf(message, sizeof(message), "Invalid codec property %s=%s: %s", key, value ? value : "", detail);
+    sevenzip_set_error_internal(SEVENZIP_ERROR_INVALID_PARAM, message, NULL, -1,
+                                "Supported properties: mf, fb, lc, lp, pb, mc, a");
+    return SEVENZIP_ERROR_INVALID_PARAM;
+}
+
+/* Helper: Apply 7-Zip style LZMA2 properties (-m0=lzma2:fb=273 etc.) on top of
+ * the level defaults. Called before normalizing, so unset fields still follow
+ * the level. */
+static SevenZipErrorCode apply_codec_properties(CLzmaEncProps* props, const SevenZipCompressOptions* opts) {
+    for (size_t i = 0; i < opts->codec_property_count; i++) {
+        const char* key = opts->codec_property_keys[i];
+        const char* value = opts->codec_property_values[i];
+        unsigned long n;
+        
+        if (!key) return codec_property_error("", value, "missing key");
+        
+        if (ascii_equal_nocase(key, "mf")) {
+            const char* v = value ? value : "";
+            if (ascii_equal_nocase(v, "bt2")) { props->btMode = 1; props->numHashBytes = 2; }
+            else if (ascii_equal_nocase(v, "bt3")) { props->btMode = 1; props->numHashBytes = 3; }
+            else if (ascii_equal_nocase(v, "bt4")) { props->btMode = 1; props->numHashBytes = 4; }
+            else if (ascii_equal_nocase(v, "hc4")) { props->btMode = 0; props->numHashBytes = 4; }
+            else return codec_property_error(key, value, "expected bt2, bt3, bt4 or hc4");
+        } else if (ascii_equal_nocase(key, "fb")) {
+            if (!parse_prop_number(value, 5, 273, &n)) return codec_property_error(key, value, "expected 5-273");
+            props->fb = (int)n;
+        } else if (ascii_equal_nocase(key, "lc")) {
+            if (!parse_prop_number(value, 0, 4, &n)) return codec_property_error(key, value, "expected 0-4");
+            props->lc = (int)n;
+        } else if (ascii_equal_nocase(key, "lp")) {
+            if (!parse_prop_number(value, 0, 4, &n)) return codec_property_error(key, value, "expected 0-4");
+            props->lp = (int)n;
+        } else if (ascii_equal_nocase(key, "pb")) {
+            if (!parse_prop_number(value, 0, 4, &n)) return codec_property_error(key, value, "expected 0-4");
+            props->pb = (int)n;
+        } else if (ascii_equal_nocase(key, "mc")) {
+            if (!parse_prop_number(value, 1, 1UL << 30, &n)) return codec_property_error(key, value, "expected 1-1073741824");
+            props->mc = (UInt32)n;
+        } else if (ascii_equal_nocase(key, "a")) {
+            if (!parse_prop_number(value, 0, 1, &n)) return codec_property_error(key, value, "expected 0 (fast) or 1 (normal)");
+            props->algo = (int)n;
+        } else {
+            char message[256];
+            snprintf(message, sizeof(message), "Unknown codec property: %s", key);
+            sevenzip_set_error_internal(SEVENZIP_ERROR_INVALID_PARAM, message, NULL, -1,
+                                        "Supported properties: mf, fb, lc, lp, pb, mc, a");
+            return SEVENZIP_ERROR_INVALID_PARAM;
+        }
+    }
+    
+    /* LZMA2 only allows lc + lp <= 4 */
+    if (props->lc >= 0 && props->lp >= 0 && props->lc + props->lp > 4) {
+        return codec_property_error("lc+lp", NULL, "LZMA2 requires lc + lp <= 4");
+    }
+    return SEVENZIP_OK;
+}
+
+/* Milliseconds on a monotonic clock, for time budgets */
+static uint64_t monotonic_ms(void) {
+#ifdef _WIN32
+    return (uint64_t)GetTickCount64();
+#else
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return (uint64_t)ts.tv_sec * 1000u + (uint64_t)ts.tv_nsec / 1000000u;
+#endif
+}
+
+/* Encoder progress that gives up once the folder is projected to finish
+ * past the time budget. May be called from encoder worker threads. */
+typedef struct {
+    ICompressProgress vt;
+    uint64_t encode_start_ms;
+    uint64_t deadline_ms;    /* start_ms + budget */
+    uint64_t total_size;     /* Input size of the folder */
+} BudgetProgress;
+
+static SRes budget_progress(ICompressProgressPtr pp, UInt64 in_size, UInt64 out_size) {
+    const BudgetProgress* p = Z7_CONTAINER_FROM_VTBL(pp, const BudgetProgress, vt);
+    (void)out_size;
+    
+    uint64_t now = monotonic_ms();
+    if (now >= p->deadline_ms) return SZ_ERROR_PROGRESS;
+    if (in_size == (UInt64)(Int64)-1 || in_size == 0) return SZ_OK;
+    
+    /* Assume the rest encodes at the rate seen so far */
+    double rate = (double)(now - p->encode_start_ms) / (double)in_size;
+    double projected = (double)now + rate * (double)(p->total_size - in_size);
+    return projected > (double)p->deadline_ms ? SZ_ERROR_PROGRESS : SZ_OK;
+}
+
+/* Helper: Approximate encoder working set for normalized LZMA2 props, after
+ * 7-Zip's rule of thumb: the match finder of each block coder takes about
+ * 11.5x the dictionary with binary trees (window 1.5x, tree 8x, hash 2x) or
+ * 7.5x with hash chains, and multithreaded LZMA2 buffers a block of input and
+ * output per block thread */
+static uint64_t lzma2_encoder_memory(const CLzma2EncProps* props) {
+    uint64_t dict = props->lzmaProps.dictSize;
+    uint64_t coder = props->lzmaProps.btMode ? dict * 23 / 2 : dict * 15 / 2;
+    coder += (uint64_t)1 << 20;  /* Price tables and range coder buffer */
+    if (props->lzmaProps.numThreads > 1) {
+        coder += (uint64_t)4 << 20;  /* Match finder thread's hash blocks */
+    }
+    
+    int block_threads = props->numBlockThreads_Reduced > 1 ? props->numBlockThreads_Reduced : 1;
+    uint64_t total = coder * (uint64_t)block_threads;
+    if (block_threads > 1) {
+        total += 2 * props->blockSize * (uint64_t)block_threads;
+    }
+    return total;
+}
+
+/* Helper: Compress all files of one method into a single folder stream */
+static SevenZipErrorCode compress_folder(
+    SevenZArchiveBuilder* builder,
+    int method,
+    SevenZFolder* folder
+) {
+    folder->use_copy_codec = (method == SEVENZIP_METHOD_COPY);
+    folder->lzma2_prop_byte = 0;
+    folder->pack_data = NULL;
+    folder->pack_size = 0;
+    folder->encrypted = 0;
+    
     /* Calculate total input size */
     size_t total_input_size = 0;
     for (size_t i = 0; i < builder->file_count; i++) {
-        if (!builder->files[i].is_dir && builder->files[i].data) {
+        if (!builder->files[i].is_dir && builder->files[i].method == method && builder->files[i].data) {
             total_input_size += builder->files[i].size;
         }
     }
+    folder->unpack_size = total_input_size;
     
     if (total_input_size == 0) {
-        *output = NULL;
-        *output_size = 0;
+        if (sevenzip_last_threads_used() == 0) sevenzip_set_last_threads_used(1);
         return SEVENZIP_OK;
     }
     
-    /* Concatenate all file data into single buffer */
+    /* Concatenate the folder's file data into single buffer */
     Byte* combined = (Byte*)malloc(total_input_size);
     if (!combined) return SEVENZIP_ERROR_MEMORY;
     
     size_t offset = 0;
     for (size_t i = 0; i < builder->file_count; i++) {
-        if (!builder->files[i].is_dir && builder->files[i].data) {
+        if (!builder->files[i].is_dir && builder->files[i].method == method && builder->files[i].data) {
             memcpy(combined + offset, builder->files[i].data, builder->files[i].size);
             /* Calculate individual CRC while we're at it */
             builder->files[i].crc = CrcCalc(builder->files[i].data, builder->files[i].size);
@@ -436,19 +1156,20 @@ static SevenZipErrorCode compress_all_files(
     /* ADAPTIVE COMPRESSION: Check if data is compressible */
     /* For large data (>1MB), if it looks like random/encrypted data, use Copy codec */
     /* Also use Copy codec if explicitly requested (Store mode) */
-    if (builder->use_copy_codec || 
-        (total_input_size > 1024 * 1024 && !is_data_compressible(combined, total_input_size))) {
+    /* Grouped duplicates compress well even when each copy alone does not */
+    if (folder->use_copy_codec || 
+        (total_input_size > 1024 * 1024 && !builder->has_duplicates &&
+         !is_data_compressible(combined, total_input_size))) {
+        if (sevenzip_last_threads_used() == 0) sevenzip_set_last_threads_used(1);
         /* Use Copy codec - return raw data directly (fastest possible) */
-        builder->use_copy_codec = 1;
-        builder->lzma2_prop_byte = 0;  /* Not used for Copy codec */
+        folder->use_copy_codec = 1;
         
-        *output = combined;  /* Return concatenated raw data */
-        *output_size = total_input_size;
+        folder->pack_data = combined;  /* Return concatenated raw data */
+        folder->pack_size = total_input_size;
         return SEVENZIP_OK;
     }
     
-    builder->use_copy_codec = 0;
-    
+
     /* Create LZMA2 encoder */
     CLzma2EncHandle enc = Lzma2Enc_Create(&g_Alloc, &g_Alloc);
     if (!enc) {
@@ -464,7 +1185,23 @@ static SevenZipErrorCode compress_all_files(
     }
     
     /* Get LZMA2 property byte for header */
-    builder->lzma2_prop_byte = Lzma2Enc_WriteProperties(enc);
+    folder->lzma2_prop_byte = Lzma2Enc_WriteProperties(enc);
+    
+    /* Record the thread count LZMA2 will really use: block threads are
+     * capped by the number of blocks the input splits into */
+    {
+        CLzma2EncProps effective = builder->props;
+        effective.lzmaProps.reduceSize = total_input_size;
+        Lzma2EncProps_Normalize(&effective);
+        sevenzip_set_last_threads_used(effective.numTotalThreads > 0 ? (uint32_t)effective.numTotalThreads : 1);
+        
+        /* Folders are compressed one after another, so the peak is the largest */
+        uint64_t memory = lzma2_encoder_memory(&effective);
+        if (memory > sevenzip_last_peak_memory()) {
+            sevenzip_set_last_peak_memory(memory);
+        }
+        sevenzip_set_last_dict_size(builder->props.lzmaProps.dictSize);
+    }
     
     /* Allocate output buffer (compressed data) */
     size_t out_size = total_input_size + total_input_size / 3 + 128;
@@ -476,12 +1213,23 @@ static SevenZipErrorCode compress_all_files(
     }
     
     /* Compress all data into single stream */
+    BudgetProgress budget;
+    budget.vt.Progress = budget_progress;
+    budget.encode_start_ms = monotonic_ms();
+    budget.deadline_ms = builder->start_ms + builder->time_budget_ms;
+    budget.total_size = total_input_size;
+    
     res = Lzma2Enc_Encode2(enc, NULL, out_buf, &out_size,
-                           NULL, combined, total_input_size, NULL);
+                           NULL, combined, total_input_size,
+                           builder->time_budget_ms ? &budget.vt : NULL);
     
     Lzma2Enc_Destroy(enc);
     free(combined);
     
+    if (res == SZ_ERROR_PROGRESS) {
+        free(out_buf);
+        return SEVENZIP_ERROR_CANCELLED;
+    }
     if (res != SZ_OK) {
         free(out_buf);
         return SEVENZIP_ERROR_COMPRESS;
@@ -496,67 +1244,226 @@ static SevenZipErrorCode compress_all_files(
      * be misinterpreted as end markers when parsing logic had errors.
      */
     
-    *output = out_buf;
-    *output_size = out_size;
+    folder->pack_data = out_buf;
+    folder->pack_size = out_size;
     return SEVENZIP_OK;
 }
 
-/* Helper: Write 7z archive with proper format structure */
-static SevenZipErrorCode write_7z_archive(
-    const char* archive_path,
-    SevenZArchiveBuilder* builder
+SevenZipErrorCode sevenzip_packed_size_of_buffer(
+    const uint8_t* data,
+    size_t size,
+    SevenZipCompressionLevel level,
+    uint64_t* packed_size
 ) {
-    FILE* f = fopen(archive_path, "wb");
-    if (!f) return SEVENZIP_ERROR_OPEN_FILE;
+    if ((!data && size > 0) || !packed_size) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    if (level == SEVENZIP_LEVEL_STORE || size == 0) {
+        *packed_size = size;
+        return SEVENZIP_OK;
+    }
     
-    /* Use 4MB write buffer for optimal I/O performance */
-    setvbuf(f, NULL, _IOFBF, 4 * 1024 * 1024);
+    CLzma2EncProps props;
+    Lzma2EncProps_Init(&props);
+    props.lzmaProps.level = (int)level;
+    props.lzmaProps.reduceSize = size;
+    Lzma2EncProps_Normalize(&props);
     
-    /* === WRITE SIGNATURE HEADER === */
-    fwrite(k7zSignature, 1, k7zSignature_Size, f);
+    CLzma2EncHandle enc = Lzma2Enc_Create(&g_Alloc, &g_Alloc);
+    if (!enc) return SEVENZIP_ERROR_MEMORY;
+    if (Lzma2Enc_SetProps(enc, &props) != SZ_OK) {
+        Lzma2Enc_Destroy(enc);
+        return SEVENZIP_ERROR_COMPRESS;
+    }
     
-    /* Version (0.4) */
-    Byte ver[2] = {k7zMajorVersion, 4};
-    fwrite(ver, 1, 2, f);
+    size_t out_size = size + size / 3 + 128;
+    Byte* out_buf = (Byte*)malloc(out_size);
+    if (!out_buf) {
+        Lzma2Enc_Destroy(enc);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    SRes res = Lzma2Enc_Encode2(enc, NULL, out_buf, &out_size, NULL, data, size, NULL);
+    Lzma2Enc_Destroy(enc);
+    free(out_buf);
+    if (res != SZ_OK) return SEVENZIP_ERROR_COMPRESS;
     
-    /* Start header CRC placeholder */
-    long start_crc_pos = ftell(f);
-    uint32_t dummy_crc = 0;
-    fwrite(&dummy_crc, 4, 1, f);
+    *packed_size = out_size;
+    return SEVENZIP_OK;
+}
+
+/* Helper: Free the packed streams of all folders */
+static void free_folders(SevenZFolder* folders, size_t count) {
+    for (size_t i = 0; i < count; i++) {
+        free(folders[i].pack_data);
+    }
+}
+
+/* Output of the archive writer: a file, or a caller-supplied write callback.
+ * The archive is written strictly sequentially, so either works. */
+typedef struct {
+    const char* path;    /* Output file, opened once the archive is ready (NULL = callback) */
+    FILE* file;
+    SevenZipWriteCallback write_callback;
+    void* user_data;
+    int failed;
+} ArchiveSink;
+
+static void sink_write(ArchiveSink* sink, const void* data, size_t size) {
+    if (sink->failed || size == 0) return;
+    if (sink->file) {
+        if (fwrite(data, 1, size, sink->file) != size) sink->failed = 1;
+    } else if (sink->write_callback((const uint8_t*)data, size, sink->user_data) != 0) {
+        sink->failed = 1;
+    }
+}
+
+/* Helper: LZMA-pack a finished header the way 7-Zip does by default and
+ * describe the packed stream in a small plain header (kEncodedHeader). The
+ * packed header is stored at pack_pos, right after the data streams; encoded
+ * needs room for 64 bytes. */
+static SevenZipErrorCode encode_header(
+    const Byte* header,
+    size_t header_size,
+    uint64_t pack_pos,
+    Byte** packed,
+    size_t* packed_size,
+    Byte* encoded,
+    size_t* encoded_size
+) {
+    CLzmaEncProps props;
+    LzmaEncProps_Init(&props);
+    props.level = 5;
+    props.reduceSize = header_size;
+    props.numThreads = 1;
+    
+    SizeT out_size = header_size + header_size / 3 + 128;
+    Byte* out = (Byte*)malloc(out_size);
+    if (!out) return SEVENZIP_ERROR_MEMORY;
+    
+    Byte props_encoded[LZMA_PROPS_SIZE];
+    SizeT props_size = LZMA_PROPS_SIZE;
+    SRes res = LzmaEncode(out, &out_size, header, header_size, &props,
+                          props_encoded, &props_size, 0, NULL, &g_Alloc, &g_Alloc);
+    if (res != SZ_OK) {
+        free(out);
+        return res == SZ_ERROR_MEM ? SEVENZIP_ERROR_MEMORY : SEVENZIP_ERROR_COMPRESS;
+    }
     
-    /* Next header offset and size placeholders */
-    long next_header_offset_pos = ftell(f);
-    uint64_t dummy_offset = 0;
-    uint64_t dummy_size = 0;
-    fwrite(&dummy_offset, 8, 1, f);
-    fwrite(&dummy_size, 8, 1, f);
+    Byte* p = encoded;
+    *p++ = k7zIdEncodedHeader;
     
-    /* Next header CRC placeholder */
-    fwrite(&dummy_crc, 4, 1, f);
+    *p++ = k7zIdPackInfo;
+    sevenzip_write_number(&p, pack_pos);
+    sevenzip_write_number(&p, 1);  /* One pack stream */
+    *p++ = k7zIdSize;
+    sevenzip_write_number(&p, out_size);
+    *p++ = k7zIdEnd;
     
-    /* === WRITE PACKED DATA === */
-    /* Compress all files into a single LZMA2 stream */
-    Byte* pack_data = NULL;
-    size_t pack_size = 0;
-    SevenZipErrorCode compress_err = compress_all_files(builder, &pack_data, &pack_size);
-    if (compress_err != SEVENZIP_OK) {
-        fclose(f);
-        return compress_err;
+    *p++ = k7zIdUnpackInfo;
+    *p++ = k7zIdFolder;
+    sevenzip_write_number(&p, 1);  /* One folder */
+    sevenzip_write_number(&p, 0);  /* Not external */
+    sevenzip_write_number(&p, 1);  /* One coder */
+    *p++ = 0x23;         /* HasProperties + ID_size=3 */
+    *p++ = 0x03;         /* LZMA = 03 01 01 */
+    *p++ = 0x01;
+    *p++ = 0x01;
+    sevenzip_write_number(&p, props_size);
+    memcpy(p, props_encoded, props_size);
+    p += props_size;
+    *p++ = k7zIdCodersUnpackSize;
+    sevenzip_write_number(&p, header_size);
+    *p++ = k7zIdCRC;     /* CRC of the unpacked header */
+    *p++ = 1;
+    SetUi32(p, CrcCalc(header, header_size));
+    p += 4;
+    *p++ = k7zIdEnd;     /* End UnpackInfo */
+    *p++ = k7zIdEnd;     /* End EncodedHeader */
+    
+    *packed = out;
+    *packed_size = out_size;
+    *encoded_size = (size_t)(p - encoded);
+    return SEVENZIP_OK;
+}
+
+/* Helper: Write 7z archive with proper format structure
+ * Everything is compressed and the header built in memory first, so the
+ * start header is known up front and no seeking back is needed. */
+static SevenZipErrorCode write_7z_archive(
+    ArchiveSink* sink,
+    SevenZArchiveBuilder* builder
+) {
+    /* === COMPRESS PACKED DATA === */
+    /* One folder per method in use (LZMA2 first, matching the file order);
+     * an archive without file data still gets one empty folder */
+    SevenZFolder folders[2];
+    int folder_methods[2];
+    size_t folder_counts[2];
+    size_t num_folders = 0;
+    for (int method = SEVENZIP_METHOD_LZMA2; method <= SEVENZIP_METHOD_COPY; method++) {
+        size_t count = 0;
+        for (size_t i = 0; i < builder->file_count; i++) {
+            if (!builder->files[i].is_dir && builder->files[i].method == method) count++;
+        }
+        if (count > 0) {
+            folder_methods[num_folders] = method;
+            folder_counts[num_folders++] = count;
+        }
+    }
+    if (num_folders == 0) {
+        folder_methods[0] = builder->use_copy_codec ? SEVENZIP_METHOD_COPY : SEVENZIP_METHOD_LZMA2;
+        folder_counts[0] = 0;
+        num_folders = 1;
     }
     
-    long pack_pos = ftell(f);
+    uint64_t total_pack_size = 0;
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        SevenZipErrorCode compress_err = compress_folder(builder, folder_methods[fi], &folders[fi]);
+        if (compress_err != SEVENZIP_OK) {
+            free_folders(folders, fi);
+            return compress_err;
+        }
+        folders[fi].num_files = folder_counts[fi];
+    }
     
-    /* Write the single compressed stream */
-    if (pack_data && pack_size > 0) {
-        fwrite(pack_data, 1, pack_size, f);
+    /* Encrypt the packed streams with one key, derived the way 7-Zip does
+     * unless the caller supplied it; an empty folder has nothing to hide and
+     * stays a plain coder */
+    if (builder->password || builder->encryption_key) {
+        uint8_t key[32];
+        SevenZipErrorCode key_err = SEVENZIP_OK;
+        if (builder->encryption_key) {
+            memcpy(key, builder->encryption_key, sizeof(key));
+        } else {
+            key_err = sevenzip_derive_7z_key(builder->password, NULL, 0, builder->kdf_cycles_power, key);
+        }
+        for (size_t fi = 0; fi < num_folders && key_err == SEVENZIP_OK; fi++) {
+            if (folders[fi].unpack_size == 0) continue;
+            folders[fi].coder_size = folders[fi].pack_size;
+            key_err = sevenzip_aes_encode_internal(key, builder->kdf_cycles_power,
+                                                   &folders[fi].pack_data, &folders[fi].pack_size,
+                                                   folders[fi].aes_props);
+            folders[fi].encrypted = 1;
+        }
+        memset(key, 0, sizeof(key));
+        if (key_err != SEVENZIP_OK) {
+            free_folders(folders, num_folders);
+            return key_err;
+        }
+    }
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        total_pack_size += folders[fi].pack_size;
     }
     
     /* === BUILD HEADER IN MEMORY === */
     size_t header_capacity = 65536;  /* 64KB buffer */
+    if (builder->store_all_times) {
+        header_capacity += 16 * builder->file_count;
+    }
     Byte* header = (Byte*)malloc(header_capacity);
     if (!header) {
-        free(pack_data);
-        fclose(f);
+        free_folders(folders, num_folders);
         return SEVENZIP_ERROR_MEMORY;
     }
     
@@ -571,13 +1478,14 @@ static SevenZipErrorCode write_7z_archive(
     
     /* --- PackInfo --- */
     *p++ = k7zIdPackInfo;
-    WriteNumber(&p, 0);  /* Pack position (offset from end of SignatureHeader) */
-    WriteNumber(&p, 1);  /* Number of pack streams - always 1 (all files in single stream) */
+    sevenzip_write_number(&p, 0);  /* Pack position (offset from end of SignatureHeader) */
+    sevenzip_write_number(&p, num_folders);  /* Number of pack streams - one per folder */
     
-    /* Pack size */
+    /* Pack sizes */
     *p++ = k7zIdSize;
-    WriteNumber(&p, pack_size);  /* Size of the single compressed stream */
-
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        sevenzip_write_number(&p, folders[fi].pack_size);
+    }
     
     *p++ = k7zIdEnd;  /* End PackInfo */
     
@@ -586,80 +1494,102 @@ static SevenZipErrorCode write_7z_archive(
     
     /* Folder */
     *p++ = k7zIdFolder;
-    WriteNumber(&p, 1);  /* Number of folders */
+    sevenzip_write_number(&p, num_folders);  /* Number of folders */
     
     /* External flag (0 = not external) */
-    WriteNumber(&p, 0);
-    
-    /* Number of coders */
-    WriteNumber(&p, 1);
-    
-    if (builder->use_copy_codec) {
-        /* Coder flags byte for Copy codec:
-         *   Bits 7-6: reserved (0)
-         *   Bit 5: HasProperties (0 = no property data)
-         *   Bit 4: IsComplex (0 = simple coder)
-         *   Bits 0-3: Codec ID size (1 byte)
-         * Value: 0x01 = 00000001 = ID_size=1, no properties
-         */
-        *p++ = 0x01;
-        
-        /* Codec ID (Copy = 0x00) */
-        *p++ = 0x00;
+    sevenzip_write_number(&p, 0);
+    
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        /* Number of coders */
+        sevenzip_write_number(&p, folders[fi].encrypted ? 2 : 1);
         
-        /* No property data for Copy codec */
-    } else {
-        /* Coder flags byte for LZMA2:
-         *   Bits 7-6: reserved (0)
-         *   Bit 5: HasProperties (1 = has property data after codec ID)
-         *   Bit 4: IsComplex (0 = simple coder, no NumIn/NumOut)
-         *   Bits 0-3: Codec ID size (1 byte for LZMA2)
-         * Value: 0x21 = 00100001 = HasProperties + ID_size=1
-         */
-        *p++ = 0x21;
+        if (folders[fi].encrypted) {
+            /* 7zAES (06 F1 07 01) comes first: coders are listed in
+             * decoding order, starting from the packed stream */
+            *p++ = 0x24;  /* HasProperties + ID_size=4 */
+            *p++ = 0x06;
+            *p++ = 0xF1;
+            *p++ = 0x07;
+            *p++ = 0x01;
+            sevenzip_write_number(&p, SEVENZIP_AES_PROPS_SIZE);
+            memcpy(p, folders[fi].aes_props, SEVENZIP_AES_PROPS_SIZE);
+            p += SEVENZIP_AES_PROPS_SIZE;
+        }
         
-        /* Codec ID (LZMA2 = 0x21) */
-        *p++ = 0x21;
+        if (folders[fi].use_copy_codec) {
+            /* Coder flags byte for Copy codec:
+             *   Bits 7-6: reserved (0)
+             *   Bit 5: HasProperties (0 = no property data)
+             *   Bit 4: IsComplex (0 = simple coder)
+             *   Bits 0-3: Codec ID size (1 byte)
+             * Value: 0x01 = 00000001 = ID_size=1, no properties
+             */
+            *p++ = 0x01;
+            
+            /* Codec ID (Copy = 0x00) */
+            *p++ = 0x00;
+            
+            /* No property data for Copy codec */
+        } else {
+            /* Coder flags byte for LZMA2:
+             *   Bits 7-6: reserved (0)
+             *   Bit 5: HasProperties (1 = has property data after codec ID)
+             *   Bit 4: IsComplex (0 = simple coder, no NumIn/NumOut)
+             *   Bits 0-3: Codec ID size (1 byte for LZMA2)
+             * Value: 0x21 = 00100001 = HasProperties + ID_size=1
+             */
+            *p++ = 0x21;
+            
+            /* Codec ID (LZMA2 = 0x21) */
+            *p++ = 0x21;
+            
+            /* Property data (because HasProperties bit is set) */
+            sevenzip_write_number(&p, 1);  /* Properties size = 1 byte */
+            *p++ = folders[fi].lzma2_prop_byte;  /* Actual LZMA2 property byte */
+        }
         
-        /* Property data (because HasProperties bit is set) */
-        WriteNumber(&p, 1);  /* Properties size = 1 byte */
-        *p++ = builder->lzma2_prop_byte;  /* Actual LZMA2 property byte */
+        if (folders[fi].encrypted) {
+            /* One bond: the codec (in stream 1) reads the AES output (out
+             * stream 0); the AES input is the folder's only packed stream */
+            sevenzip_write_number(&p, 1);
+            sevenzip_write_number(&p, 0);
+        }
     }
     
-    /* CoderUnpackSizes */
+    /* CoderUnpackSizes, one per coder in the order above */
     *p++ = k7zIdCodersUnpackSize;
-    uint64_t total_unpack_size = 0;
-    for (size_t i = 0; i < builder->file_count; i++) {
-        if (!builder->files[i].is_dir) {
-            total_unpack_size += builder->files[i].size;
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        if (folders[fi].encrypted) {
+            sevenzip_write_number(&p, folders[fi].coder_size);
         }
+        sevenzip_write_number(&p, folders[fi].unpack_size);
     }
-    WriteNumber(&p, total_unpack_size);
     
     *p++ = k7zIdEnd;  /* End UnpackInfo */
     
     /* --- SubStreamsInfo --- */
     *p++ = k7zIdSubStreamsInfo;
     
-    /* Count non-directory files */
-    size_t num_files = 0;
-    for (size_t i = 0; i < builder->file_count; i++) {
-        if (!builder->files[i].is_dir) {
-            num_files++;
-        }
-    }
-    
     /* Number of unpack streams per folder */
     *p++ = k7zIdNumUnpackStream;
-    WriteNumber(&p, num_files);  /* Number of files extracted from single stream */
+    int any_multi = 0;
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        sevenzip_write_number(&p, folders[fi].num_files);
+        if (folders[fi].num_files > 1) any_multi = 1;
+    }
     
-    /* Individual file sizes (all but last - last is implied) */
-    if (num_files > 1) {
+    /* Individual file sizes per folder (all but last - last is implied).
+     * Files are already ordered by folder, see assign_file_methods */
+    if (any_multi) {
         *p++ = k7zIdSize;
-        size_t written = 0;
-        for (size_t i = 0; i < builder->file_count && written < num_files - 1; i++) {
-            if (!builder->files[i].is_dir) {
-                WriteNumber(&p, builder->files[i].size);
+        size_t i = 0;
+        for (size_t fi = 0; fi < num_folders; fi++) {
+            size_t written = 0;
+            for (; i < builder->file_count && written < folders[fi].num_files; i++) {
+                if (builder->files[i].is_dir) continue;
+                if (written + 1 < folders[fi].num_files) {
+                    sevenzip_write_number(&p, builder->files[i].size);
+                }
                 written++;
             }
         }
@@ -671,8 +1601,7 @@ static SevenZipErrorCode write_7z_archive(
     for (size_t i = 0; i < builder->file_count; i++) {
         if (!builder->files[i].is_dir) {
             /* Write CRC in little-endian */
-            uint32_t crc_le = builder->files[i].crc;
-            memcpy(p, &crc_le, 4);
+            SetUi32(p, builder->files[i].crc);
             p += 4;
         }
     }
@@ -683,7 +1612,7 @@ static SevenZipErrorCode write_7z_archive(
     
     /* === FilesInfo === */
     *p++ = k7zIdFilesInfo;
-    WriteNumber(&p, builder->file_count);
+    sevenzip_write_number(&p, builder->file_count);
     
     /* EmptyStream bit vector (for directories) */
     int has_dirs = 0;
@@ -697,7 +1626,7 @@ static SevenZipErrorCode write_7z_archive(
     if (has_dirs) {
         *p++ = k7zIdEmptyStream;
         size_t mask_size = (builder->file_count + 7) / 8;
-        WriteNumber(&p, mask_size);
+        sevenzip_write_number(&p, mask_size);
         memset(p, 0, mask_size);
         for (size_t i = 0; i < builder->file_count; i++) {
             if (builder->files[i].is_dir) {
@@ -711,42 +1640,48 @@ static SevenZipErrorCode write_7z_archive(
     *p++ = k7zIdName;
     size_t names_size = 0;
     for (size_t i = 0; i < builder->file_count; i++) {
-        names_size += (strlen(builder->files[i].name) + 1) * 2;
+        names_size += sevenzip_write_utf16le_name(builder->files[i].name, NULL);
     }
-    WriteNumber(&p, names_size + 1);
+    sevenzip_write_number(&p, names_size + 1);
     *p++ = 0;  /* External flag = 0 (names embedded) */
     
     for (size_t i = 0; i < builder->file_count; i++) {
-        const char* name = builder->files[i].name;
-        while (*name) {
-            *p++ = (Byte)*name++;
-            *p++ = 0;  /* High byte of UTF-16LE */
+        p += sevenzip_write_utf16le_name(builder->files[i].name, p);
+    }
+    
+    /* Creation and access times, same layout as MTime below */
+    if (builder->store_all_times) {
+        for (int pass = 0; pass < 2; pass++) {
+            *p++ = pass == 0 ? k7zIdCTime : k7zIdATime;
+            sevenzip_write_number(&p, 2 + (8 * builder->file_count));
+            *p++ = 1;  /* All times defined */
+            *p++ = 0;  /* External flag = 0 */
+            for (size_t i = 0; i < builder->file_count; i++) {
+                SetUi64(p, pass == 0 ? builder->files[i].ctime : builder->files[i].atime);
+                p += 8;
+            }
         }
-        *p++ = 0;  /* Null terminator low byte */
-        *p++ = 0;  /* Null terminator high byte */
     }
     
     /* Modification times (Windows FILETIME format) */
     *p++ = k7zIdMTime;
     size_t time_size = 2 + (8 * builder->file_count);
-    WriteNumber(&p, time_size);
+    sevenzip_write_number(&p, time_size);
     *p++ = 1;  /* All times defined */
     *p++ = 0;  /* External flag = 0 */
     for (size_t i = 0; i < builder->file_count; i++) {
-        uint64_t filetime = builder->files[i].mtime;
-        memcpy(p, &filetime, 8);
+        SetUi64(p, builder->files[i].mtime);
         p += 8;
     }
     
     /* Attributes */
     *p++ = k7zIdWinAttrib;
     size_t attrib_size = 2 + (4 * builder->file_count);
-    WriteNumber(&p, attrib_size);
+    sevenzip_write_number(&p, attrib_size);
     *p++ = 1;  /* All attributes defined */
     *p++ = 0;  /* External flag = 0 */
     for (size_t i = 0; i < builder->file_count; i++) {
-        uint32_t attr = builder->files[i].attrib;
-        memcpy(p, &attr, 4);
+        SetUi32(p, builder->files[i].attrib);
         p += 4;
     }
     
@@ -759,71 +1694,144 @@ static SevenZipErrorCode write_7z_archive(
     /* Ensure we didn't overflow */
     if (actual_header_size > header_capacity) {
         free(header);
-        fclose(f);
+        free_folders(folders, num_folders);
         return SEVENZIP_ERROR_COMPRESS;  /* Header too large */
     }
     
+    /* Optionally pack the header; the start header then points at the
+     * encoded header, which follows the packed header */
+    Byte* packed_header = NULL;
+    size_t packed_header_size = 0;
+    Byte encoded_header[64];
+    size_t encoded_header_size = 0;
+    if (builder->compress_header) {
+        SevenZipErrorCode encode_err = encode_header(header_start, actual_header_size, total_pack_size,
+                                                     &packed_header, &packed_header_size,
+                                                     encoded_header, &encoded_header_size);
+        if (encode_err != SEVENZIP_OK) {
+            free(header);
+            free_folders(folders, num_folders);
+            return encode_err;
+        }
+    }
+    const Byte* next_header = packed_header ? encoded_header : header_start;
+    size_t next_header_size = packed_header ? encoded_header_size : actual_header_size;
+    
     /* Calculate header CRC */
-    uint32_t header_crc = CrcCalc(header_start, actual_header_size);
+    uint32_t header_crc = CrcCalc(next_header, next_header_size);
     
-    /* Write header to file */
-    uint64_t header_offset = ftell(f) - pack_pos;
-    fwrite(header_start, 1, actual_header_size, f);
-    free(header);
+    /* === BUILD START HEADER === */
+    /* The header follows the packed streams directly */
+    /* 7z stores integers little-endian whatever the host byte order */
+    Byte start_header[20];
+    SetUi64(start_header, total_pack_size + packed_header_size);
+    SetUi64(start_header + 8, next_header_size);
+    SetUi32(start_header + 16, header_crc);
+    Byte start_header_crc[4];
+    SetUi32(start_header_crc, CrcCalc(start_header, 20));
+    
+    if (sink->path) {
+        sink->file = fopen(sink->path, "wb");
+        if (!sink->file) {
+            free(header);
+            free(packed_header);
+            free_folders(folders, num_folders);
+            return SEVENZIP_ERROR_OPEN_FILE;
+        }
+        /* Use 4MB write buffer for optimal I/O performance */
+        setvbuf(sink->file, NULL, _IOFBF, 4 * 1024 * 1024);
+    }
     
-    /* === UPDATE START HEADER === */
-    /* Go back and write the actual values */
-    fseek(f, next_header_offset_pos, SEEK_SET);
-    fwrite(&header_offset, 8, 1, f);
-    uint64_t header_size_field = actual_header_size;
-    fwrite(&header_size_field, 8, 1, f);
-    fwrite(&header_crc, 4, 1, f);
+    /* === WRITE SIGNATURE HEADER === */
+    sink_write(sink, k7zSignature, k7zSignature_Size);
     
-    /* Calculate and write start header CRC */
-    Byte start_header[20];
-    memcpy(start_header, &header_offset, 8);
-    memcpy(start_header + 8, &header_size_field, 8);
-    memcpy(start_header + 16, &header_crc, 4);
-    uint32_t start_header_crc = CrcCalc(start_header, 20);
+    /* Version (0.4) */
+    Byte ver[2] = {k7zMajorVersion, 4};
+    sink_write(sink, ver, 2);
+    sink_write(sink, start_header_crc, 4);
+    sink_write(sink, start_header, 20);
     
-    fseek(f, start_crc_pos, SEEK_SET);
-    fwrite(&start_header_crc, 4, 1, f);
+    /* === WRITE PACKED DATA === */
+    /* The compressed streams back to back */
+    for (size_t fi = 0; fi < num_folders; fi++) {
+        if (folders[fi].pack_data && folders[fi].pack_size > 0) {
+            sink_write(sink, folders[fi].pack_data, folders[fi].pack_size);
+        }
+    }
     
-    fclose(f);
-    free(pack_data);  /* Free compressed data */
-    return SEVENZIP_OK;
+    /* === WRITE HEADER === */
+    if (packed_header) {
+        sink_write(sink, packed_header, packed_header_size);
+    }
+    sink_write(sink, next_header, next_header_size);
+    
+    if (sink->file && fclose(sink->file) != 0) sink->failed = 1;
+    sink->file = NULL;
+    
+    free(header);
+    free(packed_header);
+    free_folders(folders, num_folders);  /* Free compressed data */
+    return sink->failed ? SEVENZIP_ERROR_COMPRESS : SEVENZIP_OK;
 }
 
-/* Main API: Create 7z archive */
-SevenZipErrorCode sevenzip_create_7z(
-    const char* archive_path,
+/* Helper: Gather the inputs and write the archive to the sink */
+static SevenZipErrorCode create_7z_to_sink(
+    ArchiveSink* sink,
     const char** input_paths,
     SevenZipCompressionLevel level,
     const SevenZipCompressOptions* options,
     SevenZipProgressCallback progress_callback,
     void* user_data
 ) {
-    if (!archive_path || !input_paths) {
-        return SEVENZIP_ERROR_INVALID_PARAM;
-    }
+
+    /* Drop any stale abort request from an earlier operation */
+    (void)sevenzip_take_abort_request();
+    sevenzip_set_last_threads_used(0);
+    sevenzip_set_last_peak_memory(0);
+    sevenzip_set_last_dict_size(0);
     
     /* Initialize CRC tables */
     CrcGenerateTable();
     
     /* Set default options if not provided */
-    SevenZipCompressOptions default_opts = {
-        .num_threads = 2,
-        .dict_size = 0,  /* Auto */
-        .solid = 1,       /* Solid archive */
-        .password = NULL  /* No encryption */
-    };
+    SevenZipCompressOptions default_opts;
+    sevenzip_compress_options_init(&default_opts);
     const SevenZipCompressOptions* opts = options ? options : &default_opts;
+    if ((opts->password && !opts->password[0]) || (opts->password && opts->encryption_key)) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    /* The round count is stored as its log2, and 7-Zip decodes at most 2^24 */
+    uint32_t kdf_cycles_power = SEVENZIP_DEFAULT_KDF_CYCLES_POWER;
+    if (opts->kdf_iterations) {
+        if (!opts->password || (opts->kdf_iterations & (opts->kdf_iterations - 1)) ||
+            opts->kdf_iterations > ((uint32_t)1 << 24)) {
+            return SEVENZIP_ERROR_INVALID_PARAM;
+        }
+        for (kdf_cycles_power = 0; ((uint32_t)1 << kdf_cycles_power) < opts->kdf_iterations; kdf_cycles_power++) {}
+    }
     
     /* Create builder */
     SevenZArchiveBuilder builder;
     builder.file_count = 0;
     builder.file_capacity = 16;
     builder.use_copy_codec = 0;  /* Default: use LZMA2 compression */
+    builder.special_files = opts->special_files;
+    builder.start_ms = monotonic_ms();
+    builder.time_budget_ms = opts->time_budget_ms;
+    builder.store_all_times = opts->store_all_times;
+    builder.has_duplicates = 0;
+    builder.no_recursion = opts->no_recursion;
+    builder.limit_file_size = opts->limit_file_size;
+    builder.min_file_size = opts->min_file_size;
+    builder.max_file_size = opts->max_file_size;
+    builder.compress_header = opts->compress_header;
+    builder.inodes = NULL;
+    builder.inode_capacity = 0;
+    builder.inode_count = 0;
+    builder.detect_hardlinks = opts->detect_hardlinks;
+    builder.password = opts->password;
+    builder.kdf_cycles_power = kdf_cycles_power;
+    builder.encryption_key = opts->encryption_key;
     builder.files = (SevenZFile*)calloc(builder.file_capacity, sizeof(SevenZFile));
     if (!builder.files) {
         return SEVENZIP_ERROR_MEMORY;
@@ -877,8 +1885,14 @@ SevenZipErrorCode sevenzip_create_7z(
             if (opts->num_threads == 0) builder.props.numBlockThreads_Max = 2;
             break;
         default:
-            builder.props.lzmaProps.level = 5;
-            builder.props.lzmaProps.dictSize = opts->dict_size > 0 ? opts->dict_size : (1 << 23);
+            /* The encoder picks the dictionary for unnamed levels */
+            builder.props.lzmaProps.level = SEVENZIP_RAW_LEVEL(level);
+            builder.props.lzmaProps.dictSize = opts->dict_size > 0 ? opts->dict_size : 0;
+    }
+    SevenZipErrorCode props_err = apply_codec_properties(&builder.props.lzmaProps, opts);
+    if (props_err != SEVENZIP_OK) {
+        free(builder.files);
+        return props_err;
     }
     Lzma2EncProps_Normalize(&builder.props);
     
@@ -898,6 +1912,7 @@ SevenZipErrorCode sevenzip_create_7z(
             goto cleanup;
         }
         
+        size_t first_entry = builder.file_count;
         if (S_ISDIR(st.st_mode)) {
             /* Add directory recursively */
             result = add_directory_recursive(&builder, path, path);
@@ -905,6 +1920,20 @@ SevenZipErrorCode sevenzip_create_7z(
                 goto cleanup;
             }
         } else {
+#ifndef _WIN32
+            if (!S_ISREG(st.st_mode)) {
+                int keep = keep_special_file(path, builder.special_files);
+                if (keep < 0) {
+                    result = SEVENZIP_ERROR_INVALID_PARAM;
+                    goto cleanup;
+                }
+                if (!keep) continue;
+            }
+#endif
+            if (S_ISREG(st.st_mode) && file_size_excluded(&builder, (uint64_t)st.st_size)) {
+                continue;
+            }
+            
             /* Expand array if needed */
             if (builder.file_count >= builder.file_capacity) {
                 builder.file_capacity *= 2;
@@ -925,10 +1954,18 @@ SevenZipErrorCode sevenzip_create_7z(
             if (!name) name = strrchr(path, '\\');
             file->name = strdup(name ? name + 1 : path);
             file->mtime = (uint64_t)st.st_mtime * 10000000ULL + 116444736000000000ULL;
-            file->attrib = (uint32_t)st.st_mode;
+            file->ctime = (uint64_t)st.st_ctime * 10000000ULL + 116444736000000000ULL;
+            file->atime = (uint64_t)st.st_atime * 10000000ULL + 116444736000000000ULL;
+            file->attrib = attrib_from_mode((unsigned)st.st_mode);
             file->is_dir = 0;  /* Regular file */
             
-            if (S_ISREG(st.st_mode)) {
+            int linked = note_hardlink(&builder, file, &st);
+            if (linked < 0) {
+                result = SEVENZIP_ERROR_MEMORY;
+                goto cleanup;
+            }
+            
+            if (S_ISREG(st.st_mode) && !linked) {
                 /* Read file */
                 FILE* f = fopen(path, "rb");
                 if (!f) {
@@ -959,14 +1996,62 @@ SevenZipErrorCode sevenzip_create_7z(
             }
         }
         
+        if (opts->input_entry_names && opts->input_entry_names[i]) {
+            result = rename_input_entries(&builder, first_entry, opts->input_entry_names[i], S_ISDIR(st.st_mode));
+            if (result != SEVENZIP_OK) {
+                goto cleanup;
+            }
+        }
+        
         /* Progress callback */
         if (progress_callback) {
             progress_callback(i + 1, total_files, user_data);
+            if (sevenzip_take_abort_request()) {
+                result = SEVENZIP_ERROR_CANCELLED;
+                goto cleanup;
+            }
+        }
+    }
+    
+    if (opts->flatten_names) {
+        result = flatten_entry_names(&builder);
+        if (result != SEVENZIP_OK) {
+            goto cleanup;
+        }
+    }
+    
+    result = resolve_hardlinks(&builder);
+    if (result != SEVENZIP_OK) {
+        goto cleanup;
+    }
+    
+    if (opts->skip_empty_dirs) {
+        result = drop_empty_directories(&builder);
+        if (result != SEVENZIP_OK) {
+            goto cleanup;
         }
     }
     
+    /* Sort before grouping duplicates, which then only moves the copies */
+    result = sort_file_slots(&builder, opts->sort_order);
+    if (result != SEVENZIP_OK) {
+        goto cleanup;
+    }
+    
+    if (opts->dedup) {
+        result = group_duplicate_files(&builder);
+        if (result != SEVENZIP_OK) {
+            goto cleanup;
+        }
+    }
+    
+    result = assign_file_methods(&builder, opts);
+    if (result != SEVENZIP_OK) {
+        goto cleanup;
+    }
+    
     /* Write archive */
-    result = write_7z_archive(archive_path, &builder);
+    result = write_7z_archive(sink, &builder);
     
 cleanup:
     /* Free resources */
@@ -975,6 +2060,56 @@ cleanup:
         if (builder.files[i].data) free(builder.files[i].data);
     }
     free(builder.files);
+    free(builder.inodes);
     
     return result;
 }
+
+/* Main API: Default compression options */
+void sevenzip_compress_options_init(SevenZipCompressOptions* options) {
+    if (!options) return;
+    
+    memset(options, 0, sizeof(SevenZipCompressOptions));
+    options->num_threads = 2;
+    options->dict_size = 0;  /* Auto */
+    options->solid = 1;      /* Solid archive */
+    options->password = NULL;  /* No encryption */
+    options->method = SEVENZIP_METHOD_LZMA2;
+    options->special_files = SEVENZIP_SPECIAL_SKIP;
+    options->sort_order = SEVENZIP_SORT_NONE;
+}
+
+/* Main API: Create 7z archive */
+SevenZipErrorCode sevenzip_create_7z(
+    const char* archive_path,
+    const char** input_paths,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* options,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    if (!archive_path || !input_paths) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    ArchiveSink sink = { archive_path, NULL, NULL, NULL, 0 };
+    return create_7z_to_sink(&sink, input_paths, level, options, progress_callback, user_data);
+}
+
+/* Main API: Create 7z archive, passing the bytes to a write callback */
+SevenZipErrorCode sevenzip_create_7z_to_callback(
+    const char** input_paths,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* options,
+    SevenZipWriteCallback write_callback,
+    void* write_user_data,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    if (!input_paths || !write_callback) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    ArchiveSink sink = { NULL, NULL, write_callback, write_user_data, 0 };
+    return create_7z_to_sink(&sink, input_paths, level, options, progress_callback, user_data);
+}
@@ -20,6 +20,7 @@
  */
 
 #include "../include/7z_ffi.h"
+#include "ffi_internal.h"
 #include "Lzma2Enc.h"
 #include "Alloc.h"
 
@@ -108,8 +109,7 @@ static ArchiveBuilder* create_archive_builder(SevenZipCompressionLevel level) {
             builder->props.numBlockThreads_Max = 2;
             break;
         default:
-            builder->props.lzmaProps.level = 5;
-            builder->props.lzmaProps.dictSize = 1 << 23;
+            builder->props.lzmaProps.level = SEVENZIP_RAW_LEVEL(level);
             builder->props.numBlockThreads_Max = 1;
     }
     Lzma2EncProps_Normalize(&builder->props);
@@ -0,0 +1,335 @@
+/**
+ * 7z Archive Creation from a Byte Stream
+ *
+ * Compresses data pulled from a read callback into a single-entry .7z archive
+ * without knowing its size up front. Used for piping generated content (such
+ * as a tar stream) straight into an archive.
+ */
+
+#include "../include/7z_ffi.h"
+#include "ffi_internal.h"
+#include "Lzma2Enc.h"
+#include "7zCrc.h"
+#include "Alloc.h"
+#include "CpuArch.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+#define k7zSignature_Size 6
+#define k7zMajorVersion 0
+
+/* 7z format constants */
+static const Byte k7zSignature[k7zSignature_Size] = {'7', 'z', 0xBC, 0xAF, 0x27, 0x1C};
+
+/* Copy buffer size for the Store level */
+#define STREAM_COPY_BUFFER_SIZE ((size_t)1 << 20)
+
+/* Input stream pulling from the caller's read callback, tracking size and CRC */
+typedef struct {
+    ISeqInStream vt;
+    SevenZipReadCallback read_callback;
+    void* user_data;
+    uint64_t size;
+    uint32_t crc;
+    int failed;
+} CallbackInStream;
+
+static SRes callback_read(ISeqInStreamPtr p, void* buf, size_t* size) {
+    CallbackInStream* in = (CallbackInStream*)(void*)p;
+    if (in->read_callback((uint8_t*)buf, size, in->user_data) != 0) {
+        in->failed = 1;
+        *size = 0;
+        return SZ_ERROR_READ;
+    }
+    in->crc = CrcUpdate(in->crc, buf, *size);
+    in->size += *size;
+    return SZ_OK;
+}
+
+/* Output stream appending to the archive file, counting packed bytes */
+typedef struct {
+    ISeqOutStream vt;
+    FILE* file;
+    uint64_t written;
+} CountingOutStream;
+
+static size_t counting_write(ISeqOutStreamPtr p, const void* buf, size_t size) {
+    CountingOutStream* out = (CountingOutStream*)(void*)p;
+    size_t written = fwrite(buf, 1, size, out->file);
+    out->written += written;
+    return written;
+}
+
+static void set_lzma2_props(
+    CLzma2EncProps* props,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* opts
+) {
+    Lzma2EncProps_Init(props);
+    
+    switch (level) {
+        case SEVENZIP_LEVEL_FASTEST:
+            props->lzmaProps.level = 1;
+            props->lzmaProps.dictSize = 1 << 18;
+            break;
+        case SEVENZIP_LEVEL_FAST:
+            props->lzmaProps.level = 3;
+            props->lzmaProps.dictSize = 1 << 20;
+            break;
+        case SEVENZIP_LEVEL_MAXIMUM:
+            props->lzmaProps.level = 7;
+            props->lzmaProps.dictSize = 1 << 25;
+            break;
+        case SEVENZIP_LEVEL_ULTRA:
+            props->lzmaProps.level = 9;
+            props->lzmaProps.dictSize = 1 << 26;
+            break;
+        case SEVENZIP_LEVEL_NORMAL:
+            props->lzmaProps.level = 5;
+            props->lzmaProps.dictSize = 1 << 23;
+            break;
+        default:
+            props->lzmaProps.level = SEVENZIP_RAW_LEVEL(level);
+            break;
+    }
+    
+    if (opts) {
+        if (opts->dict_size > 0) {
+            props->lzmaProps.dictSize = (UInt32)opts->dict_size;
+        }
+        if (opts->num_threads > 0) {
+            props->numTotalThreads = opts->num_threads;
+        }
+    }
+    
+    Lzma2EncProps_Normalize(props);
+}
+
+/* Write the end header describing one streamed entry (or one empty file) */
+static SevenZipErrorCode write_single_entry_header(
+    FILE* f,
+    const char* entry_name,
+    int use_copy_codec,
+    Byte lzma2_prop_byte,
+    uint64_t pack_size,
+    uint64_t unpack_size,
+    uint32_t crc,
+    uint64_t mtime
+) {
+    size_t name_len = strlen(entry_name);
+    size_t header_capacity = 256 + (name_len + 1) * 2;
+    Byte* header = (Byte*)malloc(header_capacity);
+    if (!header) return SEVENZIP_ERROR_MEMORY;
+    
+    Byte* p = header;
+    *p++ = k7zIdHeader;
+    
+    if (unpack_size > 0) {
+        *p++ = k7zIdMainStreamsInfo;
+        
+        /* PackInfo: one pack stream right after the signature header */
+        *p++ = k7zIdPackInfo;
+        sevenzip_write_number(&p, 0);
+        sevenzip_write_number(&p, 1);
+        *p++ = k7zIdSize;
+        sevenzip_write_number(&p, pack_size);
+        *p++ = k7zIdEnd;
+        
+        /* UnpackInfo: one folder with a single coder */
+        *p++ = k7zIdUnpackInfo;
+        *p++ = k7zIdFolder;
+        sevenzip_write_number(&p, 1);
+        sevenzip_write_number(&p, 0);  /* Not external */
+        sevenzip_write_number(&p, 1);  /* One coder */
+        if (use_copy_codec) {
+            *p++ = 0x01;  /* ID size 1, no properties */
+            *p++ = 0x00;  /* Copy */
+        } else {
+            *p++ = 0x21;  /* ID size 1, has properties */
+            *p++ = 0x21;  /* LZMA2 */
+            sevenzip_write_number(&p, 1);
+            *p++ = lzma2_prop_byte;
+        }
+        *p++ = k7zIdCodersUnpackSize;
+        sevenzip_write_number(&p, unpack_size);
+        *p++ = k7zIdEnd;
+        
+        /* SubStreamsInfo: the folder holds exactly one file; store its CRC */
+        *p++ = k7zIdSubStreamsInfo;
+        *p++ = k7zIdCRC;
+        *p++ = 1;  /* All CRCs defined */
+        SetUi32(p, crc);
+        p += 4;
+        *p++ = k7zIdEnd;
+        
+        *p++ = k7zIdEnd;  /* End MainStreamsInfo */
+    }
+    
+    /* FilesInfo */
+    *p++ = k7zIdFilesInfo;
+    sevenzip_write_number(&p, 1);
+    
+    if (unpack_size == 0) {
+        /* No data: mark the entry as an empty file rather than a directory */
+        *p++ = k7zIdEmptyStream;
+        sevenzip_write_number(&p, 1);
+        *p++ = 0x80;
+        *p++ = k7zIdEmptyFile;
+        sevenzip_write_number(&p, 1);
+        *p++ = 0x80;
+    }
+    
+    /* Name (UTF-16LE) */
+    *p++ = k7zIdName;
+    sevenzip_write_number(&p, sevenzip_write_utf16le_name(entry_name, NULL) + 1);
+    *p++ = 0;  /* Not external */
+    p += sevenzip_write_utf16le_name(entry_name, p);
+    
+    /* Modification time (Windows FILETIME) */
+    *p++ = k7zIdMTime;
+    sevenzip_write_number(&p, 2 + 8);
+    *p++ = 1;  /* All defined */
+    *p++ = 0;  /* Not external */
+    SetUi64(p, mtime);
+    p += 8;
+    
+    *p++ = k7zIdEnd;  /* End FilesInfo */
+    *p++ = k7zIdEnd;  /* End Header */
+    
+    size_t header_size = (size_t)(p - header);
+    uint32_t header_crc = CrcCalc(header, header_size);
+    
+    /* Header goes after the packed data */
+    uint64_t header_offset = pack_size;
+    if (fwrite(header, 1, header_size, f) != header_size) {
+        free(header);
+        return SEVENZIP_ERROR_COMPRESS;
+    }
+    free(header);
+    
+    /* Patch the start header */
+    Byte start_header[20];
+    SetUi64(start_header, header_offset);
+    SetUi64(start_header + 8, header_size);
+    SetUi32(start_header + 16, header_crc);
+    Byte start_header_crc[4];
+    SetUi32(start_header_crc, CrcCalc(start_header, 20));
+    
+    if (fseek(f, k7zSignature_Size + 2, SEEK_SET) != 0 ||
+        fwrite(start_header_crc, 1, 4, f) != 4 ||
+        fwrite(start_header, 1, 20, f) != 20) {
+        return SEVENZIP_ERROR_COMPRESS;
+    }
+    
+    return SEVENZIP_OK;
+}
+
+SevenZipErrorCode sevenzip_create_7z_from_stream(
+    const char* archive_path,
+    const char* entry_name,
+    SevenZipCompressionLevel level,
+    const SevenZipCompressOptions* options,
+    SevenZipReadCallback read_callback,
+    void* user_data
+) {
+    if (!archive_path || !entry_name || !*entry_name || !read_callback) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Initialize CRC tables */
+    CrcGenerateTable();
+    
+    FILE* f = fopen(archive_path, "wb");
+    if (!f) return SEVENZIP_ERROR_OPEN_FILE;
+    setvbuf(f, NULL, _IOFBF, 4 * 1024 * 1024);
+    
+    SevenZipErrorCode result = SEVENZIP_OK;
+    
+    /* Signature header; the start header is patched once sizes are known */
+    Byte signature_header[32];
+    memset(signature_header, 0, sizeof(signature_header));
+    memcpy(signature_header, k7zSignature, k7zSignature_Size);
+    signature_header[6] = k7zMajorVersion;
+    signature_header[7] = 4;
+    if (fwrite(signature_header, 1, sizeof(signature_header), f) != sizeof(signature_header)) {
+        fclose(f);
+        remove(archive_path);
+        return SEVENZIP_ERROR_COMPRESS;
+    }
+    
+    CallbackInStream in_stream;
+    in_stream.vt.Read = callback_read;
+    in_stream.read_callback = read_callback;
+    in_stream.user_data = user_data;
+    in_stream.size = 0;
+    in_stream.crc = CRC_INIT_VAL;
+    in_stream.failed = 0;
+    
+    CountingOutStream out_stream;
+    out_stream.vt.Write = counting_write;
+    out_stream.file = f;
+    out_stream.written = 0;
+    
+    int use_copy_codec = (level == SEVENZIP_LEVEL_STORE);
+    Byte lzma2_prop_byte = 0;
+    
+    if (use_copy_codec) {
+        Byte* buf = (Byte*)malloc(STREAM_COPY_BUFFER_SIZE);
+        if (!buf) {
+            result = SEVENZIP_ERROR_MEMORY;
+            goto cleanup;
+        }
+        for (;;) {
+            size_t size = STREAM_COPY_BUFFER_SIZE;
+            if (callback_read(&in_stream.vt, buf, &size) != SZ_OK) {
+                result = SEVENZIP_ERROR_OPEN_FILE;
+                break;
+            }
+            if (size == 0) break;
+            if (counting_write(&out_stream.vt, buf, size) != size) {
+                result = SEVENZIP_ERROR_COMPRESS;
+                break;
+            }
+        }
+        free(buf);
+        if (result != SEVENZIP_OK) goto cleanup;
+    } else {
+        CLzma2EncHandle enc = Lzma2Enc_Create(&g_Alloc, &g_Alloc);
+        if (!enc) {
+            result = SEVENZIP_ERROR_MEMORY;
+            goto cleanup;
+        }
+        
+        CLzma2EncProps props;
+        set_lzma2_props(&props, level, options);
+        SRes res = Lzma2Enc_SetProps(enc, &props);
+        if (res == SZ_OK) {
+            lzma2_prop_byte = Lzma2Enc_WriteProperties(enc);
+            res = Lzma2Enc_Encode2(enc, &out_stream.vt, NULL, NULL,
+                                   &in_stream.vt, NULL, 0, NULL);
+        }
+        Lzma2Enc_Destroy(enc);
+        
+        if (res != SZ_OK) {
+            result = in_stream.failed ? SEVENZIP_ERROR_OPEN_FILE : SEVENZIP_ERROR_COMPRESS;
+            goto cleanup;
+        }
+    }
+    
+    uint64_t mtime = (uint64_t)time(NULL) * 10000000ULL + 116444736000000000ULL;
+    result = write_single_entry_header(
+        f, entry_name, use_copy_codec, lzma2_prop_byte,
+        out_stream.written, in_stream.size, CRC_GET_DIGEST(in_stream.crc), mtime);
+    
+cleanup:
+    if (fclose(f) != 0 && result == SEVENZIP_OK) {
+        result = SEVENZIP_ERROR_COMPRESS;
+    }
+    if (result != SEVENZIP_OK) {
+        remove(archive_path);
+    }
+    return result;
+}
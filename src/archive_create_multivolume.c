@@ -9,11 +9,13 @@
  */
 
 #include "../include/7z_ffi.h"
+#include "ffi_internal.h"
 #include "../lzma/C/7zFile.h"
 #include "../lzma/C/7zTypes.h"
 #include "../lzma/C/7zCrc.h"
 #include "../lzma/C/Lzma2Enc.h"
 #include "../lzma/C/Alloc.h"
+#include "../lzma/C/CpuArch.h"
 
 #include <stdio.h>
 #include <stdlib.h>
@@ -49,26 +51,6 @@
 
 static const Byte k7zSignature[k7zSignature_Size] = {'7', 'z', 0xBC, 0xAF, 0x27, 0x1C};
 
-/* Property IDs */
-typedef enum {
-    k7zIdEnd = 0x00,
-    k7zIdHeader = 0x01,
-    k7zIdMainStreamsInfo = 0x04,
-    k7zIdFilesInfo = 0x05,
-    k7zIdPackInfo = 0x06,
-    k7zIdUnpackInfo = 0x07,
-    k7zIdSubStreamsInfo = 0x08,
-    k7zIdSize = 0x09,
-    k7zIdCRC = 0x0A,
-    k7zIdFolder = 0x0B,
-    k7zIdCodersUnpackSize = 0x0C,
-    k7zIdNumUnpackStream = 0x0D,
-    k7zIdEmptyStream = 0x0E,
-    k7zIdName = 0x11,
-    k7zIdMTime = 0x14,
-    k7zIdWinAttrib = 0x15
-} E7zIdEnum;
-
 /* File entry for multi-volume archives */
 typedef struct {
     char* name;
@@ -244,6 +226,7 @@ typedef struct {
     uint64_t current_volume_size;
     uint64_t max_volume_size;
     char base_path[1024];
+    int volume_naming;
     
     /* Compressed data tracking */
     uint64_t total_packed_size;
@@ -253,108 +236,63 @@ typedef struct {
     void* user_data;
     uint64_t total_size;
     uint64_t bytes_written;
+    
+    /* Set when a progress callback requested an abort */
+    int cancelled;
+    
+    /* Finished-volume notification */
+    SevenZipVolumeCallback volume_callback;
+    void* volume_user_data;
 } MultiVolumeContext;
 
-/* Helper: Write number in 7z variable-length encoding (little-endian for bytes after first)
- * 
- * 7z uses a variable-length encoding where the first byte indicates how many additional
- * bytes follow:
- * - 0x00-0x7F:  1 byte  (7 bits of data)
- * - 0x80-0xBF:  2 bytes (6 bits in first + 8 bits in second = 14 bits)
- * - 0xC0-0xDF:  3 bytes (5 bits in first + 16 bits LE = 21 bits)
- * - 0xE0-0xEF:  4 bytes (4 bits in first + 24 bits LE = 28 bits)
- * - 0xF0-0xF7:  5 bytes (3 bits in first + 32 bits LE = 35 bits)
- * - 0xF8-0xFB:  6 bytes (2 bits in first + 40 bits LE = 42 bits)
- * - 0xFC-0xFD:  7 bytes (1 bit in first + 48 bits LE = 49 bits)
- * - 0xFE:       8 bytes (56 bits LE)
- * - 0xFF:       9 bytes (64 bits LE)
- */
-static void WriteNumber(Byte** buf, uint64_t value) {
-    Byte* p = *buf;
-    
-    if (value < 0x80) {
-        /* 1 byte: 7 bits */
-        *p++ = (Byte)value;
-    }
-    else if (value < ((uint64_t)1 << 14)) {
-        /* 2 bytes: 6 bits in first byte + 8 bits in second = 14 bits */
-        *p++ = (Byte)(0x80 | (value >> 8));
-        *p++ = (Byte)(value & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 21)) {
-        /* 3 bytes: 5 bits in first byte + 16 bits little-endian = 21 bits */
-        *p++ = (Byte)(0xC0 | ((value >> 16) & 0x1F));
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 28)) {
-        /* 4 bytes: 4 bits in first byte + 24 bits little-endian = 28 bits */
-        *p++ = (Byte)(0xE0 | ((value >> 24) & 0x0F));
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 35)) {
-        /* 5 bytes: 3 bits in first byte + 32 bits little-endian = 35 bits */
-        *p++ = (Byte)(0xF0 | ((value >> 32) & 0x07));
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-        *p++ = (Byte)((value >> 24) & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 42)) {
-        /* 6 bytes: 2 bits in first byte + 40 bits little-endian = 42 bits */
-        *p++ = (Byte)(0xF8 | ((value >> 40) & 0x03));
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-        *p++ = (Byte)((value >> 24) & 0xFF);
-        *p++ = (Byte)((value >> 32) & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 49)) {
-        /* 7 bytes: 1 bit in first byte + 48 bits little-endian = 49 bits */
-        *p++ = (Byte)(0xFC | ((value >> 48) & 0x01));
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-        *p++ = (Byte)((value >> 24) & 0xFF);
-        *p++ = (Byte)((value >> 32) & 0xFF);
-        *p++ = (Byte)((value >> 40) & 0xFF);
-    }
-    else if (value < ((uint64_t)1 << 56)) {
-        /* 8 bytes: first byte 0xFE + 56 bits little-endian */
-        *p++ = 0xFE;
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-        *p++ = (Byte)((value >> 24) & 0xFF);
-        *p++ = (Byte)((value >> 32) & 0xFF);
-        *p++ = (Byte)((value >> 40) & 0xFF);
-        *p++ = (Byte)((value >> 48) & 0xFF);
-    }
-    else {
-        /* 9 bytes: first byte 0xFF + 64 bits little-endian */
-        *p++ = 0xFF;
-        *p++ = (Byte)(value & 0xFF);
-        *p++ = (Byte)((value >> 8) & 0xFF);
-        *p++ = (Byte)((value >> 16) & 0xFF);
-        *p++ = (Byte)((value >> 24) & 0xFF);
-        *p++ = (Byte)((value >> 32) & 0xFF);
-        *p++ = (Byte)((value >> 40) & 0xFF);
-        *p++ = (Byte)((value >> 48) & 0xFF);
-        *p++ = (Byte)((value >> 56) & 0xFF);
-    }
-    
-    *buf = p;
+/* Helper: Get volume filename for a SevenZipVolumeNaming scheme (index is 0-based) */
+static void get_volume_filename(char* buffer, size_t size, const char* base, int naming, int index, int is_last) {
+    if (naming != SEVENZIP_VOLUME_NAMING_PART_N && naming != SEVENZIP_VOLUME_NAMING_ZIP_STYLE) {
+        snprintf(buffer, size, "%s.%03d", base, index + 1);
+        return;
+    }
+    
+    /* The other schemes number the stem: backup.7z -> backup.part1.7z, backup.z01 */
+    const char* name = base;
+    for (const char* p = base; *p; p++) {
+        if (*p == '/' || *p == '\\') name = p + 1;
+    }
+    const char* dot = strrchr(name, '.');
+    if (!dot || dot == name) dot = name + strlen(name);
+    int stem_len = (int)(dot - base);
+    
+    if (naming == SEVENZIP_VOLUME_NAMING_PART_N) {
+        snprintf(buffer, size, "%.*s.part%d%s", stem_len, base, index + 1, dot);
+    } else if (is_last) {
+        snprintf(buffer, size, "%.*s.zip", stem_len, base);
+    } else {
+        snprintf(buffer, size, "%.*s.z%02d", stem_len, base, index + 1);
+    }
 }
 
-/* Helper: Get volume filename */
-static void get_volume_filename(char* buffer, size_t size, const char* base, int index) {
-    snprintf(buffer, size, "%s.%03d", base, index + 1);
+/* Helper: Report a closed volume to the volume callback (returns 0 to abort) */
+static int report_volume(MultiVolumeContext* ctx, int index, int is_last, uint64_t size) {
+    if (!ctx->volume_callback) return 1;
+    char vol_path[1280];
+    get_volume_filename(vol_path, sizeof(vol_path), ctx->base_path, ctx->volume_naming, index, is_last);
+    if (ctx->volume_callback(vol_path, size, ctx->volume_user_data) != 0) {
+        ctx->cancelled = 1;
+        return 0;
+    }
+    return 1;
 }
 
 /* Helper: Open new volume file */
 static FILE* open_new_volume(MultiVolumeContext* ctx) {
+    /* The previous volume is full. Unless it is the first one, whose start
+     * header is only written at the end, it is finished: close and report it */
+    if (ctx->volume_callback && ctx->volume_count > 1) {
+        size_t prev = ctx->volume_count - 1;
+        int closed = fclose(ctx->volumes[prev]) == 0;
+        ctx->volumes[prev] = NULL;
+        if (!closed || !report_volume(ctx, (int)prev, 0, ctx->current_volume_size)) return NULL;
+    }
+    
     if (ctx->volume_count >= ctx->volume_capacity) {
         ctx->volume_capacity *= 2;
         FILE** new_vols = (FILE**)realloc(ctx->volumes, ctx->volume_capacity * sizeof(FILE*));
@@ -363,7 +301,7 @@ static FILE* open_new_volume(MultiVolumeContext* ctx) {
     }
     
     char vol_path[1280];
-    get_volume_filename(vol_path, sizeof(vol_path), ctx->base_path, ctx->volume_count);
+    get_volume_filename(vol_path, sizeof(vol_path), ctx->base_path, ctx->volume_naming, (int)ctx->volume_count, 0);
     
     FILE* f = fopen(vol_path, "wb");
     if (!f) return NULL;
@@ -414,6 +352,10 @@ static int write_across_volumes(MultiVolumeContext* ctx, const void* data, size_
                 "",
                 ctx->user_data
             );
+            if (sevenzip_take_abort_request()) {
+                ctx->cancelled = 1;
+                return 0;
+            }
         }
     }
     
@@ -1112,13 +1054,13 @@ static Byte* build_7z_header(
     
     /* PackInfo */
     *p++ = k7zIdPackInfo;
-    WriteNumber(&p, 0);  /* Pack position */
-    WriteNumber(&p, 1);  /* Number of pack streams */
+    sevenzip_write_number(&p, 0);  /* Pack position */
+    sevenzip_write_number(&p, 1);  /* Number of pack streams */
     
     *p++ = k7zIdSize;
     fprintf(stderr, "[DEBUG build_7z_header] total_packed_size = %llu (0x%llX)\n", 
             (unsigned long long)total_packed_size, (unsigned long long)total_packed_size);
-    WriteNumber(&p, total_packed_size);
+    sevenzip_write_number(&p, total_packed_size);
     
     *p++ = k7zIdEnd;
     
@@ -1126,9 +1068,9 @@ static Byte* build_7z_header(
     *p++ = k7zIdUnpackInfo;
     
     *p++ = k7zIdFolder;
-    WriteNumber(&p, 1);  /* One folder */
-    WriteNumber(&p, 0);  /* Not external */
-    WriteNumber(&p, 1);  /* One coder */
+    sevenzip_write_number(&p, 1);  /* One folder */
+    sevenzip_write_number(&p, 0);  /* Not external */
+    sevenzip_write_number(&p, 1);  /* One coder */
     
     /* Check if using Copy/Store method (prop_byte == 0 means Copy) */
     Byte prop_byte = 0x01;  /* Default LZMA2 */
@@ -1164,7 +1106,7 @@ static Byte* build_7z_header(
             total_unpack += files[i].size;
         }
     }
-    WriteNumber(&p, total_unpack);
+    sevenzip_write_number(&p, total_unpack);
     
     *p++ = k7zIdEnd;
     
@@ -1178,7 +1120,7 @@ static Byte* build_7z_header(
     
     /* Always emit NumUnpackStream (like working 7z_create.c) */
     *p++ = k7zIdNumUnpackStream;
-    WriteNumber(&p, num_files);
+    sevenzip_write_number(&p, num_files);
     
     /* Individual file sizes (all but last - last is implied) */
     if (num_files > 1) {
@@ -1186,7 +1128,7 @@ static Byte* build_7z_header(
         size_t written = 0;
         for (size_t i = 0; i < file_count && written < num_files - 1; i++) {
             if (!files[i].is_dir) {
-                WriteNumber(&p, files[i].size);
+                sevenzip_write_number(&p, files[i].size);
                 written++;
             }
         }
@@ -1196,7 +1138,7 @@ static Byte* build_7z_header(
     *p++ = 1;  /* All defined */
     for (size_t i = 0; i < file_count; i++) {
         if (!files[i].is_dir) {
-            memcpy(p, &files[i].crc, 4);
+            SetUi32(p, files[i].crc);
             p += 4;
         }
     }
@@ -1206,44 +1148,52 @@ static Byte* build_7z_header(
     
     /* FilesInfo */
     *p++ = k7zIdFilesInfo;
-    WriteNumber(&p, file_count);
+    sevenzip_write_number(&p, file_count);
+    
+    /* EmptyStream (directories) */
+    if (num_files < file_count) {
+        *p++ = k7zIdEmptyStream;
+        size_t bit_bytes = (file_count + 7) / 8;
+        sevenzip_write_number(&p, bit_bytes);
+        memset(p, 0, bit_bytes);
+        for (size_t i = 0; i < file_count; i++) {
+            if (files[i].is_dir) {
+                p[i / 8] |= (Byte)(0x80 >> (i % 8));
+            }
+        }
+        p += bit_bytes;
+    }
     
     /* Names */
     *p++ = k7zIdName;
     size_t names_size = 0;
     for (size_t i = 0; i < file_count; i++) {
-        names_size += (strlen(files[i].name) + 1) * 2;
+        names_size += sevenzip_write_utf16le_name(files[i].name, NULL);
     }
-    WriteNumber(&p, names_size + 1);
+    sevenzip_write_number(&p, names_size + 1);
     *p++ = 0;  /* Not external */
     
     for (size_t i = 0; i < file_count; i++) {
-        const char* name = files[i].name;
-        while (*name) {
-            *p++ = (Byte)*name++;
-            *p++ = 0;
-        }
-        *p++ = 0;
-        *p++ = 0;
+        p += sevenzip_write_utf16le_name(files[i].name, p);
     }
     
     /* MTime (Modification Time) */
     *p++ = k7zIdMTime;
-    WriteNumber(&p, file_count * 8 + 2);  /* Size: AllDefined(1) + External(1) + 8 bytes per file */
+    sevenzip_write_number(&p, file_count * 8 + 2);  /* Size: AllDefined(1) + External(1) + 8 bytes per file */
     *p++ = 1;  /* All defined */
     *p++ = 0;  /* External = 0 (inline data) */
     for (size_t i = 0; i < file_count; i++) {
-        memcpy(p, &files[i].mtime, 8);
+        SetUi64(p, files[i].mtime);
         p += 8;
     }
     
     /* WinAttrib (Windows Attributes) */
     *p++ = k7zIdWinAttrib;
-    WriteNumber(&p, file_count * 4 + 2);  /* Size: AllDefined(1) + External(1) + 4 bytes per file */
+    sevenzip_write_number(&p, file_count * 4 + 2);  /* Size: AllDefined(1) + External(1) + 4 bytes per file */
     *p++ = 1;  /* All defined */
     *p++ = 0;  /* External = 0 (inline data) */
     for (size_t i = 0; i < file_count; i++) {
-        memcpy(p, &files[i].attrib, 4);
+        SetUi32(p, files[i].attrib);
         p += 4;
     }
     
@@ -1267,6 +1217,9 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
         return SEVENZIP_ERROR_INVALID_PARAM;
     }
     
+    /* Drop any stale abort request from an earlier operation */
+    (void)sevenzip_take_abort_request();
+    
     CrcGenerateTable();
     
     /* Initialize context */
@@ -1274,8 +1227,11 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
     memset(&ctx, 0, sizeof(ctx));
     strncpy(ctx.base_path, archive_path, sizeof(ctx.base_path) - 1);
     ctx.max_volume_size = options->split_size;
+    ctx.volume_naming = options->volume_naming;
     ctx.progress_callback = progress_callback;
     ctx.user_data = user_data;
+    ctx.volume_callback = options->volume_callback;
+    ctx.volume_user_data = options->volume_user_data;
     ctx.volume_capacity = 8;
     ctx.volumes = (FILE**)malloc(ctx.volume_capacity * sizeof(FILE*));
     if (!ctx.volumes) {
@@ -1291,8 +1247,8 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
         return SEVENZIP_ERROR_MEMORY;
     }
     
-    /* Helper to add a single file entry */
-    #define ADD_FILE_ENTRY(full_path_str, relative_name_str) do { \
+    /* Helper to add a single entry, a file or an empty directory */
+    #define ADD_ENTRY(full_path_str, relative_name_str, dir) do { \
         if (file_count >= file_capacity) { \
             file_capacity *= 2; \
             MV_FileEntry* new_files = (MV_FileEntry*)realloc(files, file_capacity * sizeof(MV_FileEntry)); \
@@ -1310,12 +1266,12 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
         entry->full_path = strdup(full_path_str); \
         struct STAT _st; \
         STAT(full_path_str, &_st); \
-        entry->size = _st.st_size; \
+        entry->size = (dir) ? 0 : _st.st_size; \
         entry->mtime = ((uint64_t)_st.st_mtime * 10000000ULL) + 116444736000000000ULL; \
-        entry->attrib = 0x20; \
+        entry->attrib = (dir) ? 0x10 : 0x20; \
         if (!(_st.st_mode & S_IWUSR)) entry->attrib |= 0x01; \
-        entry->is_dir = 0; \
-        ctx.total_size += _st.st_size; \
+        entry->is_dir = (dir); \
+        ctx.total_size += entry->size; \
     } while(0)
     
     /* Process each input path - can be file or directory */
@@ -1328,7 +1284,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
         if (S_ISREG(st.st_mode)) {
             /* Regular file - add directly */
             const char* basename = strrchr(input_paths[i], PATH_SEP);
-            ADD_FILE_ENTRY(input_paths[i], basename ? basename + 1 : input_paths[i]);
+            ADD_ENTRY(input_paths[i], basename ? basename + 1 : input_paths[i], 0);
         }
         else if (S_ISDIR(st.st_mode)) {
             /* Directory - recursively gather files */
@@ -1347,6 +1303,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
             
             while (stack_size > 0) {
                 char* current_dir = dir_stack[--stack_size];
+                int has_children = 0;
                 
 #ifdef _WIN32
                 /* Windows directory iteration */
@@ -1361,6 +1318,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
                         char full_path[PATH_MAX];
                         snprintf(full_path, PATH_MAX, "%s\\%s", current_dir, fd.cFileName);
                         
+                        has_children = 1;
                         if (fd.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY) {
                             if (stack_size >= stack_capacity) {
                                 stack_capacity *= 2;
@@ -1371,7 +1329,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
                             /* Build relative name: base_dir_name + path after base_path */
                             char relative_name[PATH_MAX];
                             snprintf(relative_name, PATH_MAX, "%s%s", base_dir_name, full_path + base_path_len);
-                            ADD_FILE_ENTRY(full_path, relative_name);
+                            ADD_ENTRY(full_path, relative_name, 0);
                         }
                     } while (FindNextFileA(hFind, &fd));
                     FindClose(hFind);
@@ -1391,28 +1349,35 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
                         if (STAT(full_path, &child_st) != 0) continue;
                         
                         if (S_ISDIR(child_st.st_mode)) {
+                            has_children = 1;
                             if (stack_size >= stack_capacity) {
                                 stack_capacity *= 2;
                                 dir_stack = (char**)realloc(dir_stack, stack_capacity * sizeof(char*));
                             }
                             dir_stack[stack_size++] = strdup(full_path);
                         } else if (S_ISREG(child_st.st_mode)) {
+                            has_children = 1;
                             /* Build relative name: base_dir_name + path after base_path */
                             char relative_name[PATH_MAX];
                             snprintf(relative_name, PATH_MAX, "%s%s", base_dir_name, full_path + base_path_len);
-                            ADD_FILE_ENTRY(full_path, relative_name);
+                            ADD_ENTRY(full_path, relative_name, 0);
                         }
                     }
                     closedir(dp);
                 }
 #endif
+                if (!has_children && !options->skip_empty_dirs) {
+                    char relative_name[PATH_MAX];
+                    snprintf(relative_name, PATH_MAX, "%s%s", base_dir_name, current_dir + base_path_len);
+                    ADD_ENTRY(current_dir, relative_name, 1);
+                }
                 free(current_dir);
             }
             free(dir_stack);
         }
     }
     
-    #undef ADD_FILE_ENTRY
+    #undef ADD_ENTRY
     
     if (file_count == 0) {
         free(files);
@@ -1446,7 +1411,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
             lzma_level = 9;
             break;
         default:
-            lzma_level = 5;
+            lzma_level = SEVENZIP_RAW_LEVEL(level);
     }
     props.lzmaProps.level = lzma_level;
     
@@ -1577,16 +1542,17 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
     
     /* Build the StartHeader structure (NextHeaderOffset + NextHeaderSize + NextHeaderCRC) */
     Byte start_header_buf[20];
-    memcpy(start_header_buf, &next_header_offset, 8);
-    memcpy(start_header_buf + 8, &next_header_size, 8);
-    memcpy(start_header_buf + 16, &header_crc, 4);
+    SetUi64(start_header_buf, next_header_offset);
+    SetUi64(start_header_buf + 8, next_header_size);
+    SetUi32(start_header_buf + 16, header_crc);
     
     /* Calculate StartHeader CRC */
-    uint32_t start_header_crc = CrcCalc(start_header_buf, 20);
+    Byte start_header_crc[4];
+    SetUi32(start_header_crc, CrcCalc(start_header_buf, 20));
     
     /* Flush all volumes before seeking */
     for (size_t i = 0; i < ctx.volume_count; i++) {
-        fflush(ctx.volumes[i]);
+        if (ctx.volumes[i]) fflush(ctx.volumes[i]);
     }
     
     /* Go back and write start header in first volume (ALWAYS use ctx.volumes[0] after realloc!) */
@@ -1594,7 +1560,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
     fseek(first_vol, start_header_pos, SEEK_SET);
     
     /* Write StartHeaderCRC */
-    fwrite(&start_header_crc, 4, 1, first_vol);
+    fwrite(start_header_crc, 4, 1, first_vol);
     
     /* Write StartHeader data */
     fwrite(start_header_buf, 20, 1, first_vol);
@@ -1602,7 +1568,36 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
     
     /* Close all volumes */
     for (size_t i = 0; i < ctx.volume_count; i++) {
-        fclose(ctx.volumes[i]);
+        if (ctx.volumes[i]) fclose(ctx.volumes[i]);
+        ctx.volumes[i] = NULL;
+    }
+    
+    /* Zip-style splits only learn which volume is last now: rename it to .zip */
+    if (ctx.volume_naming == SEVENZIP_VOLUME_NAMING_ZIP_STYLE && ctx.volume_count > 0) {
+        char from[1280], to[1280];
+        int last = (int)ctx.volume_count - 1;
+        get_volume_filename(from, sizeof(from), ctx.base_path, ctx.volume_naming, last, 0);
+        get_volume_filename(to, sizeof(to), ctx.base_path, ctx.volume_naming, last, 1);
+        remove(to);
+        if (rename(from, to) != 0) {
+            for (size_t i = 0; i < file_count; i++) {
+                free(files[i].name);
+                free(files[i].full_path);
+            }
+            free(files);
+            free(ctx.volumes);
+            return SEVENZIP_ERROR_COMPRESS;
+        }
+    }
+    
+    /* Report the volumes still open until now: the first, then the last */
+    if (ctx.volume_count > 0) {
+        int last = (int)ctx.volume_count - 1;
+        uint64_t first_size = last == 0 ? ctx.current_volume_size : ctx.max_volume_size;
+        if (!report_volume(&ctx, 0, last == 0, first_size) ||
+            (last > 0 && !report_volume(&ctx, last, 1, ctx.current_volume_size))) {
+            goto error;
+        }
     }
     
     /* Cleanup */
@@ -1617,7 +1612,7 @@ SevenZipErrorCode sevenzip_create_multivolume_7z_complete(
     
 error:
     for (size_t i = 0; i < ctx.volume_count; i++) {
-        fclose(ctx.volumes[i]);
+        if (ctx.volumes[i]) fclose(ctx.volumes[i]);
     }
     for (size_t i = 0; i < file_count; i++) {
         free(files[i].name);
@@ -1625,5 +1620,5 @@ error:
     }
     free(files);
     free(ctx.volumes);
-    return SEVENZIP_ERROR_COMPRESS;
+    return ctx.cancelled ? SEVENZIP_ERROR_CANCELLED : SEVENZIP_ERROR_COMPRESS;
 }
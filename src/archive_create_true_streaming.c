@@ -21,9 +21,11 @@
  */
 
 #include "../include/7z_ffi.h"
+#include "ffi_internal.h"
 #include "Lzma2Enc.h"
 #include "7zCrc.h"
 #include "Alloc.h"
+#include "CpuArch.h"
 
 #include <stdio.h>
 #include <stdlib.h>
@@ -92,6 +94,7 @@ typedef struct {
     uint64_t packed_size;     /* Total compressed data size */
     unsigned char* chunk_buffer;  /* Reusable chunk read buffer */
     size_t chunk_size;
+    int skip_empty_dirs;      /* Leave out directories with nothing kept beneath them */
 } StreamingArchiveBuilder;
 
 /* Forward declarations */
@@ -249,9 +252,19 @@ static SevenZipErrorCode scan_file(
             builder, full_path, relative_name,
             0, unix_to_filetime(st.st_mtime), (uint32_t)st.st_mode, 1);
         if (err != SEVENZIP_OK) return err;
+        size_t dir_index = builder->file_count - 1;
         
         /* Recursively scan contents */
-        return scan_directory_recursive(builder, full_path, relative_name);
+        err = scan_directory_recursive(builder, full_path, relative_name);
+        if (err != SEVENZIP_OK) return err;
+        
+        /* Nothing was added beneath it, so it is the last entry */
+        if (builder->skip_empty_dirs && builder->file_count == dir_index + 1) {
+            free(builder->files[dir_index].name);
+            free(builder->files[dir_index].full_path);
+            builder->file_count--;
+        }
+        return SEVENZIP_OK;
     } else if (S_ISREG(st.st_mode)) {
         /* Add regular file entry */
         return builder_add_file(
@@ -521,6 +534,11 @@ static SevenZipErrorCode compress_files_streaming(
             
             /* Update progress */
             update_progress(builder, file->name, file_bytes_read, file->size);
+            if (builder->progress_callback && sevenzip_take_abort_request()) {
+                fclose(input);
+                Lzma2Enc_Destroy(enc);
+                return SEVENZIP_ERROR_CANCELLED;
+            }
         }
         
         file->crc = CRC_GET_DIGEST(crc);
@@ -570,7 +588,9 @@ static SevenZipErrorCode write_7z_archive(
     
     /* Start header (24 bytes): NextHeaderOffset, NextHeaderSize, NextHeaderCRC */
     uint64_t next_header_offset = data_size;  /* Header comes after data */
-    fwrite(&next_header_offset, 8, 1, archive);
+    unsigned char offset_le[8];
+    SetUi64(offset_le, next_header_offset);
+    fwrite(offset_le, 8, 1, archive);
     
     /* Placeholder for header size and CRC */
     uint64_t next_header_size = 0;
@@ -687,7 +707,7 @@ static SevenZipErrorCode write_7z_archive(
     /* Calculate names size (UTF-16LE + null terminators) */
     size_t names_size = 1;  /* External flag byte */
     for (size_t i = 0; i < builder->file_count; i++) {
-        names_size += (strlen(builder->files[i].name) + 1) * 2;
+        names_size += sevenzip_write_utf16le_name(builder->files[i].name, NULL);
     }
     write_number(&p, names_size);
     
@@ -695,13 +715,7 @@ static SevenZipErrorCode write_7z_archive(
     
     /* Write UTF-16LE names */
     for (size_t i = 0; i < builder->file_count; i++) {
-        const char* name = builder->files[i].name;
-        while (*name) {
-            *p++ = (unsigned char)*name;  /* Low byte */
-            *p++ = 0;                      /* High byte (ASCII only) */
-            name++;
-        }
-        *p++ = 0; *p++ = 0;  /* Null terminator */
+        p += sevenzip_write_utf16le_name(builder->files[i].name, p);
     }
     
     /* Empty stream property (directories) */
@@ -754,20 +768,20 @@ static SevenZipErrorCode write_7z_archive(
     next_header_crc = CrcCalc(header, header_size);
     next_header_size = header_size;
     
-    /* Update start header */
+    /* Update start header (little-endian on any host) */
+    unsigned char start_header[20];
+    SetUi64(start_header, next_header_offset);
+    SetUi64(start_header + 8, next_header_size);
+    SetUi32(start_header + 16, next_header_crc);
     fseek(archive, header_size_pos, SEEK_SET);
-    fwrite(&next_header_size, 8, 1, archive);
-    fwrite(&next_header_crc, 4, 1, archive);
+    fwrite(start_header + 8, 12, 1, archive);
     
     /* Calculate and write start header CRC */
-    unsigned char start_header[20];
-    memcpy(start_header, &next_header_offset, 8);
-    memcpy(start_header + 8, &next_header_size, 8);
-    memcpy(start_header + 16, &next_header_crc, 4);
-    start_header_crc = CrcCalc(start_header, 20);
+    unsigned char start_header_crc_le[4];
+    SetUi32(start_header_crc_le, CrcCalc(start_header, 20));
     
     fseek(archive, start_header_pos, SEEK_SET);
-    fwrite(&start_header_crc, 4, 1, archive);
+    fwrite(start_header_crc_le, 4, 1, archive);
     
     free(header);
     fclose(archive);
@@ -807,6 +821,9 @@ SevenZipErrorCode sevenzip_create_7z_true_streaming(
     
     fprintf(stderr, "[streaming] Starting true streaming archive creation: %s\n", archive_path);
     
+    /* Drop any stale abort request from an earlier operation */
+    (void)sevenzip_take_abort_request();
+    
     /* Initialize builder */
     StreamingArchiveBuilder builder;
     builder_init(&builder);
@@ -818,6 +835,7 @@ SevenZipErrorCode sevenzip_create_7z_true_streaming(
     if (options && options->chunk_size > 0) {
         builder.chunk_size = (size_t)options->chunk_size;
     }
+    builder.skip_empty_dirs = options ? options->skip_empty_dirs : 0;
     
     /* Phase 1: Scan all inputs and gather metadata */
     fprintf(stderr, "[streaming] Phase 1: Scanning input paths...\n");
@@ -844,8 +862,17 @@ SevenZipErrorCode sevenzip_create_7z_true_streaming(
     fprintf(stderr, "[streaming] Phase 2: Compressing files...\n");
     
     char temp_path[MAX_PATH_LENGTH];
-    const char* temp_dir = options && options->temp_dir ? options->temp_dir : "/tmp";
-    snprintf(temp_path, sizeof(temp_path), "%s/7z_stream_%d.tmp", temp_dir, (int)time(NULL));
+    if (options && options->temp_file_callback) {
+        temp_path[0] = '\0';
+        if (options->temp_file_callback(temp_path, sizeof(temp_path), options->temp_file_user_data) != 0) {
+            builder_free(&builder);
+            return SEVENZIP_ERROR_OPEN_FILE;
+        }
+        temp_path[sizeof(temp_path) - 1] = '\0';
+    } else {
+        const char* temp_dir = options && options->temp_dir ? options->temp_dir : "/tmp";
+        snprintf(temp_path, sizeof(temp_path), "%s/7z_stream_%d.tmp", temp_dir, (int)time(NULL));
+    }
     
     FILE* temp_file = fopen(temp_path, "w+b");
     if (!temp_file) {
@@ -1,5 +1,6 @@
 #include "7z_ffi.h"
 #include "7z.h"
+#include "ffi_internal.h"
 #include "7zAlloc.h"
 #include "7zBuf.h"
 #include "7zCrc.h"
@@ -87,61 +88,35 @@ static char* build_output_path(const char* output_dir, const char* filename) {
     return path;
 }
 
-SevenZipErrorCode sevenzip_extract(
-    const char* archive_path,
+SevenZipErrorCode sevenzip_extract_stream_internal(
+    ILookInStreamPtr stream,
     const char* output_dir,
     const char* password,
+    const uint8_t* key,
     SevenZipProgressCallback progress_callback,
+    void (*entry_started)(const char* name, void* user_data),
     void* user_data
 ) {
-    if (!archive_path || !output_dir) {
-        return SEVENZIP_ERROR_INVALID_PARAM;
-    }
-    
     /* Initialize CRC tables */
     CrcGenerateTable();
     
-    /* Open archive file */
-    CFileInStream archive_stream;
-    CLookToRead2 look_stream;
-    const size_t kInputBufSize = ((size_t)1 << 18);
-    
-    if (InFile_Open(&archive_stream.file, archive_path) != 0) {
-        return SEVENZIP_ERROR_OPEN_FILE;
-    }
-    
-    FileInStream_CreateVTable(&archive_stream);
-    
     /* Allocators */
     ISzAlloc alloc_imp = { SzAlloc, SzFree };
     ISzAlloc alloc_temp = { SzAllocTemp, SzFreeTemp };
     
-    /* Initialize look stream */
-    LookToRead2_CreateVTable(&look_stream, False);
-    look_stream.buf = (Byte *)ISzAlloc_Alloc(&alloc_imp, kInputBufSize);
-    if (!look_stream.buf) {
-        File_Close(&archive_stream.file);
-        return SEVENZIP_ERROR_MEMORY;
-    }
-    look_stream.bufSize = kInputBufSize;
-    look_stream.realStream = &archive_stream.vt;
-    LookToRead2_INIT(&look_stream);
-    
     /* Initialize archive database */
     CSzArEx db;
     SzArEx_Init(&db);
     
     /* Open archive */
-    SRes res = SzArEx_Open(&db, &look_stream.vt, &alloc_imp, &alloc_temp);
+    SRes res = SzArEx_Open(&db, stream, &alloc_imp, &alloc_temp);
     if (res != SZ_OK) {
-        File_Close(&archive_stream.file);
         SzArEx_Free(&db, &alloc_imp);
         return SEVENZIP_ERROR_INVALID_ARCHIVE;
     }
     
     /* Create output directory */
     if (create_directory_recursive(output_dir) != 0) {
-        File_Close(&archive_stream.file);
         SzArEx_Free(&db, &alloc_imp);
         return SEVENZIP_ERROR_OPEN_FILE;
     }
@@ -168,18 +143,23 @@ SevenZipErrorCode sevenzip_extract(
             
             SzArEx_GetFileNameUtf16(&db, i, temp);
             
-            /* Convert UTF-16 to UTF-8 (simplified) */
-            char* filename = (char*)malloc(len);
+            char* filename = sevenzip_name_from_utf16(temp, len);
+            free(temp);
             if (!filename) {
-                free(temp);
                 error_code = SEVENZIP_ERROR_MEMORY;
                 break;
             }
             
-            for (size_t j = 0; j < len; j++) {
-                filename[j] = (char)(temp[j] < 256 ? temp[j] : '?');
+            /* Never write outside output_dir (e.g. "../x") */
+            if (!sevenzip_is_contained_name(filename)) {
+                free(filename);
+                error_code = SEVENZIP_ERROR_INVALID_ARCHIVE;
+                break;
+            }
+            
+            if (entry_started) {
+                entry_started(filename, user_data);
             }
-            free(temp);
             
             /* Check if directory */
             BoolInt is_dir = SzArEx_IsDir(&db, i);
@@ -199,7 +179,7 @@ SevenZipErrorCode sevenzip_extract(
                 free(output_path);
             } else {
                 /* Extract file */
-                res = SzArEx_Extract(&db, &look_stream.vt, i,
+                res = sevenzip_extract_entry_internal(&db, stream, i, password, key,
                                     &block_index, &out_buffer, &out_buffer_size,
                                     &offset, &out_size_processed,
                                     &alloc_imp, &alloc_temp);
@@ -210,6 +190,27 @@ SevenZipErrorCode sevenzip_extract(
                     break;
                 }
                 
+                /* A hard link entry gets the content of the entry it names */
+                UInt32 target;
+                int link = sevenzip_hardlink_target_internal(&db, i, out_buffer + offset,
+                                                             out_size_processed, &target);
+                if (link < 0) {
+                    free(output_path);
+                    error_code = SEVENZIP_ERROR_INVALID_ARCHIVE;
+                    break;
+                }
+                if (link) {
+                    res = sevenzip_extract_entry_internal(&db, stream, target, password, key,
+                                        &block_index, &out_buffer, &out_buffer_size,
+                                        &offset, &out_size_processed,
+                                        &alloc_imp, &alloc_temp);
+                    if (res != SZ_OK) {
+                        free(output_path);
+                        error_code = SEVENZIP_ERROR_EXTRACT;
+                        break;
+                    }
+                }
+                
                 /* Create parent directories */
                 char* last_sep = strrchr(output_path, PATH_SEPARATOR);
                 if (last_sep) {
@@ -239,6 +240,10 @@ SevenZipErrorCode sevenzip_extract(
             /* Progress callback */
             if (progress_callback) {
                 progress_callback(i + 1, db.NumFiles, user_data);
+                if (sevenzip_take_abort_request()) {
+                    error_code = SEVENZIP_ERROR_CANCELLED;
+                    break;
+                }
             }
         }
     }
@@ -247,14 +252,82 @@ SevenZipErrorCode sevenzip_extract(
     if (out_buffer) {
         ISzAlloc_Free(&alloc_imp, out_buffer);
     }
+    SzArEx_Free(&db, &alloc_imp);
+    
+    return error_code;
+}
+
+/* Body of sevenzip_extract and sevenzip_extract_with_key */
+static SevenZipErrorCode extract_file(
+    const char* archive_path,
+    const char* output_dir,
+    const char* password,
+    const uint8_t* key,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    if (!archive_path || !output_dir) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Drop any stale abort request from an earlier operation */
+    (void)sevenzip_take_abort_request();
+    
+    /* Open archive file */
+    CFileInStream archive_stream;
+    CLookToRead2 look_stream;
+    const size_t kInputBufSize = ((size_t)1 << 18);
+    
+    if (InFile_Open(&archive_stream.file, archive_path) != 0) {
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    
+    FileInStream_CreateVTable(&archive_stream);
+    
+    /* Initialize look stream */
+    ISzAlloc alloc_imp = { SzAlloc, SzFree };
+    LookToRead2_CreateVTable(&look_stream, False);
+    look_stream.buf = (Byte *)ISzAlloc_Alloc(&alloc_imp, kInputBufSize);
+    if (!look_stream.buf) {
+        File_Close(&archive_stream.file);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    look_stream.bufSize = kInputBufSize;
+    look_stream.realStream = &archive_stream.vt;
+    LookToRead2_INIT(&look_stream);
+    
+    SevenZipErrorCode error_code = sevenzip_extract_stream_internal(
+        &look_stream.vt, output_dir, password, key, progress_callback, NULL, user_data);
     
     ISzAlloc_Free(&alloc_imp, look_stream.buf);
-    SzArEx_Free(&db, &alloc_imp);
     File_Close(&archive_stream.file);
     
     return error_code;
 }
 
+SevenZipErrorCode sevenzip_extract(
+    const char* archive_path,
+    const char* output_dir,
+    const char* password,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    return extract_file(archive_path, output_dir, password, NULL, progress_callback, user_data);
+}
+
+SevenZipErrorCode sevenzip_extract_with_key(
+    const char* archive_path,
+    const char* output_dir,
+    const uint8_t* key,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    if (!key) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    return extract_file(archive_path, output_dir, NULL, key, progress_callback, user_data);
+}
+
 SevenZipErrorCode sevenzip_extract_files(
     const char* archive_path,
     const char* output_dir,
@@ -6,6 +6,7 @@
 
 #include "../include/7z_ffi.h"
 #include "7z.h"
+#include "ffi_internal.h"
 #include "7zAlloc.h"
 #include "7zBuf.h"
 #include "7zCrc.h"
@@ -20,13 +21,9 @@
 #ifdef _WIN32
     #include <windows.h>
     #include <direct.h>
-    #define MKDIR(path) _mkdir(path)
-    #define PATH_SEP '\\'
 #else
     #include <unistd.h>
     #include <sys/types.h>
-    #define MKDIR(path) mkdir(path, 0755)
-    #define PATH_SEP '/'
 #endif
 
 /* Multi-volume input stream */
@@ -135,6 +132,37 @@ static SRes MultiVolumeInStream_Seek(ISeekInStreamPtr pp, Int64 *pos, ESzSeek or
     return SZ_OK;
 }
 
+/* Set up the stream over already opened volumes; takes ownership of both arrays */
+static int init_volume_stream(MultiVolumeInStream* stream, FILE** volumes, uint64_t* sizes, int count) {
+    // Calculate cumulative offsets
+    uint64_t* offsets = (uint64_t*)malloc((count + 1) * sizeof(uint64_t));
+    if (!offsets) {
+        for (int i = 0; i < count; i++) fclose(volumes[i]);
+        free(volumes);
+        free(sizes);
+        return 0;
+    }
+    
+    offsets[0] = 0;
+    for (int i = 0; i < count; i++) {
+        offsets[i + 1] = offsets[i] + sizes[i];
+    }
+    
+    // Initialize stream
+    stream->vt.Read = MultiVolumeInStream_Read;
+    stream->vt.Seek = MultiVolumeInStream_Seek;
+    stream->volumes = volumes;
+    stream->volume_count = count;
+    stream->volume_sizes = sizes;
+    stream->volume_offsets = offsets;
+    stream->total_size = offsets[count];
+    stream->current_volume = 0;
+    stream->current_pos = 0;
+    stream->bytes_extracted = 0;
+    
+    return 1;
+}
+
 /* Detect and open all volumes of a split archive */
 static int open_split_volumes(const char* first_volume_path, MultiVolumeInStream* stream) {
     // Parse base path (remove .001, .002, etc.)
@@ -215,33 +243,38 @@ static int open_split_volumes(const char* first_volume_path, MultiVolumeInStream
         return 0;
     }
     
-    // Calculate cumulative offsets
-    uint64_t* offsets = (uint64_t*)malloc((count + 1) * sizeof(uint64_t));
-    if (!offsets) {
-        for (int i = 0; i < count; i++) fclose(volumes[i]);
+    return init_volume_stream(stream, volumes, sizes, count);
+}
+
+/* Open an explicit, ordered list of volumes, whatever their names */
+static int open_listed_volumes(const char** volume_paths, size_t volume_count, MultiVolumeInStream* stream) {
+    if (volume_count == 0 || volume_count > INT32_MAX) {
+        return 0;
+    }
+    
+    FILE** volumes = (FILE**)calloc(volume_count, sizeof(FILE*));
+    uint64_t* sizes = (uint64_t*)malloc(volume_count * sizeof(uint64_t));
+    if (!volumes || !sizes) {
         free(volumes);
         free(sizes);
         return 0;
     }
     
-    offsets[0] = 0;
-    for (int i = 0; i < count; i++) {
-        offsets[i + 1] = offsets[i] + sizes[i];
+    for (size_t i = 0; i < volume_count; i++) {
+        volumes[i] = volume_paths[i] ? fopen(volume_paths[i], "rb") : NULL;
+        if (!volumes[i]) {
+            for (size_t j = 0; j < i; j++) fclose(volumes[j]);
+            free(volumes);
+            free(sizes);
+            return 0;
+        }
+        
+        fseek(volumes[i], 0, SEEK_END);
+        sizes[i] = (uint64_t)ftell(volumes[i]);
+        fseek(volumes[i], 0, SEEK_SET);
     }
     
-    // Initialize stream
-    stream->vt.Read = MultiVolumeInStream_Read;
-    stream->vt.Seek = MultiVolumeInStream_Seek;
-    stream->volumes = volumes;
-    stream->volume_count = count;
-    stream->volume_sizes = sizes;
-    stream->volume_offsets = offsets;
-    stream->total_size = offsets[count];
-    stream->current_volume = 0;
-    stream->current_pos = 0;
-    stream->bytes_extracted = 0;
-    
-    return 1;
+    return init_volume_stream(stream, volumes, sizes, (int)volume_count);
 }
 
 /* Close all volumes */
@@ -258,6 +291,48 @@ static void close_split_volumes(MultiVolumeInStream* stream) {
     free(stream->volume_offsets);
 }
 
+/* Name the entry being written in byte progress reports */
+static void volume_entry_started(const char* name, void* user_data) {
+    MultiVolumeInStream* in_stream = (MultiVolumeInStream*)user_data;
+    strncpy(in_stream->current_file, name, sizeof(in_stream->current_file) - 1);
+}
+
+/* Extract every entry from an opened volume stream, then close it */
+static SevenZipErrorCode extract_volume_stream(
+    MultiVolumeInStream* in_stream,
+    const char* output_dir,
+    const char* password,
+    SevenZipBytesProgressCallback progress_callback,
+    void* user_data
+) {
+    in_stream->progress_callback = progress_callback;
+    in_stream->user_data = user_data;
+    in_stream->total_bytes = in_stream->total_size;
+    
+    CLookToRead2 look_stream;
+    ISzAlloc alloc_imp = {SzAlloc, SzFree};
+    
+    LookToRead2_CreateVTable(&look_stream, False);
+    look_stream.buf = (Byte*)ISzAlloc_Alloc(&alloc_imp, (1 << 18)); // 256KB buffer
+    if (!look_stream.buf) {
+        close_split_volumes(in_stream);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    look_stream.bufSize = (1 << 18);
+    look_stream.realStream = (ISeekInStreamPtr)in_stream;
+    LookToRead2_INIT(&look_stream);
+    
+    // Same checked extraction as a single-file archive; an invalid archive
+    // here means missing, extra or misordered volumes
+    SevenZipErrorCode result = sevenzip_extract_stream_internal(
+        &look_stream.vt, output_dir, password, NULL, NULL, volume_entry_started, in_stream);
+    
+    ISzAlloc_Free(&alloc_imp, look_stream.buf);
+    close_split_volumes(in_stream);
+    
+    return result;
+}
+
 /**
  * Extract a 7z archive with streaming decompression and split volume support
  */
@@ -272,30 +347,65 @@ SevenZipErrorCode sevenzip_extract_streaming(
         return SEVENZIP_ERROR_INVALID_PARAM;
     }
     
-    // Initialize CRC tables
-    CrcGenerateTable();
-    
     // Open split volumes
     MultiVolumeInStream in_stream = {0};
     if (!open_split_volumes(archive_path, &in_stream)) {
         return SEVENZIP_ERROR_OPEN_FILE;
     }
     
-    in_stream.progress_callback = progress_callback;
-    in_stream.user_data = user_data;
-    in_stream.total_bytes = in_stream.total_size;
+    return extract_volume_stream(&in_stream, output_dir, password, progress_callback, user_data);
+}
+
+/**
+ * Extract a 7z archive from an explicit, ordered list of volumes
+ */
+SevenZipErrorCode sevenzip_extract_volumes(
+    const char** volume_paths,
+    size_t volume_count,
+    const char* output_dir,
+    const char* password,
+    SevenZipBytesProgressCallback progress_callback,
+    void* user_data
+) {
+    if (!volume_paths || volume_count == 0 || !output_dir) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    MultiVolumeInStream in_stream = {0};
+    if (!open_listed_volumes(volume_paths, volume_count, &in_stream)) {
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    
+    return extract_volume_stream(&in_stream, output_dir, password, progress_callback, user_data);
+}
+
+/**
+ * Read archive-level totals from a split archive's header
+ */
+SevenZipErrorCode sevenzip_get_split_archive_info(
+    const char* first_volume_path,
+    const char* password,
+    SevenZipArchiveInfo* info
+) {
+    (void)password;
+    if (!first_volume_path || !info) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    MultiVolumeInStream in_stream = {0};
+    if (!open_split_volumes(first_volume_path, &in_stream)) {
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
     
-    // Create output directory
-    MKDIR(output_dir);
+    CrcGenerateTable();
     
-    // Initialize 7z structures
     CLookToRead2 look_stream;
     CSzArEx db;
     ISzAlloc alloc_imp = {SzAlloc, SzFree};
     ISzAlloc alloc_temp_imp = {SzAllocTemp, SzFreeTemp};
     
     LookToRead2_CreateVTable(&look_stream, False);
-    look_stream.buf = (Byte*)ISzAlloc_Alloc(&alloc_imp, (1 << 18)); // 256KB buffer
+    look_stream.buf = (Byte*)ISzAlloc_Alloc(&alloc_imp, (1 << 18));
     if (!look_stream.buf) {
         close_split_volumes(&in_stream);
         return SEVENZIP_ERROR_MEMORY;
@@ -305,63 +415,14 @@ SevenZipErrorCode sevenzip_extract_streaming(
     LookToRead2_INIT(&look_stream);
     
     SzArEx_Init(&db);
-    
-    // Open archive
     SRes res = SzArEx_Open(&db, &look_stream.vt, &alloc_imp, &alloc_temp_imp);
-    
     if (res == SZ_OK) {
-        // Extract all files
-        UInt32 block_index = 0xFFFFFFFF;
-        Byte* out_buffer = NULL;
-        size_t out_buffer_size = 0;
-        
-        for (UInt32 i = 0; i < db.NumFiles; i++) {
-            size_t offset = 0;
-            size_t out_size_processed = 0;
-            
-            // Get file info
-            size_t len = SzArEx_GetFileNameUtf16(&db, i, NULL);
-            UInt16* temp = (UInt16*)malloc(len * sizeof(UInt16));
-            if (temp) {
-                SzArEx_GetFileNameUtf16(&db, i, temp);
-                
-                // Convert UTF-16 to UTF-8 (simplified)
-                char file_name[512] = {0};
-                for (size_t j = 0; j < len && j < 511; j++) {
-                    file_name[j] = (char)temp[j];
-                }
-                
-                strncpy(in_stream.current_file, file_name, sizeof(in_stream.current_file) - 1);
-                free(temp);
-                
-                // Extract file
-                res = SzArEx_Extract(&db, &look_stream.vt, i,
-                                    &block_index, &out_buffer, &out_buffer_size,
-                                    &offset, &out_size_processed,
-                                    &alloc_imp, &alloc_temp_imp);
-                
-                if (res == SZ_OK && !SzArEx_IsDir(&db, i)) {
-                    // Write file
-                    char out_path[1024];
-                    snprintf(out_path, sizeof(out_path), "%s%c%s", 
-                            output_dir, PATH_SEP, file_name);
-                    
-                    FILE* out_file = fopen(out_path, "wb");
-                    if (out_file) {
-                        fwrite(out_buffer + offset, 1, out_size_processed, out_file);
-                        fclose(out_file);
-                    }
-                }
-            }
-        }
-        
-        ISzAlloc_Free(&alloc_imp, out_buffer);
+        sevenzip_fill_archive_info_internal(&db, info);
     }
     
-    // Cleanup
     SzArEx_Free(&db, &alloc_imp);
     ISzAlloc_Free(&alloc_imp, look_stream.buf);
     close_split_volumes(&in_stream);
     
-    return (res == SZ_OK) ? SEVENZIP_OK : SEVENZIP_ERROR_EXTRACT;
+    return (res == SZ_OK) ? SEVENZIP_OK : SEVENZIP_ERROR_INVALID_ARCHIVE;
 }
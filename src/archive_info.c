@@ -0,0 +1,96 @@
+#include "7z_ffi.h"
+#include "7z.h"
+#include "7zAlloc.h"
+#include "7zCrc.h"
+#include "7zFile.h"
+#include "ffi_internal.h"
+
+#include <string.h>
+
+void sevenzip_fill_archive_info_internal(const CSzArEx* db, SevenZipArchiveInfo* info) {
+    memset(info, 0, sizeof(*info));
+    info->num_entries = db->NumFiles;
+    info->num_folders = db->db.NumFolders;
+    for (UInt32 i = 0; i < db->NumFiles; i++) {
+        if (SzArEx_IsDir(db, i)) {
+            info->num_directories++;
+        }
+    }
+    
+    /* Per-folder unpack sizes already add up to the archive total */
+    for (UInt32 i = 0; i < db->db.NumFolders; i++) {
+        info->unpacked_size += SzAr_GetFolderUnpackSize(&db->db, i);
+    }
+    
+    /* No folder records: fall back to summing the individual entries */
+    if (db->db.NumFolders == 0) {
+        for (UInt32 i = 0; i < db->NumFiles; i++) {
+            info->unpacked_size += SzArEx_GetFileSize(db, i);
+        }
+    }
+    
+    if (db->db.NumPackStreams > 0) {
+        info->packed_size = db->db.PackPositions[db->db.NumPackStreams];
+    }
+}
+
+SevenZipErrorCode sevenzip_get_archive_info(
+    const char* archive_path,
+    const char* password,
+    SevenZipArchiveInfo* info
+) {
+    if (!archive_path || !info) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Initialize CRC tables */
+    CrcGenerateTable();
+    
+    /* Open archive file */
+    CFileInStream archive_stream;
+    CLookToRead2 look_stream;
+    const size_t kInputBufSize = ((size_t)1 << 18);
+    
+    if (InFile_Open(&archive_stream.file, archive_path) != 0) {
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    
+    FileInStream_CreateVTable(&archive_stream);
+    
+    /* Allocators */
+    ISzAlloc alloc_imp = { SzAlloc, SzFree };
+    ISzAlloc alloc_temp = { SzAllocTemp, SzFreeTemp };
+    
+    /* Initialize look stream */
+    LookToRead2_CreateVTable(&look_stream, False);
+    look_stream.buf = (Byte *)ISzAlloc_Alloc(&alloc_imp, kInputBufSize);
+    if (!look_stream.buf) {
+        File_Close(&archive_stream.file);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    look_stream.bufSize = kInputBufSize;
+    look_stream.realStream = &archive_stream.vt;
+    LookToRead2_INIT(&look_stream);
+    
+    /* Initialize archive database */
+    CSzArEx db;
+    SzArEx_Init(&db);
+    
+    /* Open archive */
+    SRes res = SzArEx_Open(&db, &look_stream.vt, &alloc_imp, &alloc_temp);
+    if (res != SZ_OK) {
+        ISzAlloc_Free(&alloc_imp, look_stream.buf);
+        File_Close(&archive_stream.file);
+        SzArEx_Free(&db, &alloc_imp);
+        return SEVENZIP_ERROR_INVALID_ARCHIVE;
+    }
+    
+    sevenzip_fill_archive_info_internal(&db, info);
+    
+    /* Cleanup */
+    ISzAlloc_Free(&alloc_imp, look_stream.buf);
+    SzArEx_Free(&db, &alloc_imp);
+    File_Close(&archive_stream.file);
+    
+    return SEVENZIP_OK;
+}
@@ -1,5 +1,6 @@
 #include "7z_ffi.h"
 #include "7z.h"
+#include "ffi_internal.h"
 #include "7zAlloc.h"
 #include "7zBuf.h"
 #include "7zCrc.h"
@@ -10,6 +11,102 @@
 #include <string.h>
 #include <stdlib.h>
 
+/* Display name of a 7z coder method ID */
+static const char* method_name(UInt32 id) {
+    switch (id) {
+        case 0x00: return "Copy";
+        case 0x03: return "Delta";
+        case 0x0A: return "ARM64";
+        case 0x21: return "LZMA2";
+        case 0x030101: return "LZMA";
+        case 0x030401: return "PPMD";
+        case 0x03030103: return "BCJ";
+        case 0x0303011B: return "BCJ2";
+        case 0x03030205: return "PPC";
+        case 0x03030401: return "IA64";
+        case 0x03030501: return "ARM";
+        case 0x03030701: return "ARMT";
+        case 0x03030805: return "SPARC";
+        case 0x040108: return "Deflate";
+        case 0x040202: return "BZip2";
+        case 0x06F10701: return "7zAES";
+        default: return "Unknown";
+    }
+}
+
+/* Describe the coder chain of one folder, outermost coder last (shared with archive_reader.c) */
+void sevenzip_folder_method_internal(const CSzArEx* db, UInt32 folder_index, char* out, size_t out_size) {
+    out[0] = '\0';
+    
+    CSzFolder folder;
+    CSzData sd;
+    sd.Data = db->db.CodersData + db->db.FoCodersOffsets[folder_index];
+    sd.Size = db->db.FoCodersOffsets[folder_index + 1] - db->db.FoCodersOffsets[folder_index];
+    if (SzGetNextFolderItem(&folder, &sd) != SZ_OK) return;
+    
+    size_t used = 0;
+    for (UInt32 c = folder.NumCoders; c > 0; c--) {
+        int n = snprintf(out + used, out_size - used, "%s%s",
+                         used ? " " : "", method_name(folder.Coders[c - 1].MethodID));
+        if (n < 0 || (size_t)n >= out_size - used) break;
+        used += (size_t)n;
+    }
+}
+
+static void fill_entry_method(const CSzArEx* db, UInt32 i, char* out, size_t out_size) {
+    out[0] = '\0';
+    UInt32 folder_index = db->FileToFolder[i];
+    if (folder_index == (UInt32)-1) return;
+    sevenzip_folder_method_internal(db, folder_index, out, out_size);
+}
+
+/* Fill one SevenZipEntry from the archive database (shared with archive_reader.c) */
+void sevenzip_fill_entry_internal(const CSzArEx* db, UInt32 i, SevenZipEntry* entry) {
+    /* Get file name */
+    size_t len = SzArEx_GetFileNameUtf16(db, i, NULL);
+    if (len > 1) {
+        UInt16* temp = (UInt16*)malloc(len * sizeof(UInt16));
+        if (temp) {
+            SzArEx_GetFileNameUtf16(db, i, temp);
+            
+            entry->name = sevenzip_name_from_utf16(temp, len);
+            free(temp);
+        }
+    }
+    
+    /* Get file size */
+    entry->size = SzArEx_GetFileSize(db, i);
+    
+    /* Get packed size (approximate) */
+    entry->packed_size = 0; /* Would need to calculate from block info */
+    
+    /* Get modified time */
+    if (SzBitWithVals_Check(&db->MTime, i)) {
+        const CNtfsFileTime* ft = db->MTime.Vals + i;
+        /* Convert Windows FILETIME to Unix timestamp (simplified) */
+        entry->modified_time = (ft->Low | ((uint64_t)ft->High << 32)) / 10000000ULL - 11644473600ULL;
+    } else {
+        entry->modified_time = 0;
+    }
+    
+    /* Get attributes */
+    entry->attributes = 0;
+    if (SzBitWithVals_Check(&db->Attribs, i)) {
+        entry->attributes = db->Attribs.Vals[i];
+    }
+    
+    /* Check if directory */
+    entry->is_directory = SzArEx_IsDir(db, i);
+    
+    /* Get stored CRC */
+    if (SzBitWithVals_Check(&db->CRCs, i)) {
+        entry->crc = db->CRCs.Vals[i];
+        entry->has_crc = 1;
+    }
+    
+    fill_entry_method(db, i, entry->method, sizeof(entry->method));
+}
+
 SevenZipErrorCode sevenzip_list(
     const char* archive_path,
     const char* password,
@@ -80,47 +177,7 @@ SevenZipErrorCode sevenzip_list(
     
     /* Populate entry information */
     for (UInt32 i = 0; i < db.NumFiles; i++) {
-        /* Get file name */
-        size_t len = SzArEx_GetFileNameUtf16(&db, i, NULL);
-        if (len > 1) {
-            UInt16* temp = (UInt16*)malloc(len * sizeof(UInt16));
-            if (temp) {
-                SzArEx_GetFileNameUtf16(&db, i, temp);
-                
-                /* Convert UTF-16 to UTF-8 (simplified) */
-                result->entries[i].name = (char*)malloc(len);
-                if (result->entries[i].name) {
-                    for (size_t j = 0; j < len; j++) {
-                        result->entries[i].name[j] = (char)(temp[j] < 256 ? temp[j] : '?');
-                    }
-                }
-                free(temp);
-            }
-        }
-        
-        /* Get file size */
-        result->entries[i].size = SzArEx_GetFileSize(&db, i);
-        
-        /* Get packed size (approximate) */
-        result->entries[i].packed_size = 0; /* Would need to calculate from block info */
-        
-        /* Get modified time */
-        if (SzBitWithVals_Check(&db.MTime, i)) {
-            const CNtfsFileTime* ft = db.MTime.Vals + i;
-            /* Convert Windows FILETIME to Unix timestamp (simplified) */
-            result->entries[i].modified_time = (ft->Low | ((uint64_t)ft->High << 32)) / 10000000ULL - 11644473600ULL;
-        } else {
-            result->entries[i].modified_time = 0;
-        }
-        
-        /* Get attributes */
-        result->entries[i].attributes = 0;
-        if (SzBitWithVals_Check(&db.Attribs, i)) {
-            result->entries[i].attributes = db.Attribs.Vals[i];
-        }
-        
-        /* Check if directory */
-        result->entries[i].is_directory = SzArEx_IsDir(&db, i);
+        sevenzip_fill_entry_internal(&db, i, &result->entries[i]);
     }
     
     /* Cleanup */
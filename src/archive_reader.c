@@ -0,0 +1,578 @@
+#include "7z_ffi.h"
+#include "7z.h"
+#include "ffi_internal.h"
+#include "7zAlloc.h"
+#include "7zBuf.h"
+#include "7zCrc.h"
+#include "7zFile.h"
+#include "7zVersion.h"
+#include "Lzma2Dec.h"
+
+#include <stdio.h>
+#include <string.h>
+#include <stdlib.h>
+
+struct SevenZipArchive {
+    CFileInStream archive_stream;
+    CLookToRead2 look_stream;
+    CSzArEx db;
+    ISzAlloc alloc_imp;
+    ISzAlloc alloc_temp;
+    char* password;  /* Decrypts 7zAES folders (NULL = none given) */
+    uint8_t key[32]; /* Raw AES key, used instead of password when has_key */
+    int has_key;
+    
+    /* Decoded folder cache, reused across reads from the same solid block */
+    UInt32 block_index;
+    Byte* out_buffer;
+    size_t out_buffer_size;
+};
+
+int sevenzip_hardlink_target_internal(const CSzArEx* db, UInt32 index, const Byte* data, size_t size, UInt32* target) {
+    const size_t tag_len = sizeof(SEVENZIP_HARDLINK_TAG) - 1;
+    if (SzArEx_IsDir(db, index) || !SzBitWithVals_Check(&db->Attribs, index)) {
+        return 0;
+    }
+    /* The bit alone could be a stray DOS attribute: also require the Unix
+       extension with a regular-file mode (stored as POSIX S_IFREG, 0100000),
+       and the tag in the data */
+    UInt32 attrib = db->Attribs.Vals[index];
+    if (!(attrib & SEVENZIP_ATTRIB_HARDLINK) || !(attrib & 0x8000) ||
+        ((attrib >> 16) & 0170000) != 0100000 ||
+        size <= tag_len || memcmp(data, SEVENZIP_HARDLINK_TAG, tag_len) != 0) {
+        return 0;
+    }
+    
+    const char* name = (const char*)data + tag_len;
+    size_t name_len = size - tag_len;
+    for (UInt32 i = 0; i < db->NumFiles; i++) {
+        if (i == index || SzArEx_IsDir(db, i)) continue;
+        size_t len = SzArEx_GetFileNameUtf16(db, i, NULL);
+        UInt16* utf16 = (UInt16*)malloc(len * sizeof(UInt16));
+        if (!utf16) return -1;
+        SzArEx_GetFileNameUtf16(db, i, utf16);
+        char* candidate = sevenzip_name_from_utf16(utf16, len);
+        free(utf16);
+        if (!candidate) return -1;
+        int match = strlen(candidate) == name_len && memcmp(candidate, name, name_len) == 0;
+        free(candidate);
+        if (match) {
+            *target = i;
+            return 1;
+        }
+    }
+    return -1;
+}
+
+SevenZipErrorCode sevenzip_archive_open(
+    const char* archive_path,
+    const char* password,
+    SevenZipArchive** archive
+) {
+    if (!archive_path || !archive) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Initialize CRC tables */
+    CrcGenerateTable();
+    
+    SevenZipArchive* handle = (SevenZipArchive*)calloc(1, sizeof(SevenZipArchive));
+    if (!handle) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    /* Allocators */
+    handle->alloc_imp.Alloc = SzAlloc;
+    handle->alloc_imp.Free = SzFree;
+    handle->alloc_temp.Alloc = SzAllocTemp;
+    handle->alloc_temp.Free = SzFreeTemp;
+    handle->block_index = 0xFFFFFFFF;
+    if (password) {
+        handle->password = strdup(password);
+        if (!handle->password) {
+            free(handle);
+            return SEVENZIP_ERROR_MEMORY;
+        }
+    }
+    
+    /* Open archive file */
+    const size_t kInputBufSize = ((size_t)1 << 18);
+    
+    if (InFile_Open(&handle->archive_stream.file, archive_path) != 0) {
+        free(handle->password);
+        free(handle);
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    
+    FileInStream_CreateVTable(&handle->archive_stream);
+    
+    /* Initialize look stream */
+    LookToRead2_CreateVTable(&handle->look_stream, False);
+    handle->look_stream.buf = (Byte *)ISzAlloc_Alloc(&handle->alloc_imp, kInputBufSize);
+    if (!handle->look_stream.buf) {
+        File_Close(&handle->archive_stream.file);
+        free(handle->password);
+        free(handle);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    handle->look_stream.bufSize = kInputBufSize;
+    handle->look_stream.realStream = &handle->archive_stream.vt;
+    LookToRead2_INIT(&handle->look_stream);
+    
+    /* Open archive */
+    SzArEx_Init(&handle->db);
+    SRes res = SzArEx_Open(&handle->db, &handle->look_stream.vt,
+                           &handle->alloc_imp, &handle->alloc_temp);
+    if (res != SZ_OK) {
+        sevenzip_archive_close(handle);
+        return SEVENZIP_ERROR_INVALID_ARCHIVE;
+    }
+    
+    *archive = handle;
+    return SEVENZIP_OK;
+}
+
+SevenZipErrorCode sevenzip_archive_set_key(
+    SevenZipArchive* archive,
+    const uint8_t* key
+) {
+    if (!archive || !key) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Decoded data in the cache may come from the old secret */
+    memcpy(archive->key, key, sizeof(archive->key));
+    archive->has_key = 1;
+    archive->block_index = 0xFFFFFFFF;
+    return SEVENZIP_OK;
+}
+
+/* Raw key to decrypt with, or NULL to derive one from the password */
+static const uint8_t* archive_key(const SevenZipArchive* archive) {
+    return archive->has_key ? archive->key : NULL;
+}
+
+SevenZipErrorCode sevenzip_archive_list(
+    SevenZipArchive* archive,
+    SevenZipList** list
+) {
+    if (!archive) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    return sevenzip_archive_list_range(archive, 0, archive->db.NumFiles, list);
+}
+
+SevenZipErrorCode sevenzip_archive_list_range(
+    SevenZipArchive* archive,
+    uint32_t start,
+    uint32_t count,
+    SevenZipList** list
+) {
+    if (!archive || !list) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    UInt32 num_files = archive->db.NumFiles;
+    if (start > num_files) start = num_files;
+    if (count > num_files - start) count = num_files - start;
+    
+    SevenZipList* result = (SevenZipList*)malloc(sizeof(SevenZipList));
+    if (!result) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    result->count = count;
+    result->entries = (SevenZipEntry*)calloc(count, sizeof(SevenZipEntry));
+    if (!result->entries && count > 0) {
+        free(result);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    for (UInt32 i = 0; i < count; i++) {
+        sevenzip_fill_entry_internal(&archive->db, start + i, &result->entries[i]);
+    }
+    
+    *list = result;
+    return SEVENZIP_OK;
+}
+
+SevenZipErrorCode sevenzip_archive_read_entry(
+    SevenZipArchive* archive,
+    uint32_t index,
+    const uint8_t** data,
+    size_t* size
+) {
+    if (!archive || !data || !size || index >= archive->db.NumFiles) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    if (SzArEx_IsDir(&archive->db, index)) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    size_t offset = 0;
+    size_t out_size_processed = 0;
+    SRes res = sevenzip_extract_entry_internal(&archive->db, &archive->look_stream.vt, index,
+                                               archive->password, archive_key(archive),
+                                               &archive->block_index, &archive->out_buffer,
+                                               &archive->out_buffer_size, &offset, &out_size_processed,
+                                               &archive->alloc_imp, &archive->alloc_temp);
+    if (res != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    /* A hard link entry reads as the content of the entry it names */
+    UInt32 target;
+    int link = sevenzip_hardlink_target_internal(&archive->db, index,
+                                                 archive->out_buffer + offset, out_size_processed, &target);
+    if (link < 0) {
+        return SEVENZIP_ERROR_INVALID_ARCHIVE;
+    }
+    if (link) {
+        res = sevenzip_extract_entry_internal(&archive->db, &archive->look_stream.vt, target,
+                                              archive->password, archive_key(archive),
+                                              &archive->block_index, &archive->out_buffer,
+                                              &archive->out_buffer_size, &offset, &out_size_processed,
+                                              &archive->alloc_imp, &archive->alloc_temp);
+        if (res != SZ_OK) {
+            return SEVENZIP_ERROR_EXTRACT;
+        }
+    }
+    
+    *data = archive->out_buffer ? archive->out_buffer + offset : (const uint8_t*)"";
+    *size = out_size_processed;
+    return SEVENZIP_OK;
+}
+
+/* Coder IDs sevenzip_archive_stream_entry decodes itself */
+#define STREAM_METHOD_COPY 0x00
+#define STREAM_METHOD_LZMA2 0x21
+
+/* Output handed to the write callback at a time */
+#define STREAM_CHUNK_SIZE ((size_t)1 << 20)
+
+SevenZipErrorCode sevenzip_archive_stream_entry(
+    SevenZipArchive* archive,
+    uint32_t index,
+    SevenZipWriteCallback write_callback,
+    void* user_data
+) {
+    if (!archive || !write_callback || index >= archive->db.NumFiles) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    const CSzArEx* p = &archive->db;
+    const CSzAr* ar = &p->db;
+    if (SzArEx_IsDir(p, index)) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    UInt64 size = SzArEx_GetFileSize(p, index);
+    if (size == 0) {
+        return SEVENZIP_OK;
+    }
+    
+    /* Only a single Copy or LZMA2 coder is decoded here; anything else goes
+       through sevenzip_archive_read_entry */
+    UInt32 folder_index = p->FileToFolder[index];
+    CSzFolder folder;
+    CSzData sd;
+    sd.Data = ar->CodersData + ar->FoCodersOffsets[folder_index];
+    sd.Size = ar->FoCodersOffsets[folder_index + 1] - ar->FoCodersOffsets[folder_index];
+    if (SzGetNextFolderItem(&folder, &sd) != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    const CSzCoderInfo* coder = &folder.Coders[0];
+    if (folder.NumCoders != 1 || folder.NumPackStreams != 1 ||
+        (coder->MethodID != STREAM_METHOD_COPY && coder->MethodID != STREAM_METHOD_LZMA2) ||
+        (coder->MethodID == STREAM_METHOD_LZMA2 && coder->PropsSize != 1)) {
+        return SEVENZIP_ERROR_NOT_IMPLEMENTED;
+    }
+    int is_copy = coder->MethodID == STREAM_METHOD_COPY;
+    
+    UInt32 pack_index = ar->FoStartPackStreamIndex[folder_index];
+    UInt64 pack_left = ar->PackPositions[pack_index + 1] - ar->PackPositions[pack_index];
+    UInt64 skip = p->UnpackPositions[index] - p->UnpackPositions[p->FolderToFile[folder_index]];
+    UInt64 end = skip + size;
+    ILookInStreamPtr stream = &archive->look_stream.vt;
+    if (LookInStream_SeekTo(stream, p->dataPos + ar->PackPositions[pack_index]) != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    CLzma2Dec dec;
+    Lzma2Dec_CONSTRUCT(&dec)
+    if (!is_copy) {
+        Byte prop = ar->CodersData[ar->FoCodersOffsets[folder_index] + coder->PropsOffset];
+        if (Lzma2Dec_Allocate(&dec, prop, &archive->alloc_imp) != SZ_OK) {
+            return SEVENZIP_ERROR_MEMORY;
+        }
+        Lzma2Dec_Init(&dec);
+    }
+    Byte* chunk = (Byte*)malloc(STREAM_CHUNK_SIZE);
+    if (!chunk) {
+        Lzma2Dec_Free(&dec, &archive->alloc_imp);
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    
+    SevenZipErrorCode result = SEVENZIP_OK;
+    UInt32 crc = CRC_INIT_VAL;
+    UInt64 out_pos = 0;
+    while (out_pos < end) {
+        const void* in_buf = NULL;
+        size_t lookahead = (size_t)1 << 18;
+        if (lookahead > pack_left) lookahead = (size_t)pack_left;
+        if (ILookInStream_Look(stream, &in_buf, &lookahead) != SZ_OK) {
+            result = SEVENZIP_ERROR_EXTRACT;
+            break;
+        }
+        
+        SizeT in_processed = lookahead;
+        SizeT out_len = STREAM_CHUNK_SIZE;
+        if (is_copy) {
+            if (out_len > in_processed) out_len = in_processed;
+            in_processed = out_len;
+            memcpy(chunk, in_buf, out_len);
+        } else {
+            ELzmaStatus status;
+            if (Lzma2Dec_DecodeToBuf(&dec, chunk, &out_len, (const Byte*)in_buf, &in_processed,
+                                     LZMA_FINISH_ANY, &status) != SZ_OK) {
+                result = SEVENZIP_ERROR_EXTRACT;
+                break;
+            }
+        }
+        if (in_processed == 0 && out_len == 0) {
+            /* Packed stream ended before the entry did */
+            result = SEVENZIP_ERROR_EXTRACT;
+            break;
+        }
+        pack_left -= in_processed;
+        if (ILookInStream_Skip(stream, in_processed) != SZ_OK) {
+            result = SEVENZIP_ERROR_EXTRACT;
+            break;
+        }
+        
+        /* Hand over the part of the chunk that belongs to the entry */
+        UInt64 chunk_start = out_pos;
+        out_pos += out_len;
+        if (out_pos > skip) {
+            size_t from = chunk_start < skip ? (size_t)(skip - chunk_start) : 0;
+            size_t to = out_pos > end ? (size_t)(end - chunk_start) : (size_t)out_len;
+            crc = CrcUpdate(crc, chunk + from, to - from);
+            if (write_callback(chunk + from, to - from, user_data) != 0) {
+                result = SEVENZIP_ERROR_EXTRACT;
+                break;
+            }
+        }
+    }
+    
+    if (result == SEVENZIP_OK && SzBitWithVals_Check(&p->CRCs, index) &&
+        CRC_GET_DIGEST(crc) != p->CRCs.Vals[index]) {
+        result = SEVENZIP_ERROR_EXTRACT;
+    }
+    free(chunk);
+    Lzma2Dec_Free(&dec, &archive->alloc_imp);
+    return result;
+}
+
+SevenZipErrorCode sevenzip_archive_read_folder(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    const uint8_t** data,
+    size_t* size
+) {
+    if (!archive || !data || !size || folder_index >= archive->db.db.NumFolders) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    /* Decode through the folder's first file so the block cache is shared
+       with sevenzip_archive_read_entry; a folder without files has none */
+    UInt32 first_file = archive->db.FolderToFile[folder_index];
+    if (first_file >= archive->db.NumFiles || archive->db.FileToFolder[first_file] != folder_index) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    size_t offset = 0;
+    size_t out_size_processed = 0;
+    SRes res = sevenzip_extract_entry_internal(&archive->db, &archive->look_stream.vt, first_file,
+                                               archive->password, archive_key(archive),
+                                               &archive->block_index, &archive->out_buffer,
+                                               &archive->out_buffer_size, &offset, &out_size_processed,
+                                               &archive->alloc_imp, &archive->alloc_temp);
+    if (res != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    *data = archive->out_buffer ? archive->out_buffer : (const uint8_t*)"";
+    *size = archive->out_buffer_size;
+    return SEVENZIP_OK;
+}
+
+uint32_t sevenzip_archive_folder_count(SevenZipArchive* archive) {
+    return archive ? archive->db.db.NumFolders : 0;
+}
+
+SevenZipErrorCode sevenzip_archive_folder_info(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipFolderInfo* info
+) {
+    if (!archive || !info || folder_index >= archive->db.db.NumFolders) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    const CSzAr* ar = &archive->db.db;
+    UInt32 first_pack = ar->FoStartPackStreamIndex[folder_index];
+    UInt32 end_pack = ar->FoStartPackStreamIndex[folder_index + 1];
+    
+    memset(info, 0, sizeof(*info));
+    info->packed_offset = archive->db.dataPos + ar->PackPositions[first_pack];
+    info->packed_size = ar->PackPositions[end_pack] - ar->PackPositions[first_pack];
+    sevenzip_folder_method_internal(&archive->db, folder_index, info->codec, sizeof(info->codec));
+    return SEVENZIP_OK;
+}
+
+SevenZipErrorCode sevenzip_archive_raw_folder(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipRawFolder* folder
+) {
+    if (!archive || !folder || folder_index >= archive->db.db.NumFolders) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    const CSzAr* ar = &archive->db.db;
+    UInt32 first_pack = ar->FoStartPackStreamIndex[folder_index];
+    UInt32 first_unpack = ar->FoToCoderUnpackSizes[folder_index];
+    
+    folder->record = ar->CodersData + ar->FoCodersOffsets[folder_index];
+    folder->record_size = ar->FoCodersOffsets[folder_index + 1] - ar->FoCodersOffsets[folder_index];
+    folder->pack_positions = ar->PackPositions + first_pack;
+    folder->num_pack_streams = ar->FoStartPackStreamIndex[folder_index + 1] - first_pack;
+    folder->unpack_sizes = ar->CoderUnpackSizes + first_unpack;
+    folder->num_unpack_sizes = ar->FoToCoderUnpackSizes[folder_index + 1] - first_unpack;
+    return SEVENZIP_OK;
+}
+
+SevenZipErrorCode sevenzip_archive_folder_encryption(
+    SevenZipArchive* archive,
+    uint32_t folder_index,
+    SevenZipEncryptionInfo* info
+) {
+    if (!archive || !info || folder_index >= archive->db.db.NumFolders) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    memset(info, 0, sizeof(*info));
+    
+    const CSzAr* ar = &archive->db.db;
+    const Byte* coders_data = ar->CodersData + ar->FoCodersOffsets[folder_index];
+    CSzFolder folder;
+    CSzData sd;
+    sd.Data = coders_data;
+    sd.Size = ar->FoCodersOffsets[folder_index + 1] - ar->FoCodersOffsets[folder_index];
+    if (SzGetNextFolderItem(&folder, &sd) != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    for (UInt32 c = 0; c < folder.NumCoders; c++) {
+        const CSzCoderInfo* coder = &folder.Coders[c];
+        if (coder->MethodID != 0x06F10701 || coder->PropsSize == 0) continue;
+        
+        /* Same layout 7-Zip's AES decoder reads: power and size flags, then sizes - 1 */
+        const Byte* props = coders_data + coder->PropsOffset;
+        info->encrypted = 1;
+        info->cycles_power = props[0] & 0x3F;
+        if ((props[0] & 0xC0) != 0 && coder->PropsSize >= 2) {
+            info->salt_size = ((props[0] >> 7) & 1) + (props[1] >> 4);
+            info->iv_size = ((props[0] >> 6) & 1) + (props[1] & 0x0F);
+        }
+        break;
+    }
+    return SEVENZIP_OK;
+}
+
+/* Read one entry of a CTime/ATime/MTime vector, if defined */
+static int entry_time(const SevenZipArchive* archive, const CSzBitUi64s* times, uint32_t index, uint64_t* filetime) {
+    if (!filetime || index >= archive->db.NumFiles || !SzBitWithVals_Check(times, index)) {
+        return 0;
+    }
+    const CNtfsFileTime* ft = times->Vals + index;
+    *filetime = ft->Low | ((uint64_t)ft->High << 32);
+    return 1;
+}
+
+int sevenzip_archive_entry_filetime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime) {
+    return archive ? entry_time(archive, &archive->db.MTime, index, filetime) : 0;
+}
+
+int sevenzip_archive_entry_ctime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime) {
+    return archive ? entry_time(archive, &archive->db.CTime, index, filetime) : 0;
+}
+
+int sevenzip_archive_entry_atime(SevenZipArchive* archive, uint32_t index, uint64_t* filetime) {
+    return archive ? entry_time(archive, &archive->db.ATime, index, filetime) : 0;
+}
+
+uint32_t sevenzip_archive_entry_folder(SevenZipArchive* archive, uint32_t index) {
+    if (!archive || index >= archive->db.NumFiles) {
+        return SEVENZIP_NO_FOLDER;
+    }
+    /* Entries without a stream that sit between a folder's files are
+     * mapped to that folder too */
+    if (SzArEx_IsDir(&archive->db, index) || SzArEx_GetFileSize(&archive->db, index) == 0) {
+        return SEVENZIP_NO_FOLDER;
+    }
+    return archive->db.FileToFolder[index];
+}
+
+SevenZipErrorCode sevenzip_archive_hardlink_target(
+    SevenZipArchive* archive,
+    uint32_t index,
+    uint32_t* target
+) {
+    if (!archive || !target || index >= archive->db.NumFiles) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    *target = index;
+    if (SzArEx_IsDir(&archive->db, index) || !SzBitWithVals_Check(&archive->db.Attribs, index) ||
+        !(archive->db.Attribs.Vals[index] & SEVENZIP_ATTRIB_HARDLINK)) {
+        return SEVENZIP_OK;
+    }
+    
+    size_t offset = 0;
+    size_t out_size_processed = 0;
+    SRes res = sevenzip_extract_entry_internal(&archive->db, &archive->look_stream.vt, index,
+                                               archive->password, archive_key(archive),
+                                               &archive->block_index, &archive->out_buffer,
+                                               &archive->out_buffer_size, &offset, &out_size_processed,
+                                               &archive->alloc_imp, &archive->alloc_temp);
+    if (res != SZ_OK) {
+        return SEVENZIP_ERROR_EXTRACT;
+    }
+    
+    if (sevenzip_hardlink_target_internal(&archive->db, index, archive->out_buffer + offset,
+                                          out_size_processed, target) < 0) {
+        return SEVENZIP_ERROR_INVALID_ARCHIVE;
+    }
+    return SEVENZIP_OK;
+}
+
+void sevenzip_archive_close(SevenZipArchive* archive) {
+    if (!archive) {
+        return;
+    }
+    
+    if (archive->out_buffer) {
+        ISzAlloc_Free(&archive->alloc_imp, archive->out_buffer);
+    }
+    
+    if (archive->password) {
+        memset(archive->password, 0, strlen(archive->password));
+        free(archive->password);
+    }
+    memset(archive->key, 0, sizeof(archive->key));
+    
+    ISzAlloc_Free(&archive->alloc_imp, archive->look_stream.buf);
+    SzArEx_Free(&archive->db, &archive->alloc_imp);
+    File_Close(&archive->archive_stream.file);
+    free(archive);
+}
@@ -61,6 +61,19 @@ typedef struct {
     uint64_t current_file_total;
 } StreamContext;
 
+/* Forwards sevenzip_create_7z() item progress to a bytes progress callback */
+typedef struct {
+    SevenZipBytesProgressCallback progress_callback;
+    void* user_data;
+    const char** input_paths;
+} ItemProgressAdapter;
+
+static void forward_item_progress(uint64_t completed, uint64_t total, void* user_data) {
+    ItemProgressAdapter* adapter = (ItemProgressAdapter*)user_data;
+    const char* current = completed > 0 ? adapter->input_paths[completed - 1] : "";
+    adapter->progress_callback(completed, total, 0, 0, current, adapter->user_data);
+}
+
 /* File list for gathering all input files */
 typedef struct {
     char** paths;
@@ -85,6 +98,7 @@ void sevenzip_stream_options_init(SevenZipStreamOptions* options) {
     options->chunk_size = DEFAULT_CHUNK_SIZE;
     options->temp_dir = NULL;  // Use system default
     options->delete_temp_on_error = 1;
+    options->volume_naming = SEVENZIP_VOLUME_NAMING_NUMERIC;
 }
 
 /**
@@ -463,27 +477,25 @@ SevenZipErrorCode sevenzip_create_7z_streaming(
     if (options->split_size == 0) {
         // Convert StreamOptions to CompressOptions
         SevenZipCompressOptions comp_opts;
+        sevenzip_compress_options_init(&comp_opts);
         comp_opts.num_threads = options->num_threads;
         comp_opts.dict_size = options->dict_size;
         comp_opts.solid = options->solid;
         comp_opts.password = options->password;
+        comp_opts.skip_empty_dirs = options->skip_empty_dirs;
         
         // Use standard creation (which creates valid 7z archives)
-        // Note: We lose the byte-level progress callback, but archives work
-        SevenZipProgressCallback simple_callback = NULL;
-        if (progress_callback) {
-            // Wrap the bytes callback as a simple callback
-            // TODO: Implement wrapper to convert between callback types
-            simple_callback = NULL;  // For now, no progress with streaming
-        }
+        // Progress is only available per input item on this path, so it is
+        // forwarded with the item counts in place of byte counts
+        ItemProgressAdapter adapter = { progress_callback, user_data, input_paths };
         
         return sevenzip_create_7z(
             archive_path,
             input_paths,
             level,
             &comp_opts,
-            simple_callback,
-            user_data
+            progress_callback ? forward_item_progress : NULL,
+            &adapter
         );
     }
     
@@ -513,18 +525,22 @@ SevenZipErrorCode sevenzip_create_7z_streaming(
         file_list_free(&files);
         
         SevenZipCompressOptions comp_opts;
+        sevenzip_compress_options_init(&comp_opts);
         comp_opts.num_threads = options->num_threads;
         comp_opts.dict_size = options->dict_size;
         comp_opts.solid = options->solid;
         comp_opts.password = options->password;
+        comp_opts.skip_empty_dirs = options->skip_empty_dirs;
+        
+        ItemProgressAdapter adapter = { progress_callback, user_data, input_paths };
         
         return sevenzip_create_7z(
             archive_path,
             input_paths,
             level,
             &comp_opts,
-            NULL,
-            user_data
+            progress_callback ? forward_item_progress : NULL,
+            &adapter
         );
     }
     
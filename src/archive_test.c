@@ -7,6 +7,7 @@
 
 #include "../include/7z_ffi.h"
 #include "7z.h"
+#include "ffi_internal.h"
 #include "7zAlloc.h"
 #include "7zBuf.h"
 #include "7zCrc.h"
@@ -201,19 +202,14 @@ static void close_split_volumes(MultiVolumeInStream* stream) {
     free(stream->volume_offsets);
 }
 
-/**
- * Test archive integrity without extracting
- * @param archive_path Path to archive file
- * @param password Optional password (NULL if not encrypted)
- * @param progress_callback Optional progress callback
- * @param user_data User data for progress callback
- * @return SEVENZIP_OK if archive is valid, error code otherwise
- */
-SevenZipErrorCode sevenzip_test_archive(
+/* Test every file entry, reporting progress and per-entry results */
+static SevenZipErrorCode test_archive_internal(
     const char* archive_path,
     const char* password,
     SevenZipBytesProgressCallback progress_callback,
-    void* user_data
+    void* user_data,
+    SevenZipTestEntryCallback entry_callback,
+    void* entry_user_data
 ) {
     if (!archive_path) {
         return SEVENZIP_ERROR_INVALID_PARAM;
@@ -287,9 +283,10 @@ SevenZipErrorCode sevenzip_test_archive(
         
         if (temp) {
             SzArEx_GetFileNameUtf16(&db, i, temp);
-            // Convert UTF-16 to UTF-8 (simplified)
-            for (size_t j = 0; j < len && j < 511; j++) {
-                file_name[j] = (char)temp[j];
+            char* decoded = sevenzip_name_from_utf16(temp, len);
+            if (decoded) {
+                strncpy(file_name, decoded, sizeof(file_name) - 1);
+                free(decoded);
             }
             free(temp);
         }
@@ -312,7 +309,7 @@ SevenZipErrorCode sevenzip_test_archive(
         size_t offset = 0;
         size_t out_size_processed = 0;
         
-        res = SzArEx_Extract(&db, &look_stream.vt, i,
+        res = sevenzip_extract_entry_internal(&db, &look_stream.vt, i, password, NULL,
                             &block_index, &out_buffer, &out_buffer_size,
                             &offset, &out_size_processed,
                             &alloc_imp, &alloc_temp_imp);
@@ -327,6 +324,10 @@ SevenZipErrorCode sevenzip_test_archive(
                         "Failed to test file: %s (error %d)", file_name, res);
             }
         }
+        
+        if (entry_callback) {
+            entry_callback(file_name, res == SZ_OK, entry_user_data);
+        }
     }
     
     // Final progress update
@@ -353,3 +354,37 @@ SevenZipErrorCode sevenzip_test_archive(
     
     return SEVENZIP_OK;
 }
+
+/**
+ * Test archive integrity without extracting
+ * @param archive_path Path to archive file
+ * @param password Optional password (NULL if not encrypted)
+ * @param progress_callback Optional progress callback
+ * @param user_data User data for progress callback
+ * @return SEVENZIP_OK if archive is valid, error code otherwise
+ */
+SevenZipErrorCode sevenzip_test_archive(
+    const char* archive_path,
+    const char* password,
+    SevenZipBytesProgressCallback progress_callback,
+    void* user_data
+) {
+    return test_archive_internal(archive_path, password, progress_callback, user_data, NULL, NULL);
+}
+
+/**
+ * Test archive integrity, reporting each file entry as it is checked
+ * @param archive_path Path to archive file
+ * @param password Optional password (NULL if not encrypted)
+ * @param entry_callback Called after each file entry with its result
+ * @param user_data User data for the entry callback
+ * @return SEVENZIP_OK if archive is valid, error code otherwise
+ */
+SevenZipErrorCode sevenzip_test_archive_entries(
+    const char* archive_path,
+    const char* password,
+    SevenZipTestEntryCallback entry_callback,
+    void* user_data
+) {
+    return test_archive_internal(archive_path, password, NULL, NULL, entry_callback, user_data);
+}
@@ -7,9 +7,13 @@
 
 #include "../include/7z_ffi.h"
 #include "7z.h"
+#include "ffi_internal.h"
 #include "Aes.h"
 #include "Sha256.h"
 #include "7zCrc.h"
+#include "LzmaDec.h"
+#include "Lzma2Dec.h"
+#include <stdio.h>
 #include <string.h>
 #include <stdlib.h>
 
@@ -74,14 +78,31 @@ SevenZipErrorCode sevenzip_init_encryption(
         salt[i] = (uint8_t)(rand() & 0xFF);
     }
     
-    // Derive encryption key from password
-    derive_key_from_password(password, salt, 16, PBKDF2_ITERATIONS, key, AES_KEY_SIZE);
-    
     // Initialize IV (in production, use crypto-secure random)
     for (int i = 0; i < AES_BLOCK_SIZE; i++) {
         iv[i] = (uint8_t)(rand() & 0xFF);
     }
     
+    return sevenzip_init_encryption_with_salt(password, salt, 16, key, aes_context);
+}
+
+/**
+ * Initialize AES encryption context from a caller-chosen salt
+ */
+SevenZipErrorCode sevenzip_init_encryption_with_salt(
+    const char* password,
+    const uint8_t* salt,
+    size_t salt_len,
+    uint8_t* key,
+    uint32_t* aes_context
+) {
+    if (!password || !salt || !key || !aes_context) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    // Derive encryption key from password
+    derive_key_from_password(password, salt, salt_len, PBKDF2_ITERATIONS, key, AES_KEY_SIZE);
+    
     // Initialize AES tables
     AesGenTables();
     
@@ -91,6 +112,54 @@ SevenZipErrorCode sevenzip_init_encryption(
     return SEVENZIP_OK;
 }
 
+/**
+ * Derive a key with 7-Zip's 7zAES KDF (UTF-16LE password, counter-salted SHA-256)
+ */
+SevenZipErrorCode sevenzip_derive_7z_key(
+    const char* password,
+    const uint8_t* salt,
+    size_t salt_len,
+    uint32_t num_cycles_power,
+    uint8_t* key
+) {
+    if (!password || !key || (salt_len > 0 && !salt) || salt_len > 16 ||
+        (num_cycles_power > 24 && num_cycles_power != 0x3F)) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    // 7-Zip hashes the password as UTF-16LE without a terminator
+    size_t password_size = sevenzip_write_utf16le_name(password, NULL);
+    uint8_t* password_utf16 = (uint8_t*)malloc(password_size);
+    if (!password_utf16) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    sevenzip_write_utf16le_name(password, password_utf16);
+    password_size -= 2;
+    
+    if (num_cycles_power == 0x3F) {
+        // No stretching: the key is the salt and password, zero-padded
+        size_t pos = 0;
+        memset(key, 0, AES_KEY_SIZE);
+        for (size_t i = 0; i < salt_len && pos < AES_KEY_SIZE; i++) key[pos++] = salt[i];
+        for (size_t i = 0; i < password_size && pos < AES_KEY_SIZE; i++) key[pos++] = password_utf16[i];
+    } else {
+        CSha256 sha;
+        uint8_t counter[8] = {0};
+        Sha256_Init(&sha);
+        for (uint64_t round = 0; round < ((uint64_t)1 << num_cycles_power); round++) {
+            Sha256_Update(&sha, salt, salt_len);
+            Sha256_Update(&sha, password_utf16, password_size);
+            Sha256_Update(&sha, counter, sizeof(counter));
+            for (int i = 0; i < 8 && ++counter[i] == 0; i++) {}
+        }
+        Sha256_Final(&sha, key);
+    }
+    
+    memset(password_utf16, 0, password_size);
+    free(password_utf16);
+    return SEVENZIP_OK;
+}
+
 /**
  * Initialize AES decryption context
  */
@@ -117,6 +186,40 @@ SevenZipErrorCode sevenzip_init_decryption(
     return SEVENZIP_OK;
 }
 
+/**
+ * Initialize AES encryption context from a raw key
+ */
+SevenZipErrorCode sevenzip_init_encryption_with_key(
+    const uint8_t* key,
+    uint32_t* aes_context
+) {
+    if (!key || !aes_context) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    AesGenTables();
+    Aes_SetKey_Enc(aes_context, key, AES_KEY_SIZE);
+    
+    return SEVENZIP_OK;
+}
+
+/**
+ * Initialize AES decryption context from a raw key
+ */
+SevenZipErrorCode sevenzip_init_decryption_with_key(
+    const uint8_t* key,
+    uint32_t* aes_context
+) {
+    if (!key || !aes_context) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    
+    AesGenTables();
+    Aes_SetKey_Dec(aes_context, key, AES_KEY_SIZE);
+    
+    return SEVENZIP_OK;
+}
+
 /**
  * Encrypt data using AES-256-CBC
  */
@@ -219,26 +322,23 @@ SevenZipErrorCode sevenzip_decrypt_data(
     // Copy ciphertext to plaintext buffer for in-place decryption
     memcpy(plaintext, ciphertext, ciphertext_len);
     
-    // Decrypt data in-place using AES-CBC
+    // Decrypt data in-place using AES-CBC. The portable decoder is used, as
+    // for encryption, since it accepts any buffer alignment
     size_t num_blocks = ciphertext_len / AES_BLOCK_SIZE;
-    g_AesCbc_Decode(ivAes, plaintext, num_blocks);
-    
-    // Remove PKCS#7 padding
-    uint8_t padding_byte = plaintext[ciphertext_len - 1];
-    if (padding_byte > 0 && padding_byte <= AES_BLOCK_SIZE) {
-        // Verify padding
-        for (size_t i = ciphertext_len - padding_byte; i < ciphertext_len; i++) {
-            if (plaintext[i] != padding_byte) {
-                free(ivAes);
-                return SEVENZIP_ERROR_EXTRACT; // Invalid padding = wrong password
-            }
+    AesCbc_Decode(ivAes, plaintext, num_blocks);
+    free(ivAes);
+    
+    // Remove PKCS#7 padding; every valid ciphertext ends in 1-16 padding bytes
+    uint8_t padding_byte = ciphertext_len ? plaintext[ciphertext_len - 1] : 0;
+    if (padding_byte == 0 || padding_byte > AES_BLOCK_SIZE) {
+        return SEVENZIP_ERROR_EXTRACT; // Invalid padding = wrong password
+    }
+    for (size_t i = ciphertext_len - padding_byte; i < ciphertext_len; i++) {
+        if (plaintext[i] != padding_byte) {
+            return SEVENZIP_ERROR_EXTRACT; // Invalid padding = wrong password
         }
-        *plaintext_len = ciphertext_len - padding_byte;
-    } else {
-        *plaintext_len = ciphertext_len;
     }
-    
-    free(ivAes);
+    *plaintext_len = ciphertext_len - padding_byte;
     
     return SEVENZIP_OK;
 }
@@ -287,3 +387,239 @@ SevenZipErrorCode sevenzip_verify_password(
     // If decryption succeeds with valid padding, password is correct
     return result;
 }
+
+/* Method ID of the 7zAES coder in a folder's coder list */
+#define K_AES_METHOD 0x06F10701
+#define K_COPY_METHOD 0x00
+#define K_LZMA_METHOD 0x030101
+#define K_LZMA2_METHOD 0x21
+
+/* Fill buf with random bytes from the OS where there is a source, else rand() */
+static void random_bytes(uint8_t* buf, size_t size) {
+#ifndef _WIN32
+    FILE* f = fopen("/dev/urandom", "rb");
+    if (f) {
+        size_t got = fread(buf, 1, size, f);
+        fclose(f);
+        if (got == size) return;
+    }
+#endif
+    for (size_t i = 0; i < size; i++) {
+        buf[i] = (uint8_t)(rand() & 0xFF);
+    }
+}
+
+/**
+ * Encrypt a folder's packed stream for a 7zAES coder
+ */
+SevenZipErrorCode sevenzip_aes_encode_internal(
+    const uint8_t* key,
+    uint32_t cycles_power,
+    uint8_t** data,
+    size_t* size,
+    uint8_t* props
+) {
+    // 7zAES pads with zeros; the coder's unpack size tells where data ends
+    size_t padded_len = (*size + AES_BLOCK_SIZE - 1) / AES_BLOCK_SIZE * AES_BLOCK_SIZE;
+    uint8_t* padded = (uint8_t*)realloc(*data, padded_len ? padded_len : 1);
+    if (!padded) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    memset(padded + *size, 0, padded_len - *size);
+    *data = padded;
+    *size = padded_len;
+    
+    uint8_t iv[AES_BLOCK_SIZE];
+    random_bytes(iv, sizeof(iv));
+    
+    uint32_t* ivAes = (uint32_t*)aligned_alloc(16, AES_NUM_IVMRK_WORDS * sizeof(uint32_t));
+    if (!ivAes) {
+        return SEVENZIP_ERROR_MEMORY;
+    }
+    AesGenTables();
+    AesCbc_Init(ivAes, iv);
+    Aes_SetKey_Enc(ivAes + 4, key, AES_KEY_SIZE);
+    AesCbc_Encode(ivAes, padded, padded_len / AES_BLOCK_SIZE);
+    memset(ivAes, 0, AES_NUM_IVMRK_WORDS * sizeof(uint32_t));
+    free(ivAes);
+    
+    // Same layout 7-Zip writes: power with the IV flag, no salt, IV size - 1, IV
+    props[0] = (uint8_t)(cycles_power | 0x40);
+    props[1] = AES_BLOCK_SIZE - 1;
+    memcpy(props + 2, iv, AES_BLOCK_SIZE);
+    return SEVENZIP_OK;
+}
+
+/* Parse the coder list of a folder */
+static SRes read_folder(const CSzAr* ar, UInt32 folder_index, CSzFolder* folder, const Byte** coders_data) {
+    CSzData sd;
+    *coders_data = ar->CodersData + ar->FoCodersOffsets[folder_index];
+    sd.Data = *coders_data;
+    sd.Size = ar->FoCodersOffsets[(size_t)folder_index + 1] - ar->FoCodersOffsets[folder_index];
+    return SzGetNextFolderItem(folder, &sd);
+}
+
+/* Decode an AES-over-one-coder folder into a new buffer from alloc_main */
+static SRes decode_aes_folder(
+    const CSzArEx* db,
+    UInt32 folder_index,
+    const CSzFolder* folder,
+    const Byte* coders_data,
+    ILookInStreamPtr stream,
+    const char* password,
+    const uint8_t* raw_key,
+    Byte** out,
+    size_t* out_size,
+    ISzAllocPtr alloc_main,
+    ISzAllocPtr alloc_temp
+) {
+    const CSzAr* ar = &db->db;
+    const CSzCoderInfo* aes = &folder->Coders[0];
+    const CSzCoderInfo* inner = &folder->Coders[1];
+    if (folder->NumCoders != 2 || folder->NumBonds != 1 || folder->NumPackStreams != 1 ||
+        folder->Bonds[0].InIndex != 1 || folder->Bonds[0].OutIndex != 0 || folder->PackStreams[0] != 0 ||
+        (inner->MethodID != K_COPY_METHOD && inner->MethodID != K_LZMA_METHOD && inner->MethodID != K_LZMA2_METHOD)) {
+        return SZ_ERROR_UNSUPPORTED;
+    }
+    if (!password && !raw_key) {
+        return SZ_ERROR_DATA;
+    }
+    
+    const UInt64 unpack_size = SzAr_GetFolderUnpackSize(ar, folder_index);
+    const UInt64 inner_size = ar->CoderUnpackSizes[ar->FoToCoderUnpackSizes[folder_index]];
+    const UInt32 pack_index = ar->FoStartPackStreamIndex[folder_index];
+    const UInt64 pack_size = ar->PackPositions[pack_index + 1] - ar->PackPositions[pack_index];
+    if (pack_size % AES_BLOCK_SIZE != 0 || inner_size > pack_size ||
+        (size_t)pack_size != pack_size || (size_t)unpack_size != unpack_size) {
+        return SZ_ERROR_DATA;
+    }
+    
+    // Key derivation parameters, in the layout sevenzip_aes_encode_internal writes
+    const Byte* props = coders_data + aes->PropsOffset;
+    uint8_t salt[16] = {0};
+    uint8_t iv[AES_BLOCK_SIZE] = {0};
+    size_t salt_size = 0, iv_size = 0;
+    if (aes->PropsSize == 0) {
+        return SZ_ERROR_UNSUPPORTED;
+    }
+    if ((props[0] & 0xC0) != 0) {
+        if (aes->PropsSize < 2) return SZ_ERROR_UNSUPPORTED;
+        salt_size = ((props[0] >> 7) & 1) + (props[1] >> 4);
+        iv_size = ((props[0] >> 6) & 1) + (props[1] & 0x0F);
+        if (aes->PropsSize != 2 + salt_size + iv_size) return SZ_ERROR_UNSUPPORTED;
+        memcpy(salt, props + 2, salt_size);
+        memcpy(iv, props + 2 + salt_size, iv_size);
+    }
+    uint8_t key[AES_KEY_SIZE];
+    if (raw_key) {
+        memcpy(key, raw_key, AES_KEY_SIZE);
+    } else if (sevenzip_derive_7z_key(password, salt, salt_size, props[0] & 0x3F, key) != SEVENZIP_OK) {
+        return SZ_ERROR_UNSUPPORTED;
+    }
+    
+    Byte* packed = (Byte*)ISzAlloc_Alloc(alloc_temp, (size_t)pack_size ? (size_t)pack_size : 1);
+    uint32_t* ivAes = (uint32_t*)aligned_alloc(16, AES_NUM_IVMRK_WORDS * sizeof(uint32_t));
+    Byte* buffer = (Byte*)ISzAlloc_Alloc(alloc_main, (size_t)unpack_size ? (size_t)unpack_size : 1);
+    SRes res = (packed && ivAes && buffer) ? SZ_OK : SZ_ERROR_MEM;
+    if (res == SZ_OK) {
+        res = LookInStream_SeekTo(stream, db->dataPos + ar->PackPositions[pack_index]);
+    }
+    if (res == SZ_OK) {
+        res = LookInStream_Read(stream, packed, (size_t)pack_size);
+    }
+    if (res == SZ_OK) {
+        AesGenTables();
+        AesCbc_Init(ivAes, iv);
+        Aes_SetKey_Dec(ivAes + 4, key, AES_KEY_SIZE);
+        AesCbc_Decode(ivAes, packed, (size_t)pack_size / AES_BLOCK_SIZE);
+        
+        const Byte* inner_props = coders_data + inner->PropsOffset;
+        SizeT dest_len = (SizeT)unpack_size;
+        SizeT src_len = (SizeT)inner_size;
+        ELzmaStatus status;
+        if (inner->MethodID == K_COPY_METHOD) {
+            if (inner_size != unpack_size) {
+                res = SZ_ERROR_DATA;
+            } else {
+                memcpy(buffer, packed, (size_t)unpack_size);
+            }
+        } else if (inner->MethodID == K_LZMA2_METHOD) {
+            res = inner->PropsSize == 1
+                ? Lzma2Decode(buffer, &dest_len, packed, &src_len, inner_props[0], LZMA_FINISH_END, &status, alloc_temp)
+                : SZ_ERROR_UNSUPPORTED;
+        } else {
+            res = LzmaDecode(buffer, &dest_len, packed, &src_len, inner_props, inner->PropsSize,
+                             LZMA_FINISH_END, &status, alloc_temp);
+        }
+        if (res == SZ_OK && (dest_len != unpack_size || src_len != inner_size)) {
+            res = SZ_ERROR_DATA;
+        }
+    }
+    if (res == SZ_OK && SzBitWithVals_Check(&ar->FolderCRCs, folder_index) &&
+        CrcCalc(buffer, (size_t)unpack_size) != ar->FolderCRCs.Vals[folder_index]) {
+        res = SZ_ERROR_CRC;
+    }
+    
+    memset(key, 0, sizeof(key));
+    if (ivAes) {
+        memset(ivAes, 0, AES_NUM_IVMRK_WORDS * sizeof(uint32_t));
+        free(ivAes);
+    }
+    ISzAlloc_Free(alloc_temp, packed);
+    if (res != SZ_OK) {
+        ISzAlloc_Free(alloc_main, buffer);
+        return res;
+    }
+    *out = buffer;
+    *out_size = (size_t)unpack_size;
+    return SZ_OK;
+}
+
+/**
+ * SzArEx_Extract that also decodes 7zAES folders
+ */
+SRes sevenzip_extract_entry_internal(
+    const CSzArEx* db,
+    ILookInStreamPtr stream,
+    UInt32 index,
+    const char* password,
+    const uint8_t* key,
+    UInt32* block_index,
+    Byte** out_buffer,
+    size_t* out_buffer_size,
+    size_t* offset,
+    size_t* out_size,
+    ISzAllocPtr alloc_main,
+    ISzAllocPtr alloc_temp
+) {
+    const UInt32 folder_index = db->FileToFolder[index];
+    if (folder_index != (UInt32)-1 && (*out_buffer == NULL || *block_index != folder_index)) {
+        CSzFolder folder;
+        const Byte* coders_data;
+        SRes res = read_folder(&db->db, folder_index, &folder, &coders_data);
+        if (res != SZ_OK) {
+            return res;
+        }
+        
+        int encrypted = 0;
+        for (UInt32 c = 0; c < folder.NumCoders; c++) {
+            if (folder.Coders[c].MethodID == K_AES_METHOD) encrypted = 1;
+        }
+        if (encrypted) {
+            // Fill the folder cache the way SzArEx_Extract does, so that it
+            // only has to locate and CRC-check the entry below
+            ISzAlloc_Free(alloc_main, *out_buffer);
+            *out_buffer = NULL;
+            *out_buffer_size = 0;
+            *block_index = (UInt32)-1;
+            res = decode_aes_folder(db, folder_index, &folder, coders_data, stream, password, key,
+                                    out_buffer, out_buffer_size, alloc_main, alloc_temp);
+            if (res != SZ_OK) {
+                return res;
+            }
+            *block_index = folder_index;
+        }
+    }
+    return SzArEx_Extract(db, stream, index, block_index, out_buffer, out_buffer_size,
+                          offset, out_size, alloc_main, alloc_temp);
+}
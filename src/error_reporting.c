@@ -124,6 +124,8 @@ const char* sevenzip_get_error_string(SevenZipErrorCode code) {
             return "Invalid parameter provided to function";
         case SEVENZIP_ERROR_NOT_IMPLEMENTED:
             return "Feature not implemented";
+        case SEVENZIP_ERROR_CANCELLED:
+            return "Operation cancelled by caller";
         case SEVENZIP_ERROR_UNKNOWN:
         default:
             return "Unknown error occurred";
@@ -1,4 +1,5 @@
 #include "7z_ffi.h"
+#include "ffi_internal.h"
 #include "7zCrc.h"  // Add CRC header for CrcGenerateTable()
 #include <string.h>
 #include <stdio.h>
@@ -9,6 +10,12 @@
 /* Global initialization flag */
 static int g_initialized = 0;
 
+/* Abort request raised by a progress callback on this thread */
+static SEVENZIP_THREAD_LOCAL int g_abort_requested = 0;
+static SEVENZIP_THREAD_LOCAL uint32_t g_last_threads_used = 0;
+static SEVENZIP_THREAD_LOCAL uint64_t g_last_peak_memory = 0;
+static SEVENZIP_THREAD_LOCAL uint64_t g_last_dict_size = 0;
+
 SevenZipErrorCode sevenzip_init(void) {
     if (g_initialized) {
         return SEVENZIP_OK;
@@ -33,6 +40,40 @@ void sevenzip_cleanup(void) {
     g_initialized = 0;
 }
 
+void sevenzip_request_abort(void) {
+    g_abort_requested = 1;
+}
+
+int sevenzip_take_abort_request(void) {
+    int requested = g_abort_requested;
+    g_abort_requested = 0;
+    return requested;
+}
+
+uint32_t sevenzip_last_threads_used(void) {
+    return g_last_threads_used;
+}
+
+void sevenzip_set_last_threads_used(uint32_t threads) {
+    g_last_threads_used = threads;
+}
+
+uint64_t sevenzip_last_peak_memory(void) {
+    return g_last_peak_memory;
+}
+
+void sevenzip_set_last_peak_memory(uint64_t bytes) {
+    g_last_peak_memory = bytes;
+}
+
+uint64_t sevenzip_last_dict_size(void) {
+    return g_last_dict_size;
+}
+
+void sevenzip_set_last_dict_size(uint64_t bytes) {
+    g_last_dict_size = bytes;
+}
+
 const char* sevenzip_get_error_message(SevenZipErrorCode error_code) {
     switch (error_code) {
         case SEVENZIP_OK:
@@ -51,6 +92,8 @@ const char* sevenzip_get_error_message(SevenZipErrorCode error_code) {
             return "Invalid parameter";
         case SEVENZIP_ERROR_NOT_IMPLEMENTED:
             return "Feature not implemented";
+        case SEVENZIP_ERROR_CANCELLED:
+            return "Operation cancelled";
         default:
             return "Unknown error";
     }
@@ -60,6 +103,16 @@ const char* sevenzip_get_version(void) {
     return SEVENZIP_VERSION;
 }
 
+int sevenzip_codec_supported(int method) {
+    switch (method) {
+        case SEVENZIP_METHOD_LZMA2:
+        case SEVENZIP_METHOD_COPY:
+            return 1;
+        default:
+            return 0;
+    }
+}
+
 void sevenzip_free_list(SevenZipList* list) {
     if (!list) {
         return;
@@ -86,10 +139,9 @@ SevenZipErrorCode sevenzip_compress(
     SevenZipProgressCallback progress_callback,
     void* user_data
 ) {
-    SevenZipCompressOptions options = {0};
+    SevenZipCompressOptions options;
+    sevenzip_compress_options_init(&options);
     options.num_threads = 0;  /* auto */
-    options.dict_size = 0;    /* auto */
-    options.solid = 1;        /* solid archive */
     options.password = password;
     
     return sevenzip_create_7z(
@@ -0,0 +1,196 @@
+/**
+ * Internal helpers shared between the FFI implementation files.
+ * Not part of the public API.
+ */
+
+#ifndef SEVENZIP_FFI_INTERNAL_H
+#define SEVENZIP_FFI_INTERNAL_H
+
+#include <stdint.h>
+
+#if defined(_MSC_VER)
+    #define SEVENZIP_THREAD_LOCAL __declspec(thread)
+#else
+    #define SEVENZIP_THREAD_LOCAL __thread
+#endif
+
+/**
+ * LZMA level for a SevenZipCompressionLevel without a name of its own
+ * (2, 4, 6, 8); values outside 0-9 fall back to normal (5)
+ */
+#define SEVENZIP_RAW_LEVEL(level) (((int)(level) >= 0 && (int)(level) <= 9) ? (int)(level) : 5)
+
+/** Property IDs of the 7z header */
+typedef enum {
+    k7zIdEnd = 0x00,
+    k7zIdHeader = 0x01,
+    k7zIdArchiveProperties = 0x02,
+    k7zIdMainStreamsInfo = 0x04,
+    k7zIdFilesInfo = 0x05,
+    k7zIdPackInfo = 0x06,
+    k7zIdUnpackInfo = 0x07,
+    k7zIdSubStreamsInfo = 0x08,
+    k7zIdSize = 0x09,
+    k7zIdCRC = 0x0A,
+    k7zIdFolder = 0x0B,
+    k7zIdCodersUnpackSize = 0x0C,
+    k7zIdNumUnpackStream = 0x0D,
+    k7zIdEmptyStream = 0x0E,
+    k7zIdEmptyFile = 0x0F,
+    k7zIdAnti = 0x10,
+    k7zIdName = 0x11,
+    k7zIdCTime = 0x12,
+    k7zIdATime = 0x13,
+    k7zIdMTime = 0x14,
+    k7zIdWinAttrib = 0x15,
+    k7zIdEncodedHeader = 0x17,
+    k7zIdStartPos = 0x18,
+    k7zIdDummy = 0x19
+} E7zIdEnum;
+
+/**
+ * Write value as a 7z variable-length number at *buf (up to 9 bytes) and
+ * advance *buf past it (archive_create.c)
+ */
+void sevenzip_write_number(uint8_t** buf, uint64_t value);
+
+/** Size of the 7zAES coder properties sevenzip_aes_encode_internal writes */
+#define SEVENZIP_AES_PROPS_SIZE 18
+
+/**
+ * Encrypt a folder's packed stream in place for a 7zAES coder
+ * (encryption_aes.c): *data is zero-padded to whole AES blocks, reallocated
+ * and *size updated, then encrypted under key (from sevenzip_derive_7z_key
+ * with cycles_power and no salt) with a fresh random IV. The coder
+ * properties, SEVENZIP_AES_PROPS_SIZE bytes, are written to props.
+ */
+SevenZipErrorCode sevenzip_aes_encode_internal(
+    const uint8_t* key,
+    uint32_t cycles_power,
+    uint8_t** data,
+    size_t* size,
+    uint8_t* props
+);
+
+/**
+ * Return whether sevenzip_request_abort() was called on this thread since the
+ * last check, clearing the request. Call it right after invoking a progress
+ * callback, on the same thread that invoked it.
+ */
+int sevenzip_take_abort_request(void);
+
+/**
+ * Record how many threads the last compression on this thread ran with,
+ * for sevenzip_last_threads_used(). Pass 0 when unknown.
+ */
+void sevenzip_set_last_threads_used(uint32_t threads);
+
+/**
+ * Record the estimated peak encoder memory of the last compression on this
+ * thread, for sevenzip_last_peak_memory(). Pass 0 to reset.
+ */
+void sevenzip_set_last_peak_memory(uint64_t bytes);
+
+/**
+ * Record the LZMA2 dictionary size of the last compression on this thread,
+ * for sevenzip_last_dict_size(). Pass 0 to reset.
+ */
+void sevenzip_set_last_dict_size(uint64_t bytes);
+
+/**
+ * Record detailed information about the last error on this thread, for
+ * sevenzip_get_last_error(). Any string may be NULL.
+ */
+void sevenzip_set_error_internal(
+    SevenZipErrorCode code,
+    const char* message,
+    const char* file_context,
+    int64_t position,
+    const char* suggestion
+);
+
+/**
+ * Write a name as null-terminated UTF-16LE for a 7z header and return the
+ * number of bytes written. Pass out = NULL to only compute the size. Bytes
+ * that are not valid UTF-8 are kept as lone surrogates (see name_encoding.c).
+ */
+size_t sevenzip_write_utf16le_name(const char* name, uint8_t* out);
+
+/**
+ * Decode a 7z UTF-16 name of up to count units (stopping at a 0 unit) into a
+ * heap-allocated byte string, the exact inverse of sevenzip_write_utf16le_name.
+ * Returns NULL if out of memory.
+ */
+char* sevenzip_name_from_utf16(const uint16_t* units, size_t count);
+
+/**
+ * Return whether an entry name stays inside the output directory once joined
+ * to it: no ".." component, with both '/' and '\\' as separators
+ */
+int sevenzip_is_contained_name(const char* name);
+
+#ifdef ZIP7_INC_7Z_H
+/**
+ * Fill one list entry from an opened archive database. The entry must be
+ * zero-initialized; its name is heap-allocated and freed by sevenzip_free_list.
+ */
+void sevenzip_fill_entry_internal(const CSzArEx* db, UInt32 index, SevenZipEntry* entry);
+
+/** Write the coder chain of one folder (e.g. "BCJ LZMA2") into out */
+void sevenzip_folder_method_internal(const CSzArEx* db, UInt32 folder_index, char* out, size_t out_size);
+
+/** Fill archive-level totals from an opened archive database */
+void sevenzip_fill_archive_info_internal(const CSzArEx* db, SevenZipArchiveInfo* info);
+
+/**
+ * Whether the entry at index is a hard link written with detect_hardlinks,
+ * given its own decoded data: 1 with *target set to the entry holding the
+ * content, 0 for any other entry, -1 if the link names no file entry
+ * (archive_reader.c)
+ */
+int sevenzip_hardlink_target_internal(const CSzArEx* db, UInt32 index, const Byte* data, size_t size, UInt32* target);
+
+/**
+ * Extract every entry of the archive read through stream into output_dir,
+ * creating parent directories and failing on names that would leave it
+ * (archive_extract.c). Hard link entries get their target's content.
+ * entry_started, if set, gets each name before the entry is written;
+ * progress_callback gets entry counts after it. key, if set, decrypts in
+ * place of password.
+ */
+SevenZipErrorCode sevenzip_extract_stream_internal(
+    ILookInStreamPtr stream,
+    const char* output_dir,
+    const char* password,
+    const uint8_t* key,
+    SevenZipProgressCallback progress_callback,
+    void (*entry_started)(const char* name, void* user_data),
+    void* user_data
+);
+
+/**
+ * SzArEx_Extract that also decodes folders encrypted with 7zAES, deriving
+ * the key from password (NULL = none) as 7-Zip does, or using the 32-byte
+ * key as is when it is set (encryption_aes.c).
+ * Only AES over a single Copy, LZMA or LZMA2 coder is decoded, which covers
+ * what sevenzip_create_7z writes; other encrypted chains fail with
+ * SZ_ERROR_UNSUPPORTED, and a wrong or missing password with SZ_ERROR_DATA
+ * or SZ_ERROR_CRC.
+ */
+SRes sevenzip_extract_entry_internal(
+    const CSzArEx* db,
+    ILookInStreamPtr stream,
+    UInt32 index,
+    const char* password,
+    const uint8_t* key,
+    UInt32* block_index,
+    Byte** out_buffer,
+    size_t* out_buffer_size,
+    size_t* offset,
+    size_t* out_size,
+    ISzAllocPtr alloc_main,
+    ISzAllocPtr alloc_temp
+);
+#endif /* ZIP7_INC_7Z_H */
+
+#endif /* SEVENZIP_FFI_INTERNAL_H */
@@ -1,4 +1,5 @@
 #include "7z_ffi.h"
+#include "ffi_internal.h"
 #include "7zFile.h"
 #include "7zVersion.h"
 #include "Lzma2Enc.h"
@@ -8,7 +9,6 @@
 #include <string.h>
 #include <stdlib.h>
 #include <sys/stat.h>
-#include <dirent.h>
 
 #ifdef _WIN32
     #include <windows.h>
@@ -22,15 +22,6 @@
     #define STAT stat
 #endif
 
-/* Helper: Check if path is a directory */
-static int is_directory(const char* path) {
-    struct STAT st;
-    if (STAT(path, &st) != 0) {
-        return 0;
-    }
-    return S_ISDIR(st.st_mode);
-}
-
 /* Helper: Check if path is a regular file */
 static int is_regular_file(const char* path) {
     struct STAT st;
@@ -49,43 +40,6 @@ static size_t get_file_size(const char* path) {
     return (size_t)st.st_size;
 }
 
-/* Helper: Read entire file into memory */
-static unsigned char* read_file_contents(const char* path, size_t* out_size) {
-    FILE* file = fopen(path, "rb");
-    if (!file) {
-        return NULL;
-    }
-    
-    /* Get file size */
-    fseek(file, 0, SEEK_END);
-    long size = ftell(file);
-    fseek(file, 0, SEEK_SET);
-    
-    if (size <= 0) {
-        fclose(file);
-        return NULL;
-    }
-    
-    /* Allocate buffer */
-    unsigned char* buffer = (unsigned char*)malloc(size);
-    if (!buffer) {
-        fclose(file);
-        return NULL;
-    }
-    
-    /* Read file */
-    size_t read_bytes = fread(buffer, 1, size, file);
-    fclose(file);
-    
-    if (read_bytes != (size_t)size) {
-        free(buffer);
-        return NULL;
-    }
-    
-    *out_size = size;
-    return buffer;
-}
-
 /* Helper: Convert compression level to LZMA2 properties */
 static void get_lzma2_props_for_level(CLzma2EncProps* props, SevenZipCompressionLevel level) {
     Lzma2EncProps_Init(props);
@@ -120,8 +74,7 @@ static void get_lzma2_props_for_level(CLzma2EncProps* props, SevenZipCompression
             props->numBlockThreads_Max = 2;
             break;
         default:
-            props->lzmaProps.level = 5;
-            props->lzmaProps.dictSize = 1 << 23;
+            props->lzmaProps.level = SEVENZIP_RAW_LEVEL(level);
             props->numBlockThreads_Max = 1;
             break;
     }
@@ -129,140 +82,196 @@ static void get_lzma2_props_for_level(CLzma2EncProps* props, SevenZipCompression
     Lzma2EncProps_Normalize(props);
 }
 
-/* Simplified compression for single files using LZMA2 format */
-static SevenZipErrorCode compress_single_file_lzma2(
+/* Default stdio buffer size for single-file operations */
+#define SINGLE_FILE_DEFAULT_BUFFER_SIZE ((size_t)1 << 16)
+
+/* Sequential input stream over a FILE*, reporting progress per read */
+typedef struct {
+    ISeqInStream vt;
+    FILE* file;
+    UInt64 bytes_read;
+    UInt64 bytes_total;
+    SevenZipProgressCallback progress_callback;
+    void* user_data;
+} FileSeqInStream;
+
+static SRes file_seq_read(ISeqInStreamPtr p, void* buf, size_t* size) {
+    FileSeqInStream* in = (FileSeqInStream*)(void*)p;
+    size_t read = fread(buf, 1, *size, in->file);
+    if (read == 0 && ferror(in->file)) {
+        *size = 0;
+        return SZ_ERROR_READ;
+    }
+    *size = read;
+    in->bytes_read += read;
+    
+    if (in->progress_callback && read > 0) {
+        in->progress_callback(in->bytes_read, in->bytes_total, in->user_data);
+    }
+    return SZ_OK;
+}
+
+/* Sequential output stream over a FILE* */
+typedef struct {
+    ISeqOutStream vt;
+    FILE* file;
+} FileSeqOutStream;
+
+static size_t file_seq_write(ISeqOutStreamPtr p, const void* buf, size_t size) {
+    FileSeqOutStream* out = (FileSeqOutStream*)(void*)p;
+    return fwrite(buf, 1, size, out->file);
+}
+
+SevenZipErrorCode sevenzip_compress_file(
     const char* input_path,
     const char* output_path,
     SevenZipCompressionLevel level,
     SevenZipProgressCallback progress_callback,
     void* user_data
 ) {
-    /* Read input file */
-    size_t input_size = 0;
-    unsigned char* input_data = read_file_contents(input_path, &input_size);
-    if (!input_data) {
+    return sevenzip_compress_file_ex(input_path, output_path, level, 0,
+                                     progress_callback, user_data);
+}
+
+/* Shared body of sevenzip_compress_file_ex and sevenzip_compress_file_sized;
+ * `sized` writes the SEVENZIP_SIZED_MAGIC header instead of the bare
+ * property byte */
+static SevenZipErrorCode compress_single_file(
+    const char* input_path,
+    const char* output_path,
+    SevenZipCompressionLevel level,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data,
+    int sized
+) {
+    if (!input_path || !output_path) {
+        return SEVENZIP_ERROR_INVALID_PARAM;
+    }
+    if (!is_regular_file(input_path)) {
         return SEVENZIP_ERROR_OPEN_FILE;
     }
+    if (buffer_size == 0) {
+        buffer_size = SINGLE_FILE_DEFAULT_BUFFER_SIZE;
+    }
     
-    /* Create LZMA2 encoder */
-    CLzma2EncHandle encoder = Lzma2Enc_Create(&g_Alloc, &g_Alloc);
+    SevenZipErrorCode result = SEVENZIP_OK;
+    FILE* in_file = NULL;
+    FILE* out_file = NULL;
+    CLzma2EncHandle encoder = NULL;
+    
+    in_file = fopen(input_path, "rb");
+    if (!in_file) {
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    out_file = fopen(output_path, "wb");
+    if (!out_file) {
+        fclose(in_file);
+        return SEVENZIP_ERROR_OPEN_FILE;
+    }
+    
+    /* Size stdio buffers so reads and writes hit the disk in buffer_size chunks */
+    setvbuf(in_file, NULL, _IOFBF, buffer_size);
+    setvbuf(out_file, NULL, _IOFBF, buffer_size);
+    
+    encoder = Lzma2Enc_Create(&g_Alloc, &g_Alloc);
     if (!encoder) {
-        free(input_data);
-        return SEVENZIP_ERROR_MEMORY;
+        result = SEVENZIP_ERROR_MEMORY;
+        goto cleanup;
     }
     
-    /* Set compression properties */
     CLzma2EncProps props;
     get_lzma2_props_for_level(&props, level);
-    
-    SRes res = Lzma2Enc_SetProps(encoder, &props);
-    if (res != SZ_OK) {
-        Lzma2Enc_Destroy(encoder);
-        free(input_data);
-        return SEVENZIP_ERROR_COMPRESS;
+    if (Lzma2Enc_SetProps(encoder, &props) != SZ_OK) {
+        result = SEVENZIP_ERROR_COMPRESS;
+        goto cleanup;
     }
     
-    /* Get properties byte before encoding */
-    Byte prop = Lzma2Enc_WriteProperties(encoder);
-    
-    /* Allocate output buffer (worst case: input size + overhead) */
-    size_t output_buf_size = input_size + input_size / 3 + 128;
-    unsigned char* output_data = (unsigned char*)malloc(output_buf_size);
-    if (!output_data) {
-        Lzma2Enc_Destroy(encoder);
-        free(input_data);
-        return SEVENZIP_ERROR_MEMORY;
+    /* Write LZMA2 properties (1 byte), same layout as sevenzip_compress;
+     * the sized header's size field is filled in once the input is read */
+    Byte header[SEVENZIP_SIZED_HEADER_SIZE] = {0};
+    size_t header_size = 0;
+    if (sized) {
+        memcpy(header, SEVENZIP_SIZED_MAGIC, 4);
+        header[4] = SEVENZIP_SIZED_VERSION;
+        header_size = SEVENZIP_SIZED_HEADER_SIZE - 1;
+    }
+    header[header_size++] = Lzma2Enc_WriteProperties(encoder);
+    if (fwrite(header, 1, header_size, out_file) != header_size) {
+        result = SEVENZIP_ERROR_COMPRESS;
+        goto cleanup;
     }
     
-    /* Encode data */
-    size_t output_size = output_buf_size;
-    res = Lzma2Enc_Encode2(
-        encoder,
-        NULL,                  /* outStream - NULL to use buffer */
-        output_data, &output_size,
-        NULL,                  /* inStream - NULL to use buffer */
-        input_data, input_size,
-        NULL                   /* progress */
-    );
+    FileSeqInStream in_stream;
+    in_stream.vt.Read = file_seq_read;
+    in_stream.file = in_file;
+    in_stream.bytes_read = 0;
+    in_stream.bytes_total = get_file_size(input_path);
+    in_stream.progress_callback = progress_callback;
+    in_stream.user_data = user_data;
     
-    Lzma2Enc_Destroy(encoder);
-    free(input_data);
+    FileSeqOutStream out_stream;
+    out_stream.vt.Write = file_seq_write;
+    out_stream.file = out_file;
     
+    SRes res = Lzma2Enc_Encode2(encoder, &out_stream.vt, NULL, NULL,
+                                &in_stream.vt, NULL, 0, NULL);
     if (res != SZ_OK) {
-        free(output_data);
-        return SEVENZIP_ERROR_COMPRESS;
+        result = (res == SZ_ERROR_READ) ? SEVENZIP_ERROR_OPEN_FILE : SEVENZIP_ERROR_COMPRESS;
+        goto cleanup;
     }
     
-    /* Write output file with LZMA2 header */
-    FILE* out_file = fopen(output_path, "wb");
-    if (!out_file) {
-        free(output_data);
-        return SEVENZIP_ERROR_OPEN_FILE;
+    /* Record the bytes actually read, which differ from the size seen
+     * before compressing if the file grew or shrank meanwhile */
+    if (sized) {
+        Byte size_le[8];
+        for (int i = 0; i < 8; i++) {
+            size_le[i] = (Byte)(in_stream.bytes_read >> (8 * i));
+        }
+        if (fseek(out_file, 5, SEEK_SET) != 0 || fwrite(size_le, 1, 8, out_file) != 8) {
+            result = SEVENZIP_ERROR_COMPRESS;
+            goto cleanup;
+        }
     }
     
-    /* Write LZMA2 properties (1 byte) */
-    fwrite(&prop, 1, 1, out_file);
+    if (fflush(out_file) != 0) {
+        result = SEVENZIP_ERROR_COMPRESS;
+    }
     
-    /* Write compressed data */
-    fwrite(output_data, 1, output_size, out_file);
-    fclose(out_file);
-    free(output_data);
+cleanup:
+    if (encoder) Lzma2Enc_Destroy(encoder);
+    fclose(in_file);
+    if (fclose(out_file) != 0 && result == SEVENZIP_OK) {
+        result = SEVENZIP_ERROR_COMPRESS;
+    }
     
-    /* Progress callback */
-    if (progress_callback) {
-        progress_callback(1, 1, user_data);
+    if (result != SEVENZIP_OK) {
+        remove(output_path);
     }
     
-    return SEVENZIP_OK;
+    return result;
 }
 
-SevenZipErrorCode sevenzip_compress(
-    const char* archive_path,
-    const char** input_paths,
+SevenZipErrorCode sevenzip_compress_file_ex(
+    const char* input_path,
+    const char* output_path,
     SevenZipCompressionLevel level,
-    const char* password,
+    size_t buffer_size,
     SevenZipProgressCallback progress_callback,
     void* user_data
 ) {
-    if (!archive_path || !input_paths) {
-        return SEVENZIP_ERROR_INVALID_PARAM;
-    }
-    
-    /* Count input files */
-    size_t num_inputs = 0;
-    while (input_paths[num_inputs] != NULL) {
-        num_inputs++;
-    }
-    
-    if (num_inputs == 0) {
-        return SEVENZIP_ERROR_INVALID_PARAM;
-    }
-    
-    /* For now, handle single file compression to LZMA2 format
-     * Full 7z archive creation requires more complex implementation
-     * TODO: Implement full 7z format with multiple files/directories
-     */
-    
-    if (num_inputs == 1 && is_regular_file(input_paths[0])) {
-        /* Single file - use LZMA2 format */
-        return compress_single_file_lzma2(
-            input_paths[0],
-            archive_path,
-            level,
-            progress_callback,
-            user_data
-        );
-    }
-    
-    /* Multiple files or directories not yet supported
-     * TODO: Implement using 7z format encoder:
-     * 1. Traverse directories recursively
-     * 2. Build file list with metadata
-     * 3. Create 7z archive header
-     * 4. Compress files with LZMA2
-     * 5. Write solid or non-solid blocks
-     * 6. Update archive header with offsets
-     */
-    
-    return SEVENZIP_ERROR_NOT_IMPLEMENTED;
+    return compress_single_file(input_path, output_path, level, buffer_size,
+                                progress_callback, user_data, 0);
+}
+
+SevenZipErrorCode sevenzip_compress_file_sized(
+    const char* input_path,
+    const char* output_path,
+    SevenZipCompressionLevel level,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    return compress_single_file(input_path, output_path, level, buffer_size,
+                                progress_callback, user_data, 1);
 }
@@ -199,13 +199,16 @@ cleanup:
 }
 
 /**
- * Decompress LZMA2 file using streaming decoder
+ * Decompress a 1-byte-props LZMA2 file, reading and writing in buffer_size chunks
  */
-SevenZipErrorCode sevenzip_decompress_lzma2(
+static SevenZipErrorCode decompress_lzma2_file(
     const char* lzma2_path,
     const char* output_path,
+    size_t buffer_size,
     SevenZipProgressCallback progress_callback,
-    void* user_data
+    void* user_data,
+    int sized,
+    uint64_t* decoded_size
 ) {
     if (!lzma2_path || !output_path) {
         return SEVENZIP_ERROR_INVALID_PARAM;
@@ -218,6 +221,9 @@ SevenZipErrorCode sevenzip_decompress_lzma2(
     CLzma2Dec decoder;
     SevenZipErrorCode result = SEVENZIP_OK;
     SRes lzma_res;
+    UInt64 out_processed = 0;
+    
+    Lzma2Dec_Construct(&decoder);
     
     // Open input file
     in_file = fopen(lzma2_path, "rb");
@@ -225,11 +231,23 @@ SevenZipErrorCode sevenzip_decompress_lzma2(
         return SEVENZIP_ERROR_OPEN_FILE;
     }
     
-    // Read LZMA2 properties (1 byte)
-    Byte prop;
-    if (fread(&prop, 1, 1, in_file) != 1) {
+    // Read LZMA2 properties (1 byte), behind the size header if `sized`
+    Byte header[SEVENZIP_SIZED_HEADER_SIZE];
+    size_t header_size = sized ? SEVENZIP_SIZED_HEADER_SIZE : 1;
+    if (fread(header, 1, header_size, in_file) != header_size) {
         fclose(in_file);
-        return SEVENZIP_ERROR_OPEN_FILE;
+        return sized ? SEVENZIP_ERROR_INVALID_ARCHIVE : SEVENZIP_ERROR_OPEN_FILE;
+    }
+    Byte prop = header[header_size - 1];
+    UInt64 expected_size = 0;
+    if (sized) {
+        if (memcmp(header, SEVENZIP_SIZED_MAGIC, 4) != 0 || header[4] != SEVENZIP_SIZED_VERSION) {
+            fclose(in_file);
+            return SEVENZIP_ERROR_INVALID_ARCHIVE;
+        }
+        for (int i = 0; i < 8; i++) {
+            expected_size |= ((UInt64)header[5 + i]) << (i * 8);
+        }
     }
     
     // Open output file
@@ -240,15 +258,14 @@ SevenZipErrorCode sevenzip_decompress_lzma2(
     }
     
     // Allocate buffers
-    in_buf = (Byte*)malloc(IN_BUF_SIZE);
-    out_buf = (Byte*)malloc(OUT_BUF_SIZE);
+    in_buf = (Byte*)malloc(buffer_size);
+    out_buf = (Byte*)malloc(buffer_size);
     if (!in_buf || !out_buf) {
         result = SEVENZIP_ERROR_MEMORY;
         goto cleanup;
     }
     
     // Initialize decoder
-    Lzma2Dec_Construct(&decoder);
     lzma_res = Lzma2Dec_Allocate(&decoder, prop, &g_Alloc);
     if (lzma_res != SZ_OK) {
         result = SEVENZIP_ERROR_COMPRESS;
@@ -258,71 +275,81 @@ SevenZipErrorCode sevenzip_decompress_lzma2(
     Lzma2Dec_Init(&decoder);
     
     // Decompress in chunks
-    UInt64 in_processed = 0;
-    UInt64 out_processed = 0;
-    int finished = 0;
-    
-    while (!finished) {
-        // Read input chunk
-        size_t in_size = fread(in_buf, 1, IN_BUF_SIZE, in_file);
-        if (in_size == 0) {
-            break;
+    size_t in_size = 0;
+    size_t in_pos = 0;
+    int at_eof = 0;
+    
+    for (;;) {
+        // Refill input once the previous chunk is fully consumed
+        if (in_pos == in_size && !at_eof) {
+            in_size = fread(in_buf, 1, buffer_size, in_file);
+            in_pos = 0;
+            if (in_size == 0) {
+                if (ferror(in_file)) {
+                    result = SEVENZIP_ERROR_OPEN_FILE;
+                    goto cleanup;
+                }
+                at_eof = 1;
+            }
         }
         
-        Byte* in_ptr = in_buf;
-        size_t in_remaining = in_size;
+        size_t out_size = buffer_size;
+        size_t in_processed_chunk = in_size - in_pos;
+        ELzmaStatus status;
         
-        // Process input buffer
-        while (in_remaining > 0 || finished) {
-            size_t out_size = OUT_BUF_SIZE;
-            size_t in_processed_chunk = in_remaining;
-            ELzmaStatus status;
-            
-            lzma_res = Lzma2Dec_DecodeToBuf(
-                &decoder,
-                out_buf,
-                &out_size,
-                in_ptr,
-                &in_processed_chunk,
-                (in_size == 0) ? LZMA_FINISH_END : LZMA_FINISH_ANY,
-                &status
-            );
-            
-            if (lzma_res != SZ_OK) {
-                result = SEVENZIP_ERROR_COMPRESS;
+        lzma_res = Lzma2Dec_DecodeToBuf(
+            &decoder,
+            out_buf,
+            &out_size,
+            in_buf + in_pos,
+            &in_processed_chunk,
+            LZMA_FINISH_ANY,
+            &status
+        );
+        
+        // Corrupt data is reported like a truncated stream: the input is bad
+        if (lzma_res != SZ_OK) {
+            result = SEVENZIP_ERROR_INVALID_ARCHIVE;
+            goto cleanup;
+        }
+        
+        in_pos += in_processed_chunk;
+        
+        // More data than the header announced
+        if (sized && out_size > expected_size - out_processed) {
+            out_processed += out_size;
+            result = SEVENZIP_ERROR_INVALID_ARCHIVE;
+            goto cleanup;
+        }
+        
+        // Write output
+        if (out_size > 0) {
+            if (fwrite(out_buf, 1, out_size, out_file) != out_size) {
+                result = SEVENZIP_ERROR_EXTRACT;
                 goto cleanup;
             }
+            out_processed += out_size;
             
-            // Write output
-            if (out_size > 0) {
-                if (fwrite(out_buf, 1, out_size, out_file) != out_size) {
-                    result = SEVENZIP_ERROR_EXTRACT;
-                    goto cleanup;
-                }
-                out_processed += out_size;
-            }
-            
-            // Update input pointer
-            in_ptr += in_processed_chunk;
-            in_remaining -= in_processed_chunk;
-            in_processed += in_processed_chunk;
-            
-            // Check if decompression is finished
-            if (status == LZMA_STATUS_FINISHED_WITH_MARK) {
-                finished = 1;
-                break;
-            }
-            
-            // If no more output and no more input, need more input
-            if (out_size == 0 && in_remaining == 0) {
-                break;
-            }
-            
-            // Progress callback (we don't know total size for LZMA2)
+            // Progress callback (only the sized header tells the total)
             if (progress_callback) {
-                progress_callback(out_processed, out_processed, user_data);
+                progress_callback(out_processed, sized ? expected_size : out_processed, user_data);
             }
         }
+        
+        // Check if decompression is finished
+        if (status == LZMA_STATUS_FINISHED_WITH_MARK) {
+            if (sized && out_processed != expected_size) {
+                result = SEVENZIP_ERROR_INVALID_ARCHIVE;
+                goto cleanup;
+            }
+            break;
+        }
+        
+        // Input exhausted and nothing left to flush: the stream is truncated
+        if (at_eof && out_size == 0) {
+            result = SEVENZIP_ERROR_INVALID_ARCHIVE;
+            goto cleanup;
+        }
     }
     
     // Final progress callback
@@ -331,6 +358,10 @@ SevenZipErrorCode sevenzip_decompress_lzma2(
     }
     
 cleanup:
+    if (decoded_size) {
+        *decoded_size = out_processed;
+    }
+    
     // Free decoder
     Lzma2Dec_Free(&decoder, &g_Alloc);
     
@@ -340,7 +371,9 @@ cleanup:
     
     // Close files
     if (in_file) fclose(in_file);
-    if (out_file) fclose(out_file);
+    if (out_file && fclose(out_file) != 0 && result == SEVENZIP_OK) {
+        result = SEVENZIP_ERROR_EXTRACT;
+    }
     
     // Remove output file on error
     if (result != SEVENZIP_OK && output_path) {
@@ -349,3 +382,57 @@ cleanup:
     
     return result;
 }
+
+/**
+ * Decompress LZMA2 file using streaming decoder
+ */
+SevenZipErrorCode sevenzip_decompress_lzma2(
+    const char* lzma2_path,
+    const char* output_path,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    return decompress_lzma2_file(lzma2_path, output_path, OUT_BUF_SIZE,
+                                 progress_callback, user_data, 0, NULL);
+}
+
+/**
+ * Decompress a file written by sevenzip_compress_file
+ */
+SevenZipErrorCode sevenzip_decompress_file(
+    const char* input_path,
+    const char* output_path,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    return sevenzip_decompress_file_ex(input_path, output_path, 0,
+                                       progress_callback, user_data);
+}
+
+SevenZipErrorCode sevenzip_decompress_file_ex(
+    const char* input_path,
+    const char* output_path,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data
+) {
+    return decompress_lzma2_file(input_path, output_path,
+                                 buffer_size ? buffer_size : OUT_BUF_SIZE,
+                                 progress_callback, user_data, 0, NULL);
+}
+
+/**
+ * Decompress a file written by sevenzip_compress_file_sized
+ */
+SevenZipErrorCode sevenzip_decompress_file_sized(
+    const char* input_path,
+    const char* output_path,
+    size_t buffer_size,
+    SevenZipProgressCallback progress_callback,
+    void* user_data,
+    uint64_t* decoded_size
+) {
+    return decompress_lzma2_file(input_path, output_path,
+                                 buffer_size ? buffer_size : OUT_BUF_SIZE,
+                                 progress_callback, user_data, 1, decoded_size);
+}
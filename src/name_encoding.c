@@ -0,0 +1,137 @@
+/**
+ * Entry name encoding
+ *
+ * 7z stores entry names as UTF-16LE, while the C API passes names around as
+ * byte strings. Names are UTF-8 where possible, with two extensions so every
+ * name survives a round trip exactly:
+ *  - bytes that are not valid UTF-8 (e.g. Latin-1 file names on Linux) are
+ *    stored as lone surrogates U+DC80..U+DCFF and decoded back to the byte;
+ *  - other unpaired surrogates, which only Windows names can contain, are
+ *    decoded as 3-byte WTF-8 sequences.
+ */
+
+#include "7z_ffi.h"
+#include "ffi_internal.h"
+
+#include <stdlib.h>
+#include <string.h>
+
+/* Decode one UTF-8 sequence at s; returns its length, or 0 if invalid */
+static size_t decode_utf8(const unsigned char* s, uint32_t* cp) {
+    size_t len;
+    uint32_t min;
+
+    if (s[0] < 0x80) {
+        *cp = s[0];
+        return 1;
+    } else if (s[0] >= 0xC2 && s[0] <= 0xDF) {
+        len = 2; min = 0x80; *cp = s[0] & 0x1F;
+    } else if (s[0] >= 0xE0 && s[0] <= 0xEF) {
+        len = 3; min = 0x800; *cp = s[0] & 0x0F;
+    } else if (s[0] >= 0xF0 && s[0] <= 0xF4) {
+        len = 4; min = 0x10000; *cp = s[0] & 0x07;
+    } else {
+        return 0;
+    }
+
+    for (size_t i = 1; i < len; i++) {
+        /* A NUL terminator fails this check too */
+        if ((s[i] & 0xC0) != 0x80) return 0;
+        *cp = (*cp << 6) | (s[i] & 0x3F);
+    }
+
+    if (*cp < min || *cp > 0x10FFFF) return 0;
+    /* Escaped bytes have their own encoding; never accept them spelled out */
+    if (*cp >= 0xDC80 && *cp <= 0xDCFF) return 0;
+    return len;
+}
+
+static void put_unit(uint8_t** out, uint16_t unit) {
+    if (*out) {
+        (*out)[0] = (uint8_t)(unit & 0xFF);
+        (*out)[1] = (uint8_t)(unit >> 8);
+        *out += 2;
+    }
+}
+
+size_t sevenzip_write_utf16le_name(const char* name, uint8_t* out) {
+    const unsigned char* s = (const unsigned char*)name;
+    uint8_t* p = out;
+    size_t units = 0;
+
+    while (*s) {
+        uint32_t cp;
+        size_t len = decode_utf8(s, &cp);
+        if (len == 0) {
+            put_unit(&p, (uint16_t)(0xDC00 | *s));
+            units++;
+            s++;
+        } else if (cp >= 0x10000) {
+            cp -= 0x10000;
+            put_unit(&p, (uint16_t)(0xD800 | (cp >> 10)));
+            put_unit(&p, (uint16_t)(0xDC00 | (cp & 0x3FF)));
+            units += 2;
+            s += len;
+        } else {
+            put_unit(&p, (uint16_t)cp);
+            units++;
+            s += len;
+        }
+    }
+
+    put_unit(&p, 0);
+    return (units + 1) * 2;
+}
+
+char* sevenzip_name_from_utf16(const uint16_t* units, size_t count) {
+    /* Each unit needs at most 3 bytes (a surrogate pair 4 bytes for 2 units) */
+    char* name = (char*)malloc(count * 3 + 1);
+    if (!name) return NULL;
+
+    unsigned char* p = (unsigned char*)name;
+    for (size_t i = 0; i < count && units[i] != 0; i++) {
+        uint32_t cp = units[i];
+
+        if (cp >= 0xD800 && cp <= 0xDBFF && i + 1 < count &&
+            units[i + 1] >= 0xDC00 && units[i + 1] <= 0xDFFF) {
+            cp = 0x10000 + ((cp - 0xD800) << 10) + (units[i + 1] - 0xDC00);
+            i++;
+        } else if (cp >= 0xDC80 && cp <= 0xDCFF) {
+            *p++ = (unsigned char)(cp & 0xFF);
+            continue;
+        }
+
+        if (cp < 0x80) {
+            *p++ = (unsigned char)cp;
+        } else if (cp < 0x800) {
+            *p++ = (unsigned char)(0xC0 | (cp >> 6));
+            *p++ = (unsigned char)(0x80 | (cp & 0x3F));
+        } else if (cp < 0x10000) {
+            *p++ = (unsigned char)(0xE0 | (cp >> 12));
+            *p++ = (unsigned char)(0x80 | ((cp >> 6) & 0x3F));
+            *p++ = (unsigned char)(0x80 | (cp & 0x3F));
+        } else {
+            *p++ = (unsigned char)(0xF0 | (cp >> 18));
+            *p++ = (unsigned char)(0x80 | ((cp >> 12) & 0x3F));
+            *p++ = (unsigned char)(0x80 | ((cp >> 6) & 0x3F));
+            *p++ = (unsigned char)(0x80 | (cp & 0x3F));
+        }
+    }
+
+    *p = '\0';
+    return name;
+}
+
+int sevenzip_is_contained_name(const char* name) {
+    const char* component = name;
+    for (const char* p = name; ; p++) {
+        if (*p == '/' || *p == '\\' || *p == '\0') {
+            if (p - component == 2 && component[0] == '.' && component[1] == '.') {
+                return 0;
+            }
+            if (*p == '\0') break;
+            component = p + 1;
+        }
+    }
+    return 1;
+}